@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BatchItemResult, Result};
+
+/// One line of the journal
+#[derive(Serialize, Deserialize)]
+struct CheckpointEntry {
+    input: PathBuf,
+    success: bool,
+}
+
+/// Append-only JSONL journal of finished files, used to resume a batch
+///
+/// Every finished file adds one line. Lines that do not parse, such as one cut
+/// short by a crash, are ignored when the journal is read back.
+#[derive(Debug)]
+pub(crate) struct Checkpoint {
+    path: PathBuf,
+    completed: HashSet<PathBuf>,
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Read the files completed by earlier runs and open the journal for appending
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let mut completed = HashSet::new();
+        let mut cut_short = false;
+        if path.exists() {
+            let contents = fs::read_to_string(path)?;
+            for line in contents.lines() {
+                match serde_json::from_str::<CheckpointEntry>(line) {
+                    Ok(entry) if entry.success => {
+                        completed.insert(entry.input);
+                    },
+                    // Failed files are run again
+                    Ok(_) => {},
+                    Err(_) => log::warn!("Ignoring an unreadable line in checkpoint {}", path.display()),
+                }
+            }
+            cut_short = !contents.is_empty() && !contents.ends_with('\n');
+        } else if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        // Start new entries on a line of their own after a partial one
+        if cut_short {
+            file.write_all(b"\n")?;
+        }
+        Ok(Self { path: path.to_path_buf(), completed, file: Mutex::new(file) })
+    }
+
+    /// Whether an earlier run already processed this file successfully
+    pub(crate) fn is_completed(&self, input: &Path) -> bool {
+        self.completed.contains(&journal_key(input))
+    }
+
+    /// Append the outcome of a finished file
+    pub(crate) fn record(&self, result: &BatchItemResult) {
+        let entry = CheckpointEntry { input: journal_key(&result.input), success: result.success };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+
+        // One write per line, so a crash can at worst cut off the last entry
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = file.write_all(format!("{}\n", line).as_bytes()).and_then(|()| file.flush()) {
+            log::warn!("Could not write to checkpoint {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Absolute path of an input, so a resumed run may be started from another directory
+fn journal_key(input: &Path) -> PathBuf {
+    fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_skips_successful_files() {
+        let dir = std::env::temp_dir().join(format!("vtk_checkpoint_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let journal = dir.join("run.jsonl");
+        let (done, failed) = (dir.join("done.mp4"), dir.join("failed.mp4"));
+        fs::write(&done, b"").unwrap();
+        fs::write(&failed, b"").unwrap();
+
+        let result = |input: &Path, success| BatchItemResult {
+            success,
+            ..BatchItemResult::succeeded(input, None)
+        };
+
+        let checkpoint = Checkpoint::open(&journal).unwrap();
+        checkpoint.record(&result(&done, true));
+        checkpoint.record(&result(&failed, false));
+        drop(checkpoint);
+
+        // A crash in the middle of a write leaves a partial last line
+        let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+        file.write_all(b"{\"input\":\"/vid").unwrap();
+
+        let resumed = Checkpoint::open(&journal).unwrap();
+        assert!(resumed.is_completed(&done));
+        assert!(!resumed.is_completed(&failed));
+        resumed.record(&result(&failed, true));
+        drop(resumed);
+
+        let resumed = Checkpoint::open(&journal).unwrap();
+        assert!(resumed.is_completed(&failed));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
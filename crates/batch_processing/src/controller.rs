@@ -0,0 +1,944 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use clipper::{AvoidNegativeTs, ClipOptions};
+use common::{validate_timestamp, EncoderPreference, VideoFormat, Warning};
+use gif_converter::{CropMode, GifOptions, GifsicleSettings};
+use gif_transparency::{TransparencyMode, TransparencyOptions};
+use merger::MergeOptions;
+use plugin_system::PluginManager;
+use splitter::{parse_dimensions, parse_encode_options, SliceDirection, SplitOptions, DEFAULT_SLICE_COUNT};
+
+use crate::{
+    AudioPairing, BatchError, BatchItemResult, BatchOperation, BatchProcessor, BatchProgress, BatchSummary, BatchWebpConfig,
+    ErrorPolicy, ExclusionReason, FileOrder, PatternTarget, Result, parse_size_filter, read_file_list
+};
+
+/// Seconds before the first retry of a failed file when no backoff is set
+const DEFAULT_RETRY_BACKOFF_SECS: u64 = 2;
+
+/// Everything needed to set up a batch run, as filled in by the CLI or the GUI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSettings {
+    pub recursive: bool,
+    /// Descend into symlinked directories when scanning
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Directory levels scanned by a recursive run, 1 being the files directly in an input
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    pub parallel: bool,
+    /// File listing more inputs, one path per line (`-` for standard input)
+    #[serde(default)]
+    pub files_from: Option<PathBuf>,
+    /// Regex matched against file names, or the paths chosen by `pattern_target`
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub pattern_target: PatternTarget,
+    /// Globs matched against paths relative to the input directory; includes
+    /// are applied first, then excludes
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Match files by their content instead of their extension
+    #[serde(default)]
+    pub by_content: bool,
+    /// Modification-time filters (RFC 3339 or relative, e.g. `24h`)
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// File size filters in bytes or with a unit (e.g. `50MB`), both inclusive
+    #[serde(default)]
+    pub min_input_size: Option<String>,
+    #[serde(default)]
+    pub max_input_size: Option<String>,
+    /// Processing order, ascending unless `descending` is set
+    #[serde(default)]
+    pub order: FileOrder,
+    #[serde(default)]
+    pub descending: bool,
+    /// Trial-run selection; `limit` and `sample` are mutually exclusive
+    pub limit: Option<usize>,
+    pub sample: Option<usize>,
+    pub seed: Option<u64>,
+    /// Seconds a file may take before it is recorded as failed
+    #[serde(default)]
+    pub per_file_timeout: Option<u64>,
+    /// Times a file is run again after a transient failure
+    #[serde(default)]
+    pub retries: u32,
+    /// Seconds before the first retry, doubling after each one (default: 2)
+    #[serde(default)]
+    pub retry_backoff: Option<u64>,
+    /// Start no new files after the first failure, or once this many failed;
+    /// `fail_fast` and `max_failures` are mutually exclusive
+    #[serde(default)]
+    pub fail_fast: bool,
+    #[serde(default)]
+    pub max_failures: Option<usize>,
+    /// Journal of finished files; a rerun with the same file skips the successful ones
+    #[serde(default)]
+    pub checkpoint: Option<PathBuf>,
+    /// Recreate the input directories under the output directory
+    #[serde(default)]
+    pub mirror_structure: bool,
+    pub operation: OperationSettings,
+}
+
+impl BatchSettings {
+    /// Settings with default input handling for the given operation
+    pub fn new(operation: OperationSettings) -> Self {
+        Self {
+            recursive: false,
+            follow_symlinks: false,
+            max_depth: None,
+            parallel: true,
+            files_from: None,
+            pattern: None,
+            pattern_target: PatternTarget::FileName,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            by_content: false,
+            since: None,
+            until: None,
+            min_input_size: None,
+            max_input_size: None,
+            order: FileOrder::Name,
+            descending: false,
+            limit: None,
+            sample: None,
+            seed: None,
+            per_file_timeout: None,
+            retries: 0,
+            retry_backoff: None,
+            fail_fast: false,
+            max_failures: None,
+            checkpoint: None,
+            mirror_structure: false,
+            operation,
+        }
+    }
+}
+
+/// Hardware encoder preference written `auto`, `off` or an encoder name; off when not set
+fn parse_hw_accel(value: Option<&str>) -> Result<EncoderPreference> {
+    match value.map(str::trim) {
+        Some(value) if !value.is_empty() => Ok(value.parse()?),
+        _ => Ok(EncoderPreference::default()),
+    }
+}
+
+/// Operation-specific batch settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OperationSettings {
+    Clipper {
+        ranges: Vec<String>,
+        output_dir: PathBuf,
+        copy_codec: bool,
+        suffix: Option<String>,
+        /// `auto`, `make_zero` or `disabled` (default: auto)
+        #[serde(default)]
+        avoid_negative_ts: Option<String>,
+        #[serde(default)]
+        verify_outputs: bool,
+        /// `auto`, `off` or a hardware encoder name such as `h264_nvenc` (default: off)
+        #[serde(default)]
+        hw_accel: Option<String>,
+        /// Container format of the clips (default: mp4)
+        #[serde(default)]
+        format: Option<String>,
+    },
+    GifConverter {
+        width: Option<u32>,
+        fps: u32,
+        max_size_mb: f64,
+        optimize: bool,
+        output_dir: PathBuf,
+        /// `x,y,w,h` or `auto`
+        crop: Option<String>,
+        boomerang: bool,
+        loop_count: Option<u32>,
+        gifsicle: bool,
+        gifsicle_level: u8,
+        gifsicle_lossy: Option<u32>,
+        /// Write a PNG poster frame next to each GIF
+        #[serde(default)]
+        poster: bool,
+        /// Poster timestamp (default: middle of the video)
+        #[serde(default)]
+        poster_time: Option<String>,
+    },
+    GifTransparency {
+        create_backup: bool,
+        backup_dir: Option<PathBuf>,
+        webp: Option<BatchWebpConfig>,
+        /// Change the trailer byte instead of the Graphic Control Extensions
+        #[serde(default)]
+        legacy_trailer: bool,
+        /// Edit files in place instead of replacing them atomically
+        #[serde(default)]
+        no_atomic: bool,
+        /// Write transparent copies here instead of modifying the GIFs
+        #[serde(default)]
+        output_dir: Option<PathBuf>,
+    },
+    Splitter {
+        output_dir: PathBuf,
+        prefix: String,
+        custom_encode: Option<String>,
+        force: bool,
+        /// Number of slices (default: 5)
+        #[serde(default)]
+        slices: Option<u32>,
+        /// `vertical` or `horizontal` (default: vertical)
+        #[serde(default)]
+        direction: Option<String>,
+        /// Reject inputs of other dimensions, as `WIDTHxHEIGHT` (skipped with `force`)
+        #[serde(default)]
+        expected_dimensions: Option<String>,
+        /// Container format of the slices (default: mp4)
+        #[serde(default)]
+        format: Option<String>,
+        /// Cut into segments of this many seconds instead of slices
+        #[serde(default)]
+        segment_duration: Option<f64>,
+        /// Copy the streams when cutting segments
+        #[serde(default)]
+        copy_codec: bool,
+        /// Slices of a file encoded at once (default: 1 when files run in parallel)
+        #[serde(default)]
+        jobs: Option<usize>,
+        /// `auto`, `off` or a hardware encoder name (default: off)
+        #[serde(default)]
+        hw_accel: Option<String>,
+    },
+    Merger {
+        /// The same audio for every video; required unless `audio_ext` is set
+        audio_file: Option<PathBuf>,
+        output_dir: PathBuf,
+        shortest: bool,
+        copy_codec: bool,
+        /// Pair each video with the audio file of the same name and this extension
+        #[serde(default)]
+        audio_ext: Option<String>,
+        /// Directory of the paired audio files (default: next to each video)
+        #[serde(default)]
+        audio_dir: Option<PathBuf>,
+        /// Fade the audio in over this many seconds
+        #[serde(default)]
+        audio_fade_in: Option<f64>,
+        /// Fade the audio out over this many seconds
+        #[serde(default)]
+        audio_fade_out: Option<f64>,
+        /// Container of the merged files (default: mp4)
+        #[serde(default)]
+        format: Option<String>,
+        /// `auto`, `off` or a hardware encoder name (default: off)
+        #[serde(default)]
+        hw_accel: Option<String>,
+    },
+    /// Run a plugin on every file, see [`BatchProcessor::create_plugin_operation`]
+    Plugin {
+        /// Name of a loaded plugin, or of one on the plugin search path
+        name: String,
+        /// Parameters given to every run besides the input and output
+        #[serde(default)]
+        params: BTreeMap<String, String>,
+        output_dir: PathBuf,
+        /// Extensions of the files to run the plugin on (default: mp4, avi, mov, mkv)
+        #[serde(default)]
+        extensions: Vec<String>,
+    },
+}
+
+impl OperationSettings {
+    /// The batch operation these settings configure
+    pub fn operation(&self) -> BatchOperation {
+        match self {
+            OperationSettings::Clipper { .. } => BatchOperation::Clipper,
+            OperationSettings::GifConverter { .. } => BatchOperation::GifConverter,
+            OperationSettings::GifTransparency { .. } => BatchOperation::GifTransparency,
+            OperationSettings::Splitter { .. } => BatchOperation::Splitter,
+            OperationSettings::Merger { .. } => BatchOperation::Merger,
+            OperationSettings::Plugin { .. } => BatchOperation::Custom,
+        }
+    }
+
+    /// Output container chosen in the settings, for the operations that have one
+    fn output_format(&self) -> Result<Option<VideoFormat>> {
+        let format = match self {
+            OperationSettings::Clipper { format, .. }
+            | OperationSettings::Splitter { format, .. }
+            | OperationSettings::Merger { format, .. } => format.as_deref().map(str::trim),
+            OperationSettings::GifConverter { .. }
+            | OperationSettings::GifTransparency { .. }
+            | OperationSettings::Plugin { .. } => None,
+        };
+
+        match format {
+            Some(value) if !value.is_empty() => value
+                .trim_start_matches('.')
+                .parse::<VideoFormat>()
+                .map(Some)
+                .map_err(|e| BatchError::InvalidOperation(e.to_string())),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Outcome of a batch run
+#[derive(Debug)]
+pub struct BatchRunReport {
+    pub results: Vec<BatchItemResult>,
+    /// Files left out by the input filters
+    pub exclusions: Vec<(PathBuf, ExclusionReason)>,
+    /// Set when only part of the matching files was processed (`--limit`/`--sample`)
+    pub selection_summary: Option<String>,
+    /// Whether the error policy stopped the batch before all files were started
+    pub aborted: bool,
+    /// Counts, timing and the slowest files
+    pub summary: BatchSummary,
+}
+
+impl BatchRunReport {
+    /// Number of files processed successfully
+    pub fn success_count(&self) -> usize {
+        self.results.iter().filter(|r| r.success).count()
+    }
+
+    /// Results of the files that failed
+    pub fn failures(&self) -> impl Iterator<Item = &BatchItemResult> {
+        self.results.iter().filter(|r| !r.success)
+    }
+
+    /// Warnings of all processed files, in processing order
+    pub fn warnings(&self) -> impl Iterator<Item = &Warning> {
+        self.results.iter().flat_map(|r| &r.warnings)
+    }
+}
+
+/// Single construction and execution path for batch runs
+pub struct BatchController;
+
+impl BatchController {
+    /// Validate the settings and build a configured processor
+    ///
+    /// All settings errors are reported here, before any file is touched. A
+    /// plugin batch runs its plugin from a new plugin manager, after discovering
+    /// the plugins on the search path.
+    pub fn build_processor(settings: &BatchSettings) -> Result<BatchProcessor> {
+        Self::build_processor_for(settings, None)
+    }
+
+    /// Build a processor like `build_processor`, running a plugin batch with
+    /// `plugins`; its plugins are discovered first if the plugin is not loaded
+    pub fn build_processor_with_plugins(settings: &BatchSettings, plugins: &PluginManager) -> Result<BatchProcessor> {
+        Self::build_processor_for(settings, Some(plugins))
+    }
+
+    fn build_processor_for(settings: &BatchSettings, plugins: Option<&PluginManager>) -> Result<BatchProcessor> {
+        let mut processor = Self::build_operation(&settings.operation, plugins)?
+            .with_recursive(settings.recursive)
+            .with_follow_symlinks(settings.follow_symlinks)
+            .with_parallel(settings.parallel)
+            .with_content_detection(settings.by_content)
+            .with_ordering(settings.order, settings.descending)
+            .with_mirror_structure(settings.mirror_structure);
+        processor.operation_settings = Some(settings.operation.clone());
+
+        if let Some(max_depth) = settings.max_depth {
+            processor = processor.with_max_depth(max_depth)?;
+        }
+        if let Some(ref list) = settings.files_from {
+            processor = processor.with_explicit_files(read_file_list(list)?);
+        }
+        if let Some(ref pattern) = settings.pattern {
+            processor = processor.with_pattern(pattern, settings.pattern_target)?;
+        }
+        for glob in &settings.include {
+            processor = processor.with_include_glob(glob)?;
+        }
+        for glob in &settings.exclude {
+            processor = processor.with_exclude_glob(glob)?;
+        }
+        if let Some(ref since) = settings.since {
+            processor = processor.with_since(since)?;
+        }
+        if let Some(ref until) = settings.until {
+            processor = processor.with_until(until)?;
+        }
+        if let Some(ref size) = settings.min_input_size {
+            processor = processor.with_min_size(parse_size_filter(size)?);
+        }
+        if let Some(ref size) = settings.max_input_size {
+            processor = processor.with_max_size(parse_size_filter(size)?);
+        }
+
+        match (settings.limit, settings.sample) {
+            (Some(_), Some(_)) => {
+                return Err(BatchError::InvalidOperation("Use either a limit or a sample, not both".to_string()));
+            },
+            (Some(n), None) => processor = processor.with_limit(n),
+            (None, Some(n)) => processor = processor.with_sample(n, settings.seed),
+            (None, None) => {},
+        }
+
+        match settings.per_file_timeout {
+            Some(0) => return Err(BatchError::InvalidOperation("The per-file timeout must be at least one second".to_string())),
+            Some(seconds) => processor = processor.with_per_file_timeout(Duration::from_secs(seconds)),
+            None => {},
+        }
+
+        let backoff = settings.retry_backoff.unwrap_or(DEFAULT_RETRY_BACKOFF_SECS);
+        processor = processor.with_retries(settings.retries, Duration::from_secs(backoff));
+
+        let policy = match (settings.fail_fast, settings.max_failures) {
+            (true, Some(_)) => {
+                return Err(BatchError::InvalidOperation("Use either fail-fast or a failure limit, not both".to_string()));
+            },
+            (_, Some(0)) => return Err(BatchError::InvalidOperation("The failure limit must be at least 1".to_string())),
+            (true, None) => ErrorPolicy::StopOnFirstError,
+            (false, Some(n)) => ErrorPolicy::StopAfter(n),
+            (false, None) => ErrorPolicy::ContinueAll,
+        };
+        processor = processor.with_error_policy(policy);
+
+        if let Some(ref checkpoint) = settings.checkpoint {
+            processor = processor.with_checkpoint(checkpoint)?;
+        }
+
+        Ok(processor)
+    }
+
+    /// Run a processor over the inputs, reporting each file as it starts and finishes
+    pub fn run<F>(processor: BatchProcessor, inputs: &[PathBuf], on_progress: Option<F>) -> Result<BatchRunReport>
+    where
+        F: Fn(&BatchProgress) + Send + Sync + 'static,
+    {
+        let processor = match on_progress {
+            Some(callback) => processor.with_progress_callback(callback),
+            None => processor,
+        };
+
+        let started = Instant::now();
+        let results = processor.process(inputs)?;
+        let summary = BatchSummary::from_results(&results, processor.skipped_count(), started.elapsed());
+        let selection_summary = processor.selection_summary(results.len());
+
+        Ok(BatchRunReport {
+            results,
+            exclusions: processor.exclusions(),
+            selection_summary,
+            aborted: processor.aborted(),
+            summary,
+        })
+    }
+
+    /// Build the processor for the operation-specific settings, also used for
+    /// the settings of a single file with sidecar overrides
+    pub(crate) fn build_operation(operation: &OperationSettings, plugins: Option<&PluginManager>) -> Result<BatchProcessor> {
+        let processor = Self::build_operation_config(operation, plugins)?;
+        match operation.output_format()? {
+            Some(format) => processor.with_output_format(format),
+            None => Ok(processor),
+        }
+    }
+
+    fn build_operation_config(operation: &OperationSettings, plugins: Option<&PluginManager>) -> Result<BatchProcessor> {
+        let invalid = |message: &str| Err(BatchError::InvalidOperation(message.to_string()));
+
+        match operation {
+            OperationSettings::Clipper {
+                ranges, output_dir, copy_codec, suffix, avoid_negative_ts, verify_outputs, hw_accel, ..
+            } => {
+                let options = ClipOptions {
+                    avoid_negative_ts: match avoid_negative_ts {
+                        Some(mode) => mode.parse::<AvoidNegativeTs>()?,
+                        None => AvoidNegativeTs::default(),
+                    },
+                    verify_outputs: *verify_outputs,
+                    encoder: parse_hw_accel(hw_accel.as_deref())?,
+                    ..ClipOptions::default()
+                };
+
+                BatchProcessor::create_clipper(ranges, output_dir, *copy_codec, suffix.as_deref(), options)
+            },
+            OperationSettings::GifConverter {
+                width, fps, max_size_mb, optimize, output_dir, crop, boomerang, loop_count,
+                gifsicle, gifsicle_level, gifsicle_lossy, poster, poster_time
+            } => {
+                if *width == Some(0) {
+                    return invalid("Width must be a positive integer");
+                }
+                if *fps == 0 {
+                    return invalid("FPS must be a positive integer");
+                }
+                if *max_size_mb <= 0.0 {
+                    return invalid("Max size must be a positive number");
+                }
+                if !(1..=3).contains(gifsicle_level) {
+                    return invalid("gifsicle level must be between 1 and 3");
+                }
+                if poster_time.as_deref().is_some_and(|time| !validate_timestamp(time)) {
+                    return invalid("Poster time must be a timestamp such as 00:00:05 or 5.5");
+                }
+
+                let crop = match crop.as_deref().map(str::trim) {
+                    Some(value) if !value.is_empty() => Some(value.parse::<CropMode>()?),
+                    _ => None,
+                };
+
+                let options = GifOptions {
+                    crop,
+                    boomerang: *boomerang,
+                    loop_count: *loop_count,
+                    gifsicle: gifsicle.then_some(GifsicleSettings { level: *gifsicle_level, lossy: *gifsicle_lossy }),
+                    write_poster: *poster,
+                    poster_time: poster_time.clone(),
+                    ..GifOptions::default()
+                };
+
+                Ok(BatchProcessor::create_gif_converter(*width, *fps, *max_size_mb, *optimize, output_dir, options))
+            },
+            OperationSettings::GifTransparency {
+                create_backup, backup_dir, webp, legacy_trailer, no_atomic, output_dir
+            } => {
+                let options = TransparencyOptions {
+                    mode: if *legacy_trailer {
+                        TransparencyMode::LegacyTrailer
+                    } else {
+                        TransparencyMode::GraphicControl
+                    },
+                    atomic: !*no_atomic,
+                };
+
+                Ok(BatchProcessor::create_gif_transparency(
+                    *create_backup,
+                    backup_dir.as_deref(),
+                    webp.clone(),
+                    options,
+                    output_dir.as_deref(),
+                ))
+            },
+            OperationSettings::Splitter {
+                output_dir, prefix, custom_encode, force, slices, direction, expected_dimensions,
+                segment_duration, copy_codec, jobs, hw_accel, ..
+            } => {
+                if prefix.trim().is_empty() {
+                    return invalid("Filename prefix must not be empty");
+                }
+                if *slices == Some(0) {
+                    return invalid("Number of slices must be a positive integer");
+                }
+                if segment_duration.is_some_and(|seconds| seconds <= 0.0) {
+                    return invalid("Segment duration must be a positive number of seconds");
+                }
+                if let Some(encode) = custom_encode {
+                    parse_encode_options(encode)?;
+                }
+
+                let options = SplitOptions {
+                    num_slices: slices.unwrap_or(DEFAULT_SLICE_COUNT),
+                    direction: match direction.as_deref().map(str::trim) {
+                        Some(value) if !value.is_empty() => value.parse::<SliceDirection>()?,
+                        _ => SliceDirection::default(),
+                    },
+                    expected_dimensions: match expected_dimensions.as_deref().map(str::trim) {
+                        Some(value) if !value.is_empty() => Some(parse_dimensions(value)?),
+                        _ => None,
+                    },
+                    max_jobs: *jobs,
+                    encoder: parse_hw_accel(hw_accel.as_deref())?,
+                    ..SplitOptions::default()
+                };
+
+                Ok(BatchProcessor::create_splitter(
+                    output_dir,
+                    prefix,
+                    custom_encode.as_deref(),
+                    *force,
+                    options,
+                    *segment_duration,
+                    *copy_codec,
+                ))
+            },
+            OperationSettings::Merger {
+                audio_file, output_dir, shortest, copy_codec, audio_ext, audio_dir, audio_fade_in, audio_fade_out,
+                hw_accel, ..
+            } => {
+                let audio = match (audio_file, audio_ext.as_deref().map(|ext| ext.trim().trim_start_matches('.'))) {
+                    (Some(_), Some(_)) => return invalid("Use either a single audio file or an audio extension to match by name"),
+                    (None, Some("")) => return invalid("Audio extension must not be empty"),
+                    (None, Some(extension)) => {
+                        if let Some(dir) = audio_dir.as_ref().filter(|dir| !dir.is_dir()) {
+                            return Err(BatchError::InvalidOperation(format!("Audio directory not found: {}", dir.display())));
+                        }
+                        AudioPairing::MatchByName { dir: audio_dir.clone(), extension: extension.to_string() }
+                    },
+                    (Some(audio_file), None) => {
+                        if !Path::new(audio_file).is_file() {
+                            return Err(BatchError::InvalidOperation(format!("Audio file not found: {}", audio_file.display())));
+                        }
+                        AudioPairing::Single(audio_file.clone())
+                    },
+                    (None, None) => return invalid("An audio file or an audio extension to match by name is required"),
+                };
+
+                if [audio_fade_in, audio_fade_out].iter().any(|fade| fade.is_some_and(|seconds| seconds <= 0.0)) {
+                    return invalid("Audio fades must be a positive number of seconds");
+                }
+                let options = MergeOptions {
+                    audio_fade_in_secs: *audio_fade_in,
+                    audio_fade_out_secs: *audio_fade_out,
+                    encoder: parse_hw_accel(hw_accel.as_deref())?,
+                    ..MergeOptions::default()
+                };
+
+                Ok(BatchProcessor::create_merger(audio, output_dir, *shortest, *copy_codec, options))
+            },
+            OperationSettings::Plugin { name, params, output_dir, extensions } => {
+                let plugins = match plugins {
+                    Some(plugins) => plugins.clone(),
+                    None => PluginManager::new().map_err(|e| BatchError::Other(e.to_string()))?,
+                };
+                if !plugins.is_loaded(name) {
+                    plugins.discover_plugins();
+                }
+                if !plugins.is_loaded(name) {
+                    return Err(BatchError::InvalidOperation(format!("Plugin '{}' not found on the plugin search path", name)));
+                }
+
+                let base_params = params.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+                let processor = BatchProcessor::create_plugin_operation(&plugins, name, base_params, output_dir)?;
+                if extensions.is_empty() {
+                    Ok(processor)
+                } else {
+                    let extensions: Vec<&str> = extensions.iter().map(|ext| ext.trim()).collect();
+                    Ok(processor.with_custom_extensions(&extensions))
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::WarningCode;
+
+    fn gif_settings() -> OperationSettings {
+        OperationSettings::GifConverter {
+            width: Some(320),
+            fps: 10,
+            max_size_mb: 5.0,
+            optimize: false,
+            output_dir: PathBuf::from("out"),
+            crop: Some("0,0,640,360".to_string()),
+            boomerang: true,
+            loop_count: Some(1),
+            gifsicle: true,
+            gifsicle_level: 2,
+            gifsicle_lossy: Some(40),
+            poster: true,
+            poster_time: None,
+        }
+    }
+
+    #[test]
+    fn test_build_processor_for_each_operation() {
+        let audio_file = std::env::temp_dir().join(format!("batch_controller_audio_{}.mp3", std::process::id()));
+        std::fs::write(&audio_file, b"").unwrap();
+
+        let operations = vec![
+            OperationSettings::Clipper {
+                ranges: vec!["00:00:01-00:00:05".to_string()],
+                output_dir: PathBuf::from("clips"),
+                copy_codec: true,
+                suffix: None,
+                avoid_negative_ts: None,
+                verify_outputs: false,
+                hw_accel: None,
+                format: Some("mov".to_string()),
+            },
+            gif_settings(),
+            OperationSettings::GifTransparency {
+                create_backup: true,
+                backup_dir: None,
+                webp: None,
+                legacy_trailer: false,
+                no_atomic: false,
+                output_dir: None,
+            },
+            OperationSettings::Splitter {
+                output_dir: PathBuf::from("slices"),
+                prefix: "slice".to_string(),
+                custom_encode: Some("-c:v libx264 -metadata title=\"My Clip\"".to_string()),
+                force: false,
+                slices: Some(3),
+                direction: Some("horizontal".to_string()),
+                expected_dimensions: Some("1920x1080".to_string()),
+                format: Some("mkv".to_string()),
+                segment_duration: None,
+                copy_codec: false,
+                jobs: Some(2),
+                hw_accel: Some("auto".to_string()),
+            },
+            OperationSettings::Merger {
+                audio_file: Some(audio_file.clone()),
+                output_dir: PathBuf::from("merged"),
+                shortest: true,
+                copy_codec: true,
+                audio_ext: None,
+                audio_dir: None,
+            audio_fade_in: None,
+            audio_fade_out: None,
+            format: None,
+            hw_accel: None,
+            },
+            OperationSettings::Merger {
+                audio_file: None,
+                output_dir: PathBuf::from("merged"),
+                shortest: true,
+                copy_codec: true,
+                audio_ext: Some("wav".to_string()),
+                audio_dir: None,
+            audio_fade_in: None,
+            audio_fade_out: None,
+            format: None,
+            hw_accel: None,
+            },
+        ];
+
+        for operation in operations {
+            let expected = operation.operation();
+            let processor = BatchController::build_processor(&BatchSettings::new(operation)).unwrap();
+            assert_eq!(processor.operation, expected);
+        }
+
+        std::fs::remove_file(&audio_file).unwrap();
+    }
+
+    #[test]
+    fn test_gif_settings_become_options() {
+        let mut settings = BatchSettings::new(gif_settings());
+        settings.recursive = true;
+        settings.limit = Some(10);
+        settings.per_file_timeout = Some(90);
+        settings.retries = 3;
+        settings.mirror_structure = true;
+
+        let processor = BatchController::build_processor(&settings).unwrap();
+        let config = processor.gif_converter_config.as_ref().unwrap();
+
+        assert_eq!(config.options.crop, Some(CropMode::Region { x: 0, y: 0, width: 640, height: 360 }));
+        assert!(config.options.boomerang);
+        assert_eq!(config.options.loop_count, Some(1));
+        assert_eq!(config.options.gifsicle, Some(GifsicleSettings { level: 2, lossy: Some(40) }));
+        assert!(config.options.write_poster);
+        assert!(config.use_gifsicle);
+        assert!(processor.recursive);
+        assert!(processor.mirror_structure);
+        assert_eq!(processor.selection(), Some(crate::Selection::Limit(10)));
+        assert_eq!(processor.per_file_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(processor.retries.map(|retries| (retries.count, retries.backoff)), Some((3, Duration::from_secs(2))));
+        assert_eq!(processor.error_policy, ErrorPolicy::ContinueAll);
+
+        settings.per_file_timeout = Some(0);
+        assert!(BatchController::build_processor(&settings).is_err());
+
+        settings.per_file_timeout = None;
+        settings.max_failures = Some(5);
+        let processor = BatchController::build_processor(&settings).unwrap();
+        assert_eq!(processor.error_policy, ErrorPolicy::StopAfter(5));
+        settings.fail_fast = true;
+        assert!(BatchController::build_processor(&settings).is_err());
+    }
+
+    #[test]
+    fn test_output_format_settings() {
+        let clipper = |format: &str| OperationSettings::Clipper {
+            ranges: vec!["00:00:01-00:00:05".to_string()],
+            output_dir: PathBuf::from("clips"),
+            copy_codec: false,
+            suffix: None,
+            avoid_negative_ts: None,
+            verify_outputs: false,
+            hw_accel: None,
+            format: Some(format.to_string()),
+        };
+
+        let processor = BatchController::build_processor(&BatchSettings::new(clipper(" WebM "))).unwrap();
+        assert_eq!(processor.clipper_config.as_ref().unwrap().output_format, Some(VideoFormat::WebM));
+        assert!(BatchController::build_processor(&BatchSettings::new(clipper("gif"))).is_err());
+        // Known containers the clipper cannot write
+        assert!(BatchController::build_processor(&BatchSettings::new(clipper("wmv"))).is_err());
+
+        let splitter = OperationSettings::Splitter {
+            output_dir: PathBuf::from("slices"),
+            prefix: "slice".to_string(),
+            custom_encode: None,
+            force: false,
+            slices: None,
+            direction: None,
+            expected_dimensions: None,
+            format: Some("flv".to_string()),
+            segment_duration: None,
+            copy_codec: false,
+            jobs: None,
+            hw_accel: None,
+        };
+        assert!(BatchController::build_processor(&BatchSettings::new(splitter)).is_err());
+    }
+
+    #[test]
+    fn test_settings_validation_errors() {
+        let build = |settings: BatchSettings| BatchController::build_processor(&settings).err();
+
+        let clipper = OperationSettings::Clipper {
+            ranges: vec!["00:00:05".to_string()],
+            output_dir: PathBuf::from("clips"),
+            copy_codec: false,
+            suffix: None,
+            avoid_negative_ts: None,
+            verify_outputs: false,
+            hw_accel: None,
+            format: None,
+        };
+        assert!(build(BatchSettings::new(clipper)).is_some());
+
+        let clipper = OperationSettings::Clipper {
+            ranges: vec!["00:00:01-00:00:05".to_string()],
+            output_dir: PathBuf::from("clips"),
+            copy_codec: true,
+            suffix: None,
+            avoid_negative_ts: Some("sometimes".to_string()),
+            verify_outputs: true,
+            hw_accel: None,
+            format: None,
+        };
+        assert!(build(BatchSettings::new(clipper)).is_some());
+
+        let clipper = OperationSettings::Clipper {
+            ranges: vec!["00:00:01-00:00:05".to_string()],
+            output_dir: PathBuf::from("clips"),
+            copy_codec: false,
+            suffix: None,
+            avoid_negative_ts: None,
+            verify_outputs: false,
+            hw_accel: Some("nvidia gpu".to_string()),
+            format: None,
+        };
+        assert!(build(BatchSettings::new(clipper)).is_some());
+
+        for invalid in [
+            |s: &mut OperationSettings| if let OperationSettings::GifConverter { fps, .. } = s { *fps = 0 },
+            |s: &mut OperationSettings| if let OperationSettings::GifConverter { max_size_mb, .. } = s { *max_size_mb = 0.0 },
+            |s: &mut OperationSettings| if let OperationSettings::GifConverter { crop, .. } = s { *crop = Some("1,2".to_string()) },
+            |s: &mut OperationSettings| if let OperationSettings::GifConverter { gifsicle_level, .. } = s { *gifsicle_level = 4 },
+            |s: &mut OperationSettings| if let OperationSettings::GifConverter { poster_time, .. } = s { *poster_time = Some("halfway".to_string()) },
+        ] {
+            let mut operation = gif_settings();
+            invalid(&mut operation);
+            assert!(build(BatchSettings::new(operation)).is_some());
+        }
+
+        let merger = OperationSettings::Merger {
+            audio_file: Some(PathBuf::from("/nonexistent/audio.mp3")),
+            output_dir: PathBuf::from("merged"),
+            shortest: true,
+            copy_codec: true,
+            audio_ext: None,
+            audio_dir: None,
+            audio_fade_in: None,
+            audio_fade_out: None,
+            format: None,
+            hw_accel: None,
+        };
+        assert!(build(BatchSettings::new(merger)).is_some());
+        for (audio_file, audio_ext, audio_dir, audio_fade_out) in [
+            (None, None, None, None),
+            (None, Some("."), None, None),
+            (Some("music.mp3"), Some("wav"), None, None),
+            (None, Some("wav"), Some("/nonexistent/wavs"), None),
+            (None, Some("wav"), None, Some(0.0)),
+        ] {
+            let merger = OperationSettings::Merger {
+                audio_file: audio_file.map(PathBuf::from),
+                output_dir: PathBuf::from("merged"),
+                shortest: true,
+                copy_codec: true,
+                audio_ext: audio_ext.map(String::from),
+                audio_dir: audio_dir.map(PathBuf::from),
+                audio_fade_in: None,
+                audio_fade_out,
+                format: None,
+                hw_accel: None,
+            };
+            assert!(build(BatchSettings::new(merger)).is_some());
+        }
+
+        let mut settings = BatchSettings::new(gif_settings());
+        settings.limit = Some(5);
+        settings.sample = Some(5);
+        assert!(build(settings).is_some());
+
+        let mut settings = BatchSettings::new(gif_settings());
+        settings.since = Some("yesterday".to_string());
+        assert!(matches!(build(settings), Some(BatchError::InvalidTimeFilter(_))));
+
+        let mut settings = BatchSettings::new(gif_settings());
+        settings.max_input_size = Some("50 MiB".to_string());
+        assert!(matches!(build(settings), Some(BatchError::InvalidSizeFilter(_))));
+
+        let mut settings = BatchSettings::new(gif_settings());
+        settings.pattern = Some("(".to_string());
+        assert!(matches!(build(settings), Some(BatchError::RegexError(_))));
+
+        let mut settings = BatchSettings::new(gif_settings());
+        settings.exclude = vec!["**/tmp/**".to_string(), "{proxy".to_string()];
+        assert!(matches!(build(settings), Some(BatchError::GlobError(_))));
+    }
+
+    #[test]
+    fn test_settings_round_trip_json() {
+        let mut settings = BatchSettings::new(OperationSettings::GifTransparency {
+            create_backup: true,
+            backup_dir: Some(PathBuf::from("backups")),
+            webp: Some(BatchWebpConfig { quality: 80, lossless: false, output_dir: None }),
+            legacy_trailer: true,
+            no_atomic: true,
+            output_dir: Some(PathBuf::from("transparent")),
+        });
+        settings.sample = Some(3);
+        settings.seed = Some(7);
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(json.contains("\"type\":\"gif_transparency\""));
+        assert_eq!(serde_json::from_str::<BatchSettings>(&json).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_report_collects_item_warnings() {
+        let item = |name: &str, warnings: Vec<Warning>| BatchItemResult {
+            warnings,
+            duration: Duration::ZERO,
+            ..BatchItemResult::succeeded(Path::new(name), None)
+        };
+        let gifsicle = Warning::new(WarningCode::GifsicleMissing, "gifsicle not found in PATH").with_path("a.gif");
+        let width = Warning::new(WarningCode::WidthDefaulted, "used 480px").with_path("c.mp4");
+
+        let report = BatchRunReport {
+            results: vec![item("a.mp4", vec![gifsicle.clone()]), item("b.mp4", Vec::new()), item("c.mp4", vec![width.clone()])],
+            exclusions: Vec::new(),
+            selection_summary: None,
+            aborted: false,
+            summary: BatchSummary::from_results(&[], 0, Duration::ZERO),
+        };
+
+        assert_eq!(report.warnings().cloned().collect::<Vec<_>>(), [gifsicle, width]);
+    }
+}
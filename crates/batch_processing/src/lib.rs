@@ -1,17 +1,47 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use walkdir::WalkDir;
 use rayon::prelude::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use regex::Regex;
+use globset::{Glob, GlobMatcher};
 use thiserror::Error;
-
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use checkpoint::Checkpoint;
+use clipper::ClipOptions;
+use gif_converter::GifOptions;
+use gif_transparency::GifStatus;
+use splitter::SplitOptions;
+use merger::MergeOptions;
 use common::{
-    VideoToolkitError,
-    check_ffmpeg, verify_input_file, validate_time_range
+    CancellationToken, FormatType, ImageFormat, VideoFormat, VideoToolkitError, Warning,
+    cancellation_scope, check_ffmpeg, deadline_scope, detect_format_by_magic, get_supported_formats, is_dry_run,
+    is_format_supported_for_operation, is_transient_io, verify_input_file,
+    validate_time_range
 };
 
+mod checkpoint;
+mod controller;
+mod plugin;
+mod profile;
+mod report;
+mod sidecar;
+mod watch;
+
+pub use controller::{BatchController, BatchRunReport, BatchSettings, OperationSettings};
+pub use plugin::{plugin_input_parameter, plugin_output_parameter, DEFAULT_PLUGIN_EXTENSIONS};
+pub use profile::PLUGIN_PROFILE_TYPE;
+pub use report::{write_report, BatchSummary, ReportFormat, SLOWEST_SHOWN};
+pub use sidecar::{sidecar_path, SIDECAR_SUFFIX};
+pub use watch::DEFAULT_POLL_INTERVAL;
+
 /// Errors specific to batch processing
 #[derive(Error, Debug)]
 pub enum BatchError {
@@ -30,6 +60,18 @@ pub enum BatchError {
     #[error("Regex error: {0}")]
     RegexError(#[from] regex::Error),
 
+    #[error("Glob error: {0}")]
+    GlobError(#[from] globset::Error),
+
+    #[error("Invalid time filter '{0}': expected an RFC 3339 timestamp or a relative duration like 36h or 7d")]
+    InvalidTimeFilter(String),
+
+    #[error("Invalid size filter '{0}': expected a size in bytes or with a unit like 500KB, 50MB or 2GB")]
+    InvalidSizeFilter(String),
+
+    #[error("Cannot create output directory '{}': {source}", path.display())]
+    OutputDirectory { path: PathBuf, source: std::io::Error },
+
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -45,6 +87,22 @@ pub enum BatchOperation {
     GifTransparency,
     Splitter,
     Merger,
+    /// A caller-supplied operation, set with [`BatchProcessor::with_custom_operation`]
+    Custom,
+}
+
+impl BatchOperation {
+    /// Name of the operation in [`is_format_supported_for_operation`]
+    fn format_operation(&self) -> &'static str {
+        match self {
+            BatchOperation::Clipper => "clipper",
+            BatchOperation::GifConverter => "gif_converter",
+            BatchOperation::GifTransparency => "gif_transparency",
+            BatchOperation::Splitter => "splitter",
+            BatchOperation::Merger => "merger",
+            BatchOperation::Custom => "custom",
+        }
+    }
 }
 
 impl std::fmt::Display for BatchOperation {
@@ -55,6 +113,7 @@ impl std::fmt::Display for BatchOperation {
             BatchOperation::GifTransparency => write!(f, "GIF Transparency"),
             BatchOperation::Splitter => write!(f, "Video Splitter"),
             BatchOperation::Merger => write!(f, "Audio/Video Merger"),
+            BatchOperation::Custom => write!(f, "Custom Operation"),
         }
     }
 }
@@ -66,6 +125,392 @@ pub struct BatchItemResult {
     pub output: Option<PathBuf>,
     pub success: bool,
     pub error_message: Option<String>,
+    /// Additional detail about a successful item
+    pub message: Option<String>,
+    /// Copy of the original file taken before an in-place modification
+    pub backup: Option<PathBuf>,
+    /// Files written besides `output`, such as a GIF's poster frame
+    pub extra_outputs: Vec<PathBuf>,
+    /// Non-fatal problems met while processing this file
+    pub warnings: Vec<Warning>,
+    /// Number of times the operation ran on this file (0 if it never started)
+    pub attempts: u32,
+    /// Errors of the failed attempts before the last one
+    pub retried_errors: Vec<String>,
+    /// Whether the failure may go away when the file is run again
+    pub transient: bool,
+    /// Time spent on this file, retries included
+    pub duration: std::time::Duration,
+    /// Combined size of `output` and `extra_outputs`, counting only regular files
+    pub output_size_bytes: Option<u64>,
+    /// Sidecar file whose overrides were applied to this file's settings
+    pub sidecar: Option<PathBuf>,
+}
+
+impl BatchItemResult {
+    /// Result of a file processed successfully, for custom operations
+    pub fn succeeded(input: &Path, output: Option<PathBuf>) -> Self {
+        BatchItemResult {
+            input: input.to_path_buf(),
+            output,
+            success: true,
+            error_message: None,
+            message: None,
+            backup: None,
+            extra_outputs: Vec::new(),
+            warnings: Vec::new(),
+            attempts: 1,
+            retried_errors: Vec::new(),
+            transient: false,
+            duration: std::time::Duration::ZERO,
+            output_size_bytes: None,
+            sidecar: None,
+        }
+    }
+
+    /// Result of a file that failed with `error`, for custom operations
+    pub fn failed(input: &Path, error: impl Into<String>) -> Self {
+        BatchItemResult {
+            success: false,
+            error_message: Some(error.into()),
+            ..Self::succeeded(input, None)
+        }
+    }
+
+    /// Combined size of the output files written for this item, if any exist
+    fn output_size(&self) -> Option<u64> {
+        let sizes: Vec<u64> = self.output.iter().chain(&self.extra_outputs)
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .collect();
+        (!sizes.is_empty()).then(|| sizes.iter().sum())
+    }
+
+    /// Write a one-line summary of the item to the session log
+    fn log(&self) {
+        let output = self.output.as_ref().map(|output| format!(" -> {}", output.display())).unwrap_or_default();
+        let warnings = if self.warnings.is_empty() {
+            String::new()
+        } else {
+            let warnings: Vec<String> = self.warnings.iter().map(Warning::to_string).collect();
+            format!(" [warnings: {}]", warnings.join("; "))
+        };
+        let attempts = if self.attempts > 1 { format!(" (after {} attempts)", self.attempts) } else { String::new() };
+        let sidecar = self.sidecar.as_ref().map(|sidecar| format!(" [overrides: {}]", sidecar.display())).unwrap_or_default();
+        if self.success {
+            let message = self.message.as_ref().map(|message| format!(" ({})", message)).unwrap_or_default();
+            log::info!("OK {}{}{}{}{}{}", self.input.display(), output, message, attempts, sidecar, warnings);
+        } else {
+            let error = self.error_message.as_deref().unwrap_or("unknown error");
+            log::warn!("FAILED {}{}: {}{}{}{}", self.input.display(), output, error, attempts, sidecar, warnings);
+        }
+    }
+}
+
+/// Point in the processing of a file at which progress is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressPhase {
+    #[default]
+    Started,
+    Finished,
+}
+
+/// Progress of a batch run, reported when each file starts and when it finishes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BatchProgress {
+    /// Files finished so far
+    pub completed: usize,
+    pub total: usize,
+    /// File that just started or finished; `None` for the event sent before the first file
+    pub current: Option<PathBuf>,
+    pub phase: ProgressPhase,
+}
+
+type ProgressCallback = Box<dyn Fn(&BatchProgress) + Send + Sync>;
+
+/// Operation run on each file of a [`BatchOperation::Custom`] batch
+pub type CustomOperation = Box<dyn Fn(&Path) -> BatchItemResult + Send + Sync>;
+
+/// An output a batch run would write, as listed by [`BatchProcessor::plan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedItem {
+    pub input: PathBuf,
+    /// Output file; for time-based segments and slices a pattern such as `out/intro_slice_*.mp4`
+    pub output: PathBuf,
+    pub operation: BatchOperation,
+}
+
+/// Reason a discovered input file was left out of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionReason {
+    /// Last modified before the `since` bound
+    ModifiedBeforeSince,
+    /// Last modified at or after the `until` bound
+    ModifiedAfterUntil,
+    /// Smaller than the minimum size
+    BelowMinSize,
+    /// Larger than the maximum size
+    AboveMaxSize,
+    /// Size or modification time could not be read
+    MetadataUnreadable,
+    /// Processed successfully by an earlier run with the same checkpoint
+    AlreadyCompleted,
+}
+
+impl std::fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExclusionReason::ModifiedBeforeSince => write!(f, "modified before --since"),
+            ExclusionReason::ModifiedAfterUntil => write!(f, "modified at or after --until"),
+            ExclusionReason::BelowMinSize => write!(f, "smaller than --min-input-size"),
+            ExclusionReason::AboveMaxSize => write!(f, "larger than --max-input-size"),
+            ExclusionReason::MetadataUnreadable => write!(f, "size or modification time unreadable"),
+            ExclusionReason::AlreadyCompleted => write!(f, "already done according to --checkpoint"),
+        }
+    }
+}
+
+/// Parse a time filter relative to `now`
+///
+/// Accepts RFC 3339 timestamps (any offset, normalised to UTC) or relative
+/// durations such as `90s`, `30m`, `36h`, `7d` and `2w`, meaning that long before `now`.
+pub fn parse_time_filter(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    let invalid = || BatchError::InvalidTimeFilter(value.to_string());
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+
+    let duration = match unit {
+        "s" => TimeDelta::try_seconds(amount),
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => return Err(invalid()),
+    };
+
+    // Amounts too large for a date are refused rather than wrapping or panicking
+    duration.and_then(|duration| now.checked_sub_signed(duration)).ok_or_else(invalid)
+}
+
+/// Read a list of input files, one path per line; `-` reads standard input
+///
+/// Blank lines are skipped. Relative paths are relative to the working directory.
+pub fn read_file_list(source: &Path) -> Result<Vec<PathBuf>> {
+    let contents = if source == Path::new("-") {
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut contents)?;
+        contents
+    } else {
+        std::fs::read_to_string(source).map_err(|e| {
+            BatchError::Other(format!("Cannot read file list '{}': {}", source.display(), e))
+        })?
+    };
+
+    Ok(contents.lines().filter(|line| !line.trim().is_empty()).map(PathBuf::from).collect())
+}
+
+/// Parse a size filter such as `500KB`, `50MB`, `1.5GB` or a plain byte count
+///
+/// Units are case-insensitive and binary (1 KB = 1024 bytes).
+pub fn parse_size_filter(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let invalid = || BatchError::InvalidSizeFilter(value.to_string());
+
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: f64 = amount.parse().map_err(|_| invalid())?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+
+    Ok((amount * multiplier as f64).round() as u64)
+}
+
+/// Check a file size against inclusive `min` and `max` bounds
+fn check_size(size: u64, min: Option<u64>, max: Option<u64>) -> Option<ExclusionReason> {
+    if min.is_some_and(|min| size < min) {
+        return Some(ExclusionReason::BelowMinSize);
+    }
+    if max.is_some_and(|max| size > max) {
+        return Some(ExclusionReason::AboveMaxSize);
+    }
+    None
+}
+
+/// Check a modification time against an inclusive `since` and exclusive `until` bound
+fn check_time_window(
+    modified: DateTime<Utc>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Option<ExclusionReason> {
+    if since.is_some_and(|since| modified < since) {
+        return Some(ExclusionReason::ModifiedBeforeSince);
+    }
+    if until.is_some_and(|until| modified >= until) {
+        return Some(ExclusionReason::ModifiedAfterUntil);
+    }
+    None
+}
+
+/// What a batch does once files start failing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Process every file regardless of failures
+    #[default]
+    ContinueAll,
+    /// Start no new files after the first failure
+    StopOnFirstError,
+    /// Start no new files once `n` files have failed
+    StopAfter(usize),
+}
+
+impl ErrorPolicy {
+    /// Number of failures after which no new files are started
+    fn threshold(self) -> Option<usize> {
+        match self {
+            ErrorPolicy::ContinueAll => None,
+            ErrorPolicy::StopOnFirstError => Some(1),
+            ErrorPolicy::StopAfter(n) => Some(n),
+        }
+    }
+}
+
+/// Retries of transient failures, set by [`BatchProcessor::with_retries`]
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    count: u32,
+    backoff: std::time::Duration,
+}
+
+/// Longest wait before a retry, however many attempts came before
+const MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Narrowing of the matched files for a trial run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The first `n` files in sorted order
+    Limit(usize),
+    /// `count` files chosen at random with a seeded RNG, kept in sorted order
+    Sample { count: usize, seed: u64 },
+}
+
+impl std::fmt::Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Selection::Limit(_) => write!(f, "limited"),
+            Selection::Sample { seed, .. } => write!(f, "sampled with seed {}", seed),
+        }
+    }
+}
+
+/// Apply a selection to a sorted list of files
+fn select_files(mut files: Vec<PathBuf>, selection: Selection) -> Vec<PathBuf> {
+    match selection {
+        Selection::Limit(n) => {
+            files.truncate(n);
+            files
+        },
+        Selection::Sample { count, seed } => {
+            // Draw indices rather than files, so the sample keeps the processing order
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut indices = rand::seq::index::sample(&mut rng, files.len(), count.min(files.len())).into_vec();
+            indices.sort_unstable();
+            indices.into_iter().map(|i| files[i].clone()).collect()
+        },
+    }
+}
+
+/// Format a count with thousands separators (e.g. 5,000)
+fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(c);
+    }
+    formatted
+}
+
+/// Output directory and file stem of each input file, for naming its outputs
+///
+/// With `mirror`, files found under one of the input directories keep their
+/// directory relative to it; otherwise all outputs go directly into
+/// `output_dir`. Stems already taken by an earlier file get a numeric suffix
+/// (`intro`, `intro_1`, ...), so that no output is overwritten.
+pub fn output_bases(input_paths: &[PathBuf], files: &[PathBuf], output_dir: &Path, mirror: bool) -> Vec<PathBuf> {
+    let mut taken = HashSet::new();
+
+    files
+        .iter()
+        .map(|file| {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let dir = if mirror {
+                input_paths
+                    .iter()
+                    .filter(|root| root.is_dir())
+                    .find_map(|root| file.parent()?.strip_prefix(root).ok())
+                    .map(|relative| output_dir.join(relative))
+                    .unwrap_or_else(|| output_dir.to_path_buf())
+            } else {
+                output_dir.to_path_buf()
+            };
+
+            let mut base = dir.join(&stem);
+            let mut suffix = 0;
+            while !taken.insert(base.clone()) {
+                suffix += 1;
+                base = dir.join(format!("{}_{}", stem, suffix));
+            }
+            base
+        })
+        .collect()
+}
+
+/// Base of `input_file` in `bases`, or its stem in `output_dir` when it was not prepared
+fn lookup_output_base(bases: &Mutex<HashMap<PathBuf, PathBuf>>, input_file: &Path, output_dir: &Path) -> PathBuf {
+    bases.lock().unwrap().get(input_file).cloned()
+        .unwrap_or_else(|| output_dir.join(input_file.file_stem().unwrap_or_default()))
+}
+
+/// `base` with `suffix` appended to its file name, e.g. `out/intro` + `.gif`
+fn with_name_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Create `dir` when it is a subdirectory of `output_dir` made by mirroring the input tree
+fn create_mirrored_dir(dir: &Path, output_dir: &Path) -> std::io::Result<()> {
+    if dir == output_dir {
+        Ok(())
+    } else {
+        std::fs::create_dir_all(dir)
+    }
+}
+
+/// WebP written next to a transparent GIF, or into the configured WebP directory
+fn webp_output_file(gif: &Path, webp: &BatchWebpConfig) -> PathBuf {
+    let webp_file = gif.with_extension("webp");
+    match webp.output_dir {
+        Some(ref dir) => dir.join(webp_file.file_name().unwrap_or_default()),
+        None => webp_file,
+    }
 }
 
 /// Configuration for batch clipping
@@ -75,6 +520,9 @@ pub struct BatchClipperConfig {
     pub output_dir: PathBuf,
     pub copy_codec: bool,
     pub suffix: Option<String>,
+    pub options: ClipOptions,
+    /// Container of the clips; overrides `options.output_format`
+    pub output_format: Option<VideoFormat>,
 }
 
 /// Configuration for batch GIF conversion
@@ -85,12 +533,30 @@ pub struct BatchGifConverterConfig {
     pub max_size_mb: f64,
     pub optimize: bool,
     pub output_dir: PathBuf,
+    pub options: GifOptions,
+    /// Run the gifsicle post-pass (default settings unless `options.gifsicle` is set)
+    pub use_gifsicle: bool,
+}
+
+/// Configuration for writing an animated WebP alongside each processed GIF
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchWebpConfig {
+    pub quality: u32,
+    pub lossless: bool,
+    /// Directory for the WebP files; `None` writes them next to the GIFs
+    pub output_dir: Option<PathBuf>,
 }
 
 /// Configuration for batch GIF transparency
 #[derive(Debug, Clone)]
 pub struct BatchGifTransparencyConfig {
     pub create_backup: bool,
+    /// Directory for backups; `None` writes `<name>.gif.bak` next to each GIF
+    pub backup_dir: Option<PathBuf>,
+    pub webp: Option<BatchWebpConfig>,
+    pub options: gif_transparency::TransparencyOptions,
+    /// Write transparent copies here instead of modifying the GIFs in place
+    pub output_dir: Option<PathBuf>,
 }
 
 /// Configuration for batch video splitting
@@ -100,23 +566,187 @@ pub struct BatchSplitterConfig {
     pub prefix: String,
     pub custom_encode: Option<String>,
     pub force: bool,
+    /// Slice count and direction
+    pub options: SplitOptions,
+    /// Cut into segments of this many seconds instead of spatial slices
+    pub segment_duration: Option<f64>,
+    /// Copy the streams when cutting segments instead of re-encoding
+    pub copy_codec: bool,
+    /// Container of the slices; overrides `options.output_format` (segments stay MP4)
+    pub output_format: Option<VideoFormat>,
+}
+
+/// Part of a file's path the regex pattern is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PatternTarget {
+    /// The file name, e.g. `intro.mp4`
+    #[default]
+    #[serde(rename = "name")]
+    FileName,
+    /// The path relative to the scanned directory, e.g. `2024-05/intro.mp4`
+    #[serde(rename = "relative")]
+    RelativePath,
+    /// The absolute path
+    #[serde(rename = "full")]
+    FullPath,
+}
+
+impl std::fmt::Display for PatternTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternTarget::FileName => write!(f, "name"),
+            PatternTarget::RelativePath => write!(f, "relative"),
+            PatternTarget::FullPath => write!(f, "full"),
+        }
+    }
+}
+
+impl std::str::FromStr for PatternTarget {
+    type Err = BatchError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "name" => Ok(PatternTarget::FileName),
+            "relative" => Ok(PatternTarget::RelativePath),
+            "full" => Ok(PatternTarget::FullPath),
+            other => Err(BatchError::InvalidOperation(format!(
+                "Unknown pattern target '{}': expected name, relative or full",
+                other
+            ))),
+        }
+    }
+}
+
+/// Order in which the files of a batch are processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FileOrder {
+    /// By path, so the files of a directory stay together
+    #[default]
+    #[serde(rename = "name")]
+    Name,
+    /// By file size
+    #[serde(rename = "size")]
+    Size,
+    /// By modification time
+    #[serde(rename = "modified")]
+    ModifiedTime,
+}
+
+impl std::fmt::Display for FileOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileOrder::Name => write!(f, "name"),
+            FileOrder::Size => write!(f, "size"),
+            FileOrder::ModifiedTime => write!(f, "modified"),
+        }
+    }
+}
+
+impl std::str::FromStr for FileOrder {
+    type Err = BatchError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "name" => Ok(FileOrder::Name),
+            "size" => Ok(FileOrder::Size),
+            "modified" => Ok(FileOrder::ModifiedTime),
+            other => Err(BatchError::InvalidOperation(format!(
+                "Unknown file order '{}': expected name, size or modified",
+                other
+            ))),
+        }
+    }
+}
+
+/// Sort `files` by a metadata key, keeping path order among equal keys
+///
+/// Files whose metadata cannot be read sort first.
+fn sort_files_by<K: Ord>(files: &mut Vec<PathBuf>, descending: bool, key: impl Fn(&std::fs::Metadata) -> Option<K>) {
+    let mut keyed: Vec<(Option<K>, PathBuf)> = files
+        .drain(..)
+        .map(|file| (std::fs::metadata(&file).ok().and_then(|metadata| key(&metadata)), file))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| if descending { b.cmp(a) } else { a.cmp(b) });
+    files.extend(keyed.into_iter().map(|(_, file)| file));
+}
+
+/// Where the batch merger finds the audio for each video
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioPairing {
+    /// The same audio file for every video
+    Single(PathBuf),
+    /// A file named after each video (`clip001.mp4` + `clip001.wav`), in `dir` or next to the video
+    MatchByName { dir: Option<PathBuf>, extension: String },
+}
+
+impl AudioPairing {
+    /// Audio file to merge with `video`
+    pub fn audio_for(&self, video: &Path) -> PathBuf {
+        match self {
+            AudioPairing::Single(audio_file) => audio_file.clone(),
+            AudioPairing::MatchByName { dir, extension } => {
+                let stem = video.file_stem().unwrap_or_default().to_string_lossy();
+                let dir = dir.as_deref().or_else(|| video.parent()).unwrap_or_else(|| Path::new(""));
+                dir.join(format!("{}.{}", stem, extension.trim_start_matches('.')))
+            },
+        }
+    }
 }
 
 /// Configuration for batch audio/video merging
 #[derive(Debug, Clone)]
 pub struct BatchMergerConfig {
-    pub audio_file: PathBuf,
+    pub audio: AudioPairing,
     pub output_dir: PathBuf,
     pub use_shortest: bool,
     pub copy_codec: bool,
+    /// Audio mode, fades and the other merge options
+    pub options: MergeOptions,
+    /// Container of the merged files; overrides `options.output_format`
+    pub output_format: Option<VideoFormat>,
 }
 
 /// The main batch processor
 pub struct BatchProcessor {
     operation: BatchOperation,
     input_pattern: Option<Regex>,
+    // Files listed by the caller, processed without walking directories
+    explicit_files: Option<Vec<PathBuf>>,
+    pattern_target: PatternTarget,
+    // Globs matched against each file's path relative to the scanned directory
+    include_globs: Vec<GlobMatcher>,
+    exclude_globs: Vec<GlobMatcher>,
+    by_content: bool,
     parallel: bool,
     recursive: bool,
+    follow_symlinks: bool,
+    // Deepest directory level scanned by a recursive run, 1 being the files directly in an input
+    max_depth: Option<usize>,
+    modified_since: Option<DateTime<Utc>>,
+    modified_until: Option<DateTime<Utc>>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    selection: Option<Selection>,
+    order: FileOrder,
+    descending: bool,
+
+    // Files skipped by the input filters during the last run
+    exclusions: Mutex<Vec<(PathBuf, ExclusionReason)>>,
+
+    // Number of files that passed the input filters during the last run
+    matched_count: Mutex<usize>,
+
+    // Destination of each GIF when transparency writes copies to an output directory
+    gif_output_paths: Mutex<HashMap<PathBuf, PathBuf>>,
+
+    // Backup of each GIF when transparency backs up into a backup directory
+    gif_backup_paths: Mutex<HashMap<PathBuf, PathBuf>>,
+
+    // Recreate the input directories under the output directory instead of writing all outputs into it
+    mirror_structure: bool,
+
+    // Output directory and file stem of each input during the last run, shared with plugin runs
+    output_bases: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
 
     // Operation-specific configurations
     clipper_config: Option<BatchClipperConfig>,
@@ -124,9 +754,36 @@ pub struct BatchProcessor {
     gif_transparency_config: Option<BatchGifTransparencyConfig>,
     splitter_config: Option<BatchSplitterConfig>,
     merger_config: Option<BatchMergerConfig>,
+    custom_operation: Option<CustomOperation>,
+    // Directory a custom operation writes its outputs into, if it writes any
+    custom_output_dir: Option<PathBuf>,
+    // Settings the configurations were built from, which sidecar files override per file
+    operation_settings: Option<OperationSettings>,
+    // Extensions picked by a custom operation when no pattern or glob is set
+    custom_extensions: Vec<String>,
 
     // Progress callback
-    progress_callback: Option<Box<dyn Fn(usize, usize) + Send + Sync>>,
+    progress_callback: Option<ProgressCallback>,
+
+    // Stops the run: no new files are started and running FFmpeg processes are killed
+    cancel: Option<CancellationToken>,
+
+    // Time after which the FFmpeg runs of a file are killed and the file recorded as failed
+    per_file_timeout: Option<std::time::Duration>,
+
+    // How often and after which delay transient failures are retried
+    retries: Option<RetryPolicy>,
+
+    // Journal of finished files; files it lists as successful are skipped
+    checkpoint: Option<Checkpoint>,
+
+    // When to stop starting new files, and the failures counted towards it in the last run
+    error_policy: ErrorPolicy,
+    failure_count: Mutex<usize>,
+    aborted: Mutex<bool>,
+
+    // Selected files the last run did not start because it stopped early
+    not_started: Mutex<usize>,
 }
 
 impl BatchProcessor {
@@ -135,23 +792,97 @@ impl BatchProcessor {
         Self {
             operation,
             input_pattern: None,
+            explicit_files: None,
+            pattern_target: PatternTarget::FileName,
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            by_content: false,
             parallel: true,
             recursive: false,
+            follow_symlinks: false,
+            max_depth: None,
+            modified_since: None,
+            modified_until: None,
+            min_size: None,
+            max_size: None,
+            selection: None,
+            order: FileOrder::Name,
+            descending: false,
+            exclusions: Mutex::new(Vec::new()),
+            matched_count: Mutex::new(0),
+            gif_output_paths: Mutex::new(HashMap::new()),
+            gif_backup_paths: Mutex::new(HashMap::new()),
+            mirror_structure: false,
+            output_bases: Arc::new(Mutex::new(HashMap::new())),
             clipper_config: None,
             gif_converter_config: None,
             gif_transparency_config: None,
             splitter_config: None,
             merger_config: None,
+            custom_operation: None,
+            custom_output_dir: None,
+            operation_settings: None,
+            custom_extensions: Vec::new(),
             progress_callback: None,
+            cancel: None,
+            per_file_timeout: None,
+            retries: None,
+            checkpoint: None,
+            error_policy: ErrorPolicy::ContinueAll,
+            failure_count: Mutex::new(0),
+            aborted: Mutex::new(false),
+            not_started: Mutex::new(0),
         }
     }
 
-    /// Set a regex pattern to filter input files
-    pub fn with_pattern(mut self, pattern: &str) -> Result<Self> {
+    /// Set a regex pattern to filter input files, matched against `target`
+    ///
+    /// Paths are written with `/` on every OS. For a file given directly rather
+    /// than found in a directory, the relative path is just its name.
+    pub fn with_pattern(mut self, pattern: &str, target: PatternTarget) -> Result<Self> {
         self.input_pattern = Some(Regex::new(pattern)?);
+        self.pattern_target = target;
         Ok(self)
     }
 
+    /// Process these files besides the input paths, e.g. a list made by another tool
+    ///
+    /// The files are not searched for in directories, but the pattern, globs,
+    /// extensions and other filters still apply. Entries that do not exist are
+    /// kept and reported as failed files instead of stopping the batch.
+    pub fn with_explicit_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.explicit_files = Some(files);
+        self
+    }
+
+    /// Only process files matching `glob` (or another include glob), e.g. `*.mp4`
+    ///
+    /// Globs are matched against the path relative to the scanned directory, or
+    /// the file name for files given directly; `*` also matches across `/`.
+    /// Like a pattern, include globs replace the default choice of files by
+    /// extension, and they combine with a pattern when both are set.
+    pub fn with_include_glob(mut self, glob: &str) -> Result<Self> {
+        self.include_globs.push(Glob::new(glob)?.compile_matcher());
+        Ok(self)
+    }
+
+    /// Skip files matching `glob`, e.g. `*_proxy*` or `**/tmp/**`
+    ///
+    /// Matched like [`BatchProcessor::with_include_glob`], after the includes.
+    pub fn with_exclude_glob(mut self, glob: &str) -> Result<Self> {
+        self.exclude_globs.push(Glob::new(glob)?.compile_matcher());
+        Ok(self)
+    }
+
+    /// Pick input files by their content rather than their extension
+    ///
+    /// Without a pattern, any file whose first bytes identify a video (or a GIF,
+    /// for transparency) is taken, whatever it is named.
+    pub fn with_content_detection(mut self, by_content: bool) -> Self {
+        self.by_content = by_content;
+        self
+    }
+
     /// Enable or disable parallel processing
     pub fn with_parallel(mut self, parallel: bool) -> Self {
         self.parallel = parallel;
@@ -164,6 +895,108 @@ impl BatchProcessor {
         self
     }
 
+    /// Descend into symlinked directories; a link back to one of its own parents is skipped
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Limit a recursive scan to this many directory levels, 1 being the files directly in an input
+    pub fn with_max_depth(mut self, max_depth: usize) -> Result<Self> {
+        if max_depth == 0 {
+            return Err(BatchError::InvalidOperation("The maximum depth must be at least 1".to_string()));
+        }
+        self.max_depth = Some(max_depth);
+        Ok(self)
+    }
+
+    /// Only process files modified at or after this time (RFC 3339 or relative, e.g. `24h`)
+    pub fn with_since(mut self, since: &str) -> Result<Self> {
+        self.modified_since = Some(parse_time_filter(since, Utc::now())?);
+        Ok(self)
+    }
+
+    /// Only process files modified before this time (RFC 3339 or relative, e.g. `24h`)
+    pub fn with_until(mut self, until: &str) -> Result<Self> {
+        self.modified_until = Some(parse_time_filter(until, Utc::now())?);
+        Ok(self)
+    }
+
+    /// Only process files modified at or after `time`
+    pub fn with_modified_since(mut self, time: SystemTime) -> Self {
+        self.modified_since = Some(DateTime::<Utc>::from(time));
+        self
+    }
+
+    /// Only process files of at least `bytes`
+    pub fn with_min_size(mut self, bytes: u64) -> Self {
+        self.min_size = Some(bytes);
+        self
+    }
+
+    /// Only process files of at most `bytes`
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Process the files in this order instead of by ascending path
+    ///
+    /// Processing the largest files first, for example, keeps parallel workers busy
+    /// until the end. A limit takes the first files in this order, and sequential
+    /// runs return their results in it.
+    pub fn with_ordering(mut self, order: FileOrder, descending: bool) -> Self {
+        self.order = order;
+        self.descending = descending;
+        self
+    }
+
+    /// Only process the first `n` matching files (in processing order)
+    pub fn with_limit(mut self, n: usize) -> Self {
+        self.selection = Some(Selection::Limit(n));
+        self
+    }
+
+    /// Only process `n` randomly chosen matching files
+    ///
+    /// Without a seed a random one is picked; it is reported by `selection_summary`
+    /// so the same sample can be drawn again.
+    pub fn with_sample(mut self, n: usize, seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(rand::random);
+        self.selection = Some(Selection::Sample { count: n, seed });
+        self
+    }
+
+    /// The limit or sample applied to the matched files, if any
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// Describe how many of the matching files the last run processed
+    /// (e.g. "processed 10 of 5,000 matching files — limited")
+    pub fn selection_summary(&self, processed: usize) -> Option<String> {
+        let selection = self.selection?;
+        let matched = *self.matched_count.lock().unwrap();
+        Some(format!(
+            "processed {} of {} matching files — {}",
+            format_count(processed), format_count(matched), selection
+        ))
+    }
+
+    /// Files left out by the input filters during the last call to `process`
+    pub fn exclusions(&self) -> Vec<(PathBuf, ExclusionReason)> {
+        self.exclusions.lock().unwrap().clone()
+    }
+
+    /// Write each output under the path of its input relative to the input directory
+    ///
+    /// Applies to the clipper, GIF converter, splitter and merger; otherwise all
+    /// outputs go directly into the output directory.
+    pub fn with_mirror_structure(mut self, mirror_structure: bool) -> Self {
+        self.mirror_structure = mirror_structure;
+        self
+    }
+
     /// Set configuration for batch clipping
     pub fn with_clipper_config(mut self, config: BatchClipperConfig) -> Self {
         self.clipper_config = Some(config);
@@ -194,57 +1027,283 @@ impl BatchProcessor {
         self
     }
 
-    /// Set a progress callback function
+    /// Set the operation run on each file of a custom batch
+    ///
+    /// File discovery, filters, parallelism, retries, progress and reports work as
+    /// for the built-in operations. The operation returns the file's result; its
+    /// duration and output size are filled in afterwards. Custom batches do not
+    /// check for FFmpeg, and have no outputs to list in a [`plan`](Self::plan).
+    pub fn with_custom_operation(mut self, operation: CustomOperation) -> Self {
+        self.custom_operation = Some(operation);
+        self
+    }
+
+    /// Extensions of the files a custom batch picks, e.g. `&["mp4", "mov"]`
+    ///
+    /// Like the built-in extension lists, they only apply when no pattern or
+    /// include glob is set. Without any, a custom batch matches no files by
+    /// extension; with content detection, it picks video files.
+    pub fn with_custom_extensions(mut self, extensions: &[&str]) -> Self {
+        self.custom_extensions = extensions.iter().map(|ext| ext.trim_start_matches('.').to_string()).collect();
+        self
+    }
+
+    /// Write the outputs of a clipper, splitter or merger batch in `format`
+    ///
+    /// Set the operation's config first. Fails if the operation cannot write
+    /// `format` or has no output container to choose.
+    pub fn with_output_format(mut self, format: VideoFormat) -> Result<Self> {
+        let operation = self.operation.format_operation();
+        if !is_format_supported_for_operation(format.extension(), operation) {
+            return Err(BatchError::InvalidOperation(format!(
+                "The {} cannot write {} (supported: {})",
+                self.operation,
+                format,
+                get_supported_formats(operation).join(", ")
+            )));
+        }
+
+        let output_format = match self.operation {
+            BatchOperation::Clipper => self.clipper_config.as_mut().map(|config| &mut config.output_format),
+            BatchOperation::Splitter => self.splitter_config.as_mut().map(|config| &mut config.output_format),
+            BatchOperation::Merger => self.merger_config.as_mut().map(|config| &mut config.output_format),
+            BatchOperation::GifConverter | BatchOperation::GifTransparency | BatchOperation::Custom => None,
+        };
+        match output_format {
+            Some(output_format) => *output_format = Some(format),
+            None => {
+                return Err(BatchError::InvalidOperation(format!(
+                    "No output format can be set for the {} configuration",
+                    self.operation
+                )));
+            },
+        }
+        Ok(self)
+    }
+
+    /// Set a progress callback, called when each file starts and finishes
+    ///
+    /// Without one, progress is shown on a terminal progress bar.
     pub fn with_progress_callback<F>(mut self, callback: F) -> Self
     where
-        F: Fn(usize, usize) + Send + Sync + 'static,
+        F: Fn(&BatchProgress) + Send + Sync + 'static,
     {
         self.progress_callback = Some(Box::new(callback));
         self
     }
 
+    /// Stop the run when the token is cancelled
+    ///
+    /// Files not yet started are reported as failed; the files being processed
+    /// fail with [`VideoToolkitError::Cancelled`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Give up on a file once its FFmpeg runs have taken longer than `timeout`
+    ///
+    /// The file fails with [`VideoToolkitError::Timeout`] and the batch moves on,
+    /// so that a corrupt input cannot hold a worker for the rest of the run.
+    pub fn with_per_file_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.per_file_timeout = Some(timeout);
+        self
+    }
+
+    /// Run a file again up to `count` times after a transient failure, such as
+    /// an I/O error on a network share
+    ///
+    /// The wait before a retry starts at `backoff` and doubles with every attempt,
+    /// up to a minute; cancelling the batch ends it. Permanent failures (missing input, invalid settings, timeouts) are not retried.
+    pub fn with_retries(mut self, count: u32, backoff: std::time::Duration) -> Self {
+        self.retries = (count > 0).then_some(RetryPolicy { count, backoff });
+        self
+    }
+
+    /// Stop starting new files once failures reach the policy's threshold
+    ///
+    /// Files already running in parallel still finish and are reported.
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Record each finished file in a JSONL journal at `path`, and skip the files
+    /// an earlier run with the same journal finished successfully
+    ///
+    /// Files that failed are run again. The skipped files are reported as exclusions.
+    pub fn with_checkpoint(mut self, path: &Path) -> Result<Self> {
+        self.checkpoint = Some(Checkpoint::open(path)?);
+        Ok(self)
+    }
+
+    /// Selected files the last run did not start, because it stopped early or
+    /// an earlier run with the same checkpoint already did them
+    pub fn skipped_count(&self) -> usize {
+        let completed = self.exclusions.lock().unwrap()
+            .iter()
+            .filter(|(_, reason)| *reason == ExclusionReason::AlreadyCompleted)
+            .count();
+        *self.not_started.lock().unwrap() + completed
+    }
+
+    /// Whether the last run stopped early because of the error policy
+    pub fn aborted(&self) -> bool {
+        *self.aborted.lock().unwrap()
+    }
+
     /// Find all input files matching the criteria
     fn find_input_files(&self, input_paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
+        let mut candidates = Vec::new();
+        let mut missing = Vec::new();
+
+        for path in self.explicit_files.iter().flatten() {
+            if !path.is_file() {
+                missing.push(path.clone());
+            } else if self.matches_pattern(path, Path::new(path.file_name().unwrap_or_default())) {
+                candidates.push(path.clone());
+            }
+        }
 
         for path in input_paths {
             if path.is_file() {
                 // Process a single file
-                if self.matches_pattern(path) {
-                    files.push(path.clone());
+                if self.matches_pattern(path, Path::new(path.file_name().unwrap_or_default())) {
+                    candidates.push(path.clone());
                 }
             } else if path.is_dir() {
                 // Process a directory
-                let walker = if self.recursive {
-                    WalkDir::new(path)
-                } else {
-                    WalkDir::new(path).max_depth(1)
-                };
-
-                for entry in walker.into_iter().filter_map(|e| e.ok()) {
+                let max_depth = if self.recursive { self.max_depth.unwrap_or(usize::MAX) } else { 1 };
+                let walker = WalkDir::new(path).follow_links(self.follow_symlinks).max_depth(max_depth);
+
+                for entry in walker.into_iter().filter_map(|entry| match entry {
+                    Ok(entry) => Some(entry),
+                    // WalkDir reports a symlink loop instead of following it
+                    Err(e) if e.loop_ancestor().is_some() => {
+                        log::warn!("Skipping a symlink loop: {}", e);
+                        None
+                    },
+                    Err(_) => None,
+                }) {
                     let entry_path = entry.path();
-                    if entry_path.is_file() && self.matches_pattern(entry_path) {
-                        files.push(entry_path.to_path_buf());
+                    let relative = entry_path.strip_prefix(path).unwrap_or(entry_path);
+                    if entry_path.is_file() && self.matches_pattern(entry_path, relative) {
+                        candidates.push(entry_path.to_path_buf());
                     }
                 }
             }
         }
 
-        if files.is_empty() {
+        // Apply the size and modification-time filters and record what was left out
+        let mut files = Vec::with_capacity(candidates.len());
+        let mut exclusions = Vec::new();
+        for path in candidates {
+            match self.check_exclusion(&path) {
+                Some(reason) => exclusions.push((path, reason)),
+                None => files.push(path),
+            }
+        }
+        *self.exclusions.lock().unwrap() = exclusions;
+
+        if files.is_empty() && missing.is_empty() {
             return Err(BatchError::NoInputFiles);
         }
 
+        // Sort for a stable processing order, then narrow down for trial runs
+        files.sort();
+        match self.order {
+            FileOrder::Name if self.descending => files.reverse(),
+            FileOrder::Name => {},
+            FileOrder::Size => sort_files_by(&mut files, self.descending, |metadata| Some(metadata.len())),
+            FileOrder::ModifiedTime => sort_files_by(&mut files, self.descending, |metadata| metadata.modified().ok()),
+        }
+        *self.matched_count.lock().unwrap() = files.len();
+        if let Some(selection) = self.selection {
+            files = select_files(files, selection);
+        }
+        // Listed files that do not exist fail rather than vanish from the results
+        files.extend(missing);
+
         Ok(files)
     }
 
-    /// Check if a file matches the pattern
-    fn matches_pattern(&self, path: &Path) -> bool {
-        if let Some(ref pattern) = self.input_pattern {
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                return pattern.is_match(file_name);
+    /// Check whether a matched file should be excluded by the input filters
+    fn check_exclusion(&self, path: &Path) -> Option<ExclusionReason> {
+        if self.checkpoint.as_ref().is_some_and(|checkpoint| checkpoint.is_completed(path)) {
+            return Some(ExclusionReason::AlreadyCompleted);
+        }
+
+        let size_filtered = self.min_size.is_some() || self.max_size.is_some();
+        let time_filtered = self.modified_since.is_some() || self.modified_until.is_some();
+        if !size_filtered && !time_filtered {
+            return None;
+        }
+
+        // A file the filters cannot be checked against is skipped, not the whole scan
+        let unreadable = |e: std::io::Error| {
+            log::warn!("Cannot read metadata of {}: {}", path.display(), e);
+            ExclusionReason::MetadataUnreadable
+        };
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => return Some(unreadable(e)),
+        };
+
+        if size_filtered {
+            if let Some(reason) = check_size(metadata.len(), self.min_size, self.max_size) {
+                return Some(reason);
             }
+        }
+        if time_filtered {
+            let modified = match metadata.modified() {
+                Ok(modified) => modified,
+                Err(e) => return Some(unreadable(e)),
+            };
+            return check_time_window(DateTime::<Utc>::from(modified), self.modified_since, self.modified_until);
+        }
+        None
+    }
+
+    /// Text the regex pattern is matched against, with `/` as the separator
+    fn pattern_subject(&self, path: &Path, relative: &Path) -> Option<String> {
+        let subject = match self.pattern_target {
+            PatternTarget::FileName => PathBuf::from(path.file_name()?),
+            PatternTarget::RelativePath => relative.to_path_buf(),
+            PatternTarget::FullPath => std::path::absolute(path).ok()?,
+        };
+        let subject = subject.to_str()?;
+        if std::path::MAIN_SEPARATOR == '/' {
+            Some(subject.to_string())
+        } else {
+            Some(subject.replace(std::path::MAIN_SEPARATOR, "/"))
+        }
+    }
+
+    /// Check if a file matches the include globs, exclude globs and pattern
+    ///
+    /// `relative` is the file's path relative to the scanned directory.
+    fn matches_pattern(&self, path: &Path, relative: &Path) -> bool {
+        if !self.include_globs.is_empty() && !self.include_globs.iter().any(|glob| glob.is_match(relative)) {
             return false;
         }
+        if self.exclude_globs.iter().any(|glob| glob.is_match(relative)) {
+            return false;
+        }
+        if !self.include_globs.is_empty() && self.input_pattern.is_none() {
+            return true;
+        }
+
+        if let Some(ref pattern) = self.input_pattern {
+            return self.pattern_subject(path, relative).is_some_and(|subject| pattern.is_match(&subject));
+        }
+
+        if self.by_content {
+            return match detect_format_by_magic(path) {
+                Some(FormatType::Image(ImageFormat::GIF)) => self.operation == BatchOperation::GifTransparency,
+                Some(FormatType::Video(_)) => self.operation != BatchOperation::GifTransparency,
+                _ => false,
+            };
+        }
 
         // If no pattern is set, match by extension based on operation
         if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
@@ -264,35 +1323,114 @@ impl BatchProcessor {
                 BatchOperation::GifTransparency => {
                     ext.eq_ignore_ascii_case("gif")
                 },
+                BatchOperation::Custom => self.custom_extensions.iter().any(|custom| ext.eq_ignore_ascii_case(custom)),
             }
         } else {
             false
         }
     }
 
+    /// List the outputs a run over `input_paths` would write, without running FFmpeg
+    ///
+    /// Files are matched as for [`process`](Self::process); nothing is created.
+    pub fn plan(&self, input_paths: &[PathBuf]) -> Result<Vec<PlannedItem>> {
+        let input_files = self.prepare(input_paths)?;
+
+        let mut plan = Vec::new();
+        for input in &input_files {
+            let outputs = match self.operation {
+                BatchOperation::Clipper => self.clipper_config.as_ref().map(|c| self.clip_outputs(c, input)),
+                BatchOperation::GifConverter => {
+                    self.gif_converter_config.as_ref().map(|c| vec![self.gif_output_file(c, input)])
+                },
+                BatchOperation::GifTransparency => {
+                    self.gif_transparency_config.as_ref().map(|c| self.transparency_outputs(c, input))
+                },
+                BatchOperation::Splitter => self.splitter_config.as_ref().map(|c| {
+                    let (output_dir, prefix) = self.splitter_output(c, input);
+                    // Time-based segments are always MP4
+                    let extension = match (c.segment_duration, c.output_format) {
+                        (Some(_), _) => "mp4".to_string(),
+                        (None, Some(format)) => format.extension().to_string(),
+                        (None, None) => c.options.output_format.clone(),
+                    };
+                    vec![output_dir.join(format!("{}_*.{}", prefix, extension))]
+                }),
+                BatchOperation::Merger => self.merger_config.as_ref().map(|c| vec![self.merger_output_file(c, input)]),
+                BatchOperation::Custom => self.custom_operation.as_ref().map(|_| Vec::new()),
+            };
+
+            let outputs = outputs
+                .ok_or_else(|| BatchError::InvalidOperation(format!("{} configuration not set", self.operation)))?;
+            plan.extend(outputs.into_iter().map(|output| PlannedItem {
+                input: input.clone(),
+                output,
+                operation: self.operation,
+            }));
+        }
+
+        Ok(plan)
+    }
+
+    /// Find the input files and work out where their outputs go
+    fn prepare(&self, input_paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let input_files = self.find_input_files(input_paths)?;
+        if let Some(output_dir) = self.gif_transparency_config.as_ref().and_then(|c| c.output_dir.as_ref()) {
+            let destinations = gif_transparency::output_paths(input_paths, &input_files, output_dir);
+            *self.gif_output_paths.lock().unwrap() = input_files.iter().cloned().zip(destinations).collect();
+        }
+        // Backups keep their path below the input directory, so GIFs of the same name do not share one
+        if let Some(backup_dir) = self.gif_transparency_config.as_ref().and_then(|c| c.backup_dir.as_ref()) {
+            let backups = gif_transparency::output_paths(input_paths, &input_files, backup_dir);
+            *self.gif_backup_paths.lock().unwrap() = input_files.iter().cloned().zip(backups).collect();
+        }
+        if let Some(output_dir) = self.output_dir() {
+            let bases = output_bases(input_paths, &input_files, output_dir, self.mirror_structure);
+            *self.output_bases.lock().unwrap() = input_files.iter().cloned().zip(bases).collect();
+        }
+        Ok(input_files)
+    }
+
     /// Process the batch operation on the input files
     pub fn process(&self, input_paths: &[PathBuf]) -> Result<Vec<BatchItemResult>> {
-        // Check if FFmpeg is installed
-        if !check_ffmpeg() {
-            return Err(BatchError::Other("FFmpeg not found".to_string()));
-        }
+        self.check_ffmpeg()?;
 
-        // Find input files
-        let input_files = self.find_input_files(input_paths)?;
+        let input_files = self.prepare(input_paths)?;
+        self.create_output_dir()?;
         let total_files = input_files.len();
+        log::info!("{} batch over {} file(s)", self.operation, total_files);
+        *self.failure_count.lock().unwrap() = 0;
+        *self.aborted.lock().unwrap() = false;
 
         // Create a progress bar if there's no custom callback
         let progress_bar = if self.progress_callback.is_none() {
             let pb = ProgressBar::new(total_files as u64);
             pb.set_style(
                 ProgressStyle::default_bar()
-                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {wide_msg}")
                     .unwrap()
             );
             Some(pb)
         } else {
             None
         };
+        if let Some(ref callback) = self.progress_callback {
+            callback(&BatchProgress { completed: 0, total: total_files, ..Default::default() });
+        }
+
+        // Reports a file starting or finishing to the progress bar or callback
+        let report_progress = |completed: usize, file: &Path, phase: ProgressPhase| {
+            if let Some(ref progress_bar) = progress_bar {
+                match phase {
+                    ProgressPhase::Started => {
+                        progress_bar.set_message(file.file_name().unwrap_or_default().to_string_lossy().into_owned());
+                    },
+                    ProgressPhase::Finished => progress_bar.inc(1),
+                }
+            } else if let Some(ref callback) = self.progress_callback {
+                callback(&BatchProgress { completed, total: total_files, current: Some(file.to_path_buf()), phase });
+            }
+        };
 
         // Process files
         let results = if self.parallel {
@@ -302,17 +1440,19 @@ impl BatchProcessor {
             // Process in parallel using Rayon
             let results: Vec<BatchItemResult> = input_files
                 .par_iter()
+                .filter(|_| !self.aborted())
                 .map(|file| {
+                    report_progress(*processed.lock().unwrap(), file, ProgressPhase::Started);
                     let result = self.process_file(file);
+                    self.count_failure(&result);
 
                     // Update progress
-                    if let Some(ref progress_bar) = progress_bar {
-                        progress_bar.inc(1);
-                    } else if let Some(ref callback) = self.progress_callback {
+                    let completed = {
                         let mut count = processed.lock().unwrap();
                         *count += 1;
-                        callback(*count, total_files);
-                    }
+                        *count
+                    };
+                    report_progress(completed, file, ProgressPhase::Finished);
 
                     result
                 })
@@ -323,14 +1463,15 @@ impl BatchProcessor {
             // Process sequentially
             let mut results = Vec::with_capacity(total_files);
             for (i, file) in input_files.iter().enumerate() {
+                if self.aborted() {
+                    break;
+                }
+                report_progress(i, file, ProgressPhase::Started);
                 let result = self.process_file(file);
+                self.count_failure(&result);
 
                 // Update progress
-                if let Some(ref progress_bar) = progress_bar {
-                    progress_bar.inc(1);
-                } else if let Some(ref callback) = self.progress_callback {
-                    callback(i + 1, total_files);
-                }
+                report_progress(i + 1, file, ProgressPhase::Finished);
 
                 results.push(result);
             }
@@ -340,293 +1481,617 @@ impl BatchProcessor {
 
         // Finish the progress bar
         if let Some(pb) = progress_bar {
-            pb.finish_with_message("Batch processing complete");
+            if self.aborted() {
+                pb.abandon_with_message("Batch stopped early");
+            } else {
+                pb.finish_with_message("Batch processing complete");
+            }
+        }
+        *self.not_started.lock().unwrap() = total_files - results.len();
+        if self.aborted() {
+            log::warn!(
+                "Batch stopped after {} failure(s); {} of {} file(s) were not started",
+                *self.failure_count.lock().unwrap(),
+                total_files - results.len(),
+                total_files
+            );
         }
 
         Ok(results)
     }
 
-    /// Process a single file
-    fn process_file(&self, input_file: &Path) -> BatchItemResult {
+    /// Count a failed file towards the error policy, stopping the batch at its threshold
+    fn count_failure(&self, result: &BatchItemResult) {
+        // Files skipped after a cancellation never ran, so they do not count
+        if result.success || result.attempts == 0 {
+            return;
+        }
+
+        let mut failures = self.failure_count.lock().unwrap();
+        *failures += 1;
+        if self.error_policy.threshold().is_some_and(|threshold| *failures >= threshold) {
+            *self.aborted.lock().unwrap() = true;
+        }
+    }
+
+    /// Output directory of the operations that name their outputs after the input
+    fn output_dir(&self) -> Option<&Path> {
         match self.operation {
-            BatchOperation::Clipper => self.process_clipper(input_file),
-            BatchOperation::GifConverter => self.process_gif_converter(input_file),
-            BatchOperation::GifTransparency => self.process_gif_transparency(input_file),
-            BatchOperation::Splitter => self.process_splitter(input_file),
-            BatchOperation::Merger => self.process_merger(input_file),
+            BatchOperation::Clipper => self.clipper_config.as_ref().map(|c| c.output_dir.as_path()),
+            BatchOperation::GifConverter => self.gif_converter_config.as_ref().map(|c| c.output_dir.as_path()),
+            BatchOperation::Splitter => self.splitter_config.as_ref().map(|c| c.output_dir.as_path()),
+            BatchOperation::Merger => self.merger_config.as_ref().map(|c| c.output_dir.as_path()),
+            BatchOperation::Custom => self.custom_output_dir.as_deref(),
+            BatchOperation::GifTransparency => None,
+        }
+    }
+
+    /// Fail unless FFmpeg is installed; custom operations check for what they need themselves
+    fn check_ffmpeg(&self) -> Result<()> {
+        if self.operation != BatchOperation::Custom && !check_ffmpeg() {
+            return Err(BatchError::Other("FFmpeg not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Create the output directory once before the files are dispatched
+    ///
+    /// Workers then only create the mirrored subdirectories of their own files.
+    fn create_output_dir(&self) -> Result<()> {
+        match self.output_dir() {
+            Some(dir) => std::fs::create_dir_all(dir)
+                .map_err(|source| BatchError::OutputDirectory { path: dir.to_path_buf(), source }),
+            None => Ok(()),
+        }
+    }
+
+    /// Output directory and file stem for the outputs of `input_file`
+    fn output_base(&self, input_file: &Path, output_dir: &Path) -> PathBuf {
+        lookup_output_base(&self.output_bases, input_file, output_dir)
+    }
+
+    /// Output directory and options of the clips of `input_file`
+    fn clip_target(&self, config: &BatchClipperConfig, input_file: &Path) -> (PathBuf, ClipOptions) {
+        // Clips are named after the input, in its mirrored directory when enabled
+        let base = self.output_base(input_file, &config.output_dir);
+        let output_dir = base.parent().unwrap_or(&config.output_dir).to_path_buf();
+
+        let mut options = config.options.clone();
+        if let Some(format) = config.output_format {
+            options.output_format = Some(format.extension().to_string());
+        }
+        options.output_name = base.file_name().map(|name| name.to_string_lossy().into_owned());
+        (output_dir, options)
+    }
+
+    /// Clip files of `input_file`, one per time range
+    fn clip_outputs(&self, config: &BatchClipperConfig, input_file: &Path) -> Vec<PathBuf> {
+        let (output_dir, options) = self.clip_target(config, input_file);
+        let name = options.output_name.unwrap_or_default();
+        let extension = options.output_format.as_deref().unwrap_or(clipper::DEFAULT_OUTPUT_FORMAT);
+        config
+            .time_ranges
+            .iter()
+            .map(|(start, end)| {
+                clipper::clip_file_path(&name, start, end, &output_dir, config.suffix.as_deref())
+                    .with_extension(extension)
+            })
+            .collect()
+    }
+
+    /// GIF written for `input_file`
+    fn gif_output_file(&self, config: &BatchGifConverterConfig, input_file: &Path) -> PathBuf {
+        with_name_suffix(&self.output_base(input_file, &config.output_dir), ".gif")
+    }
+
+    /// The GIF made transparent: a copy in the output directory, or the input itself
+    fn transparency_target(&self, config: &BatchGifTransparencyConfig, input_file: &Path) -> PathBuf {
+        match config.output_dir {
+            Some(ref output_dir) => self.gif_output_paths.lock().unwrap().get(input_file).cloned()
+                .unwrap_or_else(|| output_dir.join(input_file.file_name().unwrap_or_default())),
+            None => input_file.to_path_buf(),
+        }
+    }
+
+    /// The transparent GIF followed by its WebP, if one is written
+    fn transparency_outputs(&self, config: &BatchGifTransparencyConfig, input_file: &Path) -> Vec<PathBuf> {
+        let target = self.transparency_target(config, input_file);
+        let webp = config.webp.as_ref().map(|webp| webp_output_file(&target, webp));
+        std::iter::once(target).chain(webp).collect()
+    }
+
+    /// Output directory and file name prefix of the slices or segments of `input_file`
+    fn splitter_output(&self, config: &BatchSplitterConfig, input_file: &Path) -> (PathBuf, String) {
+        // Outputs of different inputs must not overwrite each other, so their
+        // names start with the input's (disambiguated) stem
+        let base = self.output_base(input_file, &config.output_dir);
+        let output_dir = base.parent().unwrap_or(&config.output_dir).to_path_buf();
+        let prefix = format!("{}_{}", base.file_name().unwrap_or_default().to_string_lossy(), config.prefix);
+        (output_dir, prefix)
+    }
+
+    /// Merged file written for `input_file`
+    fn merger_output_file(&self, config: &BatchMergerConfig, input_file: &Path) -> PathBuf {
+        let extension = match config.output_format {
+            Some(ref format) => format.extension(),
+            None => config.options.output_format.as_deref().unwrap_or("mp4"),
+        };
+        with_name_suffix(&self.output_base(input_file, &config.output_dir), &format!("_merged.{}", extension))
+    }
+
+    /// Process a single file, unless the run was cancelled, and log its result
+    fn process_file(&self, input_file: &Path) -> BatchItemResult {
+        let result = if self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            BatchItemResult {
+                attempts: 0,
+                ..BatchItemResult::failed(input_file, "Not processed: the batch was cancelled")
+            }
+        } else {
+            let started = std::time::Instant::now();
+            let mut result = self.process_with_retries(input_file);
+            result.duration = started.elapsed();
+            result.output_size_bytes = result.output_size();
+            result
+        };
+
+        result.log();
+        if let Some(ref checkpoint) = self.checkpoint {
+            // Files skipped after a cancellation never ran
+            if result.attempts > 0 {
+                checkpoint.record(&result);
+            }
+        }
+        result
+    }
+
+    /// Run the operation on a file, running it again after transient failures
+    /// while retries are left
+    fn process_with_retries(&self, input_file: &Path) -> BatchItemResult {
+        let mut retried_errors = Vec::new();
+        let mut result = self.process_once(input_file);
+        loop {
+            let retries_left = self.retries.is_some_and(|retries| retried_errors.len() < retries.count as usize);
+            if result.success || !result.transient || !retries_left || self.cancelled() {
+                break;
+            }
+
+            // Back off a little longer after every failed attempt, up to a limit
+            let delay = self.retries.map_or(std::time::Duration::ZERO, |retries| {
+                retries.backoff.saturating_mul(2u32.saturating_pow(retried_errors.len() as u32)).min(MAX_RETRY_DELAY)
+            });
+            log::warn!(
+                "Attempt {} on {} failed, retrying in {:?}: {}",
+                retried_errors.len() + 1, input_file.display(), delay, result.error_message.as_deref().unwrap_or_default()
+            );
+            self.sleep_unless_cancelled(delay);
+            if self.cancelled() {
+                break;
+            }
+            retried_errors.push(result.error_message.unwrap_or_default());
+            result = self.process_once(input_file);
+        }
+
+        result.attempts = retried_errors.len() as u32 + 1;
+        result.retried_errors = retried_errors;
+        result
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Wait for `duration`, returning early once the run is cancelled
+    fn sleep_unless_cancelled(&self, duration: std::time::Duration) {
+        let until = std::time::Instant::now() + duration;
+        while !self.cancelled() {
+            let left = until.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(std::time::Duration::from_millis(100)));
         }
     }
 
+    /// Run the operation on a file once
+    fn process_once(&self, input_file: &Path) -> BatchItemResult {
+        // Rayon runs files on its own threads, so each one enters the scopes
+        let _cancel_scope = cancellation_scope(self.cancel.as_ref());
+        let _deadline_scope = deadline_scope(self.per_file_timeout);
+        if !input_file.is_file() {
+            let error = if input_file.exists() { "Input is not a file" } else { "Input file not found" };
+            return BatchItemResult::failed(input_file, error);
+        }
+
+        // A sidecar next to the file overrides part of the batch configuration
+        let (sidecar, file_config) = match self.operation {
+            BatchOperation::Custom => (None, None),
+            _ => match self.sidecar_config(input_file) {
+                Ok(Some((path, processor))) => (Some(path), Some(processor)),
+                Ok(None) => (None, None),
+                Err(e) => return BatchItemResult::failed(input_file, e.to_string()),
+            },
+        };
+        let configs = file_config.as_ref().unwrap_or(self);
+
+        let mut result = match self.operation {
+            BatchOperation::Clipper => self.process_clipper(input_file, configs.clipper_config.as_ref()),
+            BatchOperation::GifConverter => self.process_gif_converter(input_file, configs.gif_converter_config.as_ref()),
+            BatchOperation::GifTransparency => {
+                self.process_gif_transparency(input_file, configs.gif_transparency_config.as_ref())
+            },
+            BatchOperation::Splitter => self.process_splitter(input_file, configs.splitter_config.as_ref()),
+            BatchOperation::Merger => self.process_merger(input_file, configs.merger_config.as_ref()),
+            BatchOperation::Custom => match self.custom_operation {
+                Some(ref operation) => operation(input_file),
+                None => BatchItemResult::failed(input_file, "Custom operation not set"),
+            },
+        };
+        result.sidecar = sidecar;
+        result
+    }
+
     /// Process a file with the clipper
-    fn process_clipper(&self, input_file: &Path) -> BatchItemResult {
-        let config = match &self.clipper_config {
+    fn process_clipper(&self, input_file: &Path, config: Option<&BatchClipperConfig>) -> BatchItemResult {
+        let config = match config {
             Some(config) => config,
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("Clipper configuration not set".to_string()),
-            },
+            None => return BatchItemResult::failed(input_file, "Clipper configuration not set"),
         };
 
         // Verify the input file exists
         if let Err(e) = verify_input_file(&input_file.to_string_lossy()) {
-            return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error verifying input file: {}", e)),
-            };
+            return BatchItemResult::failed(input_file, format!("Error verifying input file: {}", e));
         }
 
-        // Create the output directory
-        if let Err(e) = std::fs::create_dir_all(&config.output_dir) {
+        let (output_dir, options) = self.clip_target(config, input_file);
+
+        // Create the mirrored subdirectory; the output directory itself exists already
+        if let Err(e) = create_mirrored_dir(&output_dir, &config.output_dir) {
             return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error creating output directory: {}", e)),
+                transient: is_transient_io(&e),
+                ..BatchItemResult::failed(input_file, format!("Error creating output directory: {}", e))
             };
         }
 
         // Run the clipper
-        match clipper::clip_video(
+        match clipper::clip_video_with_options(
             &input_file.to_string_lossy(),
             &config.time_ranges,
-            &config.output_dir.to_string_lossy(),
+            &output_dir.to_string_lossy(),
             config.copy_codec,
             config.suffix.as_deref(),
+            &options,
         ) {
-            Ok(true) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(config.output_dir.clone()),
-                success: true,
-                error_message: None,
-            },
+            Ok(true) => BatchItemResult::succeeded(input_file, Some(output_dir.clone())),
             Ok(false) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(config.output_dir.clone()),
-                success: false,
-                error_message: Some("Some clips failed to process".to_string()),
+                output: Some(output_dir.clone()),
+                ..BatchItemResult::failed(input_file, "Some clips failed to process")
             },
             Err(e) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error: {}", e)),
+                transient: e.is_transient(),
+                ..BatchItemResult::failed(input_file, format!("Error: {}", e))
             },
         }
     }
 
     /// Process a file with the GIF converter
-    fn process_gif_converter(&self, input_file: &Path) -> BatchItemResult {
-        let config = match &self.gif_converter_config {
+    fn process_gif_converter(&self, input_file: &Path, config: Option<&BatchGifConverterConfig>) -> BatchItemResult {
+        let config = match config {
             Some(config) => config,
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("GIF converter configuration not set".to_string()),
-            },
+            None => return BatchItemResult::failed(input_file, "GIF converter configuration not set"),
         };
 
         // Create output file path
-        let file_stem = match input_file.file_stem() {
-            Some(stem) => stem.to_string_lossy(),
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("Invalid input filename".to_string()),
-            },
-        };
+        if input_file.file_stem().is_none() {
+            return BatchItemResult::failed(input_file, "Invalid input filename");
+        }
 
-        let output_file = config.output_dir.join(format!("{}.gif", file_stem));
+        let output_file = self.gif_output_file(config, input_file);
 
-        // Create the output directory
-        if let Err(e) = std::fs::create_dir_all(&config.output_dir) {
+        // Create the mirrored subdirectory; the output directory itself exists already
+        if let Err(e) = create_mirrored_dir(output_file.parent().unwrap_or(&config.output_dir), &config.output_dir) {
             return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error creating output directory: {}", e)),
+                transient: is_transient_io(&e),
+                ..BatchItemResult::failed(input_file, format!("Error creating output directory: {}", e))
             };
         }
 
+        // The flag decides whether gifsicle runs; the options only tune it
+        let mut options = config.options.clone();
+        options.gifsicle = if config.use_gifsicle {
+            Some(options.gifsicle.unwrap_or_default())
+        } else {
+            None
+        };
+
         // Run the GIF converter
         let result = if config.optimize {
-            gif_converter::optimize_conversion(
+            gif_converter::optimize_conversion_with_options(
                 &input_file.to_string_lossy(),
                 &output_file.to_string_lossy(),
                 config.max_size_mb,
                 config.width,
+                &options,
             )
         } else {
-            gif_converter::convert_video_to_gif(
+            gif_converter::convert_video_to_gif_with_options(
                 &input_file.to_string_lossy(),
                 &output_file.to_string_lossy(),
                 config.width,
                 config.fps,
                 config.max_size_mb,
+                &options,
             )
         };
 
         match result {
-            Ok(true) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(output_file),
-                success: true,
-                error_message: None,
+            Ok(report) if report.under_limit => BatchItemResult {
+                message: Some(format!("Converted to {}", report)),
+                extra_outputs: report.poster.into_iter().collect(),
+                warnings: report.warnings,
+                ..BatchItemResult::succeeded(input_file, Some(output_file))
             },
-            Ok(false) => BatchItemResult {
-                input: input_file.to_path_buf(),
+            Ok(report) => BatchItemResult {
                 output: Some(output_file),
-                success: false,
-                error_message: Some(format!("Output file exceeds size limit (> {}MB)", config.max_size_mb)),
+                extra_outputs: report.poster.into_iter().collect(),
+                warnings: report.warnings,
+                ..BatchItemResult::failed(input_file, format!(
+                    "Output file exceeds size limit ({:.2}MB > {}MB at {}px, {}fps after {} attempt(s))",
+                    report.size_mb, config.max_size_mb, report.width, report.fps, report.attempts
+                ))
             },
             Err(e) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error: {}", e)),
+                transient: e.is_transient(),
+                ..BatchItemResult::failed(input_file, format!("Error: {}", e))
             },
         }
     }
 
     /// Process a file with the GIF transparency tool
-    fn process_gif_transparency(&self, input_file: &Path) -> BatchItemResult {
-        let _config = match &self.gif_transparency_config {
+    fn process_gif_transparency(&self, input_file: &Path, config: Option<&BatchGifTransparencyConfig>) -> BatchItemResult {
+        let config = match config {
             Some(config) => config,
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("GIF transparency configuration not set".to_string()),
-            },
+            None => return BatchItemResult::failed(input_file, "GIF transparency configuration not set"),
         };
 
-        // Run the GIF transparency tool
-        match gif_transparency::make_gif_transparent(input_file) {
-            Ok(()) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(input_file.to_path_buf()), // The output is the same file
-                success: true,
-                error_message: None,
+        // A dry run only tells what would happen to the GIF
+        if is_dry_run() {
+            let (success, error_message, message) =
+                match gif_transparency::classify_gif_with_mode(input_file, config.options.mode) {
+                    Ok(status @ (GifStatus::WouldModify | GifStatus::AlreadyTransparent)) => {
+                        (true, None, Some(format!("Dry run: {}", status)))
+                    },
+                    Ok(status) => (false, Some(format!("Dry run: {}", status)), None),
+                    Err(e) => (false, Some(format!("Error: {}", e)), None),
+                };
+            return BatchItemResult {
+                success,
+                error_message,
+                message,
+                ..BatchItemResult::succeeded(input_file, None)
+            };
+        }
+
+        let mut messages = Vec::new();
+        let (target, backup) = match config.output_dir {
+            Some(_) => {
+                let destination = self.transparency_target(config, input_file);
+
+                if let Err(e) = gif_transparency::write_transparent_copy(input_file, &destination, &config.options) {
+                    return BatchItemResult {
+                        transient: e.is_transient(),
+                        ..BatchItemResult::failed(input_file, format!("Error: {}", e))
+                    };
+                }
+
+                (destination, None)
             },
-            Err(e) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error: {}", e)),
+            None => {
+                // Only GIFs that change are backed up and rewritten, so a rerun
+                // does not copy a transparent GIF over the backup of the original
+                let changes = match gif_transparency::classify_gif_with_mode(input_file, config.options.mode) {
+                    Ok(GifStatus::WouldModify) => true,
+                    Ok(GifStatus::AlreadyTransparent) => {
+                        messages.push("already transparent".to_string());
+                        false
+                    },
+                    Ok(status) => return BatchItemResult::failed(input_file, format!("Error: {}", status)),
+                    Err(e) => return BatchItemResult {
+                        transient: e.is_transient(),
+                        ..BatchItemResult::failed(input_file, format!("Error: {}", e))
+                    },
+                };
+
+                // Transparency is applied in place, so back up first
+                let backup = if config.create_backup && changes {
+                    let backup = self.gif_backup_paths.lock().unwrap().get(input_file).cloned()
+                        .unwrap_or_else(|| gif_transparency::backup_path(input_file, config.backup_dir.as_deref()));
+                    match gif_transparency::backup_gif_to(input_file, &backup) {
+                        Ok(()) => Some(backup),
+                        Err(e) => return BatchItemResult {
+                            transient: e.is_transient(),
+                            ..BatchItemResult::failed(input_file, format!("Error creating backup: {}", e))
+                        },
+                    }
+                } else {
+                    None
+                };
+
+                let result = if changes {
+                    gif_transparency::make_gif_transparent_with_options(input_file, &config.options)
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = result {
+                    return BatchItemResult {
+                        backup,
+                        transient: e.is_transient(),
+                        ..BatchItemResult::failed(input_file, format!("Error: {}", e))
+                    };
+                }
+
+                (input_file.to_path_buf(), backup)
             },
+        };
+
+        messages.extend(backup.iter().map(|path| format!("Backup saved to {}", path.display())));
+
+        // Optionally write a transparent WebP alongside the GIF
+        if let Some(ref webp) = config.webp {
+            let webp_file = webp_output_file(&target, webp);
+
+            match gif_converter::convert_gif_to_webp(
+                &target.to_string_lossy(),
+                &webp_file.to_string_lossy(),
+                webp.quality,
+                webp.lossless,
+            ) {
+                Ok(report) => messages.push(format!("WebP written to {}", report)),
+                Err(e) => {
+                    // A GIF changed in place is not run again: that would back up
+                    // the changed GIF over the original
+                    return BatchItemResult {
+                        output: Some(target),
+                        backup,
+                        transient: e.is_transient() && config.output_dir.is_some(),
+                        ..BatchItemResult::failed(input_file, format!("GIF made transparent but WebP conversion failed: {}", e))
+                    };
+                },
+            }
+        }
+
+        BatchItemResult {
+            message: (!messages.is_empty()).then(|| messages.join("; ")),
+            backup,
+            ..BatchItemResult::succeeded(input_file, Some(target))
         }
     }
 
     /// Process a file with the splitter
-    fn process_splitter(&self, input_file: &Path) -> BatchItemResult {
-        let config = match &self.splitter_config {
+    fn process_splitter(&self, input_file: &Path, config: Option<&BatchSplitterConfig>) -> BatchItemResult {
+        let config = match config {
             Some(config) => config,
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("Splitter configuration not set".to_string()),
-            },
+            None => return BatchItemResult::failed(input_file, "Splitter configuration not set"),
         };
 
-        // Create the output directory
-        if let Err(e) = std::fs::create_dir_all(&config.output_dir) {
+        let (output_dir, prefix) = self.splitter_output(config, input_file);
+
+        // Create the mirrored subdirectory; the output directory itself exists already
+        if let Err(e) = create_mirrored_dir(&output_dir, &config.output_dir) {
             return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error creating output directory: {}", e)),
+                transient: is_transient_io(&e),
+                ..BatchItemResult::failed(input_file, format!("Error creating output directory: {}", e))
+            };
+        }
+
+        if let Some(segment_seconds) = config.segment_duration {
+            return match splitter::split_by_duration(
+                &input_file.to_string_lossy(),
+                &output_dir.to_string_lossy(),
+                &prefix,
+                segment_seconds,
+                config.copy_codec,
+            ) {
+                Ok(segments) => BatchItemResult {
+                    message: Some(format!("{} segments", segments.len())),
+                    extra_outputs: segments,
+                    ..BatchItemResult::succeeded(input_file, Some(output_dir.clone()))
+                },
+                Err(e) => BatchItemResult {
+                    transient: e.is_transient(),
+                    ..BatchItemResult::failed(input_file, format!("Error: {}", e))
+                },
             };
         }
 
+        // Files are already split in parallel; don't multiply the FFmpeg processes
+        let mut options = config.options.clone();
+        if self.parallel && options.max_jobs.is_none() {
+            options.max_jobs = Some(1);
+        }
+        if let Some(format) = config.output_format {
+            options.output_format = format.extension().to_string();
+        }
+
         // Run the splitter
-        match splitter::split_video(
+        match splitter::split_video_with_options(
             &input_file.to_string_lossy(),
-            &config.output_dir.to_string_lossy(),
-            &config.prefix,
+            &output_dir.to_string_lossy(),
+            &prefix,
             config.custom_encode.as_deref(),
             config.force,
+            &options,
         ) {
-            Ok(true) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(config.output_dir.clone()),
-                success: true,
-                error_message: None,
+            Ok(report) if report.success => BatchItemResult {
+                message: report
+                    .video_encoder
+                    .filter(|_| options.encoder.hw_ok)
+                    .map(|encoder| format!("encoder: {}", encoder)),
+                warnings: report.warnings,
+                ..BatchItemResult::succeeded(input_file, Some(output_dir.clone()))
             },
-            Ok(false) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(config.output_dir.clone()),
-                success: false,
-                error_message: Some("Some slices failed to process".to_string()),
+            Ok(report) => BatchItemResult {
+                output: Some(output_dir.clone()),
+                warnings: report.warnings,
+                transient: true,
+                ..BatchItemResult::failed(input_file, "Some slices failed to process")
             },
             Err(e) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error: {}", e)),
+                transient: e.is_transient(),
+                ..BatchItemResult::failed(input_file, format!("Error: {}", e))
             },
         }
     }
 
     /// Process a file with the merger
-    fn process_merger(&self, input_file: &Path) -> BatchItemResult {
-        let config = match &self.merger_config {
+    fn process_merger(&self, input_file: &Path, config: Option<&BatchMergerConfig>) -> BatchItemResult {
+        let config = match config {
             Some(config) => config,
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("Merger configuration not set".to_string()),
-            },
+            None => return BatchItemResult::failed(input_file, "Merger configuration not set"),
         };
 
         // Create output file path
-        let file_stem = match input_file.file_stem() {
-            Some(stem) => stem.to_string_lossy(),
-            None => return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some("Invalid input filename".to_string()),
-            },
-        };
+        if input_file.file_stem().is_none() {
+            return BatchItemResult::failed(input_file, "Invalid input filename");
+        }
+
+        let mut options = config.options.clone();
+        if let Some(format) = config.output_format {
+            options.output_format = Some(format.extension().to_string());
+        }
+        let output_file = self.merger_output_file(config, input_file);
 
-        let output_file = config.output_dir.join(format!("{}_merged.mp4", file_stem));
+        let audio_file = config.audio.audio_for(input_file);
+        if matches!(config.audio, AudioPairing::MatchByName { .. }) && !audio_file.is_file() {
+            return BatchItemResult::failed(input_file, format!("Skipped: no matching audio file ({} not found)", audio_file.display()));
+        }
 
-        // Create the output directory
-        if let Err(e) = std::fs::create_dir_all(&config.output_dir) {
+        // Create the mirrored subdirectory; the output directory itself exists already
+        if let Err(e) = create_mirrored_dir(output_file.parent().unwrap_or(&config.output_dir), &config.output_dir) {
             return BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error creating output directory: {}", e)),
+                transient: is_transient_io(&e),
+                ..BatchItemResult::failed(input_file, format!("Error creating output directory: {}", e))
             };
         }
 
         // Run the merger
-        match merger::merge_audio_video(
+        match merger::merge_audio_video_with_options(
             &input_file.to_string_lossy(),
-            &config.audio_file.to_string_lossy(),
+            &audio_file.to_string_lossy(),
             &output_file.to_string_lossy(),
             config.use_shortest,
             config.copy_codec,
+            &options,
         ) {
-            Ok(()) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: Some(output_file),
-                success: true,
-                error_message: None,
+            Ok(report) => BatchItemResult {
+                message: Some(match report.video_encoder.filter(|_| options.encoder.hw_ok) {
+                    Some(encoder) => format!("audio: {}, encoder: {}", audio_file.display(), encoder),
+                    None => format!("audio: {}", audio_file.display()),
+                }),
+                ..BatchItemResult::succeeded(input_file, Some(output_file))
             },
             Err(e) => BatchItemResult {
-                input: input_file.to_path_buf(),
-                output: None,
-                success: false,
-                error_message: Some(format!("Error: {}", e)),
+                transient: e.is_transient(),
+                ..BatchItemResult::failed(input_file, format!("Error: {}", e))
             },
         }
     }
@@ -639,7 +2104,8 @@ impl BatchProcessor {
         time_ranges_str: &[String],
         output_dir: &Path,
         copy_codec: bool,
-        suffix: Option<&str>
+        suffix: Option<&str>,
+        options: ClipOptions,
     ) -> Result<Self> {
         // Parse time ranges
         let mut time_ranges = Vec::new();
@@ -660,6 +2126,8 @@ impl BatchProcessor {
             output_dir: output_dir.to_path_buf(),
             copy_codec,
             suffix: suffix.map(String::from),
+            options,
+            output_format: None,
         };
 
         Ok(Self::new(BatchOperation::Clipper).with_clipper_config(config))
@@ -672,6 +2140,7 @@ impl BatchProcessor {
         max_size_mb: f64,
         optimize: bool,
         output_dir: &Path,
+        options: GifOptions,
     ) -> Self {
         let config = BatchGifConverterConfig {
             width,
@@ -679,15 +2148,27 @@ impl BatchProcessor {
             max_size_mb,
             optimize,
             output_dir: output_dir.to_path_buf(),
+            use_gifsicle: options.gifsicle.is_some(),
+            options,
         };
 
         Self::new(BatchOperation::GifConverter).with_gif_converter_config(config)
     }
 
     /// Create a GIF transparency batch processor
-    pub fn create_gif_transparency(create_backup: bool) -> Self {
+    pub fn create_gif_transparency(
+        create_backup: bool,
+        backup_dir: Option<&Path>,
+        webp: Option<BatchWebpConfig>,
+        options: gif_transparency::TransparencyOptions,
+        output_dir: Option<&Path>,
+    ) -> Self {
         let config = BatchGifTransparencyConfig {
             create_backup,
+            backup_dir: backup_dir.map(Path::to_path_buf),
+            webp,
+            options,
+            output_dir: output_dir.map(Path::to_path_buf),
         };
 
         Self::new(BatchOperation::GifTransparency).with_gif_transparency_config(config)
@@ -699,12 +2180,19 @@ impl BatchProcessor {
         prefix: &str,
         custom_encode: Option<&str>,
         force: bool,
+        options: SplitOptions,
+        segment_duration: Option<f64>,
+        copy_codec: bool,
     ) -> Self {
         let config = BatchSplitterConfig {
             output_dir: output_dir.to_path_buf(),
             prefix: prefix.to_string(),
             custom_encode: custom_encode.map(String::from),
             force,
+            options,
+            segment_duration,
+            copy_codec,
+            output_format: None,
         };
 
         Self::new(BatchOperation::Splitter).with_splitter_config(config)
@@ -712,18 +2200,687 @@ impl BatchProcessor {
 
     /// Create a merger batch processor
     pub fn create_merger(
-        audio_file: &Path,
+        audio: AudioPairing,
         output_dir: &Path,
         use_shortest: bool,
         copy_codec: bool,
+        options: MergeOptions,
     ) -> Self {
         let config = BatchMergerConfig {
-            audio_file: audio_file.to_path_buf(),
+            audio,
             output_dir: output_dir.to_path_buf(),
             use_shortest,
             copy_codec,
+            options,
+            output_format: None,
         };
 
         Self::new(BatchOperation::Merger).with_merger_config(config)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use gif_transparency::{TransparencyMode, TransparencyOptions};
+
+    const LEGACY: TransparencyOptions = TransparencyOptions { mode: TransparencyMode::LegacyTrailer, atomic: true };
+
+    #[test]
+    fn test_parse_relative_time_filter() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+
+        assert_eq!(parse_time_filter("36h", now).unwrap(), Utc.with_ymd_and_hms(2024, 3, 9, 0, 0, 0).unwrap());
+        assert_eq!(parse_time_filter("7d", now).unwrap(), Utc.with_ymd_and_hms(2024, 3, 3, 12, 0, 0).unwrap());
+        assert_eq!(parse_time_filter("2w", now).unwrap(), Utc.with_ymd_and_hms(2024, 2, 25, 12, 0, 0).unwrap());
+        assert_eq!(parse_time_filter("30m", now).unwrap(), Utc.with_ymd_and_hms(2024, 3, 10, 11, 30, 0).unwrap());
+
+        assert!(parse_time_filter("7", now).is_err());
+        assert!(parse_time_filter("d", now).is_err());
+        assert!(parse_time_filter("7y", now).is_err());
+        assert!(parse_time_filter("-7d", now).is_err());
+        assert!(matches!(parse_time_filter("999999999999w", now), Err(BatchError::InvalidTimeFilter(_))));
+        assert!(matches!(parse_time_filter("100000000d", now), Err(BatchError::InvalidTimeFilter(_))));
+    }
+
+    #[test]
+    fn test_parse_absolute_time_filter_timezones() {
+        let now = Utc::now();
+        let expected = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+
+        assert_eq!(parse_time_filter("2024-03-10T12:00:00Z", now).unwrap(), expected);
+        assert_eq!(parse_time_filter("2024-03-10T14:00:00+02:00", now).unwrap(), expected);
+        assert_eq!(parse_time_filter("2024-03-10T07:00:00-05:00", now).unwrap(), expected);
+        assert!(parse_time_filter("2024-03-10 12:00", now).is_err());
+    }
+
+    #[test]
+    fn test_time_window_boundaries() {
+        let since = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap();
+        let second = TimeDelta::seconds(1);
+
+        // since is inclusive
+        assert_eq!(check_time_window(since, Some(since), Some(until)), None);
+        assert_eq!(
+            check_time_window(since - second, Some(since), Some(until)),
+            Some(ExclusionReason::ModifiedBeforeSince)
+        );
+
+        // until is exclusive
+        assert_eq!(check_time_window(until - second, Some(since), Some(until)), None);
+        assert_eq!(
+            check_time_window(until, Some(since), Some(until)),
+            Some(ExclusionReason::ModifiedAfterUntil)
+        );
+
+        // open-ended windows
+        assert_eq!(check_time_window(since - second, None, Some(until)), None);
+        assert_eq!(check_time_window(until, Some(since), None), None);
+    }
+
+    #[test]
+    fn test_size_filters() {
+        assert_eq!(parse_size_filter("1024").unwrap(), 1024);
+        assert_eq!(parse_size_filter("500KB").unwrap(), 500 * 1024);
+        assert_eq!(parse_size_filter("50 mb").unwrap(), 50 * 1024 * 1024);
+        assert_eq!(parse_size_filter("1.5G").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_size_filter("50 MiB").is_err());
+        assert!(parse_size_filter("MB").is_err());
+
+        // both bounds are inclusive
+        assert_eq!(check_size(100, Some(100), Some(200)), None);
+        assert_eq!(check_size(200, Some(100), Some(200)), None);
+        assert_eq!(check_size(99, Some(100), Some(200)), Some(ExclusionReason::BelowMinSize));
+        assert_eq!(check_size(201, Some(100), None), None);
+        assert_eq!(check_size(201, None, Some(200)), Some(ExclusionReason::AboveMaxSize));
+    }
+
+    fn sorted_files(count: usize) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = (0..count).map(|i| PathBuf::from(format!("clip_{:03}.mp4", i))).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_limit_takes_first_in_sort_order() {
+        let files = sorted_files(50);
+        assert_eq!(select_files(files.clone(), Selection::Limit(3)), files[..3].to_vec());
+        assert_eq!(select_files(files.clone(), Selection::Limit(100)), files);
+    }
+
+    #[test]
+    fn test_file_ordering() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_order_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, size) in [("b.mp4", 300), ("a.mp4", 100), ("c.mp4", 300), ("d.mp4", 200)] {
+            std::fs::write(dir.join(name), vec![0u8; size]).unwrap();
+        }
+        let names = |order: FileOrder, descending: bool| -> Vec<String> {
+            BatchProcessor::new(BatchOperation::Clipper)
+                .with_ordering(order, descending)
+                .with_limit(3)
+                .find_input_files(std::slice::from_ref(&dir))
+                .unwrap()
+                .iter()
+                .map(|file| file.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(names(FileOrder::Name, false), ["a.mp4", "b.mp4", "c.mp4"]);
+        assert_eq!(names(FileOrder::Name, true), ["d.mp4", "c.mp4", "b.mp4"]);
+        // Equal sizes keep their path order either way
+        assert_eq!(names(FileOrder::Size, true), ["b.mp4", "c.mp4", "d.mp4"]);
+        assert_eq!(names(FileOrder::Size, false), ["a.mp4", "d.mp4", "b.mp4"]);
+        assert_eq!("modified".parse::<FileOrder>().unwrap(), FileOrder::ModifiedTime);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sample_is_deterministic_with_seed() {
+        let files = sorted_files(50);
+        let selection = Selection::Sample { count: 10, seed: 42 };
+
+        let first = select_files(files.clone(), selection);
+        let second = select_files(files.clone(), selection);
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 10);
+
+        // Sampled files keep the order of the full list
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+
+        let other = select_files(files, Selection::Sample { count: 10, seed: 7 });
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(10), "10");
+        assert_eq!(format_count(5000), "5,000");
+        assert_eq!(format_count(1234567), "1,234,567");
+    }
+
+    /// Write a minimal GIF (header + trailer) into a fresh temporary directory
+    ///
+    /// It has no frames, so the tests use the legacy trailer mode to see a change.
+    fn temp_gif(test_name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("batch_processing_{}_{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let gif = dir.join("anim.gif");
+        std::fs::write(&gif, b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x3B").unwrap();
+        (dir, gif)
+    }
+
+    #[test]
+    fn test_transparency_backup_next_to_file() {
+        let (dir, gif) = temp_gif("backup_default");
+        let original = std::fs::read(&gif).unwrap();
+
+        let processor = BatchProcessor::create_gif_transparency(true, None, None, LEGACY, None);
+        let result = processor.process_once(&gif);
+
+        assert!(result.success);
+        let backup = result.backup.expect("backup path reported");
+        assert_eq!(backup, dir.join("anim.gif.bak"));
+        assert_eq!(std::fs::read(&backup).unwrap(), original);
+        assert_eq!(*std::fs::read(&gif).unwrap().last().unwrap(), 0x21);
+
+        // Running again leaves the GIF and the backup of the original alone
+        let rerun = processor.process_once(&gif);
+        assert!(rerun.success);
+        assert_eq!(rerun.backup, None);
+        assert_eq!(rerun.message.as_deref(), Some("already transparent"));
+        assert_eq!(std::fs::read(&backup).unwrap(), original);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transparency_webp_failure_keeps_backup() {
+        let (dir, gif) = temp_gif("webp_failure");
+        let original = std::fs::read(&gif).unwrap();
+        // The WebP directory is a file, so writing the WebP fails with an I/O error
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+
+        let webp = BatchWebpConfig { quality: 80, lossless: false, output_dir: Some(blocked.join("webp")) };
+        let processor = BatchProcessor::create_gif_transparency(true, None, Some(webp), LEGACY, None)
+            .with_retries(2, std::time::Duration::ZERO);
+        let result = processor.process_file(&gif);
+
+        assert!(!result.success);
+        assert!(!result.transient);
+        assert_eq!(result.attempts, 1);
+        assert_eq!(std::fs::read(result.backup.unwrap()).unwrap(), original);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transparency_backup_dir_and_disabled() {
+        let (dir, gif) = temp_gif("backup_dir");
+        let original = std::fs::read(&gif).unwrap();
+        let backup_dir = dir.join("backups");
+
+        let processor = BatchProcessor::create_gif_transparency(true, Some(&backup_dir), None, LEGACY, None);
+        let result = processor.process_once(&gif);
+        assert_eq!(result.backup, Some(backup_dir.join("anim.gif")));
+        assert_eq!(std::fs::read(backup_dir.join("anim.gif")).unwrap(), original);
+
+        let processor = BatchProcessor::create_gif_transparency(false, None, None, LEGACY, None);
+        let result = processor.process_once(&gif);
+        assert!(result.success);
+        assert_eq!(result.backup, None);
+        assert!(!dir.join("anim.gif.bak").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transparency_backups_of_same_name_kept_apart() {
+        let (dir, _) = temp_gif("backup_same_name");
+        let backup_dir = dir.join("backups");
+        for (sub, bytes) in [("a", &b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x3B"[..]), ("b", &b"GIF89a\x02\x00\x01\x00\x00\x00\x00\x3B"[..])] {
+            std::fs::create_dir_all(dir.join("in").join(sub)).unwrap();
+            std::fs::write(dir.join("in").join(sub).join("x.gif"), bytes).unwrap();
+        }
+
+        let processor = BatchProcessor::create_gif_transparency(true, Some(&backup_dir), None, LEGACY, None)
+            .with_recursive(true);
+        let files = processor.prepare(&[dir.join("in")]).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|file| processor.process_once(file).success));
+
+        assert_eq!(std::fs::read(backup_dir.join("a/x.gif")).unwrap()[6], 0x01);
+        assert_eq!(std::fs::read(backup_dir.join("b/x.gif")).unwrap()[6], 0x02);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transparency_output_dir() {
+        let (dir, gif) = temp_gif("output_dir");
+        let original = std::fs::read(&gif).unwrap();
+        let output_dir = dir.join("out");
+
+        let processor = BatchProcessor::create_gif_transparency(true, None, None, LEGACY, Some(&output_dir));
+        let result = processor.process_once(&gif);
+
+        assert!(result.success);
+        assert_eq!(result.output, Some(output_dir.join("anim.gif")));
+        assert_eq!(result.backup, None);
+        assert_eq!(std::fs::read(&gif).unwrap(), original);
+        assert_eq!(*std::fs::read(output_dir.join("anim.gif")).unwrap().last().unwrap(), 0x21);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_bases() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_bases_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        let files = [
+            dir.join("a/intro.mp4"),
+            dir.join("a/intro.mkv"),
+            dir.join("b/intro.mp4"),
+            PathBuf::from("elsewhere/intro.mp4"),
+        ];
+        let inputs = [dir.clone(), PathBuf::from("elsewhere/intro.mp4")];
+        let out = Path::new("out");
+
+        assert_eq!(output_bases(&inputs, &files, out, false), [
+            out.join("intro"),
+            out.join("intro_1"),
+            out.join("intro_2"),
+            out.join("intro_3"),
+        ]);
+        assert_eq!(output_bases(&inputs, &files, out, true), [
+            out.join("a/intro"),
+            out.join("a/intro_1"),
+            out.join("b/intro"),
+            out.join("intro"),
+        ]);
+        assert_eq!(with_name_suffix(&out.join("clip.v2"), ".gif"), out.join("clip.v2.gif"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_audio_pairing() {
+        let video = Path::new("clips/clip001.mp4");
+        let single = AudioPairing::Single(PathBuf::from("music.mp3"));
+        assert_eq!(single.audio_for(video), PathBuf::from("music.mp3"));
+
+        let beside = AudioPairing::MatchByName { dir: None, extension: "wav".to_string() };
+        assert_eq!(beside.audio_for(video), PathBuf::from("clips/clip001.wav"));
+        let in_dir = AudioPairing::MatchByName { dir: Some(PathBuf::from("wavs")), extension: ".wav".to_string() };
+        assert_eq!(in_dir.audio_for(video), PathBuf::from("wavs/clip001.wav"));
+
+        // A video without its mate is skipped before FFmpeg runs
+        let processor = BatchProcessor::create_merger(beside, Path::new("merged"), true, true, MergeOptions::default());
+        let result = processor.process_merger(video, processor.merger_config.as_ref());
+        assert!(!result.success);
+        assert_eq!(result.error_message.unwrap(), "Skipped: no matching audio file (clips/clip001.wav not found)");
+    }
+
+    #[test]
+    fn test_cancelled_run_starts_no_files() {
+        let cancel = CancellationToken::new();
+        let processor = BatchProcessor::new(BatchOperation::Merger).with_cancellation(cancel.clone());
+        cancel.cancel();
+
+        let result = processor.process_file(Path::new("clips/clip001.mp4"));
+        assert!(!result.success);
+        assert_eq!(result.error_message.unwrap(), "Not processed: the batch was cancelled");
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let processor = BatchProcessor::new(BatchOperation::Clipper)
+            .with_include_glob("*.mp4").unwrap()
+            .with_include_glob("*.webm").unwrap()
+            .with_exclude_glob("*_proxy*").unwrap()
+            .with_exclude_glob("**/tmp/**").unwrap();
+        let matches = |relative: &str| processor.matches_pattern(Path::new(relative), Path::new(relative));
+
+        assert!(matches("intro.mp4"));
+        assert!(matches("season1/intro.webm"));
+        // Includes replace the default extensions
+        assert!(!matches("intro.mkv"));
+        assert!(!matches("intro_proxy.mp4"));
+        assert!(!matches("tmp/intro.mp4"));
+        assert!(!matches("season1/tmp/render/intro.mp4"));
+
+        let with_pattern = BatchProcessor::new(BatchOperation::Clipper)
+            .with_pattern("^clip", PatternTarget::FileName).unwrap()
+            .with_include_glob("*.mp4").unwrap();
+        assert!(with_pattern.matches_pattern(Path::new("a/clip1.mp4"), Path::new("a/clip1.mp4")));
+        assert!(!with_pattern.matches_pattern(Path::new("a/intro.mp4"), Path::new("a/intro.mp4")));
+
+        assert!(BatchProcessor::new(BatchOperation::Clipper).with_exclude_glob("[").is_err());
+    }
+
+    #[test]
+    fn test_pattern_targets() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_pattern_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("2024-05")).unwrap();
+        std::fs::create_dir_all(dir.join("archive")).unwrap();
+        for name in ["2024-05/intro.mp4", "archive/intro.mp4", "outro.mp4"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let found = |pattern: &str, target: PatternTarget, input: &Path| {
+            BatchProcessor::new(BatchOperation::Clipper)
+                .with_recursive(true)
+                .with_pattern(pattern, target).unwrap()
+                .find_input_files(&[input.to_path_buf()])
+                .map(|files| files.len())
+                .unwrap_or(0)
+        };
+
+        assert_eq!(found(r"^2024-05/.*\.mp4$", PatternTarget::RelativePath, &dir), 1);
+        assert_eq!(found(r"^2024-05/", PatternTarget::FileName, &dir), 0);
+        assert_eq!(found(r"^[^a].*\.mp4$", PatternTarget::RelativePath, &dir), 2);
+        assert_eq!(found(r"/archive/intro\.mp4$", PatternTarget::FullPath, &dir), 1);
+        // A file given directly is matched by its name
+        let direct = dir.join("2024-05/intro.mp4");
+        assert_eq!(found(r"^intro\.mp4$", PatternTarget::RelativePath, &direct), 1);
+        assert_eq!(found(r"^2024-05/", PatternTarget::RelativePath, &direct), 0);
+
+        assert_eq!("relative".parse::<PatternTarget>().unwrap(), PatternTarget::RelativePath);
+        assert!("path".parse::<PatternTarget>().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_and_max_depth() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_symlinks_{}", std::process::id()));
+        let library = dir.join("library");
+        std::fs::create_dir_all(library.join("junk/deeper")).unwrap();
+        std::fs::create_dir_all(dir.join("seasons/s01")).unwrap();
+        for name in ["library/intro.mp4", "library/junk/a.mp4", "library/junk/deeper/b.mp4", "seasons/s01/e01.mp4"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        std::os::unix::fs::symlink(dir.join("seasons/s01"), library.join("s01")).unwrap();
+        // A link back to the library must not hang the scan
+        std::os::unix::fs::symlink(&library, library.join("junk/loop")).unwrap();
+
+        let found = |processor: BatchProcessor| {
+            let mut names: Vec<String> = processor
+                .with_recursive(true)
+                .find_input_files(std::slice::from_ref(&library))
+                .unwrap()
+                .iter()
+                .map(|file| file.strip_prefix(&library).unwrap().display().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        let processor = || BatchProcessor::new(BatchOperation::Clipper);
+        assert_eq!(found(processor()), ["intro.mp4", "junk/a.mp4", "junk/deeper/b.mp4"]);
+        assert_eq!(found(processor().with_follow_symlinks(true)), [
+            "intro.mp4", "junk/a.mp4", "junk/deeper/b.mp4", "s01/e01.mp4"
+        ]);
+        assert_eq!(found(processor().with_max_depth(2).unwrap()), ["intro.mp4", "junk/a.mp4"]);
+        assert!(processor().with_max_depth(0).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_custom_operation_runs_a_plugin() {
+        use plugin_system::example::ExamplePlugin;
+        use plugin_system::Plugin;
+
+        let dir = std::env::temp_dir().join(format!("vtk_batch_custom_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["intro.mp4", "outro.MOV", "notes.txt", "broken.mp4"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        // Wrap a plugin run as a custom batch operation
+        let plugin = ExamplePlugin::new();
+        let output_dir = dir.join("out");
+        let operation = move |input: &Path| {
+            if input.ends_with("broken.mp4") {
+                return BatchItemResult::failed(input, "unreadable input");
+            }
+            let output = output_dir.join(input.file_name().unwrap());
+            let params = HashMap::from([
+                ("input_file".to_string(), input.display().to_string()),
+                ("output_file".to_string(), output.display().to_string()),
+            ]);
+            match plugin.execute(params) {
+                Ok(()) => BatchItemResult::succeeded(input, Some(output)),
+                Err(e) => BatchItemResult::failed(input, e.to_string()),
+            }
+        };
+
+        let processor = BatchProcessor::new(BatchOperation::Custom)
+            .with_custom_operation(Box::new(operation))
+            .with_custom_extensions(&["mp4", ".mov"])
+            .with_progress_callback(|_| {});
+        assert!(processor.plan(std::slice::from_ref(&dir)).unwrap().is_empty());
+
+        let results = processor.process(std::slice::from_ref(&dir)).unwrap();
+        let outcomes: Vec<(String, bool)> = results
+            .iter()
+            .map(|r| (r.input.file_name().unwrap().to_string_lossy().into_owned(), r.success))
+            .collect();
+        assert_eq!(outcomes, [
+            ("broken.mp4".to_string(), false),
+            ("intro.mp4".to_string(), true),
+            ("outro.MOV".to_string(), true),
+        ]);
+        assert_eq!(results[1].output, Some(dir.join("out/intro.mp4")));
+        assert_eq!(results[0].attempts, 1);
+
+        let unset = BatchProcessor::new(BatchOperation::Custom).with_custom_extensions(&["mp4"]);
+        assert!(!unset.process(std::slice::from_ref(&dir)).unwrap()[0].success);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_explicit_files() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_explicit_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("more")).unwrap();
+        for name in ["intro.mp4", "notes.txt", "more/unlisted.mp4"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let list = dir.join("list.txt");
+        let listed = format!(
+            "{}\r\n\n{}\n{}\n",
+            dir.join("intro.mp4").display(),
+            dir.join("notes.txt").display(),
+            dir.join("gone.mp4").display()
+        );
+        std::fs::write(&list, listed).unwrap();
+
+        let files = read_file_list(&list).unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(read_file_list(&dir.join("missing.txt")).is_err());
+
+        // Listed files are filtered by extension, and directories are not walked
+        let processor = BatchProcessor::new(BatchOperation::Clipper).with_explicit_files(files);
+        let found = processor.find_input_files(&[]).unwrap();
+        assert_eq!(found, [dir.join("intro.mp4"), dir.join("gone.mp4")]);
+
+        let result = processor.process_file(&dir.join("gone.mp4"));
+        assert!(!result.success);
+        assert_eq!(result.error_message.as_deref(), Some("Input file not found"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plan_lists_outputs_without_writing() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_plan_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        for name in ["intro.mp4", "a/intro.mp4", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let output_dir = dir.join("clips");
+
+        let mut settings = crate::BatchSettings::new(crate::OperationSettings::Clipper {
+            ranges: vec!["00:00:01-00:00:05".to_string(), "00:01:00-00:01:30".to_string()],
+            output_dir: output_dir.clone(),
+            copy_codec: true,
+            suffix: None,
+            avoid_negative_ts: None,
+            verify_outputs: false,
+            hw_accel: None,
+            format: Some("mkv".to_string()),
+        });
+        settings.recursive = true;
+        let processor = crate::BatchController::build_processor(&settings).unwrap();
+
+        let plan = processor.plan(std::slice::from_ref(&dir)).unwrap();
+        let outputs: Vec<PathBuf> = plan.into_iter().map(|item| item.output).collect();
+        assert_eq!(outputs, vec![
+            output_dir.join("intro_00_00_01-00_00_05.mkv"),
+            output_dir.join("intro_00_01_00-00_01_30.mkv"),
+            output_dir.join("intro_1_00_00_01-00_00_05.mkv"),
+            output_dir.join("intro_1_00_01_00-00_01_30.mkv"),
+        ]);
+        assert!(!output_dir.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_dir_created_once_up_front() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_outdir_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let gif_config = |output_dir: PathBuf| BatchGifConverterConfig {
+            width: None,
+            fps: 10,
+            max_size_mb: 5.0,
+            optimize: false,
+            output_dir,
+            options: GifOptions::default(),
+            use_gifsicle: false,
+        };
+
+        let output_dir = dir.join("gifs");
+        let processor = BatchProcessor::new(BatchOperation::GifConverter)
+            .with_gif_converter_config(gif_config(output_dir.clone()));
+        processor.create_output_dir().unwrap();
+        assert!(output_dir.is_dir());
+
+        // A file in the way fails the whole batch instead of every file
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+        let processor = BatchProcessor::new(BatchOperation::GifConverter)
+            .with_gif_converter_config(gif_config(blocked.join("gifs")));
+        assert!(matches!(processor.create_output_dir(), Err(BatchError::OutputDirectory { .. })));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_retries_only_transient_failures() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_retries_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let gif = dir.join("anim.gif");
+        std::fs::write(&gif, b"GIF89a\x01\0\x01\0\0\0\0;").unwrap();
+        // The output directory is a file, so every copy fails with an I/O error
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+
+        let config = |output_dir: PathBuf| BatchGifTransparencyConfig {
+            create_backup: false,
+            backup_dir: None,
+            webp: None,
+            options: Default::default(),
+            output_dir: Some(output_dir),
+        };
+        let retrying = |config| {
+            BatchProcessor::new(BatchOperation::GifTransparency)
+                .with_gif_transparency_config(config)
+                .with_retries(2, std::time::Duration::ZERO)
+        };
+
+        let result = retrying(config(blocked.join("out"))).process_file(&gif);
+        assert!(!result.success);
+        assert!(result.transient);
+        assert_eq!(result.attempts, 3);
+        assert_eq!(result.retried_errors.len(), 2);
+
+        let missing = retrying(config(dir.join("out"))).process_file(&dir.join("missing.gif"));
+        assert!(!missing.success);
+        assert!(!missing.transient);
+        assert_eq!(missing.attempts, 1);
+
+        // Cancelling the batch ends a long backoff
+        let token = CancellationToken::new();
+        let waiting = BatchProcessor::new(BatchOperation::GifTransparency)
+            .with_gif_transparency_config(config(blocked.join("out")))
+            .with_retries(2, std::time::Duration::from_secs(3600))
+            .with_cancellation(token.clone());
+        let cancel = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            token.cancel();
+        });
+        let started = std::time::Instant::now();
+        let result = waiting.process_with_retries(&gif);
+        cancel.join().unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert!(!result.success);
+        assert_eq!(result.attempts, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_error_policy_threshold() {
+        let failed = |attempts| BatchItemResult {
+            attempts,
+            ..BatchItemResult::failed(Path::new("a.mp4"), "Error")
+        };
+
+        let processor = BatchProcessor::new(BatchOperation::Clipper).with_error_policy(ErrorPolicy::StopAfter(2));
+        processor.count_failure(&failed(1));
+        // Files skipped after a cancellation never ran
+        processor.count_failure(&failed(0));
+        assert!(!processor.aborted());
+        processor.count_failure(&failed(1));
+        assert!(processor.aborted());
+
+        let processor = BatchProcessor::new(BatchOperation::Clipper);
+        for _ in 0..10 {
+            processor.count_failure(&failed(1));
+        }
+        assert!(!processor.aborted());
+    }
+
+    #[test]
+    fn test_content_detection_matching() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_content_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let renamed_gif = dir.join("anim.dat");
+        std::fs::write(&renamed_gif, b"GIF89a\x01\0\x01\0\0\0\0;").unwrap();
+        let fake_video = dir.join("notes.mp4");
+        std::fs::write(&fake_video, b"not a video").unwrap();
+
+        let by_extension = BatchProcessor::new(BatchOperation::GifTransparency);
+        assert!(!by_extension.matches_pattern(&renamed_gif, Path::new("anim.dat")));
+        let by_content = BatchProcessor::new(BatchOperation::GifTransparency).with_content_detection(true);
+        assert!(by_content.matches_pattern(&renamed_gif, Path::new("anim.dat")));
+
+        let clipper = BatchProcessor::new(BatchOperation::Clipper);
+        assert!(clipper.matches_pattern(&fake_video, Path::new("notes.mp4")));
+        assert!(!clipper.with_content_detection(true).matches_pattern(&fake_video, Path::new("notes.mp4")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use common::RunLimits;
+use plugin_system::{ExecutionContext, ParameterInfo, ParameterType, PluginManager};
+
+use crate::{lookup_output_base, with_name_suffix, BatchError, BatchItemResult, BatchOperation, BatchProcessor, Result};
+
+/// Extensions of the files a plugin batch picks unless others are set
+pub const DEFAULT_PLUGIN_EXTENSIONS: &[&str] = &["mp4", "avi", "mov", "mkv"];
+
+/// Parameter a plugin batch passes each input file in: the first required
+/// file parameter, or the first file parameter if none is required
+pub fn plugin_input_parameter(info: &[ParameterInfo]) -> Option<&ParameterInfo> {
+    let files = || info.iter().filter(|parameter| parameter.parameter_type == ParameterType::FilePath);
+    files().find(|parameter| parameter.required).or_else(|| files().next())
+}
+
+/// Parameter a plugin batch passes each output path in: the first one named
+/// `output...` taking a string or a file path, other than the input parameter
+pub fn plugin_output_parameter(info: &[ParameterInfo]) -> Option<&ParameterInfo> {
+    let input = plugin_input_parameter(info).map(|parameter| parameter.name.as_str());
+    info.iter().find(|parameter| {
+        parameter.name.starts_with("output")
+            && Some(parameter.name.as_str()) != input
+            && matches!(parameter.parameter_type, ParameterType::String | ParameterType::FilePath)
+    })
+}
+
+/// How a plugin batch runs the plugin on one file
+struct PluginRun {
+    plugin_manager: PluginManager,
+    plugin_name: String,
+    base_params: HashMap<String, String>,
+    input_parameter: String,
+    output_parameter: Option<String>,
+    output_dir: PathBuf,
+    /// The processor's output bases, filled when a run starts
+    output_bases: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
+}
+
+impl PluginRun {
+    fn process(&self, input_file: &Path) -> BatchItemResult {
+        let mut params = self.base_params.clone();
+        params.insert(self.input_parameter.clone(), input_file.display().to_string());
+
+        // The output is named like those of other operations and keeps the input's extension
+        let output = match self.output_parameter {
+            Some(ref parameter) => {
+                let base = lookup_output_base(&self.output_bases, input_file, &self.output_dir);
+                let output = match input_file.extension() {
+                    Some(extension) => with_name_suffix(&base, &format!(".{}", extension.to_string_lossy())),
+                    None => base,
+                };
+                if fs::canonicalize(&output).ok() == fs::canonicalize(input_file).ok() {
+                    return BatchItemResult::failed(
+                        input_file,
+                        "The output would replace the input; choose another output directory",
+                    );
+                }
+                let output_dir = output.parent().unwrap_or(&self.output_dir);
+                if let Err(e) = fs::create_dir_all(output_dir) {
+                    return BatchItemResult::failed(
+                        input_file,
+                        format!("Cannot create output directory '{}': {}", output_dir.display(), e),
+                    );
+                }
+                params.insert(parameter.clone(), output.display().to_string());
+                Some(output)
+            },
+            None => None,
+        };
+
+        // The plugin stops with the batch, and within the per-file timeout
+        let limits = RunLimits::current();
+        let mut ctx = ExecutionContext::new();
+        if let Some(token) = limits.cancellation() {
+            ctx = ctx.with_cancellation(token.clone());
+        }
+        if let Some(remaining) = limits.remaining() {
+            ctx = ctx.with_timeout(remaining);
+        }
+
+        match self.plugin_manager.execute_plugin_with_context(&self.plugin_name, params, &ctx) {
+            Ok(result) => {
+                let mut files = result.files.into_iter();
+                let mut item = BatchItemResult::succeeded(input_file, output.or_else(|| files.next()));
+                item.extra_outputs = files.collect();
+                if !result.values.is_empty() {
+                    let values: Vec<String> = result.values.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+                    item.message = Some(values.join(", "));
+                }
+                item
+            },
+            Err(e) => BatchItemResult::failed(input_file, e.to_string()),
+        }
+    }
+}
+
+impl BatchProcessor {
+    /// Create a batch running a loaded plugin on every file
+    ///
+    /// Each run gets `base_params`, the file in the plugin's input parameter (see
+    /// [`plugin_input_parameter`]) and, if the plugin has an output parameter
+    /// (see [`plugin_output_parameter`]), a path in `output_dir` named after the
+    /// file and with its extension, mirrored and kept apart from others of the
+    /// same name as for other operations (see
+    /// [`output_bases`](crate::output_bases)). Values the plugin returns become the
+    /// item's message, and files it returns the item's outputs. Files with the
+    /// [`DEFAULT_PLUGIN_EXTENSIONS`] are picked; change them with
+    /// [`with_custom_extensions`](Self::with_custom_extensions).
+    pub fn create_plugin_operation(
+        plugin_manager: &PluginManager,
+        plugin_name: &str,
+        base_params: HashMap<String, String>,
+        output_dir: &Path,
+    ) -> Result<Self> {
+        let info = plugin_manager.get_plugin_parameters(plugin_name)
+            .ok_or_else(|| BatchError::InvalidOperation(format!("Plugin '{}' is not loaded", plugin_name)))?;
+        let input_parameter = plugin_input_parameter(&info).ok_or_else(|| {
+            BatchError::InvalidOperation(format!("Plugin '{}' has no file parameter to pass the inputs in", plugin_name))
+        })?;
+
+        let output_parameter = plugin_output_parameter(&info).map(|parameter| parameter.name.clone());
+        let mut processor = Self::new(BatchOperation::Custom);
+        if output_parameter.is_some() {
+            processor.custom_output_dir = Some(output_dir.to_path_buf());
+        }
+
+        let run = PluginRun {
+            plugin_manager: plugin_manager.clone(),
+            plugin_name: plugin_name.to_string(),
+            base_params,
+            input_parameter: input_parameter.name.clone(),
+            output_parameter,
+            output_dir: output_dir.to_path_buf(),
+            output_bases: Arc::clone(&processor.output_bases),
+        };
+
+        Ok(processor
+            .with_custom_operation(Box::new(move |input_file: &Path| run.process(input_file)))
+            .with_custom_extensions(DEFAULT_PLUGIN_EXTENSIONS))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{BatchController, BatchSettings, OperationSettings};
+
+    const COPY_SCRIPT: &str = r#"
+name = "copy"
+command = "sh"
+args = ["-c", 'case "$VTK_PARAM_INPUT" in *broken*) echo unreadable >&2; exit 1;; esac; cp "$VTK_PARAM_INPUT" "$VTK_PARAM_OUTPUT_FILE" && echo "vtk:value label=$VTK_PARAM_LABEL"']
+parameters_as = "environment"
+
+[[parameters]]
+name = "label"
+
+[[parameters]]
+name = "output_file"
+
+[[parameters]]
+name = "input"
+type = "file"
+required = true
+"#;
+
+    #[test]
+    fn test_plugin_batch() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_plugin_{}", std::process::id()));
+        fs::create_dir_all(dir.join("copy")).unwrap();
+        fs::write(dir.join("copy").join("plugin.toml"), COPY_SCRIPT).unwrap();
+        let inputs = dir.join("inputs");
+        fs::create_dir_all(&inputs).unwrap();
+        for name in ["intro.mp4", "broken.mp4", "notes.txt"] {
+            fs::write(inputs.join(name), name).unwrap();
+        }
+
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin(dir.join("copy")).unwrap();
+        let info = manager.get_plugin_parameters("copy").unwrap();
+        assert_eq!(plugin_input_parameter(&info).unwrap().name, "input");
+        assert_eq!(plugin_output_parameter(&info).unwrap().name, "output_file");
+
+        let settings = BatchSettings::new(OperationSettings::Plugin {
+            name: "copy".to_string(),
+            params: [("label".to_string(), "demo".to_string())].into_iter().collect(),
+            output_dir: dir.join("out"),
+            extensions: Vec::new(),
+        });
+        let processor = BatchController::build_processor_with_plugins(&settings, &manager).unwrap();
+        let results = processor.process(std::slice::from_ref(&inputs)).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(!results[0].success);
+        assert!(results[0].error_message.as_deref().unwrap().ends_with("unreadable"));
+        assert!(results[1].success);
+        assert_eq!(results[1].output, Some(dir.join("out").join("intro.mp4")));
+        assert_eq!(results[1].message.as_deref(), Some("label=demo"));
+        assert_eq!(fs::read_to_string(dir.join("out").join("intro.mp4")).unwrap(), "intro.mp4");
+
+        // Writing next to the inputs would replace them
+        let in_place = BatchProcessor::create_plugin_operation(&manager, "copy", HashMap::new(), &inputs).unwrap()
+            .with_custom_extensions(&["txt"]);
+        let results = in_place.process(std::slice::from_ref(&inputs)).unwrap();
+        assert!(results[0].error_message.as_deref().unwrap().contains("replace the input"));
+
+        let missing = BatchSettings::new(OperationSettings::Plugin {
+            name: "missing".to_string(),
+            params: Default::default(),
+            output_dir: dir.join("out"),
+            extensions: Vec::new(),
+        });
+        assert!(BatchController::build_processor_with_plugins(&missing, &manager).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_batch_output_names() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_plugin_names_{}", std::process::id()));
+        fs::create_dir_all(dir.join("copy")).unwrap();
+        fs::write(dir.join("copy").join("plugin.toml"), COPY_SCRIPT).unwrap();
+        let inputs = dir.join("inputs");
+        for sub in ["a", "b"] {
+            fs::create_dir_all(inputs.join(sub)).unwrap();
+            fs::write(inputs.join(sub).join("intro.mp4"), sub).unwrap();
+        }
+
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin(dir.join("copy")).unwrap();
+        let out = dir.join("out");
+
+        // Files of the same name get separate outputs
+        let flat = BatchProcessor::create_plugin_operation(&manager, "copy", HashMap::new(), &out).unwrap()
+            .with_recursive(true);
+        let results = flat.process(std::slice::from_ref(&inputs)).unwrap();
+        assert!(results.iter().all(|result| result.success));
+        assert_eq!(fs::read_to_string(out.join("intro.mp4")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(out.join("intro_1.mp4")).unwrap(), "b");
+
+        // Or keep their directories
+        let mirrored = BatchProcessor::create_plugin_operation(&manager, "copy", HashMap::new(), &out).unwrap()
+            .with_recursive(true)
+            .with_mirror_structure(true);
+        let results = mirrored.process(std::slice::from_ref(&inputs)).unwrap();
+        assert_eq!(results[1].output, Some(out.join("b").join("intro.mp4")));
+        assert_eq!(fs::read_to_string(out.join("b").join("intro.mp4")).unwrap(), "b");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
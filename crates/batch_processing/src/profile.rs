@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use profile_system::{Profile, ProfileType};
+
+use crate::{
+    BatchClipperConfig, BatchController, BatchError, BatchGifConverterConfig, BatchGifTransparencyConfig,
+    BatchMergerConfig, BatchProcessor, BatchSettings, BatchSplitterConfig, BatchWebpConfig, FileOrder,
+    OperationSettings, PatternTarget, Result
+};
+
+/// Custom profile type of plugin batches; their parameters are `plugin`,
+/// `output_dir`, `extensions` (comma-separated) and `param.<name>` for each
+/// parameter given to the plugin
+pub const PLUGIN_PROFILE_TYPE: &str = "plugin";
+
+/// Typed access to the string parameters of a profile
+struct ProfileParams<'a>(&'a Profile);
+
+impl ProfileParams<'_> {
+    /// Non-empty value of a parameter
+    fn string(&self, key: &str) -> Option<String> {
+        self.0
+            .get_parameter(key)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+    }
+
+    fn string_or(&self, key: &str, default: &str) -> String {
+        self.string(key).unwrap_or_else(|| default.to_string())
+    }
+
+    fn parse<T: FromStr>(&self, key: &str) -> Result<Option<T>> {
+        match self.string(key) {
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                BatchError::InvalidOperation(format!(
+                    "Invalid value '{}' for parameter '{}' in profile '{}'",
+                    value, key, self.0.name
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_or<T: FromStr>(&self, key: &str, default: T) -> Result<T> {
+        Ok(self.parse(key)?.unwrap_or(default))
+    }
+
+    fn flag(&self, key: &str) -> Result<bool> {
+        self.parse_or(key, false)
+    }
+
+    /// `alias` when only it is set, `key` otherwise
+    fn key<'k>(&self, key: &'k str, alias: &'k str) -> &'k str {
+        if self.string(key).is_none() && self.string(alias).is_some() { alias } else { key }
+    }
+
+    fn required(&self, key: &str) -> Result<String> {
+        self.string(key).ok_or_else(|| {
+            BatchError::InvalidOperation(format!("Profile '{}' is missing the '{}' parameter", self.0.name, key))
+        })
+    }
+}
+
+/// Profile parameters being written, the reverse of `ProfileParams`
+#[derive(Default)]
+struct ParamsWriter(HashMap<String, String>);
+
+impl ParamsWriter {
+    fn set(&mut self, key: &str, value: impl ToString) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+
+    /// Set `key` only when there is a value, so the default applies when reading it back
+    fn set_some(&mut self, key: &str, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            self.set(key, value);
+        }
+    }
+
+    fn set_path(&mut self, key: &str, path: Option<&Path>) {
+        self.set_some(key, path.map(Path::display));
+    }
+}
+
+impl OperationSettings {
+    /// Operation settings from a saved profile
+    ///
+    /// Parameter names follow the batch CLI flags (e.g. `output_dir`, `max_size`,
+    /// `copy_codec`); missing parameters take the CLI defaults. `time_ranges` and
+    /// `max_size_mb` are accepted for `ranges` and `max_size`.
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        let params = ProfileParams(profile);
+
+        let settings = match profile.profile_type {
+            ProfileType::Clipper => OperationSettings::Clipper {
+                ranges: params.required(params.key("ranges", "time_ranges"))?.split(',').map(|r| r.trim().to_string()).collect(),
+                output_dir: PathBuf::from(params.string_or("output_dir", "output_clips")),
+                copy_codec: params.flag("copy_codec")?,
+                suffix: params.string("suffix"),
+                avoid_negative_ts: params.string("avoid_negative_ts"),
+                verify_outputs: params.flag("verify_outputs")?,
+                hw_accel: params.string("hw_accel"),
+                format: params.string("format"),
+            },
+            ProfileType::GifConverter => OperationSettings::GifConverter {
+                width: params.parse("width")?,
+                fps: params.parse_or("fps", 10)?,
+                max_size_mb: params.parse_or(params.key("max_size", "max_size_mb"), 5.0)?,
+                optimize: params.flag("optimize")?,
+                output_dir: PathBuf::from(params.string_or("output_dir", "output_gifs")),
+                crop: params.string("crop"),
+                boomerang: params.flag("boomerang")?,
+                loop_count: params.parse("loop_count")?,
+                gifsicle: params.flag("gifsicle")?,
+                gifsicle_level: params.parse_or("gifsicle_level", 3)?,
+                gifsicle_lossy: params.parse("gifsicle_lossy")?,
+                poster: params.flag("poster")?,
+                poster_time: params.string("poster_time"),
+            },
+            ProfileType::GifTransparency => OperationSettings::GifTransparency {
+                create_backup: params.flag("backup")?,
+                backup_dir: params.string("backup_dir").map(PathBuf::from),
+                webp: if params.flag("also_webp")? {
+                    Some(BatchWebpConfig {
+                        quality: params.parse_or("webp_quality", 80)?,
+                        lossless: params.flag("webp_lossless")?,
+                        output_dir: params.string("webp_output_dir").map(PathBuf::from),
+                    })
+                } else {
+                    None
+                },
+                legacy_trailer: params.flag("legacy_trailer")?,
+                no_atomic: params.flag("no_atomic")?,
+                output_dir: params.string("output_dir").map(PathBuf::from),
+            },
+            ProfileType::Splitter => OperationSettings::Splitter {
+                output_dir: PathBuf::from(params.string_or("output_dir", "output_slices")),
+                prefix: params.string_or("prefix", "slice"),
+                custom_encode: params.string("custom_encode"),
+                force: params.flag("force")?,
+                slices: params.parse("slices")?,
+                direction: params.string("direction"),
+                expected_dimensions: params.string("expected_dimensions"),
+                format: params.string("format"),
+                segment_duration: params.parse("segment_duration")?,
+                copy_codec: params.flag("copy_codec")?,
+                jobs: params.parse("jobs")?,
+                hw_accel: params.string("hw_accel"),
+            },
+            ProfileType::Merger => OperationSettings::Merger {
+                audio_file: params.string("audio").map(PathBuf::from),
+                output_dir: PathBuf::from(params.string_or("output_dir", "output_merged")),
+                shortest: params.flag("shortest")?,
+                copy_codec: params.flag("copy_codec")?,
+                audio_ext: params.string("audio_ext"),
+                audio_dir: params.string("audio_dir").map(PathBuf::from),
+                audio_fade_in: params.parse("audio_fade_in")?,
+                audio_fade_out: params.parse("audio_fade_out")?,
+                format: params.string("format"),
+                hw_accel: params.string("hw_accel"),
+            },
+            ProfileType::Custom(ref name) if name == PLUGIN_PROFILE_TYPE => OperationSettings::Plugin {
+                name: params.required("plugin")?,
+                params: profile.parameters.iter()
+                    .filter_map(|(key, value)| Some((key.strip_prefix("param.")?.to_string(), value.clone())))
+                    .collect(),
+                output_dir: PathBuf::from(params.string_or("output_dir", "output_plugin")),
+                extensions: params.string("extensions")
+                    .map(|extensions| extensions.split(',').map(|ext| ext.trim().to_string()).collect())
+                    .unwrap_or_default(),
+            },
+            ProfileType::Custom(ref name) => {
+                return Err(BatchError::InvalidOperation(format!(
+                    "Custom profile type '{}' cannot be run as a batch",
+                    name
+                )));
+            },
+        };
+
+        Ok(settings)
+    }
+
+    /// Type of the profiles holding settings of this operation
+    pub fn profile_type(&self) -> ProfileType {
+        match self {
+            OperationSettings::Clipper { .. } => ProfileType::Clipper,
+            OperationSettings::GifConverter { .. } => ProfileType::GifConverter,
+            OperationSettings::GifTransparency { .. } => ProfileType::GifTransparency,
+            OperationSettings::Splitter { .. } => ProfileType::Splitter,
+            OperationSettings::Merger { .. } => ProfileType::Merger,
+            OperationSettings::Plugin { .. } => ProfileType::Custom(PLUGIN_PROFILE_TYPE.to_string()),
+        }
+    }
+
+    /// Profile parameters holding these settings, the reverse of `from_profile`
+    ///
+    /// Unset optional values are left out.
+    pub fn to_profile_parameters(&self) -> HashMap<String, String> {
+        let mut params = ParamsWriter::default();
+        match self {
+            OperationSettings::Clipper {
+                ranges, output_dir, copy_codec, suffix, avoid_negative_ts, verify_outputs, hw_accel, format
+            } => {
+                params.set("ranges", ranges.join(","));
+                params.set_path("output_dir", Some(output_dir));
+                params.set("copy_codec", copy_codec);
+                params.set_some("suffix", suffix.as_ref());
+                params.set_some("avoid_negative_ts", avoid_negative_ts.as_ref());
+                params.set("verify_outputs", verify_outputs);
+                params.set_some("hw_accel", hw_accel.as_ref());
+                params.set_some("format", format.as_ref());
+            },
+            OperationSettings::GifConverter {
+                width, fps, max_size_mb, optimize, output_dir, crop, boomerang, loop_count,
+                gifsicle, gifsicle_level, gifsicle_lossy, poster, poster_time
+            } => {
+                params.set_some("width", width.as_ref());
+                params.set("fps", fps);
+                params.set("max_size", max_size_mb);
+                params.set("optimize", optimize);
+                params.set_path("output_dir", Some(output_dir));
+                params.set_some("crop", crop.as_ref());
+                params.set("boomerang", boomerang);
+                params.set_some("loop_count", loop_count.as_ref());
+                params.set("gifsicle", gifsicle);
+                params.set("gifsicle_level", gifsicle_level);
+                params.set_some("gifsicle_lossy", gifsicle_lossy.as_ref());
+                params.set("poster", poster);
+                params.set_some("poster_time", poster_time.as_ref());
+            },
+            OperationSettings::GifTransparency { create_backup, backup_dir, webp, legacy_trailer, no_atomic, output_dir } => {
+                params.set("backup", create_backup);
+                params.set_path("backup_dir", backup_dir.as_deref());
+                params.set("also_webp", webp.is_some());
+                if let Some(webp) = webp {
+                    params.set("webp_quality", webp.quality);
+                    params.set("webp_lossless", webp.lossless);
+                    params.set_path("webp_output_dir", webp.output_dir.as_deref());
+                }
+                params.set("legacy_trailer", legacy_trailer);
+                params.set("no_atomic", no_atomic);
+                params.set_path("output_dir", output_dir.as_deref());
+            },
+            OperationSettings::Splitter {
+                output_dir, prefix, custom_encode, force, slices, direction, expected_dimensions, format,
+                segment_duration, copy_codec, jobs, hw_accel
+            } => {
+                params.set_path("output_dir", Some(output_dir));
+                params.set("prefix", prefix);
+                params.set_some("custom_encode", custom_encode.as_ref());
+                params.set("force", force);
+                params.set_some("slices", slices.as_ref());
+                params.set_some("direction", direction.as_ref());
+                params.set_some("expected_dimensions", expected_dimensions.as_ref());
+                params.set_some("format", format.as_ref());
+                params.set_some("segment_duration", segment_duration.as_ref());
+                params.set("copy_codec", copy_codec);
+                params.set_some("jobs", jobs.as_ref());
+                params.set_some("hw_accel", hw_accel.as_ref());
+            },
+            OperationSettings::Merger {
+                audio_file, output_dir, shortest, copy_codec, audio_ext, audio_dir, audio_fade_in, audio_fade_out,
+                format, hw_accel
+            } => {
+                params.set_path("audio", audio_file.as_deref());
+                params.set_path("output_dir", Some(output_dir));
+                params.set("shortest", shortest);
+                params.set("copy_codec", copy_codec);
+                params.set_some("audio_ext", audio_ext.as_ref());
+                params.set_path("audio_dir", audio_dir.as_deref());
+                params.set_some("audio_fade_in", audio_fade_in.as_ref());
+                params.set_some("audio_fade_out", audio_fade_out.as_ref());
+                params.set_some("format", format.as_ref());
+                params.set_some("hw_accel", hw_accel.as_ref());
+            },
+            OperationSettings::Plugin { name, params: plugin_params, output_dir, extensions } => {
+                params.set("plugin", name);
+                for (key, value) in plugin_params {
+                    params.set(&format!("param.{}", key), value);
+                }
+                params.set_path("output_dir", Some(output_dir));
+                if !extensions.is_empty() {
+                    params.set("extensions", extensions.join(","));
+                }
+            },
+        }
+        params.0
+    }
+}
+
+impl BatchSettings {
+    /// Batch settings from a saved profile
+    ///
+    /// Besides the operation parameters, a profile may set `recursive`,
+    /// `follow_symlinks`, `max_depth`, `parallel`, `pattern`, `pattern_target`,
+    /// `by_content`, `order`, `descending`, `mirror_structure`, `per_file_timeout`
+    /// (seconds), `retries`, `retry_backoff` (seconds), `fail_fast` and `max_failures`.
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        let params = ProfileParams(profile);
+
+        let mut settings = BatchSettings::new(OperationSettings::from_profile(profile)?);
+        settings.recursive = params.flag("recursive")?;
+        settings.follow_symlinks = params.flag("follow_symlinks")?;
+        settings.max_depth = params.parse("max_depth")?;
+        settings.parallel = params.parse_or("parallel", true)?;
+        settings.pattern = params.string("pattern");
+        settings.pattern_target = params.parse_or("pattern_target", PatternTarget::FileName)?;
+        settings.by_content = params.flag("by_content")?;
+        settings.order = params.parse_or("order", FileOrder::Name)?;
+        settings.descending = params.flag("descending")?;
+        settings.per_file_timeout = params.parse("per_file_timeout")?;
+        settings.retries = params.parse_or("retries", 0)?;
+        settings.retry_backoff = params.parse("retry_backoff")?;
+        settings.fail_fast = params.flag("fail_fast")?;
+        settings.max_failures = params.parse("max_failures")?;
+        settings.mirror_structure = params.flag("mirror_structure")?;
+
+        Ok(settings)
+    }
+
+    /// A profile named `name` holding these settings, for "save current settings as profile"
+    ///
+    /// Only the settings `from_profile` reads are kept: inputs, filters and the
+    /// other options of a single run are left out.
+    pub fn to_profile(&self, name: &str) -> Profile {
+        let mut params = ParamsWriter(self.operation.to_profile_parameters());
+        params.set("recursive", self.recursive);
+        params.set("follow_symlinks", self.follow_symlinks);
+        params.set_some("max_depth", self.max_depth);
+        params.set("parallel", self.parallel);
+        params.set_some("pattern", self.pattern.as_ref());
+        params.set("pattern_target", self.pattern_target);
+        params.set("by_content", self.by_content);
+        params.set("order", self.order);
+        params.set("descending", self.descending);
+        params.set_some("per_file_timeout", self.per_file_timeout);
+        params.set("retries", self.retries);
+        params.set_some("retry_backoff", self.retry_backoff);
+        params.set("fail_fast", self.fail_fast);
+        params.set_some("max_failures", self.max_failures);
+        params.set("mirror_structure", self.mirror_structure);
+
+        Profile::new(name, self.operation.profile_type(), params.0)
+    }
+}
+
+impl BatchProcessor {
+    /// Create a batch processor configured from a saved profile
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        BatchController::build_processor(&BatchSettings::from_profile(profile)?)
+    }
+}
+
+/// Operation configuration of a profile of the `expected` type, checked as for a batch run
+fn operation_config<T>(
+    profile: &Profile,
+    expected: ProfileType,
+    config: impl FnOnce(BatchProcessor) -> Option<T>,
+) -> Result<T> {
+    if profile.profile_type != expected {
+        return Err(BatchError::InvalidOperation(format!(
+            "Profile '{}' is a {} profile, not a {} one",
+            profile.name, profile.profile_type, expected
+        )));
+    }
+
+    let processor = BatchController::build_operation(&OperationSettings::from_profile(profile)?, None)?;
+    config(processor).ok_or_else(|| BatchError::Other(format!("Profile '{}' gave no {} configuration", profile.name, expected)))
+}
+
+/// Parameters as for `OperationSettings::from_profile`
+impl TryFrom<&Profile> for BatchClipperConfig {
+    type Error = BatchError;
+
+    fn try_from(profile: &Profile) -> Result<Self> {
+        operation_config(profile, ProfileType::Clipper, |processor| processor.clipper_config)
+    }
+}
+
+/// Parameters as for `OperationSettings::from_profile`
+impl TryFrom<&Profile> for BatchGifConverterConfig {
+    type Error = BatchError;
+
+    fn try_from(profile: &Profile) -> Result<Self> {
+        operation_config(profile, ProfileType::GifConverter, |processor| processor.gif_converter_config)
+    }
+}
+
+/// Parameters as for `OperationSettings::from_profile`
+impl TryFrom<&Profile> for BatchGifTransparencyConfig {
+    type Error = BatchError;
+
+    fn try_from(profile: &Profile) -> Result<Self> {
+        operation_config(profile, ProfileType::GifTransparency, |processor| processor.gif_transparency_config)
+    }
+}
+
+/// Parameters as for `OperationSettings::from_profile`
+impl TryFrom<&Profile> for BatchSplitterConfig {
+    type Error = BatchError;
+
+    fn try_from(profile: &Profile) -> Result<Self> {
+        operation_config(profile, ProfileType::Splitter, |processor| processor.splitter_config)
+    }
+}
+
+/// Parameters as for `OperationSettings::from_profile`
+impl TryFrom<&Profile> for BatchMergerConfig {
+    type Error = BatchError;
+
+    fn try_from(profile: &Profile) -> Result<Self> {
+        operation_config(profile, ProfileType::Merger, |processor| processor.merger_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use gif_converter::GifsicleSettings;
+
+    use super::*;
+    use crate::BatchOperation;
+
+    fn profile(profile_type: ProfileType, params: &[(&str, &str)]) -> Profile {
+        let parameters: HashMap<String, String> = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Profile::new("test", profile_type, parameters)
+    }
+
+    fn profile_with(profile_type: ProfileType, key: &str, value: &str) -> Profile {
+        profile(profile_type, &[(key, value)])
+    }
+
+    #[test]
+    fn test_gif_converter_profile_to_processor() {
+        let profile = profile(ProfileType::GifConverter, &[
+            ("width", "480"),
+            ("fps", "15"),
+            ("max_size_mb", "2.5"),
+            ("boomerang", "true"),
+            ("gifsicle", "true"),
+            ("gifsicle_lossy", "60"),
+            ("recursive", "true"),
+        ]);
+
+        let settings = BatchSettings::from_profile(&profile).unwrap();
+        assert!(settings.recursive);
+        assert!(matches!(
+            settings.operation,
+            OperationSettings::GifConverter { width: Some(480), fps: 15, boomerang: true, .. }
+        ));
+
+        let processor = BatchProcessor::from_profile(&profile).unwrap();
+        let config = processor.gif_converter_config.as_ref().unwrap();
+        assert_eq!(processor.operation, BatchOperation::GifConverter);
+        assert_eq!(config.fps, 15);
+        assert_eq!(config.max_size_mb, 2.5);
+        assert_eq!(config.output_dir, PathBuf::from("output_gifs"));
+        assert_eq!(config.options.gifsicle, Some(GifsicleSettings { level: 3, lossy: Some(60) }));
+
+        let invalid = profile_with(ProfileType::GifConverter, "fps", "fast");
+        assert!(BatchProcessor::from_profile(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_clipper_profile_to_processor() {
+        let profile = profile(ProfileType::Clipper, &[
+            ("ranges", "00:00:01-00:00:05, 00:01:00-00:01:30"),
+            ("copy_codec", "true"),
+            ("verify_outputs", "true"),
+        ]);
+
+        let processor = BatchProcessor::from_profile(&profile).unwrap();
+        let config = processor.clipper_config.as_ref().unwrap();
+        assert_eq!(processor.operation, BatchOperation::Clipper);
+        assert_eq!(config.time_ranges.len(), 2);
+        assert_eq!(config.output_dir, PathBuf::from("output_clips"));
+        assert!(config.copy_codec);
+        assert!(config.options.verify_outputs);
+
+        let time_ranges = profile_with(ProfileType::Clipper, "time_ranges", "00:00:01-00:00:05");
+        assert_eq!(BatchProcessor::from_profile(&time_ranges).unwrap().clipper_config.unwrap().time_ranges.len(), 1);
+
+        let missing_ranges = profile_with(ProfileType::Clipper, "output_dir", "clips");
+        assert!(BatchProcessor::from_profile(&missing_ranges).is_err());
+
+        let custom = profile_with(ProfileType::Custom("watermark".to_string()), "text", "hi");
+        assert!(BatchProcessor::from_profile(&custom).is_err());
+    }
+
+    #[test]
+    fn test_settings_to_profile_round_trip() {
+        let mut settings = BatchSettings::new(OperationSettings::GifTransparency {
+            create_backup: true,
+            backup_dir: Some(PathBuf::from("backups")),
+            webp: Some(BatchWebpConfig { quality: 70, lossless: false, output_dir: None }),
+            legacy_trailer: false,
+            no_atomic: true,
+            output_dir: None,
+        });
+        settings.recursive = true;
+        settings.max_depth = Some(2);
+        settings.order = FileOrder::Size;
+        settings.retries = 3;
+
+        let profile = settings.to_profile("cleanup");
+        assert_eq!(profile.profile_type, ProfileType::GifTransparency);
+        assert!(profile_system::ProfileManager::validate(&profile).is_ok());
+        let loaded = BatchSettings::from_profile(&profile).unwrap();
+        assert_eq!(loaded.operation, settings.operation);
+        assert_eq!((loaded.recursive, loaded.max_depth, loaded.order, loaded.retries), (true, Some(2), FileOrder::Size, 3));
+
+        let config = BatchGifTransparencyConfig::try_from(&profile).unwrap();
+        assert!(config.create_backup);
+        assert_eq!(config.webp.unwrap().quality, 70);
+
+        let error = BatchClipperConfig::try_from(&profile).unwrap_err();
+        assert!(error.to_string().contains("is a GIF Transparency profile, not a Clipper one"), "{}", error);
+    }
+}
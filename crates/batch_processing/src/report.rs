@@ -0,0 +1,268 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::{BatchError, BatchItemResult, Result};
+
+/// File format of an exported batch report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+impl ReportFormat {
+    /// Format named by the extension of `path` (`.json` or `.csv`)
+    pub fn from_path(path: &Path) -> Result<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| BatchError::InvalidOperation(format!(
+                "Cannot tell the report format of '{}': use a .json or .csv file",
+                path.display()
+            )))
+    }
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportFormat::Json => write!(f, "json"),
+            ReportFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = BatchError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            other => Err(BatchError::InvalidOperation(format!("Unknown report format '{}'", other))),
+        }
+    }
+}
+
+/// Number of files listed in [`BatchSummary::slowest`]
+pub const SLOWEST_SHOWN: usize = 5;
+
+/// Counts, timing and throughput of a batch run
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchSummary {
+    /// Files selected for the run, skipped ones included
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Files that never ran: done in an earlier run, cancelled or not started after an early stop
+    pub skipped: usize,
+    pub wall_time: Duration,
+    pub total_output_bytes: u64,
+    /// The slowest files, slowest first
+    pub slowest: Vec<(PathBuf, Duration)>,
+}
+
+impl BatchSummary {
+    /// Summarise the results of a run that took `wall_time`, besides `skipped` files without a result
+    pub fn from_results(results: &[BatchItemResult], skipped: usize, wall_time: Duration) -> Self {
+        let ran = results.iter().filter(|r| r.attempts > 0);
+        let succeeded = ran.clone().filter(|r| r.success).count();
+        let failed = ran.clone().filter(|r| !r.success).count();
+
+        let mut slowest: Vec<(PathBuf, Duration)> = ran.map(|r| (r.input.clone(), r.duration)).collect();
+        slowest.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        slowest.truncate(SLOWEST_SHOWN);
+
+        BatchSummary {
+            total: results.len() + skipped,
+            succeeded,
+            failed,
+            skipped: skipped + results.iter().filter(|r| r.attempts == 0).count(),
+            wall_time,
+            total_output_bytes: results.iter().filter_map(|r| r.output_size_bytes).sum(),
+            slowest,
+        }
+    }
+}
+
+impl std::fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} file(s) in {}: {} succeeded, {} failed, {} skipped; {} written",
+            self.total,
+            format_elapsed(self.wall_time),
+            self.succeeded,
+            self.failed,
+            self.skipped,
+            format_bytes(self.total_output_bytes)
+        )?;
+
+        let seconds = self.wall_time.as_secs_f64();
+        if seconds > 0.0 {
+            let processed = (self.succeeded + self.failed) as f64;
+            write!(
+                f,
+                " ({:.1} files/min, {}/s)",
+                processed * 60.0 / seconds,
+                format_bytes((self.total_output_bytes as f64 / seconds) as u64)
+            )?;
+        }
+
+        if !self.slowest.is_empty() {
+            let slowest: Vec<String> = self.slowest
+                .iter()
+                .map(|(path, duration)| format!("{} ({})", path.display(), format_elapsed(*duration)))
+                .collect();
+            write!(f, "\nSlowest: {}", slowest.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `1h 02m 03s`, `2m 03s` or `3.4s`
+fn format_elapsed(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        3600.. => format!("{}h {:02}m {:02}s", seconds / 3600, seconds % 3600 / 60, seconds % 60),
+        60.. => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{:.1}s", duration.as_secs_f64()),
+    }
+}
+
+/// Size with a binary unit, e.g. `1.5 GB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// One line of a report
+#[derive(Serialize)]
+struct ReportRow {
+    input: String,
+    output: Option<String>,
+    status: &'static str,
+    error: Option<String>,
+    duration_secs: f64,
+    output_size_bytes: Option<u64>,
+}
+
+impl From<&BatchItemResult> for ReportRow {
+    fn from(result: &BatchItemResult) -> Self {
+        ReportRow {
+            input: result.input.display().to_string(),
+            output: result.output.as_ref().map(|output| output.display().to_string()),
+            status: if result.success { "success" } else { "failed" },
+            error: result.error_message.clone(),
+            duration_secs: result.duration.as_secs_f64(),
+            output_size_bytes: result.output_size_bytes,
+        }
+    }
+}
+
+/// Write the results of a batch run to `path` as JSON or CSV
+///
+/// Each file becomes one record with its input, output, status (`success` or
+/// `failed`), error message, duration in seconds and output size in bytes.
+pub fn write_report(results: &[BatchItemResult], format: ReportFormat, path: &Path) -> Result<()> {
+    let rows: Vec<ReportRow> = results.iter().map(ReportRow::from).collect();
+    let contents = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&rows)
+            .map_err(|e| BatchError::Other(format!("Failed to serialize the report: {}", e)))?,
+        ReportFormat::Csv => to_csv(&rows),
+    };
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+fn to_csv(rows: &[ReportRow]) -> String {
+    let mut csv = String::from("input,output,status,error,duration_secs,output_size_bytes\n");
+    for row in rows {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{:.3},{}",
+            csv_field(&row.input),
+            csv_field(row.output.as_deref().unwrap_or_default()),
+            row.status,
+            csv_field(row.error.as_deref().unwrap_or_default()),
+            row.duration_secs,
+            row.output_size_bytes.map(|size| size.to_string()).unwrap_or_default()
+        );
+    }
+    csv
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_counts_and_slowest() {
+        let result = |name: &str, success, attempts, seconds| BatchItemResult {
+            success,
+            attempts,
+            duration: Duration::from_secs(seconds),
+            output_size_bytes: success.then_some(3 * 1024 * 1024),
+            ..BatchItemResult::succeeded(Path::new(name), None)
+        };
+        let results: Vec<BatchItemResult> = (0..7)
+            .map(|i| result(&format!("{}.mp4", i), i != 3, 1, i))
+            .chain([result("cancelled.mp4", false, 0, 0)])
+            .collect();
+
+        let summary = BatchSummary::from_results(&results, 2, Duration::from_secs(90));
+        assert_eq!((summary.total, summary.succeeded, summary.failed, summary.skipped), (10, 6, 1, 3));
+        assert_eq!(summary.total_output_bytes, 18 * 1024 * 1024);
+        let slowest: Vec<&str> = summary.slowest.iter().map(|(path, _)| path.to_str().unwrap()).collect();
+        assert_eq!(slowest, ["6.mp4", "5.mp4", "4.mp4", "3.mp4", "2.mp4"]);
+        assert!(summary.to_string().starts_with("10 file(s) in 1m 30s: 6 succeeded, 1 failed, 3 skipped; 18.0 MB written"));
+    }
+
+    #[test]
+    fn test_csv_report_quotes_fields() {
+        let result = BatchItemResult {
+            transient: true,
+            duration: Duration::from_millis(1500),
+            ..BatchItemResult::failed(Path::new("clips/a,b.mp4"), "FFmpeg command failed:\n\"moov atom not found\"")
+        };
+
+        let csv = to_csv(&[ReportRow::from(&result)]);
+        assert_eq!(
+            csv,
+            "input,output,status,error,duration_secs,output_size_bytes\n\
+             \"clips/a,b.mp4\",,failed,\"FFmpeg command failed:\n\"\"moov atom not found\"\"\",1.500,\n"
+        );
+
+        assert_eq!(ReportFormat::from_path(Path::new("out/report.JSON")).unwrap(), ReportFormat::Json);
+        assert!(ReportFormat::from_path(Path::new("report.txt")).is_err());
+    }
+}
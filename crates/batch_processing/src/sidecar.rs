@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::{BatchController, BatchError, BatchProcessor, OperationSettings, Result};
+
+/// Appended to an input's file name to find its sidecar, e.g. `intro.mp4.vtk.json`
+pub const SIDECAR_SUFFIX: &str = ".vtk.json";
+
+/// Settings a sidecar may not change: the outputs of the whole batch are laid out with them
+const BATCH_WIDE_KEYS: [&str; 2] = ["type", "output_dir"];
+
+/// Path of the sidecar file of `input`, whether or not it exists
+pub fn sidecar_path(input: &Path) -> PathBuf {
+    let mut path = input.as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+impl OperationSettings {
+    /// These settings with the values of `overrides` in place of their own
+    ///
+    /// Keys are the field names of the operation's settings, e.g. `ranges` or
+    /// `width`. Keys of another operation, `output_dir` and values of the wrong
+    /// type are rejected.
+    pub fn with_overrides(&self, overrides: &Map<String, Value>) -> Result<Self> {
+        let mut fields = match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields,
+            _ => return Err(BatchError::Other("Failed to serialize the operation settings".to_string())),
+        };
+
+        for (key, value) in overrides {
+            if BATCH_WIDE_KEYS.contains(&key.as_str()) {
+                return Err(BatchError::InvalidOperation(format!("'{}' cannot be changed for a single file", key)));
+            }
+            match fields.get_mut(key) {
+                Some(field) => *field = value.clone(),
+                None => return Err(BatchError::InvalidOperation(format!(
+                    "Unknown setting '{}' for the {}",
+                    key,
+                    self.operation()
+                ))),
+            }
+        }
+
+        serde_json::from_value(Value::Object(fields))
+            .map_err(|e| BatchError::InvalidOperation(format!("Invalid override: {}", e)))
+    }
+}
+
+impl BatchProcessor {
+    /// Processor holding the operation configuration of `input` with the
+    /// overrides of its sidecar, and the sidecar's path; `None` without a sidecar
+    pub(crate) fn sidecar_config(&self, input: &Path) -> Result<Option<(PathBuf, BatchProcessor)>> {
+        let path = sidecar_path(input);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let invalid = |message: String| BatchError::InvalidOperation(format!("Sidecar {}: {}", path.display(), message));
+        let settings = self.operation_settings.as_ref().ok_or_else(|| {
+            invalid("overrides need a batch configured from settings".to_string())
+        })?;
+        let overrides: Map<String, Value> = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| invalid(format!("not a JSON object ({})", e)))?;
+
+        let settings = settings.with_overrides(&overrides).map_err(|e| invalid(e.to_string()))?;
+        let processor = BatchController::build_operation(&settings, None).map_err(|e| invalid(e.to_string()))?;
+        Ok(Some((path, processor)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BatchSettings;
+
+    #[test]
+    fn test_sidecar_overrides_one_file() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_sidecar_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (plain, tuned) = (dir.join("plain.gif"), dir.join("tuned.gif"));
+        for gif in [&plain, &tuned] {
+            std::fs::write(gif, b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x3B").unwrap();
+        }
+        std::fs::write(sidecar_path(&tuned), r#"{"create_backup": true, "legacy_trailer": true}"#).unwrap();
+        assert_eq!(sidecar_path(&tuned), dir.join("tuned.gif.vtk.json"));
+
+        let settings = BatchSettings::new(OperationSettings::GifTransparency {
+            create_backup: false,
+            backup_dir: None,
+            webp: None,
+            legacy_trailer: true,
+            no_atomic: false,
+            output_dir: None,
+        });
+        let processor = BatchController::build_processor(&settings).unwrap();
+
+        let result = processor.process_once(&plain);
+        assert!(result.success);
+        assert_eq!((result.backup, result.sidecar), (None, None));
+        let result = processor.process_once(&tuned);
+        assert!(result.success);
+        assert_eq!(result.backup, Some(dir.join("tuned.gif.bak")));
+        assert_eq!(result.sidecar, Some(dir.join("tuned.gif.vtk.json")));
+
+        // Bad sidecars fail their file only
+        for (contents, error) in [
+            (r#"{"width": 320}"#, "Unknown setting 'width' for the GIF Transparency"),
+            (r#"{"create_backup": "yes"}"#, "Invalid override: invalid type"),
+            (r#"{"output_dir": "elsewhere"}"#, "'output_dir' cannot be changed for a single file"),
+            ("[1, 2]", "not a JSON object"),
+        ] {
+            std::fs::write(sidecar_path(&tuned), contents).unwrap();
+            let result = processor.process_once(&tuned);
+            assert!(!result.success);
+            assert!(result.error_message.unwrap().contains(error), "{}", contents);
+        }
+
+        // A processor built without settings has nothing to apply the overrides to
+        let unconfigured = BatchProcessor::create_gif_transparency(false, None, None, Default::default(), None);
+        assert!(!unconfigured.process_once(&tuned).success);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rayon::prelude::*;
+
+use crate::{BatchError, BatchItemResult, BatchProcessor, Result};
+
+/// Time between two scans of the watched inputs when none is given
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A file as processed: the same path modified later counts as a new file
+type SeenKey = (PathBuf, Option<SystemTime>);
+
+fn seen_key(file: &Path) -> Option<SeenKey> {
+    let modified = fs::metadata(file).ok()?.modified().ok();
+    Some((fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf()), modified))
+}
+
+impl BatchProcessor {
+    /// Process the matching files of `inputs`, then keep processing new ones as they appear
+    ///
+    /// The inputs are scanned every `poll_interval` with the usual filters. A file
+    /// is processed once its size is the same in two scans in a row, so files still
+    /// being written or copied are left alone; files present at the start wait for
+    /// one scan too. A file is processed again only after it has been modified.
+    ///
+    /// Each result is passed to `on_result` as soon as its scan's files are done.
+    /// Watching stops once the cancellation token is cancelled, or never without
+    /// one; the number of files processed is returned.
+    pub fn watch<F>(&self, inputs: &[PathBuf], poll_interval: Duration, mut on_result: F) -> Result<usize>
+    where
+        F: FnMut(&BatchItemResult),
+    {
+        self.check_ffmpeg()?;
+        self.create_output_dir()?;
+
+        let mut seen: HashSet<SeenKey> = HashSet::new();
+        // Size of each unprocessed file at the previous scan
+        let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+        let mut processed = 0;
+
+        while !self.cancelled() {
+            let files = match self.prepare(inputs) {
+                Ok(files) => files,
+                Err(BatchError::NoInputFiles) => Vec::new(),
+                Err(e) => return Err(e),
+            };
+
+            let mut ready = Vec::new();
+            let mut current_sizes = HashMap::new();
+            for file in files {
+                let (Some(key), Ok(metadata)) = (seen_key(&file), fs::metadata(&file)) else {
+                    continue;
+                };
+                if seen.contains(&key) {
+                    continue;
+                }
+                if sizes.get(&file) == Some(&metadata.len()) {
+                    ready.push((file, key));
+                } else {
+                    current_sizes.insert(file, metadata.len());
+                }
+            }
+            sizes = current_sizes;
+
+            let results: Vec<BatchItemResult> = if self.parallel {
+                ready.par_iter().map(|(file, _)| self.process_file(file)).collect()
+            } else {
+                ready.iter().map(|(file, _)| self.process_file(file)).collect()
+            };
+            for ((file, key), result) in ready.into_iter().zip(&results) {
+                // Files skipped by a cancellation are left for the next watch
+                if result.attempts == 0 {
+                    continue;
+                }
+                // An operation that rewrites its input must not trigger itself again
+                seen.extend(seen_key(&file));
+                seen.insert(key);
+                on_result(result);
+                processed += 1;
+            }
+
+            self.sleep_unless_cancelled(poll_interval);
+        }
+
+        Ok(processed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use common::CancellationToken;
+
+    use super::*;
+    use crate::BatchOperation;
+
+    #[test]
+    fn test_watch_waits_for_stable_files_and_skips_seen_ones() {
+        let dir = std::env::temp_dir().join(format!("vtk_batch_watch_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("existing.mp4"), b"done").unwrap();
+
+        // Record the size each file had when it was processed
+        let operation = |input: &Path| BatchItemResult {
+            message: Some(fs::metadata(input).unwrap().len().to_string()),
+            ..BatchItemResult::succeeded(input, None)
+        };
+        let token = CancellationToken::new();
+        let processor = BatchProcessor::new(BatchOperation::Custom)
+            .with_custom_operation(Box::new(operation))
+            .with_custom_extensions(&["mp4"])
+            .with_cancellation(token.clone());
+
+        // A recording that keeps growing for a while after it appears
+        let (recording, stop) = (dir.join("recording.mp4"), token.clone());
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            let mut file = fs::File::create(&recording).unwrap();
+            for _ in 0..8 {
+                file.write_all(&[0; 100]).unwrap();
+                file.flush().unwrap();
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            std::thread::sleep(Duration::from_millis(1000));
+            stop.cancel();
+        });
+
+        let mut processed = Vec::new();
+        let count = processor
+            .watch(std::slice::from_ref(&dir), Duration::from_millis(200), |result| {
+                let name = result.input.file_name().unwrap().to_string_lossy().into_owned();
+                processed.push((name, result.message.clone().unwrap()));
+            })
+            .unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(processed, [
+            ("existing.mp4".to_string(), "4".to_string()),
+            ("recording.mp4".to_string(), "800".to_string()),
+        ]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
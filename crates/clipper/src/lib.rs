@@ -1,11 +1,149 @@
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::str::FromStr;
 
 use common::{
-    execute_ffmpeg, verify_input_file, validate_time_range,
-    Result, VideoToolkitError
+    cancellation_scope, get_codec_options, get_start_time, get_supported_formats, is_dry_run,
+    is_format_supported_for_operation, output_written, select_hw_encoder, verify_input_file, validate_time_range,
+    video_encode_args, CancellationToken, EncoderPreference, FfmpegCommand, HwEncoder, Result, VideoToolkitError, Warning, WarningCode,
+    SOFTWARE_H264_ENCODER
 };
 
+/// Largest start time (in seconds, either sign) accepted by output verification
+const START_TIME_EPSILON: f64 = 0.05;
+
+/// How FFmpeg's `-avoid_negative_ts` is set for clips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AvoidNegativeTs {
+    /// `make_zero` when copying codecs, FFmpeg's default when re-encoding
+    #[default]
+    Auto,
+    /// Always shift timestamps so the clip starts at zero
+    MakeZero,
+    /// Keep the original timestamps (`-avoid_negative_ts disabled`)
+    Disabled,
+}
+
+impl AvoidNegativeTs {
+    /// The `-avoid_negative_ts` value to pass, if any
+    fn ffmpeg_value(&self, copy_codec: bool) -> Option<&'static str> {
+        match self {
+            AvoidNegativeTs::Auto if copy_codec => Some("make_zero"),
+            AvoidNegativeTs::Auto => None,
+            AvoidNegativeTs::MakeZero => Some("make_zero"),
+            AvoidNegativeTs::Disabled => Some("disabled"),
+        }
+    }
+}
+
+impl FromStr for AvoidNegativeTs {
+    type Err = VideoToolkitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Ok(AvoidNegativeTs::Auto),
+            "make_zero" => Ok(AvoidNegativeTs::MakeZero),
+            "disabled" => Ok(AvoidNegativeTs::Disabled),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid avoid-negative-ts mode '{}': expected auto, make_zero or disabled",
+                value
+            ))),
+        }
+    }
+}
+
+/// Additional options for the clipper
+#[derive(Debug, Clone, Default)]
+pub struct ClipOptions {
+    pub avoid_negative_ts: AvoidNegativeTs,
+    /// Probe each clip after writing it and fail clips that do not start at zero
+    pub verify_outputs: bool,
+    /// Stops the remaining clips and kills the running FFmpeg when cancelled
+    pub cancel: Option<CancellationToken>,
+    /// Whether re-encoded clips may use a hardware video encoder
+    pub encoder: EncoderPreference,
+    /// Container format of the clips (default: mp4)
+    pub output_format: Option<String>,
+    /// Name the clips after this instead of the input's file stem
+    pub output_name: Option<String>,
+}
+
+/// Container format of clips when none is set
+pub const DEFAULT_OUTPUT_FORMAT: &str = "mp4";
+
+/// Normalize a clip output format, failing if the clipper cannot write it
+pub fn validate_output_format(format: &str) -> Result<String> {
+    let format = format.trim().trim_start_matches('.').to_lowercase();
+    if is_format_supported_for_operation(&format, "clipper") {
+        Ok(format)
+    } else {
+        Err(VideoToolkitError::Other(format!(
+            "Output format '{}' is not supported by the clipper (supported: {})",
+            format,
+            get_supported_formats("clipper").join(", ")
+        )))
+    }
+}
+
+/// Codec arguments for re-encoding a clip into `format`
+///
+/// H.264 containers use the encoder picked for the run along with the
+/// container's audio codec; the others take their usual codecs.
+fn encode_args(format: &str, video_encoder: Option<&HwEncoder>) -> Vec<String> {
+    let codecs = get_codec_options(format);
+    if !codecs.iter().any(|arg| arg == SOFTWARE_H264_ENCODER) {
+        return codecs;
+    }
+
+    let mut args = video_encode_args(video_encoder);
+    if let Some(i) = codecs.iter().position(|arg| arg == "-c:a") {
+        args.extend_from_slice(&codecs[i..i + 2]);
+    }
+    args
+}
+
+/// Build the FFmpeg command for a single clip, re-encoding for the container of `output_file`
+fn build_clip_command(
+    input_file: &str,
+    start_time: &str,
+    end_time: &str,
+    copy_codec: bool,
+    avoid_negative_ts: AvoidNegativeTs,
+    video_encoder: Option<&HwEncoder>,
+    output_file: &Path,
+) -> FfmpegCommand {
+    let mut command = FfmpegCommand::new()
+        .overwrite()
+        .input(input_file)
+        .trim(start_time, end_time);
+
+    command = if copy_codec {
+        command.codec("copy")
+    } else {
+        let format = output_file.extension().and_then(|ext| ext.to_str()).unwrap_or(DEFAULT_OUTPUT_FORMAT);
+        command.args(encode_args(format, video_encoder))
+    };
+
+    if let Some(value) = avoid_negative_ts.ffmpeg_value(copy_codec) {
+        command = command.option("-avoid_negative_ts", value);
+    }
+
+    command.output(output_file)
+}
+
+/// Check a probed clip start time, describing the problem when it is not close to zero
+fn check_start_time(start_time: f64) -> Option<String> {
+    if start_time.abs() <= START_TIME_EPSILON {
+        return None;
+    }
+
+    if start_time < 0.0 {
+        Some(format!("clip starts at a negative timestamp ({:.3}s)", start_time))
+    } else {
+        Some(format!("clip starts at {:.3}s instead of 0", start_time))
+    }
+}
+
 /// Create a formatted output filename based on the input file and time range
 pub fn format_output_filename(
     input_file: &Path,
@@ -16,7 +154,11 @@ pub fn format_output_filename(
 ) -> PathBuf {
     // Get the basename without extension
     let base_name = input_file.file_stem().unwrap().to_string_lossy();
+    clip_file_path(&base_name, start_time, end_time, output_dir, suffix)
+}
 
+/// Path of a clip named after `base_name` and its time range
+pub fn clip_file_path(base_name: &str, start_time: &str, end_time: &str, output_dir: &Path, suffix: Option<&str>) -> PathBuf {
     // Format timestamps for filename (replace : with _)
     let start_formatted = start_time.replace(':', "_").replace('.', "_");
     let end_formatted = end_time.replace(':', "_").replace('.', "_");
@@ -37,15 +179,37 @@ pub fn clip_video(
     output_dir: &str,
     copy_codec: bool,
     suffix: Option<&str>,
+) -> Result<bool> {
+    clip_video_with_options(input_file, time_ranges, output_dir, copy_codec, suffix, &ClipOptions::default())
+}
+
+/// Extract clips with additional options (timestamp handling, output verification)
+pub fn clip_video_with_options(
+    input_file: &str,
+    time_ranges: &[(String, String)],
+    output_dir: &str,
+    copy_codec: bool,
+    suffix: Option<&str>,
+    options: &ClipOptions,
 ) -> Result<bool> {
     // Verify input file exists
     verify_input_file(input_file)?;
+    let _cancel_scope = cancellation_scope(options.cancel.as_ref());
 
     // Create output directory if it doesn't exist
     fs::create_dir_all(output_dir).map_err(|e| VideoToolkitError::IoError(e))?;
 
     let input_path = Path::new(input_file);
     let output_path = Path::new(output_dir);
+    let format = match options.output_format {
+        Some(ref format) => validate_output_format(format)?,
+        None => DEFAULT_OUTPUT_FORMAT.to_string(),
+    };
+
+    let video_encoder = if copy_codec { None } else { select_hw_encoder(&options.encoder) };
+    if options.encoder.hw_ok && !copy_codec {
+        println!("Encoding clips with {}", video_encoder.as_ref().map_or(SOFTWARE_H264_ENCODER, |e| e.name.as_str()));
+    }
 
     // Process each time range
     let mut success = true;
@@ -53,36 +217,55 @@ pub fn clip_video(
         println!("Creating clip {}/{} ({} to {})...", i + 1, time_ranges.len(), start_time, end_time);
 
         // Create output filename
-        let output_file = format_output_filename(input_path, start_time, end_time, output_path, suffix);
-        let output_str = output_file.to_string_lossy();
-
-        // Set encoding options
-        let mut args = vec![
-            "-y",
-            "-i", input_file,
-            "-ss", start_time,
-            "-to", end_time,
-        ];
-
-        if copy_codec {
-            args.extend_from_slice(&["-c", "copy"]);
-        } else {
-            args.extend_from_slice(&["-c:v", "libx264", "-preset", "medium", "-crf", "22", "-c:a", "aac"]);
+        let output_file = match options.output_name {
+            Some(ref name) => clip_file_path(name, start_time, end_time, output_path, suffix),
+            None => format_output_filename(input_path, start_time, end_time, output_path, suffix),
         }
+        .with_extension(&format);
 
-        args.push(&output_str);
+        let command = build_clip_command(
+            input_file,
+            start_time,
+            end_time,
+            copy_codec,
+            options.avoid_negative_ts,
+            video_encoder.as_ref(),
+            &output_file,
+        );
 
         // Execute FFmpeg command
-        if let Err(e) = execute_ffmpeg(&args) {
-            eprintln!("Error processing clip {} ({} to {}): {}", i + 1, start_time, end_time, e);
-            success = false;
-            continue;
+        match command.execute() {
+            Err(e) if e.is_interruption() => {
+                // Drop the partial clip
+                let _ = fs::remove_file(&output_file);
+                return Err(e);
+            },
+            Err(e) => {
+                eprintln!("Error processing clip {} ({} to {}): {}", i + 1, start_time, end_time, e);
+                success = false;
+                continue;
+            },
+            Ok(_) => {},
         }
 
         // Verify output file was created
-        if !output_file.exists() {
+        if !output_written(&output_file) {
             eprintln!("Error: Failed to create clip {}", i + 1);
             success = false;
+            continue;
+        }
+
+        // Catch clips that players will mis-handle because of shifted timestamps
+        if options.verify_outputs && !is_dry_run() {
+            let problem = match get_start_time(&output_file.to_string_lossy()) {
+                Ok(start) => check_start_time(start),
+                Err(e) => Some(format!("could not probe start time: {}", e)),
+            };
+
+            if let Some(problem) = problem {
+                eprintln!("Error: clip {} ({}): {}", i + 1, output_file.display(), problem);
+                success = false;
+            }
         }
     }
 
@@ -94,16 +277,89 @@ pub fn clip_video(
 }
 
 /// Parse time range strings into a list of (start_time, end_time) tuples
-pub fn parse_time_ranges(time_range_args: &[String]) -> Vec<(String, String)> {
+///
+/// Invalid ranges are skipped, with a warning for each.
+pub fn parse_time_ranges(time_range_args: &[String]) -> (Vec<(String, String)>, Vec<Warning>) {
     let mut time_ranges = Vec::new();
+    let mut warnings = Vec::new();
 
     for time_range in time_range_args {
         if let Some(parsed_range) = validate_time_range(time_range) {
             time_ranges.push(parsed_range);
         } else {
-            eprintln!("Warning: Invalid time range format: '{}', skipping.", time_range);
+            warnings.push(Warning::new(
+                WarningCode::RangeSkipped,
+                format!("invalid time range format '{}', skipped", time_range),
+            ));
         }
     }
 
-    time_ranges
-}
\ No newline at end of file
+    (time_ranges, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negative_ts_value(copy_codec: bool, mode: AvoidNegativeTs) -> Option<String> {
+        let args = build_clip_command("in.mkv", "00:00:10", "00:00:20", copy_codec, mode, None, Path::new("out.mp4"))
+            .to_args();
+        assert_eq!(args.last().map(String::as_str), Some("out.mp4"));
+        args.iter()
+            .position(|arg| arg == "-avoid_negative_ts")
+            .map(|i| args[i + 1].clone())
+    }
+
+    #[test]
+    fn test_avoid_negative_ts_flag_per_mode() {
+        assert_eq!(negative_ts_value(true, AvoidNegativeTs::Auto).as_deref(), Some("make_zero"));
+        assert_eq!(negative_ts_value(false, AvoidNegativeTs::Auto), None);
+        assert_eq!(negative_ts_value(false, AvoidNegativeTs::MakeZero).as_deref(), Some("make_zero"));
+        assert_eq!(negative_ts_value(true, AvoidNegativeTs::Disabled).as_deref(), Some("disabled"));
+
+        assert_eq!("make_zero".parse::<AvoidNegativeTs>().unwrap(), AvoidNegativeTs::MakeZero);
+        assert!("zero".parse::<AvoidNegativeTs>().is_err());
+    }
+
+    #[test]
+    fn test_clip_command() {
+        let output = Path::new("clips/in_00_00_10-00_00_20.mp4");
+        let copy = build_clip_command("in.mkv", "00:00:10", "00:00:20", true, AvoidNegativeTs::Auto, None, output);
+        assert_eq!(copy.to_args(), [
+            "-y", "-i", "in.mkv", "-ss", "00:00:10", "-to", "00:00:20", "-c", "copy",
+            "-avoid_negative_ts", "make_zero", "clips/in_00_00_10-00_00_20.mp4",
+        ]);
+
+        let encode = build_clip_command("in.mkv", "1:00", "1:30", false, AvoidNegativeTs::Disabled, None, output);
+        assert_eq!(encode.to_args()[7..], [
+            "-c:v", "libx264", "-preset", "medium", "-crf", "22", "-c:a", "aac",
+            "-avoid_negative_ts", "disabled", "clips/in_00_00_10-00_00_20.mp4",
+        ]);
+
+        let webm = Path::new("clips/in_00_00_10-00_00_20.webm");
+        let encode = build_clip_command("in.mkv", "1:00", "1:30", false, AvoidNegativeTs::Auto, None, webm);
+        assert_eq!(encode.to_args()[7..], ["-c:v", "libvpx", "-c:a", "libvorbis", "clips/in_00_00_10-00_00_20.webm"]);
+        assert_eq!(encode_args("avi", None)[6..], ["-c:a", "mp3"]);
+        assert!(validate_output_format("gif").is_err());
+    }
+
+    #[test]
+    fn test_check_start_time() {
+        assert_eq!(check_start_time(0.0), None);
+        assert_eq!(check_start_time(0.023), None);
+        assert_eq!(check_start_time(-0.04), None);
+        assert!(check_start_time(-1.4).unwrap().contains("negative"));
+        assert!(check_start_time(0.5).unwrap().contains("instead of 0"));
+    }
+
+    #[test]
+    fn test_invalid_ranges_are_warned() {
+        let args = ["00:00:01-00:00:05", "1:00-", "00:01:00-00:01:30"].map(String::from);
+        let (ranges, warnings) = parse_time_ranges(&args);
+
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::RangeSkipped);
+        assert!(warnings[0].message.contains("'1:00-'"));
+    }
+}
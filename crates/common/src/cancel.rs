@@ -0,0 +1,227 @@
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, VideoToolkitError};
+
+/// Shared flag for stopping a running operation from another thread
+///
+/// Clones share the flag. Once cancelled, FFmpeg runs made under the token are
+/// killed and fail with [`VideoToolkitError::Cancelled`].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations using this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`VideoToolkitError::Cancelled`] once cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(VideoToolkitError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_TOKEN: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// Token of the innermost [`cancellation_scope`] on this thread
+pub(crate) fn current_cancellation_token() -> Option<CancellationToken> {
+    CURRENT_TOKEN.with(|token| token.borrow().clone())
+}
+
+/// Restores the previous token of the thread when dropped
+pub struct CancellationScope {
+    previous: Option<Option<CancellationToken>>,
+    // Tied to the thread whose token it restores
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for CancellationScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            CURRENT_TOKEN.with(|token| *token.borrow_mut() = previous);
+        }
+    }
+}
+
+/// Make the FFmpeg runs on this thread honor `token` until the returned scope is dropped
+///
+/// With `None`, the token of an enclosing scope (e.g. a batch run) stays in effect.
+pub fn cancellation_scope(token: Option<&CancellationToken>) -> CancellationScope {
+    let previous = token.map(|token| CURRENT_TOKEN.with(|current| current.replace(Some(token.clone()))));
+    CancellationScope { previous, _not_send: std::marker::PhantomData }
+}
+
+/// Fail with [`VideoToolkitError::Cancelled`] if the current scope was cancelled
+pub fn check_cancelled() -> Result<()> {
+    match current_cancellation_token() {
+        Some(token) => token.check(),
+        None => Ok(()),
+    }
+}
+
+/// Point in time after which FFmpeg runs are stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Deadline {
+    pub at: Instant,
+    /// Time limit the deadline was set from, for error messages
+    pub timeout: Duration,
+}
+
+impl Deadline {
+    pub fn after(timeout: Duration) -> Self {
+        Self { at: Instant::now() + timeout, timeout }
+    }
+
+    pub fn passed(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    pub fn earliest(a: Option<Self>, b: Option<Self>) -> Option<Self> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(if b.at < a.at { b } else { a }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_DEADLINE: Cell<Option<Deadline>> = const { Cell::new(None) };
+}
+
+/// Deadline of the innermost [`deadline_scope`] on this thread
+pub(crate) fn current_deadline() -> Option<Deadline> {
+    CURRENT_DEADLINE.with(Cell::get)
+}
+
+/// Restores the previous deadline of the thread when dropped
+pub struct DeadlineScope {
+    previous: Option<Option<Deadline>>,
+    // Tied to the thread whose deadline it restores
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for DeadlineScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            CURRENT_DEADLINE.with(|deadline| deadline.set(previous));
+        }
+    }
+}
+
+/// Kill the FFmpeg runs on this thread that are still going `timeout` from now,
+/// until the returned scope is dropped
+///
+/// The runs fail with [`VideoToolkitError::Timeout`]. An enclosing scope that
+/// ends sooner stays in effect, as it does with `None`.
+pub fn deadline_scope(timeout: Option<Duration>) -> DeadlineScope {
+    enter_deadline(timeout.map(Deadline::after))
+}
+
+fn enter_deadline(deadline: Option<Deadline>) -> DeadlineScope {
+    let previous = deadline.map(|deadline| {
+        CURRENT_DEADLINE.with(|current| {
+            let previous = current.get();
+            current.set(Deadline::earliest(previous, Some(deadline)));
+            previous
+        })
+    });
+    DeadlineScope { previous, _not_send: std::marker::PhantomData }
+}
+
+/// Cancellation token and deadline of a thread, for carrying them over to the
+/// threads that work is handed to
+#[derive(Debug, Clone, Default)]
+pub struct RunLimits {
+    cancel: Option<CancellationToken>,
+    deadline: Option<Deadline>,
+}
+
+impl RunLimits {
+    /// The limits in effect on the current thread
+    pub fn current() -> Self {
+        Self { cancel: current_cancellation_token(), deadline: current_deadline() }
+    }
+
+    /// Cancellation token in effect, if any
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancel.as_ref()
+    }
+
+    /// Time left before the deadline, if there is one; zero once it has passed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Apply the limits to the current thread until the returned scopes are dropped
+    pub fn enter(&self) -> (CancellationScope, DeadlineScope) {
+        (cancellation_scope(self.cancel.as_ref()), enter_deadline(self.deadline))
+    }
+
+    /// Fail if the token was cancelled or the deadline has passed
+    pub fn check(&self) -> Result<()> {
+        if let Some(ref cancel) = self.cancel {
+            cancel.check()?;
+        }
+        match self.deadline.filter(Deadline::passed) {
+            Some(deadline) => Err(VideoToolkitError::Timeout(deadline.timeout)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_scopes() {
+        let batch = CancellationToken::new();
+        let _outer = cancellation_scope(Some(&batch));
+        {
+            // Operations without a token of their own keep the batch token
+            let _inner = cancellation_scope(None);
+            batch.cancel();
+            assert!(matches!(check_cancelled(), Err(VideoToolkitError::Cancelled)));
+        }
+
+        let item = CancellationToken::new();
+        {
+            let _inner = cancellation_scope(Some(&item));
+            assert!(check_cancelled().is_ok());
+        }
+        assert!(check_cancelled().is_err());
+    }
+
+    #[test]
+    fn test_deadline_scopes() {
+        assert_eq!(current_deadline(), None);
+        let _batch = deadline_scope(Some(Duration::from_secs(60)));
+        let batch = current_deadline().unwrap();
+        {
+            // A longer inner limit does not extend the enclosing one
+            let _command = deadline_scope(Some(Duration::from_secs(600)));
+            assert_eq!(current_deadline(), Some(batch));
+            let _quick = deadline_scope(Some(Duration::from_secs(1)));
+            assert_eq!(current_deadline().unwrap().timeout, Duration::from_secs(1));
+        }
+        assert_eq!(current_deadline(), Some(batch));
+        assert!(!batch.passed());
+    }
+}
@@ -5,8 +5,21 @@ pub enum VideoToolkitError {
     #[error("FFmpeg not found. Please install FFmpeg and make sure it's in your PATH.")]
     FFmpegNotFound,
 
-    #[error("FFmpeg command failed: {0}")]
-    FFmpegCommandFailed(String),
+    #[error(
+        "FFmpeg command failed ({}):\n{stderr_tail}\nCommand: {}",
+        describe_exit(*.exit_code),
+        truncate_command(.command)
+    )]
+    FFmpegCommandFailed {
+        /// Shell-quoted rendering of the full command line
+        command: String,
+        /// Exit code, or `None` if the process was killed by a signal
+        exit_code: Option<i32>,
+        /// Last lines of stderr, where FFmpeg reports what went wrong
+        stderr_tail: String,
+        /// The whole stderr, when it was captured
+        full_log: Option<String>,
+    },
 
     #[error("Input file '{0}' not found")]
     InputFileNotFound(String),
@@ -20,12 +33,24 @@ pub enum VideoToolkitError {
     #[error("Invalid time range format: {0}")]
     InvalidTimeRange(String),
 
-    #[error("Video dimensions are {0}x{1}, expected 1920x1080")]
-    InvalidDimensions(u32, u32),
+    #[error("Video dimensions are {0}x{1}, expected {2}x{3}")]
+    InvalidDimensions(u32, u32, u32, u32),
 
     #[error("Could not determine video dimensions")]
     DimensionsError,
 
+    #[error("Crop region {0} exceeds video dimensions {1}x{2}")]
+    InvalidCropRegion(String, u32, u32),
+
+    #[error("FFmpeg encoder '{0}' is not available in this FFmpeg build")]
+    EncoderNotAvailable(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Command timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -33,5 +58,90 @@ pub enum VideoToolkitError {
     Other(String),
 }
 
+/// Longest command rendering shown in error messages; the full text stays on the error
+const MAX_COMMAND_DISPLAY_LEN: usize = 300;
+
+/// Number of stderr lines kept in the message of a failed command
+const ERROR_TAIL_LINES: usize = 20;
+
+fn describe_exit(exit_code: Option<i32>) -> String {
+    match exit_code {
+        Some(code) => format!("exit code {}", code),
+        None => "killed by a signal".to_string(),
+    }
+}
+
+/// Shorten a command line for display, keeping the start (program, inputs, filters)
+fn truncate_command(command: &str) -> String {
+    match command.char_indices().nth(MAX_COMMAND_DISPLAY_LEN) {
+        Some((index, _)) => format!("{}... (truncated, run with --verbose for the full command)", &command[..index]),
+        None => command.to_string(),
+    }
+}
+
+impl VideoToolkitError {
+    /// Error for a command that exited unsuccessfully
+    ///
+    /// `complete` tells whether `stderr` is the whole log or already only its end.
+    pub(crate) fn command_failed(command: String, exit_code: Option<i32>, stderr: &str, complete: bool) -> Self {
+        let lines: Vec<&str> = stderr.trim_end().lines().collect();
+        let stderr_tail = lines[lines.len().saturating_sub(ERROR_TAIL_LINES)..].join("\n");
+
+        VideoToolkitError::FFmpegCommandFailed {
+            command,
+            exit_code,
+            stderr_tail,
+            full_log: complete.then(|| stderr.to_string()),
+        }
+    }
+
+    /// The full command line of a failed FFmpeg/FFprobe invocation, if any
+    pub fn command(&self) -> Option<&str> {
+        match self {
+            VideoToolkitError::FFmpegCommandFailed { command, .. } => Some(command),
+            _ => None,
+        }
+    }
+
+    /// The whole stderr of a failed FFmpeg/FFprobe invocation, if it was captured
+    pub fn full_log(&self) -> Option<&str> {
+        match self {
+            VideoToolkitError::FFmpegCommandFailed { full_log, .. } => full_log.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the command was stopped (cancelled or timed out) rather than failing on its own
+    pub fn is_interruption(&self) -> bool {
+        matches!(self, VideoToolkitError::Cancelled | VideoToolkitError::Timeout(_))
+    }
+
+    /// Whether running the same operation again might succeed
+    ///
+    /// Failed FFmpeg runs and I/O errors other than missing files, denied
+    /// access and bad data count as transient (e.g. a network share dropping
+    /// out); invalid input, configuration and interruptions do not.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            VideoToolkitError::FFmpegCommandFailed { .. } |
+            VideoToolkitError::OutputFileNotCreated |
+            VideoToolkitError::DimensionsError => true,
+            VideoToolkitError::IoError(e) => is_transient_io(e),
+            _ => false,
+        }
+    }
+}
+
+/// Whether an I/O error may go away on its own, unlike a missing file or denied access
+pub fn is_transient_io(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    !matches!(
+        error.kind(),
+        ErrorKind::NotFound | ErrorKind::PermissionDenied | ErrorKind::InvalidInput | ErrorKind::InvalidData |
+            ErrorKind::AlreadyExists | ErrorKind::Unsupported
+    )
+}
+
 // Type alias for Result with our custom error type
 pub type Result<T> = std::result::Result<T, VideoToolkitError>;
\ No newline at end of file
@@ -1,11 +1,218 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
 use crate::formats::{FormatType, detect_format};
-use std::process::{Command, Output};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 use regex::Regex;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 
+use crate::cancel::{current_cancellation_token, current_deadline, CancellationToken, Deadline};
 use crate::error::{Result, VideoToolkitError};
 
+// Print every FFmpeg/FFprobe command line before running it
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable printing of full FFmpeg command lines
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+/// Whether full FFmpeg command lines are printed
+pub fn is_verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+// Print the FFmpeg commands that write files instead of running them
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Number of skipped commands kept for [`dry_run_log`]
+const DRY_RUN_LOG_LINES: usize = 200;
+
+static DRY_RUN_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Enable or disable dry runs, in which FFmpeg commands that write files are printed instead of run
+///
+/// Skipped commands succeed with an empty output. Commands that only read, such
+/// as `-encoders` or analysis passes writing to `-f null -`, still run so that
+/// the printed commands are the ones a real run would make.
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+/// Whether FFmpeg commands that write files are skipped
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Print a command skipped by a dry run and keep it for [`dry_run_log`]
+pub fn record_dry_run(command: String) {
+    println!("Would run: {}", command);
+    let mut log = DRY_RUN_LOG.lock().unwrap();
+    if log.len() == DRY_RUN_LOG_LINES {
+        log.pop_front();
+    }
+    log.push_back(command);
+}
+
+/// Commands skipped by dry runs so far, oldest first
+pub fn dry_run_log() -> Vec<String> {
+    DRY_RUN_LOG.lock().unwrap().iter().cloned().collect()
+}
+
+pub fn clear_dry_run_log() {
+    DRY_RUN_LOG.lock().unwrap().clear();
+}
+
+/// Whether the command just run wrote `path`; always true in a dry run, where nothing is written
+pub fn output_written(path: &Path) -> bool {
+    is_dry_run() || path.exists()
+}
+
+/// Whether an FFmpeg command writes files: it reads an input and does not write to stdout
+fn writes_files(args: &[&str]) -> bool {
+    args.contains(&"-i") && args.last() != Some(&"-")
+}
+
+/// Record and skip an FFmpeg command that would write files during a dry run
+fn skip_for_dry_run(args: &[&str]) -> Option<Output> {
+    if !(is_dry_run() && writes_files(args)) {
+        return None;
+    }
+
+    let command = quote_command("ffmpeg", args);
+    log::info!("Skipped (dry run): {}", command);
+    record_dry_run(command);
+    Some(Output { status: ExitStatus::default(), stdout: Vec::new(), stderr: Vec::new() })
+}
+
+/// Quote a single argument for a POSIX shell, leaving plain arguments untouched
+pub fn shell_quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Split a command line into arguments following POSIX shell quoting rules
+///
+/// Single quotes keep everything literally, double quotes allow `\"` and `\\`,
+/// and a backslash outside quotes escapes the next character. The inverse of
+/// [`shell_quote`].
+pub fn split_arguments(line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    // Whether `current` holds an argument, which may be an empty quoted string
+    let mut in_arg = false;
+    let mut chars = line.chars();
+    let unterminated = |quote: &str| VideoToolkitError::Other(format!("Unterminated {} quote in '{}'", quote, line));
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(unterminated("single")),
+                    }
+                }
+            },
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            },
+                            None => return Err(unterminated("double")),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(unterminated("double")),
+                    }
+                }
+            },
+            '\\' => {
+                in_arg = true;
+                current.push(chars.next().unwrap_or('\\'));
+            },
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            },
+            c => {
+                in_arg = true;
+                current.push(c);
+            },
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Render a program and its arguments as a copy-pasteable shell command
+pub fn quote_command(program: &str, args: &[&str]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().copied())
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Log a finished command with its duration and exit status, and its stderr at debug level
+fn log_finished(command: &str, started: Instant, status: ExitStatus, stderr: &str) {
+    let level = if status.success() { log::Level::Info } else { log::Level::Warn };
+    log::log!(level, "{} ({}, {:.2}s)", command, status, started.elapsed().as_secs_f64());
+    if !stderr.trim().is_empty() {
+        log::debug!("stderr of {}:\n{}", command, stderr.trim_end());
+    }
+}
+
+/// Run a command, turning a non-zero exit status into `FFmpegCommandFailed`
+fn run_command(program: &str, args: &[&str]) -> Result<Output> {
+    let command = quote_command(program, args);
+    if is_verbose() {
+        eprintln!("Running: {}", command);
+    }
+
+    let started = Instant::now();
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| {
+            log::warn!("Could not start {}: {}", command, e);
+            VideoToolkitError::IoError(e)
+        })?;
+    log_finished(&command, started, output.status, &String::from_utf8_lossy(&output.stderr));
+
+    if !output.status.success() {
+        if is_verbose() {
+            eprintln!("Command failed: {}", command);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(VideoToolkitError::command_failed(command, output.status.code(), &stderr, true));
+    }
+
+    Ok(output)
+}
+
 /// Check if FFmpeg is installed and accessible
 pub fn check_ffmpeg() -> bool {
     Command::new("ffmpeg")
@@ -16,38 +223,465 @@ pub fn check_ffmpeg() -> bool {
 }
 
 /// Execute an FFmpeg command with the given arguments
+///
+/// Runs that read an input (`-i`) report their progress to the observer of the
+/// current thread, if [`observe_progress`] set one.
+/// Inside a [`crate::cancellation_scope`], they are killed once its token is cancelled,
+/// and inside a [`crate::deadline_scope`] once its time is up.
 pub fn execute_ffmpeg(args: &[&str]) -> Result<Output> {
-    let output = Command::new("ffmpeg")
+    run_ffmpeg(args, current_deadline())
+}
+
+/// Execute an FFmpeg command, killing it if it reads its input for longer than `timeout`
+///
+/// Fails with [`VideoToolkitError::Timeout`]. Commands without an input (e.g.
+/// `-encoders`) are not limited.
+pub fn execute_ffmpeg_with_timeout(args: &[&str], timeout: Duration) -> Result<Output> {
+    run_ffmpeg(args, Deadline::earliest(current_deadline(), Some(Deadline::after(timeout))))
+}
+
+fn run_ffmpeg(args: &[&str], deadline: Option<Deadline>) -> Result<Output> {
+    if let Some(output) = skip_for_dry_run(args) {
+        return Ok(output);
+    }
+
+    let cancel = current_cancellation_token();
+    if let Some(ref cancel) = cancel {
+        cancel.check()?;
+    }
+
+    if args.contains(&"-i") {
+        let mut observer = PROGRESS_OBSERVER.with(|observer| observer.borrow_mut().take());
+        if observer.is_some() || cancel.is_some() || deadline.is_some() {
+            let result = stream_ffmpeg(args, cancel.as_ref(), deadline, |progress| {
+                if let Some(ref mut observer) = observer {
+                    observer(progress);
+                }
+            });
+            if observer.is_some() {
+                PROGRESS_OBSERVER.with(|slot| *slot.borrow_mut() = observer);
+            }
+            return result;
+        }
+    }
+    run_command("ffmpeg", args)
+}
+
+/// Progress of a running FFmpeg command, from its `-progress` output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FfmpegProgress {
+    /// Seconds of output written so far
+    pub out_time: f64,
+    /// Frames written so far (video outputs only)
+    pub frame: Option<u64>,
+    /// Speed relative to real time, e.g. 2.0 for twice as fast
+    pub speed: Option<f64>,
+    /// Set on the last report, once FFmpeg has finished
+    pub finished: bool,
+}
+
+impl FfmpegProgress {
+    /// Apply one `key=value` line; returns true when the line completes a report
+    fn apply_line(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+
+        match key {
+            // Despite its name, out_time_ms is in microseconds as well
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(micros) = value.parse::<i64>() {
+                    self.out_time = micros.max(0) as f64 / 1_000_000.0;
+                }
+            },
+            "frame" => self.frame = value.parse().ok(),
+            "speed" => self.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => {
+                self.finished = value == "end";
+                return true;
+            },
+            _ => {},
+        }
+        false
+    }
+}
+
+/// Number of stderr lines kept by [`execute_ffmpeg_streaming`]
+const STDERR_TAIL_LINES: usize = 100;
+
+/// How often a streaming run checks its cancellation token and deadline
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Run FFmpeg with `-progress pipe:1 -nostats`, calling `on_progress` after every progress report
+///
+/// Returns like [`execute_ffmpeg`], except that stdout holds nothing (it carries
+/// the progress reports) and stderr only its last lines. FFmpeg is killed once
+/// `cancel` is cancelled ([`VideoToolkitError::Cancelled`]) or after `timeout`
+/// ([`VideoToolkitError::Timeout`]).
+pub fn execute_ffmpeg_streaming(
+    args: &[&str],
+    cancel: Option<&CancellationToken>,
+    timeout: Option<Duration>,
+    on_progress: impl FnMut(&FfmpegProgress),
+) -> Result<Output> {
+    if let Some(output) = skip_for_dry_run(args) {
+        return Ok(output);
+    }
+
+    let deadline = Deadline::earliest(current_deadline(), timeout.map(Deadline::after));
+    stream_ffmpeg(args, cancel, deadline, on_progress)
+}
+
+fn stream_ffmpeg(
+    args: &[&str],
+    cancel: Option<&CancellationToken>,
+    deadline: Option<Deadline>,
+    on_progress: impl FnMut(&FfmpegProgress),
+) -> Result<Output> {
+    let mut full_args = vec!["-progress", "pipe:1", "-nostats"];
+    full_args.extend_from_slice(args);
+    run_streaming("ffmpeg", &full_args, cancel, deadline, on_progress)
+}
+
+/// Run a program that writes `-progress` reports to stdout, stopping it when
+/// cancelled or past the deadline
+fn run_streaming(
+    program: &str,
+    args: &[&str],
+    cancel: Option<&CancellationToken>,
+    deadline: Option<Deadline>,
+    mut on_progress: impl FnMut(&FfmpegProgress),
+) -> Result<Output> {
+    if let Some(cancel) = cancel {
+        cancel.check()?;
+    }
+    if let Some(deadline) = deadline.filter(Deadline::passed) {
+        return Err(VideoToolkitError::Timeout(deadline.timeout));
+    }
+
+    let command = quote_command(program, args);
+    if is_verbose() {
+        eprintln!("Running: {}", command);
+    }
+
+    let started = Instant::now();
+    let mut child = Command::new(program)
         .args(args)
-        .output()
-        .map_err(|e| VideoToolkitError::IoError(e))?;
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            log::warn!("Could not start {}: {}", command, e);
+            VideoToolkitError::IoError(e)
+        })?;
 
-    if !output.status.success() {
-        let error_message = String::from_utf8_lossy(&output.stderr);
-        return Err(VideoToolkitError::FFmpegCommandFailed(error_message.to_string()));
+    // Drain stderr on its own thread so that the program never blocks on a full pipe
+    let stderr = child.stderr.take();
+    let stderr_reader = thread::spawn(move || {
+        let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        if let Some(stderr) = stderr {
+            for line in BufReader::new(stderr).split(b'\n').map_while(|line| line.ok()) {
+                if tail.len() == STDERR_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+            }
+        }
+        Vec::from(tail).join("\n")
+    });
+
+    // Parse the reports on another thread as well, so that a cancellation or
+    // timeout is noticed even while the program writes nothing
+    let (reports, received) = mpsc::channel();
+    let stdout = child.stdout.take();
+    thread::spawn(move || {
+        let mut progress = FfmpegProgress::default();
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+                if progress.apply_line(&line) && reports.send(progress.clone()).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut reporting = true;
+    let status = loop {
+        let stop = if cancel.is_some_and(CancellationToken::is_cancelled) {
+            Some(VideoToolkitError::Cancelled)
+        } else {
+            deadline.filter(Deadline::passed).map(|deadline| VideoToolkitError::Timeout(deadline.timeout))
+        };
+        if let Some(error) = stop {
+            let _ = child.kill();
+            let _ = child.wait();
+            log::warn!("Stopped {} after {:.2}s: {}", command, started.elapsed().as_secs_f64(), error);
+            if is_verbose() {
+                eprintln!("Stopped: {} ({})", command, error);
+            }
+            return Err(error);
+        }
+
+        if reporting {
+            match received.recv_timeout(POLL_INTERVAL) {
+                Ok(progress) => on_progress(&progress),
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => reporting = false,
+            }
+        } else {
+            // Stdout is closed; the program may still be finishing
+            match child.try_wait().map_err(VideoToolkitError::IoError)? {
+                Some(status) => break status,
+                None => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    };
+
+    let stderr = stderr_reader.join().unwrap_or_default();
+    log_finished(&command, started, status, &stderr);
+    if !status.success() {
+        if is_verbose() {
+            eprintln!("Command failed: {}", command);
+        }
+        // Only the end of stderr was kept
+        return Err(VideoToolkitError::command_failed(command, status.code(), &stderr, false));
     }
 
-    Ok(output)
+    Ok(Output { status, stdout: Vec::new(), stderr: stderr.into_bytes() })
 }
 
-/// Get video dimensions using FFprobe
-pub fn get_video_dimensions(file_path: &str) -> Result<(u32, u32)> {
-    let output = Command::new("ffprobe")
-        .args(&[
-            "-v", "error",
-            "-select_streams", "v:0",
-            "-show_entries", "stream=width,height",
-            "-of", "csv=p=0",
-            file_path
-        ])
-        .output()
-        .map_err(|e| VideoToolkitError::IoError(e))?;
+type ProgressObserver = Box<dyn FnMut(&FfmpegProgress)>;
 
-    if !output.status.success() {
-        let error_message = String::from_utf8_lossy(&output.stderr);
-        return Err(VideoToolkitError::FFmpegCommandFailed(error_message.to_string()));
+thread_local! {
+    static PROGRESS_OBSERVER: RefCell<Option<ProgressObserver>> = RefCell::new(None);
+}
+
+/// Keeps the observer set by [`observe_progress`]; removes it when dropped
+pub struct ProgressObserverGuard {
+    // Tied to the thread whose observer it removes
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for ProgressObserverGuard {
+    fn drop(&mut self) {
+        PROGRESS_OBSERVER.with(|observer| *observer.borrow_mut() = None);
+    }
+}
+
+/// Report the progress of FFmpeg runs started on this thread to `observer`,
+/// until the returned guard is dropped
+///
+/// Lets callers such as the GUI follow operations that call [`execute_ffmpeg`]
+/// internally without changing their signatures.
+pub fn observe_progress(observer: impl FnMut(&FfmpegProgress) + 'static) -> ProgressObserverGuard {
+    PROGRESS_OBSERVER.with(|slot| *slot.borrow_mut() = Some(Box::new(observer)));
+    ProgressObserverGuard { _not_send: std::marker::PhantomData }
+}
+
+/// List the encoders compiled into the installed FFmpeg build
+pub fn list_encoders() -> Result<String> {
+    let output = execute_ffmpeg(&["-hide_banner", "-encoders"])?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Check whether an encoder appears in the output of `ffmpeg -encoders`
+pub fn encoder_listed(encoders: &str, encoder: &str) -> bool {
+    encoders
+        .lines()
+        .any(|line| line.split_whitespace().nth(1) == Some(encoder))
+}
+
+/// Hardware APIs with H.264/HEVC encoders that take frames from system memory,
+/// and the hwaccel names under which `ffmpeg -hwaccels` lists them
+const HW_ENCODER_APIS: &[(&str, &[&str])] = &[
+    ("nvenc", &["cuda", "nvdec"]),
+    ("videotoolbox", &["videotoolbox"]),
+    ("qsv", &["qsv"]),
+    ("amf", &["d3d11va", "dxva2"]),
+];
+
+/// Video encoder used when no hardware encoder is picked
+pub const SOFTWARE_H264_ENCODER: &str = "libx264";
+
+/// Hardware video encoder available to the installed FFmpeg build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HwEncoder {
+    /// FFmpeg encoder name, e.g. `h264_nvenc`
+    pub name: String,
+    /// Codec it writes: `h264` or `hevc`
+    pub codec: String,
+    /// Hardware API, e.g. `nvenc` or `videotoolbox`
+    pub api: String,
+}
+
+impl HwEncoder {
+    /// Rate control arguments of roughly the quality of libx264 at `-crf 22`
+    pub fn quality_args(&self) -> Vec<String> {
+        let args: &[&str] = match self.api.as_str() {
+            "nvenc" => &["-preset", "p4", "-rc", "vbr", "-cq", "22", "-b:v", "0"],
+            "videotoolbox" => &["-q:v", "65"],
+            "qsv" => &["-preset", "medium", "-global_quality", "22"],
+            "amf" => &["-rc", "cqp", "-qp_i", "22", "-qp_p", "22"],
+            _ => &[],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+}
+
+/// Hardware encoders named in `ffmpeg -encoders` output whose API also appears in `ffmpeg -hwaccels` output
+fn parse_hw_encoders(encoders: &str, hwaccels: &str) -> Vec<HwEncoder> {
+    let hwaccels: Vec<&str> = hwaccels.lines().map(str::trim).collect();
+
+    encoders
+        .lines()
+        .filter(|line| line.trim_start().starts_with('V'))
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|name| {
+            let (codec, api) = name.split_once('_')?;
+            let (_, accels) = HW_ENCODER_APIS.iter().find(|(known, _)| *known == api)?;
+            let supported = matches!(codec, "h264" | "hevc") && accels.iter().any(|accel| hwaccels.contains(accel));
+            supported.then(|| HwEncoder { name: name.to_string(), codec: codec.to_string(), api: api.to_string() })
+        })
+        .collect()
+}
+
+/// Check that `encoder` can actually encode on this machine with a short test run
+fn hw_encoder_works(encoder: &HwEncoder) -> bool {
+    let mut args = vec!["-hide_banner", "-f", "lavfi", "-i", "color=size=256x256:duration=0.1", "-frames:v", "1"];
+    args.extend(["-c:v", &encoder.name]);
+    args.extend(["-f", "null", "-"]);
+    execute_ffmpeg_with_timeout(&args, Duration::from_secs(10)).is_ok()
+}
+
+/// Hardware video encoders that work on this machine
+///
+/// Builds often list encoders (e.g. NVENC) for hardware that is not installed,
+/// so each candidate from `ffmpeg -encoders`/`-hwaccels` is checked with a short
+/// test encode. The result is detected once per process.
+pub fn detect_hw_encoders() -> Vec<HwEncoder> {
+    static DETECTED: OnceLock<Vec<HwEncoder>> = OnceLock::new();
+
+    DETECTED
+        .get_or_init(|| {
+            let hwaccels = match execute_ffmpeg(&["-hide_banner", "-hwaccels"]) {
+                Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+                Err(_) => return Vec::new(),
+            };
+            let encoders = list_encoders().unwrap_or_default();
+            parse_hw_encoders(&encoders, &hwaccels).into_iter().filter(hw_encoder_works).collect()
+        })
+        .clone()
+}
+
+/// Whether re-encoding may use a hardware video encoder, and which one
+///
+/// Written `off` (the default), `auto` (the first detected H.264 encoder) or an
+/// encoder name such as `h264_nvenc` or `hevc_videotoolbox`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EncoderPreference {
+    pub hw_ok: bool,
+    /// Hardware encoder to use instead of the first detected one
+    pub encoder: Option<String>,
+}
+
+impl EncoderPreference {
+    /// Use the first detected hardware encoder
+    pub fn auto() -> Self {
+        Self { hw_ok: true, encoder: None }
+    }
+}
+
+impl std::fmt::Display for EncoderPreference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.hw_ok, &self.encoder) {
+            (false, _) => write!(f, "off"),
+            (true, None) => write!(f, "auto"),
+            (true, Some(encoder)) => write!(f, "{}", encoder),
+        }
+    }
+}
+
+impl std::str::FromStr for EncoderPreference {
+    type Err = VideoToolkitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim().to_lowercase();
+        match value.as_str() {
+            "auto" => Ok(Self::auto()),
+            "off" => Ok(Self::default()),
+            name if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                Ok(Self { hw_ok: true, encoder: Some(value) })
+            },
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid hardware acceleration '{}': expected auto, off or an encoder name such as h264_nvenc",
+                value
+            ))),
+        }
+    }
+}
+
+/// Pick the hardware encoder allowed by `preference` among `available`
+fn choose_hw_encoder(preference: &EncoderPreference, available: &[HwEncoder]) -> Option<HwEncoder> {
+    if !preference.hw_ok {
+        return None;
     }
 
+    match &preference.encoder {
+        Some(name) => available.iter().find(|encoder| &encoder.name == name),
+        None => available.iter().find(|encoder| encoder.codec == "h264"),
+    }
+    .cloned()
+}
+
+/// Hardware encoder to re-encode with under `preference`, or `None` for libx264
+///
+/// A requested encoder that was not detected falls back to libx264 with a note on stderr.
+pub fn select_hw_encoder(preference: &EncoderPreference) -> Option<HwEncoder> {
+    if !preference.hw_ok {
+        return None;
+    }
+
+    let selected = choose_hw_encoder(preference, &detect_hw_encoders());
+    if selected.is_none() {
+        match &preference.encoder {
+            Some(name) => eprintln!("Hardware encoder {} is not available, using {}", name, SOFTWARE_H264_ENCODER),
+            None => eprintln!("No hardware encoder found, using {}", SOFTWARE_H264_ENCODER),
+        }
+    }
+    selected
+}
+
+/// Video encoding arguments: the hardware encoder, or libx264 at `-preset medium -crf 22`
+pub fn video_encode_args(encoder: Option<&HwEncoder>) -> Vec<String> {
+    match encoder {
+        Some(encoder) => {
+            let mut args = vec!["-c:v".to_string(), encoder.name.clone()];
+            args.extend(encoder.quality_args());
+            args
+        },
+        None => ["-c:v", SOFTWARE_H264_ENCODER, "-preset", "medium", "-crf", "22"].map(String::from).to_vec(),
+    }
+}
+
+/// The video encoder set by `-c:v` (or `-vcodec`) in FFmpeg arguments
+pub fn video_encoder_in(args: &[String]) -> Option<String> {
+    args.windows(2)
+        .rev()
+        .find(|pair| matches!(pair[0].as_str(), "-c:v" | "-vcodec" | "-codec:v"))
+        .map(|pair| pair[1].clone())
+}
+
+/// Get video dimensions using FFprobe
+pub fn get_video_dimensions(file_path: &str) -> Result<(u32, u32)> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-select_streams", "v:0",
+        "-show_entries", "stream=width,height",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
     let dimensions = String::from_utf8_lossy(&output.stdout);
     let parts: Vec<&str> = dimensions.trim().split(',').collect();
 
@@ -63,6 +697,278 @@ pub fn get_video_dimensions(file_path: &str) -> Result<(u32, u32)> {
     Ok((width, height))
 }
 
+/// Get the container start time of a media file in seconds using FFprobe
+pub fn get_start_time(file_path: &str) -> Result<f64> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-show_entries", "format=start_time",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| VideoToolkitError::Other(format!("Could not determine start time of '{}'", file_path)))
+}
+
+/// Get the duration of a media file in seconds using FFprobe
+pub fn get_duration(file_path: &str) -> Result<f64> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-show_entries", "format=duration",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| VideoToolkitError::Other(format!("Could not determine duration of '{}'", file_path)))
+}
+
+/// Get the duration of the first video stream in seconds, or of the whole
+/// file if the container does not store stream durations (e.g. Matroska)
+pub fn get_video_duration(file_path: &str) -> Result<f64> {
+    let info = probe_media(file_path)?;
+    let stream = info
+        .video_stream()
+        .ok_or_else(|| VideoToolkitError::Other(format!("'{}' has no video stream", file_path)))?;
+
+    stream
+        .duration
+        .or(info.duration)
+        .ok_or_else(|| VideoToolkitError::Other(format!("Could not determine duration of '{}'", file_path)))
+}
+
+/// Container and streams of a media file, as reported by FFprobe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaInfo {
+    /// FFprobe's format name, e.g. `mov,mp4,m4a,3gp,3g2,mj2` or `matroska,webm`
+    pub container: String,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    /// Overall bitrate in bits per second
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+/// One stream of a media file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    /// `video`, `audio`, `subtitle`, `data` or `attachment`
+    pub codec_type: String,
+    pub codec_name: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Average frame rate of a video stream
+    pub fps: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    /// Duration in seconds, if the container stores it per stream
+    pub duration: Option<f64>,
+    /// Embedded cover art rather than actual video
+    pub attached_pic: bool,
+}
+
+impl MediaInfo {
+    /// First video stream, skipping cover art
+    pub fn video_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|stream| stream.codec_type == "video" && !stream.attached_pic)
+    }
+
+    pub fn audio_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(|stream| stream.codec_type == "audio")
+    }
+}
+
+/// `ffprobe -of json` output; numbers other than sizes and channel counts are strings
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    index: u32,
+    #[serde(default)]
+    codec_type: String,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    duration: Option<String>,
+    #[serde(default)]
+    disposition: ProbeDisposition,
+}
+
+#[derive(Default, Deserialize)]
+struct ProbeDisposition {
+    #[serde(default)]
+    attached_pic: u8,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: String,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+/// Frame rate from a rational such as `30000/1001`; `0/0` means unknown
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+/// Parse the output of `ffprobe -show_format -show_streams -of json`
+pub fn parse_media_info(json: &str) -> Result<MediaInfo> {
+    let probe: ProbeOutput = serde_json::from_str(json)
+        .map_err(|e| VideoToolkitError::Other(format!("Could not parse FFprobe output: {}", e)))?;
+
+    let streams = probe
+        .streams
+        .into_iter()
+        .map(|stream| StreamInfo {
+            index: stream.index,
+            fps: stream
+                .avg_frame_rate
+                .as_deref()
+                .and_then(parse_frame_rate)
+                .or_else(|| stream.r_frame_rate.as_deref().and_then(parse_frame_rate))
+                .filter(|_| stream.codec_type == "video"),
+            codec_type: stream.codec_type,
+            codec_name: stream.codec_name,
+            width: stream.width,
+            height: stream.height,
+            sample_rate: stream.sample_rate.and_then(|rate| rate.parse().ok()),
+            channels: stream.channels,
+            duration: stream.duration.and_then(|duration| duration.parse().ok()),
+            attached_pic: stream.disposition.attached_pic != 0,
+        })
+        .collect();
+
+    Ok(MediaInfo {
+        container: probe.format.format_name,
+        duration: probe.format.duration.and_then(|duration| duration.parse().ok()),
+        bit_rate: probe.format.bit_rate.and_then(|rate| rate.parse().ok()),
+        streams,
+    })
+}
+
+/// Probe the container and streams of a media file using FFprobe
+pub fn probe_media(file_path: &str) -> Result<MediaInfo> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-show_format",
+        "-show_streams",
+        "-of", "json",
+        file_path
+    ])?;
+
+    parse_media_info(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Get the codec name of the first video stream (e.g. `h264`, `vp9`) using FFprobe
+pub fn get_video_codec(file_path: &str) -> Result<String> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-select_streams", "v:0",
+        "-show_entries", "stream=codec_name",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
+    let codec = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if codec.is_empty() {
+        return Err(VideoToolkitError::Other(format!("'{}' has no video stream", file_path)));
+    }
+    Ok(codec)
+}
+
+/// Get the codec name of the first audio stream (e.g. `aac`, `opus`) using FFprobe
+pub fn get_audio_codec(file_path: &str) -> Result<String> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-select_streams", "a:0",
+        "-show_entries", "stream=codec_name",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
+    let codec = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if codec.is_empty() {
+        return Err(VideoToolkitError::Other(format!("'{}' has no audio stream", file_path)));
+    }
+    Ok(codec)
+}
+
+/// Describe each stream of a media file (codec, size, pixel format, sample
+/// rate, channels and time base) as one line, using FFprobe
+///
+/// Files whose descriptions are equal can be joined by the concat demuxer.
+pub fn get_stream_parameters(file_path: &str) -> Result<Vec<String>> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-show_entries", "stream=codec_type,codec_name,width,height,pix_fmt,sample_rate,channels,time_base",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Check whether a media file has at least one audio stream using FFprobe
+pub fn has_audio_stream(file_path: &str) -> Result<bool> {
+    let output = run_command("ffprobe", &[
+        "-v", "error",
+        "-select_streams", "a",
+        "-show_entries", "stream=index",
+        "-of", "csv=p=0",
+        file_path
+    ])?;
+
+    Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+}
+
+/// Detect the visible region of a video using FFmpeg's cropdetect filter
+///
+/// Only the first `sample_seconds` of the input are analysed. Returns the last
+/// suggested crop as (x, y, width, height).
+pub fn detect_crop_region(file_path: &str, sample_seconds: u32) -> Result<(u32, u32, u32, u32)> {
+    let duration = sample_seconds.to_string();
+    let output = execute_ffmpeg(&[
+        "-t", &duration,
+        "-i", file_path,
+        "-vf", "cropdetect",
+        "-f", "null",
+        "-",
+    ])?;
+
+    lazy_static! {
+        static ref CROP_PATTERN: Regex = Regex::new(r"crop=(\d+):(\d+):(\d+):(\d+)").unwrap();
+    }
+
+    // cropdetect logs its suggestion for every frame; the last one has seen the most data
+    let log = String::from_utf8_lossy(&output.stderr);
+    let captures = CROP_PATTERN.captures_iter(&log).last()
+        .ok_or_else(|| VideoToolkitError::Other("cropdetect did not report a crop region".to_string()))?;
+
+    let value = |i: usize| captures[i].parse::<u32>().map_err(|_| VideoToolkitError::DimensionsError);
+    Ok((value(3)?, value(4)?, value(1)?, value(2)?))
+}
+
 /// Verify input file exists
 pub fn verify_input_file(file_path: &str) -> Result<()> {
     if !Path::new(file_path).exists() {
@@ -71,6 +977,271 @@ pub fn verify_input_file(file_path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Backslash-escape the given characters in a filter value
+fn escape_chars(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape text for use as a single filter option (e.g. `drawtext=text=...`)
+/// inside a filtergraph passed directly as an argument (no shell involved).
+/// Applies the option-level escaping (backslash, quote, colon) first, then the
+/// filtergraph-level escaping (backslash, quote, brackets, comma, semicolon).
+pub fn escape_filter_text(value: &str) -> String {
+    let option_level = escape_chars(value, &['\\', '\'', ':']);
+    escape_chars(&option_level, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// One filter of a filtergraph, e.g. `scale=480:-1:flags=lanczos`
+///
+/// Argument values are escaped with [`escape_filter_text`] when rendered, so
+/// user text and expressions with commas can be passed as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    name: String,
+    args: Vec<String>,
+}
+
+impl Filter {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), args: Vec::new() }
+    }
+
+    /// Add a positional argument, e.g. the width and height of `scale`
+    pub fn arg(mut self, value: impl std::fmt::Display) -> Self {
+        self.args.push(escape_filter_text(&value.to_string()));
+        self
+    }
+
+    /// Add a named option `key=value`
+    pub fn option(mut self, key: &str, value: impl std::fmt::Display) -> Self {
+        self.args.push(format!("{}={}", key, escape_filter_text(&value.to_string())));
+        self
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.args.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}={}", self.name, self.args.join(":"))
+        }
+    }
+}
+
+/// Filters applied one after another, with optional input and output pad labels
+/// (e.g. `[rev]reverse[bwd]`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterChain {
+    inputs: Vec<String>,
+    filters: Vec<Filter>,
+    outputs: Vec<String>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read from the pad labelled `label` (e.g. `0:v` or a previous chain's output)
+    pub fn input(mut self, label: &str) -> Self {
+        self.inputs.push(label.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Label an output pad of the last filter so that another chain can read it
+    pub fn output(mut self, label: &str) -> Self {
+        self.outputs.push(label.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for FilterChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let filters: Vec<String> = self.filters.iter().map(Filter::to_string).collect();
+        for label in &self.inputs {
+            write!(f, "[{}]", label)?;
+        }
+        write!(f, "{}", filters.join(","))?;
+        for label in &self.outputs {
+            write!(f, "[{}]", label)?;
+        }
+        Ok(())
+    }
+}
+
+/// Render chains as a filtergraph for `-vf` or `-filter_complex`
+pub fn filter_graph(chains: &[FilterChain]) -> String {
+    chains.iter().map(FilterChain::to_string).collect::<Vec<_>>().join(";")
+}
+
+/// FFmpeg command line built from typed parts
+///
+/// Each part lands where FFmpeg reads it: global options first, then every
+/// input preceded by its own options, then every output preceded by its own.
+/// Input options (e.g. [`seek`](Self::seek)) apply to the next [`input`](Self::input),
+/// output options to the next [`output`](Self::output).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FfmpegCommand {
+    global: Vec<String>,
+    inputs: Vec<String>,
+    input_options: Vec<String>,
+    outputs: Vec<String>,
+    output_options: Vec<String>,
+}
+
+impl FfmpegCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overwrite existing output files (`-y`)
+    pub fn overwrite(mut self) -> Self {
+        self.global.push("-y".to_string());
+        self
+    }
+
+    /// Leave the build information out of stderr (`-hide_banner`)
+    pub fn hide_banner(mut self) -> Self {
+        self.global.push("-hide_banner".to_string());
+        self
+    }
+
+    /// Option for the next input, e.g. `-f concat`
+    pub fn input_option(mut self, key: &str, value: impl std::fmt::Display) -> Self {
+        self.input_options.extend([key.to_string(), value.to_string()]);
+        self
+    }
+
+    /// Start reading the next input at `timestamp`, jumping there before decoding
+    pub fn seek(self, timestamp: &str) -> Self {
+        self.input_option("-ss", timestamp)
+    }
+
+    /// Add an input file, with the input options given since the previous one
+    pub fn input(mut self, path: impl AsRef<Path>) -> Self {
+        self.inputs.append(&mut self.input_options);
+        self.inputs.extend(["-i".to_string(), path.as_ref().to_string_lossy().into_owned()]);
+        self
+    }
+
+    /// Option for the next output, e.g. `-frames:v 1`
+    pub fn option(mut self, key: &str, value: impl std::fmt::Display) -> Self {
+        self.output_options.extend([key.to_string(), value.to_string()]);
+        self
+    }
+
+    /// Option without a value for the next output, e.g. `-shortest`
+    pub fn flag(mut self, key: &str) -> Self {
+        self.output_options.push(key.to_string());
+        self
+    }
+
+    /// Ready-made output arguments, such as those of [`get_codec_options`]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.output_options.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Keep the part of the inputs between `start` and `end` in the next output
+    ///
+    /// Unlike [`seek`](Self::seek), the inputs are decoded from the start, which
+    /// makes the cut frame-accurate when re-encoding.
+    pub fn trim(self, start: &str, end: &str) -> Self {
+        self.option("-ss", start).option("-to", end)
+    }
+
+    /// Video filter of the next output (`-vf`)
+    pub fn filter(self, filter: impl std::fmt::Display) -> Self {
+        self.option("-vf", filter)
+    }
+
+    /// Filtergraph over all inputs (`-filter_complex`), usually built with [`filter_graph`]
+    pub fn filter_complex(self, graph: impl std::fmt::Display) -> Self {
+        self.option("-filter_complex", graph)
+    }
+
+    /// Stream of the inputs or filtergraph label to write to the next output
+    pub fn map(self, spec: &str) -> Self {
+        self.option("-map", spec)
+    }
+
+    /// Codec for all streams of the next output, e.g. `copy`
+    pub fn codec(self, codec: &str) -> Self {
+        self.option("-c", codec)
+    }
+
+    pub fn codec_video(self, codec: &str) -> Self {
+        self.option("-c:v", codec)
+    }
+
+    pub fn codec_audio(self, codec: &str) -> Self {
+        self.option("-c:a", codec)
+    }
+
+    /// Add an output file, with the output options given since the previous one
+    pub fn output(mut self, path: impl AsRef<Path>) -> Self {
+        self.outputs.append(&mut self.output_options);
+        self.outputs.push(path.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// The argument list, without the program name
+    pub fn to_args(&self) -> Vec<String> {
+        [&self.global, &self.inputs, &self.input_options, &self.outputs, &self.output_options]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Run the command with [`execute_ffmpeg`]
+    pub fn execute(&self) -> Result<Output> {
+        let args = self.to_args();
+        execute_ffmpeg(&args.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// Run the command with [`execute_ffmpeg_with_timeout`]
+    pub fn execute_with_timeout(&self, timeout: Duration) -> Result<Output> {
+        let args = self.to_args();
+        execute_ffmpeg_with_timeout(&args.iter().map(String::as_str).collect::<Vec<_>>(), timeout)
+    }
+
+    /// Run the command with [`execute_ffmpeg_streaming`]
+    pub fn execute_streaming(
+        &self,
+        cancel: Option<&CancellationToken>,
+        timeout: Option<Duration>,
+        on_progress: impl FnMut(&FfmpegProgress),
+    ) -> Result<Output> {
+        let args = self.to_args();
+        execute_ffmpeg_streaming(&args.iter().map(String::as_str).collect::<Vec<_>>(), cancel, timeout, on_progress)
+    }
+}
+
+impl std::fmt::Display for FfmpegCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args = self.to_args();
+        write!(f, "{}", quote_command("ffmpeg", &args.iter().map(String::as_str).collect::<Vec<_>>()))
+    }
+}
+
 // Timestamp validation patterns
 lazy_static! {
     pub static ref TIMESTAMP_PATTERNS: Vec<Regex> = vec![
@@ -88,6 +1259,16 @@ pub fn validate_timestamp(timestamp: &str) -> bool {
     TIMESTAMP_PATTERNS.iter().any(|pattern| pattern.is_match(timestamp))
 }
 
+/// Seconds of a timestamp accepted by [`validate_timestamp`]
+pub fn timestamp_to_seconds(timestamp: &str) -> Option<f64> {
+    if !validate_timestamp(timestamp) {
+        return None;
+    }
+    timestamp
+        .split(':')
+        .try_fold(0.0, |total, part| part.parse::<f64>().ok().map(|value| total * 60.0 + value))
+}
+
 /// Validate time range format (start-end)
 pub fn validate_time_range(time_range: &str) -> Option<(String, String)> {
     let parts: Vec<&str> = time_range.split('-').collect();
@@ -163,6 +1344,32 @@ pub fn get_codec_options(format: &str) -> Vec<String> {
     }.iter().map(|s| s.to_string()).collect()
 }
 
+/// Codec options for a format, re-encoding H.264 with the hardware encoder allowed by `preference`
+pub fn get_codec_options_for(format: &str, preference: &EncoderPreference) -> Vec<String> {
+    let options = get_codec_options(format);
+    if !options.iter().any(|arg| arg == SOFTWARE_H264_ENCODER) {
+        return options;
+    }
+    replace_h264_encoder(options, select_hw_encoder(preference).as_ref())
+}
+
+fn replace_h264_encoder(options: Vec<String>, encoder: Option<&HwEncoder>) -> Vec<String> {
+    let Some(encoder) = encoder else {
+        return options;
+    };
+
+    let mut replaced = Vec::with_capacity(options.len());
+    for arg in options {
+        if arg == SOFTWARE_H264_ENCODER {
+            replaced.push(encoder.name.clone());
+            replaced.extend(encoder.quality_args());
+        } else {
+            replaced.push(arg);
+        }
+    }
+    replaced
+}
+
 /// Check if a format is supported for a specific operation
 pub fn is_format_supported_for_operation(format: &str, operation: &str) -> bool {
     match operation {
@@ -221,4 +1428,269 @@ pub fn get_supported_formats(operation: &str) -> Vec<String> {
         },
         _ => Vec::new(),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_filter_text() {
+        assert_eq!(escape_filter_text("Slice 1"), "Slice 1");
+        assert_eq!(
+            escape_filter_text("this is a 'string': may contain one, or more, special characters"),
+            "this is a \\\\\\'string\\\\\\'\\\\: may contain one\\, or more\\, special characters"
+        );
+        assert_eq!(escape_filter_text("it's 100%: done, right?"), "it\\\\\\'s 100%\\\\: done\\, right?");
+        assert_eq!(escape_filter_text("C:\\clips\\[1];x"), "C\\\\:\\\\\\\\clips\\\\\\\\\\[1\\]\\;x");
+        // Font paths with spaces and colors with an alpha need no escaping
+        assert_eq!(escape_filter_text("/usr/share/fonts/My Font.ttf"), "/usr/share/fonts/My Font.ttf");
+        assert_eq!(escape_filter_text("white@0.5"), "white@0.5");
+    }
+
+    #[test]
+    fn test_filter_graph() {
+        let drawtext = Filter::new("drawtext")
+            .option("text", "it's 100%: done, right?")
+            .option("expansion", "none")
+            .option("x", "if(gt(t,1),10,-100)");
+        assert_eq!(
+            drawtext.to_string(),
+            "drawtext=text=it\\\\\\'s 100%\\\\: done\\, right?:expansion=none:x=if(gt(t\\,1)\\,10\\,-100)"
+        );
+
+        let graph = filter_graph(&[
+            FilterChain::new().filter(Filter::new("scale").arg(480).arg(-1).option("flags", "lanczos")).output("x"),
+            FilterChain::new().input("x").input("1:v").filter(Filter::new("paletteuse")),
+        ]);
+        assert_eq!(graph, "scale=480:-1:flags=lanczos[x];[x][1:v]paletteuse");
+    }
+
+    #[test]
+    fn test_dry_run_skips_writing_commands() {
+        assert!(writes_files(&["-y", "-i", "in.mp4", "-c", "copy", "out.mp4"]));
+        assert!(!writes_files(&["-hide_banner", "-encoders"]));
+        assert!(!writes_files(&["-hide_banner", "-i", "in.mp4", "-af", "loudnorm", "-f", "null", "-"]));
+    }
+
+    #[test]
+    fn test_ffmpeg_command_order() {
+        // Options land next to the input or output they belong to, whatever the call order
+        let command = FfmpegCommand::new()
+            .seek("12.5")
+            .input("in.mp4")
+            .input_option("-stream_loop", -1)
+            .input("music.mp3")
+            .map("0:v")
+            .map("1:a")
+            .codec_video("libx264")
+            .flag("-shortest")
+            .overwrite()
+            .output("out file.mp4");
+        assert_eq!(command.to_args(), [
+            "-y", "-ss", "12.5", "-i", "in.mp4", "-stream_loop", "-1", "-i", "music.mp3",
+            "-map", "0:v", "-map", "1:a", "-c:v", "libx264", "-shortest", "out file.mp4",
+        ]);
+        assert!(command.to_string().ends_with("-shortest 'out file.mp4'"));
+
+        let two_outputs = FfmpegCommand::new()
+            .input("in.mp4")
+            .filter_complex("[0:v]split=2[a][b]")
+            .map("[a]")
+            .output("a.mp4")
+            .map("[b]")
+            .output("b.mp4");
+        assert_eq!(two_outputs.to_args(), [
+            "-i", "in.mp4", "-filter_complex", "[0:v]split=2[a][b]", "-map", "[a]", "a.mp4", "-map", "[b]", "b.mp4",
+        ]);
+    }
+
+    #[test]
+    fn test_quote_command() {
+        assert_eq!(
+            quote_command("ffmpeg", &["-y", "-i", "input.mp4", "-vf", "fps=10,scale=480:-1:flags=lanczos", "out/clip_1.gif"]),
+            "ffmpeg -y -i input.mp4 -vf fps=10,scale=480:-1:flags=lanczos out/clip_1.gif"
+        );
+        assert_eq!(
+            quote_command("ffmpeg", &["-i", "/videos/My Holiday.mp4", "-filter_complex", "[x][1:v]paletteuse", "out.gif"]),
+            "ffmpeg -i '/videos/My Holiday.mp4' -filter_complex '[x][1:v]paletteuse' out.gif"
+        );
+        assert_eq!(
+            quote_command("ffmpeg", &["-i", "it's \"here\".mp4", ""]),
+            "ffmpeg -i 'it'\\''s \"here\".mp4' ''"
+        );
+    }
+
+    #[test]
+    fn test_split_arguments() {
+        assert_eq!(
+            split_arguments("-c:v libx264  -metadata title=\"My Clip\" -vf 'scale=640:-2,fps=30'").unwrap(),
+            ["-c:v", "libx264", "-metadata", "title=My Clip", "-vf", "scale=640:-2,fps=30"]
+        );
+        assert_eq!(split_arguments("a\\ b \"say \\\"hi\\\"\" ''").unwrap(), ["a b", "say \"hi\"", ""]);
+        assert!(split_arguments("   ").unwrap().is_empty());
+        assert!(split_arguments("-metadata title=\"My Clip").is_err());
+        assert!(split_arguments("-vf 'crop").is_err());
+
+        let args = ["-i", "/videos/My Holiday.mp4", "it's \"here\".mp4", ""];
+        assert_eq!(split_arguments(&quote_command("ffmpeg", &args)).unwrap()[1..], args);
+    }
+
+    #[test]
+    fn test_command_failed_display_truncates() {
+        let long_path = "a".repeat(500);
+        let error = VideoToolkitError::command_failed(
+            quote_command("ffmpeg", &["-i", &long_path]),
+            Some(1),
+            "Invalid argument\n",
+            true,
+        );
+
+        let display = error.to_string();
+        assert!(display.starts_with("FFmpeg command failed (exit code 1):\nInvalid argument\nCommand: ffmpeg -i aaa"));
+        assert!(display.contains("truncated"));
+        assert_eq!(error.command().unwrap().len(), "ffmpeg -i ".len() + 500);
+        assert_eq!(error.full_log(), Some("Invalid argument\n"));
+    }
+
+    #[test]
+    fn test_command_failed_keeps_stderr_tail() {
+        let log: String = (1..=500).map(|i| format!("frame={}\n", i)).collect();
+        let error = VideoToolkitError::command_failed("ffmpeg -i in.mp4 out.mp4".to_string(), None, &log, false);
+
+        let VideoToolkitError::FFmpegCommandFailed { exit_code, ref stderr_tail, .. } = error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert_eq!(exit_code, None);
+        assert_eq!(stderr_tail.lines().count(), 20);
+        assert!(stderr_tail.starts_with("frame=481\n") && stderr_tail.ends_with("frame=500"));
+        assert_eq!(error.full_log(), None);
+        assert!(error.to_string().starts_with("FFmpeg command failed (killed by a signal):\nframe=481"));
+    }
+
+    #[test]
+    fn test_parse_media_info() {
+        let mp4 = r#"{
+            "streams": [
+                {
+                    "index": 0, "codec_name": "h264", "codec_type": "video", "width": 1920, "height": 1080,
+                    "r_frame_rate": "30000/1001", "avg_frame_rate": "30000/1001", "duration": "12.012000"
+                },
+                {
+                    "index": 1, "codec_name": "aac", "codec_type": "audio", "sample_rate": "48000", "channels": 2,
+                    "r_frame_rate": "0/0", "avg_frame_rate": "0/0", "duration": "12.021333"
+                }
+            ],
+            "format": {
+                "filename": "clip.mp4", "nb_streams": 2, "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+                "duration": "12.021333", "size": "4511427", "bit_rate": "3002283"
+            }
+        }"#;
+        let info = parse_media_info(mp4).unwrap();
+        assert_eq!(info.container, "mov,mp4,m4a,3gp,3g2,mj2");
+        assert_eq!(info.duration, Some(12.021333));
+        assert_eq!(info.bit_rate, Some(3002283));
+
+        let video = info.video_stream().unwrap();
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert!((video.fps.unwrap() - 29.97).abs() < 0.001);
+        assert_eq!(video.duration, Some(12.012));
+
+        let audio: Vec<_> = info.audio_streams().collect();
+        assert_eq!(audio.len(), 1);
+        assert_eq!((audio[0].codec_name.as_deref(), audio[0].sample_rate, audio[0].channels), (Some("aac"), Some(48000), Some(2)));
+        assert_eq!(audio[0].fps, None);
+
+        // Matroska has no per-stream durations; a file may have no streams at all
+        let mkv = r#"{
+            "streams": [
+                { "index": 0, "codec_name": "mjpeg", "codec_type": "video", "disposition": { "default": 0, "attached_pic": 1 } },
+                { "index": 1, "codec_name": "vp9", "codec_type": "video", "width": 640, "height": 360, "avg_frame_rate": "25/1" }
+            ],
+            "format": { "format_name": "matroska,webm", "duration": "5.000000" }
+        }"#;
+        let info = parse_media_info(mkv).unwrap();
+        assert_eq!(info.video_stream().unwrap().index, 1);
+        assert_eq!(info.video_stream().unwrap().duration, None);
+        assert_eq!(info.video_stream().unwrap().fps, Some(25.0));
+        assert_eq!(info.bit_rate, None);
+        assert_eq!(parse_media_info(r#"{"format": {"format_name": "wav"}}"#).unwrap().streams, []);
+
+        assert!(parse_media_info("Invalid data found when processing input").is_err());
+    }
+
+    #[test]
+    fn test_progress_reports() {
+        let output = "frame=48\nfps=24.00\nout_time_us=2002000\nout_time_ms=2002000\nout_time=00:00:02.002000\n\
+                      speed=1.98x\nprogress=continue\nframe=96\nout_time_us=N/A\nspeed=N/A\nprogress=end\n";
+        let mut progress = FfmpegProgress::default();
+        let mut reports = Vec::new();
+        for line in output.lines() {
+            if progress.apply_line(line) {
+                reports.push(progress.clone());
+            }
+        }
+
+        assert_eq!(reports, [
+            FfmpegProgress { out_time: 2.002, frame: Some(48), speed: Some(1.98), finished: false },
+            FfmpegProgress { out_time: 2.002, frame: Some(96), speed: None, finished: true },
+        ]);
+
+        assert_eq!(timestamp_to_seconds("01:02:03.5"), Some(3723.5));
+        assert_eq!(timestamp_to_seconds("90"), Some(90.0));
+        assert_eq!(timestamp_to_seconds("1:2"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_streaming_run_limits() {
+        let mut reports = Vec::new();
+        let script = "printf 'out_time_us=1500000\\nprogress=end\\n'; echo done >&2";
+        let output = run_streaming("sh", &["-c", script], None, None, |report| reports.push(report.clone())).unwrap();
+        assert_eq!(reports, [FfmpegProgress { out_time: 1.5, finished: true, ..FfmpegProgress::default() }]);
+        assert_eq!(output.stderr, b"done");
+
+        // A hung command is killed once its time is up
+        let started = std::time::Instant::now();
+        let timeout = Duration::from_millis(200);
+        let result = run_streaming("sleep", &["30"], None, Some(Deadline::after(timeout)), |_| {});
+        assert!(matches!(result, Err(VideoToolkitError::Timeout(limit)) if limit == timeout));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let result = run_streaming("sleep", &["30"], Some(&cancel), None, |_| {});
+        assert!(matches!(result, Err(VideoToolkitError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_hw_encoder_selection() {
+        let encoders = "Encoders:\n V..... = Video\n ------\n V....D libx264              libx264 H.264 (codec h264)\n \
+            V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)\n \
+            V....D hevc_nvenc           NVIDIA NVENC hevc encoder (codec hevc)\n \
+            V....D h264_vaapi           H.264/AVC (VAAPI) (codec h264)\n \
+            V....D h264_qsv             H.264 (Intel Quick Sync Video acceleration) (codec h264)\n \
+            A....D aac                  AAC (Advanced Audio Coding)\n";
+        let available = parse_hw_encoders(encoders, "Hardware acceleration methods:\nvdpau\ncuda\nvaapi\n");
+        let names: Vec<&str> = available.iter().map(|encoder| encoder.name.as_str()).collect();
+        assert_eq!(names, ["h264_nvenc", "hevc_nvenc"]);
+
+        let pick = |preference: &str| choose_hw_encoder(&preference.parse().unwrap(), &available).map(|e| e.name);
+        assert_eq!(pick("auto").as_deref(), Some("h264_nvenc"));
+        assert_eq!(pick("hevc_nvenc").as_deref(), Some("hevc_nvenc"));
+        assert_eq!(pick("h264_qsv"), None);
+        assert_eq!(pick("off"), None);
+        assert!("h264 nvenc".parse::<EncoderPreference>().is_err());
+        assert_eq!("Auto".parse::<EncoderPreference>().unwrap().to_string(), "auto");
+
+        let options = replace_h264_encoder(get_codec_options("mkv"), available.first());
+        assert_eq!(options[..2], ["-c:v", "h264_nvenc"]);
+        assert_eq!(video_encoder_in(&options).as_deref(), Some("h264_nvenc"));
+        assert!(options.ends_with(&["-c:a".to_string(), "aac".to_string()]));
+        assert_eq!(video_encode_args(None).join(" "), "-c:v libx264 -preset medium -crf 22");
+    }
+}
@@ -1,3 +1,5 @@
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 use std::fmt;
 use std::str::FromStr;
@@ -15,6 +17,9 @@ pub enum FormatError {
 
     #[error("Invalid format for operation: {0}")]
     InvalidFormatForOperation(String),
+
+    #[error("Extension says {extension} but the content is {content}")]
+    FormatMismatch { extension: FormatType, content: FormatType },
 }
 
 /// Video container formats supported by the toolkit
@@ -317,6 +322,103 @@ pub fn detect_format(path: &Path) -> Option<FormatType> {
     None
 }
 
+/// Number of leading bytes read to recognise a file's format
+const MAGIC_LEN: usize = 512;
+
+/// Detect the format of a file from its first bytes, whatever its extension
+///
+/// Returns `None` when the file cannot be read or its signature is not recognised.
+pub fn detect_format_by_magic(path: &Path) -> Option<FormatType> {
+    let mut header = Vec::with_capacity(MAGIC_LEN);
+    File::open(path).ok()?.take(MAGIC_LEN as u64).read_to_end(&mut header).ok()?;
+    format_from_magic(&header)
+}
+
+/// Match a file header against the known signatures
+fn format_from_magic(header: &[u8]) -> Option<FormatType> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+    let contains = |needle: &[u8]| header.windows(needle.len()).any(|window| window == needle);
+
+    let format = if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        FormatType::Image(ImageFormat::GIF)
+    } else if at(0, b"\x89PNG\r\n\x1a\n") {
+        FormatType::Image(ImageFormat::PNG)
+    } else if at(0, b"\xff\xd8\xff") {
+        FormatType::Image(ImageFormat::JPG)
+    } else if at(0, b"II*\0") || at(0, b"MM\0*") {
+        FormatType::Image(ImageFormat::TIFF)
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        FormatType::Image(ImageFormat::WEBP)
+    } else if at(0, b"RIFF") && at(8, b"AVI ") {
+        FormatType::Video(VideoFormat::AVI)
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        FormatType::Audio(AudioFormat::WAV)
+    } else if at(4, b"ftyp") {
+        // ISO base media files name their flavour in the major brand
+        match header.get(8..12)? {
+            b"qt  " => FormatType::Video(VideoFormat::MOV),
+            b"M4V " | b"M4VH" | b"M4VP" => FormatType::Video(VideoFormat::M4V),
+            b"M4A " | b"M4B " => FormatType::Audio(AudioFormat::M4A),
+            _ => FormatType::Video(VideoFormat::MP4),
+        }
+    } else if at(0, b"\x1a\x45\xdf\xa3") {
+        // Matroska and WebM share the EBML header and differ in the doctype
+        if contains(b"webm") {
+            FormatType::Video(VideoFormat::WebM)
+        } else {
+            FormatType::Video(VideoFormat::MKV)
+        }
+    } else if at(0, b"FLV\x01") {
+        FormatType::Video(VideoFormat::FLV)
+    } else if at(0, b"\x30\x26\xb2\x75\x8e\x66\xcf\x11") {
+        // ASF carries both WMV and WMA; the video flavour is the common case
+        FormatType::Video(VideoFormat::WMV)
+    } else if at(0, b"\0\0\x01\xba") || at(0, b"\0\0\x01\xb3") {
+        FormatType::Video(VideoFormat::MPEG)
+    } else if at(0, b"\x47") && at(188, b"\x47") {
+        FormatType::Video(VideoFormat::TS)
+    } else if at(0, b"OggS") {
+        if contains(b"theora") {
+            FormatType::Video(VideoFormat::OGV)
+        } else {
+            FormatType::Audio(AudioFormat::OGG)
+        }
+    } else if at(0, b"fLaC") {
+        FormatType::Audio(AudioFormat::FLAC)
+    } else if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+        FormatType::Audio(AudioFormat::AIFF)
+    } else if at(0, b"ID3") {
+        FormatType::Audio(AudioFormat::MP3)
+    } else if at(0, b"BM") && header.len() >= 26 {
+        FormatType::Image(ImageFormat::BMP)
+    } else {
+        // Raw MPEG audio streams start with a frame sync; layer 0 marks ADTS AAC
+        match header {
+            [0xff, second, ..] if second & 0xf6 == 0xf0 => FormatType::Audio(AudioFormat::AAC),
+            [0xff, second, ..] if second & 0xe0 == 0xe0 && second & 0x06 != 0 => FormatType::Audio(AudioFormat::MP3),
+            _ => return None,
+        }
+    };
+
+    Some(format)
+}
+
+/// Detect the format of a file from its content, checking it against the extension
+///
+/// The content decides when both are known; formats that share a container
+/// (e.g. MP4 and MOV, MKV and WebM) are not reported as a mismatch. Falls back
+/// to the extension when the signature is not recognised.
+pub fn detect_format_strict(path: &Path) -> Result<Option<FormatType>, FormatError> {
+    let by_extension = detect_format(path);
+    match (by_extension, detect_format_by_magic(path)) {
+        (Some(extension), Some(content)) if extension.container() != content.container() => {
+            Err(FormatError::FormatMismatch { extension, content })
+        },
+        (_, Some(content)) => Ok(Some(content)),
+        (extension, None) => Ok(extension),
+    }
+}
+
 /// Enum representing all supported format types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FormatType {
@@ -343,6 +445,19 @@ impl FormatType {
             FormatType::Image(format) => format.mime_type(),
         }
     }
+
+    /// Container family shared by formats that differ only in their brand
+    fn container(&self) -> &str {
+        match self {
+            FormatType::Video(VideoFormat::MP4 | VideoFormat::MOV | VideoFormat::M4V) |
+            FormatType::Audio(AudioFormat::M4A) => "isobmff",
+            FormatType::Video(VideoFormat::MKV | VideoFormat::WebM) => "matroska",
+            FormatType::Video(VideoFormat::MPEG | VideoFormat::VOB) => "mpeg-ps",
+            FormatType::Video(VideoFormat::OGV) | FormatType::Audio(AudioFormat::OGG) => "ogg",
+            FormatType::Video(VideoFormat::WMV) | FormatType::Audio(AudioFormat::WMA) => "asf",
+            other => other.extension(),
+        }
+    }
 }
 
 impl fmt::Display for FormatType {
@@ -353,4 +468,58 @@ impl fmt::Display for FormatType {
             FormatType::Image(format) => write!(f, "Image ({})", format),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_magic() {
+        let mut avi = b"RIFF\0\0\0\0AVI LIST".to_vec();
+        avi.resize(64, 0);
+        assert_eq!(format_from_magic(&avi), Some(FormatType::Video(VideoFormat::AVI)));
+        assert_eq!(format_from_magic(b"\0\0\0\x20ftypisom\0\0\x02\0"), Some(FormatType::Video(VideoFormat::MP4)));
+        assert_eq!(format_from_magic(b"\0\0\0\x14ftypqt  \0\0\0\0"), Some(FormatType::Video(VideoFormat::MOV)));
+        assert_eq!(
+            format_from_magic(b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84webm"),
+            Some(FormatType::Video(VideoFormat::WebM))
+        );
+        assert_eq!(format_from_magic(b"\x1a\x45\xdf\xa3\x42\x82\x88matroska"), Some(FormatType::Video(VideoFormat::MKV)));
+        assert_eq!(format_from_magic(b"GIF89a\x01\0\x01\0"), Some(FormatType::Image(ImageFormat::GIF)));
+        assert_eq!(format_from_magic(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some(FormatType::Image(ImageFormat::PNG)));
+        assert_eq!(format_from_magic(b"ID3\x04\0"), Some(FormatType::Audio(AudioFormat::MP3)));
+        assert_eq!(format_from_magic(b"\xff\xf1\x50\x80"), Some(FormatType::Audio(AudioFormat::AAC)));
+        assert_eq!(format_from_magic(b"plain text"), None);
+        assert_eq!(format_from_magic(b""), None);
+    }
+
+    #[test]
+    fn test_detect_format_strict() {
+        let dir = std::env::temp_dir().join(format!("vtk_formats_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+
+        // A GIF saved as .mp4 is caught, a MOV saved as .mp4 shares the container
+        let renamed_gif = write("clip.mp4", b"GIF89a\x01\0\x01\0");
+        assert_eq!(detect_format(&renamed_gif), Some(FormatType::Video(VideoFormat::MP4)));
+        assert_eq!(detect_format_by_magic(&renamed_gif), Some(FormatType::Image(ImageFormat::GIF)));
+        assert!(matches!(detect_format_strict(&renamed_gif), Err(FormatError::FormatMismatch { .. })));
+
+        let mov = write("phone.mp4", b"\0\0\0\x14ftypqt  \0\0\0\0");
+        assert_eq!(detect_format_strict(&mov).unwrap(), Some(FormatType::Video(VideoFormat::MOV)));
+
+        // Content without a known signature leaves the extension in charge
+        let unknown = write("notes.wav", b"not really audio");
+        assert_eq!(detect_format_strict(&unknown).unwrap(), Some(FormatType::Audio(AudioFormat::WAV)));
+
+        let no_extension = write("download", b"GIF87a\x01\0\x01\0");
+        assert_eq!(detect_format_strict(&no_extension).unwrap(), Some(FormatType::Image(ImageFormat::GIF)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
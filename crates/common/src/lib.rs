@@ -1,7 +1,13 @@
+pub mod cancel;
 pub mod ffmpeg;
 pub mod error;
 pub mod formats;  // New module for format handling
+pub mod logging;
+pub mod warning;
 
+pub use cancel::*;
 pub use ffmpeg::*;
 pub use error::*;
-pub use formats::*;
\ No newline at end of file
+pub use formats::*;
+pub use logging::*;
+pub use warning::*;
\ No newline at end of file
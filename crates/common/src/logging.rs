@@ -0,0 +1,129 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use directories::ProjectDirs;
+use log::{Log, Metadata, Record};
+
+pub use log::LevelFilter;
+
+use crate::error::{Result, VideoToolkitError};
+
+/// Number of session logs kept by [`init_session_log`]
+pub const SESSION_LOGS_KEPT: usize = 10;
+
+const SESSION_LOG_PREFIX: &str = "session-";
+
+/// Writes log records as timestamped lines to a file
+struct FileLogger {
+    file: Mutex<File>,
+    level: LevelFilter,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut file = self.file.lock().unwrap();
+        // A full disk should not bring the operation down with it
+        let _ = writeln!(
+            file,
+            "{} {:<5} {}: {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().flush();
+    }
+}
+
+/// Append log records up to `level` to `path` for the rest of the process
+///
+/// FFmpeg runs log their command, duration and exit status at info level and
+/// their stderr at debug level. Fails if a logger was already set.
+pub fn init_log_file(path: &Path, level: LevelFilter) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(VideoToolkitError::IoError)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(VideoToolkitError::IoError)?;
+
+    log::set_boxed_logger(Box::new(FileLogger { file: Mutex::new(file), level }))
+        .map_err(|_| VideoToolkitError::Other("A logger is already set".to_string()))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Directory of the automatic session logs, under the user's data directory
+pub fn session_log_dir() -> Option<PathBuf> {
+    ProjectDirs::from("com", "video-toolkit", "VideoToolKit").map(|dirs| dirs.data_dir().join("logs"))
+}
+
+/// Log to a new file in `dir` named after the current time, removing all but
+/// the newest `keep` session logs
+///
+/// Returns the path of the new log.
+pub fn init_session_log(dir: &Path, keep: usize, level: LevelFilter) -> Result<PathBuf> {
+    let path = dir.join(format!("{}{}.log", SESSION_LOG_PREFIX, Local::now().format("%Y%m%d-%H%M%S")));
+    init_log_file(&path, level)?;
+    prune_session_logs(dir, keep).map_err(VideoToolkitError::IoError)?;
+    Ok(path)
+}
+
+/// Remove the oldest session logs in `dir` until `keep` are left
+fn prune_session_logs(dir: &Path, keep: usize) -> io::Result<()> {
+    let mut logs: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(SESSION_LOG_PREFIX) && name.ends_with(".log"))
+        })
+        .collect();
+
+    // The timestamps in the names sort oldest first
+    logs.sort();
+    let excess = logs.len().saturating_sub(keep);
+    for old in &logs[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_session_logs() {
+        let dir = std::env::temp_dir().join(format!("vtk_logging_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["session-20240101-090000.log", "session-20240102-090000.log", "session-20240103-090000.log", "notes.log"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        prune_session_logs(&dir, 2).unwrap();
+        assert!(!dir.join("session-20240101-090000.log").exists());
+        assert!(dir.join("session-20240102-090000.log").exists());
+        assert!(dir.join("session-20240103-090000.log").exists());
+        assert!(dir.join("notes.log").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Stable identifier of a warning, so scripts can filter on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningCode {
+    /// A time range could not be parsed and was left out
+    RangeSkipped,
+    /// Slice labels were dropped because the video stream is copied
+    LabelsDisabled,
+    /// gifsicle post-optimization was requested but is not installed
+    GifsicleMissing,
+    /// gifsicle ran but failed; the unoptimized GIF was kept
+    GifsicleFailed,
+    /// The video dimensions could not be probed; the default GIF width was used
+    WidthDefaulted,
+    /// The video duration could not be probed; the first frame became the poster
+    PosterTimeDefaulted,
+}
+
+impl WarningCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::RangeSkipped => "W_RANGE_SKIPPED",
+            WarningCode::LabelsDisabled => "W_LABELS_DISABLED",
+            WarningCode::GifsicleMissing => "W_GIFSICLE_MISSING",
+            WarningCode::GifsicleFailed => "W_GIFSICLE_FAILED",
+            WarningCode::WidthDefaulted => "W_WIDTH_DEFAULTED",
+            WarningCode::PosterTimeDefaulted => "W_POSTER_TIME_DEFAULTED",
+        }
+    }
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A non-fatal problem met during an operation, reported once it has finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    /// File the warning is about, if any
+    pub path: Option<PathBuf>,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{} {}: {}", self.code, path.display(), self.message),
+            None => write!(f, "{} {}", self.code, self.message),
+        }
+    }
+}
+
+/// Render warnings as a "Warnings (N)" section, or `None` when there are none
+pub fn format_warnings(warnings: &[Warning]) -> Option<String> {
+    if warnings.is_empty() {
+        return None;
+    }
+
+    let mut section = format!("Warnings ({}):", warnings.len());
+    for warning in warnings {
+        section.push_str(&format!("\n  {}", warning));
+    }
+    Some(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_warnings() {
+        assert_eq!(format_warnings(&[]), None);
+
+        let warnings = [
+            Warning::new(WarningCode::RangeSkipped, "invalid time range '1-x'"),
+            Warning::new(WarningCode::GifsicleMissing, "gifsicle not found in PATH").with_path("out/clip.gif"),
+        ];
+        assert_eq!(
+            format_warnings(&warnings).unwrap(),
+            "Warnings (2):\n  W_RANGE_SKIPPED invalid time range '1-x'\n  W_GIFSICLE_MISSING out/clip.gif: gifsicle not found in PATH"
+        );
+    }
+}
@@ -1,12 +1,388 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::fmt;
 use std::cmp;
+use std::str::FromStr;
+use std::process::Command;
+use std::sync::OnceLock;
 
 use common::{
-    execute_ffmpeg, get_video_dimensions, verify_input_file, get_file_size_mb,
-    Result, VideoToolkitError
+    cancellation_scope, get_video_dimensions, get_duration, verify_input_file, get_file_size_mb, detect_crop_region,
+    list_encoders, encoder_listed, filter_graph, is_dry_run, output_written, quote_command, record_dry_run,
+    validate_timestamp, CancellationToken, FfmpegCommand, Filter, FilterChain, Result, VideoToolkitError, Warning,
+    WarningCode
 };
 
+/// Number of seconds sampled by cropdetect in automatic crop mode
+const AUTO_CROP_SAMPLE_SECONDS: u32 = 5;
+
+/// Region of the source video to keep before scaling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CropMode {
+    /// Explicit rectangle in source pixels
+    Region { x: u32, y: u32, width: u32, height: u32 },
+    /// Detect the visible area with FFmpeg's cropdetect over the first seconds
+    Auto,
+}
+
+impl FromStr for CropMode {
+    type Err = VideoToolkitError;
+
+    /// Parse either `auto` or `x,y,w,h`
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim().eq_ignore_ascii_case("auto") {
+            return Ok(CropMode::Auto);
+        }
+
+        let values: Vec<u32> = s.split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| VideoToolkitError::Other(format!("Invalid crop '{}', expected x,y,w,h or auto", s)))?;
+
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(CropMode::Region { x, y, width, height }),
+            _ => Err(VideoToolkitError::Other(format!("Invalid crop '{}', expected x,y,w,h or auto", s))),
+        }
+    }
+}
+
+/// Optional settings for GIF conversion
+#[derive(Debug, Clone, Default)]
+pub struct GifOptions {
+    pub crop: Option<CropMode>,
+    /// Play forward then backward; doubles the duration and buffers the clip in memory
+    pub boomerang: bool,
+    /// Number of times the GIF plays: 0 = forever, 1 = once, N = N times (default: forever)
+    pub loop_count: Option<u32>,
+    /// Run gifsicle over the result before checking the size limit
+    pub gifsicle: Option<GifsicleSettings>,
+    /// Also write a PNG poster frame at the GIF's width to `<output_stem>.png`
+    pub write_poster: bool,
+    /// Timestamp of the poster frame (default: middle of the video)
+    pub poster_time: Option<String>,
+    /// Stops the conversion and kills the running FFmpeg when cancelled
+    pub cancel: Option<CancellationToken>,
+}
+
+/// Settings for the optional gifsicle post-optimization pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GifsicleSettings {
+    /// Optimization level passed as `-O1` to `-O3`
+    pub level: u8,
+    /// Lossy compression factor (`--lossy=N`); higher is smaller but noisier
+    pub lossy: Option<u32>,
+}
+
+impl Default for GifsicleSettings {
+    fn default() -> Self {
+        Self { level: 3, lossy: None }
+    }
+}
+
+/// Check if gifsicle is installed and accessible
+pub fn check_gifsicle() -> bool {
+    Command::new("gifsicle")
+        .arg("--version")
+        .output()
+        .map(|_| true)
+        .unwrap_or(false)
+}
+
+/// Build the gifsicle arguments that optimize `input_file` into `output_file`
+fn build_gifsicle_args(settings: &GifsicleSettings, input_file: &str, output_file: &str) -> Vec<String> {
+    let mut args = vec![format!("-O{}", settings.level.clamp(1, 3))];
+    if let Some(lossy) = settings.lossy {
+        args.push(format!("--lossy={}", lossy));
+    }
+    args.extend([input_file.to_string(), "-o".to_string(), output_file.to_string()]);
+    args
+}
+
+/// Optimize a GIF in place with gifsicle
+///
+/// This pass is best-effort: when gifsicle is missing or fails, a warning is
+/// returned and the FFmpeg output is kept unchanged.
+fn run_gifsicle(gif_file: &str, settings: &GifsicleSettings) -> Option<Warning> {
+    if !check_gifsicle() {
+        return Some(
+            Warning::new(WarningCode::GifsicleMissing, "gifsicle not found in PATH; skipped post-optimization")
+                .with_path(gif_file),
+        );
+    }
+
+    let optimized_file = format!("{}.gifsicle.gif", gif_file);
+    let args = build_gifsicle_args(settings, gif_file, &optimized_file);
+    if is_dry_run() {
+        record_dry_run(quote_command("gifsicle", &args.iter().map(String::as_str).collect::<Vec<_>>()));
+        return None;
+    }
+
+    let failure = match Command::new("gifsicle").args(&args).output() {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(format!("gifsicle failed: {}", String::from_utf8_lossy(&output.stderr).trim())),
+        Err(e) => Some(format!("could not run gifsicle: {}", e)),
+    };
+
+    // Only keep the gifsicle output when it actually saved space
+    let before = get_file_size_mb(Path::new(gif_file));
+    let after = get_file_size_mb(Path::new(&optimized_file));
+    if failure.is_none() && after > 0.0 && after < before && fs::rename(&optimized_file, gif_file).is_ok() {
+        println!("gifsicle reduced size from {:.2}MB to {:.2}MB", before, after);
+    } else {
+        let _ = fs::remove_file(&optimized_file);
+    }
+
+    failure.map(|message| Warning::new(WarningCode::GifsicleFailed, message).with_path(gif_file))
+}
+
+/// Outcome of a GIF conversion
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionReport {
+    pub output_path: PathBuf,
+    pub size_mb: f64,
+    pub width: u32,
+    pub fps: u32,
+    /// Number of conversions run to produce this output (more than one when optimizing)
+    pub attempts: u32,
+    pub under_limit: bool,
+    /// Poster frame written alongside the GIF
+    pub poster: Option<PathBuf>,
+    /// Non-fatal problems met during the conversion
+    pub warnings: Vec<Warning>,
+}
+
+impl fmt::Display for ConversionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({:.2}MB, {}px wide at {}fps, {} attempt{})",
+            self.output_path.display(),
+            self.size_mb,
+            self.width,
+            self.fps,
+            self.attempts,
+            if self.attempts == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Outcome of a GIF to WebP conversion
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebpReport {
+    pub output_path: PathBuf,
+    pub gif_size_mb: f64,
+    pub webp_size_mb: f64,
+}
+
+impl WebpReport {
+    /// Share of the GIF's size saved by the WebP, in percent (negative when it grew)
+    pub fn saving_percent(&self) -> f64 {
+        if self.gif_size_mb > 0.0 { (1.0 - self.webp_size_mb / self.gif_size_mb) * 100.0 } else { 0.0 }
+    }
+
+    /// Whether the WebP is smaller than the GIF
+    pub fn is_smaller(&self) -> bool {
+        self.webp_size_mb < self.gif_size_mb
+    }
+}
+
+impl fmt::Display for WebpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (GIF {:.2}MB -> WebP {:.2}MB, {:+.1}% saved)",
+            self.output_path.display(),
+            self.gif_size_mb,
+            self.webp_size_mb,
+            self.saving_percent()
+        )
+    }
+}
+
+/// Resolve the crop mode to a concrete (x, y, width, height) rectangle inside the frame
+fn resolve_crop(input_file: &str, crop: CropMode) -> Result<(u32, u32, u32, u32)> {
+    let (x, y, width, height) = match crop {
+        CropMode::Region { x, y, width, height } => (x, y, width, height),
+        CropMode::Auto => detect_crop_region(input_file, AUTO_CROP_SAMPLE_SECONDS)?,
+    };
+
+    let (frame_width, frame_height) = get_video_dimensions(input_file)?;
+    check_crop_region((x, y, width, height), frame_width, frame_height)?;
+    Ok((x, y, width, height))
+}
+
+/// Refuse a crop region reaching past the frame
+fn check_crop_region((x, y, width, height): (u32, u32, u32, u32), frame_width: u32, frame_height: u32) -> Result<()> {
+    if x.checked_add(width).is_none_or(|right| right > frame_width)
+        || y.checked_add(height).is_none_or(|bottom| bottom > frame_height)
+    {
+        return Err(VideoToolkitError::InvalidCropRegion(
+            format!("{}x{}+{}+{}", width, height, x, y),
+            frame_width,
+            frame_height,
+        ));
+    }
+    Ok(())
+}
+
+/// Filters that crop the source, if requested, and scale it to `width`, with `fps` in between
+fn crop_and_scale(width: u32, crop: Option<(u32, u32, u32, u32)>, fps: Option<u32>) -> FilterChain {
+    let mut chain = FilterChain::new();
+    if let Some((x, y, w, h)) = crop {
+        chain = chain.filter(Filter::new("crop").arg(w).arg(h).arg(x).arg(y));
+    }
+    if let Some(fps) = fps {
+        chain = chain.filter(Filter::new("fps").arg(fps));
+    }
+    chain.filter(Filter::new("scale").arg(width).arg(-1).option("flags", "lanczos"))
+}
+
+/// Build the filter chain shared by palette generation and palette use
+fn build_base_filter(fps: u32, width: u32, crop: Option<(u32, u32, u32, u32)>, boomerang: bool) -> String {
+    let chain = crop_and_scale(width, crop, Some(fps));
+
+    if !boomerang {
+        return filter_graph(&[chain]);
+    }
+
+    // Reverse after scaling so the buffered frames are as small as possible
+    filter_graph(&[
+        chain.filter(Filter::new("split")).output("fwd").output("rev"),
+        FilterChain::new().input("rev").filter(Filter::new("reverse")).output("bwd"),
+        FilterChain::new()
+            .input("fwd")
+            .input("bwd")
+            .filter(Filter::new("concat").option("n", 2).option("v", 1).option("a", 0)),
+    ])
+}
+
+/// Width of the GIF when none is given: the crop or video width, capped at 480px
+fn resolve_width(
+    input_file: &str,
+    width: Option<u32>,
+    crop: Option<(u32, u32, u32, u32)>,
+    warnings: &mut Vec<Warning>,
+) -> u32 {
+    match (width, crop) {
+        (Some(w), _) => w,
+        (None, Some((_, _, crop_width, _))) => cmp::min(480, crop_width),
+        (None, None) => match get_video_dimensions(input_file) {
+            Ok((orig_width, _)) => cmp::min(480, orig_width),
+            Err(_) => {
+                warnings.push(
+                    Warning::new(WarningCode::WidthDefaulted, "could not determine video dimensions; used 480px")
+                        .with_path(input_file),
+                );
+                480
+            }
+        },
+    }
+}
+
+/// Path of the poster frame for a GIF: `<output_stem>.png` next to it
+pub fn poster_path(output_file: &str) -> PathBuf {
+    Path::new(output_file).with_extension("png")
+}
+
+/// Build the filter for the poster frame, matching the GIF's crop and width
+fn build_poster_filter(width: u32, crop: Option<(u32, u32, u32, u32)>) -> String {
+    crop_and_scale(width, crop, None).to_string()
+}
+
+/// Build the FFmpeg command extracting a single poster frame
+fn build_poster_command(input_file: &str, timestamp: &str, filter: &str, output_file: &Path) -> FfmpegCommand {
+    FfmpegCommand::new()
+        .overwrite()
+        .seek(timestamp)
+        .input(input_file)
+        .filter(filter)
+        .option("-frames:v", 1)
+        .output(output_file)
+}
+
+/// Timestamp of the poster frame: the given time or the middle of the video
+fn poster_timestamp(input_file: &str, poster_time: Option<&str>, warnings: &mut Vec<Warning>) -> String {
+    match poster_time {
+        Some(time) => time.to_string(),
+        None => match get_duration(input_file) {
+            Ok(duration) => format!("{:.3}", duration / 2.0),
+            Err(_) => {
+                warnings.push(
+                    Warning::new(
+                        WarningCode::PosterTimeDefaulted,
+                        "could not determine video duration; used the first frame as poster",
+                    )
+                    .with_path(input_file),
+                );
+                "0".to_string()
+            }
+        },
+    }
+}
+
+/// Extract the poster frame for a converted GIF
+fn write_poster(
+    input_file: &str,
+    output_file: &str,
+    width: u32,
+    crop: Option<(u32, u32, u32, u32)>,
+    poster_time: Option<&str>,
+    warnings: &mut Vec<Warning>,
+) -> Result<PathBuf> {
+    let timestamp = poster_timestamp(input_file, poster_time, warnings);
+
+    let poster = poster_path(output_file);
+    build_poster_command(input_file, &timestamp, &build_poster_filter(width, crop), &poster).execute()?;
+
+    if !output_written(&poster) {
+        return Err(VideoToolkitError::OutputFileNotCreated);
+    }
+
+    Ok(poster)
+}
+
+/// Map a play count to the GIF muxer's `-loop` value
+///
+/// The NETSCAPE extension stores the number of *repeats*, so playing once means
+/// no loop at all (`-1`) and playing N times means N - 1 repeats.
+fn gif_loop_value(loop_count: u32) -> String {
+    match loop_count {
+        0 => "0".to_string(),
+        1 => "-1".to_string(),
+        n => (n - 1).to_string(),
+    }
+}
+
+/// Build the FFmpeg command for the palette generation pass
+fn build_palette_command(input_file: &str, base_filter: &str, palette_file: &str) -> FfmpegCommand {
+    FfmpegCommand::new()
+        .overwrite()
+        .input(input_file)
+        .filter(format!("{},palettegen", base_filter))
+        .output(palette_file)
+}
+
+/// Build the FFmpeg command for the palette-use conversion pass
+fn build_convert_command(
+    input_file: &str,
+    palette_file: &str,
+    filter_complex: &str,
+    output_file: &str,
+    loop_count: Option<u32>,
+) -> FfmpegCommand {
+    let mut command = FfmpegCommand::new()
+        .overwrite()
+        .input(input_file)
+        .input(palette_file)
+        .filter_complex(filter_complex);
+
+    if let Some(count) = loop_count {
+        command = command.option("-loop", gif_loop_value(count));
+    }
+
+    command.output(output_file)
+}
+
 /// Convert any video format (MP4, WebM, etc.) to GIF using FFmpeg with size optimization
 pub fn convert_video_to_gif(
     input_file: &str,
@@ -15,23 +391,39 @@ pub fn convert_video_to_gif(
     fps: u32,
     max_size_mb: f64,
 ) -> Result<bool> {
+    convert_video_to_gif_with_options(input_file, output_file, width, fps, max_size_mb, &GifOptions::default())
+        .map(|report| report.under_limit)
+}
+
+/// Convert a video to GIF with additional conversion options
+pub fn convert_video_to_gif_with_options(
+    input_file: &str,
+    output_file: &str,
+    width: Option<u32>,
+    fps: u32,
+    max_size_mb: f64,
+    options: &GifOptions,
+) -> Result<ConversionReport> {
     // Check if input file exists
     verify_input_file(input_file)?;
+    let _cancel_scope = cancellation_scope(options.cancel.as_ref());
 
-    // Determine width if not provided
-    let width = match width {
-        Some(w) => w,
-        None => {
-            match get_video_dimensions(input_file) {
-                Ok((orig_width, _)) => cmp::min(480, orig_width),
-                Err(_) => {
-                    eprintln!("Warning: Could not determine video dimensions. Using default width of 480px.");
-                    480
-                }
-            }
+    if let Some(time) = options.poster_time.as_deref() {
+        if !validate_timestamp(time) {
+            return Err(VideoToolkitError::InvalidTimestamp(time.to_string()));
         }
+    }
+
+    // Validate the crop region before doing any work
+    let crop = match options.crop {
+        Some(mode) => Some(resolve_crop(input_file, mode)?),
+        None => None,
     };
 
+    let mut warnings = Vec::new();
+    let width = resolve_width(input_file, width, crop, &mut warnings);
+    let base_filter = build_base_filter(fps, width, crop, options.boomerang);
+
     // Create output directory if it doesn't exist
     if let Some(parent) = Path::new(output_file).parent() {
         if !parent.as_os_str().is_empty() {
@@ -43,55 +435,63 @@ pub fn convert_video_to_gif(
     let palette_file = format!("{}.png", output_file);
 
     // Calculate palette first (improved quality)
-    let palette_filter = format!("fps={},scale={}:-1:flags=lanczos,palettegen", fps, width);
-    let palette_args = vec![
-        "-y",
-        "-i", input_file,
-        "-vf", &palette_filter,
-        &palette_file,
-    ];
-
-    if let Err(e) = execute_ffmpeg(&palette_args) {
+    if let Err(e) = build_palette_command(input_file, &base_filter, &palette_file).execute() {
         // Clean up palette file if it exists
         let _ = fs::remove_file(&palette_file);
         return Err(e);
     }
 
     // Convert using the palette
-    let filter_complex = format!("fps={},scale={}:-1:flags=lanczos[x];[x][1:v]paletteuse", fps, width);
-    let convert_args = vec![
-        "-y",
-        "-i", input_file,
-        "-i", &palette_file,
-        "-filter_complex", &filter_complex,
-        output_file,
-    ];
-
-    let conversion_result = execute_ffmpeg(&convert_args);
+    let filter_complex = format!("{}[x];[x][1:v]paletteuse", base_filter);
+    let conversion_result =
+        build_convert_command(input_file, &palette_file, &filter_complex, output_file, options.loop_count).execute();
 
     // Clean up palette file
     let _ = fs::remove_file(&palette_file);
 
     // Check if the conversion was successful
     if let Err(e) = conversion_result {
+        if e.is_interruption() {
+            let _ = fs::remove_file(output_file);
+        }
         return Err(e);
     }
 
     // Check if the output file exists and is under size limit
     let output_path = Path::new(output_file);
-    if output_path.exists() {
-        let size_mb = get_file_size_mb(output_path);
-        if size_mb <= max_size_mb {
-            println!("Conversion successful! Output size: {:.2}MB", size_mb);
-            return Ok(true);
-        } else {
-            println!("Output file exceeds size limit ({:.2}MB > {:.2}MB).", size_mb, max_size_mb);
-            println!("Consider reducing width or FPS for smaller file size.");
-            return Ok(false);
-        }
-    } else {
+    if !output_written(output_path) {
         return Err(VideoToolkitError::OutputFileNotCreated);
     }
+
+    if let Some(settings) = &options.gifsicle {
+        warnings.extend(run_gifsicle(output_file, settings));
+    }
+
+    let poster = if options.write_poster {
+        Some(write_poster(input_file, output_file, width, crop, options.poster_time.as_deref(), &mut warnings)?)
+    } else {
+        None
+    };
+
+    let size_mb = get_file_size_mb(output_path);
+    let under_limit = size_mb <= max_size_mb;
+    if under_limit {
+        println!("Conversion successful! Output size: {:.2}MB", size_mb);
+    } else {
+        println!("Output file exceeds size limit ({:.2}MB > {:.2}MB).", size_mb, max_size_mb);
+        println!("Consider reducing width or FPS for smaller file size.");
+    }
+
+    Ok(ConversionReport {
+        output_path: output_path.to_path_buf(),
+        size_mb,
+        width,
+        fps,
+        attempts: 1,
+        under_limit,
+        poster,
+        warnings,
+    })
 }
 
 /// Iteratively attempt conversion with decreasing quality until size requirements are met
@@ -101,23 +501,70 @@ pub fn optimize_conversion(
     max_size_mb: f64,
     initial_width: Option<u32>,
 ) -> Result<bool> {
+    optimize_conversion_with_options(input_file, output_file, max_size_mb, initial_width, &GifOptions::default())
+        .map(|report| report.under_limit)
+}
+
+/// Iteratively attempt conversion with additional conversion options
+pub fn optimize_conversion_with_options(
+    input_file: &str,
+    output_file: &str,
+    max_size_mb: f64,
+    initial_width: Option<u32>,
+    options: &GifOptions,
+) -> Result<ConversionReport> {
+    verify_input_file(input_file)?;
+    let _cancel_scope = cancellation_scope(options.cancel.as_ref());
+
+    // Detect an automatic crop once instead of in every attempt
+    let crop = match options.crop {
+        Some(mode) => Some(resolve_crop(input_file, mode)?),
+        None => None,
+    };
+    let region = crop.map(|(x, y, width, height)| CropMode::Region { x, y, width, height });
+
+    // The poster is written once, for the attempt that is kept
+    let attempt_options = GifOptions { crop: region, write_poster: false, ..options.clone() };
+    let report = optimize_attempts(input_file, output_file, max_size_mb, initial_width, &attempt_options)?;
+
+    if !options.write_poster {
+        return Ok(report);
+    }
+
+    let mut warnings = report.warnings;
+    let poster = write_poster(input_file, output_file, report.width, crop, options.poster_time.as_deref(), &mut warnings)?;
+
+    Ok(ConversionReport { poster: Some(poster), warnings, ..report })
+}
+
+/// Run conversions with decreasing width and FPS until one fits the size limit
+fn optimize_attempts(
+    input_file: &str,
+    output_file: &str,
+    max_size_mb: f64,
+    initial_width: Option<u32>,
+    options: &GifOptions,
+) -> Result<ConversionReport> {
     // Try with different quality settings
     let width_options = vec![initial_width.unwrap_or(480), 360, 320, 240, 160];
     let fps_options = vec![10, 8, 5];
+    let mut attempts = 0;
 
     for width in width_options {
         for &fps in &fps_options {
             println!("Attempting conversion with width={}px, fps={}...", width, fps);
+            attempts += 1;
 
-            match convert_video_to_gif(input_file, output_file, Some(width), fps, max_size_mb) {
-                Ok(true) => return Ok(true),
-                Ok(false) => {
+            match convert_video_to_gif_with_options(input_file, output_file, Some(width), fps, max_size_mb, options) {
+                Ok(report) if report.under_limit => return Ok(ConversionReport { attempts, ..report }),
+                Ok(_) => {
                     // If file exists but is too large, remove it before the next attempt
                     let output_path = Path::new(output_file);
                     if output_path.exists() && get_file_size_mb(output_path) > max_size_mb {
                         let _ = fs::remove_file(output_path);
                     }
                 }
+                Err(e) if e.is_interruption() => return Err(e),
                 Err(e) => {
                     eprintln!("Error during conversion attempt: {}", e);
                     // Continue to the next attempt
@@ -130,7 +577,82 @@ pub fn optimize_conversion(
     println!("Could not achieve target file size with any optimization settings.");
 
     // As a last resort, try with the lowest settings
-    convert_video_to_gif(input_file, output_file, Some(120), 3, max_size_mb)
+    let report = convert_video_to_gif_with_options(input_file, output_file, Some(120), 3, max_size_mb, options)?;
+    Ok(ConversionReport { attempts: attempts + 1, ..report })
+}
+
+/// FFmpeg's list of encoders, read once per process
+///
+/// A failed read is not kept, so the next call tries again.
+fn cached_encoders() -> Result<&'static str> {
+    static ENCODERS: OnceLock<String> = OnceLock::new();
+
+    if let Some(encoders) = ENCODERS.get() {
+        return Ok(encoders);
+    }
+    let encoders = list_encoders()?;
+    Ok(ENCODERS.get_or_init(|| encoders))
+}
+
+/// Fail early when FFmpeg was built without the libwebp encoder
+fn ensure_webp_encoder(encoders: &str) -> Result<()> {
+    if encoder_listed(encoders, "libwebp") {
+        Ok(())
+    } else {
+        Err(VideoToolkitError::EncoderNotAvailable("libwebp".to_string()))
+    }
+}
+
+/// Build the FFmpeg command for an alpha-preserving GIF to animated WebP conversion
+fn build_webp_command(input_file: &str, output_file: &str, quality: u32, lossless: bool) -> FfmpegCommand {
+    let command = FfmpegCommand::new()
+        .overwrite()
+        .input(input_file)
+        .codec_video("libwebp")
+        .option("-loop", 0);
+
+    let command = if lossless {
+        command.option("-lossless", 1)
+    } else {
+        // Lossy WebP needs an explicit alpha-capable pixel format to keep transparency
+        command.option("-quality", cmp::min(quality, 100)).option("-pix_fmt", "yuva420p")
+    };
+
+    command.output(output_file)
+}
+
+/// Convert an animated GIF to an animated WebP, preserving transparency
+///
+/// The report compares the sizes of the GIF and the WebP.
+pub fn convert_gif_to_webp(
+    input_file: &str,
+    output_file: &str,
+    quality: u32,
+    lossless: bool,
+) -> Result<WebpReport> {
+    verify_input_file(input_file)?;
+
+    // Create output directory if it doesn't exist
+    if let Some(parent) = Path::new(output_file).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    ensure_webp_encoder(cached_encoders()?)?;
+
+    build_webp_command(input_file, output_file, quality, lossless).execute()?;
+
+    let output_path = Path::new(output_file);
+    if !output_written(output_path) {
+        return Err(VideoToolkitError::OutputFileNotCreated);
+    }
+
+    Ok(WebpReport {
+        output_path: output_path.to_path_buf(),
+        gif_size_mb: get_file_size_mb(Path::new(input_file)),
+        webp_size_mb: get_file_size_mb(output_path),
+    })
 }
 
 // For backward compatibility - will be deprecated
@@ -143,4 +665,150 @@ pub fn convert_mp4_to_gif(
     max_size_mb: f64,
 ) -> Result<bool> {
     convert_video_to_gif(input_file, output_file, width, fps, max_size_mb)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_crop_mode() {
+        assert_eq!("auto".parse::<CropMode>().unwrap(), CropMode::Auto);
+        assert_eq!(
+            "10, 20, 600, 400".parse::<CropMode>().unwrap(),
+            CropMode::Region { x: 10, y: 20, width: 600, height: 400 }
+        );
+        assert!("10,20,600".parse::<CropMode>().is_err());
+        assert!("0,0,0,400".parse::<CropMode>().is_err());
+    }
+
+    #[test]
+    fn test_webp_args() {
+        let lossy = build_webp_command("in.gif", "out.webp", 75, false).to_args();
+        assert_eq!(
+            lossy,
+            ["-y", "-i", "in.gif", "-c:v", "libwebp", "-loop", "0",
+             "-quality", "75", "-pix_fmt", "yuva420p", "out.webp"]
+        );
+
+        let lossless = build_webp_command("in.gif", "out.webp", 75, true).to_args();
+        assert!(lossless.windows(2).any(|w| w == ["-lossless", "1"]));
+        assert!(!lossless.contains(&"-quality".to_string()));
+    }
+
+    #[test]
+    fn test_webp_report() {
+        let report = WebpReport { output_path: PathBuf::from("out.webp"), gif_size_mb: 2.0, webp_size_mb: 0.5 };
+        assert!(report.is_smaller());
+        assert_eq!(report.to_string(), "out.webp (GIF 2.00MB -> WebP 0.50MB, +75.0% saved)");
+
+        let larger = WebpReport { webp_size_mb: 3.0, ..report };
+        assert!(!larger.is_smaller());
+        assert_eq!(larger.saving_percent(), -50.0);
+    }
+
+    #[test]
+    fn test_webp_encoder_missing() {
+        let with_webp = " V....D libwebp              libwebp WebP image (codec webp)\n";
+        let without_webp = " V....D gif                  GIF (Graphics Interchange Format)\n";
+
+        assert!(ensure_webp_encoder(with_webp).is_ok());
+        assert!(matches!(
+            ensure_webp_encoder(without_webp),
+            Err(VideoToolkitError::EncoderNotAvailable(ref name)) if name == "libwebp"
+        ));
+    }
+
+    #[test]
+    fn test_poster_args_and_naming() {
+        assert_eq!(poster_path("out/clip.gif"), PathBuf::from("out/clip.png"));
+        assert_eq!(poster_path("clip.v2.gif"), PathBuf::from("clip.v2.png"));
+
+        let filter = build_poster_filter(480, Some((10, 20, 600, 400)));
+        assert_eq!(filter, "crop=600:400:10:20,scale=480:-1:flags=lanczos");
+        assert_eq!(build_poster_filter(320, None), "scale=320:-1:flags=lanczos");
+
+        let args = build_poster_command("in.mp4", "12.500", &filter, Path::new("out/clip.png")).to_args();
+        assert_eq!(
+            args,
+            ["-y", "-ss", "12.500", "-i", "in.mp4", "-vf", "crop=600:400:10:20,scale=480:-1:flags=lanczos",
+             "-frames:v", "1", "out/clip.png"]
+        );
+    }
+
+    #[test]
+    fn test_crop_region_bounds() {
+        assert!(check_crop_region((0, 0, 1920, 1080), 1920, 1080).is_ok());
+        assert!(check_crop_region((1, 0, 1920, 1080), 1920, 1080).is_err());
+        assert!(check_crop_region((u32::MAX, 0, 2, 10), 1920, 1080).is_err());
+        assert!(check_crop_region((0, 2, 10, u32::MAX), 1920, 1080).is_err());
+    }
+
+    #[test]
+    fn test_crop_precedes_scale() {
+        assert_eq!(build_base_filter(10, 480, None, false), "fps=10,scale=480:-1:flags=lanczos");
+        assert_eq!(
+            build_base_filter(10, 480, Some((10, 20, 600, 400)), false),
+            "crop=600:400:10:20,fps=10,scale=480:-1:flags=lanczos"
+        );
+    }
+
+    #[test]
+    fn test_boomerang_follows_scale() {
+        assert_eq!(
+            build_base_filter(8, 320, Some((0, 0, 640, 360)), true),
+            "crop=640:360:0:0,fps=8,scale=320:-1:flags=lanczos,\
+             split[fwd][rev];[rev]reverse[bwd];[fwd][bwd]concat=n=2:v=1:a=0"
+        );
+    }
+
+    #[test]
+    fn test_palette_command() {
+        assert_eq!(
+            build_palette_command("in.mp4", "fps=10,scale=480:-1:flags=lanczos", "out.gif.png").to_args(),
+            ["-y", "-i", "in.mp4", "-vf", "fps=10,scale=480:-1:flags=lanczos,palettegen", "out.gif.png"]
+        );
+    }
+
+    #[test]
+    fn test_loop_count_args() {
+        let args = |loop_count| {
+            build_convert_command("in.mp4", "out.gif.png", "paletteuse", "out.gif", loop_count).to_args()
+        };
+
+        assert!(!args(None).contains(&"-loop".to_string()));
+        assert_eq!(args(Some(0))[7..], ["-loop", "0", "out.gif"]);
+        assert_eq!(args(Some(1))[7..], ["-loop", "-1", "out.gif"]);
+        assert_eq!(args(Some(3))[7..], ["-loop", "2", "out.gif"]);
+    }
+
+    #[test]
+    fn test_gifsicle_args() {
+        let settings = GifsicleSettings { level: 3, lossy: Some(80) };
+        assert_eq!(
+            build_gifsicle_args(&settings, "out.gif", "out.gif.gifsicle.gif"),
+            ["-O3", "--lossy=80", "out.gif", "-o", "out.gif.gifsicle.gif"]
+        );
+
+        let settings = GifsicleSettings { level: 7, lossy: None };
+        assert_eq!(build_gifsicle_args(&settings, "a.gif", "b.gif"), ["-O3", "a.gif", "-o", "b.gif"]);
+    }
+
+    #[test]
+    fn test_probe_fallback_warnings() {
+        let missing = "does_not_exist.mp4";
+        let mut warnings = Vec::new();
+
+        assert_eq!(resolve_width(missing, Some(320), None, &mut warnings), 320);
+        assert_eq!(resolve_width(missing, None, Some((0, 0, 300, 200)), &mut warnings), 300);
+        assert_eq!(poster_timestamp(missing, Some("00:00:02"), &mut warnings), "00:00:02");
+        assert!(warnings.is_empty());
+
+        assert_eq!(resolve_width(missing, None, None, &mut warnings), 480);
+        assert_eq!(poster_timestamp(missing, None, &mut warnings), "0");
+
+        let codes: Vec<_> = warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, [WarningCode::WidthDefaulted, WarningCode::PosterTimeDefaulted]);
+        assert!(warnings.iter().all(|w| w.path.as_deref() == Some(Path::new(missing))));
+    }
+}
@@ -0,0 +1,214 @@
+//! Minimal walker over the GIF block structure, enough to rewrite the
+//! Graphic Control Extension of every frame without decoding image data.
+
+use common::{Result, VideoToolkitError};
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+
+/// Header and logical screen descriptor
+const SCREEN_DESCRIPTOR_END: usize = 13;
+
+/// Transparent color flag in the Graphic Control Extension packed field
+const TRANSPARENT_FLAG: u8 = 0x01;
+
+fn invalid(message: &str) -> VideoToolkitError {
+    VideoToolkitError::Other(format!("Invalid GIF structure: {}", message))
+}
+
+/// Size in bytes of a color table announced by a packed field, if any
+fn color_table_size(packed: u8) -> usize {
+    if packed & 0x80 != 0 {
+        3 << ((packed & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Copy `len` bytes at `pos` to the output, advancing `pos`
+fn copy(data: &[u8], pos: &mut usize, len: usize, out: &mut Vec<u8>) -> Result<()> {
+    let bytes = data.get(*pos..*pos + len).ok_or_else(|| invalid("unexpected end of file"))?;
+    out.extend_from_slice(bytes);
+    *pos += len;
+    Ok(())
+}
+
+/// Copy a chain of data sub-blocks up to and including the zero-length terminator
+fn copy_sub_blocks(data: &[u8], pos: &mut usize, out: &mut Vec<u8>) -> Result<()> {
+    loop {
+        let size = *data.get(*pos).ok_or_else(|| invalid("unterminated data sub-blocks"))? as usize;
+        copy(data, pos, size + 1, out)?;
+        if size == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Graphic Control Extension marking `index` as transparent
+fn graphic_control(packed: u8, delay: [u8; 2], index: u8) -> [u8; 8] {
+    [
+        EXTENSION_INTRODUCER, GRAPHIC_CONTROL_LABEL, 0x04,
+        packed | TRANSPARENT_FLAG, delay[0], delay[1], index,
+        0x00,
+    ]
+}
+
+/// Rewrite a GIF so that every frame treats the background color index of the
+/// logical screen descriptor as transparent
+///
+/// Existing Graphic Control Extensions keep their disposal method and delay;
+/// frames without one get a new extension. Anything after the trailer is dropped.
+pub(crate) fn set_transparent_background(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < SCREEN_DESCRIPTOR_END || !(data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")) {
+        return Err(invalid("missing GIF header"));
+    }
+
+    // Graphic Control Extensions require the 89a version
+    let mut out = Vec::with_capacity(data.len() + 64);
+    out.extend_from_slice(b"GIF89a");
+
+    let background_index = data[11];
+    let mut pos = 6;
+    let screen_descriptor_len = SCREEN_DESCRIPTOR_END - 6 + color_table_size(data[10]);
+    copy(data, &mut pos, screen_descriptor_len, &mut out)?;
+
+    // Whether the current frame already has its Graphic Control Extension
+    let mut has_graphic_control = false;
+
+    loop {
+        match data.get(pos).copied() {
+            Some(EXTENSION_INTRODUCER) => {
+                let label = *data.get(pos + 1).ok_or_else(|| invalid("truncated extension"))?;
+
+                if label == GRAPHIC_CONTROL_LABEL {
+                    let block = data.get(pos..pos + 8).ok_or_else(|| invalid("truncated graphic control extension"))?;
+                    if block[2] != 0x04 || block[7] != 0x00 {
+                        return Err(invalid("malformed graphic control extension"));
+                    }
+
+                    out.extend_from_slice(&graphic_control(block[3], [block[4], block[5]], background_index));
+                    pos += 8;
+                    has_graphic_control = true;
+                } else {
+                    copy(data, &mut pos, 2, &mut out)?;
+                    copy_sub_blocks(data, &mut pos, &mut out)?;
+                }
+            },
+            Some(IMAGE_SEPARATOR) => {
+                if !has_graphic_control {
+                    out.extend_from_slice(&graphic_control(0x00, [0, 0], background_index));
+                }
+                has_graphic_control = false;
+
+                let packed = *data.get(pos + 9).ok_or_else(|| invalid("truncated image descriptor"))?;
+                // Image descriptor, local color table and LZW minimum code size
+                copy(data, &mut pos, 10 + color_table_size(packed) + 1, &mut out)?;
+                copy_sub_blocks(data, &mut pos, &mut out)?;
+            },
+            Some(TRAILER) => {
+                out.push(TRAILER);
+                return Ok(out);
+            },
+            Some(other) => return Err(invalid(&format!("unexpected block 0x{:02X}", other))),
+            None => return Err(invalid("missing trailer")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x1 single-frame GIF87a without a Graphic Control Extension, background index 1
+    const STILL: &[u8] = &[
+        b'G', b'I', b'F', b'8', b'7', b'a',
+        0x01, 0x00, 0x01, 0x00, 0x80, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF,
+        0x2C, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+        0x02, 0x02, 0x44, 0x01, 0x00,
+        0x3B,
+    ];
+
+    /// Two-frame looping GIF: the first frame has a non-transparent Graphic
+    /// Control Extension, the second has none but a comment and a local color table
+    const ANIMATED: &[u8] = &[
+        b'G', b'I', b'F', b'8', b'9', b'a',
+        0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF,
+        0x21, 0xFF, 0x0B, b'N', b'E', b'T', b'S', b'C', b'A', b'P', b'E', b'2', b'.', b'0',
+        0x03, 0x01, 0x00, 0x00, 0x00,
+        0x21, 0xF9, 0x04, 0x04, 0x0A, 0x00, 0x00, 0x00,
+        0x2C, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+        0x02, 0x02, 0x44, 0x01, 0x00,
+        0x21, 0xFE, 0x03, b'h', b'i', b'!', 0x00,
+        0x2C, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x80,
+        0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00,
+        0x02, 0x02, 0x44, 0x01, 0x00,
+        0x3B,
+    ];
+
+    /// (packed, delay, transparent index) of the graphic control extension before each frame
+    fn frame_controls(data: &[u8]) -> Vec<(u8, u16, u8)> {
+        let mut controls = Vec::new();
+        let mut pending = None;
+        let mut pos = SCREEN_DESCRIPTOR_END + color_table_size(data[10]);
+        let mut sink = Vec::new();
+
+        loop {
+            match data[pos] {
+                EXTENSION_INTRODUCER if data[pos + 1] == GRAPHIC_CONTROL_LABEL => {
+                    pending = Some((data[pos + 3], u16::from_le_bytes([data[pos + 4], data[pos + 5]]), data[pos + 6]));
+                    pos += 8;
+                },
+                EXTENSION_INTRODUCER => {
+                    pos += 2;
+                    copy_sub_blocks(data, &mut pos, &mut sink).unwrap();
+                },
+                IMAGE_SEPARATOR => {
+                    controls.push(pending.take().expect("frame without graphic control extension"));
+                    pos += 10 + color_table_size(data[pos + 9]) + 1;
+                    copy_sub_blocks(data, &mut pos, &mut sink).unwrap();
+                },
+                TRAILER => return controls,
+                other => panic!("unexpected block 0x{:02X}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_still_gif_gets_graphic_control() {
+        let rewritten = set_transparent_background(STILL).unwrap();
+
+        assert!(rewritten.starts_with(b"GIF89a"));
+        assert_eq!(rewritten.len(), STILL.len() + 8);
+        assert_eq!(frame_controls(&rewritten), [(0x01, 0, 1)]);
+        assert_eq!(set_transparent_background(&rewritten).unwrap(), rewritten);
+    }
+
+    #[test]
+    fn test_animated_gif_round_trip() {
+        let rewritten = set_transparent_background(ANIMATED).unwrap();
+
+        // Disposal and delay of the existing extension are kept
+        assert_eq!(frame_controls(&rewritten), [(0x05, 10, 0), (0x01, 0, 0)]);
+        assert_eq!(set_transparent_background(&rewritten).unwrap(), rewritten);
+
+        // Image data, comments and the loop extension are untouched
+        assert!(rewritten.windows(6).any(|w| w == [0x21, 0xFE, 0x03, b'h', b'i', b'!']));
+        assert!(rewritten.windows(11).any(|w| w == b"NETSCAPE2.0"));
+        assert_eq!(rewritten.len(), ANIMATED.len() + 8);
+    }
+
+    #[test]
+    fn test_invalid_structure() {
+        // The legacy trailer hack leaves a dangling extension introducer
+        let mut legacy = STILL.to_vec();
+        *legacy.last_mut().unwrap() = 0x21;
+        assert!(set_transparent_background(&legacy).is_err());
+
+        assert!(set_transparent_background(&STILL[..STILL.len() - 3]).is_err());
+        assert!(set_transparent_background(b"\x89PNG\r\n\x1a\n\0\0\0\0\0").is_err());
+    }
+}
@@ -1,27 +1,262 @@
 use std::path::{Path, PathBuf};
 use std::fs::{self, File};
 use std::io::{Read, Write, Seek, SeekFrom};
+use std::collections::HashSet;
+use std::sync::Mutex;
 
 use walkdir::WalkDir;
 use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 use indicatif::{ProgressBar, ProgressStyle};
 
 use common::{Result, VideoToolkitError};
 
+mod blocks;
+
+/// How GIFs are made transparent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransparencyMode {
+    /// Mark the background color as transparent in every frame's Graphic Control Extension
+    #[default]
+    GraphicControl,
+    /// Change the trailer byte from 0x3B to 0x21; relies on lenient decoders and
+    /// renders as a broken file in some viewers
+    LegacyTrailer,
+}
+
+/// Additional options for making GIFs transparent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransparencyOptions {
+    pub mode: TransparencyMode,
+    /// Modify a copy and rename it over the original, so an interrupted run never
+    /// leaves a corrupted GIF; disable for a faster in-place edit of huge files
+    pub atomic: bool,
+}
+
+impl Default for TransparencyOptions {
+    fn default() -> Self {
+        Self {
+            mode: TransparencyMode::default(),
+            atomic: true,
+        }
+    }
+}
+
+/// How a batch of GIFs is run
+#[derive(Default)]
+pub struct BatchGifOptions {
+    /// Called with (processed, total) after each file; replaces the terminal progress bar
+    pub progress: Option<Box<dyn Fn(usize, usize) + Send + Sync>>,
+    /// Maximum number of files processed at once; `None` uses the global thread pool
+    pub max_parallel: Option<usize>,
+    /// Write transparent copies into this directory instead of modifying the inputs
+    pub output_dir: Option<PathBuf>,
+    /// Also pick up GIFs without a `.gif` extension, recognised by their header
+    pub by_content: bool,
+}
+
+impl BatchGifOptions {
+    /// Set a progress callback function
+    pub fn with_progress_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Limit the number of files processed at once
+    pub fn with_max_parallel(mut self, max_parallel: usize) -> Self {
+        self.max_parallel = Some(max_parallel);
+        self
+    }
+
+    /// Write transparent copies into a directory instead of modifying the inputs
+    pub fn with_output_dir<P: AsRef<Path>>(mut self, output_dir: P) -> Self {
+        self.output_dir = Some(output_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Recognise GIFs by their header as well as by their extension
+    pub fn with_content_detection(mut self, by_content: bool) -> Self {
+        self.by_content = by_content;
+        self
+    }
+}
+
+/// Why a file with a `.gif` extension was not processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file does not start with a GIF header (e.g. an MP4 renamed to .gif)
+    WrongMagic,
+    /// The file is empty
+    Empty,
+    /// The file could not be opened or read
+    Unreadable(String),
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::WrongMagic => write!(f, "wrong magic bytes, not a GIF"),
+            SkipReason::Empty => write!(f, "zero length"),
+            SkipReason::Unreadable(error) => write!(f, "unreadable: {}", error),
+        }
+    }
+}
+
+/// A file left out of a batch transparency run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Checks that a file starts with the GIF magic number (GIF87a or GIF89a)
+fn check_gif_header(path: &Path) -> std::result::Result<(), SkipReason> {
+    let file = File::open(path).map_err(|e| SkipReason::Unreadable(e.to_string()))?;
+    let mut buffer = Vec::with_capacity(6);
+    file.take(6)
+        .read_to_end(&mut buffer)
+        .map_err(|e| SkipReason::Unreadable(e.to_string()))?;
+
+    if buffer.is_empty() {
+        Err(SkipReason::Empty)
+    } else if buffer.starts_with(b"GIF87a") || buffer.starts_with(b"GIF89a") {
+        Ok(())
+    } else {
+        Err(SkipReason::WrongMagic)
+    }
+}
+
 /// Checks if a file is a GIF by verifying its magic number
 fn is_gif_file(path: &Path) -> bool {
-    if let Ok(mut file) = File::open(path) {
-        let mut buffer = [0; 6];
-        if file.read_exact(&mut buffer).is_ok() {
-            // Check for the GIF magic number (GIF87a or GIF89a)
-            return buffer.starts_with(b"GIF87a") || buffer.starts_with(b"GIF89a");
+    check_gif_header(path).is_ok()
+}
+
+/// What processing would do to a file, as found by a dry run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GifStatus {
+    /// Ends with the regular 0x3B trailer and would be made transparent
+    WouldModify,
+    /// Already ends with 0x21
+    AlreadyTransparent,
+    /// Has a `.gif` extension but no GIF header
+    NotAGif,
+    /// Ends with a byte that is neither 0x3B nor 0x21
+    UnexpectedTrailer(u8),
+}
+
+impl std::fmt::Display for GifStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GifStatus::WouldModify => write!(f, "would modify"),
+            GifStatus::AlreadyTransparent => write!(f, "already transparent"),
+            GifStatus::NotAGif => write!(f, "not a GIF"),
+            GifStatus::UnexpectedTrailer(byte) => write!(f, "unexpected trailer byte 0x{:02X}", byte),
         }
     }
-    false
 }
 
-/// Converts the final byte of a GIF file from 0x3B to 0x21 to create transparency
-pub fn make_gif_transparent(file_path: &Path) -> Result<()> {
+/// Per-file result of a batch transparency run
+#[derive(Debug, Clone)]
+pub struct GifReportEntry {
+    pub path: PathBuf,
+    /// Classification before any change was made
+    pub status: GifStatus,
+    /// Set when modifying the file failed
+    pub error: Option<String>,
+    /// Transparent copy written in output-directory mode
+    pub output: Option<PathBuf>,
+}
+
+/// Report of a batch transparency run
+#[derive(Debug, Clone, Default)]
+pub struct TransparencyReport {
+    /// No file was written
+    pub dry_run: bool,
+    pub entries: Vec<GifReportEntry>,
+    /// Files with a `.gif` extension that are not GIFs
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl TransparencyReport {
+    /// Number of valid GIF files among the inputs
+    pub fn gif_count(&self) -> usize {
+        self.entries.iter().filter(|e| e.status != GifStatus::NotAGif).count()
+    }
+
+    /// Number of GIF files that are (or, in a dry run, would end up) transparent
+    pub fn success_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, GifStatus::WouldModify | GifStatus::AlreadyTransparent) && e.error.is_none())
+            .count()
+    }
+
+    /// Number of entries with the given status
+    pub fn count(&self, status: GifStatus) -> usize {
+        self.entries.iter().filter(|e| e.status == status).count()
+    }
+
+    /// One-line breakdown of the entries by status
+    pub fn breakdown(&self) -> String {
+        let unexpected = self.entries
+            .iter()
+            .filter(|e| matches!(e.status, GifStatus::UnexpectedTrailer(_)))
+            .count();
+        let modified = if self.dry_run { "would be modified" } else { "modified" };
+
+        format!(
+            "{} {}, {} already transparent, {} with an unexpected trailer, {} skipped (not a GIF)",
+            self.count(GifStatus::WouldModify),
+            modified,
+            self.count(GifStatus::AlreadyTransparent),
+            unexpected,
+            self.skipped.len() + self.count(GifStatus::NotAGif)
+        )
+    }
+}
+
+/// Classifies a file without modifying it
+pub fn classify_gif(file_path: &Path) -> Result<GifStatus> {
+    classify_gif_with_mode(file_path, TransparencyMode::default())
+}
+
+/// Classifies a file for the given transparency mode without modifying it
+///
+/// In Graphic Control mode a file whose block structure cannot be parsed (such
+/// as one modified by the legacy mode) is reported by its final byte.
+pub fn classify_gif_with_mode(file_path: &Path, mode: TransparencyMode) -> Result<GifStatus> {
+    if !is_gif_file(file_path) {
+        return Ok(GifStatus::NotAGif);
+    }
+
+    if mode == TransparencyMode::GraphicControl {
+        let data = fs::read(file_path)?;
+        return Ok(match blocks::set_transparent_background(&data) {
+            Ok(rewritten) if rewritten == data => GifStatus::AlreadyTransparent,
+            Ok(_) => GifStatus::WouldModify,
+            Err(_) => GifStatus::UnexpectedTrailer(data.last().copied().unwrap_or(0)),
+        });
+    }
+
+    let mut file = File::open(file_path)?;
+    file.seek(SeekFrom::End(-1))?;
+    let mut last_byte = [0; 1];
+    file.read_exact(&mut last_byte)?;
+
+    Ok(match last_byte[0] {
+        0x3B => GifStatus::WouldModify,
+        0x21 => GifStatus::AlreadyTransparent,
+        other => GifStatus::UnexpectedTrailer(other),
+    })
+}
+
+/// Opens a GIF for in-place editing and reads its final byte
+///
+/// Returns the open file, the position of the final byte and its value.
+fn open_gif_trailer(file_path: &Path) -> Result<(File, u64, u8)> {
     // Open the file for reading and writing
     let mut file = fs::OpenOptions::new()
         .read(true)
@@ -59,8 +294,95 @@ pub fn make_gif_transparent(file_path: &Path) -> Result<()> {
     file.read_exact(&mut last_byte)
         .map_err(|e| VideoToolkitError::IoError(e))?;
 
+    Ok((file, last_position, last_byte[0]))
+}
+
+/// Makes the background of a GIF transparent
+pub fn make_gif_transparent(file_path: &Path) -> Result<()> {
+    make_gif_transparent_with_options(file_path, &TransparencyOptions::default())
+}
+
+/// Makes the background of a GIF transparent with additional options
+pub fn make_gif_transparent_with_options(file_path: &Path, options: &TransparencyOptions) -> Result<()> {
+    match options.mode {
+        TransparencyMode::GraphicControl => set_graphic_control_transparency(file_path, options.atomic),
+        TransparencyMode::LegacyTrailer => set_trailer_transparency(file_path, options.atomic),
+    }
+}
+
+/// Replaces a file by one written next to it
+///
+/// `write_temp` creates the temporary file; it is then synced to disk, given the
+/// permissions and modification time of the original and renamed over it. The
+/// temporary file is removed if any step fails.
+fn replace_atomically(file_path: &Path, write_temp: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let temp_path = with_name_suffix(file_path, ".tmp");
+
+    let result = write_temp(&temp_path).and_then(|_| {
+        let metadata = fs::metadata(file_path)?;
+        let temp = fs::OpenOptions::new().write(true).open(&temp_path)?;
+        if let Ok(modified) = metadata.modified() {
+            // Best effort, not every filesystem supports it
+            let _ = temp.set_modified(modified);
+        }
+        temp.sync_all()?;
+        drop(temp);
+
+        fs::set_permissions(&temp_path, metadata.permissions())?;
+        fs::rename(&temp_path, file_path)?;
+        Ok(())
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Rewrites the Graphic Control Extensions so the background color is transparent
+fn set_graphic_control_transparency(file_path: &Path, atomic: bool) -> Result<()> {
+    let data = fs::read(file_path)?;
+    let rewritten = blocks::set_transparent_background(&data).map_err(|e| {
+        VideoToolkitError::Other(format!("{}: {}", file_path.display(), e))
+    })?;
+
+    if rewritten == data {
+        // Already transparent
+        return Ok(());
+    }
+
+    if atomic {
+        replace_atomically(file_path, |temp_path| Ok(fs::write(temp_path, &rewritten)?))
+    } else {
+        Ok(fs::write(file_path, &rewritten)?)
+    }
+}
+
+/// Converts the final byte of a GIF file from 0x3B to 0x21 (legacy mode)
+fn set_trailer_transparency(file_path: &Path, atomic: bool) -> Result<()> {
+    if !atomic {
+        return patch_trailer(file_path);
+    }
+
+    // Only copy files that actually change
+    match classify_gif_with_mode(file_path, TransparencyMode::LegacyTrailer)? {
+        GifStatus::WouldModify => replace_atomically(file_path, |temp_path| {
+            // Copy the contents only, the original may be read-only
+            std::io::copy(&mut File::open(file_path)?, &mut File::create(temp_path)?)?;
+            patch_trailer(temp_path)
+        }),
+        GifStatus::AlreadyTransparent => Ok(()),
+        // Fails with the same errors as the in-place edit, before anything is written
+        GifStatus::NotAGif | GifStatus::UnexpectedTrailer(_) => patch_trailer(file_path),
+    }
+}
+
+/// Changes the final byte of a GIF file from 0x3B to 0x21 in place
+fn patch_trailer(file_path: &Path) -> Result<()> {
+    let (mut file, last_position, last_byte) = open_gif_trailer(file_path)?;
+
     // Check if the last byte is 0x3B (GIF trailer)
-    if last_byte[0] == 0x3B {
+    if last_byte == 0x3B {
         // Move back to the last byte position
         file.seek(SeekFrom::Start(last_position))
             .map_err(|e| VideoToolkitError::IoError(e))?;
@@ -70,24 +392,146 @@ pub fn make_gif_transparent(file_path: &Path) -> Result<()> {
             .map_err(|e| VideoToolkitError::IoError(e))?;
 
         return Ok(());
-    } else if last_byte[0] == 0x21 {
+    } else if last_byte == 0x21 {
         // Already transparent
         return Ok(());
     } else {
         return Err(VideoToolkitError::Other(format!(
             "Unexpected GIF trailer byte: 0x{:02X}",
-            last_byte[0]
+            last_byte
         )));
     }
 }
 
-/// Process multiple GIF files in batch, making them transparent
-pub fn batch_process_gifs(
-    input_paths: &[PathBuf],
-    recursive: bool,
-    create_backup: bool,
-) -> Result<(usize, usize)> {
-    // Collect all GIF files
+/// Undoes the legacy trailer mode by changing a final 0x21 back to the 0x3B trailer
+///
+/// Files made transparent in Graphic Control mode can only be restored from a backup.
+///
+/// Returns `false` when the file already ends with the regular trailer.
+pub fn restore_gif(file_path: &Path) -> Result<bool> {
+    let (mut file, last_position, last_byte) = open_gif_trailer(file_path)?;
+
+    match last_byte {
+        0x21 => {
+            file.seek(SeekFrom::Start(last_position))?;
+            file.write_all(&[0x3B])?;
+            Ok(true)
+        },
+        0x3B => Ok(false),
+        other => Err(VideoToolkitError::Other(format!(
+            "Unexpected GIF trailer byte: 0x{:02X}",
+            other
+        ))),
+    }
+}
+
+/// `path` with `suffix` appended to its full file name, e.g. `clip.dat` + `.bak`
+///
+/// Files found by content keep their own extension in the name, so `clip.gif`
+/// and `clip.dat` next to each other do not share a temporary file or backup.
+fn with_name_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Moves the `<name>.gif.bak` backup of a GIF back over it
+///
+/// Returns `false` when there is no backup next to the file.
+pub fn restore_from_backup(file_path: &Path) -> Result<bool> {
+    let backup = backup_path(file_path, None);
+    if !backup.is_file() {
+        return Ok(false);
+    }
+
+    fs::rename(&backup, file_path)?;
+    Ok(true)
+}
+
+/// Path of the backup copy for a GIF: its file name with `.bak` appended
+/// (`<name>.gif.bak`) next to it, or the original file name inside
+/// `backup_dir` when one is given
+///
+/// GIFs of the same name from different directories share a path in
+/// `backup_dir`; give a batch of them separate paths with [`output_paths`].
+pub fn backup_path(file_path: &Path, backup_dir: Option<&Path>) -> PathBuf {
+    match (backup_dir, file_path.file_name()) {
+        (Some(dir), Some(file_name)) => dir.join(file_name),
+        _ => with_name_suffix(file_path, ".bak"),
+    }
+}
+
+/// Copy a GIF to its backup location before it is modified in place
+pub fn backup_gif(file_path: &Path, backup_dir: Option<&Path>) -> Result<PathBuf> {
+    let backup = backup_path(file_path, backup_dir);
+    backup_gif_to(file_path, &backup)?;
+    Ok(backup)
+}
+
+/// Copy a GIF to `backup`, creating its directory
+pub fn backup_gif_to(file_path: &Path, backup: &Path) -> Result<()> {
+    if let Some(dir) = backup.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::copy(file_path, backup)?;
+    Ok(())
+}
+
+/// Destination of each file when writing copies into `output_dir`
+///
+/// Files found under one of the input directories keep their path relative to
+/// it; other files go directly into `output_dir`. Names already taken by an
+/// earlier file get a numeric suffix (`name_1.gif`, `name_2.gif`, ...).
+pub fn output_paths(input_paths: &[PathBuf], files: &[PathBuf], output_dir: &Path) -> Vec<PathBuf> {
+    let mut taken = HashSet::new();
+
+    files
+        .iter()
+        .map(|file| {
+            let relative = input_paths
+                .iter()
+                .filter(|root| root.is_dir())
+                .find_map(|root| file.strip_prefix(root).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from(file.file_name().unwrap_or_default()));
+
+            let mut destination = output_dir.join(&relative);
+            let stem = destination.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let extension = destination.extension().map(|ext| ext.to_string_lossy().into_owned());
+            let mut suffix = 0;
+            while !taken.insert(destination.clone()) {
+                suffix += 1;
+                let name = match &extension {
+                    Some(ext) => format!("{}_{}.{}", stem, suffix, ext),
+                    None => format!("{}_{}", stem, suffix),
+                };
+                destination.set_file_name(name);
+            }
+
+            destination
+        })
+        .collect()
+}
+
+/// Copies a GIF to `destination` and makes the copy transparent, leaving the source untouched
+pub fn write_transparent_copy(source: &Path, destination: &Path, options: &TransparencyOptions) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, destination)?;
+    make_gif_transparent_with_options(destination, options)
+}
+
+/// Collect the `.gif` files among the inputs, walking directories when recursive
+///
+/// With `by_content`, files with a GIF header are collected whatever their
+/// extension. The header of `.gif` files is not checked here, so that dry runs
+/// can report invalid files.
+fn collect_gif_files(input_paths: &[PathBuf], recursive: bool, by_content: bool) -> Result<Vec<PathBuf>> {
+    let is_candidate = |path: &Path| {
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif")) || (by_content && is_gif_file(path))
+    };
     let mut gif_files = Vec::new();
 
     for path in input_paths {
@@ -95,15 +539,11 @@ pub fn batch_process_gifs(
             // Recursively walk directory
             for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
                 let entry_path = entry.path();
-                if entry_path.is_file() &&
-                    entry_path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("gif")) &&
-                    is_gif_file(entry_path) {
+                if entry_path.is_file() && is_candidate(entry_path) {
                     gif_files.push(entry_path.to_owned());
                 }
             }
-        } else if path.is_file() &&
-            path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("gif")) &&
-            is_gif_file(path) {
+        } else if path.is_file() && is_candidate(path) {
             gif_files.push(path.to_owned());
         }
     }
@@ -112,39 +552,197 @@ pub fn batch_process_gifs(
         return Err(VideoToolkitError::Other("No GIF files found".to_string()));
     }
 
-    let total_files = gif_files.len();
+    Ok(gif_files)
+}
+
+/// Progress bar shared by the batch GIF operations
+fn gif_progress_bar(total_files: usize) -> ProgressBar {
     let progress_bar = ProgressBar::new(total_files as u64);
     progress_bar.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
             .unwrap()
     );
+    progress_bar
+}
+
+/// Process multiple GIF files in batch, making them transparent
+///
+/// With `dry_run`, files are only classified and nothing is written.
+pub fn batch_process_gifs(
+    input_paths: &[PathBuf],
+    recursive: bool,
+    create_backup: bool,
+    dry_run: bool,
+    options: &TransparencyOptions,
+    batch_options: &BatchGifOptions,
+) -> Result<TransparencyReport> {
+    // Leave out files that only have the extension, reporting why
+    let mut gif_files = Vec::new();
+    let mut skipped = Vec::new();
+    for path in collect_gif_files(input_paths, recursive, batch_options.by_content)? {
+        match check_gif_header(&path) {
+            Ok(()) => gif_files.push(path),
+            Err(reason) => skipped.push(SkippedFile { path, reason }),
+        }
+    }
+
+    let total_files = gif_files.len();
+    let destinations = match batch_options.output_dir {
+        Some(ref output_dir) => output_paths(input_paths, &gif_files, output_dir).into_iter().map(Some).collect(),
+        None => vec![None; total_files],
+    };
+
+    // Create a progress bar if there's no custom callback
+    let progress_bar = if batch_options.progress.is_none() {
+        Some(gif_progress_bar(total_files))
+    } else {
+        None
+    };
+    let processed = Mutex::new(0);
+
+    // Classify and process files in parallel
+    let process_all = || -> Vec<GifReportEntry> {
+        gif_files
+            .par_iter()
+            .zip(&destinations)
+            .map(|(file_path, destination)| {
+                let entry = match destination {
+                    Some(destination) => process_gif_copy(file_path, destination, dry_run, options),
+                    None => process_gif(file_path, create_backup, dry_run, options),
+                };
+
+                if let Some(ref progress_bar) = progress_bar {
+                    progress_bar.inc(1);
+                } else if let Some(ref callback) = batch_options.progress {
+                    let mut count = processed.lock().unwrap();
+                    *count += 1;
+                    callback(*count, total_files);
+                }
+
+                entry
+            })
+            .collect()
+    };
+
+    let entries = match batch_options.max_parallel {
+        Some(max_parallel) => ThreadPoolBuilder::new()
+            .num_threads(max_parallel.max(1))
+            .build()
+            .map_err(|e| VideoToolkitError::Other(format!("Could not start worker threads: {}", e)))?
+            .install(process_all),
+        None => process_all(),
+    };
+
+    if let Some(progress_bar) = progress_bar {
+        progress_bar.finish_with_message("GIF processing complete");
+    }
+
+    Ok(TransparencyReport { dry_run, entries, skipped })
+}
 
-    // Create backups if requested
-    if create_backup {
-        for file_path in &gif_files {
-            let backup_path = file_path.with_extension("gif.bak");
-            fs::copy(file_path, backup_path)
-                .map_err(|e| VideoToolkitError::IoError(e))?;
+/// Classify one file and, unless in a dry run, make it transparent
+fn process_gif(
+    file_path: &Path,
+    create_backup: bool,
+    dry_run: bool,
+    options: &TransparencyOptions,
+) -> GifReportEntry {
+    let mut entry = GifReportEntry {
+        path: file_path.to_path_buf(),
+        status: GifStatus::NotAGif,
+        error: None,
+        output: None,
+    };
+
+    match classify_gif_with_mode(file_path, options.mode) {
+        Ok(status) => entry.status = status,
+        Err(e) => {
+            entry.error = Some(e.to_string());
+            return entry;
         }
     }
 
-    // Process files in parallel
-    let results: Vec<Result<()>> = gif_files
+    if dry_run || entry.status != GifStatus::WouldModify {
+        return entry;
+    }
+
+    let result = if create_backup {
+        backup_gif(file_path, None).and_then(|_| make_gif_transparent_with_options(file_path, options))
+    } else {
+        make_gif_transparent_with_options(file_path, options)
+    };
+    entry.error = result.err().map(|e| e.to_string());
+
+    entry
+}
+
+/// Classify one file and, unless in a dry run, write a transparent copy of it
+///
+/// GIFs that are already transparent are copied unchanged, so the output
+/// directory holds every valid input.
+fn process_gif_copy(
+    file_path: &Path,
+    destination: &Path,
+    dry_run: bool,
+    options: &TransparencyOptions,
+) -> GifReportEntry {
+    let mut entry = GifReportEntry {
+        path: file_path.to_path_buf(),
+        status: GifStatus::NotAGif,
+        error: None,
+        output: None,
+    };
+
+    match classify_gif_with_mode(file_path, options.mode) {
+        Ok(status) => entry.status = status,
+        Err(e) => {
+            entry.error = Some(e.to_string());
+            return entry;
+        }
+    }
+
+    if dry_run || !matches!(entry.status, GifStatus::WouldModify | GifStatus::AlreadyTransparent) {
+        return entry;
+    }
+
+    match write_transparent_copy(file_path, destination, options) {
+        Ok(()) => entry.output = Some(destination.to_path_buf()),
+        Err(e) => entry.error = Some(e.to_string()),
+    }
+
+    entry
+}
+
+/// Restore GIFs previously made transparent, preferring a `.gif.bak` backup
+/// over patching the trailer byte back
+///
+/// Returns the number of restored and skipped files; files that were never
+/// modified or could not be restored are skipped.
+pub fn batch_restore_gifs(input_paths: &[PathBuf], recursive: bool) -> Result<(usize, usize)> {
+    let gif_files: Vec<PathBuf> = collect_gif_files(input_paths, recursive, false)?
+        .into_iter()
+        .filter(|path| is_gif_file(path))
+        .collect();
+    let progress_bar = gif_progress_bar(gif_files.len());
+
+    let results: Vec<Result<bool>> = gif_files
         .par_iter()
         .map(|file_path| {
-            let result = make_gif_transparent(file_path);
+            let result = match restore_from_backup(file_path) {
+                Ok(false) => restore_gif(file_path),
+                other => other,
+            };
             progress_bar.inc(1);
             result
         })
         .collect();
 
-    progress_bar.finish_with_message("GIF processing complete");
+    progress_bar.finish_with_message("GIF restore complete");
 
-    // Count successful operations
-    let success_count = results.iter().filter(|r| r.is_ok()).count();
+    let restored = results.iter().filter(|r| matches!(r, Ok(true))).count();
 
-    Ok((success_count, total_files))
+    Ok((restored, gif_files.len() - restored))
 }
 
 /// Find and process all GIFs in a directory
@@ -152,7 +750,10 @@ pub fn process_directory(
     dir_path: &str,
     recursive: bool,
     create_backup: bool,
-) -> Result<(usize, usize)> {
+    dry_run: bool,
+    options: &TransparencyOptions,
+    batch_options: &BatchGifOptions,
+) -> Result<TransparencyReport> {
     let path = Path::new(dir_path);
 
     if !path.exists() {
@@ -163,5 +764,266 @@ pub fn process_directory(
         return Err(VideoToolkitError::Other(format!("Not a directory: {}", dir_path)));
     }
 
-    batch_process_gifs(&[path.to_path_buf()], recursive, create_backup)
-}
\ No newline at end of file
+    batch_process_gifs(&[path.to_path_buf()], recursive, create_backup, dry_run, options, batch_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY: TransparencyOptions = TransparencyOptions { mode: TransparencyMode::LegacyTrailer, atomic: true };
+
+    /// Write a minimal single-frame GIF into a fresh temporary directory
+    fn temp_gif(test_name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("gif_transparency_{}_{}", test_name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let gif = dir.join("anim.gif");
+        fs::write(
+            &gif,
+            b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x2C\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00\x3B",
+        )
+        .unwrap();
+        (dir, gif)
+    }
+
+    #[test]
+    fn test_restore_gif_round_trip() {
+        let (dir, gif) = temp_gif("restore_trailer");
+        let original = fs::read(&gif).unwrap();
+
+        make_gif_transparent_with_options(&gif, &LEGACY).unwrap();
+        assert!(restore_gif(&gif).unwrap());
+        assert_eq!(fs::read(&gif).unwrap(), original);
+        assert!(!restore_gif(&gif).unwrap());
+
+        let not_gif = dir.join("fake.gif");
+        fs::write(&not_gif, b"\x89PNG\r\n\x1a\n\x21").unwrap();
+        assert!(restore_gif(&not_gif).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_from_backup_and_counts() {
+        let (dir, gif) = temp_gif("restore_backup");
+        let original = fs::read(&gif).unwrap();
+        let untouched = dir.join("untouched.gif");
+        fs::write(&untouched, &original).unwrap();
+
+        assert!(!restore_from_backup(&gif).unwrap());
+        batch_process_gifs(
+            std::slice::from_ref(&gif), false, true, false, &TransparencyOptions::default(), &BatchGifOptions::default()
+        ).unwrap();
+        assert_ne!(fs::read(&gif).unwrap(), original);
+
+        let inputs = vec![gif.clone(), untouched];
+        assert_eq!(batch_restore_gifs(&inputs, false).unwrap(), (1, 1));
+        assert_eq!(fs::read(&gif).unwrap(), original);
+        assert!(!backup_path(&gif, None).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_classifies_without_writing() {
+        let (dir, gif) = temp_gif("dry_run");
+        let transparent = dir.join("transparent.gif");
+        fs::write(&transparent, b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x21").unwrap();
+        let odd = dir.join("odd.gif");
+        fs::write(&odd, b"GIF87a\x01\x00\x01\x00\x00\x00\x00\x00").unwrap();
+        let fake = dir.join("fake.gif");
+        fs::write(&fake, b"\x89PNG\r\n\x1a\n\x3B").unwrap();
+        let empty = dir.join("empty.gif");
+        fs::write(&empty, b"").unwrap();
+
+        let original = fs::read(&gif).unwrap();
+        let report =
+            process_directory(&dir.to_string_lossy(), true, true, true, &LEGACY, &BatchGifOptions::default()).unwrap();
+
+        let status_of = |path: &Path| report.entries.iter().find(|e| e.path == path).unwrap().status;
+        assert_eq!(status_of(&gif), GifStatus::WouldModify);
+        assert_eq!(status_of(&transparent), GifStatus::AlreadyTransparent);
+        assert_eq!(status_of(&odd), GifStatus::UnexpectedTrailer(0x00));
+        assert_eq!((report.success_count(), report.gif_count()), (2, 3));
+
+        let mut skipped = report.skipped.clone();
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(skipped, [
+            SkippedFile { path: empty, reason: SkipReason::Empty },
+            SkippedFile { path: fake, reason: SkipReason::WrongMagic },
+        ]);
+        assert!(report.breakdown().ends_with("2 skipped (not a GIF)"));
+
+        assert_eq!(fs::read(&gif).unwrap(), original);
+        assert!(!backup_path(&gif, None).exists());
+
+        let report =
+            batch_process_gifs(std::slice::from_ref(&gif), false, false, false, &LEGACY, &BatchGifOptions::default())
+                .unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.success_count(), 1);
+        assert_eq!(*fs::read(&gif).unwrap().last().unwrap(), 0x21);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_content_detection_finds_renamed_gifs() {
+        let (dir, gif) = temp_gif("by_content");
+        let renamed = dir.join("download");
+        fs::copy(&gif, &renamed).unwrap();
+        fs::write(dir.join("notes.txt"), b"GIF? no").unwrap();
+
+        assert_eq!(collect_gif_files(std::slice::from_ref(&dir), true, false).unwrap(), std::slice::from_ref(&gif));
+        let mut by_content = collect_gif_files(std::slice::from_ref(&dir), true, true).unwrap();
+        by_content.sort();
+        assert_eq!(by_content, [gif.clone(), renamed]);
+
+        // A GIF of another extension next to one of the same stem gets its own backup
+        let other = dir.join("anim.dat");
+        fs::write(&other, b"GIF89a\x02\x00\x01\x00\x00\x00\x00\x3B").unwrap();
+        let originals = [fs::read(&gif).unwrap(), fs::read(&other).unwrap()];
+        let options = BatchGifOptions::default().with_content_detection(true);
+        batch_process_gifs(&[gif.clone(), other.clone()], false, true, false, &LEGACY, &options).unwrap();
+        assert_eq!(backup_path(&other, None), dir.join("anim.dat.bak"));
+        assert_eq!(fs::read(backup_path(&gif, None)).unwrap(), originals[0]);
+        assert_eq!(fs::read(backup_path(&other, None)).unwrap(), originals[1]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_graphic_control_mode() {
+        let (dir, gif) = temp_gif("graphic_control");
+
+        assert_eq!(classify_gif(&gif).unwrap(), GifStatus::WouldModify);
+        make_gif_transparent(&gif).unwrap();
+        let transparent = fs::read(&gif).unwrap();
+        assert_eq!(*transparent.last().unwrap(), 0x3B);
+        assert!(transparent.windows(4).any(|w| w == [0x21, 0xF9, 0x04, 0x01]));
+
+        // Running again is a no-op
+        assert_eq!(classify_gif(&gif).unwrap(), GifStatus::AlreadyTransparent);
+        make_gif_transparent(&gif).unwrap();
+        assert_eq!(fs::read(&gif).unwrap(), transparent);
+
+        // A file broken by the legacy mode is reported rather than rewritten
+        make_gif_transparent_with_options(&gif, &LEGACY).unwrap();
+        assert_eq!(classify_gif(&gif).unwrap(), GifStatus::UnexpectedTrailer(0x21));
+        assert!(make_gif_transparent(&gif).is_err());
+        assert!(!dir.join("anim.gif.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_replace_keeps_metadata() {
+        let (dir, gif) = temp_gif("atomic");
+        let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        File::options().write(true).open(&gif).unwrap().set_modified(modified).unwrap();
+        let mut permissions = fs::metadata(&gif).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&gif, permissions).unwrap();
+
+        for options in [TransparencyOptions::default(), LEGACY] {
+            make_gif_transparent_with_options(&gif, &options).unwrap();
+
+            let metadata = fs::metadata(&gif).unwrap();
+            assert!(metadata.permissions().readonly());
+            assert_eq!(metadata.modified().unwrap(), modified);
+            assert!(!dir.join("anim.gif.tmp").exists());
+        }
+        assert_eq!(*fs::read(&gif).unwrap().last().unwrap(), 0x21);
+
+        // The in-place fast path gives the same result
+        let in_place = dir.join("in_place.gif");
+        fs::write(&in_place, fs::read(&gif).unwrap()).unwrap();
+        restore_gif(&in_place).unwrap();
+        make_gif_transparent_with_options(&in_place, &TransparencyOptions { atomic: false, ..LEGACY }).unwrap();
+        assert_eq!(fs::read(&in_place).unwrap(), fs::read(&gif).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_progress_callback_and_max_parallel() {
+        let (dir, gif) = temp_gif("progress");
+        for i in 0..4 {
+            fs::copy(&gif, dir.join(format!("copy{}.gif", i))).unwrap();
+        }
+
+        let calls = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let calls_clone = std::sync::Arc::clone(&calls);
+        let batch_options = BatchGifOptions::default()
+            .with_max_parallel(2)
+            .with_progress_callback(move |processed, total| calls_clone.lock().unwrap().push((processed, total)));
+
+        let options = TransparencyOptions::default();
+        let report = batch_process_gifs(std::slice::from_ref(&dir), true, false, true, &options, &batch_options).unwrap();
+        assert_eq!(report.gif_count(), 5);
+
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort();
+        assert_eq!(calls, (1..=5).map(|n| (n, 5)).collect::<Vec<_>>());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_paths() {
+        let root = std::env::temp_dir().join(format!("gif_transparency_paths_{}", std::process::id()));
+        fs::create_dir_all(root.join("nested")).unwrap();
+        let out = Path::new("out");
+
+        let inputs = vec![root.clone(), PathBuf::from("a/clip.gif"), PathBuf::from("b/clip.gif")];
+        let files = vec![
+            root.join("nested/anim.gif"),
+            root.join("clip.gif"),
+            PathBuf::from("a/clip.gif"),
+            PathBuf::from("b/clip.gif"),
+        ];
+        assert_eq!(
+            output_paths(&inputs, &files, out),
+            [
+                out.join("nested/anim.gif"),
+                out.join("clip.gif"),
+                out.join("clip_1.gif"),
+                out.join("clip_2.gif"),
+            ]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_output_dir_leaves_sources_untouched() {
+        let (dir, gif) = temp_gif("output_dir");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let nested = dir.join("sub/anim.gif");
+        fs::copy(&gif, &nested).unwrap();
+        let original = fs::read(&gif).unwrap();
+        let out = std::env::temp_dir().join(format!("gif_transparency_output_dir_out_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&out);
+
+        let batch_options = BatchGifOptions::default().with_output_dir(&out);
+        let report = batch_process_gifs(
+            std::slice::from_ref(&dir), true, true, false, &TransparencyOptions::default(), &batch_options
+        ).unwrap();
+
+        assert_eq!(report.success_count(), 2);
+        assert_eq!(fs::read(&gif).unwrap(), original);
+        assert_eq!(fs::read(&nested).unwrap(), original);
+        assert!(!backup_path(&gif, None).exists());
+
+        let copy = out.join("sub/anim.gif");
+        let entry = report.entries.iter().find(|e| e.path == nested).unwrap();
+        assert_eq!(entry.output.as_deref(), Some(copy.as_path()));
+        assert_eq!(classify_gif(&copy).unwrap(), GifStatus::AlreadyTransparent);
+        assert_eq!(classify_gif(&out.join("anim.gif")).unwrap(), GifStatus::AlreadyTransparent);
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&out).unwrap();
+    }
+}
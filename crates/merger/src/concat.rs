@@ -0,0 +1,306 @@
+//! Joining several clips into one file, either losslessly with the concat
+//! demuxer or by re-encoding them through the concat filter.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use common::{
+    get_codec_options, get_duration, get_stream_parameters, get_video_dimensions, has_audio_stream,
+    output_written, verify_input_file, FfmpegCommand, Result, VideoToolkitError
+};
+
+/// How clips are joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConcatMode {
+    /// Use the demuxer when all clips share their stream parameters, re-encode otherwise
+    #[default]
+    Auto,
+    /// Copy the streams with the concat demuxer; fails if the clips differ
+    Demuxer,
+    /// Re-encode through the concat filter, scaling every clip to the first one's size
+    Reencode,
+}
+
+impl fmt::Display for ConcatMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConcatMode::Auto => write!(f, "auto"),
+            ConcatMode::Demuxer => write!(f, "demuxer"),
+            ConcatMode::Reencode => write!(f, "reencode"),
+        }
+    }
+}
+
+impl FromStr for ConcatMode {
+    type Err = VideoToolkitError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ConcatMode::Auto),
+            "demuxer" => Ok(ConcatMode::Demuxer),
+            "reencode" | "re-encode" => Ok(ConcatMode::Reencode),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid concat mode '{}': expected auto, demuxer or reencode",
+                s
+            ))),
+        }
+    }
+}
+
+/// Read an ordered list of clips from a text manifest
+///
+/// One path per line; blank lines and lines starting with `#` are skipped.
+/// Relative paths are resolved against the manifest's directory.
+pub fn read_concat_manifest(manifest: &Path) -> Result<Vec<PathBuf>> {
+    let content = fs::read_to_string(manifest).map_err(VideoToolkitError::IoError)?;
+    let base = manifest.parent().unwrap_or_else(|| Path::new(""));
+    let inputs = parse_concat_manifest(&content, base);
+
+    if inputs.is_empty() {
+        return Err(VideoToolkitError::Other(format!("Manifest '{}' lists no files", manifest.display())));
+    }
+    Ok(inputs)
+}
+
+fn parse_concat_manifest(content: &str, base: &Path) -> Vec<PathBuf> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(line))
+        .collect()
+}
+
+/// First difference between the stream parameters of the clips, if any
+fn stream_mismatch(parameters: &[(String, Vec<String>)]) -> Option<String> {
+    let (first_file, first) = parameters.first()?;
+    parameters
+        .iter()
+        .skip(1)
+        .find(|(_, streams)| streams != first)
+        .map(|(file, streams)| {
+            format!(
+                "'{}' ({}) does not match '{}' ({})",
+                file,
+                streams.join(" / "),
+                first_file,
+                first.join(" / ")
+            )
+        })
+}
+
+/// Line of a concat demuxer list; single quotes are closed, escaped and reopened
+fn concat_list_entry(path: &Path) -> String {
+    format!("file '{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+fn build_demuxer_command(list_file: &str, output_file: &str) -> FfmpegCommand {
+    FfmpegCommand::new()
+        .overwrite()
+        .input_option("-f", "concat")
+        .input_option("-safe", 0)
+        .input(list_file)
+        .map("0")
+        .codec("copy")
+        .output(output_file)
+}
+
+/// A clip as seen by the re-encoding fallback
+struct FilterInput {
+    path: String,
+    has_audio: bool,
+    duration: f64,
+}
+
+/// Concat filter command joining `inputs` at the given frame size
+///
+/// Clips are letterboxed into the frame. Clips without audio get silence so
+/// that every segment has the same streams; the audio is dropped only if no
+/// clip has any.
+fn build_filter_command(inputs: &[FilterInput], (width, height): (u32, u32), output_file: &str) -> FfmpegCommand {
+    let with_audio = inputs.iter().any(|input| input.has_audio);
+
+    let mut command = FfmpegCommand::new().overwrite();
+    let mut graph = String::new();
+    let mut segments = String::new();
+    for (i, input) in inputs.iter().enumerate() {
+        command = command.input(&input.path);
+        graph.push_str(&format!(
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1[v{i}];",
+            i = i,
+            w = width,
+            h = height
+        ));
+        segments.push_str(&format!("[v{}]", i));
+
+        if with_audio {
+            if input.has_audio {
+                graph.push_str(&format!("[{}:a]aformat=sample_rates=48000:channel_layouts=stereo[a{}];", i, i));
+            } else {
+                graph.push_str(&format!("anullsrc=r=48000:cl=stereo,atrim=duration={}[a{}];", input.duration, i));
+            }
+            segments.push_str(&format!("[a{}]", i));
+        }
+    }
+
+    graph.push_str(&format!(
+        "{}concat=n={}:v=1:a={}[v]{}",
+        segments,
+        inputs.len(),
+        u8::from(with_audio),
+        if with_audio { "[a]" } else { "" }
+    ));
+    command = command.filter_complex(graph).map("[v]");
+    if with_audio {
+        command = command.map("[a]");
+    }
+
+    let format = Path::new(output_file)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "mp4".to_string());
+    command.args(get_codec_options(&format)).output(output_file)
+}
+
+fn concat_with_demuxer(inputs: &[String], output_file: &str) -> Result<()> {
+    let list_file = format!("{}.concat.txt", output_file);
+    let mut list = String::new();
+    for input in inputs {
+        let path = fs::canonicalize(input).map_err(VideoToolkitError::IoError)?;
+        list.push_str(&concat_list_entry(&path));
+        list.push('\n');
+    }
+    fs::write(&list_file, list).map_err(VideoToolkitError::IoError)?;
+
+    let result = build_demuxer_command(&list_file, output_file).execute();
+    let _ = fs::remove_file(&list_file);
+    result.map(|_| ())
+}
+
+fn concat_with_filter(inputs: &[String], output_file: &str) -> Result<()> {
+    let size = get_video_dimensions(&inputs[0])?;
+    let mut filter_inputs = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let has_audio = has_audio_stream(input)?;
+        // The length of the silence only matters for clips without audio
+        let duration = if has_audio { 0.0 } else { get_duration(input)? };
+        filter_inputs.push(FilterInput { path: input.clone(), has_audio, duration });
+    }
+
+    build_filter_command(&filter_inputs, size, output_file).execute().map(|_| ())
+}
+
+/// Join clips into one file, in the given order
+///
+/// Returns the method that was used, [`ConcatMode::Demuxer`] or
+/// [`ConcatMode::Reencode`].
+pub fn concat_videos(inputs: &[PathBuf], output_file: &Path, mode: ConcatMode) -> Result<ConcatMode> {
+    if inputs.len() < 2 {
+        return Err(VideoToolkitError::Other("At least two files are needed to concatenate".to_string()));
+    }
+
+    let inputs: Vec<String> = inputs.iter().map(|input| input.to_string_lossy().to_string()).collect();
+    for input in &inputs {
+        verify_input_file(input)?;
+    }
+    let output = output_file.to_string_lossy().to_string();
+
+    let method = match mode {
+        ConcatMode::Reencode => ConcatMode::Reencode,
+        ConcatMode::Auto | ConcatMode::Demuxer => {
+            let mut parameters = Vec::with_capacity(inputs.len());
+            for input in &inputs {
+                parameters.push((input.clone(), get_stream_parameters(input)?));
+            }
+
+            match stream_mismatch(&parameters) {
+                None => ConcatMode::Demuxer,
+                Some(mismatch) if mode == ConcatMode::Demuxer => {
+                    return Err(VideoToolkitError::Other(format!(
+                        "The concat demuxer needs matching streams: {}; use the reencode mode instead",
+                        mismatch
+                    )));
+                },
+                Some(_) => ConcatMode::Reencode,
+            }
+        },
+    };
+
+    // Create output directory if it doesn't exist
+    if let Some(parent) = output_file.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(VideoToolkitError::IoError)?;
+        }
+    }
+
+    match method {
+        ConcatMode::Demuxer => concat_with_demuxer(&inputs, &output)?,
+        _ => concat_with_filter(&inputs, &output)?,
+    }
+
+    if !output_written(output_file) {
+        return Err(VideoToolkitError::OutputFileNotCreated);
+    }
+    Ok(method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concat_helpers() {
+        let manifest = "# intro first\nintro.mp4\n\n  /clips/main.mp4  \n";
+        assert_eq!(parse_concat_manifest(manifest, Path::new("/work")), [
+            PathBuf::from("/work/intro.mp4"),
+            PathBuf::from("/clips/main.mp4"),
+        ]);
+
+        assert_eq!(concat_list_entry(Path::new("/clips/it's.mp4")), r"file '/clips/it'\''s.mp4'");
+
+        let hd = vec!["h264,1920,1080,yuv420p,,,1/12800".to_string(), "aac,,,,48000,2,1/48000".to_string()];
+        let mut parameters = vec![("a.mp4".to_string(), hd.clone()), ("b.mp4".to_string(), hd.clone())];
+        assert_eq!(stream_mismatch(&parameters), None);
+        parameters.push(("c.mp4".to_string(), hd[..1].to_vec()));
+        assert!(stream_mismatch(&parameters).unwrap().starts_with("'c.mp4' (h264,1920,1080,yuv420p,,,1/12800) does not match 'a.mp4'"));
+
+        assert_eq!("re-encode".parse::<ConcatMode>().unwrap(), ConcatMode::Reencode);
+        assert!("fast".parse::<ConcatMode>().is_err());
+    }
+
+    #[test]
+    fn test_demuxer_command() {
+        assert_eq!(build_demuxer_command("out.mp4.concat.txt", "out.mp4").to_args(), [
+            "-y", "-f", "concat", "-safe", "0", "-i", "out.mp4.concat.txt", "-map", "0", "-c", "copy", "out.mp4",
+        ]);
+    }
+
+    #[test]
+    fn test_filter_args() {
+        let inputs = [
+            FilterInput { path: "a.mp4".to_string(), has_audio: true, duration: 0.0 },
+            FilterInput { path: "b.mp4".to_string(), has_audio: false, duration: 4.5 },
+        ];
+        let args = build_filter_command(&inputs, (1280, 720), "out.mp4").to_args();
+        assert_eq!(args[..5], ["-y", "-i", "a.mp4", "-i", "b.mp4"]);
+        assert_eq!(
+            args[6],
+            "[0:v]scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2,setsar=1[v0];\
+             [0:a]aformat=sample_rates=48000:channel_layouts=stereo[a0];\
+             [1:v]scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2,setsar=1[v1];\
+             anullsrc=r=48000:cl=stereo,atrim=duration=4.5[a1];\
+             [v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"
+        );
+        assert_eq!(args[7..], ["-map", "[v]", "-map", "[a]", "-c:v", "libx264", "-c:a", "aac", "out.mp4"]);
+
+        let silent = [
+            FilterInput { path: "a.mkv".to_string(), has_audio: false, duration: 2.0 },
+            FilterInput { path: "b.mkv".to_string(), has_audio: false, duration: 3.0 },
+        ];
+        let args = build_filter_command(&silent, (640, 480), "out.webm").to_args();
+        assert!(args[6].ends_with("[v0][v1]concat=n=2:v=1:a=0[v]"));
+        assert_eq!(args[7..], ["-map", "[v]", "-c:v", "libvpx", "-c:a", "libvorbis", "out.webm"]);
+    }
+}
@@ -1,40 +1,649 @@
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::str::FromStr;
 
 use common::{
-    execute_ffmpeg, verify_input_file,
-    Result, VideoToolkitError
+    cancellation_scope, escape_filter_text, get_audio_codec, get_codec_options, get_codec_options_for, get_duration,
+    get_video_codec, has_audio_stream, output_written, verify_input_file, video_encoder_in,
+    CancellationToken, EncoderPreference, FfmpegCommand, Result, VideoToolkitError
 };
 
-/// Extract audio from a video file
-pub fn extract_audio(video_file: &str, audio_file: &str) -> Result<()> {
+mod concat;
+
+pub use concat::{concat_videos, read_concat_manifest, ConcatMode};
+
+/// What happens to the video's own audio when a new track is merged in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AudioMode {
+    /// Keep only the new audio
+    #[default]
+    Replace,
+    /// Mix the new audio with the video's own track
+    Mix,
+    /// Keep both as separate tracks, for players that support track selection
+    AddTrack,
+}
+
+impl fmt::Display for AudioMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioMode::Replace => write!(f, "replace"),
+            AudioMode::Mix => write!(f, "mix"),
+            AudioMode::AddTrack => write!(f, "add-track"),
+        }
+    }
+}
+
+impl FromStr for AudioMode {
+    type Err = VideoToolkitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "replace" => Ok(AudioMode::Replace),
+            "mix" => Ok(AudioMode::Mix),
+            "add-track" => Ok(AudioMode::AddTrack),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid audio mode '{}': expected replace, mix or add-track",
+                value
+            ))),
+        }
+    }
+}
+
+/// How a new audio track of another length than the video is fitted to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DurationPolicy {
+    /// Leave both as they are; `use_shortest` decides where the output ends
+    #[default]
+    Shortest,
+    /// Repeat the audio until the video ends
+    LoopAudio,
+    /// Fill the end of the video with silence
+    PadSilence,
+}
+
+impl fmt::Display for DurationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurationPolicy::Shortest => write!(f, "shortest"),
+            DurationPolicy::LoopAudio => write!(f, "loop-audio"),
+            DurationPolicy::PadSilence => write!(f, "pad-silence"),
+        }
+    }
+}
+
+impl FromStr for DurationPolicy {
+    type Err = VideoToolkitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "shortest" => Ok(DurationPolicy::Shortest),
+            "loop-audio" => Ok(DurationPolicy::LoopAudio),
+            "pad-silence" => Ok(DurationPolicy::PadSilence),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid duration policy '{}': expected shortest, loop-audio or pad-silence",
+                value
+            ))),
+        }
+    }
+}
+
+/// How a subtitle file ends up in the merged output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubtitleMode {
+    /// Mux the subtitles as a selectable track
+    #[default]
+    Soft,
+    /// Draw the subtitles into the video with the subtitles filter (re-encodes the video)
+    Burn,
+}
+
+impl fmt::Display for SubtitleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubtitleMode::Soft => write!(f, "soft"),
+            SubtitleMode::Burn => write!(f, "burn"),
+        }
+    }
+}
+
+impl FromStr for SubtitleMode {
+    type Err = VideoToolkitError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "soft" => Ok(SubtitleMode::Soft),
+            "burn" => Ok(SubtitleMode::Burn),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid subtitle mode '{}': expected soft or burn",
+                s
+            ))),
+        }
+    }
+}
+
+/// Additional options for merging audio into a video
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    pub audio_mode: AudioMode,
+    /// Volume of the video's own audio when mixing (1.0 = unchanged)
+    pub video_volume: f32,
+    /// Volume of the new audio when mixing (1.0 = unchanged)
+    pub audio_volume: f32,
+    pub duration_policy: DurationPolicy,
+    /// Normalize the loudness of the merged audio; forces the audio to be re-encoded
+    pub normalize_loudness: Option<LoudnessTarget>,
+    /// Subtitle file (.srt, .ass, .ssa or .vtt) to add to the output
+    pub subtitle_file: Option<String>,
+    pub subtitle_mode: SubtitleMode,
+    /// Fade the merged audio in over this many seconds; forces the audio to be re-encoded
+    pub audio_fade_in_secs: Option<f64>,
+    /// Fade the merged audio out over this many seconds before it ends; forces the audio to be re-encoded
+    pub audio_fade_out_secs: Option<f64>,
+    /// Container format of the output (see [`MERGE_FORMATS`]); taken from the output file's extension if not set
+    pub output_format: Option<String>,
+    /// Stops the merge and kills the running FFmpeg when cancelled
+    pub cancel: Option<CancellationToken>,
+    /// Whether a re-encoded video may use a hardware video encoder
+    pub encoder: EncoderPreference,
+}
+
+/// Output containers the merger picks codecs for
+pub const MERGE_FORMATS: [&str; 6] = ["mp4", "mkv", "mov", "webm", "avi", "flv"];
+
+/// Loudness reached by FFmpeg's loudnorm filter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// Integrated loudness in LUFS
+    pub i: f32,
+    /// Maximum true peak in dBTP
+    pub tp: f32,
+    /// Loudness range in LU
+    pub lra: f32,
+    /// Measure the audio in a first pass for a more accurate, linear normalization
+    pub two_pass: bool,
+}
+
+impl Default for LoudnessTarget {
+    fn default() -> Self {
+        Self {
+            i: -16.0,
+            tp: -1.5,
+            lra: 11.0,
+            two_pass: false,
+        }
+    }
+}
+
+/// Outcome of a merge
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeReport {
+    /// How the durations of the video and the new audio were reconciled, if they could be probed
+    pub duration_note: Option<String>,
+    /// Video encoder the output was written with, unless the video was copied
+    pub video_encoder: Option<String>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            audio_mode: AudioMode::default(),
+            video_volume: 1.0,
+            audio_volume: 1.0,
+            duration_policy: DurationPolicy::default(),
+            normalize_loudness: None,
+            subtitle_file: None,
+            subtitle_mode: SubtitleMode::default(),
+            audio_fade_in_secs: None,
+            audio_fade_out_secs: None,
+            output_format: None,
+            cancel: None,
+            encoder: EncoderPreference::default(),
+        }
+    }
+}
+
+/// Normalize an output format name, rejecting containers the merger does not support
+pub fn validate_output_format(format: &str) -> Result<String> {
+    let format = format.trim().trim_start_matches('.').to_lowercase();
+    if MERGE_FORMATS.contains(&format.as_str()) {
+        Ok(format)
+    } else {
+        Err(VideoToolkitError::Other(format!(
+            "Output format '{}' is not supported by the merger (supported: {})",
+            format,
+            MERGE_FORMATS.join(", ")
+        )))
+    }
+}
+
+/// Whether a video container holds a stream of `codec` as is; Matroska and
+/// unknown containers are not checked
+fn container_accepts_stream(container: &str, codec: &str) -> bool {
+    let accepted: &[&str] = match container {
+        "mp4" => &["h264", "hevc", "mpeg4", "av1", "vp9", "aac", "mp3", "alac", "ac3", "eac3", "opus", "flac"],
+        "mov" => &["h264", "hevc", "mpeg4", "prores", "mjpeg", "aac", "mp3", "alac", "ac3", "pcm_s16le", "pcm_s24le"],
+        "webm" => &["vp8", "vp9", "av1", "vorbis", "opus"],
+        "avi" => &["h264", "mpeg4", "mjpeg", "msmpeg4v3", "mp3", "ac3", "pcm_s16le"],
+        "flv" => &["h264", "flv1", "aac", "mp3"],
+        _ => return true,
+    };
+    accepted.contains(&codec)
+}
+
+/// Fail before running FFmpeg if a copied stream does not fit the output container
+fn check_copied_streams(container: &str, streams: &[(&str, String)]) -> Result<()> {
+    match streams.iter().find(|(_, codec)| !container_accepts_stream(container, codec)) {
+        Some((file, codec)) => Err(VideoToolkitError::Other(format!(
+            "The {} stream of '{}' cannot be copied into a {} file; turn off copy codec or choose another format",
+            codec, file, container
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Container of the output: the requested format, which must match the file's extension, or the extension itself
+fn output_container(output_file: &str, format: Option<&str>) -> Result<String> {
+    let extension = file_extension(output_file);
+    match format {
+        Some(format) => {
+            let format = validate_output_format(format)?;
+            if format != extension {
+                return Err(VideoToolkitError::Other(format!(
+                    "Output file '{}' does not have the .{} extension of the requested format",
+                    output_file, format
+                )));
+            }
+            Ok(format)
+        },
+        None => Ok(extension),
+    }
+}
+
+/// Whether the options filter the audio, which then has to be re-encoded
+fn filters_audio(options: &MergeOptions) -> bool {
+    options.normalize_loudness.is_some() || options.audio_fade_in_secs.is_some() || options.audio_fade_out_secs.is_some()
+}
+
+/// File extension of a container that holds `codec` without re-encoding
+fn extension_for_codec(codec: &str) -> &'static str {
+    match codec {
+        "aac" | "alac" => "m4a",
+        "mp3" => "mp3",
+        "opus" => "opus",
+        "vorbis" => "ogg",
+        "flac" => "flac",
+        "ac3" => "ac3",
+        "eac3" => "eac3",
+        codec if codec.starts_with("pcm_") => "wav",
+        // Matroska audio takes any codec
+        _ => "mka",
+    }
+}
+
+/// Whether a file with this extension can hold `codec` as is
+fn extension_accepts_codec(extension: &str, codec: &str) -> bool {
+    match extension {
+        "mka" | "mkv" => true,
+        "m4a" | "mp4" | "mov" => matches!(codec, "aac" | "alac" | "mp3" | "ac3" | "eac3"),
+        "aac" => codec == "aac",
+        "ogg" | "oga" => matches!(codec, "vorbis" | "opus" | "flac"),
+        "wav" => codec.starts_with("pcm_"),
+        "mp3" | "opus" | "flac" | "ac3" | "eac3" => extension == codec,
+        _ => false,
+    }
+}
+
+/// Encoder that converts audio for a file with this extension
+fn encoder_for_extension(extension: &str) -> Option<&'static str> {
+    match extension {
+        "aac" | "m4a" | "mp4" | "mov" => Some("aac"),
+        "mp3" => Some("libmp3lame"),
+        "ogg" | "oga" => Some("libvorbis"),
+        "opus" => Some("libopus"),
+        "flac" => Some("flac"),
+        "wav" => Some("pcm_s16le"),
+        "ac3" => Some("ac3"),
+        "eac3" => Some("eac3"),
+        _ => None,
+    }
+}
+
+/// Output path and audio codec (`copy` or an encoder) for extracting `codec` audio to `audio_file`
+fn plan_extraction(audio_file: &Path, codec: &str) -> Result<(PathBuf, &'static str)> {
+    let extension = match audio_file.extension() {
+        Some(extension) => extension.to_string_lossy().to_lowercase(),
+        None => return Ok((audio_file.with_extension(extension_for_codec(codec)), "copy")),
+    };
+
+    if extension_accepts_codec(&extension, codec) {
+        return Ok((audio_file.to_path_buf(), "copy"));
+    }
+
+    match encoder_for_extension(&extension) {
+        Some(encoder) => Ok((audio_file.to_path_buf(), encoder)),
+        None => Err(VideoToolkitError::Other(format!(
+            "Cannot write {} audio to a .{} file; leave out the extension to pick a matching one",
+            codec, extension
+        ))),
+    }
+}
+
+/// Extract the audio of a video file, returning the path of the file written
+///
+/// Without an extension in `audio_file`, one that fits the source codec is added
+/// and the audio is copied. With an extension, the audio is copied if that
+/// container can hold it and converted otherwise.
+pub fn extract_audio(video_file: &str, audio_file: &str) -> Result<PathBuf> {
     verify_input_file(video_file)?;
 
+    let codec = get_audio_codec(video_file)?;
+    let (output, audio_codec) = plan_extraction(Path::new(audio_file), &codec)?;
+
     // Create output directory if it doesn't exist
-    if let Some(parent) = Path::new(audio_file).parent() {
+    if let Some(parent) = output.parent() {
         if !parent.as_os_str().is_empty() {
             fs::create_dir_all(parent).map_err(|e| VideoToolkitError::IoError(e))?;
         }
     }
 
     // Extract audio command
-    let args = vec![
-        "-y",
-        "-i", video_file,
-        "-acodec", "copy",
-        audio_file,
-    ];
+    FfmpegCommand::new()
+        .overwrite()
+        .input(video_file)
+        .map("0:a:0")
+        .codec_audio(audio_codec)
+        .output(&output)
+        .execute()?;
 
-    execute_ffmpeg(&args)?;
-
-    if !Path::new(audio_file).exists() {
+    if !output_written(&output) {
         return Err(VideoToolkitError::OutputFileNotCreated);
     }
 
+    Ok(output)
+}
+
+/// Reject option combinations FFmpeg cannot carry out
+fn validate_merge_options(copy_codec: bool, options: &MergeOptions) -> Result<()> {
+    let pad = options.duration_policy == DurationPolicy::PadSilence;
+    if copy_codec && (options.audio_mode == AudioMode::Mix || pad) {
+        let operation = if pad { "Padding with silence" } else { "Mixing" };
+        return Err(VideoToolkitError::Other(format!(
+            "{} re-encodes the audio, so it cannot be combined with copy codec",
+            operation
+        )));
+    }
+
+    if options.audio_mode == AudioMode::Mix && (options.video_volume < 0.0 || options.audio_volume < 0.0) {
+        return Err(VideoToolkitError::Other("Mix volumes must not be negative".to_string()));
+    }
+
+    if let Some(subtitle_file) = &options.subtitle_file {
+        let extension = file_extension(subtitle_file);
+        if !["srt", "ass", "ssa", "vtt"].contains(&extension.as_str()) {
+            return Err(VideoToolkitError::Other(format!(
+                "Unsupported subtitle file '{}': expected .srt, .ass, .ssa or .vtt",
+                subtitle_file
+            )));
+        }
+        if copy_codec && options.subtitle_mode == SubtitleMode::Burn {
+            return Err(VideoToolkitError::Other(
+                "Burning in subtitles re-encodes the video, so it cannot be combined with copy codec".to_string(),
+            ));
+        }
+    }
+
+    for (name, fade) in [("in", options.audio_fade_in_secs), ("out", options.audio_fade_out_secs)] {
+        if fade.is_some_and(|secs| !secs.is_finite() || secs <= 0.0) {
+            return Err(VideoToolkitError::Other(format!("Audio fade-{} must be a positive number of seconds", name)));
+        }
+    }
+
+    if let Some(target) = &options.normalize_loudness {
+        if !(-70.0..=-5.0).contains(&target.i) || !(-9.0..=0.0).contains(&target.tp) || !(1.0..=50.0).contains(&target.lra) {
+            return Err(VideoToolkitError::Other(format!(
+                "Invalid loudness target {} LUFS / {} dBTP / {} LU: expected -70 to -5 LUFS, -9 to 0 dBTP and 1 to 50 LU",
+                target.i, target.tp, target.lra
+            )));
+        }
+    }
+
     Ok(())
 }
 
-/// Merge audio and video files
+/// Lowercase extension of a file name, or an empty string
+fn file_extension(file: &str) -> String {
+    Path::new(file)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Subtitle codec for a soft subtitle track in the container of `output_file`
+fn subtitle_codec(output_file: &str) -> Result<&'static str> {
+    match file_extension(output_file).as_str() {
+        "mp4" | "m4v" | "mov" => Ok("mov_text"),
+        // Matroska stores SRT and ASS as they are
+        "mkv" => Ok("copy"),
+        "webm" => Ok("webvtt"),
+        other => Err(VideoToolkitError::Other(format!(
+            "Subtitle tracks cannot be muxed into '.{}' files; use mp4, mkv, mov or webm, or burn them in",
+            other
+        ))),
+    }
+}
+
+/// loudnorm filter reaching `target`, using the first pass's measurement if there was one
+fn loudnorm_filter(target: &LoudnessTarget, measured: Option<&LoudnessMeasurement>) -> String {
+    let filter = format!("loudnorm=I={}:TP={}:LRA={}", target.i, target.tp, target.lra);
+    match measured {
+        Some(m) => format!(
+            "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+            filter, m.input_i, m.input_tp, m.input_lra, m.input_thresh, m.target_offset
+        ),
+        None => filter,
+    }
+}
+
+/// Values printed by a measuring loudnorm pass
+#[derive(Debug, Clone, PartialEq)]
+struct LoudnessMeasurement {
+    input_i: f32,
+    input_tp: f32,
+    input_lra: f32,
+    input_thresh: f32,
+    target_offset: f32,
+}
+
+/// Read the JSON summary that loudnorm prints to stderr with `print_format=json`
+fn parse_loudnorm_stats(stderr: &str) -> Result<LoudnessMeasurement> {
+    let value = |key: &str| -> Result<f32> {
+        stderr
+            .lines()
+            .filter_map(|line| line.trim().trim_end_matches(',').split_once(':'))
+            .find(|(name, _)| name.trim().trim_matches('"') == key)
+            .and_then(|(_, value)| value.trim().trim_matches('"').parse().ok())
+            .ok_or_else(|| VideoToolkitError::Other(format!("Loudness measurement is missing '{}'", key)))
+    };
+
+    Ok(LoudnessMeasurement {
+        input_i: value("input_i")?,
+        input_tp: value("input_tp")?,
+        input_lra: value("input_lra")?,
+        input_thresh: value("input_thresh")?,
+        target_offset: value("target_offset")?,
+    })
+}
+
+/// Seconds after which the merged audio ends, the point the fade-out leads up to
+fn audio_end(video: f64, audio: f64, use_shortest: bool, options: &MergeOptions) -> f64 {
+    match (options.audio_mode, options.duration_policy) {
+        (_, DurationPolicy::LoopAudio) | (AudioMode::Mix, DurationPolicy::PadSilence) => video,
+        // The fade belongs to the audio itself, not to the silence after it
+        (_, DurationPolicy::PadSilence) => audio.min(video),
+        _ if use_shortest => audio.min(video),
+        (AudioMode::Mix, DurationPolicy::Shortest) => audio.max(video),
+        _ => audio,
+    }
+}
+
+/// afade filters for the fades in `options`; `end` is only needed for a fade-out
+fn fade_filters(options: &MergeOptions, end: Option<f64>) -> Result<Vec<String>> {
+    let mut filters = Vec::new();
+    if let Some(secs) = options.audio_fade_in_secs {
+        filters.push(format!("afade=t=in:st=0:d={}", secs));
+    }
+    if let Some(secs) = options.audio_fade_out_secs {
+        let end = end.ok_or_else(|| {
+            VideoToolkitError::Other("Could not determine the audio duration needed for the fade-out".to_string())
+        })?;
+        filters.push(format!("afade=t=out:st={:.3}:d={}", (end - secs).max(0.0), secs));
+    }
+    Ok(filters)
+}
+
+/// Filter graph that outputs the merged audio as `[a]`, if the audio is filtered at all
+///
+/// `filters` (loudness normalization, fades) are applied in order to the merged audio.
+fn audio_filter_graph(options: &MergeOptions, filters: &[String]) -> Option<String> {
+    let pad = options.duration_policy == DurationPolicy::PadSilence;
+
+    match options.audio_mode {
+        AudioMode::Mix => {
+            // An endless (looped or padded) track must not set the length of the mix;
+            // normalize=0 keeps the given volumes instead of dividing them by the number of inputs
+            let duration = if options.duration_policy == DurationPolicy::Shortest { "longest" } else { "first" };
+            Some(format!(
+                "[0:a]volume={}[a0];[1:a]volume={}{}[a1];[a0][a1]amix=inputs=2:duration={}:normalize=0{}[a]",
+                options.video_volume,
+                options.audio_volume,
+                if pad { ",apad" } else { "" },
+                duration,
+                filters.iter().map(|filter| format!(",{}", filter)).collect::<String>()
+            ))
+        },
+        AudioMode::Replace | AudioMode::AddTrack => {
+            let filters: Vec<&str> = filters.iter().map(String::as_str).chain(pad.then_some("apad")).collect();
+            (!filters.is_empty()).then(|| format!("[1:a]{}[a]", filters.join(",")))
+        },
+    }
+}
+
+/// FFmpeg command that merges `audio_file` into `video_file`
+///
+/// `audio_filters` are the loudness normalization and fade filters, if any.
+fn build_merge_command(
+    video_file: &str,
+    audio_file: &str,
+    output_file: &str,
+    use_shortest: bool,
+    copy_codec: bool,
+    options: &MergeOptions,
+    audio_filters: &[String],
+) -> Result<FfmpegCommand> {
+    validate_merge_options(copy_codec, options)?;
+
+    let mut command = FfmpegCommand::new().overwrite().input(video_file);
+    if options.duration_policy == DurationPolicy::LoopAudio {
+        command = command.input_option("-stream_loop", -1);
+    }
+    command = command.input(audio_file);
+
+    let soft_subtitles = options.subtitle_file.as_deref().filter(|_| options.subtitle_mode == SubtitleMode::Soft);
+    if let Some(subtitle_file) = soft_subtitles {
+        command = command.input(subtitle_file);
+    }
+
+    let graph = audio_filter_graph(options, audio_filters);
+    if let Some(graph) = &graph {
+        command = command.filter_complex(graph);
+    }
+
+    command = command.map("0:v");
+    if options.audio_mode == AudioMode::AddTrack {
+        command = command.map("0:a?");
+    }
+    command = command.map(if graph.is_some() { "[a]" } else { "1:a" });
+    if soft_subtitles.is_some() {
+        command = command.map("2:s");
+    }
+
+    if let (Some(subtitle_file), SubtitleMode::Burn) = (&options.subtitle_file, options.subtitle_mode) {
+        command = command.filter(format!("subtitles=filename={}", escape_filter_text(subtitle_file)));
+    }
+
+    let container = file_extension(output_file);
+    let known_container = MERGE_FORMATS.contains(&container.as_str());
+    if copy_codec && !audio_filters.is_empty() {
+        // Filtered audio has to be encoded again; only the video can be copied
+        let audio_encoder = get_codec_options(&container)
+            .iter()
+            .skip_while(|arg| *arg != "-c:a")
+            .nth(1)
+            .filter(|_| known_container)
+            .cloned()
+            .unwrap_or_else(|| "aac".to_string());
+        command = command.codec_video("copy").codec_audio(&audio_encoder);
+    } else if copy_codec {
+        command = command.codec("copy");
+    } else if known_container {
+        command = command.args(get_codec_options_for(&container, &options.encoder));
+    }
+    if soft_subtitles.is_some() {
+        // Overrides `-c copy` for the subtitle stream, which usually needs converting
+        command = command.option("-c:s", subtitle_codec(output_file)?);
+    }
+
+    // A looped or padded track only ends with the video
+    if use_shortest || options.duration_policy != DurationPolicy::Shortest {
+        command = command.flag("-shortest");
+    }
+
+    Ok(command.output(output_file))
+}
+
+/// FFmpeg command of the first loudnorm pass, which measures the merged audio without writing it
+fn build_measure_command(video_file: &str, audio_file: &str, options: &MergeOptions, target: &LoudnessTarget) -> FfmpegCommand {
+    // Looping and padding do not change the loudness, but would make the measurement endless
+    let options = MergeOptions { duration_policy: DurationPolicy::Shortest, ..options.clone() };
+    let measure = format!("{}:print_format=json", loudnorm_filter(target, None));
+    let graph = audio_filter_graph(&options, &[measure]).unwrap_or_default();
+
+    FfmpegCommand::new()
+        .hide_banner()
+        .input(video_file)
+        .input(audio_file)
+        .filter_complex(graph)
+        .map("[a]")
+        .option("-f", "null")
+        .output("-")
+}
+
+/// Describe how the durations of the video and the new audio are reconciled
+fn describe_durations(video: f64, audio: f64, use_shortest: bool, policy: DurationPolicy) -> String {
+    let durations = format!("video {:.1}s, audio {:.1}s", video, audio);
+    let difference = (video - audio).abs();
+
+    let outcome = if difference < 0.1 {
+        "durations match".to_string()
+    } else if audio < video {
+        match policy {
+            DurationPolicy::LoopAudio => "audio looped to the end of the video".to_string(),
+            DurationPolicy::PadSilence => format!("audio padded with {:.1}s of silence", difference),
+            DurationPolicy::Shortest if use_shortest => format!("video cut to {:.1}s", audio),
+            DurationPolicy::Shortest => format!("last {:.1}s of video without the new audio", difference),
+        }
+    } else if use_shortest || policy != DurationPolicy::Shortest {
+        format!("audio cut to {:.1}s", video)
+    } else {
+        format!("audio plays {:.1}s past the end of the video", difference)
+    };
+
+    format!("{}: {}", durations, outcome)
+}
+
+/// Merge audio and video files, replacing the video's own audio
 pub fn merge_audio_video(
     video_file: &str,
     audio_file: &str,
@@ -42,8 +651,71 @@ pub fn merge_audio_video(
     use_shortest: bool,
     copy_codec: bool,
 ) -> Result<()> {
+    merge_audio_video_with_options(video_file, audio_file, output_file, use_shortest, copy_codec, &MergeOptions::default())
+        .map(|_| ())
+}
+
+/// Merge audio and video files, choosing how the video's own audio is kept and
+/// how a new track of another length is fitted to the video
+pub fn merge_audio_video_with_options(
+    video_file: &str,
+    audio_file: &str,
+    output_file: &str,
+    use_shortest: bool,
+    copy_codec: bool,
+    options: &MergeOptions,
+) -> Result<MergeReport> {
     verify_input_file(video_file)?;
     verify_input_file(audio_file)?;
+    if let Some(subtitle_file) = &options.subtitle_file {
+        verify_input_file(subtitle_file)?;
+    }
+    let _cancel_scope = cancellation_scope(options.cancel.as_ref());
+
+    validate_merge_options(copy_codec, options)?;
+    let container = output_container(output_file, options.output_format.as_deref())?;
+    if options.audio_mode == AudioMode::Mix && !has_audio_stream(video_file)? {
+        return Err(VideoToolkitError::Other(format!(
+            "'{}' has no audio track to mix with; use the replace mode instead",
+            video_file
+        )));
+    }
+
+    if copy_codec {
+        let mut copied = vec![(video_file, get_video_codec(video_file)?)];
+        if !filters_audio(options) {
+            copied.push((audio_file, get_audio_codec(audio_file)?));
+        }
+        if options.audio_mode == AudioMode::AddTrack && has_audio_stream(video_file)? {
+            copied.push((video_file, get_audio_codec(video_file)?));
+        }
+        check_copied_streams(&container, &copied)?;
+    }
+
+    let durations = (get_duration(video_file), get_duration(audio_file));
+    let end = match &durations {
+        (Ok(video), Ok(audio)) => Some(audio_end(*video, *audio, use_shortest, options)),
+        _ => None,
+    };
+
+    let loudnorm = match &options.normalize_loudness {
+        Some(target) if target.two_pass => {
+            let output = build_measure_command(video_file, audio_file, options, target).execute()?;
+            let measured = parse_loudnorm_stats(&String::from_utf8_lossy(&output.stderr))?;
+            Some(loudnorm_filter(target, Some(&measured)))
+        },
+        Some(target) => Some(loudnorm_filter(target, None)),
+        None => None,
+    };
+    let mut audio_filters: Vec<String> = loudnorm.into_iter().collect();
+    audio_filters.extend(fade_filters(options, end)?);
+    let command = build_merge_command(video_file, audio_file, output_file, use_shortest, copy_codec, options, &audio_filters)?;
+    let video_encoder = video_encoder_in(&command.to_args()).filter(|encoder| encoder != "copy");
+
+    let duration_note = match durations {
+        (Ok(video), Ok(audio)) => Some(describe_durations(video, audio, use_shortest, options.duration_policy)),
+        _ => None,
+    };
 
     // Create output directory if it doesn't exist
     if let Some(parent) = Path::new(output_file).parent() {
@@ -52,29 +724,205 @@ pub fn merge_audio_video(
         }
     }
 
-    // Build merge command
-    let mut args = vec![
-        "-y",
-        "-i", video_file,
-        "-i", audio_file,
-    ];
+    // Execute FFmpeg command
+    if let Err(e) = command.execute() {
+        if e.is_interruption() {
+            let _ = fs::remove_file(output_file);
+        }
+        return Err(e);
+    }
 
-    if copy_codec {
-        args.extend_from_slice(&["-c", "copy"]);
+    if !output_written(Path::new(output_file)) {
+        return Err(VideoToolkitError::OutputFileNotCreated);
     }
 
-    if use_shortest {
-        args.push("-shortest");
+    Ok(MergeReport { duration_note, video_encoder })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_args(copy_codec: bool, options: &MergeOptions) -> Result<Vec<String>> {
+        let command = build_merge_command("in.mp4", "music.mp3", "out.mp4", true, copy_codec, options, &[])?;
+        Ok(command.to_args())
     }
 
-    args.push(output_file);
+    #[test]
+    fn test_merge_args_per_audio_mode() {
+        let replace = merge_args(true, &MergeOptions::default()).unwrap();
+        assert_eq!(replace[5..], ["-map", "0:v", "-map", "1:a", "-c", "copy", "-shortest", "out.mp4"]);
 
-    // Execute FFmpeg command
-    execute_ffmpeg(&args)?;
+        let add_track = MergeOptions { audio_mode: AudioMode::AddTrack, ..MergeOptions::default() };
+        assert_eq!(merge_args(false, &add_track).unwrap()[5..], [
+            "-map", "0:v", "-map", "0:a?", "-map", "1:a", "-c:v", "libx264", "-c:a", "aac", "-shortest", "out.mp4",
+        ]);
 
-    if !Path::new(output_file).exists() {
-        return Err(VideoToolkitError::OutputFileNotCreated);
+        let mix = MergeOptions { audio_mode: AudioMode::Mix, video_volume: 0.3, ..MergeOptions::default() };
+        let args = merge_args(false, &mix).unwrap();
+        assert!(args.windows(2).any(|pair| pair == [
+            "-filter_complex",
+            "[0:a]volume=0.3[a0];[1:a]volume=1[a1];[a0][a1]amix=inputs=2:duration=longest:normalize=0[a]",
+        ]));
+        assert!(args.windows(2).any(|pair| pair == ["-map", "[a]"]));
+        assert!(merge_args(true, &mix).is_err());
+
+        assert_eq!("add-track".parse::<AudioMode>().unwrap(), AudioMode::AddTrack);
+        assert!("both".parse::<AudioMode>().is_err());
     }
 
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn test_extraction_plan() {
+        // No extension: a container that fits the codec, stream copied
+        assert_eq!(plan_extraction(Path::new("tmp/audio"), "opus").unwrap(), (PathBuf::from("tmp/audio.opus"), "copy"));
+        assert_eq!(plan_extraction(Path::new("audio"), "aac").unwrap(), (PathBuf::from("audio.m4a"), "copy"));
+        assert_eq!(plan_extraction(Path::new("audio"), "pcm_s24le").unwrap(), (PathBuf::from("audio.wav"), "copy"));
+        assert_eq!(plan_extraction(Path::new("audio"), "truehd").unwrap(), (PathBuf::from("audio.mka"), "copy"));
+
+        // Requested format: copied when it fits, converted otherwise
+        assert_eq!(plan_extraction(Path::new("audio.aac"), "aac").unwrap(), (PathBuf::from("audio.aac"), "copy"));
+        assert_eq!(plan_extraction(Path::new("audio.AAC"), "opus").unwrap(), (PathBuf::from("audio.AAC"), "aac"));
+        assert_eq!(plan_extraction(Path::new("audio.ogg"), "opus").unwrap(), (PathBuf::from("audio.ogg"), "copy"));
+        assert_eq!(plan_extraction(Path::new("audio.mp3"), "flac").unwrap(), (PathBuf::from("audio.mp3"), "libmp3lame"));
+        assert!(plan_extraction(Path::new("audio.txt"), "aac").is_err());
+    }
+
+    #[test]
+    fn test_duration_policies() {
+        let looped = MergeOptions { duration_policy: DurationPolicy::LoopAudio, ..MergeOptions::default() };
+        let command = build_merge_command("in.mp4", "music.mp3", "out.mp4", false, true, &looped, &[]).unwrap();
+        assert_eq!(command.to_args(), [
+            "-y", "-i", "in.mp4", "-stream_loop", "-1", "-i", "music.mp3",
+            "-map", "0:v", "-map", "1:a", "-c", "copy", "-shortest", "out.mp4",
+        ]);
+
+        let padded = MergeOptions { duration_policy: DurationPolicy::PadSilence, ..MergeOptions::default() };
+        assert_eq!(merge_args(false, &padded).unwrap()[5..], [
+            "-filter_complex", "[1:a]apad[a]", "-map", "0:v", "-map", "[a]", "-c:v", "libx264", "-c:a", "aac", "-shortest", "out.mp4",
+        ]);
+        assert!(merge_args(true, &padded).is_err());
+
+        let padded_mix = MergeOptions { audio_mode: AudioMode::Mix, ..padded };
+        assert!(merge_args(false, &padded_mix).unwrap().contains(
+            &"[0:a]volume=1[a0];[1:a]volume=1,apad[a1];[a0][a1]amix=inputs=2:duration=first:normalize=0[a]".to_string()
+        ));
+
+        assert_eq!(
+            describe_durations(190.0, 151.0, false, DurationPolicy::LoopAudio),
+            "video 190.0s, audio 151.0s: audio looped to the end of the video"
+        );
+        assert_eq!(
+            describe_durations(60.0, 75.5, false, DurationPolicy::Shortest),
+            "video 60.0s, audio 75.5s: audio plays 15.5s past the end of the video"
+        );
+        assert_eq!(
+            describe_durations(60.0, 45.0, true, DurationPolicy::Shortest),
+            "video 60.0s, audio 45.0s: video cut to 45.0s"
+        );
+        assert_eq!("pad-silence".parse::<DurationPolicy>().unwrap(), DurationPolicy::PadSilence);
+    }
+
+    #[test]
+    fn test_loudness_normalization() {
+        let target = LoudnessTarget::default();
+        let options = MergeOptions { normalize_loudness: Some(target), ..MergeOptions::default() };
+        let loudnorm = loudnorm_filter(&target, None);
+        assert_eq!(loudnorm, "loudnorm=I=-16:TP=-1.5:LRA=11");
+
+        // The video is still copied, the audio is encoded again
+        let args = build_merge_command("in.mp4", "music.mp3", "out.mp4", false, true, &options, &[loudnorm]).unwrap().to_args();
+        assert_eq!(args[5..], [
+            "-filter_complex", "[1:a]loudnorm=I=-16:TP=-1.5:LRA=11[a]",
+            "-map", "0:v", "-map", "[a]", "-c:v", "copy", "-c:a", "aac", "out.mp4",
+        ]);
+
+        let stderr = "[Parsed_loudnorm_0 @ 0x5581]\n{\n\t\"input_i\" : \"-27.61\",\n\t\"input_tp\" : \"-4.47\",\n\t\"input_lra\" : \"18.06\",\n\t\"input_thresh\" : \"-39.20\",\n\t\"output_i\" : \"-16.58\",\n\t\"target_offset\" : \"0.58\"\n}";
+        let measured = parse_loudnorm_stats(stderr).unwrap();
+        assert_eq!(
+            loudnorm_filter(&target, Some(&measured)),
+            "loudnorm=I=-16:TP=-1.5:LRA=11:measured_I=-27.61:measured_TP=-4.47:measured_LRA=18.06:measured_thresh=-39.2:offset=0.58:linear=true"
+        );
+        assert!(parse_loudnorm_stats("no summary").is_err());
+
+        let mix = MergeOptions { audio_mode: AudioMode::Mix, duration_policy: DurationPolicy::LoopAudio, ..options };
+        let measure = build_measure_command("in.mp4", "music.mp3", &mix, &target).to_args();
+        assert!(!measure.contains(&"-stream_loop".to_string()));
+        assert!(measure[6].ends_with("amix=inputs=2:duration=longest:normalize=0,loudnorm=I=-16:TP=-1.5:LRA=11:print_format=json[a]"));
+        assert_eq!(measure[7..], ["-map", "[a]", "-f", "null", "-"]);
+
+        let too_loud = MergeOptions { normalize_loudness: Some(LoudnessTarget { i: 0.0, ..target }), ..MergeOptions::default() };
+        assert!(merge_args(false, &too_loud).is_err());
+    }
+
+    #[test]
+    fn test_subtitle_args() {
+        let soft = MergeOptions { subtitle_file: Some("subs.srt".to_string()), ..MergeOptions::default() };
+        let args = build_merge_command("in.mp4", "music.mp3", "out.mp4", false, true, &soft, &[]).unwrap().to_args();
+        assert_eq!(args[1..], [
+            "-i", "in.mp4", "-i", "music.mp3", "-i", "subs.srt",
+            "-map", "0:v", "-map", "1:a", "-map", "2:s", "-c", "copy", "-c:s", "mov_text", "out.mp4",
+        ]);
+        let args = build_merge_command("in.mp4", "music.mp3", "out.mkv", false, true, &soft, &[]).unwrap().to_args();
+        assert_eq!(args[args.len() - 3..], ["-c:s", "copy", "out.mkv"]);
+        assert!(build_merge_command("in.mp4", "music.mp3", "out.avi", false, true, &soft, &[]).is_err());
+
+        let burn = MergeOptions {
+            subtitle_file: Some(r"C:\subs\film.ass".to_string()),
+            subtitle_mode: SubtitleMode::Burn,
+            ..MergeOptions::default()
+        };
+        let args = build_merge_command("in.mp4", "music.mp3", "out.avi", false, false, &burn, &[]).unwrap().to_args();
+        assert_eq!(args[5..], [
+            "-map", "0:v", "-map", "1:a", "-vf", r"subtitles=filename=C\\:\\\\subs\\\\film.ass",
+            "-c:v", "libx264", "-c:a", "mp3", "out.avi",
+        ]);
+        assert!(build_merge_command("in.mp4", "music.mp3", "out.mp4", false, true, &burn, &[]).is_err());
+
+        let text = MergeOptions { subtitle_file: Some("subs.txt".to_string()), ..MergeOptions::default() };
+        assert!(merge_args(false, &text).is_err());
+    }
+
+    #[test]
+    fn test_audio_fades() {
+        let fades = MergeOptions { audio_fade_in_secs: Some(2.0), audio_fade_out_secs: Some(3.0), ..MergeOptions::default() };
+        assert_eq!(audio_end(60.0, 45.5, true, &fades), 45.5);
+        assert_eq!(audio_end(60.0, 45.5, false, &MergeOptions { duration_policy: DurationPolicy::LoopAudio, ..fades.clone() }), 60.0);
+        assert_eq!(audio_end(60.0, 75.0, false, &MergeOptions { audio_mode: AudioMode::Mix, ..fades.clone() }), 75.0);
+
+        let filters = fade_filters(&fades, Some(45.5)).unwrap();
+        assert_eq!(filters, ["afade=t=in:st=0:d=2", "afade=t=out:st=42.500:d=3"]);
+        assert!(fade_filters(&fades, None).is_err());
+
+        // Only the video is copied
+        let args = build_merge_command("in.mp4", "music.mp3", "out.mp4", true, true, &fades, &filters).unwrap().to_args();
+        assert_eq!(args[5..], [
+            "-filter_complex", "[1:a]afade=t=in:st=0:d=2,afade=t=out:st=42.500:d=3[a]",
+            "-map", "0:v", "-map", "[a]", "-c:v", "copy", "-c:a", "aac", "-shortest", "out.mp4",
+        ]);
+
+        assert!(merge_args(false, &MergeOptions { audio_fade_in_secs: Some(0.0), ..MergeOptions::default() }).is_err());
+    }
+
+    #[test]
+    fn test_output_container() {
+        assert_eq!(output_container("out/merged.webm", Some(" WEBM ")).unwrap(), "webm");
+        assert_eq!(output_container("merged.ts", None).unwrap(), "ts");
+        assert!(output_container("merged.mp4", Some("webm")).is_err());
+        assert!(validate_output_format("gif").is_err());
+
+        // H.264 cannot be copied into WebM, VP9/Opus can
+        let h264 = [("in.mp4", "h264".to_string()), ("music.mp3", "mp3".to_string())];
+        assert!(check_copied_streams("webm", &h264).unwrap_err().to_string().contains("h264 stream of 'in.mp4'"));
+        assert!(check_copied_streams("mp4", &h264).is_ok());
+        assert!(check_copied_streams("mkv", &h264).is_ok());
+        assert!(check_copied_streams("webm", &[("in.webm", "vp9".to_string()), ("a.opus", "opus".to_string())]).is_ok());
+
+        // Codecs follow the container; re-encoded audio too when the video is copied
+        let defaults = MergeOptions::default();
+        let args = build_merge_command("in.webm", "music.ogg", "out.webm", false, false, &defaults, &[]).unwrap().to_args();
+        assert_eq!(args[args.len() - 5..], ["-c:v", "libvpx", "-c:a", "libvorbis", "out.webm"]);
+        let fade = ["afade=t=in:st=0:d=1".to_string()];
+        let args = build_merge_command("in.webm", "music.ogg", "out.webm", false, true, &defaults, &fade).unwrap().to_args();
+        assert_eq!(args[args.len() - 5..], ["-c:v", "copy", "-c:a", "libvorbis", "out.webm"]);
+    }
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Sample script plugin: reports its text in the chosen case
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --text) text=$2 ;;
+        --case) letter_case=$2 ;;
+    esac
+    shift 2
+done
+
+if [ "$text" = fail ]; then
+    echo "asked to fail" >&2
+    exit 3
+fi
+
+if [ "$letter_case" = lower ]; then
+    text=$(echo "$text" | tr '[:upper:]' '[:lower:]')
+else
+    text=$(echo "$text" | tr '[:lower:]' '[:upper:]')
+fi
+
+echo "vtk:progress 0.5 Converting"
+echo "vtk:value text=$text"
+echo "vtk:file hello.txt"
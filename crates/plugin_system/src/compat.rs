@@ -0,0 +1,213 @@
+use std::fmt;
+use std::ops::BitOr;
+
+use serde::Deserialize;
+
+use crate::{PluginError, PluginMetadata, MIN_PLUGIN_API_VERSION, PLUGIN_API_VERSION};
+
+/// Plugin API versions a plugin works with
+///
+/// A plugin declares the oldest version whose features it uses, and a newest
+/// one only if it is known not to work with later versions. Versions only
+/// adding to the API keep older plugins loading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub struct ApiRequirement {
+    pub min: u32,
+    #[serde(default)]
+    pub max: Option<u32>,
+}
+
+impl ApiRequirement {
+    /// `min` or any later version
+    pub const fn at_least(min: u32) -> Self {
+        Self { min, max: None }
+    }
+
+    /// `min` to `max`, both included
+    pub const fn between(min: u32, max: u32) -> Self {
+        Self { min, max: Some(max) }
+    }
+
+    /// Versions this host supports
+    pub const fn host() -> Self {
+        Self::between(MIN_PLUGIN_API_VERSION, PLUGIN_API_VERSION)
+    }
+
+    /// Whether a version is in both `self` and `other`
+    pub fn overlaps(&self, other: &ApiRequirement) -> bool {
+        self.max.is_none_or(|max| max >= other.min) && other.max.is_none_or(|max| max >= self.min)
+    }
+}
+
+impl fmt::Display for ApiRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            None => write!(f, ">={}", self.min),
+            Some(max) if max == self.min => write!(f, "={}", max),
+            Some(max) => write!(f, ">={}, <={}", self.min, max),
+        }
+    }
+}
+
+/// Features of the host a plugin uses, as a set of flags
+///
+/// Flags this host does not know, set by plugins built for a later version,
+/// are kept so that such plugins are refused.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PluginCapabilities(u32);
+
+impl PluginCapabilities {
+    /// Progress, cancellation and the scratch directory of `ExecutionContext`
+    pub const CONTEXT: Self = Self(1);
+    /// Values and files returned in a `PluginOutput`
+    pub const OUTPUT: Self = Self(1 << 1);
+    /// Parameters of `ParameterType::Choice`
+    pub const CHOICE_PARAMETERS: Self = Self(1 << 2);
+
+    const NAMES: [(Self, &'static str); 3] = [
+        (Self::CONTEXT, "context"),
+        (Self::OUTPUT, "output"),
+        (Self::CHOICE_PARAMETERS, "choice_parameters"),
+    ];
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Flags of `self` that `other` lacks
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Flag of a name used in manifests, e.g. `context`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().find(|(_, known)| *known == name).map(|(flag, _)| *flag)
+    }
+}
+
+impl BitOr for PluginCapabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl fmt::Display for PluginCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let mut names: Vec<String> = Self::NAMES.iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name.to_string())
+            .collect();
+        let known = Self::NAMES.iter().fold(Self::empty(), |all, (flag, _)| all | *flag);
+        let unknown = self.difference(known);
+        if !unknown.is_empty() {
+            names.push(format!("unknown ({:#x})", unknown.bits()));
+        }
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// Features this host provides to plugins
+pub const HOST_CAPABILITIES: PluginCapabilities = PluginCapabilities::CONTEXT
+    .union(PluginCapabilities::OUTPUT)
+    .union(PluginCapabilities::CHOICE_PARAMETERS);
+
+/// Check a plugin's API requirement and capabilities against this host
+pub(crate) fn check_compatibility(
+    name: &str,
+    requirement: ApiRequirement,
+    capabilities: PluginCapabilities,
+) -> Result<(), PluginError> {
+    if !requirement.overlaps(&ApiRequirement::host()) {
+        return Err(PluginError::IncompatibleVersion {
+            name: name.to_string(),
+            required: requirement,
+            supported: ApiRequirement::host(),
+        });
+    }
+    let missing = capabilities.difference(HOST_CAPABILITIES);
+    if !missing.is_empty() {
+        return Err(PluginError::MissingCapabilities { name: name.to_string(), missing });
+    }
+    Ok(())
+}
+
+impl PluginMetadata {
+    /// Whether this host can run the plugin, or why not
+    pub fn check_compatibility(&self) -> Result<(), PluginError> {
+        check_compatibility(&self.name, self.api_requirement, self.capabilities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(api_requirement: ApiRequirement, capabilities: PluginCapabilities) -> PluginMetadata {
+        PluginMetadata {
+            name: "plugin".to_string(),
+            version: "1.0.0".to_string(),
+            author: String::new(),
+            description: String::new(),
+            api_requirement,
+            capabilities,
+        }
+    }
+
+    #[test]
+    fn test_compatibility_ranges_and_capabilities() {
+        // Plugins built for an older version load on a newer host
+        assert!(metadata(ApiRequirement::at_least(MIN_PLUGIN_API_VERSION), PluginCapabilities::empty())
+            .check_compatibility()
+            .is_ok());
+        assert!(metadata(ApiRequirement::between(1, 1), PluginCapabilities::empty()).check_compatibility().is_ok());
+        assert!(metadata(ApiRequirement::at_least(1), HOST_CAPABILITIES).check_compatibility().is_ok());
+
+        let error = metadata(ApiRequirement::at_least(PLUGIN_API_VERSION + 1), PluginCapabilities::empty())
+            .check_compatibility()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Plugin plugin needs plugin API >={}, this version supports >={}, <={}",
+                PLUGIN_API_VERSION + 1,
+                MIN_PLUGIN_API_VERSION,
+                PLUGIN_API_VERSION
+            )
+        );
+        assert!(metadata(ApiRequirement::between(0, 0), PluginCapabilities::empty()).check_compatibility().is_err());
+
+        // Only plugins using a feature the host lacks are refused
+        let future = PluginCapabilities::from_bits(1 << 20);
+        let error = metadata(ApiRequirement::at_least(1), PluginCapabilities::CONTEXT | future)
+            .check_compatibility()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Plugin plugin uses features this version lacks: unknown (0x100000)");
+        assert_eq!(PluginCapabilities::from_name("output"), Some(PluginCapabilities::OUTPUT));
+        assert_eq!(HOST_CAPABILITIES.to_string(), "context, output, choice_parameters");
+    }
+}
@@ -0,0 +1,314 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::compat::check_compatibility;
+use crate::{
+    open_library, ApiRequirement, LoadedPlugin, Plugin, PluginCapabilities, PluginError, PluginManager, PluginMetadata,
+    ScriptPlugin, SCRIPT_MANIFEST,
+};
+
+/// Description of a plugin stored next to its library, e.g. `watermark_plugin.toml`
+/// for `watermark_plugin.so`, read without loading the library
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    /// Plugin API versions the plugin works with, e.g. `{ min = 1, max = 2 }`
+    #[serde(default)]
+    pub api_requirement: Option<ApiRequirement>,
+    /// The API version the plugin was built for, read by manifests without
+    /// `api_requirement` as the oldest version it works with
+    #[serde(default)]
+    pub api_version: Option<u32>,
+    /// Names of the capabilities the plugin uses, e.g. `["context", "output"]`
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl PluginManifest {
+    /// Path of the manifest of `library`, whether or not it exists
+    pub fn path_for(library: &Path) -> PathBuf {
+        library.with_extension("toml")
+    }
+
+    pub fn read(path: &Path) -> Result<Self, PluginError> {
+        let contents = fs::read_to_string(path).map_err(|e| PluginError::LoadError(e.to_string()))?;
+        let manifest: Self = toml::from_str(&contents)
+            .map_err(|e| PluginError::InvalidPlugin(format!("Invalid manifest: {}", e)))?;
+        if manifest.api_requirement.is_none() && manifest.api_version.is_none() {
+            return Err(PluginError::InvalidPlugin("Invalid manifest: missing api_requirement".to_string()));
+        }
+        Ok(manifest)
+    }
+
+    pub fn requirement(&self) -> ApiRequirement {
+        self.api_requirement
+            .or(self.api_version.map(ApiRequirement::at_least))
+            .unwrap_or(ApiRequirement::at_least(0))
+    }
+
+    /// Whether this host can run the plugin, checked without loading it
+    ///
+    /// Capabilities this host does not know by name count as missing.
+    pub fn check_compatibility(&self) -> Result<(), PluginError> {
+        let unknown: Vec<&str> = self.capabilities.iter()
+            .filter(|name| PluginCapabilities::from_name(name).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(PluginError::InvalidPlugin(format!(
+                "{} uses capabilities this version does not know: {}",
+                self.name,
+                unknown.join(", ")
+            )));
+        }
+        let capabilities = self.capabilities.iter()
+            .filter_map(|name| PluginCapabilities::from_name(name))
+            .fold(PluginCapabilities::empty(), |all, flag| all | flag);
+        check_compatibility(&self.name, self.requirement(), capabilities)
+    }
+}
+
+/// What discovery did with one library
+#[derive(Debug)]
+pub enum DiscoveryOutcome {
+    Loaded(PluginMetadata),
+    /// Left alone without an error, e.g. already loaded; the reason is given
+    Skipped(String),
+    /// Available but disabled with `PluginManager::set_enabled`; the plugin's name is given
+    Disabled(String),
+    /// The error names the library
+    Failed(PluginError),
+}
+
+#[derive(Debug)]
+pub struct DiscoveredFile {
+    pub path: PathBuf,
+    pub outcome: DiscoveryOutcome,
+}
+
+/// Outcome of `PluginManager::discover_plugins` for every library found
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    /// Plugin directories that exist and were scanned
+    pub directories: Vec<PathBuf>,
+    pub files: Vec<DiscoveredFile>,
+}
+
+impl DiscoveryReport {
+    pub fn loaded(&self) -> impl Iterator<Item = &PluginMetadata> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            DiscoveryOutcome::Loaded(metadata) => Some(metadata),
+            _ => None,
+        })
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            DiscoveryOutcome::Skipped(reason) => Some((file.path.as_path(), reason.as_str())),
+            _ => None,
+        })
+    }
+
+    /// Names of the plugins found but left unloaded because they are disabled
+    pub fn disabled(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            DiscoveryOutcome::Disabled(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &PluginError> {
+        self.files.iter().filter_map(|file| match &file.outcome {
+            DiscoveryOutcome::Failed(error) => Some(error),
+            _ => None,
+        })
+    }
+}
+
+/// Whether `path` has the shared library extension of the platform
+fn is_plugin_library(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str());
+
+    #[cfg(target_os = "windows")]
+    let is_plugin = extension == Some("dll");
+
+    #[cfg(target_os = "linux")]
+    let is_plugin = extension == Some("so");
+
+    #[cfg(target_os = "macos")]
+    let is_plugin = extension == Some("dylib");
+
+    is_plugin
+}
+
+impl PluginManager {
+    /// Discover and load all plugins from `plugin_directories`:
+    /// dynamic libraries, and script plugins in subdirectories with a `plugin.toml`
+    ///
+    /// A library with a manifest is skipped without being loaded when its API
+    /// version is not supported, a plugin of its name is loaded already or it
+    /// is disabled. Libraries without one are disabled by their file name
+    /// (without a `lib` prefix), or loaded to learn their name and dropped again
+    /// if it is taken or disabled.
+    pub fn discover_plugins(&self) -> DiscoveryReport {
+        let mut report = DiscoveryReport::default();
+
+        for dir in self.plugin_directories() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            report.directories.push(dir);
+
+            let mut plugins: Vec<PathBuf> = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| is_plugin_library(path) || path.join(SCRIPT_MANIFEST).is_file())
+                .collect();
+            plugins.sort();
+
+            for path in plugins {
+                let discovered = if path.is_dir() { self.discover_script(&path) } else { self.discover_library(&path) };
+                let outcome = match discovered {
+                    Ok(outcome) => outcome,
+                    Err(e) => DiscoveryOutcome::Failed(PluginError::InFile { path: path.clone(), source: Box::new(e) }),
+                };
+                report.files.push(DiscoveredFile { path, outcome });
+            }
+        }
+
+        report
+    }
+
+    fn discover_library(&self, path: &Path) -> Result<DiscoveryOutcome, PluginError> {
+        let manifest_path = PluginManifest::path_for(path);
+        let manifest = if manifest_path.is_file() { Some(PluginManifest::read(&manifest_path)?) } else { None };
+
+        if let Some(ref manifest) = manifest {
+            if let Err(e) = manifest.check_compatibility() {
+                return Ok(DiscoveryOutcome::Skipped(e.to_string()));
+            }
+            if self.is_loaded(&manifest.name) {
+                return Ok(DiscoveryOutcome::Skipped(format!("{} is already loaded", manifest.name)));
+            }
+        }
+
+        let known_name = match manifest {
+            Some(ref manifest) => Some(manifest.name.clone()),
+            None => path.file_stem().map(|stem| {
+                let stem = stem.to_string_lossy();
+                stem.strip_prefix("lib").unwrap_or(&stem).to_string()
+            }),
+        };
+        if let Some(name) = known_name.filter(|name| !self.is_enabled(name)) {
+            return Ok(DiscoveryOutcome::Disabled(name));
+        }
+
+        let loaded = open_library(path)?;
+        let metadata = loaded.plugin().metadata();
+        let skip = if let Some(manifest) = manifest.filter(|manifest| manifest.name != metadata.name) {
+            Some(Err(PluginError::InvalidPlugin(format!(
+                "manifest names the plugin {}, the library {}",
+                manifest.name, metadata.name
+            ))))
+        } else if self.is_loaded(&metadata.name) {
+            Some(Ok(DiscoveryOutcome::Skipped(format!("{} is already loaded", metadata.name))))
+        } else if !self.is_enabled(&metadata.name) {
+            Some(Ok(DiscoveryOutcome::Disabled(metadata.name.clone())))
+        } else {
+            None
+        };
+
+        if let Some(outcome) = skip {
+            let _ = loaded.plugin().shutdown();
+            return outcome;
+        }
+
+        self.register(loaded);
+        Ok(DiscoveryOutcome::Loaded(metadata))
+    }
+
+    fn discover_script(&self, dir: &Path) -> Result<DiscoveryOutcome, PluginError> {
+        let plugin = ScriptPlugin::from_dir(dir)?;
+        let metadata = plugin.metadata();
+        if self.is_loaded(&metadata.name) {
+            return Ok(DiscoveryOutcome::Skipped(format!("{} is already loaded", metadata.name)));
+        }
+        if !self.is_enabled(&metadata.name) {
+            return Ok(DiscoveryOutcome::Disabled(metadata.name));
+        }
+
+        self.register(LoadedPlugin::new(Box::new(plugin), None));
+        Ok(DiscoveryOutcome::Loaded(metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discovery_skips_incompatible_manifests_and_reports_paths() {
+        let dir = std::env::temp_dir().join(format!("vtk_plugin_discovery_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let extension = if cfg!(windows) { "dll" } else if cfg!(target_os = "macos") { "dylib" } else { "so" };
+        let library = |name: &str| dir.join(name).with_extension(extension);
+
+        // Not a library, but the manifest keeps it from being opened
+        fs::write(library("future"), b"not a library").unwrap();
+        fs::write(dir.join("future.toml"), "name = \"future\"\nversion = \"1.0.0\"\napi_version = 99\n").unwrap();
+        fs::write(library("teleport"), b"not a library").unwrap();
+        fs::write(
+            dir.join("teleport.toml"),
+            "name = \"teleport\"\nversion = \"1.0.0\"\napi_requirement = { min = 1 }\ncapabilities = [\"context\", \"teleport\"]\n",
+        )
+        .unwrap();
+        fs::write(library("broken"), b"not a library either").unwrap();
+        fs::write(library("bad_manifest"), b"").unwrap();
+        fs::write(dir.join("bad_manifest.toml"), "name = ").unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("echo_script");
+        fs::create_dir_all(dir.join("echo")).unwrap();
+        fs::copy(fixture.join(SCRIPT_MANIFEST), dir.join("echo").join(SCRIPT_MANIFEST)).unwrap();
+
+        let mut manager = PluginManager::new().unwrap().with_state_file(dir.join("plugins.json"));
+        manager.plugin_dirs = vec![dir.clone()];
+        let report = manager.discover_plugins();
+        assert_eq!(report.directories, std::slice::from_ref(&dir));
+
+        let loaded: Vec<&str> = report.loaded().map(|metadata| metadata.name.as_str()).collect();
+        assert_eq!(loaded, ["echo_script"]);
+        let skipped: Vec<_> = report.skipped().collect();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].0, library("future"));
+        assert!(skipped[0].1.contains("needs plugin API >=99, this version supports"), "{}", skipped[0].1);
+        assert_eq!(skipped[1].0, library("teleport"));
+        assert!(skipped[1].1.ends_with("capabilities this version does not know: teleport"), "{}", skipped[1].1);
+
+        let failed: Vec<String> = report.failed().map(ToString::to_string).collect();
+        assert_eq!(failed.len(), 2);
+        assert!(failed[0].starts_with(&library("bad_manifest").display().to_string()), "{}", failed[0]);
+        assert!(failed[0].contains("Invalid manifest"));
+        assert!(failed[1].starts_with(&library("broken").display().to_string()), "{}", failed[1]);
+
+        // Plugins loaded before are not loaded again
+        let again = manager.discover_plugins();
+        assert_eq!(again.loaded().count(), 0);
+        assert!(again.skipped().any(|(path, reason)| path == dir.join("echo") && reason == "echo_script is already loaded"));
+
+        // Disabled plugins are reported but not loaded, even without a manifest
+        let mut fresh = PluginManager::new().unwrap().with_state_file(dir.join("plugins.json"));
+        fresh.plugin_dirs = vec![dir.clone()];
+        fresh.set_enabled("echo_script", false).unwrap();
+        fresh.set_enabled("broken", false).unwrap();
+        let report = fresh.discover_plugins();
+        assert_eq!(report.loaded().count(), 0);
+        assert_eq!(report.disabled().collect::<Vec<_>>(), ["broken", "echo_script"]);
+        assert!(!fresh.is_loaded("echo_script"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
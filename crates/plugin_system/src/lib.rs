@@ -1,11 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::any::Any;
+use std::mem::ManuallyDrop;
 
+use common::{cancellation_scope, deadline_scope, CancellationToken, RunLimits, VideoToolkitError};
 use libloading::{Library, Symbol};
 use thiserror::Error;
 
+mod compat;
+mod discovery;
+mod script;
+mod state;
+
+pub use compat::{ApiRequirement, PluginCapabilities, HOST_CAPABILITIES};
+pub use discovery::{DiscoveredFile, DiscoveryOutcome, DiscoveryReport, PluginManifest};
+pub use script::{ParameterPassing, ScriptPlugin, SCRIPT_MANIFEST};
+pub use state::PLUGIN_PATH_VAR;
+
 /// Errors specific to the plugin system
 #[derive(Error, Debug)]
 pub enum PluginError {
@@ -21,12 +39,62 @@ pub enum PluginError {
     #[error("Plugin not found: {0}")]
     NotFound(String),
 
-    #[error("Plugin {0} is not compatible with this version")]
-    IncompatibleVersion(String),
+    #[error("Plugin {name} needs plugin API {required}, this version supports {supported}")]
+    IncompatibleVersion {
+        name: String,
+        required: ApiRequirement,
+        supported: ApiRequirement,
+    },
+
+    #[error("Plugin {name} uses features this version lacks: {missing}")]
+    MissingCapabilities {
+        name: String,
+        missing: PluginCapabilities,
+    },
+
+    #[error("Plugin settings could not be saved: {0}")]
+    StateError(String),
+
+    #[error("{}: {source}", .path.display())]
+    InFile {
+        path: PathBuf,
+        source: Box<PluginError>,
+    },
+
+    #[error("Invalid plugin parameters: {}", .0.iter().map(ParamIssue::to_string).collect::<Vec<_>>().join("; "))]
+    InvalidParameters(Vec<ParamIssue>),
+
+    #[error("Plugin {0} did not finish within {1:?}")]
+    Timeout(String, Duration),
+
+    #[error("Plugin crashed: {0}")]
+    Crashed(String),
+
+    #[error("Plugin {0} is still running and cannot be unloaded")]
+    InUse(String),
+}
+
+/// A problem with one parameter given to a plugin
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamIssue {
+    /// Name of the parameter
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for ParamIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.message)
+    }
 }
 
-/// Plugin API version to ensure compatibility
-pub const PLUGIN_API_VERSION: u32 = 1;
+/// Plugin API version of this host, checked against each plugin's `ApiRequirement`
+///
+/// Version 2 added `Plugin::execute_with_context` and its `PluginOutput`.
+pub const PLUGIN_API_VERSION: u32 = 2;
+
+/// Oldest plugin API version this host still supports
+pub const MIN_PLUGIN_API_VERSION: u32 = 1;
 
 /// Represents the metadata of a plugin
 #[derive(Clone, Debug)]
@@ -35,7 +103,10 @@ pub struct PluginMetadata {
     pub version: String,
     pub author: String,
     pub description: String,
-    pub api_version: u32,
+    /// Plugin API versions the plugin works with
+    pub api_requirement: ApiRequirement,
+    /// Host features the plugin uses; it is refused by hosts lacking any of them
+    pub capabilities: PluginCapabilities,
 }
 
 /// Trait that must be implemented by all plugins
@@ -49,6 +120,20 @@ pub trait Plugin: Send + Sync {
     /// Execute the plugin's functionality with the given parameters
     fn execute(&self, params: HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>>;
 
+    /// Execute the plugin, reporting progress and stopping when cancelled through `ctx`,
+    /// and return what it produced
+    ///
+    /// Plugins written for API version 1 only implement `execute`, which this
+    /// calls by default, returning an empty output.
+    fn execute_with_context(
+        &self,
+        params: HashMap<String, String>,
+        ctx: &ExecutionContext,
+    ) -> Result<PluginOutput, Box<dyn std::error::Error>> {
+        let _ = ctx;
+        self.execute(params).map(|()| PluginOutput::default())
+    }
+
     /// Get information about the parameters the plugin accepts
     fn get_parameter_info(&self) -> Vec<ParameterInfo>;
 
@@ -75,29 +160,302 @@ pub enum ParameterType {
     String,
     Integer,
     Float,
+    /// `true` or `false`
     Boolean,
+    /// An existing file; declare output paths as `String`
     FilePath,
+    /// An existing directory
     DirectoryPath,
+    /// One of the given values, compared exactly
+    Choice(Vec<String>),
+}
+
+/// Check `params` against the parameters a plugin declares, filling in the
+/// defaults of the optional ones that are missing
+///
+/// Empty values count as missing. Parameters the plugin does not declare are
+/// passed on unchecked. Every problem found is returned, not only the first.
+pub fn validate_parameters(
+    info: &[ParameterInfo],
+    mut params: HashMap<String, String>,
+) -> Result<HashMap<String, String>, PluginError> {
+    params.retain(|_, value| !value.trim().is_empty());
+
+    let mut issues = Vec::new();
+    for parameter in info {
+        let issue = |message: String| ParamIssue { name: parameter.name.clone(), message };
+        let Some(value) = params.get(&parameter.name) else {
+            match &parameter.default_value {
+                Some(default) => {
+                    params.insert(parameter.name.clone(), default.clone());
+                },
+                None if parameter.required => issues.push(issue("required parameter is missing".to_string())),
+                None => {},
+            }
+            continue;
+        };
+
+        let value = value.trim();
+        let problem = match parameter.parameter_type {
+            ParameterType::String => None,
+            ParameterType::Integer => value.parse::<i64>().err().map(|_| format!("'{}' is not a whole number", value)),
+            ParameterType::Float => value.parse::<f64>().err().map(|_| format!("'{}' is not a number", value)),
+            ParameterType::Boolean => value.parse::<bool>().err().map(|_| format!("'{}' is not true or false", value)),
+            ParameterType::FilePath => (!Path::new(value).is_file()).then(|| format!("file '{}' does not exist", value)),
+            ParameterType::DirectoryPath => {
+                (!Path::new(value).is_dir()).then(|| format!("directory '{}' does not exist", value))
+            },
+            ParameterType::Choice(ref choices) => (!choices.iter().any(|choice| choice == value))
+                .then(|| format!("'{}' is not one of: {}", value, choices.join(", "))),
+        };
+        issues.extend(problem.map(issue));
+    }
+
+    if issues.is_empty() {
+        Ok(params)
+    } else {
+        Err(PluginError::InvalidParameters(issues))
+    }
+}
+
+/// What a plugin run produced: named values, e.g. measurements, and the files it wrote
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PluginOutput {
+    pub values: BTreeMap<String, String>,
+    pub files: Vec<PathBuf>,
+}
+
+impl PluginOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named value
+    pub fn with_value(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Add a file the run produced
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.files.is_empty()
+    }
+}
+
+/// Callback receiving the fraction of a plugin run that is done and what it is doing
+pub type ProgressCallback = Arc<dyn Fn(f32, &str) + Send + Sync>;
+
+/// What a plugin run can use besides its parameters: somewhere to report
+/// progress, a cancellation token, a scratch directory and a time limit
+#[derive(Clone)]
+pub struct ExecutionContext {
+    progress: Option<ProgressCallback>,
+    cancel: CancellationToken,
+    temp_dir: PathBuf,
+    timeout: Option<Duration>,
+}
+
+impl Default for ExecutionContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExecutionContext {
+    /// Context without a progress callback, with a token nobody else cancels
+    /// and a scratch directory of its own under the system temp directory
+    pub fn new() -> Self {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let run = RUNS.fetch_add(1, Ordering::Relaxed);
+        Self {
+            progress: None,
+            cancel: CancellationToken::new(),
+            temp_dir: std::env::temp_dir().join(format!("vtk_plugin_{}_{}", std::process::id(), run)),
+            timeout: None,
+        }
+    }
+
+    /// Pass the progress reports of the run to `progress`
+    pub fn with_progress(mut self, progress: impl Fn(f32, &str) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Stop the run once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Use `dir` as the scratch directory instead of a new temporary one
+    pub fn with_temp_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.temp_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Give up on the run after `timeout`, failing it with `PluginError::Timeout`
+    ///
+    /// The plugin sees its run cancelled then and its FFmpeg runs are killed, but
+    /// a plugin that ignores both keeps its thread until it returns.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Report that `fraction` (0.0 to 1.0) of the run is done
+    pub fn report_progress(&self, fraction: f32, message: &str) {
+        if let Some(ref progress) = self.progress {
+            progress(fraction.clamp(0.0, 1.0), message);
+        }
+    }
+
+    /// Token a plugin should check between steps; FFmpeg runs through `common` honor it already
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Directory for intermediate files; it exists while the plugin runs
+    pub fn temp_dir(&self) -> &Path {
+        &self.temp_dir
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// Run a plugin on the current thread, with the scratch directory and the
+/// cancellation and deadline of `ctx` in place
+fn run_plugin(
+    plugin: &dyn Plugin,
+    params: HashMap<String, String>,
+    ctx: &ExecutionContext,
+) -> Result<PluginOutput, Box<dyn std::error::Error>> {
+    let params = validate_parameters(&plugin.get_parameter_info(), params)?;
+    ctx.cancellation().check()?;
+
+    let created = !ctx.temp_dir().exists();
+    std::fs::create_dir_all(ctx.temp_dir())?;
+    let result = {
+        let _scope = cancellation_scope(Some(ctx.cancellation()));
+        let _deadline = deadline_scope(ctx.timeout());
+        plugin.execute_with_context(params, ctx)
+    };
+    if created {
+        let _ = std::fs::remove_dir_all(ctx.temp_dir());
+    }
+    result
+}
+
+/// Make a plugin error fit to cross threads, keeping the errors callers look into
+fn into_sendable(error: Box<dyn std::error::Error>) -> Box<dyn std::error::Error + Send + Sync> {
+    let error = match error.downcast::<PluginError>() {
+        Ok(error) => return error,
+        Err(error) => error,
+    };
+    match error.downcast::<VideoToolkitError>() {
+        Ok(error) => error,
+        Err(error) => error.to_string().into(),
+    }
+}
+
+/// Message of a caught panic
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(|| "unknown panic".to_string(), |message| message.to_string()),
+    }
 }
 
 /// Type definition for the plugin creation function that must be exported by plugin libraries
 pub type CreatePluginFunc = unsafe fn() -> *mut dyn Plugin;
 
+/// A plugin and the library its code lives in, if any
+///
+/// Dropping it drops the plugin before unloading the library, so whoever holds
+/// an `Arc<LoadedPlugin>` can call into the plugin however the manager changes.
+struct LoadedPlugin {
+    plugin: ManuallyDrop<Box<dyn Plugin>>,
+    library: Option<Library>,
+}
+
+impl LoadedPlugin {
+    fn new(plugin: Box<dyn Plugin>, library: Option<Library>) -> Self {
+        Self { plugin: ManuallyDrop::new(plugin), library }
+    }
+
+    fn plugin(&self) -> &dyn Plugin {
+        self.plugin.as_ref()
+    }
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        // SAFETY: the plugin is not used again; its code is still mapped until
+        // the library is dropped below
+        unsafe { ManuallyDrop::drop(&mut self.plugin) };
+        drop(self.library.take());
+    }
+}
+
+/// Load a dynamic library and create and initialize its plugin
+fn open_library(path: &Path) -> Result<LoadedPlugin, PluginError> {
+    // Load the dynamic library
+    let lib = unsafe {
+        Library::new(path).map_err(|e| PluginError::LoadError(e.to_string()))?
+    };
+
+    // Get the plugin creation function
+    let constructor: Symbol<CreatePluginFunc> = unsafe {
+        lib.get(b"create_plugin")
+            .map_err(|e| PluginError::InvalidPlugin(format!("Missing create_plugin symbol: {}", e)))?
+    };
+
+    // Create the plugin instance
+    let plugin_ptr = unsafe { constructor() };
+    if plugin_ptr.is_null() {
+        return Err(PluginError::InitError("Plugin creation returned null".to_string()));
+    }
+
+    let mut loaded = LoadedPlugin::new(unsafe { Box::from_raw(plugin_ptr) }, Some(lib));
+
+    // Check API version and capability compatibility, so an incompatible plugin is never initialized
+    loaded.plugin().metadata().check_compatibility()?;
+
+    // Initialize the plugin
+    loaded.plugin.initialize()
+        .map_err(|e| PluginError::InitError(e.to_string()))?;
+
+    Ok(loaded)
+}
+
 /// Manages loading and interaction with plugins
 #[derive(Clone)]
 pub struct PluginManager {
-    plugins: Arc<Mutex<HashMap<String, Box<dyn Plugin>>>>,
-    libraries: Arc<Mutex<HashMap<String, Library>>>,
+    /// Runs hold a clone of their plugin's entry until they end
+    plugins: Arc<Mutex<HashMap<String, Arc<LoadedPlugin>>>>,
     plugin_dirs: Vec<PathBuf>,
+    /// File keeping which plugins are disabled
+    state_file: Option<PathBuf>,
 }
 
 impl PluginManager {
-    /// Create a new plugin manager with the default plugin directory
+    /// Create a new plugin manager searching the default plugin directories,
+    /// see `plugin_directories`
     pub fn new() -> Result<Self, PluginError> {
         Ok(Self {
             plugins: Arc::new(Mutex::new(HashMap::new())),
-            libraries: Arc::new(Mutex::new(HashMap::new())),
-            plugin_dirs: vec![PathBuf::from("plugins")],
+            plugin_dirs: state::default_plugin_dirs(),
+            state_file: state::default_state_file(),
         })
     }
 
@@ -106,87 +464,28 @@ impl PluginManager {
         self.plugin_dirs.push(dir.as_ref().to_path_buf());
     }
 
-    /// Load a plugin from a dynamic library
-    pub fn load_plugin<P: AsRef<Path>>(&self, path: P) -> Result<(), PluginError> {
+    /// Load a plugin from a dynamic library, or a script plugin from its directory
+    pub fn load_plugin<P: AsRef<Path>>(&self, path: P) -> Result<PluginMetadata, PluginError> {
         let path = path.as_ref();
-
-        // Load the dynamic library
-        let lib = unsafe {
-            Library::new(path).map_err(|e| PluginError::LoadError(e.to_string()))?
+        let loaded = if path.is_dir() {
+            LoadedPlugin::new(Box::new(ScriptPlugin::from_dir(path)?), None)
+        } else {
+            open_library(path)?
         };
-
-        // Get the plugin creation function
-        let constructor: Symbol<CreatePluginFunc> = unsafe {
-            lib.get(b"create_plugin")
-                .map_err(|e| PluginError::InvalidPlugin(format!("Missing create_plugin symbol: {}", e)))?
-        };
-
-        // Create the plugin instance
-        let plugin_ptr = unsafe { constructor() };
-        if plugin_ptr.is_null() {
-            return Err(PluginError::InitError("Plugin creation returned null".to_string()));
-        }
-
-        let mut plugin = unsafe { Box::from_raw(plugin_ptr) };
-
-        // Initialize the plugin
-        plugin.initialize()
-            .map_err(|e| PluginError::InitError(e.to_string()))?;
-
-        // Check API version compatibility
-        let metadata = plugin.metadata();
-        if metadata.api_version != PLUGIN_API_VERSION {
-            return Err(PluginError::IncompatibleVersion(metadata.name.clone()));
-        }
-
-        // Store the plugin and library
-        let plugin_name = metadata.name.clone();
-        self.plugins.lock().unwrap().insert(plugin_name.clone(), plugin);
-        self.libraries.lock().unwrap().insert(plugin_name, lib);
-
-        Ok(())
+        let metadata = loaded.plugin().metadata();
+        self.register(loaded);
+        Ok(metadata)
     }
 
-    /// Discover and load all plugins from the configured plugin directories
-    pub fn discover_plugins(&self) -> Vec<Result<PluginMetadata, PluginError>> {
-        let mut results = Vec::new();
-
-        for dir in &self.plugin_dirs {
-            if !dir.exists() || !dir.is_dir() {
-                continue;
-            }
-
-            if let Ok(entries) = std::fs::read_dir(dir) {
-                for entry in entries.filter_map(Result::ok) {
-                    let path = entry.path();
-                    let extension = path.extension().and_then(|e| e.to_str());
-
-                    // Check for platform-specific plugin extensions
-                    #[cfg(target_os = "windows")]
-                    let is_plugin = extension == Some("dll");
-
-                    #[cfg(target_os = "linux")]
-                    let is_plugin = extension == Some("so");
-
-                    #[cfg(target_os = "macos")]
-                    let is_plugin = extension == Some("dylib");
-
-                    if is_plugin {
-                        match self.load_plugin(&path) {
-                            Ok(()) => {
-                                let plugin_name = path.file_stem().unwrap().to_string_lossy().to_string();
-                                if let Some(metadata) = self.with_plugin(&plugin_name, |plugin| plugin.metadata()) {
-                                    results.push(Ok(metadata));
-                                }
-                            },
-                            Err(e) => results.push(Err(e)),
-                        }
-                    }
-                }
-            }
-        }
+    /// Store a loaded plugin under its name
+    fn register(&self, loaded: LoadedPlugin) {
+        let plugin_name = loaded.plugin().metadata().name;
+        self.plugins.lock().unwrap().insert(plugin_name, Arc::new(loaded));
+    }
 
-        results
+    /// Whether a plugin of this name is loaded
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.plugins.lock().unwrap().contains_key(name)
     }
 
     /// Get a plugin by name and execute a function on it
@@ -195,7 +494,7 @@ impl PluginManager {
         F: FnOnce(&dyn Plugin) -> R,
     {
         let plugins = self.plugins.lock().unwrap();
-        plugins.get(name).map(|plugin| f(plugin.as_ref()))
+        plugins.get(name).map(|loaded| f(loaded.plugin()))
     }
     
     /// Get parameter info for a plugin
@@ -203,40 +502,102 @@ impl PluginManager {
         self.with_plugin(name, |plugin| plugin.get_parameter_info())
     }
     
-    /// Execute a plugin with the given parameters
-    pub fn execute_plugin(&self, name: &str, params: HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
-        match self.with_plugin(name, |plugin| plugin.execute(params.clone())) {
-            Some(result) => result,
-            None => Err(Box::new(PluginError::NotFound(name.to_string())))
+    /// Execute a plugin with the given parameters, once they are checked with `validate_parameters`
+    ///
+    /// Invalid parameters fail with `PluginError::InvalidParameters` without running the plugin.
+    pub fn execute_plugin(&self, name: &str, params: HashMap<String, String>) -> Result<PluginOutput, Box<dyn std::error::Error>> {
+        self.execute_plugin_with_context(name, params, &ExecutionContext::new())
+    }
+
+    /// Execute a plugin like `execute_plugin`, with progress reporting, cancellation
+    /// and an optional time limit through `ctx`
+    ///
+    /// The plugin runs on a thread of its own. A panic fails the run with
+    /// `PluginError::Crashed`, and a run still going after the timeout of `ctx`
+    /// fails with `PluginError::Timeout`; its thread is left to finish, or leaked
+    /// if the plugin never returns. The scratch directory of `ctx` is created for
+    /// the run if missing, and then removed after it.
+    pub fn execute_plugin_with_context(
+        &self,
+        name: &str,
+        params: HashMap<String, String>,
+        ctx: &ExecutionContext,
+    ) -> Result<PluginOutput, Box<dyn std::error::Error>> {
+        let plugin = self.plugins.lock().unwrap().get(name).cloned()
+            .ok_or_else(|| PluginError::NotFound(name.to_string()))?;
+
+        // The run gets a token of its own, so that a timeout does not cancel the caller's
+        let run_token = CancellationToken::new();
+        if ctx.is_cancelled() {
+            run_token.cancel();
+        }
+        let (sender, received) = mpsc::channel();
+        let run_ctx = ctx.clone().with_cancellation(run_token.clone());
+        let limits = RunLimits::current();
+        thread::Builder::new().name(format!("plugin {}", name)).spawn(move || {
+            let _limits = limits.enter();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_plugin(plugin.plugin(), params, &run_ctx).map_err(into_sendable)
+            }));
+            let _ = sender.send(result);
+        })?;
+
+        let deadline = ctx.timeout().map(|timeout| Instant::now() + timeout);
+        let result = loop {
+            match received.recv_timeout(Duration::from_millis(50)) {
+                Err(RecvTimeoutError::Timeout) => {
+                    if ctx.is_cancelled() {
+                        run_token.cancel();
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        run_token.cancel();
+                        return Err(Box::new(PluginError::Timeout(name.to_string(), ctx.timeout().unwrap_or_default())));
+                    }
+                },
+                result => break result,
+            }
+        };
+        match result {
+            Ok(Ok(result)) => result.map_err(|e| e as Box<dyn std::error::Error>),
+            Ok(Err(payload)) => Err(Box::new(PluginError::Crashed(panic_message(payload)))),
+            Err(_) => Err(Box::new(PluginError::Crashed("the plugin thread ended without a result".to_string()))),
         }
     }
 
     /// Get metadata for all loaded plugins
     pub fn get_all_plugin_metadata(&self) -> Vec<PluginMetadata> {
         let plugins = self.plugins.lock().unwrap();
-        plugins.values().map(|p| p.metadata()).collect()
+        plugins.values().map(|loaded| loaded.plugin().metadata()).collect()
     }
 
     /// Unload a plugin by name
+    ///
+    /// A plugin that is still running, e.g. after its run timed out, stays
+    /// loaded and `PluginError::InUse` is returned.
     pub fn unload_plugin(&self, name: &str) -> Result<(), PluginError> {
-        // Get the plugin
+        // Runs take their clone of the entry under this lock, so none can start meanwhile
         let mut plugins = self.plugins.lock().unwrap();
-        let plugin = plugins.remove(name).ok_or_else(|| PluginError::NotFound(name.to_string()))?;
-
-        // Shut down the plugin
-        plugin.shutdown().map_err(|e| PluginError::InitError(e.to_string()))?;
-
-        // Remove the library
-        let mut libraries = self.libraries.lock().unwrap();
-        libraries.remove(name);
+        let loaded = plugins.get(name).ok_or_else(|| PluginError::NotFound(name.to_string()))?;
+        if Arc::strong_count(loaded) > 1 {
+            return Err(PluginError::InUse(name.to_string()));
+        }
+        let loaded = plugins.remove(name).expect("plugin is in the map");
+        drop(plugins);
 
-        Ok(())
+        // Shut down the plugin; the library is unloaded with the entry
+        loaded.plugin().shutdown().map_err(|e| PluginError::InitError(e.to_string()))
     }
 }
 
 impl Drop for PluginManager {
     fn drop(&mut self) {
-        // Make sure all plugins are properly shut down
+        // Clones share the plugins; only the last one shuts them down
+        if Arc::strong_count(&self.plugins) > 1 {
+            return;
+        }
+
+        // Make sure all plugins are properly shut down; those still running are
+        // dropped, and their libraries unloaded, when their runs end
         let plugin_names: Vec<String> = self.plugins.lock().unwrap().keys().cloned().collect();
         for name in plugin_names {
             let _ = self.unload_plugin(&name);
@@ -273,7 +634,8 @@ pub mod example {
                     version: "0.1.0".to_string(),
                     author: "Video-ToolKit Team".to_string(),
                     description: "An example plugin that demonstrates the plugin system".to_string(),
-                    api_version: PLUGIN_API_VERSION,
+                    api_requirement: ApiRequirement::at_least(1),
+                    capabilities: PluginCapabilities::empty(),
                 },
             }
         }
@@ -308,7 +670,7 @@ pub mod example {
                     description: "Where to save the result".to_string(),
                     required: true,
                     default_value: None,
-                    parameter_type: ParameterType::FilePath,
+                    parameter_type: ParameterType::String,
                 },
             ]
         }
@@ -326,4 +688,274 @@ pub mod example {
 
 // Export the example plugin (only when building as a dynamic library)
 #[cfg(feature = "dynamic")]
-export_plugin!(example::ExamplePlugin);
\ No newline at end of file
+export_plugin!(example::ExamplePlugin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    fn parameter(name: &str, required: bool, default_value: Option<&str>, parameter_type: ParameterType) -> ParameterInfo {
+        ParameterInfo {
+            name: name.to_string(),
+            description: String::new(),
+            required,
+            default_value: default_value.map(str::to_string),
+            parameter_type,
+        }
+    }
+
+    #[test]
+    fn test_validate_parameters() {
+        let info = [
+            parameter("input", true, None, ParameterType::FilePath),
+            parameter("font_size", false, Some("24"), ParameterType::Integer),
+            parameter("opacity", false, None, ParameterType::Float),
+            parameter("bold", false, None, ParameterType::Boolean),
+            parameter("output_dir", false, None, ParameterType::DirectoryPath),
+            parameter("position", false, None, ParameterType::Choice(vec!["top".to_string(), "bottom".to_string()])),
+        ];
+        let params = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+        };
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let dir = env!("CARGO_MANIFEST_DIR");
+
+        let valid = validate_parameters(&info, params(&[("input", manifest), ("opacity", ""), ("output_dir", dir)])).unwrap();
+        assert_eq!(valid, params(&[("input", manifest), ("font_size", "24"), ("output_dir", dir)]));
+
+        let Err(PluginError::InvalidParameters(issues)) = validate_parameters(&info, params(&[
+            ("font_size", "abc"),
+            ("opacity", "0.5"),
+            ("bold", "yes"),
+            ("output_dir", manifest),
+            ("position", "Top"),
+        ])) else {
+            panic!("invalid parameters accepted");
+        };
+        let names: Vec<&str> = issues.iter().map(|issue| issue.name.as_str()).collect();
+        assert_eq!(names, ["input", "font_size", "bold", "output_dir", "position"]);
+        assert_eq!(issues[1].to_string(), "font_size: 'abc' is not a whole number");
+        assert_eq!(issues[4].message, "'Top' is not one of: top, bottom");
+    }
+
+    /// Plugin reporting progress and whether its scratch directory exists, or
+    /// misbehaving as its `fault` parameter asks
+    struct ProgressPlugin;
+
+    impl Plugin for ProgressPlugin {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                name: "progress".to_string(),
+                version: "0.1.0".to_string(),
+                author: String::new(),
+                description: String::new(),
+                api_requirement: ApiRequirement::at_least(2),
+                capabilities: PluginCapabilities::CONTEXT | PluginCapabilities::OUTPUT,
+            }
+        }
+
+        fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        fn execute(&self, _params: HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+            Err("called without a context".into())
+        }
+
+        fn execute_with_context(
+            &self,
+            params: HashMap<String, String>,
+            ctx: &ExecutionContext,
+        ) -> Result<PluginOutput, Box<dyn std::error::Error>> {
+            match params.get("fault").map(String::as_str) {
+                Some("panic") => panic!("fault injected"),
+                Some("hang") => {
+                    while !ctx.is_cancelled() {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                },
+                _ => {},
+            }
+            ctx.report_progress(0.5, &format!("scratch: {}", ctx.temp_dir().is_dir()));
+            Ok(PluginOutput::new().with_value("fraction", 0.5).with_file("report.txt"))
+        }
+
+        fn get_parameter_info(&self) -> Vec<ParameterInfo> {
+            Vec::new()
+        }
+
+        fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_execute_with_context() {
+        let manager = PluginManager::new().unwrap();
+        manager.register(LoadedPlugin::new(Box::new(ProgressPlugin), None));
+        manager.register(LoadedPlugin::new(Box::new(example::ExamplePlugin::new()), None));
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&reports);
+        let ctx = ExecutionContext::new()
+            .with_progress(move |fraction, message| recorded.lock().unwrap().push((fraction, message.to_string())));
+        let output = manager.execute_plugin_with_context("progress", HashMap::new(), &ctx).unwrap();
+        assert_eq!(*reports.lock().unwrap(), [(0.5, "scratch: true".to_string())]);
+        assert_eq!(output.values["fraction"], "0.5");
+        assert_eq!(output.files, [PathBuf::from("report.txt")]);
+        assert!(!ctx.temp_dir().exists());
+
+        // Plugins of API version 1 run through `execute`
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let params: HashMap<String, String> =
+            [("input_file", manifest), ("output_file", "out.mp4")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let output = manager.execute_plugin_with_context("example_plugin", params.clone(), &ExecutionContext::new()).unwrap();
+        assert!(output.is_empty());
+
+        // A cancelled run does not start
+        let token = CancellationToken::new();
+        token.cancel();
+        let ctx = ExecutionContext::new().with_cancellation(token);
+        assert!(manager.execute_plugin_with_context("example_plugin", params, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_plugin_panics_and_timeouts_are_contained() {
+        let manager = PluginManager::new().unwrap();
+        manager.register(LoadedPlugin::new(Box::new(ProgressPlugin), None));
+        let fault = |kind: &str| -> HashMap<String, String> { [("fault".to_string(), kind.to_string())].into_iter().collect() };
+
+        let error = manager.execute_plugin("progress", fault("panic")).unwrap_err();
+        assert!(matches!(error.downcast_ref::<PluginError>(), Some(PluginError::Crashed(message)) if message == "fault injected"));
+
+        let ctx = ExecutionContext::new().with_timeout(Duration::from_millis(50));
+        let error = manager.execute_plugin_with_context("progress", fault("hang"), &ctx).unwrap_err();
+        assert!(matches!(error.downcast_ref::<PluginError>(), Some(PluginError::Timeout(name, _)) if name == "progress"));
+        assert!(!ctx.is_cancelled());
+
+        // Cancelling the caller's token reaches the run
+        let token = CancellationToken::new();
+        let ctx = ExecutionContext::new().with_cancellation(token.clone());
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        assert!(manager.execute_plugin_with_context("progress", fault("hang"), &ctx).is_ok());
+        canceller.join().unwrap();
+
+        // The manager is still usable afterwards
+        assert!(manager.execute_plugin("progress", HashMap::new()).is_ok());
+    }
+
+    /// Counts what happens to all instances of `CountingPlugin`
+    #[derive(Default)]
+    struct Counters {
+        /// Runs of an instance that was already shut down
+        runs_after_shutdown: AtomicUsize,
+        dropped: AtomicUsize,
+    }
+
+    struct CountingPlugin {
+        counters: Arc<Counters>,
+        shut_down: AtomicBool,
+    }
+
+    impl Plugin for CountingPlugin {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                name: "counting".to_string(),
+                version: "0.1.0".to_string(),
+                author: String::new(),
+                description: String::new(),
+                api_requirement: ApiRequirement::at_least(1),
+                capabilities: PluginCapabilities::empty(),
+            }
+        }
+
+        fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        fn execute(&self, _params: HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+            for _ in 0..2 {
+                if self.shut_down.load(Ordering::SeqCst) {
+                    self.counters.runs_after_shutdown.fetch_add(1, Ordering::SeqCst);
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            Ok(())
+        }
+
+        fn get_parameter_info(&self) -> Vec<ParameterInfo> {
+            Vec::new()
+        }
+
+        fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
+            self.shut_down.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl Drop for CountingPlugin {
+        fn drop(&mut self) {
+            self.counters.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_unload_while_running_concurrently() {
+        let counters = Arc::new(Counters::default());
+        let manager = PluginManager::new().unwrap();
+        let register = |manager: &PluginManager| {
+            let plugin = CountingPlugin { counters: Arc::clone(&counters), shut_down: AtomicBool::new(false) };
+            manager.register(LoadedPlugin::new(Box::new(plugin), None));
+        };
+        register(&manager);
+        let mut registered = 1;
+
+        let runners: Vec<_> = (0..4)
+            .map(|_| {
+                let manager = manager.clone();
+                thread::spawn(move || {
+                    for _ in 0..25 {
+                        if let Err(e) = manager.execute_plugin("counting", HashMap::new()) {
+                            assert!(matches!(e.downcast_ref::<PluginError>(), Some(PluginError::NotFound(_))), "{}", e);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Unloading is refused while a run holds the plugin, and reloading it never disturbs a run
+        let mut refused = 0;
+        while !runners.iter().all(|runner| runner.is_finished()) {
+            match manager.unload_plugin("counting") {
+                Ok(()) => {
+                    register(&manager);
+                    registered += 1;
+                },
+                Err(PluginError::InUse(_)) => refused += 1,
+                Err(e) => panic!("{}", e),
+            }
+            thread::sleep(Duration::from_micros(200));
+        }
+        for runner in runners {
+            runner.join().unwrap();
+        }
+        assert!(refused > 0);
+        assert_eq!(counters.runs_after_shutdown.load(Ordering::SeqCst), 0);
+
+        // Every instance is dropped once it is unloaded and its runs are over
+        manager.unload_plugin("counting").unwrap();
+        assert_eq!(counters.dropped.load(Ordering::SeqCst), registered);
+    }
+}
@@ -0,0 +1,321 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    ApiRequirement, ExecutionContext, ParameterInfo, ParameterType, Plugin, PluginError, PluginCapabilities, PluginMetadata,
+    PluginOutput, PLUGIN_API_VERSION,
+};
+
+/// Manifest file of a script plugin, at the top of its directory
+pub const SCRIPT_MANIFEST: &str = "plugin.toml";
+
+/// Prefix of the lines a script prints to report progress, values and files
+const PROTOCOL_PREFIX: &str = "vtk:";
+
+/// How a script plugin receives its parameters
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterPassing {
+    /// `--key value` after the command's own arguments, in name order
+    #[default]
+    Arguments,
+    /// `VTK_PARAM_KEY=value` environment variables, the name uppercased
+    Environment,
+}
+
+/// A parameter declared in `plugin.toml`
+#[derive(Clone, Debug, Deserialize)]
+struct ScriptParameter {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    required: bool,
+    default: Option<String>,
+    /// `string`, `integer`, `float`, `boolean`, `file`, `directory` or `choice`
+    #[serde(rename = "type", default = "string_type")]
+    parameter_type: String,
+    /// Values of a `choice` parameter
+    #[serde(default)]
+    choices: Vec<String>,
+}
+
+fn string_type() -> String {
+    "string".to_string()
+}
+
+impl ScriptParameter {
+    fn to_parameter_info(&self) -> Result<ParameterInfo, PluginError> {
+        let parameter_type = match self.parameter_type.as_str() {
+            "string" => ParameterType::String,
+            "integer" => ParameterType::Integer,
+            "float" => ParameterType::Float,
+            "boolean" => ParameterType::Boolean,
+            "file" => ParameterType::FilePath,
+            "directory" => ParameterType::DirectoryPath,
+            "choice" if !self.choices.is_empty() => ParameterType::Choice(self.choices.clone()),
+            "choice" => return Err(PluginError::InvalidPlugin(format!("Parameter '{}' lists no choices", self.name))),
+            other => return Err(PluginError::InvalidPlugin(format!(
+                "Unknown type '{}' of parameter '{}'",
+                other, self.name
+            ))),
+        };
+
+        Ok(ParameterInfo {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            required: self.required,
+            default_value: self.default.clone(),
+            parameter_type,
+        })
+    }
+}
+
+/// Contents of `plugin.toml`
+#[derive(Clone, Debug, Deserialize)]
+struct ScriptManifest {
+    name: String,
+    #[serde(default = "default_version")]
+    version: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    description: String,
+    /// Program to run; a relative path is taken from the plugin directory,
+    /// a bare name is looked up in `PATH`
+    command: String,
+    /// Arguments given before the parameters, e.g. the script for an interpreter
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    parameters_as: ParameterPassing,
+    #[serde(default)]
+    parameters: Vec<ScriptParameter>,
+}
+
+fn default_version() -> String {
+    "0.1.0".to_string()
+}
+
+/// Plugin running an executable or script described by a `plugin.toml` manifest
+///
+/// Besides its parameters, the program gets the scratch directory of the run in
+/// `VTK_TEMP_DIR` and runs in the plugin directory. Lines it prints to stdout
+/// starting with `vtk:` are read as it runs: `vtk:progress 0.5 Encoding`,
+/// `vtk:value key=value` and `vtk:file path/to/output`. A non-zero exit fails
+/// the run with the program's stderr.
+pub struct ScriptPlugin {
+    metadata: PluginMetadata,
+    dir: PathBuf,
+    command: String,
+    args: Vec<String>,
+    parameters_as: ParameterPassing,
+    parameters: Vec<ParameterInfo>,
+}
+
+impl ScriptPlugin {
+    /// Read the plugin in `dir` from its `plugin.toml`
+    pub fn from_dir(dir: &Path) -> Result<Self, PluginError> {
+        let manifest_path = dir.join(SCRIPT_MANIFEST);
+        let contents = fs::read_to_string(&manifest_path).map_err(|e| PluginError::LoadError(e.to_string()))?;
+        let manifest: ScriptManifest = toml::from_str(&contents)
+            .map_err(|e| PluginError::InvalidPlugin(format!("Invalid manifest: {}", e)))?;
+
+        let parameters = manifest.parameters.iter()
+            .map(ScriptParameter::to_parameter_info)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            metadata: PluginMetadata {
+                name: manifest.name,
+                version: manifest.version,
+                author: manifest.author,
+                description: manifest.description,
+                api_requirement: ApiRequirement::at_least(PLUGIN_API_VERSION),
+                capabilities: PluginCapabilities::CONTEXT | PluginCapabilities::OUTPUT,
+            },
+            dir: dir.to_path_buf(),
+            command: manifest.command,
+            args: manifest.args,
+            parameters_as: manifest.parameters_as,
+            parameters,
+        })
+    }
+
+    fn command_path(&self) -> PathBuf {
+        let command = Path::new(&self.command);
+        if command.components().count() > 1 {
+            self.dir.join(command)
+        } else {
+            command.to_path_buf()
+        }
+    }
+
+    fn build_command(&self, params: &HashMap<String, String>, ctx: &ExecutionContext) -> Command {
+        let mut command = Command::new(self.command_path());
+        command.current_dir(&self.dir).args(&self.args).env("VTK_TEMP_DIR", ctx.temp_dir());
+
+        let mut names: Vec<&String> = params.keys().collect();
+        names.sort();
+        for name in names {
+            match self.parameters_as {
+                ParameterPassing::Arguments => {
+                    command.arg(format!("--{}", name)).arg(&params[name]);
+                },
+                ParameterPassing::Environment => {
+                    command.env(format!("VTK_PARAM_{}", name.to_uppercase()), &params[name]);
+                },
+            }
+        }
+        command
+    }
+}
+
+/// Apply one `vtk:` line of a script's stdout; relative file paths are relative to `dir`,
+/// the directory the script runs in
+fn apply_protocol_line(line: &str, dir: &Path, output: &mut PluginOutput, ctx: &ExecutionContext) {
+    let Some(message) = line.strip_prefix(PROTOCOL_PREFIX) else {
+        return;
+    };
+    let (kind, rest) = message.split_once(' ').unwrap_or((message, ""));
+    match kind {
+        "progress" => {
+            let (fraction, text) = rest.split_once(' ').unwrap_or((rest, ""));
+            if let Ok(fraction) = fraction.parse::<f32>() {
+                ctx.report_progress(fraction, text);
+            }
+        },
+        "value" => {
+            if let Some((key, value)) = rest.split_once('=') {
+                output.values.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        },
+        "file" if !rest.trim().is_empty() => output.files.push(dir.join(rest.trim())),
+        _ => {},
+    }
+}
+
+impl Plugin for ScriptPlugin {
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn execute(&self, params: HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.execute_with_context(params, &ExecutionContext::new()).map(|_| ())
+    }
+
+    fn execute_with_context(
+        &self,
+        params: HashMap<String, String>,
+        ctx: &ExecutionContext,
+    ) -> Result<PluginOutput, Box<dyn std::error::Error>> {
+        let mut child = self.build_command(&params, ctx)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start {}: {}", self.command_path().display(), e))?;
+
+        // Read both pipes on their own threads, so that the script never blocks on a full pipe
+        let mut stderr = child.stderr.take();
+        let stderr_reader = thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(ref mut stderr) = stderr {
+                let _ = stderr.read_to_string(&mut text);
+            }
+            text
+        });
+        let (lines, received) = mpsc::channel();
+        let stdout = child.stdout.take();
+        thread::spawn(move || {
+            if let Some(stdout) = stdout {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if lines.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut output = PluginOutput::new();
+        let status = loop {
+            if ctx.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Box::new(common::VideoToolkitError::Cancelled));
+            }
+            while let Ok(line) = received.try_recv() {
+                apply_protocol_line(&line, &self.dir, &mut output, ctx);
+            }
+            match child.try_wait()? {
+                Some(status) => break status,
+                None => thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        // Lines printed just before the exit
+        for line in received.iter() {
+            apply_protocol_line(&line, &self.dir, &mut output, ctx);
+        }
+
+        let stderr = stderr_reader.join().unwrap_or_default();
+        if !status.success() {
+            let code = status.code().map_or_else(|| "a signal".to_string(), |code| format!("code {}", code));
+            return Err(format!("{} exited with {}: {}", self.metadata.name, code, stderr.trim()).into());
+        }
+        Ok(output)
+    }
+
+    fn get_parameter_info(&self) -> Vec<ParameterInfo> {
+        self.parameters.clone()
+    }
+
+    fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::PluginManager;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("echo_script")
+    }
+
+    #[test]
+    fn test_script_plugin() {
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin(fixture()).unwrap();
+        let info = manager.get_plugin_parameters("echo_script").unwrap();
+        assert_eq!(info[1].parameter_type, ParameterType::Choice(vec!["upper".to_string(), "lower".to_string()]));
+
+        let params: HashMap<String, String> = [("text".to_string(), "Hello".to_string())].into_iter().collect();
+        let output = manager.execute_plugin("echo_script", params).unwrap();
+        assert_eq!(output.values["text"], "HELLO");
+        // The file is reported in the plugin directory, where the script ran
+        assert_eq!(output.files, [fixture().join("hello.txt")]);
+
+        // A non-zero exit is an error carrying the script's stderr
+        let params: HashMap<String, String> = [("text".to_string(), "fail".to_string())].into_iter().collect();
+        let error = manager.execute_plugin("echo_script", params).unwrap_err().to_string();
+        assert_eq!(error, "echo_script exited with code 3: asked to fail");
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{PluginError, PluginManager};
+
+/// Plugin settings kept between sessions
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PluginState {
+    /// Plugins that discovery leaves alone
+    #[serde(default)]
+    disabled: BTreeSet<String>,
+    /// More directories to search for plugins; relative ones are taken from
+    /// the directory of the settings file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    directories: Vec<PathBuf>,
+}
+
+/// Environment variable listing directories to search for plugins, separated
+/// like `PATH` (`:` or `;` on Windows)
+pub const PLUGIN_PATH_VAR: &str = "VIDEO_TOOLKIT_PLUGIN_PATH";
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "video-toolkit", "VideoToolKit")
+}
+
+/// `plugins.json` in the configuration directory of the toolkit, if there is one
+pub(crate) fn default_state_file() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().join("plugins.json"))
+}
+
+/// Plugin directories searched by default: `plugins` in the working directory,
+/// those in `VIDEO_TOOLKIT_PLUGIN_PATH` and `plugins` in the data directory
+pub(crate) fn default_plugin_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("plugins")];
+    if let Some(path) = std::env::var_os(PLUGIN_PATH_VAR) {
+        dirs.extend(std::env::split_paths(&path).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    dirs.extend(project_dirs().map(|dirs| dirs.data_dir().join("plugins")));
+    dirs
+}
+
+impl PluginManager {
+    /// Keep the enabled state of the plugins in `path` instead of the configuration directory
+    pub fn with_state_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Saved state; empty when missing or unreadable, so every plugin is enabled
+    fn read_state(&self) -> PluginState {
+        self.state_file.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Enable or disable a plugin by name, for this and later sessions
+    ///
+    /// Disabled plugins are skipped by `discover_plugins`; a plugin that is
+    /// loaded already stays loaded until it is unloaded.
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<(), PluginError> {
+        let path = self.state_file.as_ref()
+            .ok_or_else(|| PluginError::StateError("no configuration directory".to_string()))?;
+
+        let mut state = self.read_state();
+        if enabled {
+            state.disabled.remove(name);
+        } else {
+            state.disabled.insert(name.to_string());
+        }
+
+        let json = serde_json::to_string_pretty(&state).map_err(|e| PluginError::StateError(e.to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| PluginError::StateError(e.to_string()))?;
+        }
+        fs::write(path, json).map_err(|e| PluginError::StateError(e.to_string()))
+    }
+
+    /// Whether discovery loads the plugin of this name; plugins are enabled unless disabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.read_state().disabled.contains(name)
+    }
+
+    /// Names of the disabled plugins, sorted
+    pub fn disabled_plugins(&self) -> Vec<String> {
+        self.read_state().disabled.into_iter().collect()
+    }
+
+    /// Directories searched by `discover_plugins`, in order: the default ones,
+    /// those added with `add_plugin_directory`, then those listed under
+    /// `directories` in the settings file
+    ///
+    /// A directory given twice, also through a different path, is listed once.
+    /// Directories that do not exist are listed too.
+    pub fn plugin_directories(&self) -> Vec<PathBuf> {
+        let base = self.state_file.as_ref().and_then(|path| path.parent()).unwrap_or(Path::new(""));
+        let configured = self.read_state().directories.into_iter().map(|dir| base.join(dir));
+
+        let mut seen = Vec::new();
+        let mut dirs = Vec::new();
+        for dir in self.plugin_dirs.iter().cloned().chain(configured) {
+            let key = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            if !seen.contains(&key) {
+                seen.push(key);
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_state_is_persisted() {
+        let dir = std::env::temp_dir().join(format!("vtk_plugin_state_{}", std::process::id()));
+        let state_file = dir.join("config").join("plugins.json");
+        let manager = PluginManager::new().unwrap().with_state_file(&state_file);
+        assert!(manager.is_enabled("slow_plugin"));
+
+        manager.set_enabled("slow_plugin", false).unwrap();
+        manager.set_enabled("other", false).unwrap();
+        manager.set_enabled("other", true).unwrap();
+
+        let reopened = PluginManager::new().unwrap().with_state_file(&state_file);
+        assert!(!reopened.is_enabled("slow_plugin"));
+        assert!(reopened.is_enabled("other"));
+        assert_eq!(reopened.disabled_plugins(), ["slow_plugin"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_directories_merge_settings_and_skip_duplicates() {
+        let dir = std::env::temp_dir().join(format!("vtk_plugin_dirs_{}", std::process::id()));
+        fs::create_dir_all(dir.join("extra")).unwrap();
+        let state_file = dir.join("plugins.json");
+        fs::write(&state_file, r#"{"directories": ["extra", "extra/../extra", "/nonexistent/plugins"]}"#).unwrap();
+
+        let mut manager = PluginManager::new().unwrap().with_state_file(&state_file);
+        manager.plugin_dirs = vec![PathBuf::from("plugins")];
+        manager.add_plugin_directory(dir.join("extra"));
+        manager.add_plugin_directory("plugins");
+        assert_eq!(manager.plugin_directories(), [
+            PathBuf::from("plugins"),
+            dir.join("extra"),
+            PathBuf::from("/nonexistent/plugins"),
+        ]);
+
+        // Enabling and disabling keeps the listed directories
+        manager.set_enabled("other", false).unwrap();
+        assert_eq!(manager.plugin_directories().len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
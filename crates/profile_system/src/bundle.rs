@@ -0,0 +1,210 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{migration, OverwriteMode, Profile, ProfileError, ProfileManager, ProfileType, Result};
+
+/// Version of the bundle files written by `export_all`
+const BUNDLE_VERSION: u32 = 1;
+
+/// What importing a bundle does with a profile whose name and type are taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep the existing profile
+    #[default]
+    Skip,
+    /// Replace the existing profile
+    Overwrite,
+    /// Import under the first free name of the form `name (2)`
+    Rename,
+}
+
+impl std::fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictPolicy::Skip => write!(f, "skip"),
+            ConflictPolicy::Overwrite => write!(f, "overwrite"),
+            ConflictPolicy::Rename => write!(f, "rename"),
+        }
+    }
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = ProfileError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "rename" => Ok(ConflictPolicy::Rename),
+            other => Err(ProfileError::InvalidData(format!(
+                "Unknown conflict policy '{}': expected skip, overwrite or rename",
+                other
+            ))),
+        }
+    }
+}
+
+/// What happened to the profiles of a bundle, each as its type and name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundleImportReport {
+    pub imported: Vec<(ProfileType, String)>,
+    /// Not imported because a profile of the same name existed
+    pub skipped: Vec<(ProfileType, String)>,
+    /// Imported under a new name: the name in the bundle, then the new one
+    pub renamed: Vec<(ProfileType, String, String)>,
+    /// Entries that could not be read or saved, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// All profiles of a manager in one file, grouped by type
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    bundle_version: u32,
+    exported: chrono::DateTime<chrono::Utc>,
+    groups: Vec<BundleGroup>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundleGroup {
+    profile_type: ProfileType,
+    /// Kept as JSON so profiles of older versions are upgraded like files
+    profiles: Vec<Value>,
+}
+
+impl ProfileManager {
+    /// Write every saved profile to a single JSON bundle, grouped by type
+    ///
+    /// Built-in presets and profile files that cannot be read are left out. Returns the number of
+    /// profiles written.
+    pub fn export_all<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
+        let mut all_profiles: Vec<(ProfileType, Vec<String>)> = self.list_all_profiles()?.into_iter().collect();
+        all_profiles.sort_by_key(|(profile_type, _)| profile_type.to_string());
+
+        let mut groups = Vec::new();
+        let mut count = 0;
+        for (profile_type, mut names) in all_profiles {
+            names.sort();
+            let profiles: Vec<Value> = names
+                .iter()
+                .filter_map(|name| self.load_profile(name, profile_type.clone()).ok())
+                .filter(|profile| !profile.builtin)
+                .map(|profile| serde_json::to_value(profile).map_err(|e| ProfileError::ParseError(e.to_string())))
+                .collect::<Result<_>>()?;
+            if !profiles.is_empty() {
+                count += profiles.len();
+                groups.push(BundleGroup { profile_type, profiles });
+            }
+        }
+
+        let bundle = Bundle { bundle_version: BUNDLE_VERSION, exported: chrono::Utc::now(), groups };
+        let json = serde_json::to_string_pretty(&bundle)
+            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        fs::write(path, json)
+            .map_err(ProfileError::ReadError)?;
+
+        Ok(count)
+    }
+
+    /// Restore the profiles of a bundle written by `export_all`
+    ///
+    /// Profiles whose name and type are taken are handled by `policy`. An entry
+    /// that cannot be read or saved is reported as failed without stopping the
+    /// others; a file that is not a bundle at all is an error.
+    pub fn import_bundle<P: AsRef<Path>>(&self, path: P, policy: ConflictPolicy) -> Result<BundleImportReport> {
+        let contents = fs::read_to_string(path)
+            .map_err(ProfileError::ReadError)?;
+        let bundle: Bundle = serde_json::from_str(&contents)
+            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        if bundle.bundle_version > BUNDLE_VERSION {
+            return Err(ProfileError::UnsupportedVersion(bundle.bundle_version, BUNDLE_VERSION));
+        }
+
+        let mut report = BundleImportReport::default();
+        for entry in bundle.groups.into_iter().flat_map(|group| group.profiles) {
+            let name = entry.get("name").and_then(Value::as_str).unwrap_or("?").to_string();
+            let imported = migration::parse_profile_value(entry)
+                .and_then(|profile| self.import_bundle_entry(profile, policy, &mut report));
+            if let Err(e) = imported {
+                report.failed.push((name, e.to_string()));
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn import_bundle_entry(&self, mut profile: Profile, policy: ConflictPolicy, report: &mut BundleImportReport) -> Result<()> {
+        let profile_type = profile.profile_type.clone();
+        let exists = self.get_profile_path(&profile.name, profile_type.clone())?.exists();
+
+        match (exists, policy) {
+            (true, ConflictPolicy::Skip) => report.skipped.push((profile_type, profile.name)),
+            (true, ConflictPolicy::Rename) => {
+                let original = profile.name.clone();
+                profile.name = self.free_name(&original, &profile_type)?;
+                self.save_profile(&profile)?;
+                report.renamed.push((profile_type, original, profile.name));
+            },
+            _ => {
+                self.save_profile_with(&profile, OverwriteMode::Overwrite)?;
+                report.imported.push((profile_type, profile.name));
+            },
+        }
+        Ok(())
+    }
+
+    /// First name of the form `name (2)`, `name (3)`, ... not taken by a profile of the type
+    fn free_name(&self, name: &str, profile_type: &ProfileType) -> Result<String> {
+        for n in 2.. {
+            let candidate = format!("{} ({})", name, n);
+            if !self.get_profile_path(&candidate, profile_type.clone())?.exists() {
+                return Ok(candidate);
+            }
+        }
+        unreachable!("some numbered name is free")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_export_all_and_import_bundle() {
+        let root = std::env::temp_dir().join(format!("video_toolkit_test_profiles_bundle_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let source = ProfileManager::with_directory(root.join("source")).unwrap();
+        let fps = |fps: &str| HashMap::from([("fps".to_string(), fps.to_string())]);
+        source.save_profile(&Profile::new("small", ProfileType::GifConverter, fps("10"))).unwrap();
+        source.save_profile(&Profile::new("large", ProfileType::GifConverter, fps("30"))).unwrap();
+        source.save_profile(&Profile::new("stamp", ProfileType::Custom("watermark".to_string()), HashMap::new())).unwrap();
+
+        let bundle = root.join("profiles.json");
+        assert_eq!(source.export_all(&bundle).unwrap(), 3);
+
+        let target = ProfileManager::with_directory(root.join("target")).unwrap();
+        target.save_profile(&Profile::new("small", ProfileType::GifConverter, fps("12"))).unwrap();
+
+        let report = target.import_bundle(&bundle, ConflictPolicy::Skip).unwrap();
+        assert_eq!(report.imported.len(), 2);
+        assert_eq!(report.skipped, [(ProfileType::GifConverter, "small".to_string())]);
+        assert_eq!(target.load_profile("small", ProfileType::GifConverter).unwrap().get_parameter("fps").unwrap(), "12");
+
+        let report = target.import_bundle(&bundle, ConflictPolicy::Rename).unwrap();
+        assert_eq!(report.renamed.len(), 3);
+        assert!(report.renamed.contains(&(ProfileType::GifConverter, "small".to_string(), "small (2)".to_string())));
+        assert_eq!(target.load_profile("small (2)", ProfileType::GifConverter).unwrap().get_parameter("fps").unwrap(), "10");
+
+        let report = target.import_bundle(&bundle, ConflictPolicy::Overwrite).unwrap();
+        assert_eq!((report.imported.len(), report.failed.len()), (3, 0));
+        assert_eq!(target.load_profile("small", ProfileType::GifConverter).unwrap().get_parameter("fps").unwrap(), "10");
+
+        assert!(target.import_bundle(root.join("source").join("gifconverter").join("small.json"), ConflictPolicy::Skip).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{migration, Profile, ProfileError, Result};
+
+/// File formats profiles can be imported from and exported to
+///
+/// Saved profiles are always stored as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProfileFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ProfileFormat {
+    /// Format of a file from its extension: `.toml`, `.yaml`/`.yml` or `.json`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        extension.parse().ok()
+    }
+
+    /// Read a profile written in this format
+    ///
+    /// TOML and YAML files may leave out the timestamps, and give parameter
+    /// values as numbers or booleans (`fps = 15`); they are stored as text.
+    pub fn parse(self, contents: &str) -> Result<Profile> {
+        let parse_error = |e: &dyn std::fmt::Display| ProfileError::ParseError(format!("{} profile: {}", self, e));
+        let mut value = match self {
+            ProfileFormat::Json => return migration::parse_profile(contents),
+            ProfileFormat::Toml => toml_to_json(toml::from_str(contents).map_err(|e| parse_error(&e))?),
+            ProfileFormat::Yaml => yaml_to_json(serde_yaml::from_str(contents).map_err(|e| parse_error(&e))?),
+        };
+
+        if let Some(object) = value.as_object_mut() {
+            migration::fill_timestamps(object);
+            if let Some(Value::Object(parameters)) = object.get_mut("parameters") {
+                for parameter in parameters.values_mut() {
+                    if let Value::Number(_) | Value::Bool(_) = parameter {
+                        *parameter = Value::String(parameter.to_string());
+                    }
+                }
+            }
+        }
+        migration::parse_profile_value(value)
+    }
+
+    /// Write a profile in this format
+    pub fn serialize(self, profile: &Profile) -> Result<String> {
+        match self {
+            ProfileFormat::Json => serde_json::to_string_pretty(profile).map_err(|e| e.to_string()),
+            ProfileFormat::Toml => toml::to_string_pretty(profile).map_err(|e| e.to_string()),
+            ProfileFormat::Yaml => serde_yaml::to_string(profile).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| ProfileError::ParseError(format!("Failed to write {} profile: {}", self, e)))
+    }
+}
+
+impl std::fmt::Display for ProfileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileFormat::Json => write!(f, "json"),
+            ProfileFormat::Toml => write!(f, "toml"),
+            ProfileFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+impl std::str::FromStr for ProfileFormat {
+    type Err = ProfileError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(ProfileFormat::Json),
+            "toml" => Ok(ProfileFormat::Toml),
+            "yaml" | "yml" => Ok(ProfileFormat::Yaml),
+            other => Err(ProfileError::InvalidData(format!(
+                "Unknown profile format '{}': expected json, toml or yaml",
+                other
+            ))),
+        }
+    }
+}
+
+/// TOML as JSON, with TOML dates and times as text
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
+
+/// YAML as JSON, with tagged values like `!Custom watermark` as `{"Custom": "watermark"}`
+fn yaml_to_json(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => n.as_i64().map(Value::from)
+            .or_else(|| n.as_u64().map(Value::from))
+            .unwrap_or_else(|| Value::from(n.as_f64())),
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(items) => Value::Array(items.into_iter().map(yaml_to_json).collect()),
+        serde_yaml::Value::Mapping(mapping) => Value::Object(
+            mapping.into_iter()
+                .map(|(key, value)| {
+                    let key = match yaml_to_json(key) {
+                        Value::String(key) => key,
+                        other => other.to_string(),
+                    };
+                    (key, yaml_to_json(value))
+                })
+                .collect()
+        ),
+        serde_yaml::Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string().trim_start_matches('!').to_string();
+            Value::Object([(tag, yaml_to_json(tagged.value))].into_iter().collect())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProfileType;
+
+    #[test]
+    fn test_toml_profile_survives_json_round_trip() {
+        let toml = r#"
+            name = "pipeline"
+            description = "Settings kept in git"
+            profile_type = "GifConverter"
+
+            [parameters]
+            fps = 15
+            max_size = 2.5
+            optimize = true
+            output_dir = "gifs"
+        "#;
+        let profile = ProfileFormat::Toml.parse(toml).unwrap();
+        assert_eq!(profile.profile_type, ProfileType::GifConverter);
+        assert_eq!(profile.get_parameter("fps").unwrap(), "15");
+        assert_eq!(profile.get_parameter("max_size").unwrap(), "2.5");
+        assert_eq!(profile.get_parameter("optimize").unwrap(), "true");
+
+        let json = ProfileFormat::Json.serialize(&profile).unwrap();
+        let reloaded = ProfileFormat::Json.parse(&json).unwrap();
+        assert_eq!(reloaded.parameters, profile.parameters);
+        assert_eq!((reloaded.created, reloaded.description), (profile.created, profile.description.clone()));
+
+        // Exported TOML and YAML read back the same, custom types included
+        let mut custom = profile.clone();
+        custom.profile_type = ProfileType::Custom("watermark".to_string());
+        for format in [ProfileFormat::Toml, ProfileFormat::Yaml] {
+            let reread = format.parse(&format.serialize(&custom).unwrap()).unwrap();
+            assert_eq!((reread.profile_type, reread.parameters), (custom.profile_type.clone(), custom.parameters.clone()));
+            assert_eq!(reread.last_modified, custom.last_modified);
+        }
+
+        let yaml = "name: small\nprofile_type: Splitter\nparameters:\n  slices: 4\n  prefix: part\n";
+        assert_eq!(ProfileFormat::Yaml.parse(yaml).unwrap().get_parameter("slices").unwrap(), "4");
+
+        assert_eq!(ProfileFormat::from_path(Path::new("team/gif.YML")), Some(ProfileFormat::Yaml));
+        assert_eq!(ProfileFormat::from_path(Path::new("profile.txt")), None);
+    }
+}
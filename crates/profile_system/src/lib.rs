@@ -7,6 +7,20 @@ use serde::{Serialize, Deserialize};
 use thiserror::Error;
 use directories::ProjectDirs;
 
+mod bundle;
+mod format;
+mod migration;
+mod names;
+mod presets;
+mod recent;
+mod schema;
+
+pub use bundle::{BundleImportReport, ConflictPolicy};
+pub use format::ProfileFormat;
+pub use migration::PROFILE_SCHEMA_VERSION;
+pub use recent::ProfileUsage;
+pub use schema::{schema, ParameterInfo, ParameterType, ValidationIssue};
+
 /// Errors that can occur in the profile system
 #[derive(Error, Debug)]
 pub enum ProfileError {
@@ -25,6 +39,18 @@ pub enum ProfileError {
     #[error("Invalid profile data: {0}")]
     InvalidData(String),
 
+    #[error("Invalid profile parameters: {}", join_issues(.0))]
+    InvalidParameters(Vec<ValidationIssue>),
+
+    #[error("Profile '{0}' is a built-in preset and cannot be changed; duplicate it instead")]
+    ReadOnly(String),
+
+    #[error("Invalid profile name: {0}")]
+    InvalidName(String),
+
+    #[error("Profile has schema version {0}, but this version of the toolkit only reads up to {1}")]
+    UnsupportedVersion(u32, u32),
+
     #[error("Failed to create profile directory")]
     DirectoryCreationFailed,
 }
@@ -32,6 +58,10 @@ pub enum ProfileError {
 /// Result type for profile operations
 pub type Result<T> = std::result::Result<T, ProfileError>;
 
+fn join_issues(issues: &[ValidationIssue]) -> String {
+    issues.iter().map(ValidationIssue::to_string).collect::<Vec<_>>().join("; ")
+}
+
 /// Profile types supported by the system
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ProfileType {
@@ -59,10 +89,19 @@ impl std::fmt::Display for ProfileType {
 /// A profile containing parameters for a specific operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
+    /// Format version of the saved file, see `PROFILE_SCHEMA_VERSION`
+    #[serde(default = "migration::unversioned")]
+    pub schema_version: u32,
     pub name: String,
     pub description: Option<String>,
     pub profile_type: ProfileType,
     pub parameters: HashMap<String, String>,
+    /// Free-form labels to find the profile by, e.g. `twitter` or `720p`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Shipped with the toolkit and read-only, see `ProfileManager::builtin_profiles`
+    #[serde(skip)]
+    pub builtin: bool,
     pub created: chrono::DateTime<chrono::Utc>,
     pub last_modified: chrono::DateTime<chrono::Utc>,
 }
@@ -72,10 +111,13 @@ impl Profile {
     pub fn new(name: &str, profile_type: ProfileType, parameters: HashMap<String, String>) -> Self {
         let now = chrono::Utc::now();
         Self {
+            schema_version: PROFILE_SCHEMA_VERSION,
             name: name.to_string(),
             description: None,
             profile_type,
             parameters,
+            tags: Vec::new(),
+            builtin: false,
             created: now,
             last_modified: now,
         }
@@ -87,6 +129,22 @@ impl Profile {
         self
     }
 
+    /// Set the tags of the profile, trimmed, without empty or repeated ones
+    pub fn with_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.tags.clear();
+        for tag in tags {
+            let tag = tag.as_ref().trim();
+            if !tag.is_empty() && !self.tags.iter().any(|t| t == tag) {
+                self.tags.push(tag.to_string());
+            }
+        }
+        self
+    }
+
     /// Update the parameters of the profile
     pub fn update_parameters(&mut self, parameters: HashMap<String, String>) {
         self.parameters = parameters;
@@ -105,9 +163,55 @@ impl Profile {
     }
 }
 
+/// What a profile listing knows about a profile, read from its file once
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub description: Option<String>,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
+    pub parameter_count: usize,
+    pub tags: Vec<String>,
+    /// A built-in preset, which cannot be changed or deleted
+    pub builtin: bool,
+    /// The file could not be read as a profile; only `name` is known
+    pub invalid: bool,
+}
+
+impl ProfileSummary {
+    fn invalid(name: String) -> Self {
+        Self { name, description: None, created: None, last_modified: None, parameter_count: 0, tags: Vec::new(), builtin: false, invalid: true }
+    }
+
+    /// Whether every word of `query` is found, ignoring case, in the name,
+    /// the description or a tag; an empty query matches every profile
+    pub fn matches(&self, query: &str) -> bool {
+        let fields: Vec<String> = std::iter::once(&self.name)
+            .chain(&self.description)
+            .chain(&self.tags)
+            .map(|field| field.to_lowercase())
+            .collect();
+        query.to_lowercase()
+            .split_whitespace()
+            .all(|word| fields.iter().any(|field| field.contains(word)))
+    }
+}
+
+/// What saving a profile does when one of the same name and type exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwriteMode {
+    /// Fail with `ProfileError::AlreadyExists`
+    #[default]
+    ErrorIfExists,
+    /// Replace the existing profile
+    Overwrite,
+}
+
 /// Manages profile storage and retrieval
 pub struct ProfileManager {
     profiles_dir: PathBuf,
+    // Check parameters against the schema of their type before saving
+    validate: bool,
 }
 
 impl ProfileManager {
@@ -122,7 +226,7 @@ impl ProfileManager {
         fs::create_dir_all(&profiles_dir)
             .map_err(|_| ProfileError::DirectoryCreationFailed)?;
 
-        Ok(Self { profiles_dir })
+        Ok(Self { profiles_dir, validate: true })
     }
 
     /// Create a profile manager with a custom directory
@@ -131,75 +235,151 @@ impl ProfileManager {
         fs::create_dir_all(&profiles_dir)
             .map_err(|_| ProfileError::DirectoryCreationFailed)?;
 
-        Ok(Self { profiles_dir })
+        Ok(Self { profiles_dir, validate: true })
     }
 
-    /// Get the path to the profile file
-    fn get_profile_path(&self, name: &str, profile_type: ProfileType) -> PathBuf {
-        let type_dir = match profile_type {
-            ProfileType::Custom(ref custom) => self.profiles_dir.join("custom").join(custom),
-            _ => self.profiles_dir.join(format!("{:?}", profile_type).to_lowercase()),
-        };
+    /// Enable or disable the parameter check done before saving (enabled by default)
+    pub fn with_validation(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
 
-        // Create the directory if it doesn't exist
-        let _ = fs::create_dir_all(&type_dir);
+    /// Check the parameters of a profile against the schema of its type
+    ///
+    /// Reports missing required parameters, unknown names (with the closest
+    /// known one) and values of the wrong type. Custom profiles always pass.
+    pub fn validate(profile: &Profile) -> std::result::Result<(), Vec<ValidationIssue>> {
+        let issues = schema::validate(profile);
+        if issues.is_empty() { Ok(()) } else { Err(issues) }
+    }
 
-        type_dir.join(format!("{}.json", name))
+    fn check_parameters(&self, profile: &Profile) -> Result<()> {
+        if self.validate {
+            Self::validate(profile).map_err(ProfileError::InvalidParameters)?;
+        }
+        Ok(())
     }
 
-    /// Save a profile
-    pub fn save_profile(&self, profile: &Profile) -> Result<()> {
-        let profile_path = self.get_profile_path(&profile.name, profile.profile_type.clone());
+    /// The read-only preset profiles shipped with the toolkit
+    ///
+    /// They are loaded, listed and searched like saved profiles, but cannot
+    /// be updated, renamed or deleted, nor their names used for new profiles.
+    pub fn builtin_profiles() -> Vec<Profile> {
+        presets::presets().to_vec()
+    }
 
-        // Create parent directories if they don't exist
-        if let Some(parent) = profile_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(ProfileError::ReadError)?;
+    /// Fail for the name of a built-in preset of the type
+    fn check_writable(&self, name: &str, profile_type: &ProfileType) -> Result<()> {
+        if presets::find(name, profile_type).is_some() {
+            return Err(ProfileError::ReadOnly(name.to_string()));
         }
+        Ok(())
+    }
 
-        // Check if the profile already exists
-        if profile_path.exists() {
-            return Err(ProfileError::AlreadyExists(profile.name.clone()));
+    /// Get the directory of the profiles of a type
+    fn type_dir(&self, profile_type: &ProfileType) -> Result<PathBuf> {
+        Ok(match profile_type {
+            ProfileType::Custom(custom) => self.profiles_dir.join("custom").join(names::file_stem(custom)?),
+            _ => self.profiles_dir.join(format!("{:?}", profile_type).to_lowercase()),
+        })
+    }
+
+    /// Get the path to the profile file, rejecting names that are not safe as file names
+    fn get_profile_path(&self, name: &str, profile_type: ProfileType) -> Result<PathBuf> {
+        let type_dir = self.type_dir(&profile_type)?;
+        let path = type_dir.join(format!("{}.json", names::file_stem(name)?));
+
+        // Profiles saved before names were escaped are stored under the plain name
+        let legacy_path = type_dir.join(format!("{}.json", name));
+        if !path.exists() && legacy_path.exists() {
+            return Ok(legacy_path);
         }
+        Ok(path)
+    }
 
-        // Serialize and save the profile
-        let json = serde_json::to_string_pretty(profile)
-            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+    /// Save a new profile, failing if one of the same name and type exists
+    pub fn save_profile(&self, profile: &Profile) -> Result<()> {
+        self.save_profile_with(profile, OverwriteMode::ErrorIfExists)
+    }
 
-        let mut file = File::create(profile_path)
-            .map_err(ProfileError::ReadError)?;
+    /// Save a profile, replacing an existing one of the same name and type only with `OverwriteMode::Overwrite`
+    pub fn save_profile_with(&self, profile: &Profile, mode: OverwriteMode) -> Result<()> {
+        self.check_writable(&profile.name, &profile.profile_type)?;
+        self.check_parameters(profile)?;
+        let profile_path = self.get_profile_path(&profile.name, profile.profile_type.clone())?;
 
-        file.write_all(json.as_bytes())
-            .map_err(ProfileError::ReadError)?;
+        if mode == OverwriteMode::ErrorIfExists && profile_path.exists() {
+            return Err(ProfileError::AlreadyExists(profile.name.clone()));
+        }
 
-        Ok(())
+        write_profile(&profile_path, profile)
     }
 
     /// Update an existing profile
     pub fn update_profile(&self, profile: &Profile) -> Result<()> {
-        let profile_path = self.get_profile_path(&profile.name, profile.profile_type.clone());
+        self.check_writable(&profile.name, &profile.profile_type)?;
+        self.check_parameters(profile)?;
+        let profile_path = self.get_profile_path(&profile.name, profile.profile_type.clone())?;
 
         // Check if the profile exists
         if !profile_path.exists() {
             return Err(ProfileError::NotFound(profile.name.clone()));
         }
 
-        // Serialize and save the profile
-        let json = serde_json::to_string_pretty(profile)
-            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        write_profile(&profile_path, profile)
+    }
 
-        let mut file = File::create(profile_path)
-            .map_err(ProfileError::ReadError)?;
+    /// Rename a profile, failing if a profile named `new_name` of the same type exists
+    ///
+    /// The renamed profile is written before the old one is removed, so a
+    /// failure leaves at least one of them in place.
+    pub fn rename_profile(&self, old_name: &str, new_name: &str, profile_type: ProfileType) -> Result<()> {
+        self.check_writable(old_name, &profile_type)?;
+        self.check_writable(new_name, &profile_type)?;
+        let mut profile = self.load_profile(old_name, profile_type.clone())?;
+        if old_name == new_name {
+            return Ok(());
+        }
 
-        file.write_all(json.as_bytes())
-            .map_err(ProfileError::ReadError)?;
+        let new_path = self.get_profile_path(new_name, profile_type.clone())?;
+        if new_path.exists() {
+            return Err(ProfileError::AlreadyExists(new_name.to_string()));
+        }
 
-        Ok(())
+        profile.name = new_name.to_string();
+        profile.last_modified = chrono::Utc::now();
+        write_profile(&new_path, &profile)?;
+
+        fs::remove_file(self.get_profile_path(old_name, profile_type)?)
+            .map_err(ProfileError::ReadError)
+    }
+
+    /// Save a copy of a profile under `new_name`, failing if a profile of that name and type exists
+    ///
+    /// The copy gets new creation and modification times; its parameters are
+    /// copied as they are, without checking them again.
+    pub fn duplicate_profile(&self, name: &str, profile_type: ProfileType, new_name: &str) -> Result<Profile> {
+        self.check_writable(new_name, &profile_type)?;
+        let original = self.load_profile(name, profile_type.clone())?;
+
+        let new_path = self.get_profile_path(new_name, profile_type)?;
+        if new_path.exists() {
+            return Err(ProfileError::AlreadyExists(new_name.to_string()));
+        }
+
+        let now = chrono::Utc::now();
+        let copy = Profile { name: new_name.to_string(), builtin: false, created: now, last_modified: now, ..original };
+        write_profile(&new_path, &copy)?;
+
+        Ok(copy)
     }
 
     /// Load a profile by name and type
     pub fn load_profile(&self, name: &str, profile_type: ProfileType) -> Result<Profile> {
-        let profile_path = self.get_profile_path(name, profile_type);
+        if let Some(preset) = presets::find(name, &profile_type) {
+            return Ok(preset.clone());
+        }
+        let profile_path = self.get_profile_path(name, profile_type)?;
 
         // Check if the profile exists
         if !profile_path.exists() {
@@ -214,16 +394,16 @@ impl ProfileManager {
         file.read_to_string(&mut contents)
             .map_err(ProfileError::ReadError)?;
 
-        // Parse the profile
-        let profile: Profile = serde_json::from_str(&contents)
-            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        // Parse the profile, upgrading older files
+        let profile = migration::parse_profile(&contents)?;
 
         Ok(profile)
     }
 
     /// Delete a profile
     pub fn delete_profile(&self, name: &str, profile_type: ProfileType) -> Result<()> {
-        let profile_path = self.get_profile_path(name, profile_type);
+        self.check_writable(name, &profile_type)?;
+        let profile_path = self.get_profile_path(name, profile_type)?;
 
         // Check if the profile exists
         if !profile_path.exists() {
@@ -237,12 +417,78 @@ impl ProfileManager {
         Ok(())
     }
 
-    /// List all profiles of a specific type
+    /// List all saved profiles of a specific type, without the built-in presets
     pub fn list_profiles(&self, profile_type: ProfileType) -> Result<Vec<String>> {
-        let type_dir = match profile_type {
-            ProfileType::Custom(ref custom) => self.profiles_dir.join("custom").join(custom),
-            _ => self.profiles_dir.join(format!("{:?}", profile_type).to_lowercase()),
-        };
+        Ok(self.profile_files(profile_type)?.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// List all profiles of a specific type with their description, timestamps
+    /// and parameter count, sorted by name, built-in presets included
+    ///
+    /// Files that cannot be read as a profile are listed as `invalid` instead
+    /// of failing the listing.
+    pub fn list_profiles_detailed(&self, profile_type: ProfileType) -> Result<Vec<ProfileSummary>> {
+        let mut summaries: Vec<ProfileSummary> = self.profile_files(profile_type.clone())?
+            .into_iter()
+            .map(|(name, path)| {
+                let profile = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|contents| migration::parse_profile(&contents).ok());
+                match profile {
+                    Some(profile) => ProfileSummary {
+                        name,
+                        description: profile.description,
+                        created: Some(profile.created),
+                        last_modified: Some(profile.last_modified),
+                        parameter_count: profile.parameters.len(),
+                        tags: profile.tags,
+                        builtin: false,
+                        invalid: false,
+                    },
+                    None => ProfileSummary::invalid(name),
+                }
+            })
+            .collect();
+
+        summaries.extend(presets::presets().iter().filter(|preset| preset.profile_type == profile_type).map(|preset| {
+            ProfileSummary {
+                name: preset.name.clone(),
+                description: preset.description.clone(),
+                created: Some(preset.created),
+                last_modified: Some(preset.last_modified),
+                parameter_count: preset.parameters.len(),
+                tags: preset.tags.clone(),
+                builtin: true,
+                invalid: false,
+            }
+        }));
+
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(summaries)
+    }
+
+    /// Profiles of every type whose name, description or tags match `query`,
+    /// ignoring case; see `ProfileSummary::matches`
+    ///
+    /// Results are sorted by type, then name.
+    pub fn search(&self, query: &str) -> Result<Vec<(ProfileType, ProfileSummary)>> {
+        let mut profile_types: Vec<ProfileType> = self.list_all_profiles()?.into_keys().collect();
+        profile_types.sort_by_key(|profile_type| profile_type.to_string());
+
+        let mut results = Vec::new();
+        for profile_type in profile_types {
+            for summary in self.list_profiles_detailed(profile_type.clone())? {
+                if summary.matches(query) {
+                    results.push((profile_type.clone(), summary));
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Name and path of each profile file of a type
+    fn profile_files(&self, profile_type: ProfileType) -> Result<Vec<(String, PathBuf)>> {
+        let type_dir = self.type_dir(&profile_type)?;
 
         // Check if the directory exists
         if !type_dir.exists() {
@@ -263,7 +509,7 @@ impl ProfileManager {
                 if extension == "json" {
                     if let Some(stem) = path.file_stem() {
                         if let Some(name) = stem.to_str() {
-                            profiles.push(name.to_string());
+                            profiles.push((names::name_from_stem(name), path.clone()));
                         }
                     }
                 }
@@ -273,7 +519,7 @@ impl ProfileManager {
         Ok(profiles)
     }
 
-    /// List all profiles of all types
+    /// List all profiles of all types, built-in presets included
     pub fn list_all_profiles(&self) -> Result<HashMap<ProfileType, Vec<String>>> {
         let mut result = HashMap::new();
 
@@ -284,33 +530,54 @@ impl ProfileManager {
         result.insert(ProfileType::Splitter, self.list_profiles(ProfileType::Splitter)?);
         result.insert(ProfileType::Merger, self.list_profiles(ProfileType::Merger)?);
 
+        // Built-in presets follow the saved profiles of their type
+        for preset in presets::presets() {
+            result.entry(preset.profile_type.clone()).or_insert_with(Vec::new).push(preset.name.clone());
+        }
+
         // List custom profiles
+        for custom_type in self.list_custom_types()? {
+            let custom_profile_type = ProfileType::Custom(custom_type);
+            let profiles = self.list_profiles(custom_profile_type.clone())?;
+            if !profiles.is_empty() {
+                result.insert(custom_profile_type, profiles);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Names of the custom profile types, sorted
+    ///
+    /// A custom type exists from its first saved profile until its directory is removed.
+    pub fn list_custom_types(&self) -> Result<Vec<String>> {
         let custom_dir = self.profiles_dir.join("custom");
-        if custom_dir.exists() {
-            let entries = fs::read_dir(custom_dir)
-                .map_err(ProfileError::ReadError)?;
-
-            for entry in entries {
-                let entry = entry.map_err(ProfileError::ReadError)?;
-                let path = entry.path();
-
-                if path.is_dir() {
-                    if let Some(custom_type) = path.file_name().and_then(|n| n.to_str()) {
-                        let custom_profile_type = ProfileType::Custom(custom_type.to_string());
-                        let profiles = self.list_profiles(custom_profile_type.clone())?;
-                        if !profiles.is_empty() {
-                            result.insert(custom_profile_type, profiles);
-                        }
-                    }
+        if !custom_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut custom_types = Vec::new();
+        for entry in fs::read_dir(custom_dir).map_err(ProfileError::ReadError)? {
+            let path = entry.map_err(ProfileError::ReadError)?.path();
+            if path.is_dir() {
+                if let Some(stem) = path.file_name().and_then(|n| n.to_str()) {
+                    custom_types.push(names::name_from_stem(stem));
                 }
             }
         }
 
-        Ok(result)
+        custom_types.sort();
+        Ok(custom_types)
     }
 
-    /// Import a profile from a file
+    /// Import a profile from a file, in the format given by its extension (JSON if unknown)
     pub fn import_profile<P: AsRef<Path>>(&self, path: P) -> Result<Profile> {
+        let format = ProfileFormat::from_path(path.as_ref()).unwrap_or_default();
+        self.import_profile_as(path, format)
+    }
+
+    /// Import a profile from a file in the given format
+    pub fn import_profile_as<P: AsRef<Path>>(&self, path: P, format: ProfileFormat) -> Result<Profile> {
         // Read the profile file
         let mut file = File::open(path)
             .map_err(ProfileError::ReadError)?;
@@ -319,9 +586,8 @@ impl ProfileManager {
         file.read_to_string(&mut contents)
             .map_err(ProfileError::ReadError)?;
 
-        // Parse the profile
-        let profile: Profile = serde_json::from_str(&contents)
-            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        // Parse the profile, upgrading older files
+        let profile = format.parse(&contents)?;
 
         // Save the profile
         self.save_profile(&profile)?;
@@ -329,26 +595,64 @@ impl ProfileManager {
         Ok(profile)
     }
 
-    /// Export a profile to a file
+    /// Export a profile to a file, in the format given by its extension (JSON if unknown)
     pub fn export_profile<P: AsRef<Path>>(&self, name: &str, profile_type: ProfileType, path: P) -> Result<()> {
+        let format = ProfileFormat::from_path(path.as_ref()).unwrap_or_default();
+        self.export_profile_as(name, profile_type, path, format)
+    }
+
+    /// Export a profile to a file in the given format
+    pub fn export_profile_as<P: AsRef<Path>>(&self, name: &str, profile_type: ProfileType, path: P, format: ProfileFormat) -> Result<()> {
         // Load the profile
         let profile = self.load_profile(name, profile_type)?;
 
         // Serialize the profile
-        let json = serde_json::to_string_pretty(&profile)
-            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        let contents = format.serialize(&profile)?;
 
         // Write to the file
         let mut file = File::create(path)
             .map_err(ProfileError::ReadError)?;
 
-        file.write_all(json.as_bytes())
+        file.write_all(contents.as_bytes())
             .map_err(ProfileError::ReadError)?;
 
         Ok(())
     }
 }
 
+/// Write a profile to `path` through a temporary file in the same directory,
+/// so an interrupted write never leaves a truncated profile behind
+fn write_profile(path: &Path, profile: &Profile) -> Result<()> {
+    let json = serde_json::to_string_pretty(profile)
+        .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+    write_atomically(path, &json)
+}
+
+/// Write `contents` to `path` through a temporary file renamed into place
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let dir = path.parent().ok_or(ProfileError::DirectoryCreationFailed)?;
+    fs::create_dir_all(dir)
+        .map_err(ProfileError::ReadError)?;
+
+    // Not a `.json` name, so profile listings never show it
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temp_path = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let written = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(ProfileError::ReadError(e));
+    }
+
+    Ok(())
+}
+
 // Test module
 #[cfg(test)]
 mod tests {
@@ -389,8 +693,9 @@ mod tests {
         let manager = create_test_profile_manager();
 
         // Create test profiles
-        let profile1 = Profile::new("profile1", ProfileType::Clipper, HashMap::new());
-        let profile2 = Profile::new("profile2", ProfileType::Clipper, HashMap::new());
+        let params = HashMap::from([("ranges".to_string(), "00:00:01-00:00:05".to_string())]);
+        let profile1 = Profile::new("profile1", ProfileType::Clipper, params.clone());
+        let profile2 = Profile::new("profile2", ProfileType::Clipper, params);
 
         manager.save_profile(&profile1).unwrap();
         manager.save_profile(&profile2).unwrap();
@@ -421,4 +726,225 @@ mod tests {
         // Verify it's gone
         assert!(manager.load_profile("delete_me", ProfileType::Merger).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_save_validates_unless_disabled() {
+        let dir = env::temp_dir().join(format!("video_toolkit_test_profiles_validation_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+
+        let params = HashMap::from([("widht".to_string(), "480".to_string())]);
+        let profile = Profile::new("typo", ProfileType::GifConverter, params);
+        match manager.save_profile(&profile) {
+            Err(ProfileError::InvalidParameters(issues)) => assert_eq!(issues.len(), 1),
+            other => panic!("expected invalid parameters, got {:?}", other),
+        }
+        assert!(manager.list_profiles(ProfileType::GifConverter).unwrap().is_empty());
+
+        let manager = manager.with_validation(false);
+        manager.save_profile(&profile).unwrap();
+        assert!(manager.update_profile(&profile).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_overwrite_rename_and_duplicate_profile() {
+        let dir = env::temp_dir().join(format!("video_toolkit_test_profiles_rename_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+
+        let fps = |fps: &str| HashMap::from([("fps".to_string(), fps.to_string())]);
+        manager.save_profile(&Profile::new("draft", ProfileType::GifConverter, fps("10"))).unwrap();
+        let replacement = Profile::new("draft", ProfileType::GifConverter, fps("24"));
+        assert!(matches!(manager.save_profile(&replacement), Err(ProfileError::AlreadyExists(_))));
+        manager.save_profile_with(&replacement, OverwriteMode::Overwrite).unwrap();
+        assert_eq!(manager.load_profile("draft", ProfileType::GifConverter).unwrap().get_parameter("fps").unwrap(), "24");
+
+        manager.save_profile(&Profile::new("taken", ProfileType::GifConverter, fps("5"))).unwrap();
+        assert!(matches!(
+            manager.rename_profile("draft", "taken", ProfileType::GifConverter),
+            Err(ProfileError::AlreadyExists(_))
+        ));
+        manager.rename_profile("draft", "final", ProfileType::GifConverter).unwrap();
+
+        let copy = manager.duplicate_profile("final", ProfileType::GifConverter, "final_copy").unwrap();
+        assert_eq!((copy.name.as_str(), copy.get_parameter("fps").unwrap().as_str()), ("final_copy", "24"));
+        assert!(copy.created > replacement.created);
+        assert!(matches!(
+            manager.duplicate_profile("final", ProfileType::GifConverter, "taken"),
+            Err(ProfileError::AlreadyExists(_))
+        ));
+        manager.delete_profile("final_copy", ProfileType::GifConverter).unwrap();
+
+        let mut names = manager.list_profiles(ProfileType::GifConverter).unwrap();
+        names.sort();
+        assert_eq!(names, ["final", "taken"]);
+        let renamed = manager.load_profile("final", ProfileType::GifConverter).unwrap();
+        assert_eq!((renamed.name.as_str(), renamed.get_parameter("fps").unwrap().as_str()), ("final", "24"));
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.join("gifconverter")).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_custom_types() {
+        let dir = env::temp_dir().join(format!("video_toolkit_test_profiles_custom_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+        assert!(manager.list_custom_types().unwrap().is_empty());
+
+        // Looking a custom type up does not create it
+        assert!(manager.load_profile("missing", ProfileType::Custom("Upscale".to_string())).is_err());
+        assert!(manager.list_custom_types().unwrap().is_empty());
+
+        for custom_type in ["Upscale", "denoise", "Upscale"] {
+            let name = format!("profile {}", manager.list_custom_types().unwrap().len());
+            manager.save_profile(&Profile::new(&name, ProfileType::Custom(custom_type.to_string()), HashMap::new())).unwrap();
+        }
+        assert_eq!(manager.list_custom_types().unwrap(), ["Upscale", "denoise"]);
+        assert_eq!(manager.list_profiles(ProfileType::Custom("Upscale".to_string())).unwrap().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_profiles_detailed() {
+        let dir = env::temp_dir().join(format!("video_toolkit_test_profiles_detailed_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+
+        let params = HashMap::from([("fps".to_string(), "10".to_string()), ("width".to_string(), "320".to_string())]);
+        let profile = Profile::new("small", ProfileType::GifConverter, params).with_description("Small GIFs");
+        manager.save_profile(&profile).unwrap();
+        fs::write(dir.join("gifconverter").join("broken.json"), "{ not json").unwrap();
+
+        let mut summaries = manager.list_profiles_detailed(ProfileType::GifConverter).unwrap();
+        let presets = summaries.iter().filter(|summary| summary.builtin).count();
+        assert_eq!(presets, 2);
+        summaries.retain(|summary| !summary.builtin);
+        assert_eq!(summaries, [
+            ProfileSummary {
+                name: "broken".to_string(),
+                description: None,
+                created: None,
+                last_modified: None,
+                parameter_count: 0,
+                tags: Vec::new(),
+                builtin: false,
+                invalid: true,
+            },
+            ProfileSummary {
+                name: "small".to_string(),
+                description: Some("Small GIFs".to_string()),
+                created: Some(profile.created),
+                last_modified: Some(profile.last_modified),
+                parameter_count: 2,
+                tags: Vec::new(),
+                builtin: false,
+                invalid: false,
+            },
+        ]);
+        assert!(manager.list_profiles_detailed(ProfileType::Merger).unwrap().is_empty());
+        assert_eq!(manager.list_profiles_detailed(ProfileType::Splitter).unwrap()[0].name, "Split – wall 5x");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_profile_names_stay_inside_the_profiles_directory() {
+        let root = env::temp_dir().join(format!("video_toolkit_test_profiles_names_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("profiles");
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+        let profile = |name: &str| Profile::new(name, ProfileType::Custom("notes".to_string()), HashMap::new());
+
+        for name in ["../evil", "../../evil", "nested/evil", "..\\evil", "", "con:", "line\nbreak"] {
+            assert!(matches!(manager.save_profile(&profile(name)), Err(ProfileError::InvalidName(_))), "{:?}", name);
+            assert!(matches!(manager.load_profile(name, ProfileType::Merger), Err(ProfileError::InvalidName(_))));
+            assert!(matches!(manager.delete_profile(name, ProfileType::Merger), Err(ProfileError::InvalidName(_))));
+        }
+        let traversal = Profile::new("x", ProfileType::Custom("../escape".to_string()), HashMap::new());
+        assert!(matches!(manager.save_profile(&traversal), Err(ProfileError::InvalidName(_))));
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        // Unicode names and names differing only by case are separate profiles
+        for name in ["Résumé clips", "résumé clips", "日本語"] {
+            manager.save_profile(&profile(name)).unwrap();
+        }
+        let mut names = manager.list_profiles(ProfileType::Custom("notes".to_string())).unwrap();
+        names.sort();
+        assert_eq!(names, ["Résumé clips", "résumé clips", "日本語"]);
+        assert_eq!(manager.load_profile("日本語", ProfileType::Custom("notes".to_string())).unwrap().name, "日本語");
+        manager.delete_profile("Résumé clips", ProfileType::Custom("notes".to_string())).unwrap();
+        assert!(manager.load_profile("résumé clips", ProfileType::Custom("notes".to_string())).is_ok());
+
+        // Profiles saved under their plain name before names were escaped still load
+        let legacy = Profile::new("Legacy", ProfileType::Splitter, HashMap::new());
+        fs::create_dir_all(dir.join("splitter")).unwrap();
+        fs::write(dir.join("splitter").join("Legacy.json"), serde_json::to_string(&legacy).unwrap()).unwrap();
+        assert_eq!(manager.list_profiles(ProfileType::Splitter).unwrap(), ["Legacy"]);
+        assert!(matches!(manager.save_profile(&legacy), Err(ProfileError::AlreadyExists(_))));
+        manager.delete_profile("Legacy", ProfileType::Splitter).unwrap();
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_search_profiles() {
+        let dir = env::temp_dir().join(format!("video_toolkit_test_profiles_search_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+
+        let twitter = Profile::new("small_gifs", ProfileType::GifConverter, HashMap::new())
+            .with_description("720p GIFs for posts")
+            .with_tags([" Twitter ", "social", "", "Twitter"]);
+        assert_eq!(twitter.tags, ["Twitter", "social"]);
+        manager.save_profile(&twitter).unwrap();
+        let clips = HashMap::from([("ranges".to_string(), "00:00:00-00:00:10".to_string())]);
+        manager.save_profile(&Profile::new("twitter_intro", ProfileType::Clipper, clips).with_tags(["intro"])).unwrap();
+        manager.save_profile(&Profile::new("archive", ProfileType::Merger, HashMap::new())).unwrap();
+
+        let found = |query: &str| -> Vec<String> {
+            manager.search(query).unwrap().into_iter().map(|(_, summary)| summary.name).collect()
+        };
+        assert_eq!(found("TWITTER"), ["twitter_intro", "small_gifs"]);
+        assert_eq!(found("720P twitter"), ["small_gifs"]);
+        assert_eq!(found("social intro"), Vec::<String>::new());
+        assert_eq!(found("").len(), 3 + ProfileManager::builtin_profiles().len());
+
+        let (profile_type, summary) = manager.search("social").unwrap().remove(0);
+        assert_eq!((profile_type, summary.tags), (ProfileType::GifConverter, vec!["Twitter".to_string(), "social".to_string()]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_builtin_presets_are_read_only() {
+        let dir = env::temp_dir().join(format!("video_toolkit_test_profiles_presets_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+        let (name, profile_type) = ("GIF – Slack 2MB", ProfileType::GifConverter);
+
+        let preset = manager.load_profile(name, profile_type.clone()).unwrap();
+        assert!(preset.builtin);
+        assert!(manager.list_all_profiles().unwrap()[&profile_type].contains(&name.to_string()));
+
+        let read_only = |result: Result<()>| matches!(result, Err(ProfileError::ReadOnly(_)));
+        assert!(read_only(manager.delete_profile(name, profile_type.clone())));
+        assert!(read_only(manager.update_profile(&preset)));
+        assert!(read_only(manager.save_profile_with(&preset, OverwriteMode::Overwrite)));
+        assert!(read_only(manager.rename_profile(name, "mine", profile_type.clone())));
+
+        let copy = manager.duplicate_profile(name, profile_type.clone(), "slack").unwrap();
+        assert!(!copy.builtin);
+        assert_eq!(copy.parameters, preset.parameters);
+        let mut copy = manager.load_profile("slack", profile_type.clone()).unwrap();
+        copy.set_parameter("fps", "8");
+        manager.update_profile(&copy).unwrap();
+        assert_eq!(manager.list_profiles(profile_type).unwrap(), ["slack"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
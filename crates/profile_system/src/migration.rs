@@ -0,0 +1,113 @@
+use serde_json::{Map, Value};
+
+use crate::{schema, Profile, ProfileError, Result};
+
+/// Version of the profile files written by this version of the toolkit
+///
+/// 1: the first format, without a `schema_version` field.
+/// 2: parameters use their current names (`ranges`, not `time_ranges`).
+pub const PROFILE_SCHEMA_VERSION: u32 = 2;
+
+/// Version of the profile files saved before versions were recorded
+pub(crate) fn unversioned() -> u32 {
+    1
+}
+
+/// Parse a profile file, upgrading files of older versions to the current one
+///
+/// Files of a newer version are rejected rather than read with fields missing.
+pub(crate) fn parse_profile(contents: &str) -> Result<Profile> {
+    let value: Value = serde_json::from_str(contents)
+        .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+    parse_profile_value(value)
+}
+
+/// Parse a profile already read as JSON, as for `parse_profile`
+pub(crate) fn parse_profile_value(mut value: Value) -> Result<Profile> {
+    let object = value.as_object_mut()
+        .ok_or_else(|| ProfileError::ParseError("a profile must be a JSON object".to_string()))?;
+
+    let version = match object.get("schema_version") {
+        None => unversioned(),
+        Some(version) => version.as_u64().and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| ProfileError::ParseError(format!("invalid schema_version {}", version)))?,
+    };
+    if version > PROFILE_SCHEMA_VERSION {
+        return Err(ProfileError::UnsupportedVersion(version, PROFILE_SCHEMA_VERSION));
+    }
+
+    if version < 2 {
+        fill_timestamps(object);
+    }
+    let mut profile: Profile = serde_json::from_value(value)
+        .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+    if version < 2 {
+        rename_legacy_parameters(&mut profile);
+    }
+
+    profile.schema_version = PROFILE_SCHEMA_VERSION;
+    Ok(profile)
+}
+
+/// Hand-written files may lack the timestamps; they count as created now
+pub(crate) fn fill_timestamps(object: &mut Map<String, Value>) {
+    let now = Value::String(chrono::Utc::now().to_rfc3339());
+    for key in ["created", "last_modified"] {
+        object.entry(key).or_insert_with(|| now.clone());
+    }
+}
+
+/// Move parameters saved under older names to their current names; a value
+/// already under the current name is kept
+fn rename_legacy_parameters(profile: &mut Profile) {
+    let mut legacy: Vec<(String, &'static str)> = profile.parameters.keys()
+        .filter_map(|name| Some((name.clone(), schema::canonical_name(&profile.profile_type, name)?)))
+        .collect();
+    legacy.sort();
+
+    for (old, new) in legacy {
+        if let Some(value) = profile.parameters.remove(&old) {
+            profile.parameters.entry(new.to_string()).or_insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProfileType;
+
+    #[test]
+    fn test_version_1_fixtures_keep_loading() {
+        let clipper = parse_profile(include_str!("../fixtures/v1_clipper.json")).unwrap();
+        assert_eq!(clipper.schema_version, PROFILE_SCHEMA_VERSION);
+        assert_eq!((clipper.name.as_str(), clipper.profile_type.clone()), ("highlights", ProfileType::Clipper));
+        assert_eq!(clipper.description.as_deref(), Some("Intro and outro clips"));
+        assert_eq!(clipper.get_parameter("ranges").unwrap(), "00:00:00-00:00:10,00:05:00-00:05:30");
+        assert!(clipper.get_parameter("time_ranges").is_none());
+        assert_eq!(clipper.created.to_rfc3339(), "2024-03-02T10:15:00+00:00");
+        assert!(crate::ProfileManager::validate(&clipper).is_ok());
+
+        let gif = parse_profile(include_str!("../fixtures/v1_gif_converter_minimal.json")).unwrap();
+        assert_eq!(gif.description, None);
+        assert_eq!(gif.created, gif.last_modified);
+        assert_eq!(gif.get_parameter("max_size").unwrap(), "2");
+        assert_eq!(gif.parameters.len(), 2);
+
+        // Custom parameters have no schema, so their names are left alone
+        let custom = parse_profile(include_str!("../fixtures/v1_custom.json")).unwrap();
+        assert_eq!(custom.profile_type, ProfileType::Custom("watermark".to_string()));
+        assert_eq!(custom.get_parameter("time_ranges").unwrap(), "kept as is");
+    }
+
+    #[test]
+    fn test_newer_versions_are_rejected() {
+        let current = serde_json::to_string(&Profile::new("now", ProfileType::Merger, Default::default())).unwrap();
+        assert!(current.contains("\"schema_version\":2"));
+        assert_eq!(parse_profile(&current).unwrap().name, "now");
+
+        let newer = current.replace("\"schema_version\":2", "\"schema_version\":3");
+        assert!(matches!(parse_profile(&newer), Err(ProfileError::UnsupportedVersion(3, 2))));
+        assert!(matches!(parse_profile("[]"), Err(ProfileError::ParseError(_))));
+    }
+}
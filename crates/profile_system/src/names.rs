@@ -0,0 +1,116 @@
+use crate::{ProfileError, Result};
+
+/// Characters that are not allowed in file names on some systems
+const RESERVED_CHARS: &[char] = &['/', '\\', '<', '>', ':', '"', '|', '?', '*'];
+
+/// File names Windows reserves for devices, whatever their extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Check that `name` can name a profile (or a custom profile type)
+///
+/// Empty names, path separators, characters reserved on Windows, control
+/// characters, `.` and `..`, and leading or trailing whitespace are rejected.
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    let invalid = |reason: &str| Err(ProfileError::InvalidName(format!("'{}' {}", name.escape_debug(), reason)));
+
+    if name.trim().is_empty() {
+        return invalid("is empty");
+    }
+    if name == "." || name == ".." {
+        return invalid("is a directory name");
+    }
+    if let Some(c) = name.chars().find(|c| RESERVED_CHARS.contains(c) || c.is_control()) {
+        return invalid(&format!("contains the character '{}'", c.escape_debug()));
+    }
+    if name.trim() != name || name.ends_with('.') {
+        return invalid("starts or ends with whitespace or ends with a dot");
+    }
+    Ok(())
+}
+
+/// File name, without extension, a profile named `name` is stored under
+///
+/// Lowercase ASCII letters, digits, `-`, `_`, `.` and spaces are kept, every
+/// other byte is written as `%XX`. So names differing only by case get
+/// different files on case-insensitive file systems, and unicode names work
+/// everywhere. The first letter of a device name like `con` is escaped too.
+pub(crate) fn file_stem(name: &str) -> Result<String> {
+    check_name(name)?;
+
+    let mut stem = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.' | ' ') {
+            stem.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                stem.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+
+    let base = stem.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.contains(&base) {
+        stem.replace_range(..1, &format!("%{:02X}", stem.as_bytes()[0]));
+    }
+    Ok(stem)
+}
+
+/// Name of the profile stored under a file stem, the reverse of `file_stem`
+///
+/// Stems that are not valid escapes, e.g. of files saved before names were
+/// escaped, are taken as the name itself.
+pub(crate) fn name_from_stem(stem: &str) -> String {
+    let mut bytes = Vec::with_capacity(stem.len());
+    let mut rest = stem.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let decoded = tail.get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match decoded {
+                Some(decoded) => {
+                    bytes.push(decoded);
+                    rest = &tail[2..];
+                    continue;
+                },
+                None => return stem.to_string(),
+            }
+        }
+        bytes.push(byte);
+        rest = tail;
+    }
+    String::from_utf8(bytes).unwrap_or_else(|_| stem.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stems_round_trip() {
+        for (name, stem) in [
+            ("gif_480-15fps", "gif_480-15fps"),
+            ("My Clips", "%4Dy %43lips"),
+            ("café ☕", "caf%C3%A9 %E2%98%95"),
+            ("100%", "100%25"),
+            ("con", "%63on"),
+            ("nul.backup", "%6Eul.backup"),
+            ("console", "console"),
+        ] {
+            assert_eq!(file_stem(name).unwrap(), stem);
+            assert_eq!(name_from_stem(stem), name);
+        }
+
+        for name in ["", "  ", ".", "..", "../evil", "a/b", "a\\b", "bad:name", "tab\there", " padded", "trailing."] {
+            assert!(matches!(file_stem(name), Err(ProfileError::InvalidName(_))), "{:?}", name);
+        }
+
+        // Files saved before names were escaped keep their name
+        assert_eq!(name_from_stem("Legacy Name"), "Legacy Name");
+        assert_eq!(name_from_stem("50%off"), "50%off");
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::OnceLock;
+
+use crate::{migration, Profile, ProfileType};
+
+/// Preset profiles shipped with the toolkit, one JSON profile each
+const PRESET_FILES: &[&str] = &[
+    include_str!("../presets/gif_slack_2mb.json"),
+    include_str!("../presets/gif_hq_15fps.json"),
+    include_str!("../presets/clip_copy_codec.json"),
+    include_str!("../presets/split_wall_5x.json"),
+];
+
+/// The built-in presets, parsed once
+pub(crate) fn presets() -> &'static [Profile] {
+    static PRESETS: OnceLock<Vec<Profile>> = OnceLock::new();
+    PRESETS.get_or_init(|| {
+        PRESET_FILES
+            .iter()
+            .map(|contents| {
+                let mut profile = migration::parse_profile(contents).expect("built-in presets are valid profiles");
+                profile.builtin = true;
+                profile
+            })
+            .collect()
+    })
+}
+
+/// The built-in preset of a type named `name`
+pub(crate) fn find(name: &str, profile_type: &ProfileType) -> Option<&'static Profile> {
+    presets().iter().find(|preset| preset.name == name && preset.profile_type == *profile_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProfileManager;
+
+    #[test]
+    fn test_presets_are_valid() {
+        assert_eq!(presets().len(), PRESET_FILES.len());
+        for preset in presets() {
+            assert!(preset.builtin);
+            assert!(ProfileManager::validate(preset).is_ok(), "{}", preset.name);
+            assert!(crate::names::file_stem(&preset.name).is_ok(), "{}", preset.name);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{presets, write_atomically, ProfileError, ProfileManager, ProfileType, Result};
+
+/// File in the profiles directory holding the recently used profiles
+const RECENT_FILE: &str = "recent.json";
+
+/// Entries kept in the recently used list
+const MAX_RECENT: usize = 20;
+
+/// A profile applied to an operation, and when
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileUsage {
+    pub name: String,
+    pub profile_type: ProfileType,
+    pub used: chrono::DateTime<chrono::Utc>,
+}
+
+impl ProfileManager {
+    fn recent_path(&self) -> PathBuf {
+        self.profiles_dir.join(RECENT_FILE)
+    }
+
+    /// Recently used list as saved, most recent first; empty if missing or unreadable
+    fn read_recent(&self) -> Vec<ProfileUsage> {
+        fs::read_to_string(self.recent_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record that a profile was applied to an operation, for `recent_profiles`
+    ///
+    /// Using a profile again moves it to the front; only the last few are kept.
+    pub fn record_usage(&self, name: &str, profile_type: ProfileType) -> Result<()> {
+        let mut recent = self.read_recent();
+        recent.retain(|usage| !(usage.name == name && usage.profile_type == profile_type));
+        recent.insert(0, ProfileUsage { name: name.to_string(), profile_type, used: chrono::Utc::now() });
+        recent.truncate(MAX_RECENT);
+
+        let json = serde_json::to_string_pretty(&recent)
+            .map_err(|e| ProfileError::ParseError(e.to_string()))?;
+        write_atomically(&self.recent_path(), &json)
+    }
+
+    /// Up to `limit` recently used profiles of any type, most recent first
+    ///
+    /// Profiles deleted or renamed since are left out.
+    pub fn recent_profiles(&self, limit: usize) -> Result<Vec<ProfileUsage>> {
+        Ok(self.read_recent()
+            .into_iter()
+            .filter(|usage| self.profile_exists(&usage.name, &usage.profile_type))
+            .take(limit)
+            .collect())
+    }
+
+    /// Up to `limit` recently used profiles of one type, most recent first
+    pub fn recent_profiles_of(&self, profile_type: &ProfileType, limit: usize) -> Result<Vec<ProfileUsage>> {
+        let recent = self.recent_profiles(MAX_RECENT)?;
+        Ok(recent.into_iter().filter(|usage| usage.profile_type == *profile_type).take(limit).collect())
+    }
+
+    fn profile_exists(&self, name: &str, profile_type: &ProfileType) -> bool {
+        presets::find(name, profile_type).is_some()
+            || self.get_profile_path(name, profile_type.clone()).is_ok_and(|path| path.exists())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Profile;
+
+    #[test]
+    fn test_recent_profiles_are_deduplicated_and_persisted() {
+        let dir = std::env::temp_dir().join(format!("video_toolkit_test_profiles_recent_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = ProfileManager::with_directory(&dir).unwrap();
+        for name in ["a", "b", "c"] {
+            manager.save_profile(&Profile::new(name, ProfileType::Merger, HashMap::new())).unwrap();
+        }
+        assert!(manager.recent_profiles(3).unwrap().is_empty());
+
+        for name in ["a", "b", "c", "a"] {
+            manager.record_usage(name, ProfileType::Merger).unwrap();
+        }
+        manager.record_usage("Split – wall 5x", ProfileType::Splitter).unwrap();
+
+        let names = |recent: Vec<ProfileUsage>| recent.into_iter().map(|usage| usage.name).collect::<Vec<_>>();
+        let reopened = ProfileManager::with_directory(&dir).unwrap();
+        assert_eq!(names(reopened.recent_profiles(10).unwrap()), ["Split – wall 5x", "a", "c", "b"]);
+        assert_eq!(names(reopened.recent_profiles_of(&ProfileType::Merger, 2).unwrap()), ["a", "c"]);
+
+        reopened.delete_profile("c", ProfileType::Merger).unwrap();
+        assert_eq!(names(reopened.recent_profiles_of(&ProfileType::Merger, 3).unwrap()), ["a", "b"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
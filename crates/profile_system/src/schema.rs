@@ -0,0 +1,308 @@
+use crate::{Profile, ProfileType};
+
+/// Kind of value a profile parameter holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterType {
+    String,
+    /// Whole number of zero or more
+    Integer,
+    Float,
+    /// `true` or `false`
+    Boolean,
+    FilePath,
+    DirectoryPath,
+    /// One of a fixed set of values, compared without case
+    Choice(&'static [&'static str]),
+}
+
+impl std::fmt::Display for ParameterType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterType::String => write!(f, "text"),
+            ParameterType::Integer => write!(f, "a whole number"),
+            ParameterType::Float => write!(f, "a number"),
+            ParameterType::Boolean => write!(f, "true or false"),
+            ParameterType::FilePath => write!(f, "a file path"),
+            ParameterType::DirectoryPath => write!(f, "a directory path"),
+            ParameterType::Choice(values) => write!(f, "one of {}", values.join(", ")),
+        }
+    }
+}
+
+/// Describes a parameter that profiles of a type accept
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+    pub parameter_type: ParameterType,
+    /// Older names accepted for this parameter
+    pub aliases: &'static [&'static str],
+}
+
+impl ParameterInfo {
+    const fn new(name: &'static str, parameter_type: ParameterType, description: &'static str) -> Self {
+        Self { name, description, required: false, parameter_type, aliases: &[] }
+    }
+
+    const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    const fn alias(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Whether `value` is acceptable, once trimmed
+    fn accepts(&self, value: &str) -> bool {
+        match self.parameter_type {
+            ParameterType::String | ParameterType::FilePath | ParameterType::DirectoryPath => true,
+            ParameterType::Integer => value.parse::<u64>().is_ok(),
+            ParameterType::Float => value.parse::<f64>().is_ok_and(f64::is_finite),
+            ParameterType::Boolean => value.parse::<bool>().is_ok(),
+            ParameterType::Choice(values) => values.iter().any(|choice| choice.eq_ignore_ascii_case(value)),
+        }
+    }
+}
+
+/// A problem found in the parameters of a profile
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// A required parameter is not set
+    Missing(&'static str),
+    /// A parameter the profile type does not have, with the closest known name
+    Unknown { name: String, suggestion: Option<&'static str> },
+    /// A value of the wrong type or outside the allowed values
+    InvalidValue { name: String, value: String, expected: ParameterType },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::Missing(name) => write!(f, "'{}' is required", name),
+            ValidationIssue::Unknown { name, suggestion: Some(suggestion) } => {
+                write!(f, "unknown parameter '{}' (did you mean '{}'?)", name, suggestion)
+            },
+            ValidationIssue::Unknown { name, suggestion: None } => write!(f, "unknown parameter '{}'", name),
+            ValidationIssue::InvalidValue { name, value, expected } => {
+                write!(f, "'{}' is '{}', expected {}", name, value, expected)
+            },
+        }
+    }
+}
+
+const VIDEO_FORMATS: ParameterType = ParameterType::Choice(&["mp4", "mkv", "mov", "webm", "avi", "flv"]);
+
+/// Input and run options shared by the batch operations
+const BATCH_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("recursive", ParameterType::Boolean, "Process directories recursively"),
+    ParameterInfo::new("follow_symlinks", ParameterType::Boolean, "Descend into symlinked directories"),
+    ParameterInfo::new("max_depth", ParameterType::Integer, "Directory levels scanned when recursive"),
+    ParameterInfo::new("parallel", ParameterType::Boolean, "Process files in parallel"),
+    ParameterInfo::new("pattern", ParameterType::String, "Regex the files must match"),
+    ParameterInfo::new("pattern_target", ParameterType::Choice(&["name", "relative", "full"]), "What the pattern is matched against"),
+    ParameterInfo::new("by_content", ParameterType::Boolean, "Match files by content instead of extension"),
+    ParameterInfo::new("order", ParameterType::Choice(&["name", "size", "modified"]), "Order the files are processed in"),
+    ParameterInfo::new("descending", ParameterType::Boolean, "Process the files in descending order"),
+    ParameterInfo::new("mirror_structure", ParameterType::Boolean, "Recreate the input directories in the output directory"),
+    ParameterInfo::new("per_file_timeout", ParameterType::Integer, "Seconds a file may take before it fails"),
+    ParameterInfo::new("retries", ParameterType::Integer, "Times a file is run again after a transient failure"),
+    ParameterInfo::new("retry_backoff", ParameterType::Integer, "Seconds before the first retry"),
+    ParameterInfo::new("fail_fast", ParameterType::Boolean, "Start no new files after the first failure"),
+    ParameterInfo::new("max_failures", ParameterType::Integer, "Start no new files once this many failed"),
+];
+
+const CLIPPER_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("ranges", ParameterType::String, "Comma-separated time ranges, e.g. 00:01:00-00:02:00")
+        .required()
+        .alias(&["time_ranges"]),
+    ParameterInfo::new("output_dir", ParameterType::DirectoryPath, "Directory of the clips"),
+    ParameterInfo::new("copy_codec", ParameterType::Boolean, "Copy the streams instead of re-encoding"),
+    ParameterInfo::new("suffix", ParameterType::String, "Suffix of the clip names"),
+    ParameterInfo::new("avoid_negative_ts", ParameterType::Choice(&["auto", "make_zero", "disabled"]), "Timestamp handling of copied clips"),
+    ParameterInfo::new("verify_outputs", ParameterType::Boolean, "Check the duration of each clip"),
+    ParameterInfo::new("hw_accel", ParameterType::String, "auto, off or a hardware encoder name"),
+    ParameterInfo::new("format", VIDEO_FORMATS, "Container of the clips"),
+];
+
+const GIF_CONVERTER_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("width", ParameterType::Integer, "Width of the GIFs in pixels"),
+    ParameterInfo::new("fps", ParameterType::Integer, "Frames per second"),
+    ParameterInfo::new("max_size", ParameterType::Float, "Largest GIF size in MB").alias(&["max_size_mb"]),
+    ParameterInfo::new("optimize", ParameterType::Boolean, "Shrink GIFs larger than max_size"),
+    ParameterInfo::new("output_dir", ParameterType::DirectoryPath, "Directory of the GIFs"),
+    ParameterInfo::new("crop", ParameterType::String, "x,y,w,h or auto"),
+    ParameterInfo::new("boomerang", ParameterType::Boolean, "Play forward then backward"),
+    ParameterInfo::new("loop_count", ParameterType::Integer, "Times the GIF loops (0 forever)"),
+    ParameterInfo::new("gifsicle", ParameterType::Boolean, "Optimize with gifsicle"),
+    ParameterInfo::new("gifsicle_level", ParameterType::Choice(&["1", "2", "3"]), "gifsicle optimization level"),
+    ParameterInfo::new("gifsicle_lossy", ParameterType::Integer, "gifsicle lossy compression"),
+    ParameterInfo::new("poster", ParameterType::Boolean, "Write a PNG poster frame next to each GIF"),
+    ParameterInfo::new("poster_time", ParameterType::String, "Timestamp of the poster frame"),
+];
+
+const GIF_TRANSPARENCY_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("backup", ParameterType::Boolean, "Back up each GIF before changing it"),
+    ParameterInfo::new("backup_dir", ParameterType::DirectoryPath, "Directory of the backups"),
+    ParameterInfo::new("also_webp", ParameterType::Boolean, "Also write an animated WebP"),
+    ParameterInfo::new("webp_quality", ParameterType::Integer, "WebP quality from 0 to 100"),
+    ParameterInfo::new("webp_lossless", ParameterType::Boolean, "Write lossless WebP files"),
+    ParameterInfo::new("webp_output_dir", ParameterType::DirectoryPath, "Directory of the WebP files"),
+    ParameterInfo::new("legacy_trailer", ParameterType::Boolean, "Change the trailer byte instead"),
+    ParameterInfo::new("no_atomic", ParameterType::Boolean, "Edit the GIFs in place"),
+    ParameterInfo::new("output_dir", ParameterType::DirectoryPath, "Write transparent copies here"),
+];
+
+const SPLITTER_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("output_dir", ParameterType::DirectoryPath, "Directory of the slices"),
+    ParameterInfo::new("prefix", ParameterType::String, "Prefix of the slice names"),
+    ParameterInfo::new("custom_encode", ParameterType::String, "FFmpeg encoding options"),
+    ParameterInfo::new("force", ParameterType::Boolean, "Skip the dimension check"),
+    ParameterInfo::new("slices", ParameterType::Integer, "Number of slices"),
+    ParameterInfo::new("direction", ParameterType::Choice(&["vertical", "horizontal"]), "Direction of the cuts"),
+    ParameterInfo::new("expected_dimensions", ParameterType::String, "WIDTHxHEIGHT the inputs must have"),
+    ParameterInfo::new("format", VIDEO_FORMATS, "Container of the slices"),
+    ParameterInfo::new("segment_duration", ParameterType::Float, "Cut into segments of this many seconds"),
+    ParameterInfo::new("copy_codec", ParameterType::Boolean, "Copy the streams when cutting segments"),
+    ParameterInfo::new("jobs", ParameterType::Integer, "Slices of a file encoded at once"),
+    ParameterInfo::new("hw_accel", ParameterType::String, "auto, off or a hardware encoder name"),
+];
+
+const MERGER_PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo::new("audio", ParameterType::FilePath, "The same audio file for every video"),
+    ParameterInfo::new("output_dir", ParameterType::DirectoryPath, "Directory of the merged files"),
+    ParameterInfo::new("shortest", ParameterType::Boolean, "Stop at the end of the shorter stream"),
+    ParameterInfo::new("copy_codec", ParameterType::Boolean, "Copy the video stream"),
+    ParameterInfo::new("audio_ext", ParameterType::String, "Extension of the audio file named after each video"),
+    ParameterInfo::new("audio_dir", ParameterType::DirectoryPath, "Directory of the audio files"),
+    ParameterInfo::new("audio_fade_in", ParameterType::Float, "Seconds of audio fade-in"),
+    ParameterInfo::new("audio_fade_out", ParameterType::Float, "Seconds of audio fade-out"),
+    ParameterInfo::new("format", VIDEO_FORMATS, "Container of the merged files"),
+    ParameterInfo::new("hw_accel", ParameterType::String, "auto, off or a hardware encoder name"),
+];
+
+/// Parameters accepted by profiles of a type; `None` for custom types, whose parameters are free-form
+pub fn schema(profile_type: &ProfileType) -> Option<Vec<&'static ParameterInfo>> {
+    let operation = match profile_type {
+        ProfileType::Clipper => CLIPPER_PARAMETERS,
+        ProfileType::GifConverter => GIF_CONVERTER_PARAMETERS,
+        ProfileType::GifTransparency => GIF_TRANSPARENCY_PARAMETERS,
+        ProfileType::Splitter => SPLITTER_PARAMETERS,
+        ProfileType::Merger => MERGER_PARAMETERS,
+        ProfileType::Custom(_) => return None,
+    };
+    Some(operation.iter().chain(BATCH_PARAMETERS).collect())
+}
+
+/// Current name of a parameter given under one of its older names
+pub(crate) fn canonical_name(profile_type: &ProfileType, name: &str) -> Option<&'static str> {
+    schema(profile_type)?.into_iter().find(|info| info.aliases.contains(&name)).map(|info| info.name)
+}
+
+/// Check the parameters of a profile against the schema of its type
+///
+/// Empty values count as unset. Issues are sorted by parameter name.
+pub fn validate(profile: &Profile) -> Vec<ValidationIssue> {
+    let Some(schema) = schema(&profile.profile_type) else {
+        return Vec::new();
+    };
+    let is_set = |name: &str| profile.parameters.get(name).is_some_and(|value| !value.trim().is_empty());
+
+    let mut issues: Vec<ValidationIssue> = schema
+        .iter()
+        .filter(|info| info.required && !is_set(info.name) && !info.aliases.iter().any(|alias| is_set(alias)))
+        .map(|info| ValidationIssue::Missing(info.name))
+        .collect();
+
+    let mut parameters: Vec<(&String, &String)> = profile.parameters.iter().collect();
+    parameters.sort();
+    for (name, value) in parameters {
+        let value = value.trim();
+        match schema.iter().find(|info| info.name == name || info.aliases.contains(&name.as_str())) {
+            Some(info) if value.is_empty() || info.accepts(value) => {},
+            Some(info) => issues.push(ValidationIssue::InvalidValue {
+                name: name.clone(),
+                value: value.to_string(),
+                expected: info.parameter_type,
+            }),
+            None => issues.push(ValidationIssue::Unknown { name: name.clone(), suggestion: closest_name(&schema, name) }),
+        }
+    }
+    issues
+}
+
+/// Known parameter name within two typos of `name`
+fn closest_name(schema: &[&'static ParameterInfo], name: &str) -> Option<&'static str> {
+    schema
+        .iter()
+        .map(|info| (edit_distance(info.name, name), info.name))
+        .filter(|&(distance, _)| distance <= 2)
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// Levenshtein distance, counting a swap of neighbouring letters as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    rows[0] = (0..=b.len()).collect();
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn profile(profile_type: ProfileType, parameters: &[(&str, &str)]) -> Profile {
+        let parameters: HashMap<String, String> =
+            parameters.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        Profile::new("test", profile_type, parameters)
+    }
+
+    #[test]
+    fn test_validate_reports_typos_types_and_missing_keys() {
+        let issues = validate(&profile(ProfileType::GifConverter, &[
+            ("widht", "480"),
+            ("fps", "fast"),
+            ("order", "Size"),
+            ("gifsicle_level", "5"),
+            ("max_size_mb", "4.5"),
+            ("poster_time", ""),
+        ]));
+        assert_eq!(issues, [
+            ValidationIssue::InvalidValue { name: "fps".to_string(), value: "fast".to_string(), expected: ParameterType::Integer },
+            ValidationIssue::InvalidValue {
+                name: "gifsicle_level".to_string(),
+                value: "5".to_string(),
+                expected: ParameterType::Choice(&["1", "2", "3"]),
+            },
+            ValidationIssue::Unknown { name: "widht".to_string(), suggestion: Some("width") },
+        ]);
+        assert_eq!(issues[2].to_string(), "unknown parameter 'widht' (did you mean 'width'?)");
+
+        let issues = validate(&profile(ProfileType::Clipper, &[("recursive", "yes")]));
+        assert_eq!(issues[0], ValidationIssue::Missing("ranges"));
+        assert_eq!(issues[1].to_string(), "'recursive' is 'yes', expected true or false");
+        assert!(validate(&profile(ProfileType::Clipper, &[("time_ranges", "00:00:01-00:00:02")])).is_empty());
+
+        // Custom profiles hold whatever their plugin needs
+        assert!(validate(&profile(ProfileType::Custom("watermark".to_string()), &[("anything", "goes")])).is_empty());
+    }
+}
@@ -1,24 +1,483 @@
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::str::FromStr;
+
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 
 use common::{
-    execute_ffmpeg, get_video_dimensions, verify_input_file,
-    Result, VideoToolkitError
+    cancellation_scope, escape_filter_text, get_codec_options_for, get_duration, get_supported_formats,
+    get_video_dimensions, is_dry_run, is_format_supported_for_operation, output_written, split_arguments,
+    verify_input_file, video_encoder_in,
+    CancellationToken, EncoderPreference, FfmpegCommand, Result, RunLimits, VideoToolkitError, Warning, WarningCode,
+    SOFTWARE_H264_ENCODER
 };
 
-/// Verify that the input video has the expected 1920x1080 dimensions
-pub fn verify_video_dimensions(input_file: &str) -> Result<(u32, u32)> {
+/// Corner of a slice where the identification label is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl LabelCorner {
+    /// drawtext x/y expressions for this corner, `margin` pixels from the edges
+    fn position(&self, margin: u32) -> (String, String) {
+        let left = margin.to_string();
+        let top = margin.to_string();
+        let right = format!("w-tw-{}", margin);
+        let bottom = format!("h-th-{}", margin);
+
+        match self {
+            LabelCorner::TopLeft => (left, top),
+            LabelCorner::TopRight => (right, top),
+            LabelCorner::BottomLeft => (left, bottom),
+            LabelCorner::BottomRight => (right, bottom),
+            LabelCorner::Center => ("(w-tw)/2".to_string(), "(h-th)/2".to_string()),
+        }
+    }
+}
+
+impl FromStr for LabelCorner {
+    type Err = VideoToolkitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "top-left" => Ok(LabelCorner::TopLeft),
+            "top-right" => Ok(LabelCorner::TopRight),
+            "bottom-left" => Ok(LabelCorner::BottomLeft),
+            "bottom-right" => Ok(LabelCorner::BottomRight),
+            "center" => Ok(LabelCorner::Center),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid label corner '{}': expected top-left, top-right, bottom-left, bottom-right or center",
+                value
+            ))),
+        }
+    }
+}
+
+/// Direction in which a video is cut into slices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SliceDirection {
+    /// Side-by-side columns spanning the full height
+    #[default]
+    Vertical,
+    /// Stacked rows spanning the full width
+    Horizontal,
+}
+
+impl fmt::Display for SliceDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceDirection::Vertical => write!(f, "vertical"),
+            SliceDirection::Horizontal => write!(f, "horizontal"),
+        }
+    }
+}
+
+impl FromStr for SliceDirection {
+    type Err = VideoToolkitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "vertical" => Ok(SliceDirection::Vertical),
+            "horizontal" => Ok(SliceDirection::Horizontal),
+            _ => Err(VideoToolkitError::Other(format!(
+                "Invalid slice direction '{}': expected vertical or horizontal",
+                value
+            ))),
+        }
+    }
+}
+
+/// Number of slices when none is given, matching a row of five 384px-wide screens
+pub const DEFAULT_SLICE_COUNT: u32 = 5;
+
+/// Region of the source video covered by one slice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slice {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Cut a `width`x`height` frame into `count` equal slices along `direction`
+///
+/// When the size does not divide evenly, the last slice gets the extra pixels.
+pub fn slice_geometry(width: u32, height: u32, count: u32, direction: SliceDirection) -> Result<Vec<Slice>> {
+    let length = match direction {
+        SliceDirection::Vertical => width,
+        SliceDirection::Horizontal => height,
+    };
+
+    if count == 0 {
+        return Err(VideoToolkitError::Other("Number of slices must be at least 1".to_string()));
+    }
+    if count > length {
+        return Err(VideoToolkitError::Other(format!(
+            "Cannot cut {} {} slices from a {}x{} video",
+            count, direction, width, height
+        )));
+    }
+
+    let size = length / count;
+    let slices = (0..count)
+        .map(|i| {
+            let offset = i * size;
+            let size = if i == count - 1 { length - offset } else { size };
+            match direction {
+                SliceDirection::Vertical => Slice { x: offset, y: 0, width: size, height },
+                SliceDirection::Horizontal => Slice { x: 0, y: offset, width, height: size },
+            }
+        })
+        .collect();
+
+    Ok(slices)
+}
+
+/// Widen each slice by `overlap` pixels into its neighbours, clamped at the frame edges
+///
+/// Neighbouring slices then share `2 * overlap` pixels for edge blending. The
+/// overlap must be less than half the narrowest slice.
+pub fn overlap_slices(slices: &[Slice], overlap: u32, direction: SliceDirection) -> Result<Vec<Slice>> {
+    let span = |slice: &Slice| match direction {
+        SliceDirection::Vertical => (slice.x, slice.width),
+        SliceDirection::Horizontal => (slice.y, slice.height),
+    };
+
+    let narrowest = slices.iter().map(|slice| span(slice).1).min().unwrap_or(0);
+    if overlap.saturating_mul(2) >= narrowest {
+        return Err(VideoToolkitError::Other(format!(
+            "Overlap of {}px is too large for {}px slices: twice the overlap must be smaller than the slice",
+            overlap, narrowest
+        )));
+    }
+
+    let length = slices.iter().map(|slice| span(slice).0 + span(slice).1).max().unwrap_or(0);
+    let widened = slices
+        .iter()
+        .map(|slice| {
+            let (offset, size) = span(slice);
+            let start = offset.saturating_sub(overlap);
+            let size = (offset + size + overlap).min(length) - start;
+            match direction {
+                SliceDirection::Vertical => Slice { x: start, width: size, ..*slice },
+                SliceDirection::Horizontal => Slice { y: start, height: size, ..*slice },
+            }
+        })
+        .collect();
+
+    Ok(widened)
+}
+
+/// Appearance of the slice identification label
+#[derive(Debug, Clone)]
+pub struct LabelStyle {
+    /// Label text; `{index}` is replaced by the 1-based slice number, `{x}` and `{y}` by its offsets
+    pub template: String,
+    /// Font size as a fraction of the slice's shorter side
+    pub size_ratio: f32,
+    pub corner: LabelCorner,
+}
+
+impl Default for LabelStyle {
+    fn default() -> Self {
+        Self {
+            template: "{index}".to_string(),
+            size_ratio: 0.5,
+            corner: LabelCorner::default(),
+        }
+    }
+}
+
+/// Additional options for the video splitter
+#[derive(Debug, Clone)]
+pub struct SplitOptions {
+    /// Number of equal slices to cut
+    pub num_slices: u32,
+    pub direction: SliceDirection,
+    /// Reject inputs whose dimensions differ (e.g. a wall built for 1920x1080 sources)
+    pub expected_dimensions: Option<(u32, u32)>,
+    /// Container format (file extension) of the slices
+    pub output_format: String,
+    /// Burn the slice number into each output to tell the files apart when wiring screens
+    pub label_slices: bool,
+    pub label_style: LabelStyle,
+    /// Pixels each slice extends into its neighbours, for blending on LED walls
+    pub overlap_px: u32,
+    /// Maximum number of slices encoded at once when they are encoded separately;
+    /// `None` uses one per CPU core
+    pub max_jobs: Option<usize>,
+    /// Stops the split and kills the running FFmpeg processes when cancelled
+    pub cancel: Option<CancellationToken>,
+    /// Whether slices encoded with the format's usual codecs may use a hardware video encoder
+    pub encoder: EncoderPreference,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        Self {
+            num_slices: DEFAULT_SLICE_COUNT,
+            direction: SliceDirection::default(),
+            expected_dimensions: None,
+            output_format: DEFAULT_OUTPUT_FORMAT.to_string(),
+            label_slices: false,
+            label_style: LabelStyle::default(),
+            overlap_px: 0,
+            max_jobs: None,
+            cancel: None,
+            encoder: EncoderPreference::default(),
+        }
+    }
+}
+
+/// Outcome of a split
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitReport {
+    /// Whether every slice was written
+    pub success: bool,
+    /// Non-fatal problems met during the split
+    pub warnings: Vec<Warning>,
+    /// Region of the input written to each slice, overlap included
+    pub slices: Vec<Slice>,
+    /// Video encoder the slices were written with, unless the stream was copied
+    pub video_encoder: Option<String>,
+}
+
+/// Split custom encoding options into FFmpeg arguments
+///
+/// Quoted arguments are kept whole (e.g. `-metadata title="My Clip"`). Inputs and
+/// output files are added by the splitter, so `-i` and any argument that is not
+/// an option or an option's value are rejected.
+pub fn parse_encode_options(encode_options: &str) -> Result<Vec<String>> {
+    let invalid = |reason: String| VideoToolkitError::Other(format!("Invalid encode options '{}': {}", encode_options, reason));
+
+    let args = split_arguments(encode_options).map_err(|e| invalid(e.to_string()))?;
+    let is_option = |arg: &str| arg.len() > 1 && arg.starts_with('-') && arg.parse::<f64>().is_err();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "-i" {
+            return Err(invalid("inputs cannot be added with '-i'".to_string()));
+        }
+        if !is_option(arg) && (i == 0 || !is_option(&args[i - 1])) {
+            return Err(invalid(format!("'{}' is not an option value; output files cannot be given here", arg)));
+        }
+    }
+
+    Ok(args)
+}
+
+/// Check whether the encode arguments stream-copy the video, which rules out any filtering
+pub fn copies_video_stream(encode_args: &[String]) -> bool {
+    encode_args.windows(2).any(|pair| {
+        matches!(pair[0].as_str(), "-c:v" | "-vcodec" | "-codec:v" | "-c" | "-codec") && pair[1] == "copy"
+    })
+}
+
+/// Build the drawtext filter that labels a single slice
+fn build_label_filter(index: usize, slice: &Slice, style: &LabelStyle) -> String {
+    let text = style.template
+        .replace("{index}", &index.to_string())
+        .replace("{x}", &slice.x.to_string())
+        .replace("{y}", &slice.y.to_string());
+    let font_size = ((slice.width.min(slice.height) as f32 * style.size_ratio).round() as u32).max(1);
+    let (x, y) = style.corner.position(font_size / 4);
+
+    format!(
+        "drawtext=text={}:expansion=none:fontsize={}:fontcolor=white@0.6:box=1:boxcolor=black@0.4:boxborderw={}:x={}:y={}",
+        escape_filter_text(&text), font_size, font_size / 8, x, y
+    )
+}
+
+/// Label style to burn into the slices, if labels are requested and possible
+///
+/// Labels require re-encoding; stream copy cannot carry a drawtext overlay.
+fn slice_label<'a>(encode_args: &[String], options: &'a SplitOptions, warnings: &mut Vec<Warning>) -> Option<&'a LabelStyle> {
+    if !options.label_slices {
+        None
+    } else if copies_video_stream(encode_args) {
+        warnings.push(Warning::new(
+            WarningCode::LabelsDisabled,
+            "slice labels disabled because the encode options copy the video stream",
+        ));
+        None
+    } else {
+        Some(&options.label_style)
+    }
+}
+
+/// Build the complete video filter for one slice
+fn build_slice_filter(index: usize, slice: &Slice, label: Option<&LabelStyle>) -> String {
+    let crop = format!("crop={}:{}:{}:{}", slice.width, slice.height, slice.x, slice.y);
+    match label {
+        Some(style) => format!("{},{}", crop, build_label_filter(index, slice, style)),
+        None => crop,
+    }
+}
+
+/// Encoding options used when none are given
+const DEFAULT_ENCODE_OPTIONS: &str = "-c:v libx264 -preset medium -crf 22 -c:a copy";
+
+/// Container format of the slices when none is given
+pub const DEFAULT_OUTPUT_FORMAT: &str = "mp4";
+
+/// Check that the splitter can write `format`, returning it in lower case
+pub fn validate_output_format(format: &str) -> Result<String> {
+    let format = format.trim().to_lowercase();
+    if is_format_supported_for_operation(&format, "splitter") {
+        Ok(format)
+    } else {
+        Err(VideoToolkitError::Other(format!(
+            "Output format '{}' is not supported by the splitter (supported: {})",
+            format,
+            get_supported_formats("splitter").join(", ")
+        )))
+    }
+}
+
+/// Encoding options for a container format: its usual codecs, at the default x264 quality
+fn format_encode_options(format: &str, encoder: &EncoderPreference) -> Vec<String> {
+    let mut codecs = get_codec_options_for(format, encoder);
+    if codecs.iter().any(|arg| arg == SOFTWARE_H264_ENCODER) {
+        codecs.extend(["-preset", "medium", "-crf", "22"].map(String::from));
+    }
+    codecs
+}
+
+/// Encode one slice of the input with the given video filter
+fn write_slice(input_file: &str, filter: &str, encode_args: &[String], output_file: &Path) -> Result<()> {
+    FfmpegCommand::new()
+        .overwrite()
+        .input(input_file)
+        .option("-filter:v", filter)
+        .args(encode_args.iter().cloned())
+        .output(output_file)
+        .execute()?;
+
+    // Verify output file was created
+    if output_written(output_file) {
+        Ok(())
+    } else {
+        Err(VideoToolkitError::OutputFileNotCreated)
+    }
+}
+
+/// One slice to encode: its video filter and output file
+struct SliceJob {
+    filter: String,
+    output: PathBuf,
+}
+
+/// Single FFmpeg run that decodes the input once and writes every slice
+///
+/// The decoded video is duplicated with `split`, each copy goes through its own
+/// slice filter and is mapped, with the input audio, to its own output.
+fn build_single_pass_command(input_file: &str, jobs: &[SliceJob], encode_args: &[String]) -> FfmpegCommand {
+    let inputs: String = (0..jobs.len()).map(|i| format!("[in{}]", i)).collect();
+    let mut graph = format!("[0:v]split={}{}", jobs.len(), inputs);
+    for (i, job) in jobs.iter().enumerate() {
+        graph.push_str(&format!(";[in{}]{}[out{}]", i, job.filter, i));
+    }
+
+    let mut command = FfmpegCommand::new().overwrite().input(input_file).filter_complex(graph);
+    for (i, job) in jobs.iter().enumerate() {
+        command = command
+            .map(&format!("[out{}]", i))
+            .map("0:a?")
+            .args(encode_args.iter().cloned())
+            .output(&job.output);
+    }
+
+    command
+}
+
+/// Encode all slices, decoding the input only once when possible
+///
+/// Falls back to one FFmpeg run per slice, at most `max_jobs` at a time, if the
+/// combined run fails. Returns, for each slice, the error that kept its output
+/// from being produced, or the error that stopped the split if it was cancelled or timed out.
+fn write_slices(
+    input_file: &str,
+    jobs: &[SliceJob],
+    encode_args: &[String],
+    max_jobs: Option<usize>,
+) -> Result<Vec<Option<String>>> {
+    if jobs.len() > 1 {
+        // Leftovers from an earlier run would pass for produced slices
+        if !is_dry_run() {
+            for job in jobs {
+                let _ = fs::remove_file(&job.output);
+            }
+        }
+
+        match build_single_pass_command(input_file, jobs, encode_args).execute() {
+            Ok(_) => {
+                return Ok(jobs
+                    .iter()
+                    .map(|job| {
+                        (!output_written(&job.output)).then(|| VideoToolkitError::OutputFileNotCreated.to_string())
+                    })
+                    .collect());
+            },
+            Err(e) if e.is_interruption() => return Err(e),
+            Err(e) => eprintln!("Single-pass split failed, encoding one slice at a time: {}", e),
+        }
+    }
+
+    // The worker threads do not see the caller's cancellation and deadline scopes
+    let limits = RunLimits::current();
+    let encode = |job: &SliceJob| {
+        let _scopes = limits.enter();
+        write_slice(input_file, &job.filter, encode_args, &job.output).err().map(|e| e.to_string())
+    };
+    let errors: Vec<Option<String>> = match max_jobs {
+        None => jobs.par_iter().map(encode).collect(),
+        Some(max_jobs) if max_jobs > 1 => match ThreadPoolBuilder::new().num_threads(max_jobs).build() {
+            Ok(pool) => pool.install(|| jobs.par_iter().map(encode).collect()),
+            Err(_) => jobs.iter().map(encode).collect(),
+        },
+        Some(_) => jobs.iter().map(encode).collect(),
+    };
+
+    // Slices that failed because the split was stopped fail it as a whole
+    if errors.iter().any(Option::is_some) {
+        limits.check()?;
+    }
+    Ok(errors)
+}
+
+/// Parse video dimensions written `WIDTHxHEIGHT` (e.g. `1920x1080`)
+pub fn parse_dimensions(value: &str) -> Result<(u32, u32)> {
+    let invalid = || VideoToolkitError::Other(format!("Invalid dimensions '{}': expected WIDTHxHEIGHT, e.g. 1920x1080", value));
+
+    let (width, height) = value.trim().split_once(['x', 'X', '×']).ok_or_else(invalid)?;
+    match (width.trim().parse(), height.trim().parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Verify that the input video has the expected dimensions, returning them
+pub fn verify_video_dimensions(input_file: &str, expected: (u32, u32)) -> Result<(u32, u32)> {
     let dimensions = get_video_dimensions(input_file)?;
     let (width, height) = dimensions;
 
-    if width == 1920 && height == 1080 {
+    if dimensions == expected {
         Ok(dimensions)
     } else {
-        Err(VideoToolkitError::InvalidDimensions(width, height))
+        Err(VideoToolkitError::InvalidDimensions(width, height, expected.0, expected.1))
     }
 }
 
-/// Split a 1920x1080 video into 5 equal vertical slices of 384x1080 each
+/// Split a video into 5 equal vertical slices (384x1080 each for a 1920x1080 source)
+///
+/// Use [`split_video_with_options`] for another number of slices or direction.
 pub fn split_video(
     input_file: &str,
     output_dir: &str,
@@ -26,66 +485,653 @@ pub fn split_video(
     encode_options: Option<&str>,
     force: bool,
 ) -> Result<bool> {
+    split_video_with_options(input_file, output_dir, output_prefix, encode_options, force, &SplitOptions::default())
+        .map(|report| report.success)
+}
+
+/// Split a video into equal slices with additional options (slice count, direction, labels)
+///
+/// `force` skips the [`SplitOptions::expected_dimensions`] check.
+pub fn split_video_with_options(
+    input_file: &str,
+    output_dir: &str,
+    output_prefix: &str,
+    encode_options: Option<&str>,
+    force: bool,
+    options: &SplitOptions,
+) -> Result<SplitReport> {
     // Verify input file exists
     verify_input_file(input_file)?;
+    let _cancel_scope = cancellation_scope(options.cancel.as_ref());
+    let custom_args = encode_options.map(parse_encode_options).transpose()?;
 
-    // Verify video dimensions if not forced
-    if !force {
-        verify_video_dimensions(input_file)?;
+    // Slice sizes follow the probed dimensions; only check them when asked to
+    let (width, height) = match options.expected_dimensions {
+        Some(expected) if !force => verify_video_dimensions(input_file, expected)?,
+        _ => get_video_dimensions(input_file)?,
+    };
+    let mut slices = slice_geometry(width, height, options.num_slices, options.direction)?;
+    if options.overlap_px > 0 {
+        slices = overlap_slices(&slices, options.overlap_px, options.direction)?;
     }
 
+    let format = validate_output_format(&options.output_format)?;
+
     // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir).map_err(|e| VideoToolkitError::IoError(e))?;
+    fs::create_dir_all(output_dir)?;
 
-    // Set default encoding options if none provided
-    let encode_options = encode_options.unwrap_or("-c:v libx264 -preset medium -crf 22 -c:a copy");
+    // Pick codecs for the output format if no encoding options are provided
+    let encode_args = custom_args.unwrap_or_else(|| format_encode_options(&format, &options.encoder));
+    let video_encoder = video_encoder_in(&encode_args).filter(|encoder| encoder != "copy");
+    if options.encoder.hw_ok {
+        if let Some(encoder) = &video_encoder {
+            println!("Encoding slices with {}", encoder);
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let label = slice_label(&encode_args, options, &mut warnings);
 
-    // Define slice parameters (x position, width)
-    let slices = vec![
-        (0, 384),     // Slice 1: 0-383
-        (384, 384),   // Slice 2: 384-767
-        (768, 384),   // Slice 3: 768-1151
-        (1152, 384),  // Slice 4: 1152-1535
-        (1536, 384)   // Slice 5: 1536-1919
-    ];
+    let jobs: Vec<SliceJob> = slices
+        .iter()
+        .enumerate()
+        .map(|(i, slice)| {
+            println!(
+                "Creating slice {}/{} (x={}, y={}, {}x{})...",
+                i + 1, slices.len(), slice.x, slice.y, slice.width, slice.height
+            );
 
-    // Process each slice
+            SliceJob {
+                filter: build_slice_filter(i + 1, slice, label),
+                output: Path::new(output_dir).join(format!("{}_{}.{}", output_prefix, i + 1, format)),
+            }
+        })
+        .collect();
+
+    // Report each slice that was not produced
     let mut success = true;
-    for (i, (x_pos, width)) in slices.iter().enumerate() {
-        let output_file = format!("{}/{}_{}.mp4", output_dir, output_prefix, i + 1);
+    for (i, error) in write_slices(input_file, &jobs, &encode_args, options.max_jobs)?.into_iter().enumerate() {
+        if let Some(e) = error {
+            eprintln!("Error while processing slice {}: {}", i + 1, e);
+            success = false;
+        }
+    }
 
-        println!("Creating slice {}/5 (x={}, width={})...", i + 1, x_pos, width);
+    if success {
+        println!("Successfully split video into {} slices. Files saved in: {}", slices.len(), output_dir);
+    }
 
-        // Build FFmpeg command
-        let filter_value = format!("crop={}:1080:{}:0", width, x_pos);
-        let mut args = vec![
-            "-y",
-            "-i", input_file,
-            "-filter:v", &filter_value,
-        ];
+    Ok(SplitReport { success, warnings, slices, video_encoder })
+}
 
-        // Add encoding options
-        args.extend(encode_options.split_whitespace());
+/// Number of rows and columns of a tile grid, written `ROWSxCOLS` (e.g. `3x3`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridSize {
+    pub rows: u32,
+    pub cols: u32,
+}
 
-        args.push(&output_file);
+impl fmt::Display for GridSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.rows, self.cols)
+    }
+}
 
-        // Execute FFmpeg command
-        if let Err(e) = execute_ffmpeg(&args) {
-            eprintln!("Error while processing slice {}: {}", i + 1, e);
-            success = false;
-            continue;
-        }
+impl FromStr for GridSize {
+    type Err = VideoToolkitError;
 
-        // Verify output file was created
-        if !Path::new(&output_file).exists() {
-            eprintln!("Error: Failed to create slice {}", i + 1);
-            success = false;
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || VideoToolkitError::Other(format!("Invalid grid '{}': expected ROWSxCOLS, e.g. 3x3", value));
+
+        let (rows, cols) = value.trim().split_once(['x', 'X', '×']).ok_or_else(invalid)?;
+        let rows: u32 = rows.trim().parse().map_err(|_| invalid())?;
+        let cols: u32 = cols.trim().parse().map_err(|_| invalid())?;
+        if rows == 0 || cols == 0 {
+            return Err(invalid());
         }
+
+        Ok(GridSize { rows, cols })
     }
+}
 
-    if success {
-        println!("Successfully split video into 5 slices. Files saved in: {}", output_dir);
+/// One cell of a grid, with 1-based row and column numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridTile {
+    pub row: u32,
+    pub col: u32,
+    pub region: Slice,
+}
+
+/// Cut a `width`x`height` frame into a `rows`x`cols` grid, row by row
+///
+/// The size must divide evenly unless `allow_uneven` is set, in which case
+/// the last row and column get the extra pixels.
+pub fn grid_geometry(width: u32, height: u32, rows: u32, cols: u32, allow_uneven: bool) -> Result<Vec<GridTile>> {
+    if !allow_uneven && (rows == 0 || cols == 0 || !width.is_multiple_of(cols) || !height.is_multiple_of(rows)) {
+        return Err(VideoToolkitError::Other(format!(
+            "A {}x{} video cannot be divided evenly into a {}x{} grid (use force to allow an uneven last row/column)",
+            width, height, rows, cols
+        )));
+    }
+
+    let columns = slice_geometry(width, height, cols, SliceDirection::Vertical)?;
+    let row_slices = slice_geometry(width, height, rows, SliceDirection::Horizontal)?;
+
+    let tiles = row_slices
+        .iter()
+        .zip(1..)
+        .flat_map(|(row_slice, row)| {
+            columns.iter().zip(1..).map(move |(column, col)| GridTile {
+                row,
+                col,
+                region: Slice { x: column.x, y: row_slice.y, width: column.width, height: row_slice.height },
+            })
+        })
+        .collect();
+
+    Ok(tiles)
+}
+
+/// Outcome of one tile of a grid split
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileResult {
+    pub row: u32,
+    pub col: u32,
+    pub output: PathBuf,
+    /// Why the tile could not be written, if it failed
+    pub error: Option<String>,
+}
+
+/// Additional options for a grid split
+#[derive(Debug, Clone, Default)]
+pub struct GridOptions {
+    /// Maximum number of tiles encoded at once when they are encoded separately;
+    /// `None` uses one per CPU core
+    pub max_jobs: Option<usize>,
+}
+
+/// Split a video into a `rows`x`cols` grid of tiles, e.g. for a video wall
+///
+/// Outputs are named `<prefix>_r<row>_c<col>.mp4`. Any resolution that divides
+/// evenly into the grid is accepted; `force` allows an uneven last row/column.
+pub fn split_video_grid(
+    input_file: &str,
+    output_dir: &str,
+    output_prefix: &str,
+    rows: u32,
+    cols: u32,
+    encode_options: Option<&str>,
+    force: bool,
+) -> Result<Vec<TileResult>> {
+    let grid = GridSize { rows, cols };
+    split_video_grid_with_options(input_file, output_dir, output_prefix, grid, encode_options, force, &GridOptions::default())
+}
+
+/// Split a video into a grid of tiles with additional options (encode jobs)
+pub fn split_video_grid_with_options(
+    input_file: &str,
+    output_dir: &str,
+    output_prefix: &str,
+    grid: GridSize,
+    encode_options: Option<&str>,
+    force: bool,
+    options: &GridOptions,
+) -> Result<Vec<TileResult>> {
+    verify_input_file(input_file)?;
+    let encode_args = parse_encode_options(encode_options.unwrap_or(DEFAULT_ENCODE_OPTIONS))?;
+
+    let (width, height) = get_video_dimensions(input_file)?;
+    let tiles = grid_geometry(width, height, grid.rows, grid.cols, force)?;
+
+    fs::create_dir_all(output_dir)?;
+
+    let outputs: Vec<PathBuf> = tiles
+        .iter()
+        .map(|tile| Path::new(output_dir).join(format!("{}_r{}_c{}.mp4", output_prefix, tile.row, tile.col)))
+        .collect();
+    let jobs: Vec<SliceJob> = tiles
+        .iter()
+        .zip(&outputs)
+        .map(|(tile, output)| {
+            let region = &tile.region;
+            println!(
+                "Creating tile r{} c{} (x={}, y={}, {}x{})...",
+                tile.row, tile.col, region.x, region.y, region.width, region.height
+            );
+
+            SliceJob {
+                filter: build_slice_filter(0, region, None),
+                output: output.clone(),
+            }
+        })
+        .collect();
+
+    let results = write_slices(input_file, &jobs, &encode_args, options.max_jobs)?
+        .into_iter()
+        .zip(tiles.iter().zip(outputs))
+        .map(|(error, (tile, output))| TileResult { row: tile.row, col: tile.col, output, error })
+        .collect();
+
+    Ok(results)
+}
+
+/// FFmpeg command that cuts the input into `segment_seconds` chunks with the segment muxer
+fn build_segment_command(
+    input_file: &str,
+    segment_seconds: f64,
+    copy_codec: bool,
+    list_file: &Path,
+    pattern: &Path,
+) -> FfmpegCommand {
+    let command = FfmpegCommand::new().overwrite().input(input_file).map("0");
+
+    let command = if copy_codec {
+        // Stream copy can only cut at existing keyframes
+        command.codec("copy")
+    } else {
+        command
+            .args(DEFAULT_ENCODE_OPTIONS.split_whitespace())
+            .option("-force_key_frames", format!("expr:gte(t,n_forced*{})", segment_seconds))
+    };
+
+    command
+        .option("-f", "segment")
+        .option("-segment_time", segment_seconds)
+        .option("-reset_timestamps", 1)
+        .option("-segment_start_number", 1)
+        .option("-segment_list", list_file.to_string_lossy())
+        .option("-segment_list_type", "flat")
+        .output(pattern)
+}
+
+/// Cut a video into consecutive segments of `segment_seconds` each (the last one may be shorter)
+///
+/// Outputs are named `<prefix>_001.mp4`, `<prefix>_002.mp4`, ... With `copy_codec`
+/// the streams are copied and cuts fall on the nearest keyframe; otherwise the
+/// video is re-encoded with a keyframe at every segment boundary.
+/// Returns the created segments in order.
+pub fn split_by_duration(
+    input_file: &str,
+    output_dir: &str,
+    output_prefix: &str,
+    segment_seconds: f64,
+    copy_codec: bool,
+) -> Result<Vec<PathBuf>> {
+    verify_input_file(input_file)?;
+
+    if !(segment_seconds > 0.0 && segment_seconds.is_finite()) {
+        return Err(VideoToolkitError::Other(format!(
+            "Segment duration must be a positive number of seconds, got {}",
+            segment_seconds
+        )));
+    }
+
+    fs::create_dir_all(output_dir)?;
+
+    // FFmpeg lists the segments it wrote, which tells them apart from older files
+    let list_file = Path::new(output_dir).join(format!(".{}_segments.txt", output_prefix));
+    let pattern = Path::new(output_dir).join(format!("{}_%03d.mp4", output_prefix));
+    let command = build_segment_command(input_file, segment_seconds, copy_codec, &list_file, &pattern);
+
+    println!("Cutting video into {}s segments...", segment_seconds);
+    let result = command.execute();
+
+    let listed = fs::read_to_string(&list_file);
+    let _ = fs::remove_file(&list_file);
+    result?;
+    if is_dry_run() {
+        // Nothing was cut, so FFmpeg wrote no list of segments
+        return Ok(Vec::new());
+    }
+
+    let segments: Vec<PathBuf> = listed?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let name = Path::new(line.trim()).file_name().unwrap_or_default();
+            Path::new(output_dir).join(name)
+        })
+        .collect();
+
+    if segments.is_empty() || segments.iter().any(|segment| !segment.exists()) {
+        return Err(VideoToolkitError::OutputFileNotCreated);
+    }
+
+    println!("Created {} segments in: {}", segments.len(), output_dir);
+    Ok(segments)
+}
+
+/// Largest difference in duration, in seconds, tolerated between slices being merged
+const MERGE_DURATION_TOLERANCE: f64 = 0.5;
+
+/// `[0:v][1:v]...` input labels for a stacking filter
+fn stack_inputs(count: usize) -> String {
+    (0..count).map(|i| format!("[{}:v]", i)).collect()
+}
+
+/// hstack/vstack filter that puts slices of the given dimensions back side by side
+/// (vertical slices) or on top of each other (horizontal slices)
+fn stack_filter(dimensions: &[(u32, u32)], direction: SliceDirection) -> Result<String> {
+    if dimensions.len() < 2 {
+        return Err(VideoToolkitError::Other("At least two slices are needed to merge".to_string()));
+    }
+
+    let (shared, name, stack) = match direction {
+        SliceDirection::Vertical => (dimensions.iter().map(|d| d.1).collect::<Vec<_>>(), "height", "hstack"),
+        SliceDirection::Horizontal => (dimensions.iter().map(|d| d.0).collect::<Vec<_>>(), "width", "vstack"),
+    };
+    if let Some(i) = shared.iter().position(|size| *size != shared[0]) {
+        return Err(VideoToolkitError::Other(format!(
+            "{} slices must share the same {}: slice 1 is {}x{}, slice {} is {}x{}",
+            direction, name, dimensions[0].0, dimensions[0].1, i + 1, dimensions[i].0, dimensions[i].1
+        )));
+    }
+
+    Ok(format!("{}{}=inputs={}[v]", stack_inputs(dimensions.len()), stack, dimensions.len()))
+}
+
+/// xstack filter that puts grid tiles, given row by row, back together
+fn grid_stack_filter(dimensions: &[(u32, u32)], grid: GridSize) -> Result<String> {
+    let (rows, cols) = (grid.rows as usize, grid.cols as usize);
+    if dimensions.len() != rows * cols {
+        return Err(VideoToolkitError::Other(format!(
+            "A {} grid needs {} tiles, got {}",
+            grid, rows * cols, dimensions.len()
+        )));
+    }
+
+    // Tiles of a row share the height of its first tile, tiles of a column the width of the top one
+    let mismatch = (0..dimensions.len()).find(|&i| {
+        dimensions[i].1 != dimensions[i / cols * cols].1 || dimensions[i].0 != dimensions[i % cols].0
+    });
+    if let Some(i) = mismatch {
+        return Err(VideoToolkitError::Other(format!(
+            "Tile r{} c{} ({}x{}) does not line up with its row and column",
+            i / cols + 1, i % cols + 1, dimensions[i].0, dimensions[i].1
+        )));
+    }
+
+    let layout: Vec<String> = (0..dimensions.len())
+        .map(|i| {
+            let x: u32 = dimensions[..i % cols].iter().map(|d| d.0).sum();
+            let y: u32 = (0..i / cols).map(|row| dimensions[row * cols].1).sum();
+            format!("{}_{}", x, y)
+        })
+        .collect();
+
+    Ok(format!(
+        "{}xstack=inputs={}:layout={}[v]",
+        stack_inputs(dimensions.len()), dimensions.len(), layout.join("|")
+    ))
+}
+
+/// Probe the inputs, build the stacking filter from their dimensions and encode the result
+fn merge_with_filter(
+    inputs: &[PathBuf],
+    output: &Path,
+    encode_options: Option<&str>,
+    build_filter: impl FnOnce(&[(u32, u32)]) -> Result<String>,
+) -> Result<()> {
+    let encode_args = parse_encode_options(encode_options.unwrap_or(DEFAULT_ENCODE_OPTIONS))?;
+
+    let mut dimensions = Vec::with_capacity(inputs.len());
+    let mut durations = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let input = input.to_string_lossy();
+        verify_input_file(&input)?;
+        dimensions.push(get_video_dimensions(&input)?);
+        durations.push(get_duration(&input)?);
+    }
+
+    let filter = build_filter(&dimensions)?;
+
+    let shortest = durations.iter().cloned().fold(f64::INFINITY, f64::min);
+    let longest = durations.iter().cloned().fold(0.0, f64::max);
+    if longest - shortest > MERGE_DURATION_TOLERANCE {
+        return Err(VideoToolkitError::Other(format!(
+            "Slice durations differ by {:.2}s ({:.2}s to {:.2}s); they do not come from the same video",
+            longest - shortest, shortest, longest
+        )));
+    }
+
+    if let Some(parent) = output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
     }
 
-    Ok(success)
-}
\ No newline at end of file
+    let command = inputs.iter().fold(FfmpegCommand::new().overwrite(), |command, input| command.input(input));
+    // The audio of the first slice stands for the whole video
+    command
+        .filter_complex(filter)
+        .map("[v]")
+        .map("0:a?")
+        .args(encode_args)
+        .output(output)
+        .execute()?;
+
+    if output_written(output) {
+        Ok(())
+    } else {
+        Err(VideoToolkitError::OutputFileNotCreated)
+    }
+}
+
+/// Reassemble slices, in order, into a single video
+///
+/// Vertical slices are placed side by side and must share their height;
+/// horizontal slices are stacked and must share their width. All slices must
+/// have the same duration (within half a second). The audio of the first slice is kept.
+pub fn merge_slices(
+    inputs: &[PathBuf],
+    output: &Path,
+    direction: SliceDirection,
+    encode_options: Option<&str>,
+) -> Result<()> {
+    merge_with_filter(inputs, output, encode_options, |dimensions| stack_filter(dimensions, direction))
+}
+
+/// Reassemble the tiles of a grid, given row by row, into a single video
+pub fn merge_grid(inputs: &[PathBuf], output: &Path, grid: GridSize, encode_options: Option<&str>) -> Result<()> {
+    merge_with_filter(inputs, output, encode_options, |dimensions| grid_stack_filter(dimensions, grid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_filter_per_slice() {
+        let style = LabelStyle::default();
+        let slice = |x| Slice { x, y: 0, width: 384, height: 1080 };
+        assert_eq!(build_slice_filter(1, &slice(0), None), "crop=384:1080:0:0");
+        assert_eq!(
+            build_slice_filter(3, &slice(768), Some(&style)),
+            "crop=384:1080:768:0,drawtext=text=3:expansion=none:fontsize=192:fontcolor=white@0.6:\
+             box=1:boxcolor=black@0.4:boxborderw=24:x=48:y=48"
+        );
+
+        let style = LabelStyle {
+            template: "Screen {index}: x={x}".to_string(),
+            size_ratio: 0.25,
+            corner: LabelCorner::BottomRight,
+        };
+        assert_eq!(
+            build_label_filter(5, &slice(1536), &style),
+            "drawtext=text=Screen 5\\\\: x=1536:expansion=none:fontsize=96:fontcolor=white@0.6:\
+             box=1:boxcolor=black@0.4:boxborderw=12:x=w-tw-24:y=h-th-24"
+        );
+    }
+
+    #[test]
+    fn test_slice_geometry() {
+        // The default matches the former hardcoded 384px columns
+        let columns = slice_geometry(1920, 1080, DEFAULT_SLICE_COUNT, SliceDirection::Vertical).unwrap();
+        let offsets: Vec<u32> = columns.iter().map(|s| s.x).collect();
+        assert_eq!(offsets, [0, 384, 768, 1152, 1536]);
+        assert!(columns.iter().all(|s| (s.y, s.width, s.height) == (0, 384, 1080)));
+
+        // The last slice takes the remainder
+        let rows = slice_geometry(1280, 720, 7, SliceDirection::Horizontal).unwrap();
+        assert_eq!(rows[0], Slice { x: 0, y: 0, width: 1280, height: 102 });
+        assert_eq!(rows[6], Slice { x: 0, y: 612, width: 1280, height: 108 });
+
+        // Other resolutions split proportionally
+        let uhd = slice_geometry(3840, 2160, DEFAULT_SLICE_COUNT, SliceDirection::Vertical).unwrap();
+        assert_eq!(uhd[4], Slice { x: 3072, y: 0, width: 768, height: 2160 });
+        let hd = slice_geometry(1280, 720, DEFAULT_SLICE_COUNT, SliceDirection::Vertical).unwrap();
+        assert_eq!(hd.iter().map(|s| s.width).collect::<Vec<_>>(), [256; 5]);
+        assert!(hd.iter().all(|s| s.height == 720));
+        let odd = slice_geometry(1366, 768, DEFAULT_SLICE_COUNT, SliceDirection::Vertical).unwrap();
+        assert_eq!(odd.iter().map(|s| s.width).collect::<Vec<_>>(), [273, 273, 273, 273, 274]);
+        assert_eq!(odd.iter().map(|s| s.width).sum::<u32>(), 1366);
+
+        assert!(slice_geometry(1920, 1080, 0, SliceDirection::Vertical).is_err());
+        assert!(slice_geometry(1920, 4, 5, SliceDirection::Horizontal).is_err());
+        assert_eq!("Horizontal".parse::<SliceDirection>().unwrap(), SliceDirection::Horizontal);
+        assert!("diagonal".parse::<SliceDirection>().is_err());
+
+        assert_eq!(parse_dimensions("1920x1080").unwrap(), (1920, 1080));
+        assert!(parse_dimensions("1920").is_err());
+        assert!(parse_dimensions("0x1080").is_err());
+    }
+
+    #[test]
+    fn test_overlap_slices() {
+        let columns = slice_geometry(1920, 1080, DEFAULT_SLICE_COUNT, SliceDirection::Vertical).unwrap();
+        let widened = overlap_slices(&columns, 16, SliceDirection::Vertical).unwrap();
+        let spans: Vec<(u32, u32)> = widened.iter().map(|s| (s.x, s.width)).collect();
+        assert_eq!(spans, [(0, 400), (368, 416), (752, 416), (1136, 416), (1520, 400)]);
+        assert!(widened.iter().all(|s| (s.y, s.height) == (0, 1080)));
+
+        let rows = slice_geometry(1280, 720, 2, SliceDirection::Horizontal).unwrap();
+        assert_eq!(overlap_slices(&rows, 8, SliceDirection::Horizontal).unwrap()[1], Slice { x: 0, y: 352, width: 1280, height: 368 });
+
+        assert!(overlap_slices(&columns, 191, SliceDirection::Vertical).is_ok());
+        assert!(overlap_slices(&columns, 192, SliceDirection::Vertical).is_err());
+    }
+
+    #[test]
+    fn test_grid_geometry() {
+        let tiles = grid_geometry(1920, 1080, 3, 3, false).unwrap();
+        assert_eq!(tiles.len(), 9);
+        assert_eq!(tiles[0], GridTile { row: 1, col: 1, region: Slice { x: 0, y: 0, width: 640, height: 360 } });
+        assert_eq!(tiles[5], GridTile { row: 2, col: 3, region: Slice { x: 1280, y: 360, width: 640, height: 360 } });
+
+        // 1080 rows do not divide by 7
+        assert!(grid_geometry(1920, 1080, 7, 4, false).is_err());
+        let uneven = grid_geometry(1920, 1080, 7, 4, true).unwrap();
+        assert_eq!(uneven.last().unwrap().region, Slice { x: 1440, y: 924, width: 480, height: 156 });
+
+        assert_eq!("2x4".parse::<GridSize>().unwrap(), GridSize { rows: 2, cols: 4 });
+        assert_eq!("3×3".parse::<GridSize>().unwrap(), GridSize { rows: 3, cols: 3 });
+        assert!("0x3".parse::<GridSize>().is_err());
+        assert!("3".parse::<GridSize>().is_err());
+    }
+
+    #[test]
+    fn test_single_pass_args() {
+        let jobs: Vec<SliceJob> = slice_geometry(1920, 1080, 2, SliceDirection::Vertical)
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(i, slice)| SliceJob {
+                filter: build_slice_filter(i + 1, slice, None),
+                output: Path::new("out").join(format!("slice_{}.mp4", i + 1)),
+            })
+            .collect();
+
+        let encode_args = parse_encode_options("-c:v libx264 -c:a copy").unwrap();
+        assert_eq!(build_single_pass_command("in.mp4", &jobs, &encode_args).to_args(), [
+            "-y", "-i", "in.mp4",
+            "-filter_complex", "[0:v]split=2[in0][in1];[in0]crop=960:1080:0:0[out0];[in1]crop=960:1080:960:0[out1]",
+            "-map", "[out0]", "-map", "0:a?", "-c:v", "libx264", "-c:a", "copy", "out/slice_1.mp4",
+            "-map", "[out1]", "-map", "0:a?", "-c:v", "libx264", "-c:a", "copy", "out/slice_2.mp4",
+        ]);
+    }
+
+    #[test]
+    fn test_segment_args() {
+        let list = Path::new("out/.part_segments.txt");
+        let args = build_segment_command("in.mp4", 600.0, true, list, Path::new("out/part_%03d.mp4")).to_args();
+        assert_eq!(args[..7], ["-y", "-i", "in.mp4", "-map", "0", "-c", "copy"]);
+        assert!(args.windows(2).any(|pair| pair == ["-segment_time", "600"]));
+        assert!(args.windows(2).any(|pair| pair == ["-reset_timestamps", "1"]));
+        assert_eq!(args.last().unwrap(), "out/part_%03d.mp4");
+
+        let args = build_segment_command("in.mp4", 2.5, false, Path::new("list.txt"), Path::new("part_%03d.mp4")).to_args();
+        assert!(args.windows(2).any(|pair| pair == ["-force_key_frames", "expr:gte(t,n_forced*2.5)"]));
+        assert!(!args.windows(2).any(|pair| pair == ["-c", "copy"]));
+
+        assert!(split_by_duration("missing.mp4", "out", "part", 60.0, true).is_err());
+    }
+
+    #[test]
+    fn test_merge_filters() {
+        let columns = [(384, 1080); 5];
+        assert_eq!(
+            stack_filter(&columns, SliceDirection::Vertical).unwrap(),
+            "[0:v][1:v][2:v][3:v][4:v]hstack=inputs=5[v]"
+        );
+        assert!(stack_filter(&[(384, 1080), (400, 1080)], SliceDirection::Horizontal).is_err());
+        assert!(stack_filter(&[(384, 1080), (384, 720)], SliceDirection::Vertical).is_err());
+        assert!(stack_filter(&[(384, 1080)], SliceDirection::Vertical).is_err());
+
+        // Uneven last row and column, as written by a forced grid split
+        let grid = GridSize { rows: 2, cols: 2 };
+        let tiles = [(960, 500), (961, 500), (960, 501), (961, 501)];
+        assert_eq!(
+            grid_stack_filter(&tiles, grid).unwrap(),
+            "[0:v][1:v][2:v][3:v]xstack=inputs=4:layout=0_0|960_0|0_500|960_500[v]"
+        );
+        assert!(grid_stack_filter(&tiles[..3], grid).is_err());
+        assert!(grid_stack_filter(&[(960, 500), (961, 500), (961, 501), (960, 501)], grid).is_err());
+    }
+
+    #[test]
+    fn test_output_format() {
+        assert_eq!(validate_output_format(" MKV ").unwrap(), "mkv");
+        assert!(validate_output_format("gif").is_err());
+
+        let software = EncoderPreference::default();
+        assert_eq!(format_encode_options("mp4", &software).join(" "), "-c:v libx264 -c:a aac -preset medium -crf 22");
+        assert_eq!(format_encode_options("webm", &software).join(" "), "-c:v libvpx -c:a libvorbis");
+    }
+
+    #[test]
+    fn test_copy_codec_guard() {
+        let copies = |options: &str| copies_video_stream(&parse_encode_options(options).unwrap());
+        assert!(copies("-c:v copy -c:a aac"));
+        assert!(copies("-c copy"));
+        assert!(copies("-vcodec copy"));
+        assert!(!copies("-c:v libx264 -c:a copy"));
+        assert!(!copies("-c:v libx264 -preset medium -crf 22 -c:a copy"));
+    }
+
+    #[test]
+    fn test_parse_encode_options() {
+        assert_eq!(
+            parse_encode_options("-c:v libx264 -metadata title=\"My Clip\" -vf 'eq=gamma=1.2, hue=s=0' -itsoffset -0.5").unwrap(),
+            ["-c:v", "libx264", "-metadata", "title=My Clip", "-vf", "eq=gamma=1.2, hue=s=0", "-itsoffset", "-0.5"]
+        );
+        assert!(parse_encode_options("").unwrap().is_empty());
+
+        assert!(parse_encode_options("-i other.mp4 -c:v libx264").is_err());
+        assert!(parse_encode_options("-c:v libx264 out.mp4").is_err());
+        assert!(parse_encode_options("libx264").is_err());
+        assert!(parse_encode_options("-metadata title=\"My Clip").is_err());
+    }
+
+    #[test]
+    fn test_labels_disabled_for_stream_copy() {
+        let options = SplitOptions { label_slices: true, ..SplitOptions::default() };
+        let mut warnings = Vec::new();
+
+        let args = |options: &str| parse_encode_options(options).unwrap();
+        assert!(slice_label(&args("-c:v libx264"), &options, &mut warnings).is_some());
+        assert!(slice_label(&args("-c:v copy"), &SplitOptions::default(), &mut warnings).is_none());
+        assert!(warnings.is_empty());
+
+        assert!(slice_label(&args("-c:v copy -c:a aac"), &options, &mut warnings).is_none());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::LabelsDisabled);
+    }
+}
@@ -2,7 +2,7 @@ use eframe::egui;
 use egui::{Color32, RichText};
 use std::sync::{Arc, Mutex};
 
-use common::check_ffmpeg;
+use common::{check_ffmpeg, clear_dry_run_log, dry_run_log, set_dry_run};
 use crate::tabs::{
     clipper_tab::ClipperTab,
     gif_converter_tab::GifConverterTab,
@@ -30,6 +30,10 @@ pub struct VideoToolKitApp {
     active_tab: Tab,
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    /// Fraction of the running operation that is done, if it can be told
+    progress: Arc<Mutex<Option<f32>>>,
+    /// Show the FFmpeg commands that would write files instead of running them
+    preview_commands: bool,
 
     clipper_tab: ClipperTab,
     gif_converter_tab: GifConverterTab,
@@ -45,20 +49,29 @@ impl Default for VideoToolKitApp {
     fn default() -> Self {
         let status = Arc::new(Mutex::new("Ready".to_string()));
         let processing = Arc::new(Mutex::new(false));
+        let progress = Arc::new(Mutex::new(None));
+        let cancel = Arc::new(Mutex::new(None));
 
         Self {
             active_tab: Tab::Clipper,
             status: Arc::clone(&status),
             processing: Arc::clone(&processing),
-
-            clipper_tab: ClipperTab::new(Arc::clone(&status), Arc::clone(&processing)),
-            gif_converter_tab: GifConverterTab::new(Arc::clone(&status), Arc::clone(&processing)),
+            progress: Arc::clone(&progress),
+            preview_commands: false,
+
+            clipper_tab: ClipperTab::new(Arc::clone(&status), Arc::clone(&processing), Arc::clone(&progress), Arc::clone(&cancel)),
+            gif_converter_tab: GifConverterTab::new(
+                Arc::clone(&status),
+                Arc::clone(&processing),
+                Arc::clone(&progress),
+                Arc::clone(&cancel),
+            ),
             gif_transparency_tab: GifTransparencyTab::new(Arc::clone(&status), Arc::clone(&processing)),
-            splitter_tab: SplitterTab::new(Arc::clone(&status), Arc::clone(&processing)),
-            merger_tab: MergerTab::new(Arc::clone(&status), Arc::clone(&processing)),
-            batch_tab: BatchTab::new(Arc::clone(&status), Arc::clone(&processing)),
-            profiles_tab: ProfilesTab::new(Arc::clone(&status), Arc::clone(&processing)),
-            plugins_tab: PluginsTab::new(Arc::clone(&status), Arc::clone(&processing)),
+            splitter_tab: SplitterTab::new(Arc::clone(&status), Arc::clone(&processing), Arc::clone(&progress), Arc::clone(&cancel)),
+            merger_tab: MergerTab::new(Arc::clone(&status), Arc::clone(&processing), Arc::clone(&progress), Arc::clone(&cancel)),
+            batch_tab: BatchTab::new(Arc::clone(&status), Arc::clone(&processing), Arc::clone(&cancel)),
+            profiles_tab: ProfilesTab::new(Arc::clone(&status), Arc::clone(&processing), Arc::clone(&cancel)),
+            plugins_tab: PluginsTab::new(Arc::clone(&status), Arc::clone(&processing), Arc::clone(&progress), Arc::clone(&cancel)),
         }
     }
 }
@@ -93,6 +106,14 @@ impl eframe::App for VideoToolKitApp {
                 ui.selectable_value(&mut self.active_tab, Tab::Batch, "Batch Processing");
                 ui.selectable_value(&mut self.active_tab, Tab::Profiles, "Profiles");
                 ui.selectable_value(&mut self.active_tab, Tab::Plugins, "Plugins");
+
+                ui.separator();
+                if ui.checkbox(&mut self.preview_commands, "Preview commands")
+                    .on_hover_text("List the FFmpeg commands below instead of running them; no file is written")
+                    .changed()
+                {
+                    set_dry_run(self.preview_commands);
+                }
             });
 
             ui.separator();
@@ -116,9 +137,31 @@ impl eframe::App for VideoToolKitApp {
                 ui.label(&status);
 
                 if *self.processing.lock().unwrap() {
-                    ui.spinner();
+                    match *self.progress.lock().unwrap() {
+                        Some(fraction) => {
+                            ui.add(egui::ProgressBar::new(fraction).show_percentage().animate(true).desired_width(200.0));
+                        },
+                        None => {
+                            ui.spinner();
+                        },
+                    }
                 }
             });
+
+            if self.preview_commands {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Previewed commands:");
+                    if ui.button("Clear").clicked() {
+                        clear_dry_run_log();
+                    }
+                });
+                egui::ScrollArea::vertical().max_height(150.0).stick_to_bottom(true).show(ui, |ui| {
+                    for command in dry_run_log() {
+                        ui.label(RichText::new(command).monospace());
+                    }
+                });
+            }
         });
     }
 }
\ No newline at end of file
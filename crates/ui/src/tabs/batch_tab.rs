@@ -1,13 +1,21 @@
 use eframe::egui::{self, Ui, ComboBox, TextEdit};
-use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use batch_processing::{
-    BatchProcessor, BatchOperation, BatchItemResult,
-    BatchClipperConfig, BatchGifConverterConfig, BatchGifTransparencyConfig,
-    BatchSplitterConfig, BatchMergerConfig
+    BatchOperation, BatchItemResult, BatchProcessor, BatchProgress, BatchWebpConfig,
+    BatchController, FileOrder, PatternTarget, ProgressPhase, BatchSettings, OperationSettings, BatchSummary, PlannedItem, ReportFormat, write_report,
+    plugin_input_parameter, plugin_output_parameter, DEFAULT_PLUGIN_EXTENSIONS, PLUGIN_PROFILE_TYPE
 };
+use common::{get_supported_formats, EncoderPreference, VideoFormat};
+use plugin_system::PluginManager;
+use profile_system::{OverwriteMode, ProfileManager, ProfileType};
+use splitter::SliceDirection;
+
+use super::{cancel_button, encoder_preference, hw_encoder_checkbox, start_cancellation, SharedCancel};
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum BatchOperationType {
@@ -16,6 +24,7 @@ pub enum BatchOperationType {
     GifTransparency,
     Splitter,
     Merger,
+    Plugin,
 }
 
 impl BatchOperationType {
@@ -26,6 +35,18 @@ impl BatchOperationType {
             BatchOperationType::GifTransparency => BatchOperation::GifTransparency,
             BatchOperationType::Splitter => BatchOperation::Splitter,
             BatchOperationType::Merger => BatchOperation::Merger,
+            BatchOperationType::Plugin => BatchOperation::Custom,
+        }
+    }
+
+    fn to_profile_type(self) -> ProfileType {
+        match self {
+            BatchOperationType::Clipper => ProfileType::Clipper,
+            BatchOperationType::GifConverter => ProfileType::GifConverter,
+            BatchOperationType::GifTransparency => ProfileType::GifTransparency,
+            BatchOperationType::Splitter => ProfileType::Splitter,
+            BatchOperationType::Merger => ProfileType::Merger,
+            BatchOperationType::Plugin => ProfileType::Custom(PLUGIN_PROFILE_TYPE.to_string()),
         }
     }
 
@@ -36,6 +57,7 @@ impl BatchOperationType {
             BatchOperationType::GifTransparency => "GIF Transparency",
             BatchOperationType::Splitter => "Video Splitter",
             BatchOperationType::Merger => "Audio/Video Merger",
+            BatchOperationType::Plugin => "Plugin",
         }
     }
 }
@@ -45,8 +67,37 @@ pub struct BatchTab {
     operation_type: BatchOperationType,
     input_paths: Vec<PathBuf>,
     recursive: bool,
+    follow_symlinks: bool,
+    /// Deepest directory level scanned when recursive, as typed
+    max_depth: String,
     pattern: String,
+    /// Part of the path the pattern is matched against
+    pattern_target: PatternTarget,
+    /// Space-separated include and exclude globs
+    include_globs: String,
+    exclude_globs: String,
+    /// Pick files by their content instead of their extension
+    by_content: bool,
+    /// Recreate the input directories under the output directory
+    mirror_structure: bool,
     parallel: bool,
+    /// Re-encode clips, slices and merges with a hardware encoder when available
+    hw_encoding: bool,
+    /// Modification-time and size filters, as typed
+    since: String,
+    until: String,
+    min_input_size: String,
+    max_input_size: String,
+    /// Processing order of the files
+    order: FileOrder,
+    descending: bool,
+    limit: String,
+    sample: String,
+    seed: String,
+    /// Times a file is run again after a transient failure
+    retries: String,
+    /// Number of failed files after which no new files are started
+    max_failures: String,
 
     // Operation-specific settings
 
@@ -55,6 +106,7 @@ pub struct BatchTab {
     clipper_output_dir: String,
     clipper_copy_codec: bool,
     clipper_suffix: String,
+    clipper_format: String,
 
     // GIF converter settings
     gif_output_dir: String,
@@ -62,65 +114,149 @@ pub struct BatchTab {
     gif_fps: String,
     gif_max_size: String,
     gif_optimize: bool,
+    gif_crop: String,
+    gif_boomerang: bool,
+    gif_poster: bool,
 
     // GIF transparency settings
     transparency_backup: bool,
+    transparency_webp: bool,
+    transparency_legacy: bool,
 
     // Splitter settings
     splitter_output_dir: String,
     splitter_prefix: String,
     splitter_custom_encode: String,
-    splitter_force: bool,
+    splitter_expected_dimensions: String,
+    splitter_format: String,
+    splitter_slices: String,
+    splitter_direction: SliceDirection,
+    splitter_segment_duration: String,
+    splitter_copy_codec: bool,
 
     // Merger settings
+    merger_match_by_name: bool,
     merger_audio_file: String,
+    merger_audio_ext: String,
+    merger_audio_dir: String,
     merger_output_dir: String,
     merger_shortest: bool,
     merger_copy_codec: bool,
+    merger_format: String,
+
+    // Plugin settings
+    /// Plugins the batch can run from, discovered when the plugin operation is first chosen
+    plugin_manager: Option<PluginManager>,
+    plugin_name: String,
+    /// `key=value` per line, given to every run
+    plugin_params: String,
+    plugin_extensions: String,
+    plugin_output_dir: String,
+
+    /// Saved profiles that can pre-fill the settings, if the profile directory is available
+    profile_manager: Option<ProfileManager>,
+    /// Profile the settings were last filled from
+    selected_profile: String,
+    /// Name to save the current settings under
+    new_profile_name: String,
+    /// Last profiles used for the current operation, loaded again when `None`
+    recent_profiles: Option<Vec<String>>,
 
     // Processing state
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
     results: Arc<Mutex<Vec<BatchItemResult>>>,
-    progress: Arc<Mutex<(usize, usize)>>,
+    summary: Arc<Mutex<Option<BatchSummary>>>,
+    progress: Arc<Mutex<BatchProgress>>,
+    cancel: SharedCancel,
+    /// Outputs listed by the last preview
+    plan: Vec<PlannedItem>,
 }
 
 impl BatchTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, cancel: SharedCancel) -> Self {
         Self {
             operation_type: BatchOperationType::Clipper,
             input_paths: Vec::new(),
             recursive: true,
+            follow_symlinks: false,
+            max_depth: String::new(),
             pattern: String::new(),
+            pattern_target: PatternTarget::FileName,
+            include_globs: String::new(),
+            exclude_globs: String::new(),
+            by_content: false,
+            mirror_structure: false,
             parallel: true,
+            hw_encoding: false,
+            since: String::new(),
+            until: String::new(),
+            min_input_size: String::new(),
+            max_input_size: String::new(),
+            order: FileOrder::Name,
+            descending: false,
+            limit: String::new(),
+            sample: String::new(),
+            seed: String::new(),
+            retries: String::new(),
+            max_failures: String::new(),
 
             clipper_time_ranges: vec![String::new()],
             clipper_output_dir: String::from("output_clips"),
             clipper_copy_codec: false,
             clipper_suffix: String::new(),
+            clipper_format: String::from("mp4"),
 
             gif_output_dir: String::from("output_gifs"),
             gif_width: String::new(),
             gif_fps: String::from("10"),
             gif_max_size: String::from("5.0"),
             gif_optimize: true,
+            gif_crop: String::new(),
+            gif_boomerang: false,
+            gif_poster: false,
 
             transparency_backup: true,
+            transparency_webp: false,
+            transparency_legacy: false,
 
             splitter_output_dir: String::from("output_slices"),
             splitter_prefix: String::from("slice"),
             splitter_custom_encode: String::new(),
-            splitter_force: false,
-
+            splitter_expected_dimensions: String::new(),
+            splitter_format: String::from("mp4"),
+            splitter_slices: String::from("5"),
+            splitter_direction: SliceDirection::Vertical,
+            splitter_segment_duration: String::new(),
+            splitter_copy_codec: false,
+
+            merger_match_by_name: false,
             merger_audio_file: String::new(),
+            merger_audio_ext: String::from("wav"),
+            merger_audio_dir: String::new(),
             merger_output_dir: String::from("output_merged"),
             merger_shortest: true,
             merger_copy_codec: true,
+            merger_format: String::from("mp4"),
+
+            plugin_manager: None,
+            plugin_name: String::new(),
+            plugin_params: String::new(),
+            plugin_extensions: String::new(),
+            plugin_output_dir: String::from("output_plugin"),
+
+            profile_manager: ProfileManager::new().ok(),
+            selected_profile: String::new(),
+            new_profile_name: String::new(),
+            recent_profiles: None,
 
             status,
             processing: processing.clone(),
             results: Arc::new(Mutex::new(Vec::new())),
-            progress: Arc::new(Mutex::new((0, 0))),
+            summary: Arc::new(Mutex::new(None)),
+            progress: Arc::new(Mutex::new(BatchProgress::default())),
+            cancel,
+            plan: Vec::new(),
         }
     }
 
@@ -128,6 +264,7 @@ impl BatchTab {
         ui.heading("Batch Processing");
 
         // Operation type selection
+        let previous_operation = self.operation_type;
         ui.horizontal(|ui| {
             ui.label("Operation Type:");
             ComboBox::from_id_source("batch_operation_type")
@@ -138,8 +275,15 @@ impl BatchTab {
                     ui.selectable_value(&mut self.operation_type, BatchOperationType::GifTransparency, "GIF Transparency");
                     ui.selectable_value(&mut self.operation_type, BatchOperationType::Splitter, "Video Splitter");
                     ui.selectable_value(&mut self.operation_type, BatchOperationType::Merger, "Audio/Video Merger");
+                    ui.selectable_value(&mut self.operation_type, BatchOperationType::Plugin, "Plugin");
                 });
         });
+        if self.operation_type != previous_operation {
+            self.selected_profile.clear();
+            self.recent_profiles = None;
+        }
+
+        self.profile_picker_ui(ui);
 
         ui.separator();
 
@@ -180,12 +324,89 @@ impl BatchTab {
         // Batch settings
         ui.heading("Batch Settings");
 
-        ui.checkbox(&mut self.recursive, "Process directories recursively");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.recursive, "Process directories recursively");
+            ui.add_enabled_ui(self.recursive, |ui| {
+                ui.checkbox(&mut self.follow_symlinks, "Follow symlinks");
+                ui.label("Max depth:");
+                ui.add(TextEdit::singleline(&mut self.max_depth).hint_text("any").desired_width(40.0))
+                    .on_hover_text("Directory levels to scan, 1 being the files directly in an input folder");
+            });
+        });
+        if self.operation_type != BatchOperationType::GifTransparency {
+            ui.checkbox(&mut self.mirror_structure, "Keep the input folder structure in the output directory");
+        }
         ui.checkbox(&mut self.parallel, "Process files in parallel");
+        if matches!(
+            self.operation_type,
+            BatchOperationType::Clipper | BatchOperationType::Splitter | BatchOperationType::Merger
+        ) {
+            hw_encoder_checkbox(ui, &mut self.hw_encoding, false);
+        }
 
         ui.horizontal(|ui| {
-            ui.label("Filename pattern (regex):");
+            ui.label("Pattern (regex):");
             ui.text_edit_singleline(&mut self.pattern);
+            ComboBox::from_id_source("batch_pattern_target")
+                .selected_text(pattern_target_name(self.pattern_target))
+                .show_ui(ui, |ui| {
+                    for target in [PatternTarget::FileName, PatternTarget::RelativePath, PatternTarget::FullPath] {
+                        ui.selectable_value(&mut self.pattern_target, target, pattern_target_name(target));
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Include:");
+            ui.add(TextEdit::singleline(&mut self.include_globs).hint_text("*.mp4 *.mov"));
+            ui.label("Exclude:");
+            ui.add(TextEdit::singleline(&mut self.exclude_globs).hint_text("*_proxy* **/tmp/**"));
+        })
+        .response
+        .on_hover_text("Globs separated by spaces, matched against each file's path inside the input folder");
+        ui.checkbox(&mut self.by_content, "Match files by content instead of extension")
+            .on_hover_text("Reads the start of each file, so renamed or extensionless videos and GIFs are found");
+
+        ui.horizontal(|ui| {
+            ui.label("Modified since:");
+            ui.add(TextEdit::singleline(&mut self.since).hint_text("7d").desired_width(140.0));
+            ui.label("until:");
+            ui.add(TextEdit::singleline(&mut self.until).hint_text("2024-03-01T00:00:00Z").desired_width(140.0));
+        })
+        .response
+        .on_hover_text("RFC 3339 timestamps or durations before now, such as 90m, 36h, 7d or 2w");
+        ui.horizontal(|ui| {
+            ui.label("File size from:");
+            ui.add(TextEdit::singleline(&mut self.min_input_size).hint_text("500KB").desired_width(80.0));
+            ui.label("to:");
+            ui.add(TextEdit::singleline(&mut self.max_input_size).hint_text("2GB").desired_width(80.0));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Process files by:");
+            ComboBox::from_id_source("batch_file_order")
+                .selected_text(file_order_name(self.order))
+                .show_ui(ui, |ui| {
+                    for order in [FileOrder::Name, FileOrder::Size, FileOrder::ModifiedTime] {
+                        ui.selectable_value(&mut self.order, order, file_order_name(order));
+                    }
+                });
+            ui.checkbox(&mut self.descending, "Descending");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Trial run - first N files:");
+            ui.add(TextEdit::singleline(&mut self.limit).desired_width(60.0));
+            ui.label("or random N files:");
+            ui.add(TextEdit::singleline(&mut self.sample).desired_width(60.0));
+            ui.label("Seed:");
+            ui.add(TextEdit::singleline(&mut self.seed).desired_width(80.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Retries after a transient failure:");
+            ui.add(TextEdit::singleline(&mut self.retries).hint_text("0").desired_width(40.0))
+                .on_hover_text("Runs a file again after I/O errors or failed FFmpeg runs, waiting 2s, 4s, 8s, ... in between");
+            ui.label("Stop after N failed files:");
+            ui.add(TextEdit::singleline(&mut self.max_failures).desired_width(40.0))
+                .on_hover_text("Leave empty to process every file; 1 stops at the first failure");
         });
 
         ui.separator();
@@ -197,239 +418,474 @@ impl BatchTab {
             BatchOperationType::GifTransparency => self.gif_transparency_settings_ui(ui),
             BatchOperationType::Splitter => self.splitter_settings_ui(ui),
             BatchOperationType::Merger => self.merger_settings_ui(ui),
+            BatchOperationType::Plugin => self.plugin_settings_ui(ui),
         }
 
         ui.separator();
 
         // Process button and progress
         let processing = *self.processing.lock().unwrap();
+        batch_progress_ui(ui, processing, &self.progress, &self.results, &self.summary);
+        if !processing && !self.results.lock().unwrap().is_empty() && ui.button("Export report").clicked() {
+            self.export_report();
+        }
 
-        if processing {
-            // Show progress
-            let (current, total) = *self.progress.lock().unwrap();
-            ui.label(format!("Processing file {} of {}", current, total));
-            ui.add(egui::ProgressBar::new(if total > 0 { current as f32 / total as f32 } else { 0.0 })
-                .show_percentage());
-        } else {
-            // Show results if available
-            let results = self.results.lock().unwrap();
-            if !results.is_empty() {
-                let success_count = results.iter().filter(|r| r.success).count();
-                ui.label(format!("Processed {} files: {} succeeded, {} failed",
-                                 results.len(), success_count, results.len() - success_count));
-
-                if results.len() - success_count > 0 {
-                    ui.collapsing("Show errors", |ui| {
-                        for result in results.iter().filter(|r| !r.success) {
-                            if let Some(ref error) = result.error_message {
-                                ui.label(format!("{}: {}", result.input.display(), error));
-                            }
-                        }
-                    });
-                }
-            }
+        if !self.plan.is_empty() {
+            ui.collapsing(format!("Planned outputs ({})", self.plan.len()), |ui| {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for item in &self.plan {
+                        ui.label(format!("{} -> {}", item.input.display(), item.output.display()));
+                    }
+                });
+            });
         }
 
-        // Process button
-        let button = ui.add_enabled(!processing, egui::Button::new("Start Batch Processing"));
+        // Preview and process buttons
+        let (preview, button) = ui.horizontal(|ui| {
+            let preview = ui.add_enabled(!processing, egui::Button::new("Preview"))
+                .on_hover_text("List the matched files and the outputs they would produce, without running FFmpeg");
+            let button = ui.add_enabled(!processing, egui::Button::new("Start Batch Processing"));
+            (preview, button)
+        }).inner;
+        cancel_button(ui, &self.processing, &self.cancel, &self.status);
+
+        if preview.clicked() {
+            self.plan.clear();
+            let plan = self.build_processor()
+                .and_then(|processor| processor.plan(&self.input_paths).map_err(|e| e.to_string()));
+            match plan {
+                Ok(plan) => {
+                    *self.status.lock().unwrap() = format!("{} output(s) planned", plan.len());
+                    self.plan = plan;
+                },
+                Err(message) => *self.status.lock().unwrap() = format!("Error: {}", message),
+            }
+        }
 
         if button.clicked() {
-            // Validate inputs
-            if self.input_paths.is_empty() {
-                *self.status.lock().unwrap() = "Error: Please add at least one input file or directory.".to_string();
-                return;
-            }
+            self.plan.clear();
+            let processor = match self.build_processor() {
+                Ok(p) => p,
+                Err(message) => {
+                    *self.status.lock().unwrap() = format!("Error: {}", message);
+                    return;
+                }
+            };
 
-            // Operation-specific validation
-            match self.operation_type {
-                BatchOperationType::Clipper => {
-                    let has_valid_ranges = self.clipper_time_ranges.iter()
-                        .any(|r| !r.trim().is_empty());
+            // Start processing
+            *self.status.lock().unwrap() = format!("Starting batch {} processing...", self.operation_type.display_name());
+            spawn_batch_run(processor, self.input_paths.clone(), BatchRunState {
+                status: Arc::clone(&self.status),
+                processing: Arc::clone(&self.processing),
+                results: Arc::clone(&self.results),
+                summary: Arc::clone(&self.summary),
+                progress: Arc::clone(&self.progress),
+                cancel: Arc::clone(&self.cancel),
+            });
+        }
+    }
+
+    /// Save the results of the last run as JSON or CSV, chosen by the file extension
+    fn export_report(&self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .add_filter("CSV", &["csv"])
+            .set_file_name("batch_report.json")
+            .save_file()
+        else {
+            return;
+        };
+
+        let written = ReportFormat::from_path(&path)
+            .and_then(|format| write_report(&self.results.lock().unwrap(), format, &path));
+        *self.status.lock().unwrap() = match written {
+            Ok(()) => format!("Report written to {}", path.display()),
+            Err(e) => format!("Error writing the report: {}", e),
+        };
+    }
+
+    /// Pick a saved profile of the current operation to pre-fill the settings
+    fn profile_picker_ui(&mut self, ui: &mut Ui) {
+        let Some(manager) = &self.profile_manager else {
+            return;
+        };
+
+        let recent = self.recent_profiles.get_or_insert_with(|| {
+            manager.recent_profiles_of(&self.operation_type.to_profile_type(), 3)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|usage| usage.name)
+                .collect()
+        });
 
-                    if !has_valid_ranges {
-                        *self.status.lock().unwrap() = "Error: Please add at least one time range.".to_string();
-                        return;
+        let (mut picked, mut save) = (None, false);
+        if !recent.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label("Recent:");
+                for name in recent.iter() {
+                    if ui.small_button(name).on_hover_text("Fill the settings from this profile").clicked() {
+                        picked = Some(name.clone());
                     }
-                },
-                BatchOperationType::Merger => {
-                    if self.merger_audio_file.is_empty() {
-                        *self.status.lock().unwrap() = "Error: Please select an audio file.".to_string();
-                        return;
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Use profile:");
+            ComboBox::from_id_source("batch_profile")
+                .selected_text(if self.selected_profile.is_empty() { "None" } else { &self.selected_profile })
+                .show_ui(ui, |ui| {
+                    // Listed only while open, so profiles saved meanwhile show up
+                    let names: Vec<String> = manager.list_profiles_detailed(self.operation_type.to_profile_type())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|summary| !summary.invalid)
+                        .map(|summary| summary.name)
+                        .collect();
+                    if names.is_empty() {
+                        ui.label("No profiles for this operation");
                     }
-                },
-                _ => {}
-            }
-
-            // Start processing
-            *self.status.lock().unwrap() = format!("Starting batch {} processing...", self.operation_type.display_name());
-            *self.processing.lock().unwrap() = true;
-            self.results.lock().unwrap().clear();
-            *self.progress.lock().unwrap() = (0, 0);
-
-            // Clone values for thread
-            let operation_type = self.operation_type;
-            let input_paths = self.input_paths.clone();
-            let recursive = self.recursive;
-            let pattern = self.pattern.clone();
-            let parallel = self.parallel;
-
-            // Operation-specific clones
-            let clipper_time_ranges = self.clipper_time_ranges.clone();
-            let clipper_output_dir = self.clipper_output_dir.clone();
-            let clipper_copy_codec = self.clipper_copy_codec;
-            let clipper_suffix = self.clipper_suffix.clone();
-
-            let gif_output_dir = self.gif_output_dir.clone();
-            let gif_width_str = self.gif_width.clone();
-            let gif_fps_str = self.gif_fps.clone();
-            let gif_max_size_str = self.gif_max_size.clone();
-            let gif_optimize = self.gif_optimize;
-
-            let transparency_backup = self.transparency_backup;
-
-            let splitter_output_dir = self.splitter_output_dir.clone();
-            let splitter_prefix = self.splitter_prefix.clone();
-            let splitter_custom_encode = self.splitter_custom_encode.clone();
-            let splitter_force = self.splitter_force;
-
-            let merger_audio_file = self.merger_audio_file.clone();
-            let merger_output_dir = self.merger_output_dir.clone();
-            let merger_shortest = self.merger_shortest;
-            let merger_copy_codec = self.merger_copy_codec;
-
-            let status_clone = Arc::clone(&self.status);
-            let processing_clone = Arc::clone(&self.processing);
-            let results_clone: Arc<Mutex<Vec<batch_processing::BatchItemResult>>> = Arc::clone(&self.results);
-            let progress_clone = Arc::clone(&self.progress);
-
-            thread::spawn(move || {
-                // Create batch processor based on operation type
-                let mut processor = match operation_type {
-                    BatchOperationType::Clipper => {
-                        // Create processor for clipper
-                        match BatchProcessor::create_clipper(
-                            &clipper_time_ranges,
-                            Path::new(&clipper_output_dir),
-                            clipper_copy_codec,
-                            if clipper_suffix.is_empty() { None } else { Some(&clipper_suffix) }
-                        ) {
-                            Ok(p) => p,
-                            Err(e) => {
-                                *status_clone.lock().unwrap() = format!("Error: {}", e);
-                                *processing_clone.lock().unwrap() = false;
-                                return;
-                            }
+                    for name in names {
+                        if ui.selectable_label(self.selected_profile == name, &name).clicked() {
+                            picked = Some(name);
                         }
-                    },
-                    BatchOperationType::GifConverter => {
-                        // Parse GIF converter settings
-                        let width = if gif_width_str.is_empty() {
-                            None
-                        } else {
-                            match gif_width_str.parse::<u32>() {
-                                Ok(w) => Some(w),
-                                Err(_) => {
-                                    *status_clone.lock().unwrap() = "Error: Width must be a positive integer.".to_string();
-                                    *processing_clone.lock().unwrap() = false;
-                                    return;
-                                }
-                            }
-                        };
-
-                        let fps = match gif_fps_str.parse::<u32>() {
-                            Ok(f) => f,
-                            Err(_) => {
-                                *status_clone.lock().unwrap() = "Error: FPS must be a positive integer.".to_string();
-                                *processing_clone.lock().unwrap() = false;
-                                return;
-                            }
-                        };
-
-                        let max_size = match gif_max_size_str.parse::<f64>() {
-                            Ok(s) => s,
-                            Err(_) => {
-                                *status_clone.lock().unwrap() = "Error: Max size must be a positive number.".to_string();
-                                *processing_clone.lock().unwrap() = false;
-                                return;
-                            }
-                        };
-
-                        // Create processor for GIF converter
-                        BatchProcessor::create_gif_converter(
-                            width,
-                            fps,
-                            max_size,
-                            gif_optimize,
-                            Path::new(&gif_output_dir)
-                        )
-                    },
-                    BatchOperationType::GifTransparency => {
-                        // Create processor for GIF transparency
-                        BatchProcessor::create_gif_transparency(transparency_backup)
-                    },
-                    BatchOperationType::Splitter => {
-                        // Create processor for splitter
-                        BatchProcessor::create_splitter(
-                            Path::new(&splitter_output_dir),
-                            &splitter_prefix,
-                            if splitter_custom_encode.is_empty() { None } else { Some(&splitter_custom_encode) },
-                            splitter_force
-                        )
-                    },
-                    BatchOperationType::Merger => {
-                        // Create processor for merger
-                        BatchProcessor::create_merger(
-                            Path::new(&merger_audio_file),
-                            Path::new(&merger_output_dir),
-                            merger_shortest,
-                            merger_copy_codec
-                        )
-                    },
-                };
-
-                // Configure processor
-                processor = processor.with_recursive(recursive).with_parallel(parallel);
-
-                if !pattern.is_empty() {
-                    processor = match processor.with_pattern(&pattern) {
-                        Ok(p) => p,
-                        Err(e) => {
-                            *status_clone.lock().unwrap() = format!("Error: Invalid pattern - {}", e);
-                            *processing_clone.lock().unwrap() = false;
-                            return;
-                        }
-                    };
+                    }
+                });
+
+            ui.separator();
+            ui.add(TextEdit::singleline(&mut self.new_profile_name).hint_text("Profile name").desired_width(140.0));
+            save = ui.add_enabled(!self.new_profile_name.trim().is_empty(), egui::Button::new("Save as profile"))
+                .on_hover_text("Save the current settings as a profile of this operation")
+                .clicked();
+        });
+
+        if let Some(name) = picked {
+            self.use_profile(&name);
+        }
+        if save {
+            self.save_as_profile();
+        }
+    }
+
+    /// Save the current settings under the new profile name, replacing the
+    /// profile they were filled from when the name is the same
+    fn save_as_profile(&mut self) {
+        let Some(manager) = &self.profile_manager else {
+            return;
+        };
+        let name = self.new_profile_name.trim().to_string();
+
+        let saved = self.build_settings().and_then(|settings| {
+            let profile = settings.to_profile(&name);
+            let mode = if name == self.selected_profile { OverwriteMode::Overwrite } else { OverwriteMode::ErrorIfExists };
+            manager.save_profile_with(&profile, mode).map_err(|e| e.to_string())
+        });
+
+        *self.status.lock().unwrap() = match saved {
+            Ok(()) => {
+                self.selected_profile = name.clone();
+                format!("Settings saved as profile '{}'.", name)
+            },
+            Err(e) => format!("Error saving profile '{}': {}", name, e),
+        };
+    }
+
+    fn use_profile(&mut self, name: &str) {
+        let Some(manager) = &self.profile_manager else {
+            return;
+        };
+
+        let settings = manager
+            .load_profile(name, self.operation_type.to_profile_type())
+            .map_err(|e| e.to_string())
+            .and_then(|profile| BatchSettings::from_profile(&profile).map_err(|e| e.to_string()));
+
+        if settings.is_ok() {
+            let _ = manager.record_usage(name, self.operation_type.to_profile_type());
+        }
+        *self.status.lock().unwrap() = match settings {
+            Ok(settings) => {
+                self.apply_settings(&settings);
+                self.selected_profile = name.to_string();
+                self.recent_profiles = None;
+                format!("Settings filled from profile '{}'.", name)
+            },
+            Err(e) => format!("Error using profile '{}': {}", name, e),
+        };
+    }
+
+    /// Fill the form from batch settings, the reverse of `build_settings`
+    ///
+    /// Settings the form has no field for keep their defaults when the batch is run.
+    fn apply_settings(&mut self, settings: &BatchSettings) {
+        let text = |value: Option<String>| value.unwrap_or_default();
+        let number = |value: Option<u64>| value.map(|n| n.to_string()).unwrap_or_default();
+        let uses_hw = |hw_accel: &Option<String>| {
+            hw_accel.as_deref().and_then(|h| h.parse::<EncoderPreference>().ok()).is_some_and(|p| p.hw_ok)
+        };
+
+        self.recursive = settings.recursive;
+        self.follow_symlinks = settings.follow_symlinks;
+        self.max_depth = number(settings.max_depth.map(|n| n as u64));
+        self.parallel = settings.parallel;
+        self.pattern = text(settings.pattern.clone());
+        self.pattern_target = settings.pattern_target;
+        self.include_globs = settings.include.join(" ");
+        self.exclude_globs = settings.exclude.join(" ");
+        self.by_content = settings.by_content;
+        self.mirror_structure = settings.mirror_structure;
+        self.since = text(settings.since.clone());
+        self.until = text(settings.until.clone());
+        self.min_input_size = text(settings.min_input_size.clone());
+        self.max_input_size = text(settings.max_input_size.clone());
+        self.order = settings.order;
+        self.descending = settings.descending;
+        self.limit = number(settings.limit.map(|n| n as u64));
+        self.sample = number(settings.sample.map(|n| n as u64));
+        self.seed = number(settings.seed);
+        self.retries = number(Some(settings.retries.into()).filter(|&n| n > 0));
+        self.max_failures = if settings.fail_fast {
+            String::from("1")
+        } else {
+            number(settings.max_failures.map(|n| n as u64))
+        };
+
+        match &settings.operation {
+            OperationSettings::Clipper { ranges, output_dir, copy_codec, suffix, hw_accel, format, .. } => {
+                self.clipper_time_ranges = ranges.clone();
+                self.clipper_output_dir = output_dir.display().to_string();
+                self.clipper_copy_codec = *copy_codec;
+                self.clipper_suffix = text(suffix.clone());
+                self.clipper_format = format.clone().unwrap_or_else(|| String::from("mp4"));
+                self.hw_encoding = uses_hw(hw_accel);
+            },
+            OperationSettings::GifConverter { width, fps, max_size_mb, optimize, output_dir, crop, boomerang, poster, .. } => {
+                self.gif_width = number(width.map(u64::from));
+                self.gif_fps = fps.to_string();
+                self.gif_max_size = max_size_mb.to_string();
+                self.gif_optimize = *optimize;
+                self.gif_output_dir = output_dir.display().to_string();
+                self.gif_crop = text(crop.clone());
+                self.gif_boomerang = *boomerang;
+                self.gif_poster = *poster;
+            },
+            OperationSettings::GifTransparency { create_backup, webp, legacy_trailer, .. } => {
+                self.transparency_backup = *create_backup;
+                self.transparency_webp = webp.is_some();
+                self.transparency_legacy = *legacy_trailer;
+            },
+            OperationSettings::Splitter {
+                output_dir, prefix, custom_encode, slices, direction, expected_dimensions, format,
+                segment_duration, copy_codec, hw_accel, ..
+            } => {
+                self.splitter_output_dir = output_dir.display().to_string();
+                self.splitter_prefix = prefix.clone();
+                self.splitter_custom_encode = text(custom_encode.clone());
+                self.splitter_expected_dimensions = text(expected_dimensions.clone());
+                self.splitter_format = format.clone().unwrap_or_else(|| String::from("mp4"));
+                self.splitter_slices = number(slices.map(u64::from));
+                if let Some(direction) = direction.as_deref().and_then(|d| d.parse().ok()) {
+                    self.splitter_direction = direction;
                 }
+                self.splitter_segment_duration = segment_duration.map(|d| d.to_string()).unwrap_or_default();
+                self.splitter_copy_codec = *copy_codec;
+                self.hw_encoding = uses_hw(hw_accel);
+            },
+            OperationSettings::Merger {
+                audio_file, output_dir, shortest, copy_codec, audio_ext, audio_dir, format, hw_accel, ..
+            } => {
+                self.merger_match_by_name = audio_ext.is_some();
+                self.merger_audio_file = audio_file.as_ref().map(|f| f.display().to_string()).unwrap_or_default();
+                self.merger_audio_ext = audio_ext.clone().unwrap_or_else(|| String::from("wav"));
+                self.merger_audio_dir = audio_dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default();
+                self.merger_output_dir = output_dir.display().to_string();
+                self.merger_shortest = *shortest;
+                self.merger_copy_codec = *copy_codec;
+                self.merger_format = format.clone().unwrap_or_else(|| String::from("mp4"));
+                self.hw_encoding = uses_hw(hw_accel);
+            },
+            OperationSettings::Plugin { name, params, output_dir, extensions } => {
+                self.plugin_name = name.clone();
+                self.plugin_params = params.iter().map(|(key, value)| format!("{}={}\n", key, value)).collect();
+                self.plugin_output_dir = output_dir.display().to_string();
+                self.plugin_extensions = extensions.join(", ");
+            },
+        }
+    }
 
-                // Add progress callback
-                processor = processor.with_progress_callback(move |current, total| {
-                    *progress_clone.lock().unwrap() = (current, total);
-                });
+    /// Check the inputs and settings and build the processor for a run or preview
+    fn build_processor(&self) -> Result<BatchProcessor, String> {
+        if self.input_paths.is_empty() {
+            return Err("Please add at least one input file or directory.".to_string());
+        }
 
-                // Process files
-                let process_result = processor.process(&input_paths);
-
-                match process_result {
-                    Ok(batch_results) => {
-                        // Store results
-                        let success_count = batch_results.iter().filter(|r| r.success).count();
-                        *results_clone.lock().unwrap() = batch_results;
-
-                        // Update status
-                        *status_clone.lock().unwrap() = format!(
-                            "Batch processing complete: {}/{} files processed successfully.",
-                            success_count,
-                            results_clone.lock().unwrap().len()
-                        );
-                    },
-                    Err(e) => {
-                        *status_clone.lock().unwrap() = format!("Error during batch processing: {}", e);
+        let settings = self.build_settings()?;
+        match self.plugin_manager {
+            Some(ref plugins) => BatchController::build_processor_with_plugins(&settings, plugins),
+            None => BatchController::build_processor(&settings),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    /// Collect the form fields into batch settings, reporting fields that do not parse
+    fn build_settings(&self) -> Result<BatchSettings, String> {
+        let optional = |value: &str| {
+            let value = value.trim();
+            if value.is_empty() { None } else { Some(value.to_string()) }
+        };
+        let hw_accel = Some(encoder_preference(self.hw_encoding).to_string());
+
+        let operation = match self.operation_type {
+            BatchOperationType::Clipper => {
+                let ranges: Vec<String> = self.clipper_time_ranges.iter()
+                    .filter_map(|r| optional(r))
+                    .collect();
+
+                if ranges.is_empty() {
+                    return Err("Please add at least one time range.".to_string());
+                }
+
+                OperationSettings::Clipper {
+                    ranges,
+                    output_dir: PathBuf::from(&self.clipper_output_dir),
+                    copy_codec: self.clipper_copy_codec,
+                    suffix: optional(&self.clipper_suffix),
+                    avoid_negative_ts: None,
+                    verify_outputs: false,
+                    hw_accel,
+                    format: Some(self.clipper_format.clone()),
+                }
+            },
+            BatchOperationType::GifConverter => {
+                let width = parse_field(&self.gif_width, "Width must be a positive integer.")?;
+                let fps = self.gif_fps.trim().parse::<u32>()
+                    .map_err(|_| "FPS must be a positive integer.".to_string())?;
+                let max_size_mb = self.gif_max_size.trim().parse::<f64>()
+                    .map_err(|_| "Max size must be a positive number.".to_string())?;
+
+                OperationSettings::GifConverter {
+                    width,
+                    fps,
+                    max_size_mb,
+                    optimize: self.gif_optimize,
+                    output_dir: PathBuf::from(&self.gif_output_dir),
+                    crop: optional(&self.gif_crop),
+                    boomerang: self.gif_boomerang,
+                    loop_count: None,
+                    gifsicle: false,
+                    gifsicle_level: 3,
+                    gifsicle_lossy: None,
+                    poster: self.gif_poster,
+                    poster_time: None,
+                }
+            },
+            BatchOperationType::GifTransparency => OperationSettings::GifTransparency {
+                create_backup: self.transparency_backup,
+                backup_dir: None,
+                webp: if self.transparency_webp {
+                    Some(BatchWebpConfig { quality: 80, lossless: false, output_dir: None })
+                } else {
+                    None
+                },
+                legacy_trailer: self.transparency_legacy,
+                no_atomic: false,
+                output_dir: None,
+            },
+            BatchOperationType::Splitter => OperationSettings::Splitter {
+                output_dir: PathBuf::from(&self.splitter_output_dir),
+                prefix: self.splitter_prefix.clone(),
+                custom_encode: optional(&self.splitter_custom_encode),
+                force: false,
+                slices: parse_field(&self.splitter_slices, "Number of slices must be a positive integer.")?,
+                direction: Some(self.splitter_direction.to_string()),
+                expected_dimensions: optional(&self.splitter_expected_dimensions),
+                format: Some(self.splitter_format.clone()),
+                segment_duration: parse_field(&self.splitter_segment_duration, "Segment duration must be a number of seconds.")?,
+                copy_codec: self.splitter_copy_codec,
+                jobs: None,
+                hw_accel,
+            },
+            BatchOperationType::Merger => {
+                if self.merger_match_by_name {
+                    if self.merger_audio_ext.trim().is_empty() {
+                        return Err("Please enter the extension of the audio files.".to_string());
                     }
+                } else if self.merger_audio_file.trim().is_empty() {
+                    return Err("Please select an audio file.".to_string());
                 }
 
-                *processing_clone.lock().unwrap() = false;
-            });
-        }
+                OperationSettings::Merger {
+                    audio_file: (!self.merger_match_by_name).then(|| PathBuf::from(&self.merger_audio_file)),
+                    output_dir: PathBuf::from(&self.merger_output_dir),
+                    shortest: self.merger_shortest,
+                    copy_codec: self.merger_copy_codec,
+                    audio_ext: self.merger_match_by_name.then(|| self.merger_audio_ext.trim().to_string()),
+                    audio_dir: if self.merger_match_by_name { optional(&self.merger_audio_dir).map(PathBuf::from) } else { None },
+                    audio_fade_in: None,
+                    audio_fade_out: None,
+                    format: Some(self.merger_format.clone()),
+                    hw_accel,
+                }
+            },
+            BatchOperationType::Plugin => {
+                if self.plugin_name.is_empty() {
+                    return Err("Please choose a plugin.".to_string());
+                }
+                let mut params = BTreeMap::new();
+                for line in self.plugin_params.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                    let (key, value) = line.split_once('=')
+                        .ok_or_else(|| format!("Plugin parameter '{}' is not of the form key=value.", line))?;
+                    params.insert(key.trim().to_string(), value.trim().to_string());
+                }
+
+                OperationSettings::Plugin {
+                    name: self.plugin_name.clone(),
+                    params,
+                    output_dir: PathBuf::from(&self.plugin_output_dir),
+                    extensions: self.plugin_extensions.split([',', ' ']).filter(|ext| !ext.is_empty()).map(String::from).collect(),
+                }
+            },
+        };
+
+        // Trial-run selection; a limit takes precedence over a sample
+        let limit = parse_field(&self.limit, "Invalid file limit")?;
+        let (sample, seed) = if limit.is_some() {
+            (None, None)
+        } else {
+            (
+                parse_field(&self.sample, "Invalid sample size")?,
+                parse_field(&self.seed, "Invalid sample seed")?,
+            )
+        };
+
+        Ok(BatchSettings {
+            recursive: self.recursive,
+            follow_symlinks: self.follow_symlinks,
+            max_depth: parse_field(&self.max_depth, "Invalid maximum depth")?,
+            parallel: self.parallel,
+            files_from: None,
+            pattern: optional(&self.pattern),
+            pattern_target: self.pattern_target,
+            include: self.include_globs.split_whitespace().map(String::from).collect(),
+            exclude: self.exclude_globs.split_whitespace().map(String::from).collect(),
+            by_content: self.by_content,
+            since: optional(&self.since),
+            until: optional(&self.until),
+            min_input_size: optional(&self.min_input_size),
+            max_input_size: optional(&self.max_input_size),
+            order: self.order,
+            descending: self.descending,
+            limit,
+            sample,
+            seed,
+            per_file_timeout: None,
+            retries: parse_field(&self.retries, "Invalid retry count")?.unwrap_or(0),
+            retry_backoff: None,
+            fail_fast: false,
+            max_failures: parse_field(&self.max_failures, "Invalid failure limit")?,
+            checkpoint: None,
+            mirror_structure: self.mirror_structure,
+            operation,
+        })
     }
 
     fn clipper_settings_ui(&mut self, ui: &mut Ui) {
@@ -491,6 +947,8 @@ impl BatchTab {
             ui.label("Suffix:");
             ui.text_edit_singleline(&mut self.clipper_suffix);
         });
+
+        output_format_ui(ui, "clipper", &mut self.clipper_format);
     }
 
     fn gif_converter_settings_ui(&mut self, ui: &mut Ui) {
@@ -524,6 +982,15 @@ impl BatchTab {
             ui.text_edit_singleline(&mut self.gif_max_size);
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Crop:");
+            ui.text_edit_singleline(&mut self.gif_crop);
+            ui.label("(x,y,w,h or auto)");
+        });
+
+        ui.checkbox(&mut self.gif_boomerang, "Boomerang (play forward then backward)");
+        ui.checkbox(&mut self.gif_poster, "Write a PNG poster frame next to each GIF");
+
         // Optimization option
         ui.checkbox(&mut self.gif_optimize, "Optimize (try multiple settings to achieve size target)");
     }
@@ -533,6 +1000,8 @@ impl BatchTab {
 
         // Backup option
         ui.checkbox(&mut self.transparency_backup, "Create backup of original files");
+        ui.checkbox(&mut self.transparency_webp, "Also write a transparent WebP next to each GIF");
+        ui.checkbox(&mut self.transparency_legacy, "Legacy mode (change the trailer byte)");
     }
 
     fn splitter_settings_ui(&mut self, ui: &mut Ui) {
@@ -560,26 +1029,76 @@ impl BatchTab {
             ui.text_edit_singleline(&mut self.splitter_custom_encode);
         });
 
-        // Force option
-        ui.checkbox(&mut self.splitter_force, "Force (process even if video dimensions are not 1920x1080)");
+        output_format_ui(ui, "splitter", &mut self.splitter_format);
+
+        // Slice geometry
+        ui.horizontal(|ui| {
+            ui.label("Slices:");
+            ui.add(TextEdit::singleline(&mut self.splitter_slices).desired_width(40.0));
+            ComboBox::from_id_source("batch_splitter_direction")
+                .selected_text(format!("{:?}", self.splitter_direction))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.splitter_direction, SliceDirection::Vertical, "Vertical");
+                    ui.selectable_value(&mut self.splitter_direction, SliceDirection::Horizontal, "Horizontal");
+                });
+        });
+
+        // Time-based segments instead of slices
+        ui.horizontal(|ui| {
+            ui.label("Segment Duration (s):");
+            ui.add(TextEdit::singleline(&mut self.splitter_segment_duration).desired_width(60.0));
+            ui.label("(leave empty to cut slices)");
+        });
+        ui.checkbox(&mut self.splitter_copy_codec, "Copy codec when cutting segments (faster)");
+
+        // Optional dimension check
+        ui.horizontal(|ui| {
+            ui.label("Required Dimensions:");
+            ui.add(TextEdit::singleline(&mut self.splitter_expected_dimensions).desired_width(90.0));
+            ui.label("(e.g. 1920x1080, leave empty for any)");
+        });
     }
 
     fn merger_settings_ui(&mut self, ui: &mut Ui) {
         ui.heading("Merger Settings");
 
-        // Audio file
         ui.horizontal(|ui| {
-            ui.label("Audio File:");
-            ui.text_edit_singleline(&mut self.merger_audio_file);
-            if ui.button("Browse").clicked() {
-                if let Some(path) = rfd::FileDialog::new()
-                    .add_filter("Audio Files", &["mp3", "aac", "wav", "m4a", "flac", "ogg"])
-                    .pick_file() {
-                    self.merger_audio_file = path.to_string_lossy().to_string();
-                }
-            }
+            ui.label("Audio:");
+            ui.radio_value(&mut self.merger_match_by_name, false, "Single audio file");
+            ui.radio_value(&mut self.merger_match_by_name, true, "Match by name");
         });
 
+        if self.merger_match_by_name {
+            ui.horizontal(|ui| {
+                ui.label("Audio Extension:");
+                ui.add(TextEdit::singleline(&mut self.merger_audio_ext).desired_width(60.0));
+                ui.label("(clip001.mp4 is merged with clip001.<extension>)");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Audio Directory:");
+                ui.text_edit_singleline(&mut self.merger_audio_dir);
+                if ui.button("Browse").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        self.merger_audio_dir = path.to_string_lossy().to_string();
+                    }
+                }
+                ui.label("(leave empty to look next to each video)");
+            });
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("Audio File:");
+                ui.text_edit_singleline(&mut self.merger_audio_file);
+                if ui.button("Browse").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Audio Files", &["mp3", "aac", "wav", "m4a", "flac", "ogg"])
+                        .pick_file() {
+                        self.merger_audio_file = path.to_string_lossy().to_string();
+                    }
+                }
+            });
+        }
+
         // Output directory
         ui.horizontal(|ui| {
             ui.label("Output Directory:");
@@ -594,5 +1113,228 @@ impl BatchTab {
         // Options
         ui.checkbox(&mut self.merger_shortest, "Use -shortest flag (end when shortest input stream ends)");
         ui.checkbox(&mut self.merger_copy_codec, "Copy codec without re-encoding (faster)");
+
+        output_format_ui(ui, "merger", &mut self.merger_format);
+    }
+
+    fn plugin_settings_ui(&mut self, ui: &mut Ui) {
+        ui.heading("Plugin Settings");
+
+        // Discover the plugins once, when they are first needed
+        let plugins = self.plugin_manager.get_or_insert_with(|| {
+            let plugins = PluginManager::new().expect("Failed to create plugin manager");
+            plugins.discover_plugins();
+            plugins
+        });
+        let mut names: Vec<String> = plugins.get_all_plugin_metadata().into_iter().map(|metadata| metadata.name).collect();
+        names.sort();
+
+        ui.horizontal(|ui| {
+            ui.label("Plugin:");
+            ComboBox::from_id_source("batch_plugin_name")
+                .selected_text(if self.plugin_name.is_empty() { "Choose a plugin" } else { self.plugin_name.as_str() })
+                .show_ui(ui, |ui| {
+                    for name in &names {
+                        ui.selectable_value(&mut self.plugin_name, name.clone(), name);
+                    }
+                });
+            if ui.button("Rediscover").on_hover_text("Search the plugin directories again").clicked() {
+                self.plugin_manager = None;
+            }
+        });
+        if names.is_empty() {
+            ui.label("No plugins found on the plugin search path (see the Plugins tab).");
+        }
+
+        // Tell which parameters each file fills in
+        if let Some(info) = self.plugin_manager.as_ref().and_then(|plugins| plugins.get_plugin_parameters(&self.plugin_name)) {
+            match plugin_input_parameter(&info) {
+                Some(input) => {
+                    let output = plugin_output_parameter(&info)
+                        .map(|output| format!(", the output path to '{}'", output.name))
+                        .unwrap_or_default();
+                    ui.label(format!("Each file is passed to '{}'{}.", input.name, output));
+                },
+                None => {
+                    ui.colored_label(egui::Color32::RED, "This plugin has no file parameter and cannot be run in batch.");
+                },
+            }
+        }
+
+        ui.label("Parameters (key=value, one per line):");
+        ui.add(TextEdit::multiline(&mut self.plugin_params).desired_rows(3).hint_text("position=bottom_right"));
+
+        ui.horizontal(|ui| {
+            ui.label("Extensions:");
+            ui.add(TextEdit::singleline(&mut self.plugin_extensions).hint_text(DEFAULT_PLUGIN_EXTENSIONS.join(", ")));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Output Directory:");
+            ui.text_edit_singleline(&mut self.plugin_output_dir);
+            if ui.button("Browse").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.plugin_output_dir = path.to_string_lossy().to_string();
+                }
+            }
+        });
+    }
+}
+
+/// Dropdown of the video containers an operation can write
+fn output_format_ui(ui: &mut Ui, operation: &str, selected: &mut String) {
+    ui.horizontal(|ui| {
+        ui.label("Output Format:");
+        ComboBox::from_id_source(format!("batch_{}_format", operation))
+            .selected_text(selected.as_str())
+            .show_ui(ui, |ui| {
+                for format in get_supported_formats(operation) {
+                    // The merger also lists the audio formats it reads
+                    if format.parse::<VideoFormat>().is_ok() {
+                        ui.selectable_value(selected, format.clone(), format);
+                    }
+                }
+            });
+    });
+}
+
+/// Label of a file order in the dropdown
+fn file_order_name(order: FileOrder) -> &'static str {
+    match order {
+        FileOrder::Name => "Path",
+        FileOrder::Size => "Size",
+        FileOrder::ModifiedTime => "Modification time",
+    }
+}
+
+/// Label of a pattern target in the dropdown
+fn pattern_target_name(target: PatternTarget) -> &'static str {
+    match target {
+        PatternTarget::FileName => "File name",
+        PatternTarget::RelativePath => "Relative path",
+        PatternTarget::FullPath => "Full path",
+    }
+}
+
+/// Parse an optional numeric form field; empty means "not set"
+fn parse_field<T: FromStr>(value: &str, message: &str) -> Result<Option<T>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
     }
-}
\ No newline at end of file
+    value.parse::<T>().map(Some).map_err(|_| message.to_string())
+}
+
+/// Progress of a running batch, or the results of the last run
+pub(crate) fn batch_progress_ui(
+    ui: &mut Ui,
+    processing: bool,
+    progress: &Mutex<BatchProgress>,
+    results: &Mutex<Vec<BatchItemResult>>,
+    summary: &Mutex<Option<BatchSummary>>,
+) {
+    if processing {
+        // Show progress
+        let progress = progress.lock().unwrap();
+        let (completed, total) = (progress.completed, progress.total);
+        ui.label(format!("Processed {} of {} files", completed, total));
+        ui.add(egui::ProgressBar::new(if total > 0 { completed as f32 / total as f32 } else { 0.0 })
+            .show_percentage());
+        if let Some(ref current) = progress.current {
+            ui.label(format!("Processing {}", current.file_name().unwrap_or_default().to_string_lossy()));
+        }
+    } else {
+        // Show results if available
+        let results = results.lock().unwrap();
+        if !results.is_empty() {
+            let success_count = results.iter().filter(|r| r.success).count();
+            ui.label(format!("Processed {} files: {} succeeded, {} failed",
+                             results.len(), success_count, results.len() - success_count));
+            if let Some(ref summary) = *summary.lock().unwrap() {
+                ui.label(summary.to_string());
+            }
+
+            if results.len() - success_count > 0 {
+                ui.collapsing("Show errors", |ui| {
+                    for result in results.iter().filter(|r| !r.success) {
+                        if let Some(ref error) = result.error_message {
+                            ui.label(format!("{}: {}", result.input.display(), error));
+                        }
+                    }
+                });
+            }
+
+            let warning_count: usize = results.iter().map(|r| r.warnings.len()).sum();
+            if warning_count > 0 {
+                ui.collapsing(format!("Warnings ({})", warning_count), |ui| {
+                    for warning in results.iter().flat_map(|r| &r.warnings) {
+                        ui.label(warning.to_string());
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Handles to the state a batch run reports into, shared with the tab showing it
+pub(crate) struct BatchRunState {
+    pub status: Arc<Mutex<String>>,
+    pub processing: Arc<Mutex<bool>>,
+    pub results: Arc<Mutex<Vec<BatchItemResult>>>,
+    pub summary: Arc<Mutex<Option<BatchSummary>>>,
+    pub progress: Arc<Mutex<BatchProgress>>,
+    pub cancel: SharedCancel,
+}
+
+/// Run a configured processor on a worker thread, reporting into the shared state
+pub(crate) fn spawn_batch_run(processor: BatchProcessor, input_paths: Vec<PathBuf>, state: BatchRunState) {
+    *state.processing.lock().unwrap() = true;
+    let cancel = start_cancellation(&state.cancel);
+    let processor = processor.with_cancellation(cancel.clone());
+    state.results.lock().unwrap().clear();
+    *state.summary.lock().unwrap() = None;
+    *state.progress.lock().unwrap() = BatchProgress::default();
+
+    thread::spawn(move || {
+        let on_progress = move |update: &BatchProgress| {
+            let mut progress = state.progress.lock().unwrap();
+            progress.completed = update.completed;
+            progress.total = update.total;
+            // Keep showing the last file started while parallel files finish
+            if update.phase == ProgressPhase::Started {
+                progress.current = update.current.clone();
+            }
+        };
+
+        match BatchController::run(processor, &input_paths, Some(on_progress)) {
+            Ok(report) => {
+                // Update status
+                let mut status = format!(
+                    "Batch processing {}: {}/{} files processed successfully.",
+                    if cancel.is_cancelled() {
+                        "cancelled"
+                    } else if report.aborted {
+                        "stopped at the failure limit"
+                    } else {
+                        "complete"
+                    },
+                    report.success_count(),
+                    report.results.len()
+                );
+                if let Some(ref summary) = report.selection_summary {
+                    status.push_str(&format!(" ({})", summary));
+                }
+                *state.status.lock().unwrap() = status;
+
+                // Store results
+                *state.results.lock().unwrap() = report.results;
+                *state.summary.lock().unwrap() = Some(report.summary);
+            },
+            Err(e) => {
+                *state.status.lock().unwrap() = format!("Error during batch processing: {}", e);
+            }
+        }
+
+        *state.processing.lock().unwrap() = false;
+    });
+}
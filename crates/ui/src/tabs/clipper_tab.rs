@@ -2,29 +2,40 @@ use eframe::egui::{self, Ui};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use clipper::clip_video;
-use common::validate_time_range;
+use clipper::{clip_video_with_options, ClipOptions};
+use common::{cancellation_scope, timestamp_to_seconds, validate_time_range};
+
+use super::{
+    cancel_button, encoder_preference, error_status, hw_encoder_checkbox, start_cancellation, ProgressTracker,
+    SharedCancel
+};
 
 pub struct ClipperTab {
     input_file: String,
     output_dir: String,
     time_ranges: Vec<String>,
     copy_codec: bool,
+    hw_encoding: bool,
     suffix: String,
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    progress: Arc<Mutex<Option<f32>>>,
+    cancel: SharedCancel,
 }
 
 impl ClipperTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, progress: Arc<Mutex<Option<f32>>>, cancel: SharedCancel) -> Self {
         Self {
             input_file: String::new(),
             output_dir: String::from("output_clips"),
             time_ranges: vec![String::new()],
             copy_codec: false,
+            hw_encoding: false,
             suffix: String::new(),
             status,
             processing,
+            progress,
+            cancel,
         }
     }
 
@@ -90,6 +101,7 @@ impl ClipperTab {
         // Options section
         ui.heading("Options");
         ui.checkbox(&mut self.copy_codec, "Copy codec (faster but less precise)");
+        hw_encoder_checkbox(ui, &mut self.hw_encoding, self.copy_codec);
 
         ui.horizontal(|ui| {
             ui.label("Suffix:");
@@ -99,6 +111,7 @@ impl ClipperTab {
         // Execute button
         ui.add_space(10.0);
         let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new("Extract Clips"));
+        cancel_button(ui, &self.processing, &self.cancel, &self.status);
 
         if button.clicked() {
             if self.input_file.is_empty() {
@@ -138,16 +151,27 @@ impl ClipperTab {
             let output_dir = self.output_dir.clone();
             let copy_codec = self.copy_codec;
             let suffix = if self.suffix.is_empty() { None } else { Some(self.suffix.clone()) };
+            let options = ClipOptions { encoder: encoder_preference(self.hw_encoding), ..ClipOptions::default() };
             let status_clone = Arc::clone(&self.status);
             let processing_clone = Arc::clone(&self.processing);
+            let progress = Arc::clone(&self.progress);
+            let cancel = start_cancellation(&self.cancel);
 
             thread::spawn(move || {
-                let result = clip_video(
+                let _cancel_scope = cancellation_scope(Some(&cancel));
+                // Clips are cut one after another, so progress is measured against their total length
+                let total = parsed_ranges.iter().try_fold(0.0, |total, (start, end)| {
+                    Some(total + timestamp_to_seconds(end)? - timestamp_to_seconds(start)?)
+                });
+                let _tracker = ProgressTracker::start(&progress, total, true);
+
+                let result = clip_video_with_options(
                     &input_file,
                     &parsed_ranges,
                     &output_dir,
                     copy_codec,
-                    suffix.as_deref()
+                    suffix.as_deref(),
+                    &options,
                 );
 
                 match result {
@@ -158,7 +182,7 @@ impl ClipperTab {
                         *status_clone.lock().unwrap() = "Completed with some errors.".to_string();
                     }
                     Err(e) => {
-                        *status_clone.lock().unwrap() = format!("Error: {}", e);
+                        *status_clone.lock().unwrap() = error_status(&e);
                     }
                 }
 
@@ -3,7 +3,12 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use gif_converter::{convert_video_to_gif, optimize_conversion};
+use common::{format_warnings, get_duration};
+use gif_converter::{
+    convert_video_to_gif_with_options, optimize_conversion_with_options, CropMode, GifOptions, GifsicleSettings
+};
+
+use super::{cancel_button, error_status, start_cancellation, ProgressTracker, SharedCancel};
 
 pub struct GifConverterTab {
     input_file: String,
@@ -12,12 +17,20 @@ pub struct GifConverterTab {
     fps: String,
     max_size: String,
     optimize: bool,
+    crop: String,
+    boomerang: bool,
+    loop_count: String,
+    use_gifsicle: bool,
+    write_poster: bool,
+    poster_time: String,
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    progress: Arc<Mutex<Option<f32>>>,
+    cancel: SharedCancel,
 }
 
 impl GifConverterTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, progress: Arc<Mutex<Option<f32>>>, cancel: SharedCancel) -> Self {
         Self {
             input_file: String::new(),
             output_file: String::new(),
@@ -25,8 +38,16 @@ impl GifConverterTab {
             fps: String::from("10"),
             max_size: String::from("5.0"),
             optimize: true,
+            crop: String::new(),
+            boomerang: false,
+            loop_count: String::new(),
+            use_gifsicle: false,
+            write_poster: false,
+            poster_time: String::new(),
             status,
             processing,
+            progress,
+            cancel,
         }
     }
 
@@ -92,11 +113,35 @@ impl GifConverterTab {
             ui.text_edit_singleline(&mut self.max_size);
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Crop:");
+            ui.text_edit_singleline(&mut self.crop);
+            ui.label("(x,y,w,h or auto; leave empty for full frame)");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Play Count:");
+            ui.text_edit_singleline(&mut self.loop_count);
+            ui.label("(0 = forever, 1 = once; leave empty for forever)");
+        });
+
+        ui.checkbox(&mut self.boomerang, "Boomerang (play forward then backward)");
+        ui.checkbox(&mut self.use_gifsicle, "Post-optimize with gifsicle (if installed)");
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.write_poster, "Write poster frame (PNG)");
+            if self.write_poster {
+                ui.label("at");
+                ui.text_edit_singleline(&mut self.poster_time);
+                ui.label("(timestamp; leave empty for the middle)");
+            }
+        });
         ui.checkbox(&mut self.optimize, "Optimize (try multiple settings to achieve size target)");
 
         // Execute button
         ui.add_space(10.0);
         let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new("Convert to GIF"));
+        cancel_button(ui, &self.processing, &self.cancel, &self.status);
 
         if button.clicked() {
             if self.input_file.is_empty() {
@@ -138,6 +183,40 @@ impl GifConverterTab {
                 }
             };
 
+            let crop = if self.crop.trim().is_empty() {
+                None
+            } else {
+                match self.crop.parse::<CropMode>() {
+                    Ok(c) => Some(c),
+                    Err(e) => {
+                        *self.status.lock().unwrap() = format!("Error: {}", e);
+                        return;
+                    }
+                }
+            };
+
+            let loop_count = if self.loop_count.trim().is_empty() {
+                None
+            } else {
+                match self.loop_count.trim().parse::<u32>() {
+                    Ok(n) => Some(n),
+                    Err(_) => {
+                        *self.status.lock().unwrap() = "Error: Play count must be a non-negative integer.".to_string();
+                        return;
+                    }
+                }
+            };
+            let cancel = start_cancellation(&self.cancel);
+            let options = GifOptions {
+                crop,
+                boomerang: self.boomerang,
+                loop_count,
+                gifsicle: self.use_gifsicle.then(GifsicleSettings::default),
+                write_poster: self.write_poster,
+                poster_time: Some(self.poster_time.trim().to_string()).filter(|time| !time.is_empty()),
+                cancel: Some(cancel),
+            };
+
             // Start processing in a separate thread
             *self.status.lock().unwrap() = "Converting video to GIF...".to_string();
             *self.processing.lock().unwrap() = true;
@@ -148,23 +227,31 @@ impl GifConverterTab {
             let optimize = self.optimize;
             let status_clone = Arc::clone(&self.status);
             let processing_clone = Arc::clone(&self.processing);
+            let progress = Arc::clone(&self.progress);
 
             thread::spawn(move || {
+                let _tracker = ProgressTracker::start(&progress, get_duration(&input_file).ok(), false);
+
                 let result = if optimize {
-                    optimize_conversion(&input_file, &output_file, max_size, width)
+                    optimize_conversion_with_options(&input_file, &output_file, max_size, width, &options)
                 } else {
-                    convert_video_to_gif(&input_file, &output_file, width, fps, max_size)
+                    convert_video_to_gif_with_options(&input_file, &output_file, width, fps, max_size, &options)
                 };
 
                 match result {
-                    Ok(true) => {
-                        *status_clone.lock().unwrap() = "Conversion successful!".to_string();
-                    }
-                    Ok(false) => {
-                        *status_clone.lock().unwrap() = format!("Output file exceeds size limit (> {}MB).", max_size);
+                    Ok(report) => {
+                        let mut status = if report.under_limit {
+                            format!("Conversion successful! {}", report)
+                        } else {
+                            format!("Output file exceeds size limit ({:.2}MB > {}MB): {}", report.size_mb, max_size, report)
+                        };
+                        if let Some(warnings) = format_warnings(&report.warnings) {
+                            status = format!("{}\n{}", status, warnings);
+                        }
+                        *status_clone.lock().unwrap() = status;
                     }
                     Err(e) => {
-                        *status_clone.lock().unwrap() = format!("Error: {}", e);
+                        *status_clone.lock().unwrap() = error_status(&e);
                     }
                 }
 
@@ -3,7 +3,12 @@ use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use gif_transparency::{batch_process_gifs, process_directory};
+use common::is_dry_run;
+use gif_transparency::{
+    batch_process_gifs, batch_restore_gifs, process_directory, BatchGifOptions, TransparencyMode,
+    TransparencyOptions,
+    TransparencyReport
+};
 
 pub struct GifTransparencyTab {
     input_paths: Vec<PathBuf>,
@@ -11,6 +16,14 @@ pub struct GifTransparencyTab {
     directory_path: String,
     recursive: bool,
     create_backup: bool,
+    dry_run: bool,
+    legacy_trailer: bool,
+    in_place: bool,
+    /// Directory for transparent copies; empty modifies the GIFs themselves
+    output_dir: String,
+    report: Arc<Mutex<Option<TransparencyReport>>>,
+    /// (processed, total) of the running batch
+    progress: Arc<Mutex<(usize, usize)>>,
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
 }
@@ -23,6 +36,12 @@ impl GifTransparencyTab {
             directory_path: String::new(),
             recursive: true,
             create_backup: true,
+            dry_run: false,
+            legacy_trailer: false,
+            in_place: false,
+            output_dir: String::new(),
+            report: Arc::new(Mutex::new(None)),
+            progress: Arc::new(Mutex::new((0, 0))),
             status,
             processing,
         }
@@ -86,6 +105,21 @@ impl GifTransparencyTab {
         ui.heading("Options");
         ui.checkbox(&mut self.recursive, "Process subdirectories recursively");
         ui.checkbox(&mut self.create_backup, "Create backup of original files");
+        ui.checkbox(&mut self.dry_run, "Dry run (only report what would change)");
+        ui.checkbox(&mut self.legacy_trailer, "Legacy mode (change the trailer byte)")
+            .on_hover_text("Older behavior that some viewers render as a broken file");
+        ui.checkbox(&mut self.in_place, "Edit files in place")
+            .on_hover_text("Faster for huge files, but an interrupted run can corrupt them");
+        ui.horizontal(|ui| {
+            ui.label("Output directory:");
+            ui.text_edit_singleline(&mut self.output_dir)
+                .on_hover_text("Write transparent copies here; leave empty to modify the GIFs themselves");
+            if ui.button("Browse").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.output_dir = path.to_string_lossy().to_string();
+                }
+            }
+        });
 
         ui.separator();
 
@@ -96,7 +130,33 @@ impl GifTransparencyTab {
             "Process Selected GIF Files"
         };
 
-        let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new(button_text));
+        let idle = !*self.processing.lock().unwrap();
+        let (button, restore_button) = ui.horizontal(|ui| {
+            (
+                ui.add_enabled(idle, egui::Button::new(button_text)),
+                ui.add_enabled(idle, egui::Button::new("Restore originals")),
+            )
+        }).inner;
+
+        if restore_button.clicked() {
+            let Some(inputs) = self.selected_inputs() else { return };
+
+            *self.status.lock().unwrap() = "Restoring original GIF files...".to_string();
+            *self.processing.lock().unwrap() = true;
+
+            let recursive = self.recursive;
+            let status_clone = Arc::clone(&self.status);
+            let processing_clone = Arc::clone(&self.processing);
+
+            thread::spawn(move || {
+                *status_clone.lock().unwrap() = match batch_restore_gifs(&inputs, recursive) {
+                    Ok((restored, skipped)) => format!("Restored {} GIF files, skipped {}", restored, skipped),
+                    Err(e) => format!("Error: {}", e),
+                };
+
+                *processing_clone.lock().unwrap() = false;
+            });
+        }
 
         if button.clicked() {
             // Validate inputs
@@ -113,6 +173,7 @@ impl GifTransparencyTab {
             // Start processing in a separate thread
             *self.status.lock().unwrap() = "Processing GIF files for transparency...".to_string();
             *self.processing.lock().unwrap() = true;
+            *self.progress.lock().unwrap() = (0, 0);
 
             // Clone values for thread
             let directory_mode = self.directory_mode;
@@ -120,23 +181,46 @@ impl GifTransparencyTab {
             let input_paths = self.input_paths.clone();
             let recursive = self.recursive;
             let create_backup = self.create_backup;
+            // Previewing commands must not write any GIF either
+            let dry_run = self.dry_run || is_dry_run();
+            let options = TransparencyOptions {
+                mode: if self.legacy_trailer {
+                    TransparencyMode::LegacyTrailer
+                } else {
+                    TransparencyMode::GraphicControl
+                },
+                atomic: !self.in_place,
+            };
+            let progress_clone = Arc::clone(&self.progress);
+            let mut batch_options = BatchGifOptions::default().with_progress_callback(move |processed, total| {
+                *progress_clone.lock().unwrap() = (processed, total);
+            });
+            if !self.output_dir.trim().is_empty() {
+                batch_options = batch_options.with_output_dir(self.output_dir.trim());
+            }
+            let report_clone = Arc::clone(&self.report);
             let status_clone = Arc::clone(&self.status);
             let processing_clone = Arc::clone(&self.processing);
 
             thread::spawn(move || {
                 let result = if directory_mode {
-                    process_directory(&directory_path, recursive, create_backup)
+                    process_directory(&directory_path, recursive, create_backup, dry_run, &options, &batch_options)
                 } else {
-                    batch_process_gifs(&input_paths, recursive, create_backup)
+                    batch_process_gifs(&input_paths, recursive, create_backup, dry_run, &options, &batch_options)
                 };
 
                 match result {
-                    Ok((success_count, total_count)) => {
-                        *status_clone.lock().unwrap() = format!(
-                            "Successfully processed {}/{} GIF files",
-                            success_count,
-                            total_count
-                        );
+                    Ok(report) => {
+                        *status_clone.lock().unwrap() = if report.dry_run {
+                            format!("Dry run: {}", report.breakdown())
+                        } else {
+                            format!(
+                                "Successfully processed {}/{} GIF files",
+                                report.success_count(),
+                                report.gif_count()
+                            )
+                        };
+                        *report_clone.lock().unwrap() = Some(report);
                     },
                     Err(e) => {
                         *status_clone.lock().unwrap() = format!("Error: {}", e);
@@ -146,5 +230,67 @@ impl GifTransparencyTab {
                 *processing_clone.lock().unwrap() = false;
             });
         }
+
+        if *self.processing.lock().unwrap() {
+            let (processed, total) = *self.progress.lock().unwrap();
+            if total > 0 {
+                ui.label(format!("Processed {} of {} GIF files", processed, total));
+                ui.add(egui::ProgressBar::new(processed as f32 / total as f32).show_percentage());
+            }
+        }
+
+        self.report_ui(ui);
+    }
+
+    /// Per-file results of the last run, in a collapsible list
+    fn report_ui(&self, ui: &mut Ui) {
+        let report = self.report.lock().unwrap();
+        let Some(report) = report.as_ref() else { return };
+
+        ui.separator();
+        ui.label(report.breakdown());
+
+        let title = format!("Per-file report ({} files)", report.entries.len());
+        egui::CollapsingHeader::new(title).show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for entry in &report.entries {
+                    let text = match &entry.error {
+                        Some(error) => format!("{}: {} ({})", entry.path.display(), entry.status, error),
+                        None => format!("{}: {}", entry.path.display(), entry.status),
+                    };
+                    ui.label(text);
+                }
+            });
+        });
+
+        if !report.skipped.is_empty() {
+            let title = format!("Skipped ({} files, not GIFs)", report.skipped.len());
+            egui::CollapsingHeader::new(title).show(ui, |ui| {
+                egui::ScrollArea::vertical().id_source("skipped_gifs").max_height(200.0).show(ui, |ui| {
+                    for skipped in &report.skipped {
+                        ui.label(format!("{}: {}", skipped.path.display(), skipped.reason));
+                    }
+                });
+            });
+        }
+    }
+
+    /// The selected directory or files, or `None` (with an error status) when nothing is selected
+    fn selected_inputs(&self) -> Option<Vec<PathBuf>> {
+        if self.directory_mode && self.directory_path.is_empty() {
+            *self.status.lock().unwrap() = "Error: Please select a directory.".to_string();
+            return None;
+        }
+
+        if !self.directory_mode && self.input_paths.is_empty() {
+            *self.status.lock().unwrap() = "Error: Please select at least one GIF file.".to_string();
+            return None;
+        }
+
+        if self.directory_mode {
+            Some(vec![PathBuf::from(&self.directory_path)])
+        } else {
+            Some(self.input_paths.clone())
+        }
     }
-}
\ No newline at end of file
+}
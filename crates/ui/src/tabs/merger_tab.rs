@@ -3,7 +3,22 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use merger::{extract_audio, merge_audio_video};
+use common::{cancellation_scope, get_duration};
+use merger::{
+    concat_videos, extract_audio, merge_audio_video_with_options, AudioMode, ConcatMode, DurationPolicy, LoudnessTarget,
+    MergeOptions, SubtitleMode
+};
+
+use super::{
+    cancel_button, encoder_preference, error_status, hw_encoder_checkbox, start_cancellation, ProgressTracker,
+    SharedCancel
+};
+
+#[derive(PartialEq)]
+pub enum MergerMode {
+    MergeAudio,
+    Concatenate,
+}
 
 #[derive(PartialEq)]
 pub enum AudioSource {
@@ -12,6 +27,7 @@ pub enum AudioSource {
 }
 
 pub struct MergerTab {
+    mode: MergerMode,
     video_file: String,
     audio_source: AudioSource,
     audio_file: String,
@@ -19,13 +35,30 @@ pub struct MergerTab {
     output_file: String,
     use_shortest: bool,
     copy_codec: bool,
+    hw_encoding: bool,
+    audio_mode: AudioMode,
+    video_volume: f32,
+    audio_volume: f32,
+    duration_policy: DurationPolicy,
+    normalize_loudness: bool,
+    loudness_target: LoudnessTarget,
+    subtitle_file: String,
+    burn_subtitles: bool,
+    fade_in_secs: f64,
+    fade_out_secs: f64,
+    concat_files: Vec<PathBuf>,
+    concat_output: String,
+    concat_mode: ConcatMode,
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    progress: Arc<Mutex<Option<f32>>>,
+    cancel: SharedCancel,
 }
 
 impl MergerTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, progress: Arc<Mutex<Option<f32>>>, cancel: SharedCancel) -> Self {
         Self {
+            mode: MergerMode::MergeAudio,
             video_file: String::new(),
             audio_source: AudioSource::File,
             audio_file: String::new(),
@@ -33,12 +66,39 @@ impl MergerTab {
             output_file: String::new(),
             use_shortest: true,
             copy_codec: true,
+            hw_encoding: false,
+            audio_mode: AudioMode::Replace,
+            video_volume: 1.0,
+            audio_volume: 1.0,
+            duration_policy: DurationPolicy::Shortest,
+            normalize_loudness: false,
+            loudness_target: LoudnessTarget::default(),
+            subtitle_file: String::new(),
+            burn_subtitles: false,
+            fade_in_secs: 0.0,
+            fade_out_secs: 0.0,
+            concat_files: Vec::new(),
+            concat_output: String::new(),
+            concat_mode: ConcatMode::Auto,
             status,
             processing,
+            progress,
+            cancel,
         }
     }
 
     pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.mode, MergerMode::MergeAudio, "Merge Audio");
+            ui.radio_value(&mut self.mode, MergerMode::Concatenate, "Concatenate");
+        });
+        ui.separator();
+
+        if self.mode == MergerMode::Concatenate {
+            self.concat_ui(ui);
+            return;
+        }
+
         // Video input section
         ui.heading("Video Input");
         ui.horizontal(|ui| {
@@ -98,6 +158,21 @@ impl MergerTab {
             }
         }
 
+        ui.heading("Subtitles (optional)");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.subtitle_file);
+            if ui.button("Browse").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Subtitle Files", &["srt", "ass", "ssa", "vtt"])
+                    .pick_file() {
+                    self.subtitle_file = path.to_string_lossy().to_string();
+                }
+            }
+        });
+        if !self.subtitle_file.is_empty() {
+            ui.checkbox(&mut self.burn_subtitles, "Burn into the video (re-encodes the video)");
+        }
+
         // Output file section
         ui.heading("Output File");
         ui.horizontal(|ui| {
@@ -120,10 +195,56 @@ impl MergerTab {
         ui.heading("Options");
         ui.checkbox(&mut self.use_shortest, "Use -shortest flag (end when shortest input stream ends)");
         ui.checkbox(&mut self.copy_codec, "Copy codec without re-encoding (faster)");
+        hw_encoder_checkbox(ui, &mut self.hw_encoding, self.copy_codec);
+
+        ui.horizontal(|ui| {
+            ui.label("Video's own audio:");
+            ui.radio_value(&mut self.audio_mode, AudioMode::Replace, "Replace");
+            ui.radio_value(&mut self.audio_mode, AudioMode::Mix, "Mix");
+            ui.radio_value(&mut self.audio_mode, AudioMode::AddTrack, "Keep as separate track");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Audio length:");
+            ui.radio_value(&mut self.duration_policy, DurationPolicy::Shortest, "As is");
+            ui.radio_value(&mut self.duration_policy, DurationPolicy::LoopAudio, "Loop to video length");
+            ui.radio_value(&mut self.duration_policy, DurationPolicy::PadSilence, "Pad with silence");
+        });
+
+        if self.audio_mode == AudioMode::Mix {
+            ui.horizontal(|ui| {
+                ui.label("Video Volume:");
+                ui.add(egui::Slider::new(&mut self.video_volume, 0.0..=2.0));
+                ui.label("New Audio Volume:");
+                ui.add(egui::Slider::new(&mut self.audio_volume, 0.0..=2.0));
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Fade in:");
+            ui.add(egui::DragValue::new(&mut self.fade_in_secs).clamp_range(0.0..=60.0).speed(0.1).suffix(" s"));
+            ui.label("Fade out:");
+            ui.add(egui::DragValue::new(&mut self.fade_out_secs).clamp_range(0.0..=60.0).speed(0.1).suffix(" s"));
+            ui.label("(0 = none; fading re-encodes the audio)");
+        });
+
+        ui.checkbox(&mut self.normalize_loudness, "Normalize loudness (re-encodes the audio)");
+        if self.normalize_loudness {
+            ui.horizontal(|ui| {
+                ui.label("Target:");
+                ui.add(egui::DragValue::new(&mut self.loudness_target.i).clamp_range(-70.0..=-5.0).speed(0.1).suffix(" LUFS"));
+                ui.label("True peak:");
+                ui.add(egui::DragValue::new(&mut self.loudness_target.tp).clamp_range(-9.0..=0.0).speed(0.1).suffix(" dBTP"));
+                ui.label("Range:");
+                ui.add(egui::DragValue::new(&mut self.loudness_target.lra).clamp_range(1.0..=50.0).speed(0.1).suffix(" LU"));
+                ui.checkbox(&mut self.loudness_target.two_pass, "Two-pass");
+            });
+        }
 
         // Execute button
         ui.add_space(10.0);
         let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new("Merge Audio and Video"));
+        cancel_button(ui, &self.processing, &self.cancel, &self.status);
 
         if button.clicked() {
             if self.video_file.is_empty() {
@@ -146,6 +267,16 @@ impl MergerTab {
                 return;
             }
 
+            if (self.audio_mode == AudioMode::Mix || self.duration_policy == DurationPolicy::PadSilence) && self.copy_codec {
+                *self.status.lock().unwrap() = "Error: Mixing or padding re-encodes the audio; untick \"Copy codec\".".to_string();
+                return;
+            }
+
+            if self.burn_subtitles && !self.subtitle_file.trim().is_empty() && self.copy_codec {
+                *self.status.lock().unwrap() = "Error: Burning in subtitles re-encodes the video; untick \"Copy codec\".".to_string();
+                return;
+            }
+
             // Start processing in a separate thread
             *self.status.lock().unwrap() = "Merging audio and video...".to_string();
             *self.processing.lock().unwrap() = true;
@@ -155,9 +286,9 @@ impl MergerTab {
             let audio_source = match self.audio_source {
                 AudioSource::File => self.audio_file.clone(),
                 AudioSource::Extract => {
-                    // We'll extract to a temporary file
+                    // We'll extract to a temporary file; its extension follows the source codec
                     let temp_dir = Path::new(&self.output_file).parent().unwrap_or_else(|| Path::new(""));
-                    let temp_audio = temp_dir.join("temp_audio.aac").to_string_lossy().to_string();
+                    let temp_audio = temp_dir.join("temp_audio").to_string_lossy().to_string();
                     temp_audio
                 }
             };
@@ -165,28 +296,49 @@ impl MergerTab {
             let output_file = self.output_file.clone();
             let use_shortest = self.use_shortest;
             let copy_codec = self.copy_codec;
+            let options = MergeOptions {
+                audio_mode: self.audio_mode,
+                video_volume: self.video_volume,
+                audio_volume: self.audio_volume,
+                duration_policy: self.duration_policy,
+                normalize_loudness: self.normalize_loudness.then_some(self.loudness_target),
+                subtitle_file: Some(self.subtitle_file.trim().to_string()).filter(|file| !file.is_empty()),
+                subtitle_mode: if self.burn_subtitles { SubtitleMode::Burn } else { SubtitleMode::Soft },
+                audio_fade_in_secs: (self.fade_in_secs > 0.0).then_some(self.fade_in_secs),
+                audio_fade_out_secs: (self.fade_out_secs > 0.0).then_some(self.fade_out_secs),
+                encoder: encoder_preference(self.hw_encoding),
+                // The worker's cancellation scope also covers the audio extraction
+                ..MergeOptions::default()
+            };
             let is_extract = matches!(self.audio_source, AudioSource::Extract);
             let status_clone = Arc::clone(&self.status);
             let processing_clone = Arc::clone(&self.processing);
+            let progress = Arc::clone(&self.progress);
+            let cancel = start_cancellation(&self.cancel);
 
             thread::spawn(move || {
+                let _cancel_scope = cancellation_scope(Some(&cancel));
+                let _tracker = ProgressTracker::start(&progress, get_duration(&video_file).ok(), false);
+
                 let result = if is_extract {
                     // First extract audio
                     *status_clone.lock().unwrap() = "Extracting audio from video...".to_string();
                     match extract_audio(&audio_extract_file, &audio_source) {
-                        Ok(_) => {
+                        Ok(extracted) => {
                             // Then merge
                             *status_clone.lock().unwrap() = "Merging audio with video...".to_string();
-                            let merge_result = merge_audio_video(
+                            let extracted = extracted.to_string_lossy().to_string();
+                            let merge_result = merge_audio_video_with_options(
                                 &video_file,
-                                &audio_source,
+                                &extracted,
                                 &output_file,
                                 use_shortest,
-                                copy_codec
+                                copy_codec,
+                                &options
                             );
 
                             // Clean up temporary file
-                            let _ = std::fs::remove_file(&audio_source);
+                            let _ = std::fs::remove_file(&extracted);
 
                             merge_result
                         },
@@ -194,21 +346,134 @@ impl MergerTab {
                     }
                 } else {
                     // Directly merge with existing audio file
-                    merge_audio_video(
+                    merge_audio_video_with_options(
                         &video_file,
                         &audio_source,
                         &output_file,
                         use_shortest,
-                        copy_codec
+                        copy_codec,
+                        &options
                     )
                 };
 
                 match result {
-                    Ok(_) => {
-                        *status_clone.lock().unwrap() = format!("Successfully merged audio and video. Output: {}", output_file);
+                    Ok(report) => {
+                        let mut status = format!("Successfully merged audio and video. Output: {}", output_file);
+                        if let Some(note) = report.duration_note {
+                            status.push_str(&format!("\nDuration: {}", note));
+                        }
+                        if let Some(encoder) = report.video_encoder.filter(|_| options.encoder.hw_ok) {
+                            status.push_str(&format!("\nVideo encoder: {}", encoder));
+                        }
+                        *status_clone.lock().unwrap() = status;
+                    }
+                    Err(e) => {
+                        *status_clone.lock().unwrap() = error_status(&e);
+                    }
+                }
+
+                *processing_clone.lock().unwrap() = false;
+            });
+        }
+    }
+    fn concat_ui(&mut self, ui: &mut Ui) {
+        ui.heading("Clips (in order)");
+
+        let mut to_remove = None;
+        let mut to_swap = None;
+        let count = self.concat_files.len();
+        for (idx, path) in self.concat_files.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(idx > 0, egui::Button::new("Up")).clicked() {
+                    to_swap = Some((idx - 1, idx));
+                }
+                if ui.add_enabled(idx + 1 < count, egui::Button::new("Down")).clicked() {
+                    to_swap = Some((idx, idx + 1));
+                }
+                if ui.button("Remove").clicked() {
+                    to_remove = Some(idx);
+                }
+                ui.label(format!("{}. {}", idx + 1, path.to_string_lossy()));
+            });
+        }
+
+        if let Some((a, b)) = to_swap {
+            self.concat_files.swap(a, b);
+        }
+        if let Some(idx) = to_remove {
+            self.concat_files.remove(idx);
+        }
+
+        if ui.button("Add Video Files").clicked() {
+            if let Some(paths) = rfd::FileDialog::new()
+                .add_filter("Video Files", &["mp4", "avi", "mov", "mkv", "webm"])
+                .pick_files() {
+                if self.concat_output.is_empty() {
+                    if let Some(first) = paths.first() {
+                        self.concat_output = first.with_file_name("joined.mp4").to_string_lossy().to_string();
+                    }
+                }
+                self.concat_files.extend(paths);
+            }
+        }
+
+        ui.heading("Output File");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.concat_output);
+            if ui.button("Browse").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Video Files", &["mp4", "mkv", "mov", "webm"])
+                    .save_file() {
+                    self.concat_output = path.to_string_lossy().to_string();
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Method:");
+            ui.radio_value(&mut self.concat_mode, ConcatMode::Auto, "Automatic");
+            ui.radio_value(&mut self.concat_mode, ConcatMode::Demuxer, "Stream copy (matching clips only)");
+            ui.radio_value(&mut self.concat_mode, ConcatMode::Reencode, "Re-encode");
+        });
+
+        ui.add_space(10.0);
+        let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new("Concatenate Videos"));
+        cancel_button(ui, &self.processing, &self.cancel, &self.status);
+
+        if button.clicked() {
+            if self.concat_files.len() < 2 {
+                *self.status.lock().unwrap() = "Error: Please add at least two video files.".to_string();
+                return;
+            }
+
+            if self.concat_output.is_empty() {
+                *self.status.lock().unwrap() = "Error: Please specify an output file.".to_string();
+                return;
+            }
+
+            *self.status.lock().unwrap() = "Concatenating videos...".to_string();
+            *self.processing.lock().unwrap() = true;
+
+            let inputs = self.concat_files.clone();
+            let output_file = PathBuf::from(&self.concat_output);
+            let mode = self.concat_mode;
+            let status_clone = Arc::clone(&self.status);
+            let processing_clone = Arc::clone(&self.processing);
+            let cancel = start_cancellation(&self.cancel);
+
+            thread::spawn(move || {
+                let _cancel_scope = cancellation_scope(Some(&cancel));
+                match concat_videos(&inputs, &output_file, mode) {
+                    Ok(method) => {
+                        *status_clone.lock().unwrap() = format!(
+                            "Successfully joined {} files ({}). Output: {}",
+                            inputs.len(),
+                            method,
+                            output_file.display()
+                        );
                     }
                     Err(e) => {
-                        *status_clone.lock().unwrap() = format!("Error: {}", e);
+                        *status_clone.lock().unwrap() = error_status(&e);
                     }
                 }
 
@@ -216,4 +481,4 @@ impl MergerTab {
             });
         }
     }
-}
\ No newline at end of file
+}
@@ -5,4 +5,106 @@ pub mod splitter_tab;
 pub mod merger_tab;
 pub mod batch_tab;
 pub mod profiles_tab;
-pub mod plugins_tab;
\ No newline at end of file
+pub mod plugins_tab;
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use common::{observe_progress, CancellationToken, EncoderPreference, ProgressObserverGuard, VideoToolkitError};
+use eframe::egui::{self, Ui};
+
+/// Publishes the progress of the FFmpeg runs on the current thread for the status bar,
+/// and clears it when dropped
+pub(crate) struct ProgressTracker {
+    progress: Arc<Mutex<Option<f32>>>,
+    _observer: ProgressObserverGuard,
+}
+
+impl ProgressTracker {
+    /// Track progress as a fraction of `total` seconds of output; `None` if the total is unknown
+    ///
+    /// With `sequential`, the runs write consecutive parts of the output (e.g. one clip
+    /// each) and their times add up; otherwise every run covers the whole duration.
+    pub(crate) fn start(progress: &Arc<Mutex<Option<f32>>>, total: Option<f64>, sequential: bool) -> Option<Self> {
+        let total = total.filter(|total| *total > 0.0)?;
+        let shared = Arc::clone(progress);
+        let mut done = 0.0;
+
+        let observer = observe_progress(move |report| {
+            let fraction = ((done + report.out_time) / total).clamp(0.0, 1.0) as f32;
+            *shared.lock().unwrap() = Some(fraction);
+            if report.finished && sequential {
+                done += report.out_time;
+            }
+        });
+
+        Some(Self { progress: Arc::clone(progress), _observer: observer })
+    }
+}
+
+impl Drop for ProgressTracker {
+    fn drop(&mut self) {
+        *self.progress.lock().unwrap() = None;
+    }
+}
+
+/// Token of the running operation; one slot for all tabs, as only one operation runs at a time
+pub(crate) type SharedCancel = Arc<Mutex<Option<CancellationToken>>>;
+
+/// New token for an operation that is starting, published for the Cancel buttons
+pub(crate) fn start_cancellation(slot: &SharedCancel) -> CancellationToken {
+    let token = CancellationToken::new();
+    *slot.lock().unwrap() = Some(token.clone());
+    token
+}
+
+/// Cancel button for the running operation, shown while one runs
+pub(crate) fn cancel_button(ui: &mut Ui, processing: &Mutex<bool>, slot: &SharedCancel, status: &Mutex<String>) {
+    if !*processing.lock().unwrap() {
+        return;
+    }
+    let Some(token) = slot.lock().unwrap().clone() else {
+        return;
+    };
+
+    if ui.add_enabled(!token.is_cancelled(), egui::Button::new("Cancel")).clicked() {
+        token.cancel();
+        *status.lock().unwrap() = "Cancelling...".to_string();
+    }
+}
+
+/// Open `dir` in the file manager of the platform
+pub(crate) fn open_folder(dir: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(dir).spawn().map(|_| ())
+}
+
+/// Status line for an operation that failed or was cancelled
+pub(crate) fn error_status(error: &VideoToolkitError) -> String {
+    match error {
+        VideoToolkitError::Cancelled => "Cancelled.".to_string(),
+        _ => format!("Error: {}", error),
+    }
+}
+
+/// Checkbox for re-encoding with a detected hardware encoder, disabled when the streams are copied
+pub(crate) fn hw_encoder_checkbox(ui: &mut Ui, hw_encoding: &mut bool, copy_codec: bool) {
+    ui.add_enabled(!copy_codec, egui::Checkbox::new(hw_encoding, "Hardware encoding when available"))
+        .on_hover_text("Re-encode with NVENC, VideoToolbox, Quick Sync or AMF if FFmpeg can use them; libx264 otherwise");
+}
+
+/// Encoder preference set by [`hw_encoder_checkbox`]
+pub(crate) fn encoder_preference(hw_encoding: bool) -> EncoderPreference {
+    if hw_encoding {
+        EncoderPreference::auto()
+    } else {
+        EncoderPreference::default()
+    }
+}
@@ -1,10 +1,16 @@
-use eframe::egui::{self, Ui, Grid, ScrollArea};
+use eframe::egui::{self, Color32, ComboBox, Ui, Grid, ScrollArea};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use std::thread;
+use std::time::Duration;
 
-use plugin_system::{PluginManager, PluginMetadata};
+use common::VideoToolkitError;
+use plugin_system::{
+    ExecutionContext, ParamIssue, ParameterType, PluginError, PluginManager, PluginMetadata, PluginOutput, PLUGIN_PATH_VAR,
+};
+
+use crate::tabs::{cancel_button, error_status, open_folder, start_cancellation, SharedCancel};
 
 pub struct PluginsTab {
     plugin_manager: PluginManager,
@@ -16,6 +22,12 @@ pub struct PluginsTab {
 
     // Plugin execution
     execution_parameters: Vec<(String, String)>,
+    /// Problems with the parameters of the last execution, shown next to their field
+    parameter_issues: Arc<Mutex<Vec<ParamIssue>>>,
+    /// Values and files returned by the last successful execution
+    last_output: Arc<Mutex<Option<PluginOutput>>>,
+    /// Time limit of a run in seconds, 0 for none
+    timeout_secs: u64,
 
     // Plugin loading
     plugin_path: String,
@@ -23,10 +35,12 @@ pub struct PluginsTab {
     // Status
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    progress: Arc<Mutex<Option<f32>>>,
+    cancel: SharedCancel,
 }
 
 impl PluginsTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, progress: Arc<Mutex<Option<f32>>>, cancel: SharedCancel) -> Self {
         // Create plugin manager
         let plugin_manager = match PluginManager::new() {
             Ok(pm) => pm,
@@ -46,9 +60,14 @@ impl PluginsTab {
             selected_plugin_index: None,
             plugin_directory: "plugins".to_string(),
             execution_parameters: Vec::new(),
+            parameter_issues: Arc::new(Mutex::new(Vec::new())),
+            last_output: Arc::new(Mutex::new(None)),
+            timeout_secs: 0,
             plugin_path: String::new(),
             status,
             processing,
+            progress,
+            cancel,
         }
     }
 
@@ -101,11 +120,25 @@ impl PluginsTab {
             }
         });
 
+        // Every directory discovery searches, besides the one above
+        ui.collapsing("Search Path", |ui| {
+            for dir in self.plugin_manager.plugin_directories() {
+                if dir.is_dir() {
+                    ui.label(dir.display().to_string());
+                } else {
+                    ui.weak(format!("{} (not found)", dir.display()));
+                }
+            }
+            ui.weak(format!("Add directories with {} or under \"directories\" in plugins.json.", PLUGIN_PATH_VAR));
+        });
+
         ui.separator();
 
         // Plugin list
+        let disabled = self.plugin_manager.disabled_plugins();
+        let mut toggled = None;
         ScrollArea::vertical().show(ui, |ui| {
-            if self.plugin_list.is_empty() {
+            if self.plugin_list.is_empty() && disabled.is_empty() {
                 ui.label("No plugins loaded. Use 'Discover Plugins' or 'Load Plugin' to find plugins.");
             } else {
                 ui.heading("Installed Plugins");
@@ -116,11 +149,28 @@ impl PluginsTab {
 
                 for (i, plugin) in self.plugin_list.iter().enumerate() {
                     let is_selected = current_selected == Some(i);
-                    let selection_ui = ui.selectable_label(is_selected, format!("{} v{}", plugin.name, plugin.version));
+                    ui.horizontal(|ui| {
+                        let mut enabled = true;
+                        if ui.checkbox(&mut enabled, "").on_hover_text("Disable: unload it and skip it when discovering plugins").changed() {
+                            toggled = Some((plugin.name.clone(), false));
+                        }
+                        let selection_ui = ui.selectable_label(is_selected, format!("{} v{}", plugin.name, plugin.version));
 
-                    if selection_ui.clicked() {
-                        clicked_idx = Some(i);
-                    }
+                        if selection_ui.clicked() {
+                            clicked_idx = Some(i);
+                        }
+                    });
+                }
+
+                // Disabled plugins are not loaded, so only their names are known
+                for name in &disabled {
+                    ui.horizontal(|ui| {
+                        let mut enabled = false;
+                        if ui.checkbox(&mut enabled, "").on_hover_text("Enable: load it when discovering plugins").changed() {
+                            toggled = Some((name.clone(), true));
+                        }
+                        ui.add_enabled(false, egui::Label::new(format!("{} (disabled)", name)));
+                    });
                 }
                 
                 // Handle selection changes after the loop
@@ -135,6 +185,10 @@ impl PluginsTab {
             }
         });
 
+        if let Some((name, enabled)) = toggled {
+            self.set_plugin_enabled(&name, enabled);
+        }
+
         ui.separator();
 
         // Load individual plugin
@@ -149,6 +203,11 @@ impl PluginsTab {
                     self.plugin_path = path.to_string_lossy().to_string();
                 }
             }
+            if ui.button("Script Folder…").on_hover_text("Pick the directory of a script plugin, holding its plugin.toml").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.plugin_path = path.to_string_lossy().to_string();
+                }
+            }
         });
 
         if ui.button("Load Plugin").clicked() {
@@ -166,7 +225,8 @@ impl PluginsTab {
                 ui.label(format!("Name: {} v{}", plugin.name, plugin.version));
                 ui.label(format!("Author: {}", plugin.author));
                 ui.label(format!("Description: {}", plugin.description));
-                ui.label(format!("API Version: {}", plugin.api_version));
+                ui.label(format!("Plugin API: {}", plugin.api_requirement));
+                ui.label(format!("Capabilities: {}", plugin.capabilities));
 
                 ui.separator();
 
@@ -177,6 +237,7 @@ impl PluginsTab {
                         ui.heading("Parameters");
 
                         // Use a grid for parameter editing
+                        let issues = self.parameter_issues.lock().unwrap().clone();
                         Grid::new("parameters_grid").show(ui, |ui| {
                             for (i, info) in param_info.iter().enumerate() {
                                 // Ensure we have a parameter entry for this info
@@ -190,10 +251,29 @@ impl PluginsTab {
 
                                 // Update parameter value
                                 let (_, value) = &mut self.execution_parameters[i];
-                                ui.text_edit_singleline(value);
+                                match &info.parameter_type {
+                                    ParameterType::Choice(choices) => {
+                                        ComboBox::from_id_source(("plugin_choice", &info.name))
+                                            .selected_text(if value.is_empty() { "None" } else { value.as_str() })
+                                            .show_ui(ui, |ui| {
+                                                if !info.required {
+                                                    ui.selectable_value(value, String::new(), "None");
+                                                }
+                                                for choice in choices {
+                                                    ui.selectable_value(value, choice.clone(), choice);
+                                                }
+                                            });
+                                    },
+                                    _ => {
+                                        ui.text_edit_singleline(value);
+                                    },
+                                }
 
                                 // Parameter description
                                 ui.label(&info.description);
+                                for issue in issues.iter().filter(|issue| issue.name == info.name) {
+                                    ui.colored_label(Color32::RED, &issue.message);
+                                }
                                 ui.end_row();
                             }
                         });
@@ -201,22 +281,20 @@ impl PluginsTab {
                         ui.separator();
 
                         // Execute plugin button
-                        if ui.button("Execute Plugin").clicked() {
-                            self.execute_plugin();
-                        }
+                        self.execute_controls_ui(ui);
                     } else {
                         ui.label("This plugin does not declare any parameters.");
 
                         ui.separator();
 
                         // Execute plugin button (no parameters)
-                        if ui.button("Execute Plugin").clicked() {
-                            self.execute_plugin();
-                        }
+                        self.execute_controls_ui(ui);
                     }
                 } else {
                     ui.label("Error: Unable to access plugin.");
                 }
+
+                self.output_ui(ui);
             }
         } else {
             ui.heading("Plugin Details");
@@ -224,6 +302,47 @@ impl PluginsTab {
         }
     }
 
+    /// Execute and cancel buttons, with the time limit of the run
+    fn execute_controls_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Execute Plugin").clicked() {
+                self.execute_plugin();
+            }
+            cancel_button(ui, &self.processing, &self.cancel, &self.status);
+            ui.label("Timeout:");
+            ui.add(egui::DragValue::new(&mut self.timeout_secs).clamp_range(0..=86_400).suffix(" s"))
+                .on_hover_text("Give up on a plugin still running after this long; 0 for no limit");
+        });
+    }
+
+    /// Values and files returned by the last execution, with a button opening each file's folder
+    fn output_ui(&mut self, ui: &mut Ui) {
+        let Some(output) = self.last_output.lock().unwrap().clone().filter(|output| !output.is_empty()) else {
+            return;
+        };
+
+        ui.separator();
+        ui.heading("Output");
+        Grid::new("plugin_output_grid").show(ui, |ui| {
+            for (name, value) in &output.values {
+                ui.label(format!("{}:", name));
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+        for file in &output.files {
+            ui.horizontal(|ui| {
+                ui.label(file.display().to_string());
+                let folder = file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                if ui.button("Open folder").clicked() {
+                    if let Err(e) = open_folder(folder) {
+                        *self.status.lock().unwrap() = format!("Error opening {}: {}", folder.display(), e);
+                    }
+                }
+            });
+        }
+    }
+
     fn discover_plugins(&mut self) {
         *self.status.lock().unwrap() = "Discovering plugins...".to_string();
         *self.processing.lock().unwrap() = true;
@@ -242,18 +361,26 @@ impl PluginsTab {
         let plugin_list_clone = Arc::clone(&plugin_list);
 
         thread::spawn(move || {
-            let results = plugin_manager.discover_plugins();
+            let report = plugin_manager.discover_plugins();
 
             // Count successes and failures
-            let success_count = results.iter().filter(|r| r.is_ok()).count();
-            let failure_count = results.len() - success_count;
+            let success_count = report.loaded().count();
+            let skipped_count = report.skipped().count();
+            let failures: Vec<String> = report.failed().map(ToString::to_string).collect();
 
-            // Update status
-            if failure_count > 0 {
+            // Update status, naming the first library that failed
+            if let Some(first_failure) = failures.first() {
                 *status_clone.lock().unwrap() = format!(
-                    "Discovered {} plugin(s), {} failed to load.",
+                    "Discovered {} plugin(s), {} failed to load ({}).",
                     success_count,
-                    failure_count
+                    failures.len(),
+                    first_failure
+                );
+            } else if skipped_count > 0 {
+                *status_clone.lock().unwrap() = format!(
+                    "Discovered {} plugin(s), skipped {} already loaded or incompatible.",
+                    success_count,
+                    skipped_count
                 );
             } else if success_count > 0 {
                 *status_clone.lock().unwrap() = format!("Successfully discovered {} plugin(s).", success_count);
@@ -262,9 +389,7 @@ impl PluginsTab {
             }
 
             // Update plugin list
-            *plugin_list_clone.lock().unwrap() = results.into_iter()
-                .filter_map(|r| r.ok())
-                .collect();
+            *plugin_list_clone.lock().unwrap() = report.loaded().cloned().collect();
 
             *processing_clone.lock().unwrap() = false;
         });
@@ -297,7 +422,7 @@ impl PluginsTab {
 
         thread::spawn(move || {
             match plugin_manager.load_plugin(Path::new(&path)) {
-                Ok(()) => {
+                Ok(_) => {
                     *status_clone.lock().unwrap() = "Plugin loaded successfully.".to_string();
                 },
                 Err(e) => {
@@ -318,6 +443,27 @@ impl PluginsTab {
         self.refresh_plugin_list();
     }
 
+    /// Persist the plugin's state; a disabled plugin is unloaded right away,
+    /// an enabled one is loaded by the next discovery
+    fn set_plugin_enabled(&mut self, name: &str, enabled: bool) {
+        let message = match self.plugin_manager.set_enabled(name, enabled) {
+            Ok(()) if enabled => format!("Enabled '{}'. Discover plugins to load it.", name),
+            Ok(()) => {
+                let unloaded = !self.plugin_manager.is_loaded(name) || self.plugin_manager.unload_plugin(name).is_ok();
+                self.selected_plugin_index = None;
+                self.execution_parameters.clear();
+                if unloaded {
+                    format!("Disabled '{}'.", name)
+                } else {
+                    format!("Disabled '{}'. It is still running and stays loaded for now.", name)
+                }
+            },
+            Err(e) => format!("Error: {}", e),
+        };
+        *self.status.lock().unwrap() = message;
+        self.refresh_plugin_list();
+    }
+
     fn refresh_plugin_list(&mut self) {
         self.plugin_list = self.plugin_manager.get_all_plugin_metadata();
 
@@ -333,6 +479,8 @@ impl PluginsTab {
     fn update_execution_parameters(&mut self) {
         // Clear current parameters
         self.execution_parameters.clear();
+        self.parameter_issues.lock().unwrap().clear();
+        *self.last_output.lock().unwrap() = None;
 
         // Get parameters from the plugin
         if let Some(index) = self.selected_plugin_index {
@@ -366,16 +514,51 @@ impl PluginsTab {
                 let plugin_manager = self.plugin_manager.clone();
                 let status_clone = Arc::clone(&self.status);
                 let processing_clone = Arc::clone(&self.processing);
+                let issues_clone = Arc::clone(&self.parameter_issues);
+                issues_clone.lock().unwrap().clear();
+                let output_clone = Arc::clone(&self.last_output);
+                *output_clone.lock().unwrap() = None;
+
+                // Plugins written for API version 2 report progress to the status bar
+                let progress = Arc::clone(&self.progress);
+                let (progress_clone, status_progress) = (Arc::clone(&progress), Arc::clone(&self.status));
+                let mut ctx = ExecutionContext::new()
+                    .with_cancellation(start_cancellation(&self.cancel))
+                    .with_progress(move |fraction, message| {
+                        *progress_clone.lock().unwrap() = Some(fraction);
+                        if !message.is_empty() {
+                            *status_progress.lock().unwrap() = message.to_string();
+                        }
+                    });
+                if self.timeout_secs > 0 {
+                    ctx = ctx.with_timeout(Duration::from_secs(self.timeout_secs));
+                }
 
                 thread::spawn(move || {
                     // Execute the plugin
-                    match plugin_manager.execute_plugin(&plugin_name, params) {
-                        Ok(()) => {
+                    let result = plugin_manager.execute_plugin_with_context(&plugin_name, params, &ctx);
+                    *progress.lock().unwrap() = None;
+                    match result {
+                        Ok(output) => {
                             *status_clone.lock().unwrap() = format!("Plugin '{}' executed successfully.", plugin_name);
+                            *output_clone.lock().unwrap() = Some(output);
+                        },
+                        Err(e) => match e.downcast_ref::<PluginError>() {
+                            Some(PluginError::InvalidParameters(issues)) => {
+                                *issues_clone.lock().unwrap() = issues.clone();
+                                *status_clone.lock().unwrap() = format!(
+                                    "Plugin '{}' not run: check the highlighted parameters.",
+                                    plugin_name
+                                );
+                            },
+                            Some(error @ (PluginError::Timeout(..) | PluginError::Crashed(_))) => {
+                                *status_clone.lock().unwrap() = format!("{}. The plugin may be left in a bad state; reload it if it misbehaves.", error);
+                            },
+                            _ => match e.downcast_ref::<VideoToolkitError>() {
+                                Some(error) => *status_clone.lock().unwrap() = error_status(error),
+                                None => *status_clone.lock().unwrap() = format!("Error executing plugin: {}", e),
+                            },
                         },
-                        Err(e) => {
-                            *status_clone.lock().unwrap() = format!("Error executing plugin: {}", e);
-                        }
                     }
 
                     *processing_clone.lock().unwrap() = false;
@@ -1,9 +1,13 @@
-use eframe::egui::{self, Ui, ComboBox, TextEdit};
-use std::path::Path;
+use eframe::egui::{self, Color32, RichText, Ui, ComboBox, TextEdit};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 
-use profile_system::{ProfileManager, Profile, ProfileType, ProfileError};
+use batch_processing::{BatchItemResult, BatchProcessor, BatchProgress, BatchSummary, PatternTarget};
+use profile_system::{ConflictPolicy, ProfileFormat, ProfileManager, Profile, ProfileSummary, ProfileType, ProfileError};
+
+use crate::tabs::batch_tab::{batch_progress_ui, spawn_batch_run, BatchRunState};
+use crate::tabs::{cancel_button, SharedCancel};
 
 pub struct ProfilesTab {
     profile_manager: ProfileManager,
@@ -11,21 +15,39 @@ pub struct ProfilesTab {
     // UI state
     selected_profile_type: ProfileTypeSelection,
     selected_profile_name: String,
-    available_profiles: HashMap<ProfileType, Vec<String>>,
+    available_profiles: HashMap<ProfileType, Vec<ProfileSummary>>,
+    search_query: String,
+    /// Profiles of every type matching `search_query`, updated when it changes
+    search_results: Vec<(ProfileType, ProfileSummary)>,
+    /// Name typed for a custom profile type that has no profiles yet
+    new_custom_type: String,
 
     // Profile creation/editing
     edit_mode: EditMode,
     profile_name: String,
     profile_description: String,
+    /// Comma-separated tags of the profile being created or edited
+    profile_tags: String,
     profile_parameters: Vec<(String, String)>,
 
     // Import/Export
     import_path: String,
     export_path: String,
+    /// What importing a bundle does with profiles whose name is taken
+    bundle_conflict_policy: ConflictPolicy,
+
+    // Batch run with the selected profile
+    run_inputs: Vec<PathBuf>,
+    run_recursive: bool,
+    run_pattern: String,
+    run_results: Arc<Mutex<Vec<BatchItemResult>>>,
+    run_summary: Arc<Mutex<Option<BatchSummary>>>,
+    run_progress: Arc<Mutex<BatchProgress>>,
 
     // Status
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    cancel: SharedCancel,
 }
 
 #[derive(PartialEq, Clone)]
@@ -39,6 +61,17 @@ pub enum ProfileTypeSelection {
 }
 
 impl ProfileTypeSelection {
+    fn from_profile_type(profile_type: ProfileType) -> Self {
+        match profile_type {
+            ProfileType::Clipper => ProfileTypeSelection::Clipper,
+            ProfileType::GifConverter => ProfileTypeSelection::GifConverter,
+            ProfileType::GifTransparency => ProfileTypeSelection::GifTransparency,
+            ProfileType::Splitter => ProfileTypeSelection::Splitter,
+            ProfileType::Merger => ProfileTypeSelection::Merger,
+            ProfileType::Custom(name) => ProfileTypeSelection::Custom(name),
+        }
+    }
+
     fn to_profile_type(&self) -> ProfileType {
         match self {
             ProfileTypeSelection::Clipper => ProfileType::Clipper,
@@ -67,13 +100,15 @@ enum EditMode {
     None,
     Create,
     Edit,
+    Duplicate,
     Delete,
     Import,
     Export,
+    RunBatch,
 }
 
 impl ProfilesTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, cancel: SharedCancel) -> Self {
         let profile_manager = match ProfileManager::new() {
             Ok(pm) => pm,
             Err(e) => {
@@ -84,30 +119,41 @@ impl ProfilesTab {
         };
 
         // Get available profiles
-        let available_profiles = match profile_manager.list_all_profiles() {
-            Ok(profiles) => profiles,
-            Err(_) => HashMap::new(),
-        };
+        let available_profiles = list_profile_summaries(&profile_manager).unwrap_or_default();
 
         Self {
             profile_manager,
             selected_profile_type: ProfileTypeSelection::Clipper,
             selected_profile_name: String::new(),
             available_profiles,
+            search_query: String::new(),
+            search_results: Vec::new(),
+            new_custom_type: String::new(),
             edit_mode: EditMode::None,
             profile_name: String::new(),
             profile_description: String::new(),
+            profile_tags: String::new(),
             profile_parameters: Vec::new(),
             import_path: String::new(),
             export_path: String::new(),
+            bundle_conflict_policy: ConflictPolicy::Skip,
+            run_inputs: Vec::new(),
+            run_recursive: false,
+            run_pattern: String::new(),
+            run_results: Arc::new(Mutex::new(Vec::new())),
+            run_summary: Arc::new(Mutex::new(None)),
+            run_progress: Arc::new(Mutex::new(BatchProgress::default())),
             status,
             processing,
+            cancel,
         }
     }
 
     pub fn ui(&mut self, ui: &mut Ui) {
         ui.heading("Profile Management");
 
+        self.search_ui(ui);
+
         // Profile type selection
         ui.horizontal(|ui| {
             ui.label("Profile Type:");
@@ -119,8 +165,27 @@ impl ProfilesTab {
                     ui.selectable_value(&mut self.selected_profile_type, ProfileTypeSelection::GifTransparency, "GIF Transparency");
                     ui.selectable_value(&mut self.selected_profile_type, ProfileTypeSelection::Splitter, "Splitter");
                     ui.selectable_value(&mut self.selected_profile_type, ProfileTypeSelection::Merger, "Merger");
-                    // TODO: Add custom profile types if needed
+                    // Listed only while open, so types created meanwhile show up
+                    let custom_types = self.profile_manager.list_custom_types().unwrap_or_default();
+                    if !custom_types.is_empty() {
+                        ui.separator();
+                    }
+                    for custom_type in custom_types {
+                        let label = format!("Custom: {}", custom_type);
+                        ui.selectable_value(&mut self.selected_profile_type, ProfileTypeSelection::Custom(custom_type), label);
+                    }
                 });
+
+            ui.separator();
+            ui.add(TextEdit::singleline(&mut self.new_custom_type).hint_text("New custom type").desired_width(120.0));
+            let new_type = self.new_custom_type.trim();
+            if ui.add_enabled(!new_type.is_empty(), egui::Button::new("Use"))
+                .on_hover_text("Select a new custom type; it is listed once a profile of it is saved")
+                .clicked()
+            {
+                self.selected_profile_type = ProfileTypeSelection::Custom(new_type.to_string());
+                self.new_custom_type.clear();
+            }
         });
 
         ui.separator();
@@ -130,18 +195,72 @@ impl ProfilesTab {
             EditMode::None => self.show_profile_list(ui),
             EditMode::Create => self.show_create_profile(ui),
             EditMode::Edit => self.show_edit_profile(ui),
+            EditMode::Duplicate => self.show_duplicate_profile(ui),
             EditMode::Delete => self.show_delete_profile(ui),
             EditMode::Import => self.show_import_profile(ui),
             EditMode::Export => self.show_export_profile(ui),
+            EditMode::RunBatch => self.show_run_batch(ui),
+        }
+    }
+
+    /// Search box finding profiles of every type by name, description or tag
+    fn search_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            let response = ui.add(TextEdit::singleline(&mut self.search_query).hint_text("name, description or tag"));
+            if response.changed() {
+                self.update_search();
+            }
+        });
+
+        if self.search_query.trim().is_empty() {
+            return;
+        }
+        if self.search_results.is_empty() {
+            ui.label("No profiles match.");
+        }
+
+        let mut picked = None;
+        for (profile_type, summary) in &self.search_results {
+            let mut label = format!("{}: {}", profile_type, summary.name);
+            if !summary.tags.is_empty() {
+                label.push_str(&format!("  [{}]", summary.tags.join(", ")));
+            }
+            let mut response = ui.selectable_label(self.selected_profile_name == summary.name, label);
+            if let Some(description) = &summary.description {
+                response = response.on_hover_text(description);
+            }
+            if response.clicked() {
+                picked = Some((profile_type.clone(), summary.name.clone()));
+            }
+        }
+
+        if let Some((profile_type, name)) = picked {
+            self.selected_profile_type = ProfileTypeSelection::from_profile_type(profile_type);
+            self.selected_profile_name = name;
+            self.edit_mode = EditMode::None;
+        }
+        ui.separator();
+    }
+
+    fn update_search(&mut self) {
+        if self.search_query.trim().is_empty() {
+            self.search_results.clear();
+            return;
+        }
+        match self.profile_manager.search(&self.search_query) {
+            Ok(results) => self.search_results = results,
+            Err(e) => *self.status.lock().unwrap() = format!("Error searching profiles: {}", e),
         }
     }
 
     fn show_profile_list(&mut self, ui: &mut Ui) {
         // Refresh profile list button
         if ui.button("Refresh Profile List").clicked() {
-            match self.profile_manager.list_all_profiles() {
+            match list_profile_summaries(&self.profile_manager) {
                 Ok(profiles) => {
                     self.available_profiles = profiles;
+                    self.update_search();
                     *self.status.lock().unwrap() = "Profile list refreshed.".to_string();
                 },
                 Err(e) => {
@@ -154,9 +273,11 @@ impl ProfilesTab {
         let profile_type = self.selected_profile_type.to_profile_type();
         let profiles = self.available_profiles.get(&profile_type).cloned().unwrap_or_default();
 
+        let (presets, saved): (Vec<&ProfileSummary>, Vec<&ProfileSummary>) = profiles.iter().partition(|summary| summary.builtin);
+
         // Profile selection
-        if profiles.is_empty() {
-            ui.label("No profiles available for this type.");
+        if saved.is_empty() {
+            ui.label("No saved profiles for this type.");
         } else {
             ui.label("Select a profile:");
 
@@ -167,29 +288,67 @@ impl ProfilesTab {
                     self.selected_profile_name.clone()
                 })
                 .show_ui(ui, |ui| {
-                    for name in &profiles {
-                        if ui.selectable_label(self.selected_profile_name == *name, name).clicked() {
-                            self.selected_profile_name = name.clone();
+                    for summary in &saved {
+                        let label = if summary.invalid {
+                            RichText::new(format!("{} (unreadable)", summary.name)).color(Color32::RED)
+                        } else {
+                            RichText::new(&summary.name)
+                        };
+                        let mut response = ui.selectable_label(self.selected_profile_name == summary.name, label);
+                        if let Some(description) = &summary.description {
+                            response = response.on_hover_text(description);
+                        }
+                        if response.clicked() {
+                            self.selected_profile_name = summary.name.clone();
                         }
                     }
                 });
+        }
 
-            // Show profile details if selected
-            if !self.selected_profile_name.is_empty() {
-                ui.separator();
+        // Read-only presets shipped with the toolkit
+        if !presets.is_empty() {
+            ui.separator();
+            ui.label(RichText::new("Presets").strong());
+            for summary in &presets {
+                let mut response = ui.selectable_label(self.selected_profile_name == summary.name, &summary.name);
+                if let Some(description) = &summary.description {
+                    response = response.on_hover_text(description);
+                }
+                if response.clicked() {
+                    self.selected_profile_name = summary.name.clone();
+                }
+            }
+        }
 
-                match self.profile_manager.load_profile(&self.selected_profile_name, profile_type) {
-                    Ok(profile) => {
-                        ui.heading("Profile Details");
-                        ui.label(format!("Name: {}", profile.name));
-                        if let Some(desc) = &profile.description {
-                            ui.label(format!("Description: {}", desc));
-                        }
-                        ui.label(format!("Created: {}", profile.created));
-                        ui.label(format!("Last Modified: {}", profile.last_modified));
+        // Show profile details if selected
+        let selected = profiles.iter().find(|summary| summary.name == self.selected_profile_name);
+        let read_only = selected.is_some_and(|summary| summary.builtin);
+        if let Some(summary) = selected {
+            ui.separator();
 
-                        ui.separator();
-                        ui.label("Parameters:");
+            ui.heading("Profile Details");
+            ui.label(format!("Name: {}", summary.name));
+            if summary.builtin {
+                ui.label("Built-in preset: read-only, duplicate it to make changes.");
+            }
+            if summary.invalid {
+                ui.colored_label(Color32::RED, "This profile file cannot be read.");
+            } else {
+                if let Some(desc) = &summary.description {
+                    ui.label(format!("Description: {}", desc));
+                }
+                if !summary.tags.is_empty() {
+                    ui.label(format!("Tags: {}", summary.tags.join(", ")));
+                }
+                if let (Some(created), Some(last_modified)) = (summary.created, summary.last_modified) {
+                    ui.label(format!("Created: {}", created));
+                    ui.label(format!("Last Modified: {}", last_modified));
+                }
+
+                ui.separator();
+                ui.label(format!("Parameters ({}):", summary.parameter_count));
+                match self.profile_manager.load_profile(&summary.name, profile_type) {
+                    Ok(profile) => {
                         for (key, value) in &profile.parameters {
                             ui.horizontal(|ui| {
                                 ui.label(format!("{}:", key));
@@ -212,19 +371,32 @@ impl ProfilesTab {
                 self.edit_mode = EditMode::Create;
                 self.profile_name = String::new();
                 self.profile_description = String::new();
+                self.profile_tags = String::new();
                 self.profile_parameters = vec![(String::new(), String::new())];
             }
 
             if !self.selected_profile_name.is_empty() {
-                if ui.button("Edit").clicked() {
+                if ui.add_enabled(!read_only, egui::Button::new("Edit")).clicked() {
                     // Load profile for editing
                     self.edit_mode = EditMode::Edit;
                     self.load_profile_for_editing();
                 }
 
-                if ui.button("Delete").clicked() {
+                if ui.button("Duplicate").clicked() {
+                    self.edit_mode = EditMode::Duplicate;
+                    self.profile_name = format!("{}_copy", self.selected_profile_name);
+                }
+
+                if ui.add_enabled(!read_only, egui::Button::new("Delete")).clicked() {
                     self.edit_mode = EditMode::Delete;
                 }
+
+                let runnable = !matches!(self.selected_profile_type, ProfileTypeSelection::Custom(_));
+                if ui.add_enabled(runnable, egui::Button::new("Run batch with this profile…")).clicked() {
+                    self.edit_mode = EditMode::RunBatch;
+                    self.run_results.lock().unwrap().clear();
+                    *self.run_summary.lock().unwrap() = None;
+                }
             }
         });
 
@@ -244,6 +416,35 @@ impl ProfilesTab {
                 }
             }
         });
+
+        // Bundles of all profiles
+        ui.horizontal(|ui| {
+            if ui.button("Export All…").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON Files", &["json"])
+                    .set_file_name("profiles.json")
+                    .save_file() {
+                    self.export_all(&path);
+                }
+            }
+
+            if ui.button("Import Bundle…").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON Files", &["json"])
+                    .pick_file() {
+                    self.import_bundle(&path);
+                }
+            }
+
+            ui.label("Existing names:");
+            ComboBox::from_id_source("bundle_conflict_policy")
+                .selected_text(self.bundle_conflict_policy.to_string())
+                .show_ui(ui, |ui| {
+                    for policy in [ConflictPolicy::Skip, ConflictPolicy::Overwrite, ConflictPolicy::Rename] {
+                        ui.selectable_value(&mut self.bundle_conflict_policy, policy, policy.to_string());
+                    }
+                });
+        });
     }
 
     fn show_create_profile(&mut self, ui: &mut Ui) {
@@ -282,6 +483,27 @@ impl ProfilesTab {
         });
     }
 
+    fn show_duplicate_profile(&mut self, ui: &mut Ui) {
+        ui.heading(format!("Duplicate Profile: {}", self.selected_profile_name));
+
+        ui.horizontal(|ui| {
+            ui.label("New name:");
+            ui.text_edit_singleline(&mut self.profile_name);
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Duplicate").clicked() {
+                self.duplicate_profile();
+            }
+
+            if ui.button("Cancel").clicked() {
+                self.edit_mode = EditMode::None;
+            }
+        });
+    }
+
     fn show_delete_profile(&mut self, ui: &mut Ui) {
         ui.heading("Delete Profile");
 
@@ -309,7 +531,7 @@ impl ProfilesTab {
             ui.text_edit_singleline(&mut self.import_path);
             if ui.button("Browse").clicked() {
                 if let Some(path) = rfd::FileDialog::new()
-                    .add_filter("JSON Files", &["json"])
+                    .add_filter("Profile Files", &["json", "toml", "yaml", "yml"])
                     .pick_file() {
                     self.import_path = path.to_string_lossy().to_string();
                 }
@@ -340,11 +562,13 @@ impl ProfilesTab {
             if ui.button("Browse").clicked() {
                 if let Some(path) = rfd::FileDialog::new()
                     .add_filter("JSON Files", &["json"])
+                    .add_filter("TOML Files", &["toml"])
+                    .add_filter("YAML Files", &["yaml", "yml"])
                     .save_file() {
                     self.export_path = path.to_string_lossy().to_string();
 
-                    // Add .json extension if missing
-                    if !self.export_path.ends_with(".json") {
+                    // Add .json extension if there is no known one
+                    if ProfileFormat::from_path(&path).is_none() {
                         self.export_path.push_str(".json");
                     }
                 }
@@ -364,6 +588,106 @@ impl ProfilesTab {
         });
     }
 
+    fn show_run_batch(&mut self, ui: &mut Ui) {
+        ui.heading(format!("Run Batch: {}", self.selected_profile_name));
+
+        // Input files
+        ui.label("Input files:");
+        let mut to_remove = None;
+        for (idx, path) in self.run_inputs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}. {}", idx + 1, path.to_string_lossy()));
+                if ui.button("Remove").clicked() {
+                    to_remove = Some(idx);
+                }
+            });
+        }
+
+        if let Some(idx) = to_remove {
+            self.run_inputs.remove(idx);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add Files").clicked() {
+                if let Some(paths) = rfd::FileDialog::new().pick_files() {
+                    self.run_inputs.extend(paths);
+                }
+            }
+
+            if ui.button("Add Directory").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.run_inputs.push(path);
+                }
+            }
+        });
+
+        ui.checkbox(&mut self.run_recursive, "Process directories recursively");
+        ui.horizontal(|ui| {
+            ui.label("Filename pattern (regex):");
+            ui.text_edit_singleline(&mut self.run_pattern);
+        });
+
+        ui.separator();
+
+        let processing = *self.processing.lock().unwrap();
+        batch_progress_ui(ui, processing, &self.run_progress, &self.run_results, &self.run_summary);
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!processing, egui::Button::new("Run")).clicked() {
+                self.run_batch();
+            }
+            cancel_button(ui, &self.processing, &self.cancel, &self.status);
+
+            if ui.button("Back").clicked() {
+                self.edit_mode = EditMode::None;
+            }
+        });
+    }
+
+    fn run_batch(&mut self) {
+        if self.run_inputs.is_empty() {
+            *self.status.lock().unwrap() = "Error: Please add at least one input file or directory.".to_string();
+            return;
+        }
+
+        let profile_type = self.selected_profile_type.to_profile_type();
+        let profile = match self.profile_manager.load_profile(&self.selected_profile_name, profile_type) {
+            Ok(profile) => profile,
+            Err(e) => {
+                *self.status.lock().unwrap() = format!("Error loading profile: {}", e);
+                return;
+            }
+        };
+
+        // Settings errors are reported before any file is touched
+        let processor = BatchProcessor::from_profile(&profile).and_then(|processor| {
+            let processor = processor.with_recursive(self.run_recursive);
+            match self.run_pattern.trim() {
+                "" => Ok(processor),
+                pattern => processor.with_pattern(pattern, PatternTarget::FileName),
+            }
+        });
+
+        let processor = match processor {
+            Ok(processor) => processor,
+            Err(e) => {
+                *self.status.lock().unwrap() = format!("Error: {}", e);
+                return;
+            }
+        };
+
+        let _ = self.profile_manager.record_usage(&profile.name, profile.profile_type.clone());
+        *self.status.lock().unwrap() = format!("Starting batch processing with profile '{}'...", profile.name);
+        spawn_batch_run(processor, self.run_inputs.clone(), BatchRunState {
+            status: Arc::clone(&self.status),
+            processing: Arc::clone(&self.processing),
+            results: Arc::clone(&self.run_results),
+            summary: Arc::clone(&self.run_summary),
+            progress: Arc::clone(&self.run_progress),
+            cancel: Arc::clone(&self.cancel),
+        });
+    }
+
     fn profile_edit_form(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             ui.label("Name:");
@@ -375,6 +699,11 @@ impl ProfilesTab {
             ui.text_edit_singleline(&mut self.profile_description);
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Tags:");
+            ui.add(TextEdit::singleline(&mut self.profile_tags).hint_text("twitter, 720p"));
+        });
+
         ui.separator();
         ui.label("Parameters:");
 
@@ -404,6 +733,15 @@ impl ProfilesTab {
         if ui.button("Add Parameter").clicked() {
             self.profile_parameters.push((String::new(), String::new()));
         }
+
+        // Problems that would stop the profile from being saved
+        let profile = Profile::new(&self.profile_name, self.selected_profile_type.to_profile_type(), self.build_parameters_map());
+        if let Err(issues) = ProfileManager::validate(&profile) {
+            ui.label(RichText::new(format!("Parameter problems ({}):", issues.len())).color(Color32::RED));
+            for issue in issues {
+                ui.label(RichText::new(format!("  • {}", issue)).color(Color32::RED));
+            }
+        }
     }
 
     fn load_profile_for_editing(&mut self) {
@@ -413,6 +751,7 @@ impl ProfilesTab {
             Ok(profile) => {
                 self.profile_name = profile.name.clone();
                 self.profile_description = profile.description.unwrap_or_default();
+                self.profile_tags = profile.tags.join(", ");
 
                 // Convert parameters to vector of key-value pairs
                 self.profile_parameters = profile.parameters
@@ -449,6 +788,7 @@ impl ProfilesTab {
         if !self.profile_description.is_empty() {
             profile = profile.with_description(&self.profile_description);
         }
+        profile = profile.with_tags(self.profile_tags.split(','));
 
         // Save profile
         match self.profile_manager.save_profile(&profile) {
@@ -457,9 +797,10 @@ impl ProfilesTab {
                 self.edit_mode = EditMode::None;
 
                 // Update available profiles
-                if let Ok(profiles) = self.profile_manager.list_all_profiles() {
+                if let Ok(profiles) = list_profile_summaries(&self.profile_manager) {
                     self.available_profiles = profiles;
                 }
+                self.update_search();
 
                 self.selected_profile_name = self.profile_name.clone();
             },
@@ -479,46 +820,61 @@ impl ProfilesTab {
         // Convert parameters to HashMap
         let parameters = self.build_parameters_map();
 
-        // Create updated profile
+        // Save the changes under the current name, then rename if the name changed
         let profile_type = self.selected_profile_type.to_profile_type();
-        let profile_type_for_delete = profile_type.clone();
-        let mut profile = Profile::new(&self.profile_name, profile_type, parameters);
+        let mut profile = Profile::new(&self.selected_profile_name, profile_type.clone(), parameters);
 
         if !self.profile_description.is_empty() {
             profile = profile.with_description(&self.profile_description);
         }
+        profile = profile.with_tags(self.profile_tags.split(','));
 
-        // If name changed, delete old profile first
-        if self.profile_name != self.selected_profile_name {
-            let _ = self.profile_manager.delete_profile(&self.selected_profile_name, profile_type_for_delete);
-
-            // Save new profile
-            match self.profile_manager.save_profile(&profile) {
-                Ok(()) => {
-                    *self.status.lock().unwrap() = format!("Profile '{}' updated successfully.", self.profile_name);
-                    self.edit_mode = EditMode::None;
+        let result = self.profile_manager.update_profile(&profile).and_then(|()| {
+            self.profile_manager.rename_profile(&self.selected_profile_name, &self.profile_name, profile_type)
+        });
 
-                    // Update available profiles
-                    if let Ok(profiles) = self.profile_manager.list_all_profiles() {
-                        self.available_profiles = profiles;
-                    }
+        match result {
+            Ok(()) => {
+                *self.status.lock().unwrap() = format!("Profile '{}' updated successfully.", self.profile_name);
+                self.edit_mode = EditMode::None;
 
-                    self.selected_profile_name = self.profile_name.clone();
-                },
-                Err(e) => {
-                    *self.status.lock().unwrap() = format!("Error updating profile: {}", e);
+                // Update available profiles
+                if let Ok(profiles) = list_profile_summaries(&self.profile_manager) {
+                    self.available_profiles = profiles;
                 }
+                self.update_search();
+
+                self.selected_profile_name = self.profile_name.clone();
+            },
+            Err(e) => {
+                *self.status.lock().unwrap() = format!("Error updating profile: {}", e);
             }
-        } else {
-            // Update existing profile
-            match self.profile_manager.update_profile(&profile) {
-                Ok(()) => {
-                    *self.status.lock().unwrap() = format!("Profile '{}' updated successfully.", self.profile_name);
-                    self.edit_mode = EditMode::None;
-                },
-                Err(e) => {
-                    *self.status.lock().unwrap() = format!("Error updating profile: {}", e);
+        }
+    }
+
+    fn duplicate_profile(&mut self) {
+        if self.profile_name.is_empty() {
+            *self.status.lock().unwrap() = "Error: Profile name cannot be empty.".to_string();
+            return;
+        }
+
+        let profile_type = self.selected_profile_type.to_profile_type();
+
+        match self.profile_manager.duplicate_profile(&self.selected_profile_name, profile_type, &self.profile_name) {
+            Ok(copy) => {
+                *self.status.lock().unwrap() = format!("Profile '{}' duplicated as '{}'.", self.selected_profile_name, copy.name);
+                self.edit_mode = EditMode::None;
+
+                // Update available profiles
+                if let Ok(profiles) = list_profile_summaries(&self.profile_manager) {
+                    self.available_profiles = profiles;
                 }
+                self.update_search();
+
+                self.selected_profile_name = copy.name;
+            },
+            Err(e) => {
+                *self.status.lock().unwrap() = format!("Error duplicating profile: {}", e);
             }
         }
     }
@@ -532,9 +888,10 @@ impl ProfilesTab {
                 self.edit_mode = EditMode::None;
 
                 // Update available profiles
-                if let Ok(profiles) = self.profile_manager.list_all_profiles() {
+                if let Ok(profiles) = list_profile_summaries(&self.profile_manager) {
                     self.available_profiles = profiles;
                 }
+                self.update_search();
 
                 self.selected_profile_name = String::new();
             },
@@ -556,19 +913,13 @@ impl ProfilesTab {
                 self.edit_mode = EditMode::None;
 
                 // Update available profiles
-                if let Ok(profiles) = self.profile_manager.list_all_profiles() {
+                if let Ok(profiles) = list_profile_summaries(&self.profile_manager) {
                     self.available_profiles = profiles;
                 }
+                self.update_search();
 
                 // Select the imported profile
-                self.selected_profile_type = match profile.profile_type {
-                    ProfileType::Clipper => ProfileTypeSelection::Clipper,
-                    ProfileType::GifConverter => ProfileTypeSelection::GifConverter,
-                    ProfileType::GifTransparency => ProfileTypeSelection::GifTransparency,
-                    ProfileType::Splitter => ProfileTypeSelection::Splitter,
-                    ProfileType::Merger => ProfileTypeSelection::Merger,
-                    ProfileType::Custom(name) => ProfileTypeSelection::Custom(name),
-                };
+                self.selected_profile_type = ProfileTypeSelection::from_profile_type(profile.profile_type);
 
                 self.selected_profile_name = profile.name;
             },
@@ -597,6 +948,38 @@ impl ProfilesTab {
         }
     }
 
+    fn export_all(&mut self, path: &Path) {
+        *self.status.lock().unwrap() = match self.profile_manager.export_all(path) {
+            Ok(count) => format!("{} profile(s) exported to {}.", count, path.display()),
+            Err(e) => format!("Error exporting profiles: {}", e),
+        };
+    }
+
+    fn import_bundle(&mut self, path: &Path) {
+        let report = match self.profile_manager.import_bundle(path, self.bundle_conflict_policy) {
+            Ok(report) => report,
+            Err(e) => {
+                *self.status.lock().unwrap() = format!("Error importing profiles: {}", e);
+                return;
+            }
+        };
+
+        let mut status = format!(
+            "{} imported, {} renamed, {} skipped, {} failed.",
+            report.imported.len(), report.renamed.len(), report.skipped.len(), report.failed.len()
+        );
+        for (name, error) in &report.failed {
+            status.push_str(&format!("\n'{}': {}", name, error));
+        }
+        *self.status.lock().unwrap() = status;
+
+        // Update available profiles
+        if let Ok(profiles) = list_profile_summaries(&self.profile_manager) {
+            self.available_profiles = profiles;
+        }
+        self.update_search();
+    }
+
     fn build_parameters_map(&self) -> HashMap<String, String> {
         let mut parameters = HashMap::new();
 
@@ -608,4 +991,15 @@ impl ProfilesTab {
 
         parameters
     }
-}
\ No newline at end of file
+}
+
+/// Summaries of the saved profiles of every type, each profile file read once
+fn list_profile_summaries(manager: &ProfileManager) -> Result<HashMap<ProfileType, Vec<ProfileSummary>>, ProfileError> {
+    manager.list_all_profiles()?
+        .into_keys()
+        .map(|profile_type| {
+            let summaries = manager.list_profiles_detailed(profile_type.clone())?;
+            Ok((profile_type, summaries))
+        })
+        .collect()
+}
@@ -1,8 +1,26 @@
 use eframe::egui::{self, Ui};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use splitter::split_video;
+use common::{cancellation_scope, format_warnings, get_duration, get_supported_formats};
+use splitter::{
+    merge_grid, merge_slices, parse_dimensions, parse_encode_options, split_by_duration, split_video_grid, split_video_with_options,
+    GridSize, LabelCorner, LabelStyle, SliceDirection, SplitOptions
+};
+
+use super::{
+    cancel_button, encoder_preference, error_status, hw_encoder_checkbox, start_cancellation, ProgressTracker,
+    SharedCancel
+};
+
+/// How the video is cut
+#[derive(PartialEq, Clone, Copy)]
+enum SplitMode {
+    Slices,
+    Grid,
+    Segments,
+}
 
 pub struct SplitterTab {
     input_file: String,
@@ -10,26 +28,68 @@ pub struct SplitterTab {
     prefix: String,
     encode_options: String,
     force: bool,
+    num_slices: u32,
+    direction: SliceDirection,
+    overlap_px: u32,
+    mode: SplitMode,
+    grid_rows: u32,
+    grid_cols: u32,
+    segment_seconds: f64,
+    segment_copy_codec: bool,
+    output_format: String,
+    check_dimensions: bool,
+    expected_dimensions: String,
+    merge_inputs: Vec<PathBuf>,
+    merge_output: String,
+    merge_grid: bool,
+    label_slices: bool,
+    label_template: String,
+    label_size: f32,
+    label_corner: LabelCorner,
+    hw_encoding: bool,
     status: Arc<Mutex<String>>,
     processing: Arc<Mutex<bool>>,
+    progress: Arc<Mutex<Option<f32>>>,
+    cancel: SharedCancel,
 }
 
 impl SplitterTab {
-    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>) -> Self {
+    pub fn new(status: Arc<Mutex<String>>, processing: Arc<Mutex<bool>>, progress: Arc<Mutex<Option<f32>>>, cancel: SharedCancel) -> Self {
         Self {
             input_file: String::new(),
             output_dir: String::from("output_slices"),
             prefix: String::from("slice"),
             encode_options: String::new(),
             force: false,
+            num_slices: 5,
+            direction: SliceDirection::Vertical,
+            overlap_px: 0,
+            mode: SplitMode::Slices,
+            grid_rows: 3,
+            grid_cols: 3,
+            segment_seconds: 600.0,
+            segment_copy_codec: true,
+            output_format: String::from("mp4"),
+            check_dimensions: false,
+            expected_dimensions: String::from("1920x1080"),
+            merge_inputs: Vec::new(),
+            merge_output: String::from("merged.mp4"),
+            merge_grid: false,
+            label_slices: false,
+            label_template: String::from("{index}"),
+            label_size: 0.5,
+            label_corner: LabelCorner::TopLeft,
+            hw_encoding: false,
             status,
             processing,
+            progress,
+            cancel,
         }
     }
 
     pub fn ui(&mut self, ui: &mut Ui) {
         // Input file section
-        ui.heading("Input Video (should be 1920x1080)");
+        ui.heading("Input Video");
         ui.horizontal(|ui| {
             ui.text_edit_singleline(&mut self.input_file);
             if ui.button("Browse").clicked() {
@@ -66,60 +126,336 @@ impl SplitterTab {
             ui.text_edit_singleline(&mut self.prefix);
         });
 
+        if self.mode == SplitMode::Slices {
+            ui.horizontal(|ui| {
+                ui.label("Output Format:");
+                egui::ComboBox::from_id_source("splitter_format")
+                    .selected_text(&self.output_format)
+                    .show_ui(ui, |ui| {
+                        for format in get_supported_formats("splitter") {
+                            ui.selectable_value(&mut self.output_format, format.clone(), format);
+                        }
+                    });
+            });
+        }
+
         ui.horizontal(|ui| {
             ui.label("Custom Encode Options:");
             ui.text_edit_singleline(&mut self.encode_options);
             ui.label("(advanced users only)");
         });
 
-        ui.checkbox(&mut self.force, "Force (process even if video dimensions are not 1920x1080)");
+        ui.horizontal(|ui| {
+            ui.label("Mode:");
+            ui.radio_value(&mut self.mode, SplitMode::Slices, "Slices");
+            ui.radio_value(&mut self.mode, SplitMode::Grid, "Grid (video wall)");
+            ui.radio_value(&mut self.mode, SplitMode::Segments, "Time segments");
+        });
+
+        if self.mode == SplitMode::Segments {
+            ui.horizontal(|ui| {
+                ui.label("Segment Duration:");
+                ui.add(egui::DragValue::new(&mut self.segment_seconds).clamp_range(1.0..=86400.0).suffix(" s"));
+            });
+            ui.checkbox(&mut self.segment_copy_codec, "Copy codec (faster, cuts at the nearest keyframe)");
+        } else if self.mode == SplitMode::Grid {
+            ui.checkbox(&mut self.force, "Force (allow an uneven last row/column)");
+
+            ui.horizontal(|ui| {
+                ui.label("Grid:");
+                ui.add(egui::DragValue::new(&mut self.grid_rows).clamp_range(1..=16).suffix(" rows"));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut self.grid_cols).clamp_range(1..=16).suffix(" columns"));
+            });
+        } else {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.check_dimensions, "Require dimensions:");
+                ui.add_enabled(
+                    self.check_dimensions,
+                    egui::TextEdit::singleline(&mut self.expected_dimensions).desired_width(90.0)
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Slices:");
+                ui.add(egui::DragValue::new(&mut self.num_slices).clamp_range(1..=64));
+                egui::ComboBox::from_id_source("slice_direction")
+                    .selected_text(format!("{:?}", self.direction))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.direction, SliceDirection::Vertical, "Vertical (columns)");
+                        ui.selectable_value(&mut self.direction, SliceDirection::Horizontal, "Horizontal (rows)");
+                    });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Overlap:");
+                ui.add(egui::DragValue::new(&mut self.overlap_px).clamp_range(0..=512).suffix(" px"));
+                ui.label("(shared with each neighbouring slice, for edge blending)");
+            });
+
+            // Custom encode options choose their own encoder
+            hw_encoder_checkbox(ui, &mut self.hw_encoding, !self.encode_options.trim().is_empty());
+
+            ui.checkbox(&mut self.label_slices, "Label slices (burn slice number into each output)");
+            if self.label_slices {
+                ui.horizontal(|ui| {
+                    ui.label("Label Text:");
+                    ui.text_edit_singleline(&mut self.label_template);
+                    ui.label("({index} = slice number, {x}/{y} = slice offset)");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Label Size:");
+                    ui.add(egui::Slider::new(&mut self.label_size, 0.1..=1.0).text("of slice's shorter side"));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Label Corner:");
+                    egui::ComboBox::from_id_source("label_corner")
+                        .selected_text(format!("{:?}", self.label_corner))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.label_corner, LabelCorner::TopLeft, "TopLeft");
+                            ui.selectable_value(&mut self.label_corner, LabelCorner::TopRight, "TopRight");
+                            ui.selectable_value(&mut self.label_corner, LabelCorner::BottomLeft, "BottomLeft");
+                            ui.selectable_value(&mut self.label_corner, LabelCorner::BottomRight, "BottomRight");
+                            ui.selectable_value(&mut self.label_corner, LabelCorner::Center, "Center");
+                        });
+                });
+            }
+        }
 
         // Execute button
         ui.add_space(10.0);
         let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new("Split Video"));
+        cancel_button(ui, &self.processing, &self.cancel, &self.status);
 
         if button.clicked() {
             if self.input_file.is_empty() {
                 *self.status.lock().unwrap() = "Error: Please select an input video file.".to_string();
+            } else {
+                self.start_split();
+            }
+        }
+
+        self.merge_ui(ui);
+    }
+
+    /// Split the selected video in a background thread
+    fn start_split(&self) {
+        let expected_dimensions = if self.mode == SplitMode::Slices && self.check_dimensions {
+            match parse_dimensions(&self.expected_dimensions) {
+                Ok(dimensions) => Some(dimensions),
+                Err(e) => {
+                    *self.status.lock().unwrap() = format!("Error: {}", e);
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        // Segments are cut with their own codec settings
+        if self.mode != SplitMode::Segments && !self.encode_options.is_empty() {
+            if let Err(e) = parse_encode_options(&self.encode_options) {
+                *self.status.lock().unwrap() = format!("Error: {}", e);
                 return;
             }
+        }
 
-            // Start processing in a separate thread
-            *self.status.lock().unwrap() = "Processing video split...".to_string();
-            *self.processing.lock().unwrap() = true;
-
-            // Clone values for thread
-            let input_file = self.input_file.clone();
-            let output_dir = self.output_dir.clone();
-            let prefix = self.prefix.clone();
-            let encode_options = if self.encode_options.is_empty() { None } else { Some(self.encode_options.clone()) };
-            let force = self.force;
-            let status_clone = Arc::clone(&self.status);
-            let processing_clone = Arc::clone(&self.processing);
-
-            thread::spawn(move || {
-                let result = split_video(
-                    &input_file,
-                    &output_dir,
-                    &prefix,
-                    encode_options.as_deref(),
-                    force
-                );
+        // Start processing in a separate thread
+        *self.status.lock().unwrap() = "Processing video split...".to_string();
+        *self.processing.lock().unwrap() = true;
+
+        // Clone values for thread
+        let input_file = self.input_file.clone();
+        let output_dir = self.output_dir.clone();
+        let prefix = self.prefix.clone();
+        let encode_options = if self.encode_options.is_empty() { None } else { Some(self.encode_options.clone()) };
+        let force = self.force;
+        let num_slices = self.num_slices;
+        let mode = self.mode;
+        let (rows, cols) = (self.grid_rows, self.grid_cols);
+        let (segment_seconds, segment_copy_codec) = (self.segment_seconds, self.segment_copy_codec);
+        let options = SplitOptions {
+            num_slices,
+            direction: self.direction,
+            expected_dimensions,
+            output_format: self.output_format.clone(),
+            label_slices: self.label_slices,
+            label_style: LabelStyle {
+                template: self.label_template.clone(),
+                size_ratio: self.label_size,
+                corner: self.label_corner,
+            },
+            overlap_px: self.overlap_px,
+            encoder: encoder_preference(self.hw_encoding),
+            // The worker's cancellation scope covers every mode
+            ..SplitOptions::default()
+        };
+        let status_clone = Arc::clone(&self.status);
+        let processing_clone = Arc::clone(&self.processing);
+        let progress = Arc::clone(&self.progress);
+        let cancel = start_cancellation(&self.cancel);
 
-                match result {
-                    Ok(true) => {
-                        *status_clone.lock().unwrap() = format!("Successfully split video into 5 slices. Files saved in: {}", output_dir);
+        thread::spawn(move || {
+            let _cancel_scope = cancellation_scope(Some(&cancel));
+            // Segments come out of one run; every grid tile is a run over the whole video
+            let duration = get_duration(&input_file).ok();
+            let _tracker = match mode {
+                SplitMode::Segments => ProgressTracker::start(&progress, duration, false),
+                SplitMode::Grid => ProgressTracker::start(&progress, duration.map(|d| d * (rows * cols) as f64), true),
+                SplitMode::Slices => None,
+            };
+
+            let status = match mode {
+                SplitMode::Segments => {
+                    match split_by_duration(&input_file, &output_dir, &prefix, segment_seconds, segment_copy_codec) {
+                        Ok(segments) => format!(
+                            "Successfully cut video into {} segments. Files saved in: {}",
+                            segments.len(), output_dir
+                        ),
+                        Err(e) => error_status(&e),
                     }
-                    Ok(false) => {
-                        *status_clone.lock().unwrap() = "Completed with some errors.".to_string();
+                }
+                SplitMode::Grid => {
+                    let result = split_video_grid(
+                        &input_file,
+                        &output_dir,
+                        &prefix,
+                        rows,
+                        cols,
+                        encode_options.as_deref(),
+                        force
+                    );
+
+                    match result {
+                        Ok(tiles) => {
+                            let failed: Vec<String> = tiles.iter()
+                                .filter_map(|tile| {
+                                    let error = tile.error.as_ref()?;
+                                    Some(format!("  r{} c{}: {}", tile.row, tile.col, error))
+                                })
+                                .collect();
+                            if failed.is_empty() {
+                                format!("Successfully split video into a {}x{} grid. Files saved in: {}", rows, cols, output_dir)
+                            } else {
+                                format!("Failed to create {}/{} tiles:\n{}", failed.len(), tiles.len(), failed.join("\n"))
+                            }
+                        }
+                        Err(e) => error_status(&e),
                     }
-                    Err(e) => {
-                        *status_clone.lock().unwrap() = format!("Error: {}", e);
+                }
+                SplitMode::Slices => {
+                    let result = split_video_with_options(
+                        &input_file,
+                        &output_dir,
+                        &prefix,
+                        encode_options.as_deref(),
+                        false,
+                        &options
+                    );
+
+                    match result {
+                        Ok(report) => {
+                            let mut status = if report.success {
+                                format!("Successfully split video into {} slices. Files saved in: {}", num_slices, output_dir)
+                            } else {
+                                "Completed with some errors.".to_string()
+                            };
+                            if options.overlap_px > 0 {
+                                for (i, slice) in report.slices.iter().enumerate() {
+                                    status.push_str(&format!(
+                                        "\n  Slice {}: x={}, y={}, {}x{}",
+                                        i + 1, slice.x, slice.y, slice.width, slice.height
+                                    ));
+                                }
+                            }
+                            if let Some(encoder) = report.video_encoder.filter(|_| options.encoder.hw_ok) {
+                                status.push_str(&format!("\n  Encoder: {}", encoder));
+                            }
+                            match format_warnings(&report.warnings) {
+                                Some(warnings) => format!("{}\n{}", status, warnings),
+                                None => status,
+                            }
+                        }
+                        Err(e) => error_status(&e),
                     }
                 }
+            };
+
+            *status_clone.lock().unwrap() = status;
+            *processing_clone.lock().unwrap() = false;
+        });
+    }
 
-                *processing_clone.lock().unwrap() = false;
+    /// Reassemble slices into a single preview video
+    fn merge_ui(&mut self, ui: &mut Ui) {
+        ui.add_space(10.0);
+        egui::CollapsingHeader::new("Reassemble Slices").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Select Slices").clicked() {
+                    if let Some(paths) = rfd::FileDialog::new()
+                        .add_filter("Video Files", &["mp4", "avi", "mov", "mkv"])
+                        .pick_files() {
+                        self.merge_inputs = paths;
+                        self.merge_inputs.sort();
+                    }
+                }
+                ui.label(format!("{} slices selected (merged in file name order)", self.merge_inputs.len()));
             });
-        }
+
+            ui.horizontal(|ui| {
+                ui.label("Output File:");
+                ui.text_edit_singleline(&mut self.merge_output);
+                if ui.button("Browse").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("MP4", &["mp4"])
+                        .save_file() {
+                        self.merge_output = path.to_string_lossy().to_string();
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Layout:");
+                ui.radio_value(&mut self.merge_grid, false, format!("{:?} slices", self.direction));
+                ui.radio_value(&mut self.merge_grid, true, format!("{}x{} grid", self.grid_rows, self.grid_cols));
+            });
+            ui.label("(slice direction and grid size are taken from the options above)");
+
+            let button = ui.add_enabled(!*self.processing.lock().unwrap(), egui::Button::new("Merge Slices"));
+            cancel_button(ui, &self.processing, &self.cancel, &self.status);
+            if button.clicked() {
+                if self.merge_inputs.len() < 2 || self.merge_output.trim().is_empty() {
+                    *self.status.lock().unwrap() = "Error: Please select at least two slices and an output file.".to_string();
+                    return;
+                }
+
+                *self.status.lock().unwrap() = "Merging slices...".to_string();
+                *self.processing.lock().unwrap() = true;
+
+                let inputs = self.merge_inputs.clone();
+                let output = PathBuf::from(self.merge_output.trim());
+                let grid = self.merge_grid.then_some(GridSize { rows: self.grid_rows, cols: self.grid_cols });
+                let direction = self.direction;
+                let status_clone = Arc::clone(&self.status);
+                let processing_clone = Arc::clone(&self.processing);
+                let cancel = start_cancellation(&self.cancel);
+
+                thread::spawn(move || {
+                    let _cancel_scope = cancellation_scope(Some(&cancel));
+                    let result = match grid {
+                        Some(grid) => merge_grid(&inputs, &output, grid, None),
+                        None => merge_slices(&inputs, &output, direction, None),
+                    };
+
+                    *status_clone.lock().unwrap() = match result {
+                        Ok(()) => format!("Successfully merged slices. Output: {}", output.display()),
+                        Err(e) => error_status(&e),
+                    };
+                    *processing_clone.lock().unwrap() = false;
+                });
+            }
+        });
     }
-}
\ No newline at end of file
+}
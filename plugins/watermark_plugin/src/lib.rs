@@ -3,9 +3,131 @@ use std::collections::HashMap;
 use std::process::Command;
 use std::path::Path;
 
-use plugin_system::{Plugin, PluginMetadata, ParameterInfo, ParameterType, PLUGIN_API_VERSION};
+use common::{execute_ffmpeg_streaming, filter_graph, get_duration, get_video_dimensions, Filter, FilterChain};
+use plugin_system::{
+    ApiRequirement, ExecutionContext, Plugin, PluginCapabilities, PluginMetadata, PluginOutput, ParameterInfo, ParameterType,
+    PLUGIN_API_VERSION,
+};
 
-/// Watermark Plugin - Adds a text watermark to videos
+/// Watermark positions, as accepted by the `position` parameter
+const POSITIONS: [&str; 5] = ["top_left", "top_right", "bottom_left", "bottom_right", "center"];
+
+/// Colors offered for the `font_color` parameter
+const FONT_COLORS: [&str; 8] = ["white", "black", "gray", "red", "green", "blue", "yellow", "orange"];
+
+fn choices(values: &[&str]) -> ParameterType {
+    ParameterType::Choice(values.iter().map(|value| value.to_string()).collect())
+}
+
+/// What the watermark draws
+#[derive(Debug, PartialEq)]
+enum WatermarkMode<'a> {
+    Text(&'a str),
+    /// Path of the image
+    Image(&'a str),
+}
+
+/// Choose between `watermark_text` and `watermark_image`; exactly one must be given
+fn watermark_mode(params: &HashMap<String, String>) -> Result<WatermarkMode<'_>, String> {
+    let given = |name: &str| params.get(name).map(String::as_str).filter(|value| !value.is_empty());
+    match (given("watermark_text"), given("watermark_image")) {
+        (Some(text), None) => Ok(WatermarkMode::Text(text)),
+        (None, Some(image)) => Ok(WatermarkMode::Image(image)),
+        (Some(_), Some(_)) => Err("Give either watermark_text or watermark_image, not both".to_string()),
+        (None, None) => Err("Give watermark_text for a text watermark or watermark_image for a logo".to_string()),
+    }
+}
+
+/// Coordinates of a watermark `width` by `height` (FFmpeg expressions) at `position`,
+/// `margin` pixels from the edges
+fn position_coordinates(position: &str, margin: u32, width: &str, height: &str) -> (String, String) {
+    let left = margin.to_string();
+    let right = format!("main_w-{}-{}", width, margin);
+    let top = margin.to_string();
+    let bottom = format!("main_h-{}-{}", height, margin);
+    match position {
+        "top_left" => (left, top),
+        "top_right" => (right, top),
+        "bottom_left" => (left, bottom),
+        "center" => (format!("main_w/2-{}/2", width), format!("main_h/2-{}/2", height)),
+        _ => (right, bottom),  // Default to bottom right
+    }
+}
+
+/// How the text of a text watermark looks
+struct TextStyle<'a> {
+    font_size: &'a str,
+    font_color: &'a str,
+    /// From 0 (invisible) to 1 (opaque)
+    opacity: f32,
+    font_file: Option<&'a str>,
+    /// Color of the box drawn behind the text, if any, e.g. `black@0.5`
+    box_color: Option<&'a str>,
+    /// Width of the box around the text, in pixels
+    box_border: u32,
+}
+
+/// Build the drawtext filter for a watermark, escaping the user-provided values
+fn build_drawtext_filter(text: &str, style: &TextStyle, position: &str, margin: u32) -> String {
+    let (x, y) = position_coordinates(position, margin, "text_w", "text_h");
+
+    let mut filter = Filter::new("drawtext");
+    if let Some(font_file) = style.font_file {
+        filter = filter.option("fontfile", font_file);
+    }
+    let font_color = if style.opacity < 1.0 {
+        format!("{}@{}", style.font_color, style.opacity)
+    } else {
+        style.font_color.to_string()
+    };
+
+    // No %{...} expansion, so the text is drawn exactly as given
+    filter = filter
+        .option("text", text)
+        .option("expansion", "none")
+        .option("fontsize", style.font_size)
+        .option("fontcolor", font_color);
+    if let Some(box_color) = style.box_color {
+        filter = filter.option("box", 1).option("boxcolor", box_color).option("boxborderw", style.box_border);
+    }
+    filter.option("x", x).option("y", y).to_string()
+}
+
+/// Build the filtergraph laying the image of the second input over the video,
+/// `logo_width` pixels wide if given, with its output labelled `video`
+fn build_overlay_graph(position: &str, margin: u32, opacity: f32, logo_width: Option<u32>) -> String {
+    let (x, y) = position_coordinates(position, margin, "overlay_w", "overlay_h");
+
+    let mut logo = FilterChain::new().input("1:v").filter(Filter::new("format").arg("rgba"));
+    if let Some(width) = logo_width {
+        logo = logo.filter(Filter::new("scale").arg(width).arg(-1));
+    }
+    let logo = logo
+        .filter(Filter::new("colorchannelmixer").option("aa", opacity))
+        .output("logo");
+    let video = FilterChain::new()
+        .input("0:v")
+        .input("logo")
+        .filter(Filter::new("overlay").option("x", x).option("y", y))
+        .output("video");
+    filter_graph(&[logo, video])
+}
+
+/// Parse an optional number parameter, checking it lies in `range`
+fn number_param<T>(params: &HashMap<String, String>, name: &str, range: std::ops::RangeInclusive<T>) -> Result<Option<T>, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let Some(value) = params.get(name).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    match value.parse::<T>() {
+        Ok(number) if range.contains(&number) => Ok(Some(number)),
+        _ => Err(format!("{} must be a number from {} to {}, not '{}'", name, range.start(), range.end(), value)),
+    }
+}
+
+/// Watermark Plugin - Adds a text or image (logo) watermark to videos
 pub struct WatermarkPlugin {
     metadata: PluginMetadata,
 }
@@ -17,8 +139,9 @@ impl WatermarkPlugin {
                 name: "watermark_plugin".to_string(),
                 version: "0.1.0".to_string(),
                 author: "Video-ToolKit Team".to_string(),
-                description: "Adds a text watermark to videos".to_string(),
-                api_version: PLUGIN_API_VERSION,
+                description: "Adds a text or image watermark to videos".to_string(),
+                api_requirement: ApiRequirement::at_least(PLUGIN_API_VERSION),
+                capabilities: PluginCapabilities::CONTEXT | PluginCapabilities::OUTPUT | PluginCapabilities::CHOICE_PARAMETERS,
             },
         }
     }
@@ -43,6 +166,14 @@ impl Plugin for WatermarkPlugin {
     }
 
     fn execute(&self, params: HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.execute_with_context(params, &ExecutionContext::new()).map(|_| ())
+    }
+
+    fn execute_with_context(
+        &self,
+        params: HashMap<String, String>,
+        ctx: &ExecutionContext,
+    ) -> Result<PluginOutput, Box<dyn std::error::Error>> {
         // Get parameters
         let input_file = params.get("input_file")
             .ok_or("Input file parameter is missing")?;
@@ -50,8 +181,7 @@ impl Plugin for WatermarkPlugin {
         let output_file = params.get("output_file")
             .ok_or("Output file parameter is missing")?;
 
-        let watermark_text = params.get("watermark_text")
-            .ok_or("Watermark text parameter is missing")?;
+        let mode = watermark_mode(&params)?;
 
         let default_position = "bottom_right".to_string();
         let default_font_size = "24".to_string();
@@ -60,6 +190,9 @@ impl Plugin for WatermarkPlugin {
         let position = params.get("position").unwrap_or(&default_position);
         let font_size = params.get("font_size").unwrap_or(&default_font_size);
         let font_color = params.get("font_color").unwrap_or(&default_font_color);
+        let margin = number_param(&params, "margin", 0..=u32::MAX)?.unwrap_or(10);
+        let opacity = number_param(&params, "opacity", 0.0..=1.0)?.unwrap_or(1.0);
+        let scale = number_param(&params, "scale", 0.1..=100.0)?;
 
         // Verify input file exists
         if !Path::new(input_file).exists() {
@@ -73,44 +206,69 @@ impl Plugin for WatermarkPlugin {
             }
         }
 
-        // Determine text position coordinates
-        let position_coords = match position.as_str() {
-            "top_left" => "10:10",
-            "top_right" => "main_w-text_w-10:10",
-            "bottom_left" => "10:main_h-text_h-10",
-            "bottom_right" => "main_w-text_w-10:main_h-text_h-10",
-            "center" => "main_w/2-text_w/2:main_h/2-text_h/2",
-            _ => "main_w-text_w-10:main_h-text_h-10",  // Default to bottom right
-        };
-
-        // Create FFmpeg command
-        let drawtext_filter = format!(
-            "drawtext=text='{}':fontsize={}:fontcolor={}:x={}:y={}",
-            watermark_text, font_size, font_color, position_coords.split(':').next().unwrap(), position_coords.split(':').nth(1).unwrap()
-        );
-
-        // Execute FFmpeg command
-        let output = Command::new("ffmpeg")
-            .args(&[
-                "-i", input_file,
-                "-vf", &drawtext_filter,
-                "-c:a", "copy",
-                "-y",  // Overwrite output file if it exists
-                output_file,
-            ])
-            .output()?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("FFmpeg command failed: {}", error).into());
+        // Create FFmpeg command: drawtext for text, overlay of the second input for an image
+        let mut args: Vec<String> = vec!["-i".to_string(), input_file.clone()];
+        match mode {
+            WatermarkMode::Text(text) => {
+                let font_file = params.get("font_file").map(String::as_str).filter(|path| !path.is_empty());
+                if let Some(font_file) = font_file.filter(|path| !Path::new(path).is_file()) {
+                    return Err(format!("Font file does not exist: {}", font_file).into());
+                }
+                let draw_box = match params.get("box").map(String::as_str) {
+                    None | Some("") => false,
+                    Some(value) => value.parse::<bool>().map_err(|_| format!("box must be true or false, not '{}'", value))?,
+                };
+                let style = TextStyle {
+                    font_size,
+                    font_color,
+                    opacity,
+                    font_file,
+                    box_color: draw_box.then(|| params.get("box_color").map_or("black@0.5", String::as_str)),
+                    box_border: number_param(&params, "box_border", 0..=u32::MAX)?.unwrap_or(5),
+                };
+                let drawtext_filter = build_drawtext_filter(text, &style, position, margin);
+                args.extend(["-vf".to_string(), drawtext_filter]);
+            },
+            WatermarkMode::Image(image) => {
+                if !Path::new(image).is_file() {
+                    return Err(format!("Watermark image does not exist: {}", image).into());
+                }
+                // The scale is a share of the frame width
+                let logo_width = match scale {
+                    Some(percent) => {
+                        let (frame_width, _) = get_video_dimensions(input_file)?;
+                        Some(((frame_width as f64 * percent / 100.0).round() as u32).max(1))
+                    },
+                    None => None,
+                };
+                let graph = build_overlay_graph(position, margin, opacity, logo_width);
+                args.extend(["-i", image, "-filter_complex", &graph, "-map", "[video]", "-map", "0:a?"].map(String::from));
+            },
         }
+        args.extend(["-c:a", "copy", "-y", output_file].map(String::from));  // -y overwrites the output file
+
+        // Execute FFmpeg command, reporting progress against the input's duration
+        let duration = get_duration(input_file).ok().filter(|duration| *duration > 0.0);
+        ctx.report_progress(0.0, "Rendering watermark");
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        execute_ffmpeg_streaming(
+            &args,
+            Some(ctx.cancellation()),
+            None,
+            |progress| {
+                if let Some(duration) = duration {
+                    ctx.report_progress((progress.out_time / duration) as f32, "Rendering watermark");
+                }
+            },
+        )?;
+        ctx.report_progress(1.0, "Watermark rendered");
 
         // Verify output file was created
         if !Path::new(output_file).exists() {
             return Err("Failed to create output file".into());
         }
 
-        Ok(())
+        Ok(PluginOutput::new().with_file(output_file))
     }
 
     fn get_parameter_info(&self) -> Vec<ParameterInfo> {
@@ -127,21 +285,35 @@ impl Plugin for WatermarkPlugin {
                 description: "Path to save the output video file".to_string(),
                 required: true,
                 default_value: None,
-                parameter_type: ParameterType::FilePath,
+                parameter_type: ParameterType::String,
             },
             ParameterInfo {
                 name: "watermark_text".to_string(),
-                description: "Text to use as watermark".to_string(),
-                required: true,
-                default_value: Some("© Video-ToolKit".to_string()),
+                description: "Text to use as watermark; leave empty when using watermark_image".to_string(),
+                required: false,
+                default_value: None,
                 parameter_type: ParameterType::String,
             },
+            ParameterInfo {
+                name: "watermark_image".to_string(),
+                description: "Image (e.g. a PNG logo) to lay over the video instead of text".to_string(),
+                required: false,
+                default_value: None,
+                parameter_type: ParameterType::FilePath,
+            },
             ParameterInfo {
                 name: "position".to_string(),
-                description: "Position of the watermark (top_left, top_right, bottom_left, bottom_right, center)".to_string(),
+                description: "Position of the watermark".to_string(),
                 required: false,
                 default_value: Some("bottom_right".to_string()),
-                parameter_type: ParameterType::String,
+                parameter_type: choices(&POSITIONS),
+            },
+            ParameterInfo {
+                name: "margin".to_string(),
+                description: "Distance of the watermark from the edges of the frame, in pixels".to_string(),
+                required: false,
+                default_value: Some("10".to_string()),
+                parameter_type: ParameterType::Integer,
             },
             ParameterInfo {
                 name: "font_size".to_string(),
@@ -155,8 +327,50 @@ impl Plugin for WatermarkPlugin {
                 description: "Font color for the watermark text".to_string(),
                 required: false,
                 default_value: Some("white".to_string()),
+                parameter_type: choices(&FONT_COLORS),
+            },
+            ParameterInfo {
+                name: "font_file".to_string(),
+                description: "Font file (e.g. a .ttf) to draw the text with; FFmpeg's default font if empty".to_string(),
+                required: false,
+                default_value: None,
+                parameter_type: ParameterType::FilePath,
+            },
+            ParameterInfo {
+                name: "box".to_string(),
+                description: "Draw a box behind the text, to keep it readable over bright footage".to_string(),
+                required: false,
+                default_value: Some("false".to_string()),
+                parameter_type: ParameterType::Boolean,
+            },
+            ParameterInfo {
+                name: "box_color".to_string(),
+                description: "Color of the box, with its opacity after an @, e.g. black@0.5".to_string(),
+                required: false,
+                default_value: Some("black@0.5".to_string()),
                 parameter_type: ParameterType::String,
             },
+            ParameterInfo {
+                name: "box_border".to_string(),
+                description: "Width of the box around the text, in pixels".to_string(),
+                required: false,
+                default_value: Some("5".to_string()),
+                parameter_type: ParameterType::Integer,
+            },
+            ParameterInfo {
+                name: "scale".to_string(),
+                description: "Width of the image as a percentage of the frame width; its own size if empty".to_string(),
+                required: false,
+                default_value: None,
+                parameter_type: ParameterType::Float,
+            },
+            ParameterInfo {
+                name: "opacity".to_string(),
+                description: "Opacity of the text or image, from 0 (invisible) to 1 (opaque)".to_string(),
+                required: false,
+                default_value: Some("1.0".to_string()),
+                parameter_type: ParameterType::Float,
+            },
         ]
     }
 
@@ -171,4 +385,4 @@ impl Plugin for WatermarkPlugin {
 }
 
 // Export the plugin
-plugin_system::export_plugin!(WatermarkPlugin);
\ No newline at end of file
+plugin_system::export_plugin!(WatermarkPlugin);
@@ -1,26 +1,55 @@
 use eframe::NativeOptions;
-use clap::{Parser, Subcommand, ArgGroup};
+use clap::{Args, Parser, Subcommand};
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
+use std::time::Duration;
 
-use common::{check_ffmpeg, formats::*, get_supported_formats};
-use clipper::{clip_video, parse_time_ranges};
-use gif_converter::{convert_video_to_gif, optimize_conversion};
-use gif_transparency::{batch_process_gifs, process_directory};
-use splitter::split_video;
-use merger::merge_audio_video;
+use common::{
+    check_ffmpeg, format_warnings, formats::*, CancellationToken, get_supported_formats, init_log_file, init_session_log,
+    is_dry_run, is_verbose, session_log_dir, set_dry_run, set_verbose, EncoderPreference, LevelFilter, VideoToolkitError, Warning,
+    SESSION_LOGS_KEPT
+};
+use clipper::{clip_video_with_options, parse_time_ranges, AvoidNegativeTs, ClipOptions};
+use gif_converter::{
+    convert_video_to_gif_with_options, optimize_conversion_with_options, ConversionReport, CropMode, GifOptions,
+    GifsicleSettings
+};
+use gif_transparency::{
+    batch_process_gifs, batch_restore_gifs, process_directory, BatchGifOptions, GifStatus, TransparencyMode,
+    TransparencyOptions, TransparencyReport
+};
+use splitter::{
+    merge_grid, merge_slices, parse_dimensions, parse_encode_options, split_by_duration, split_video_grid_with_options, split_video_with_options, GridOptions, GridSize, LabelCorner, LabelStyle, SliceDirection, SplitOptions
+};
+use merger::{
+    concat_videos, merge_audio_video_with_options, read_concat_manifest, AudioMode, ConcatMode, DurationPolicy,
+    LoudnessTarget, MergeOptions, SubtitleMode
+};
 use ui::VideoToolKitApp;
-use plugin_system::PluginManager;
-use profile_system::{ProfileManager, Profile, ProfileType};
+use plugin_system::{ExecutionContext, ParameterType, PluginError, PluginManager};
+use profile_system::{ConflictPolicy, ProfileError, ProfileFormat, ProfileManager, Profile, ProfileType};
 use batch_processing::{
-    BatchProcessor, BatchOperation, BatchClipperConfig,
-    BatchGifConverterConfig, BatchGifTransparencyConfig,
-    BatchSplitterConfig, BatchMergerConfig
+    BatchWebpConfig, BatchController, BatchSettings, BatchProcessor, BatchProgress, ExclusionReason, OperationSettings, ReportFormat,
+    FileOrder, PatternTarget, write_report
 };
 
 #[derive(Parser)]
 #[clap(author, version, about = "Video processing utilities")]
 struct Cli {
+    /// Print the full FFmpeg command line for every invocation
+    #[clap(long, global = true)]
+    verbose: bool,
+
+    /// Print the FFmpeg commands that would write files instead of running them
+    /// (gif-transparency only reports what would change)
+    #[clap(long, global = true)]
+    dry_run: bool,
+
+    /// Append a log of every FFmpeg run (command, duration, exit status, stderr)
+    /// and batch item result to this file
+    #[clap(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
     #[clap(subcommand)]
     command: Option<Commands>,
 }
@@ -48,7 +77,19 @@ enum Commands {
         #[clap(short, long)]
         suffix: Option<String>,
 
-        /// Output format (e.g., mp4, mkv, avi)
+        /// Timestamp shifting for the output: auto, make_zero or disabled (auto = make_zero with --copy-codec)
+        #[clap(long, default_value = "auto")]
+        avoid_negative_ts: AvoidNegativeTs,
+
+        /// Probe each clip after writing and fail if it does not start at 0
+        #[clap(long)]
+        verify_outputs: bool,
+
+        /// Re-encode with a hardware video encoder: auto, off or an encoder name such as h264_nvenc
+        #[clap(long, default_value = "off")]
+        hw_accel: EncoderPreference,
+
+        /// Output format of the clips: mp4, mkv, avi, mov, webm, flv, ts, m4v, mpeg or ogv
         #[clap(long, default_value = "mp4")]
         format: String,
     },
@@ -77,9 +118,37 @@ enum Commands {
         /// Try multiple settings to achieve size target
         #[clap(long)]
         optimize: bool,
+
+        /// Crop region applied before scaling (x,y,w,h or "auto")
+        #[clap(long)]
+        crop: Option<CropMode>,
+
+        /// Play the GIF forward then backward (doubles the duration)
+        #[clap(long)]
+        boomerang: bool,
+
+        /// Number of times the GIF plays (0 = forever, 1 = once)
+        #[clap(long)]
+        loop_count: Option<u32>,
+
+        /// Post-optimize the GIF with gifsicle when it is installed
+        #[clap(long)]
+        gifsicle: bool,
+
+        /// gifsicle optimization level (1-3)
+        #[clap(long, default_value = "3")]
+        gifsicle_level: u8,
+
+        /// gifsicle lossy compression factor (e.g. 80)
+        #[clap(long)]
+        gifsicle_lossy: Option<u32>,
+
+        /// Also write a PNG poster frame next to each GIF, optionally at TIMESTAMP (default: middle)
+        #[clap(long, num_args = 0..=1, value_name = "TIMESTAMP")]
+        poster: Option<Option<String>>,
     },
 
-    /// Make GIF backgrounds transparent by modifying trailer byte
+    /// Make GIF backgrounds transparent
     GifTransparency {
         /// Input GIF files or directories
         #[clap(required = true)]
@@ -92,6 +161,31 @@ enum Commands {
         /// Create backup of original files
         #[clap(short, long)]
         backup: bool,
+
+        /// Undo a previous run, from the .gif.bak backups where present
+        #[clap(long, conflicts_with = "backup")]
+        restore: bool,
+
+        /// Use the legacy trailer-byte change instead of rewriting the Graphic Control Extensions
+        #[clap(long)]
+        legacy: bool,
+
+        /// Edit files in place instead of writing a copy and renaming it over the original
+        /// (faster for huge files, but an interrupted run can corrupt them)
+        #[clap(long)]
+        no_atomic: bool,
+
+        /// Maximum number of GIFs processed at once (default: one per CPU core)
+        #[clap(long)]
+        max_parallel: Option<usize>,
+
+        /// Write transparent copies into this directory instead of modifying the GIFs
+        #[clap(short, long, conflicts_with = "restore")]
+        output_dir: Option<PathBuf>,
+
+        /// Also process GIFs without a .gif extension, recognised by their header
+        #[clap(long, conflicts_with = "restore")]
+        by_content: bool,
     },
 
     /// Make all GIFs in a directory transparent
@@ -106,32 +200,111 @@ enum Commands {
         /// Create backup of original files
         #[clap(short, long)]
         backup: bool,
+
+        /// Use the legacy trailer-byte change instead of rewriting the Graphic Control Extensions
+        #[clap(long)]
+        legacy: bool,
+
+        /// Edit files in place instead of writing a copy and renaming it over the original
+        /// (faster for huge files, but an interrupted run can corrupt them)
+        #[clap(long)]
+        no_atomic: bool,
+
+        /// Maximum number of GIFs processed at once (default: one per CPU core)
+        #[clap(long)]
+        max_parallel: Option<usize>,
+
+        /// Write transparent copies into this directory instead of modifying the GIFs
+        #[clap(short, long)]
+        output_dir: Option<PathBuf>,
     },
 
-    /// Split a video into equal vertical slices
+    /// Split a video into equal slices
     Splitter {
         /// Input video file path
-        input: String,
+        #[clap(required_unless_present = "merge")]
+        input: Option<String>,
 
-        /// Output directory for video slices
+        /// Output directory for video slices (with --merge: the output video file)
         #[clap(short, long, default_value = "output_slices")]
         output_dir: String,
 
+        /// Reassemble these slices (in order) into one video written to --output-dir,
+        /// using --direction, or --grid for tiles given row by row
+        #[clap(
+            long, num_args = 2.., requires = "output_dir",
+            conflicts_with_all = &["input", "slices", "segment_duration", "label_slices", "force", "expect_dimensions"]
+        )]
+        merge: Vec<PathBuf>,
+
         /// Prefix for output filenames
         #[clap(short, long, default_value = "slice")]
         prefix: String,
 
-        /// Custom FFmpeg encoding options (advanced users only)
-        #[clap(long)]
+        /// Custom FFmpeg encoding options, quoted as in a shell (advanced users only)
+        #[clap(long, allow_hyphen_values = true, value_parser = encode_options_arg)]
         custom_encode: Option<String>,
 
-        /// Process even if video dimensions are not 1920x1080
+        /// Only split videos of these dimensions, as WIDTHxHEIGHT (e.g. 1920x1080)
+        #[clap(long, value_parser = parse_dimensions)]
+        expect_dimensions: Option<(u32, u32)>,
+
+        /// Skip the --expect-dimensions check (with --grid: allow an uneven last row/column)
         #[clap(long)]
         force: bool,
 
-        /// Output format (e.g., mp4, mkv, avi)
+        /// Number of equal slices to cut
+        #[clap(long, default_value = "5")]
+        slices: u32,
+
+        /// Slice direction: vertical (side-by-side columns) or horizontal (stacked rows)
+        #[clap(long, default_value = "vertical")]
+        direction: SliceDirection,
+
+        /// Split into a grid of tiles instead of slices, as ROWSxCOLS (e.g. 3x3)
+        #[clap(long, conflicts_with_all = &["slices", "direction", "label_slices"])]
+        grid: Option<GridSize>,
+
+        /// Cut into consecutive segments of this many seconds instead of spatial slices
+        #[clap(long, conflicts_with_all = &["slices", "direction", "label_slices", "grid", "custom_encode"])]
+        segment_duration: Option<f64>,
+
+        /// Copy the streams when cutting segments (faster, cuts at the nearest keyframe)
+        #[clap(long, requires = "segment_duration")]
+        copy_codec: bool,
+
+        /// Output format of the slices: mp4, mkv, avi, mov or webm
         #[clap(long, default_value = "mp4")]
         format: String,
+
+        /// Extend each slice this many pixels into its neighbours, for edge blending
+        #[clap(long, default_value = "0", conflicts_with_all = &["merge", "grid", "segment_duration"])]
+        overlap: u32,
+
+        /// Maximum number of slices or tiles encoded at once when they cannot be written
+        /// in a single FFmpeg run (default: one per CPU core)
+        #[clap(long, conflicts_with_all = &["merge", "segment_duration"])]
+        jobs: Option<usize>,
+
+        /// Re-encode with a hardware video encoder: auto, off or an encoder name such as h264_nvenc
+        #[clap(long, default_value = "off", conflicts_with_all = &["merge", "grid", "segment_duration"])]
+        hw_accel: EncoderPreference,
+
+        /// Burn the slice number into each output for identification
+        #[clap(long)]
+        label_slices: bool,
+
+        /// Label text template ({index} = slice number, {x}/{y} = slice offset)
+        #[clap(long, default_value = "{index}")]
+        label_template: String,
+
+        /// Label font size as a fraction of the slice's shorter side
+        #[clap(long, default_value = "0.5")]
+        label_size: f32,
+
+        /// Label position (top-left, top-right, bottom-left, bottom-right, center)
+        #[clap(long, default_value = "top-left")]
+        label_corner: LabelCorner,
     },
 
     /// Merge video with audio
@@ -154,9 +327,85 @@ enum Commands {
         #[clap(long)]
         copy_codec: bool,
 
-        /// Output format (e.g., mp4, mkv, avi)
-        #[clap(long, default_value = "mp4")]
-        format: String,
+        /// What to do with the video's own audio: replace, mix or add-track
+        #[clap(long, default_value = "replace")]
+        audio_mode: AudioMode,
+
+        /// Volume of the video's own audio with --audio-mode mix (1.0 = unchanged)
+        #[clap(long, default_value = "1.0")]
+        video_volume: f32,
+
+        /// Volume of the new audio with --audio-mode mix (1.0 = unchanged)
+        #[clap(long, default_value = "1.0")]
+        audio_volume: f32,
+
+        /// Fit audio of another length to the video: shortest, loop-audio or pad-silence
+        #[clap(long, default_value = "shortest")]
+        duration_policy: DurationPolicy,
+
+        /// Normalize the loudness of the merged audio with FFmpeg's loudnorm filter (re-encodes the audio)
+        #[clap(long)]
+        normalize_loudness: bool,
+
+        /// Integrated loudness target in LUFS for --normalize-loudness
+        #[clap(long, default_value = "-16", allow_hyphen_values = true, requires = "normalize_loudness")]
+        target_lufs: f32,
+
+        /// Maximum true peak in dBTP for --normalize-loudness
+        #[clap(long, default_value = "-1.5", allow_hyphen_values = true, requires = "normalize_loudness")]
+        true_peak: f32,
+
+        /// Loudness range target in LU for --normalize-loudness
+        #[clap(long, default_value = "11", requires = "normalize_loudness")]
+        loudness_range: f32,
+
+        /// Measure the audio in a first pass for a more accurate normalization
+        #[clap(long, requires = "normalize_loudness")]
+        two_pass: bool,
+
+        /// Subtitle file (.srt, .ass, .ssa or .vtt) to add as a selectable track
+        #[clap(long)]
+        subtitles: Option<String>,
+
+        /// Draw the subtitles into the video instead (re-encodes the video)
+        #[clap(long, requires = "subtitles")]
+        burn_subtitles: bool,
+
+        /// Fade the new audio in over this many seconds (re-encodes the audio)
+        #[clap(long)]
+        audio_fade_in: Option<f64>,
+
+        /// Fade the audio out over this many seconds before it ends (re-encodes the audio)
+        #[clap(long)]
+        audio_fade_out: Option<f64>,
+
+        /// Output format: mp4, mkv, mov, webm, avi or flv; sets the output file's extension
+        /// (default: taken from the output file name)
+        #[clap(long, value_parser = merge_format_arg)]
+        format: Option<String>,
+
+        /// Re-encode with a hardware video encoder: auto, off or an encoder name such as h264_nvenc
+        #[clap(long, default_value = "off")]
+        hw_accel: EncoderPreference,
+    },
+
+    /// Join clips into one file, in order
+    Concat {
+        /// Clips to join, in order
+        #[clap(required_unless_present = "list", conflicts_with = "list")]
+        inputs: Vec<PathBuf>,
+
+        /// Text file listing the clips, one per line (relative paths are resolved against its directory)
+        #[clap(long)]
+        list: Option<PathBuf>,
+
+        /// Output file path
+        #[clap(short, long)]
+        output: PathBuf,
+
+        /// How the clips are joined: auto, demuxer (stream copy) or reencode
+        #[clap(long, default_value = "auto")]
+        mode: ConcatMode,
     },
 
     /// Manage plugins
@@ -184,9 +433,9 @@ enum PluginCommands {
     /// List available plugins
     List,
 
-    /// Load a plugin from a file
+    /// Load a plugin from a library file or a script plugin directory
     Load {
-        /// Path to the plugin file
+        /// Path to the plugin library, or to a directory with a plugin.toml
         path: String,
     },
 
@@ -198,10 +447,26 @@ enum PluginCommands {
         /// Parameters to pass to the plugin (key=value)
         #[clap(short, long)]
         params: Vec<String>,
+
+        /// Give up on the plugin if it takes longer than this many seconds
+        #[clap(long, value_name = "SECS")]
+        timeout: Option<u64>,
     },
 
-    /// Discover and load plugins from the default plugin directory
+    /// Discover and load plugins from the plugin search path (listed with --verbose)
     Discover,
+
+    /// Let discovery load a disabled plugin again
+    Enable {
+        /// Name of the plugin
+        name: String,
+    },
+
+    /// Keep discovery from loading a plugin, in this and later sessions
+    Disable {
+        /// Name of the plugin
+        name: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -211,6 +476,14 @@ enum ProfileCommands {
         /// Profile type to list
         #[clap(long)]
         profile_type: Option<String>,
+
+        /// Show the description, modification time and parameter count of each profile
+        #[clap(short, long)]
+        long: bool,
+
+        /// Only list profiles whose name, description or tags contain every word of this text
+        #[clap(long, value_name = "TEXT")]
+        search: Option<String>,
     },
 
     /// Show a specific profile
@@ -239,6 +512,14 @@ enum ProfileCommands {
         /// Parameters for the profile (key=value)
         #[clap(short, long)]
         params: Vec<String>,
+
+        /// Tags to find the profile by (comma-separated)
+        #[clap(long, value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// Save the profile even if its parameters do not match its type
+        #[clap(long)]
+        no_validate: bool,
     },
 
     /// Delete a profile
@@ -251,10 +532,28 @@ enum ProfileCommands {
         profile_type: String,
     },
 
-    /// Import a profile from a file
+    /// Save a copy of a profile under a new name
+    Duplicate {
+        /// Name of the profile to copy
+        name: String,
+
+        /// Type of the profile
+        #[clap(long, required = true)]
+        profile_type: String,
+
+        /// Name of the copy
+        #[clap(long = "as", value_name = "NEW_NAME", required = true)]
+        new_name: String,
+    },
+
+    /// Import a profile from a JSON, TOML or YAML file
     Import {
         /// Path to the profile file
         path: String,
+
+        /// Format of the file: json, toml or yaml (default: from the extension)
+        #[clap(long)]
+        format: Option<ProfileFormat>,
     },
 
     /// Export a profile to a file
@@ -269,6 +568,27 @@ enum ProfileCommands {
         /// Path to export the profile to
         #[clap(short, long, required = true)]
         output: String,
+
+        /// Format of the file: json, toml or yaml (default: from the extension)
+        #[clap(long)]
+        format: Option<ProfileFormat>,
+    },
+
+    /// Export every profile to a single bundle file
+    ExportAll {
+        /// Path to write the bundle to
+        #[clap(short, long, required = true)]
+        output: String,
+    },
+
+    /// Import the profiles of a bundle written by export-all
+    ImportBundle {
+        /// Path to the bundle file
+        path: String,
+
+        /// What to do with profiles whose name is taken: skip, overwrite or rename
+        #[clap(long, default_value = "skip")]
+        on_conflict: ConflictPolicy,
     },
 }
 
@@ -276,17 +596,12 @@ enum ProfileCommands {
 enum BatchCommands {
     /// Batch process files with the clipper
     Clipper {
-        /// Input files or directories
-        #[clap(required = true)]
-        inputs: Vec<PathBuf>,
-
-        /// Process directories recursively
-        #[clap(short, long)]
-        recursive: bool,
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
 
-        /// File pattern to match (regex)
-        #[clap(short, long)]
-        pattern: Option<String>,
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
 
         /// Output directory
         #[clap(short, long, default_value = "output_clips")]
@@ -304,28 +619,31 @@ enum BatchCommands {
         #[clap(short, long)]
         suffix: Option<String>,
 
-        /// Process files in parallel
-        #[clap(long, default_value = "true")]
-        parallel: bool,
+        /// Timestamp shifting for the output: auto, make_zero or disabled (auto = make_zero with --copy-codec)
+        #[clap(long)]
+        avoid_negative_ts: Option<String>,
+
+        /// Probe each clip after writing and fail if it does not start at 0
+        #[clap(long)]
+        verify_outputs: bool,
+
+        /// Re-encode with a hardware video encoder: auto, off or an encoder name such as h264_nvenc
+        #[clap(long)]
+        hw_accel: Option<String>,
 
-        /// Output format (e.g., mp4, mkv, avi)
+        /// Output format of the clips: mp4, mkv, avi, mov, webm, flv, ts, m4v, mpeg or ogv
         #[clap(long, default_value = "mp4")]
         format: String,
     },
 
     /// Batch convert videos to GIF
     GifConverter {
-        /// Input files or directories
-        #[clap(required = true)]
-        inputs: Vec<PathBuf>,
-
-        /// Process directories recursively
-        #[clap(short, long)]
-        recursive: bool,
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
 
-        /// File pattern to match (regex)
-        #[clap(short, long)]
-        pattern: Option<String>,
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
 
         /// Output directory
         #[clap(short, long, default_value = "output_gifs")]
@@ -347,47 +665,86 @@ enum BatchCommands {
         #[clap(long)]
         optimize: bool,
 
-        /// Process files in parallel
-        #[clap(long, default_value = "true")]
-        parallel: bool,
+        /// Crop region applied before scaling (x,y,w,h or "auto")
+        #[clap(long)]
+        crop: Option<String>,
+
+        /// Play the GIF forward then backward (doubles the duration)
+        #[clap(long)]
+        boomerang: bool,
+
+        /// Number of times the GIF plays (0 = forever, 1 = once)
+        #[clap(long)]
+        loop_count: Option<u32>,
+
+        /// Post-optimize the GIF with gifsicle when it is installed
+        #[clap(long)]
+        gifsicle: bool,
+
+        /// gifsicle optimization level (1-3)
+        #[clap(long, default_value = "3")]
+        gifsicle_level: u8,
+
+        /// gifsicle lossy compression factor (e.g. 80)
+        #[clap(long)]
+        gifsicle_lossy: Option<u32>,
+
+        /// Also write a PNG poster frame next to each GIF, optionally at TIMESTAMP (default: middle)
+        #[clap(long, num_args = 0..=1, value_name = "TIMESTAMP")]
+        poster: Option<Option<String>>,
     },
 
     /// Batch process GIFs for transparency
     GifTransparency {
-        /// Input files or directories
-        #[clap(required = true)]
-        inputs: Vec<PathBuf>,
-
-        /// Process directories recursively
-        #[clap(short, long)]
-        recursive: bool,
-
-        /// File pattern to match (regex)
-        #[clap(short, long)]
-        pattern: Option<String>,
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
 
         /// Create backup of original files
         #[clap(short, long)]
         backup: bool,
 
-        /// Process files in parallel
-        #[clap(long, default_value = "true")]
-        parallel: bool,
+        /// Directory for backups (default: <name>.gif.bak next to each GIF)
+        #[clap(long, requires = "backup")]
+        backup_dir: Option<PathBuf>,
+
+        /// Also write an animated WebP with the same transparency for each GIF
+        #[clap(long)]
+        also_webp: bool,
+
+        /// WebP quality from 0 to 100 (ignored for lossless output)
+        #[clap(long, default_value = "80")]
+        webp_quality: u32,
+
+        /// Encode the WebP losslessly
+        #[clap(long)]
+        webp_lossless: bool,
+
+        /// Directory for the WebP files (defaults to next to each GIF)
+        #[clap(long)]
+        webp_output_dir: Option<PathBuf>,
+
+        /// Use the legacy trailer-byte change instead of rewriting the Graphic Control Extensions
+        #[clap(long)]
+        legacy: bool,
+
+        /// Edit files in place instead of writing a copy and renaming it over the original
+        /// (faster for huge files, but an interrupted run can corrupt them)
+        #[clap(long)]
+        no_atomic: bool,
+
+        /// Write transparent copies into this directory instead of modifying the GIFs
+        #[clap(short, long)]
+        output_dir: Option<PathBuf>,
     },
 
     /// Batch split videos
     Splitter {
-        /// Input files or directories
-        #[clap(required = true)]
-        inputs: Vec<PathBuf>,
-
-        /// Process directories recursively
-        #[clap(short, long)]
-        recursive: bool,
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
 
-        /// File pattern to match (regex)
-        #[clap(short, long)]
-        pattern: Option<String>,
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
 
         /// Output directory
         #[clap(short, long, default_value = "output_slices")]
@@ -397,40 +754,68 @@ enum BatchCommands {
         #[clap(short, long, default_value = "slice")]
         prefix: String,
 
-        /// Custom FFmpeg encoding options
-        #[clap(long)]
+        /// Custom FFmpeg encoding options, quoted as in a shell
+        #[clap(long, allow_hyphen_values = true, value_parser = encode_options_arg)]
         custom_encode: Option<String>,
 
-        /// Process even if video dimensions are not 1920x1080
+        /// Only split videos of these dimensions, as WIDTHxHEIGHT (e.g. 1920x1080)
+        #[clap(long)]
+        expect_dimensions: Option<String>,
+
+        /// Skip the --expect-dimensions check
         #[clap(long)]
         force: bool,
 
-        /// Process files in parallel
-        #[clap(long, default_value = "true")]
-        parallel: bool,
+        /// Number of equal slices to cut
+        #[clap(long, default_value = "5")]
+        slices: u32,
+
+        /// Slice direction: vertical (side-by-side columns) or horizontal (stacked rows)
+        #[clap(long, default_value = "vertical")]
+        direction: SliceDirection,
+
+        /// Cut into consecutive segments of this many seconds instead of spatial slices
+        #[clap(long, conflicts_with_all = &["slices", "direction", "custom_encode"])]
+        segment_duration: Option<f64>,
+
+        /// Copy the streams when cutting segments (faster, cuts at the nearest keyframe)
+        #[clap(long, requires = "segment_duration")]
+        copy_codec: bool,
 
-        /// Output format (e.g., mp4, mkv, avi)
+        /// Output format of the slices: mp4, mkv, avi, mov or webm
         #[clap(long, default_value = "mp4")]
         format: String,
+
+        /// Maximum number of slices of a file encoded at once when they cannot be written
+        /// in a single FFmpeg run (default: 1 with --parallel, otherwise one per CPU core)
+        #[clap(long, conflicts_with = "segment_duration")]
+        jobs: Option<usize>,
+
+        /// Re-encode with a hardware video encoder: auto, off or an encoder name such as h264_nvenc
+        #[clap(long, conflicts_with = "segment_duration")]
+        hw_accel: Option<String>,
     },
 
     /// Batch merge videos with audio
     Merger {
-        /// Input video files or directories
-        #[clap(required = true)]
-        inputs: Vec<PathBuf>,
-
-        /// Process directories recursively
-        #[clap(short, long)]
-        recursive: bool,
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
 
-        /// File pattern to match (regex)
-        #[clap(short, long)]
-        pattern: Option<String>,
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
 
         /// Input audio file to use for all videos
-        #[clap(long, required = true)]
-        audio: PathBuf,
+        #[clap(long, required_unless_present = "audio_ext", conflicts_with = "audio_ext")]
+        audio: Option<PathBuf>,
+
+        /// Pair each video with the audio file of the same name and this extension (e.g. wav)
+        #[clap(long)]
+        audio_ext: Option<String>,
+
+        /// Directory of the paired audio files (default: next to each video)
+        #[clap(long, requires = "audio_ext")]
+        audio_dir: Option<PathBuf>,
 
         /// Output directory
         #[clap(short, long, default_value = "output_merged")]
@@ -444,28 +829,86 @@ enum BatchCommands {
         #[clap(long)]
         copy_codec: bool,
 
-        /// Process files in parallel
-        #[clap(long, default_value = "true")]
-        parallel: bool,
+        /// Fade the audio in over this many seconds (re-encodes the audio)
+        #[clap(long)]
+        audio_fade_in: Option<f64>,
 
-        /// Output format (e.g., mp4, mkv, avi)
-        #[clap(long, default_value = "mp4")]
+        /// Fade the audio out over this many seconds before it ends (re-encodes the audio)
+        #[clap(long)]
+        audio_fade_out: Option<f64>,
+
+        /// Output format: mp4, mkv, mov, webm, avi or flv
+        #[clap(long, default_value = "mp4", value_parser = merge_format_arg)]
         format: String,
+
+        /// Re-encode with a hardware video encoder: auto, off or an encoder name such as h264_nvenc
+        #[clap(long)]
+        hw_accel: Option<String>,
+    },
+
+    /// Run a plugin on every file
+    Plugin {
+        /// Name of the plugin, loaded from the plugin search path
+        name: String,
+
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
+
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
+
+        /// Parameters given to every run (key=value); the input file and the output
+        /// path are filled in for each file
+        #[clap(short = 'P', long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+
+        /// Extensions of the files to run the plugin on (repeatable) [default: mp4, avi, mov, mkv]
+        #[clap(long = "extension", value_name = "EXT")]
+        extensions: Vec<String>,
+
+        /// Output directory
+        #[clap(short, long, default_value = "output_plugin")]
+        output_dir: String,
     },
 
     /// Use a profile for batch processing
     WithProfile {
-        /// Input files or directories
+        #[clap(flatten)]
+        batch: Box<BatchArgs>,
+
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
+
+        /// Name of the profile to use
+        #[clap(long, required = true)]
+        profile: String,
+
+        /// Type of the profile
+        #[clap(long, required = true)]
+        profile_type: String,
+    },
+
+    /// Keep watching directories and process new files with a profile as they appear
+    Watch {
+        /// Input directories to watch
         #[clap(required = true)]
         inputs: Vec<PathBuf>,
 
-        /// Process directories recursively
-        #[clap(short, long)]
-        recursive: bool,
+        #[clap(flatten)]
+        filters: InputFilterArgs,
 
-        /// File pattern to match (regex)
-        #[clap(short, long)]
-        pattern: Option<String>,
+        #[clap(flatten)]
+        policy: FilePolicyArgs,
+
+        /// Recreate the input directories under the output directory (default: all outputs directly in it)
+        #[clap(long)]
+        mirror_structure: bool,
+
+        /// Seconds between two scans of the inputs; a file is processed once its size is the same in two scans
+        #[clap(long, value_name = "SECS", default_value_t = 2)]
+        poll_interval: u64,
 
         /// Name of the profile to use
         #[clap(long, required = true)]
@@ -481,7 +924,155 @@ enum BatchCommands {
     },
 }
 
-fn main() -> Result<(), eframe::Error> {
+/// Inputs and options shared by the batch subcommands that run once over their inputs
+#[derive(Args)]
+struct BatchArgs {
+    /// Input files or directories
+    #[clap(required_unless_present = "files_from")]
+    inputs: Vec<PathBuf>,
+
+    /// Also process the files listed in this file, one per line (- for standard input)
+    #[clap(long, value_name = "LIST")]
+    files_from: Option<PathBuf>,
+
+    #[clap(flatten)]
+    filters: InputFilterArgs,
+
+    /// Only process files modified at or after this time (RFC 3339 or relative, e.g. 24h, 7d)
+    #[clap(long)]
+    since: Option<String>,
+
+    /// Only process files modified before this time (RFC 3339 or relative, e.g. 24h, 7d)
+    #[clap(long)]
+    until: Option<String>,
+
+    /// Order the files are processed in: name (by path), size or modified
+    #[clap(long, default_value = "name")]
+    order: FileOrder,
+
+    /// Process the files in descending --order, e.g. the largest first
+    #[clap(long)]
+    descending: bool,
+
+    /// Only process the first N matching files (trial run)
+    #[clap(long)]
+    limit: Option<usize>,
+
+    /// Only process N randomly chosen matching files (trial run)
+    #[clap(long, conflicts_with = "limit")]
+    sample: Option<usize>,
+
+    /// Seed for --sample, to draw the same files again
+    #[clap(long, requires = "sample")]
+    seed: Option<u64>,
+
+    #[clap(flatten)]
+    policy: FilePolicyArgs,
+
+    /// Start no new files after the first failure
+    #[clap(long)]
+    fail_fast: bool,
+
+    /// Start no new files once this many have failed
+    #[clap(long, value_name = "N", conflicts_with = "fail_fast")]
+    max_failures: Option<usize>,
+
+    /// Write the results to this file as JSON or CSV, chosen by its extension
+    #[clap(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Record finished files in this journal and skip those it lists as done, to resume an interrupted batch
+    #[clap(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// Process files in parallel
+    #[clap(long, default_value = "true")]
+    parallel: bool,
+}
+
+/// Which files of the inputs a batch picks
+#[derive(Args)]
+struct InputFilterArgs {
+    /// Process directories recursively
+    #[clap(short, long)]
+    recursive: bool,
+
+    /// Descend into symlinked directories (a link back to a parent directory is skipped)
+    #[clap(long)]
+    follow_symlinks: bool,
+
+    /// Only scan this many directory levels when recursive, 1 being the files directly in an input
+    #[clap(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// File pattern to match (regex)
+    #[clap(short, long)]
+    pattern: Option<String>,
+
+    /// What --pattern is matched against: name (the file name), relative (the path under the input directory) or full (the absolute path)
+    #[clap(long, default_value = "name", value_name = "TARGET")]
+    pattern_target: PatternTarget,
+
+    /// Only process files matching this glob, relative to the input directory (repeatable)
+    #[clap(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files matching this glob, relative to the input directory (repeatable)
+    #[clap(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Match files by their content (magic bytes) instead of their extension
+    #[clap(long)]
+    by_content: bool,
+
+    /// Only process files of at least this size (bytes or with a unit, e.g. 500KB, 50MB)
+    #[clap(long, value_name = "SIZE")]
+    min_input_size: Option<String>,
+
+    /// Only process files of at most this size (bytes or with a unit, e.g. 2GB)
+    #[clap(long, value_name = "SIZE")]
+    max_input_size: Option<String>,
+}
+
+/// How a batch handles a file that fails or hangs
+#[derive(Args)]
+struct FilePolicyArgs {
+    /// Record a file as failed if it takes longer than this many seconds, and move on
+    #[clap(long, value_name = "SECS")]
+    per_file_timeout: Option<u64>,
+
+    /// Run a file again up to N times after a transient failure (e.g. an I/O error on a network share)
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
+    /// Seconds before the first retry, doubling after each one [default: 2]
+    #[clap(long, value_name = "SECS", requires = "retries")]
+    retry_backoff: Option<u64>,
+}
+
+/// Log to `--log-file`, or to a new session log when the GUI starts without one
+fn init_logging(cli: &Cli) {
+    let result = match (&cli.log_file, &cli.command) {
+        (Some(path), _) => init_log_file(path, LevelFilter::Debug).map(|_| path.clone()),
+        (None, None) => match session_log_dir() {
+            Some(dir) => init_session_log(&dir, SESSION_LOGS_KEPT, LevelFilter::Debug),
+            None => return,
+        },
+        (None, Some(_)) => return,
+    };
+
+    match result {
+        Ok(path) => log::info!(
+            "Video-ToolKit {} started ({}), logging to {}",
+            env!("CARGO_PKG_VERSION"),
+            std::env::args().collect::<Vec<_>>().join(" "),
+            path.display()
+        ),
+        Err(e) => eprintln!("Warning: could not open the log file: {}", e),
+    }
+}
+
+fn main() -> Result<(), eframe::Error> {
     // Check if FFmpeg is installed
     if !check_ffmpeg() {
         eprintln!("Error: FFmpeg is not installed or not found in PATH. Please install FFmpeg.");
@@ -490,6 +1081,9 @@ fn main() -> Result<(), eframe::Error> {
 
     // Parse command-line arguments
     let cli = Cli::parse();
+    set_verbose(cli.verbose);
+    set_dry_run(cli.dry_run);
+    init_logging(&cli);
 
     // Run GUI if no subcommand is provided
     if cli.command.is_none() {
@@ -503,16 +1097,29 @@ fn main() -> Result<(), eframe::Error> {
 
     // Otherwise, run the appropriate command-line tool
     match cli.command.unwrap() {
-        Commands::Clipper { input, ranges, output_dir, copy_codec, suffix, format } => {
+        Commands::Clipper {
+            input, ranges, output_dir, copy_codec, suffix, avoid_negative_ts, verify_outputs, hw_accel, format
+        } => {
             println!("Running clipper...");
 
-            let time_ranges = parse_time_ranges(&ranges);
+            let (time_ranges, warnings) = parse_time_ranges(&ranges);
             if time_ranges.is_empty() {
+                print_warnings(&warnings);
                 eprintln!("Error: No valid time ranges provided.");
                 std::process::exit(1);
             }
 
-            match clip_video(&input, &time_ranges, &output_dir, copy_codec, suffix.as_deref()) {
+            let options = ClipOptions {
+                avoid_negative_ts,
+                verify_outputs,
+                encoder: hw_accel,
+                output_format: Some(format),
+                ..ClipOptions::default()
+            };
+
+            let result = clip_video_with_options(&input, &time_ranges, &output_dir, copy_codec, suffix.as_deref(), &options);
+            print_warnings(&warnings);
+            match result {
                 Ok(true) => println!("Successfully extracted all {} clip(s).", time_ranges.len()),
                 Ok(false) => {
                     eprintln!("Completed with some errors.");
@@ -525,7 +1132,10 @@ fn main() -> Result<(), eframe::Error> {
             }
         },
 
-        Commands::GifConverter { input, output, width, fps, max_size, optimize } => {
+        Commands::GifConverter {
+            input, output, width, fps, max_size, optimize, crop, boomerang, loop_count,
+            gifsicle, gifsicle_level, gifsicle_lossy, poster
+        } => {
             println!("Running GIF converter...");
 
             // Determine output filename if not provided
@@ -548,16 +1158,34 @@ fn main() -> Result<(), eframe::Error> {
                 }
             };
 
+            let gifsicle = gifsicle.then_some(GifsicleSettings { level: gifsicle_level, lossy: gifsicle_lossy });
+            let options = GifOptions {
+                crop,
+                boomerang,
+                loop_count,
+                gifsicle,
+                write_poster: poster.is_some(),
+                poster_time: poster.flatten(),
+                cancel: None,
+            };
+
             let result = if optimize {
-                optimize_conversion(&input, &output, max_size, width)
+                optimize_conversion_with_options(&input, &output, max_size, width, &options)
             } else {
-                convert_video_to_gif(&input, &output, width, fps, max_size)
+                convert_video_to_gif_with_options(&input, &output, width, fps, max_size, &options)
             };
 
+            if let Ok(ConversionReport { poster: Some(ref poster), .. }) = result {
+                println!("Poster frame: {}", poster.display());
+            }
+            if let Ok(ref report) = result {
+                print_warnings(&report.warnings);
+            }
+
             match result {
-                Ok(true) => println!("Conversion successful! Output: {}", output),
-                Ok(false) => {
-                    eprintln!("Output file exceeds size limit (> {}MB).", max_size);
+                Ok(report) if report.under_limit => println!("Conversion successful! Output: {}", report),
+                Ok(report) => {
+                    eprintln!("Output file exceeds size limit ({:.2}MB > {}MB): {}", report.size_mb, max_size, report);
                     std::process::exit(1);
                 },
                 Err(e) => {
@@ -567,16 +1195,16 @@ fn main() -> Result<(), eframe::Error> {
             }
         },
 
-        Commands::GifTransparency { inputs, recursive, backup } => {
-            println!("Processing GIF files for transparency...");
+        Commands::GifTransparency { inputs, recursive, restore: true, .. } => {
+            if cli.dry_run {
+                eprintln!("Error: --restore cannot be combined with --dry-run");
+                std::process::exit(1);
+            }
+            println!("Restoring original GIF files...");
 
-            match batch_process_gifs(&inputs, recursive, backup) {
-                Ok((success_count, total_count)) => {
-                    println!("Successfully processed {}/{} GIF files", success_count, total_count);
-                    if success_count < total_count {
-                        eprintln!("Failed to process {} GIF files", total_count - success_count);
-                        std::process::exit(1);
-                    }
+            match batch_restore_gifs(&inputs, recursive) {
+                Ok((restored, skipped)) => {
+                    println!("Restored {} GIF files, skipped {}", restored, skipped);
                 },
                 Err(e) => {
                     eprintln!("Error: {}", e);
@@ -585,16 +1213,73 @@ fn main() -> Result<(), eframe::Error> {
             }
         },
 
-        Commands::GifTransparencyDir { directory, recursive, backup } => {
+        Commands::GifTransparency {
+            inputs, recursive, backup, restore: false, legacy, no_atomic, max_parallel, output_dir, by_content
+        } => {
+            println!("Processing GIF files for transparency...");
+
+            let options = transparency_options(legacy, no_atomic);
+            let batch_options = batch_gif_options(max_parallel, output_dir).with_content_detection(by_content);
+            match batch_process_gifs(&inputs, recursive, backup, cli.dry_run, &options, &batch_options) {
+                Ok(report) => report_transparency(&report),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+
+        Commands::GifTransparencyDir {
+            directory, recursive, backup, legacy, no_atomic, max_parallel, output_dir
+        } => {
             println!("Processing all GIFs in directory: {}", directory);
 
-            match process_directory(&directory, recursive, backup) {
-                Ok((success_count, total_count)) => {
-                    println!("Successfully processed {}/{} GIF files", success_count, total_count);
-                    if success_count < total_count {
-                        eprintln!("Failed to process {} GIF files", total_count - success_count);
-                        std::process::exit(1);
+            let options = transparency_options(legacy, no_atomic);
+            let batch_options = batch_gif_options(max_parallel, output_dir);
+            match process_directory(&directory, recursive, backup, cli.dry_run, &options, &batch_options) {
+                Ok(report) => report_transparency(&report),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+
+        Commands::Splitter { merge, output_dir, custom_encode, direction, grid, .. } if !merge.is_empty() => {
+            println!("Reassembling {} slices...", merge.len());
+
+            let output = PathBuf::from(&output_dir);
+            let result = match grid {
+                Some(grid) => merge_grid(&merge, &output, grid, custom_encode.as_deref()),
+                None => merge_slices(&merge, &output, direction, custom_encode.as_deref()),
+            };
+
+            match result {
+                Ok(()) => println!("Successfully merged slices. Output: {}", output.display()),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+
+        Commands::Splitter { input: Some(input), output_dir, prefix, custom_encode, force, jobs, grid: Some(grid), .. } => {
+            println!("Running video splitter...");
+
+            run_grid_split(&input, &output_dir, &prefix, grid, custom_encode.as_deref(), force, jobs);
+        },
+
+        Commands::Splitter {
+            input: Some(input), output_dir, prefix, segment_duration: Some(seconds), copy_codec, ..
+        } => {
+            println!("Running video splitter...");
+
+            match split_by_duration(&input, &output_dir, &prefix, seconds, copy_codec) {
+                Ok(segments) => {
+                    for segment in &segments {
+                        println!("  {}", segment.display());
                     }
+                    println!("Successfully cut video into {} segments. Files saved in: {}", segments.len(), output_dir);
                 },
                 Err(e) => {
                     eprintln!("Error: {}", e);
@@ -603,11 +1288,36 @@ fn main() -> Result<(), eframe::Error> {
             }
         },
 
-        Commands::Splitter { input, output_dir, prefix, custom_encode, force, format } => {
+        Commands::Splitter {
+            input: Some(input), output_dir, prefix, custom_encode, force, slices, direction, expect_dimensions,
+            format, overlap, jobs, hw_accel, label_slices, label_template, label_size, label_corner, grid: None, segment_duration: None, ..
+        } => {
             println!("Running video splitter...");
 
-            match split_video(&input, &output_dir, &prefix, custom_encode.as_deref(), force) {
-                Ok(true) => println!("Successfully split video into 5 slices. Files saved in: {}", output_dir),
+            let options = SplitOptions {
+                num_slices: slices,
+                direction,
+                expected_dimensions: expect_dimensions,
+                output_format: format,
+                label_slices,
+                label_style: LabelStyle {
+                    template: label_template,
+                    size_ratio: label_size,
+                    corner: label_corner,
+                },
+                overlap_px: overlap,
+                max_jobs: jobs,
+                cancel: None,
+                encoder: hw_accel,
+            };
+
+            let result = split_video_with_options(&input, &output_dir, &prefix, custom_encode.as_deref(), force, &options);
+            if let Ok(ref report) = result {
+                print_warnings(&report.warnings);
+            }
+
+            match result.map(|report| report.success) {
+                Ok(true) => println!("Successfully split video into {} slices. Files saved in: {}", slices, output_dir),
                 Ok(false) => {
                     eprintln!("Completed with some errors.");
                     std::process::exit(1);
@@ -619,11 +1329,74 @@ fn main() -> Result<(), eframe::Error> {
             }
         },
 
-        Commands::Merger { video, audio, output, shortest, copy_codec, format } => {
+        Commands::Splitter { input: None, .. } => unreachable!("clap requires an input unless --merge is given"),
+
+        Commands::Merger {
+            video, audio, output, shortest, copy_codec, audio_mode, video_volume, audio_volume, duration_policy,
+            normalize_loudness, target_lufs, true_peak, loudness_range, two_pass, subtitles, burn_subtitles,
+            audio_fade_in, audio_fade_out, format, hw_accel
+        } => {
             println!("Running audio/video merger...");
 
-            match merge_audio_video(&video, &audio, &output, shortest, copy_codec) {
-                Ok(_) => println!("Successfully merged audio and video. Output: {}", output),
+            let output = match &format {
+                Some(format) => Path::new(&output).with_extension(format).to_string_lossy().to_string(),
+                None => output,
+            };
+            let normalize_loudness = normalize_loudness.then_some(LoudnessTarget {
+                i: target_lufs,
+                tp: true_peak,
+                lra: loudness_range,
+                two_pass,
+            });
+            let options = MergeOptions {
+                audio_mode,
+                video_volume,
+                audio_volume,
+                duration_policy,
+                normalize_loudness,
+                subtitle_file: subtitles,
+                subtitle_mode: if burn_subtitles { SubtitleMode::Burn } else { SubtitleMode::Soft },
+                audio_fade_in_secs: audio_fade_in,
+                audio_fade_out_secs: audio_fade_out,
+                output_format: format,
+                cancel: None,
+                encoder: hw_accel,
+            };
+            match merge_audio_video_with_options(&video, &audio, &output, shortest, copy_codec, &options) {
+                Ok(report) => {
+                    println!("Successfully merged audio and video. Output: {}", output);
+                    if let Some(note) = report.duration_note {
+                        println!("Duration ({}): {}", duration_policy, note);
+                    }
+                    if let Some(encoder) = report.video_encoder.filter(|_| options.encoder.hw_ok) {
+                        println!("Video encoder: {}", encoder);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+
+        Commands::Concat { inputs, list, output, mode } => {
+            println!("Concatenating videos...");
+
+            let inputs = match list {
+                Some(list) => match read_concat_manifest(&list) {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                },
+                None => inputs,
+            };
+
+            match concat_videos(&inputs, &output, mode) {
+                Ok(method) => {
+                    println!("Joined {} files ({}). Output: {}", inputs.len(), method, output.display());
+                },
                 Err(e) => {
                     eprintln!("Error: {}", e);
                     std::process::exit(1);
@@ -700,28 +1473,28 @@ fn handle_plugin_command(cmd: PluginCommands) {
 
         PluginCommands::Load { path } => {
             match plugin_manager.load_plugin(Path::new(&path)) {
-                Ok(()) => {
+                Ok(meta) => {
                     println!("Plugin loaded successfully!");
 
                     // Display plugin info
-                    let plugin_name = Path::new(&path).file_stem().unwrap().to_string_lossy();
-                    if let Some(meta) = plugin_manager.with_plugin(&plugin_name, |plugin| plugin.metadata()) {
-                        println!("Name: {} v{}", meta.name, meta.version);
-                        println!("Author: {}", meta.author);
-                        println!("Description: {}", meta.description);
-
-                        // Show parameters
-                        let params = plugin_manager.get_plugin_parameters(&plugin_name).unwrap_or_default();
-                        if !params.is_empty() {
-                            println!("Parameters:");
-                            for param in params {
-                                let required = if param.required { " (required)" } else { "" };
-                                let default = match param.default_value {
-                                    Some(ref v) => format!(" [default: {}]", v),
-                                    None => String::new(),
-                                };
-
-                                println!("  {}{}{} - {}", param.name, required, default, param.description);
+                    println!("Name: {} v{}", meta.name, meta.version);
+                    println!("Author: {}", meta.author);
+                    println!("Description: {}", meta.description);
+
+                    // Show parameters
+                    let params = plugin_manager.get_plugin_parameters(&meta.name).unwrap_or_default();
+                    if !params.is_empty() {
+                        println!("Parameters:");
+                        for param in params {
+                            let required = if param.required { " (required)" } else { "" };
+                            let default = match param.default_value {
+                                Some(ref v) => format!(" [default: {}]", v),
+                                None => String::new(),
+                            };
+
+                            println!("  {}{}{} - {}", param.name, required, default, param.description);
+                            if let ParameterType::Choice(choices) = &param.parameter_type {
+                                println!("      one of: {}", choices.join(", "));
                             }
                         }
                     }
@@ -733,39 +1506,38 @@ fn handle_plugin_command(cmd: PluginCommands) {
             }
         },
 
-        PluginCommands::Run { name, params } => {
+        PluginCommands::Run { name, params, timeout } => {
             // Check if plugin exists
             if !plugin_manager.with_plugin(&name, |_| true).unwrap_or(false) {
                 eprintln!("Plugin '{}' not found.", name);
                 std::process::exit(1);
             }
 
-            // Parse parameters
-            let mut param_map = HashMap::new();
-            for param in params {
-                let parts: Vec<&str> = param.splitn(2, '=').collect();
-                if parts.len() == 2 {
-                    param_map.insert(parts[0].to_string(), parts[1].to_string());
-                } else {
-                    eprintln!("Invalid parameter format: {}. Expected key=value", param);
-                    std::process::exit(1);
-                }
-            }
+            let param_map = parse_plugin_params(&params);
 
-            // Check required parameters
-            if let Some(param_info) = plugin_manager.get_plugin_parameters(&name) {
-                for info in &param_info {
-                    if info.required && !param_map.contains_key(&info.name) {
-                        eprintln!("Missing required parameter: {}", info.name);
-                        std::process::exit(1);
-                    }
-                }
+            // Execute the plugin; its parameters are checked first
+            let mut ctx = ExecutionContext::new();
+            if let Some(secs) = timeout {
+                ctx = ctx.with_timeout(Duration::from_secs(secs));
             }
-
-            // Execute the plugin
-            match plugin_manager.execute_plugin(&name, param_map) {
-                Ok(()) => println!("Plugin executed successfully!"),
+            match plugin_manager.execute_plugin_with_context(&name, param_map, &ctx) {
+                Ok(output) => {
+                    println!("Plugin executed successfully!");
+                    for (key, value) in &output.values {
+                        println!("  {} = {}", key, value);
+                    }
+                    for file in &output.files {
+                        println!("  Wrote {}", file.display());
+                    }
+                },
                 Err(e) => {
+                    if let Some(PluginError::InvalidParameters(issues)) = e.downcast_ref::<PluginError>() {
+                        eprintln!("Invalid parameters for plugin '{}':", name);
+                        for issue in issues {
+                            eprintln!("  {}", issue);
+                        }
+                        std::process::exit(1);
+                    }
                     eprintln!("Error executing plugin: {}", e);
                     std::process::exit(1);
                 }
@@ -773,21 +1545,40 @@ fn handle_plugin_command(cmd: PluginCommands) {
         },
 
         PluginCommands::Discover => {
-            let results = plugin_manager.discover_plugins();
-
-            let successes: Vec<_> = results.iter().filter_map(|r| r.as_ref().ok()).collect();
-            let failures: Vec<_> = results.iter().filter_map(|r| r.as_ref().err()).collect();
+            let report = plugin_manager.discover_plugins();
+            if is_verbose() {
+                println!("Plugin search path:");
+                for dir in plugin_manager.plugin_directories() {
+                    let scanned = if report.directories.contains(&dir) { "" } else { " (not found)" };
+                    println!("  {}{}", dir.display(), scanned);
+                }
+            }
+            let loaded: Vec<_> = report.loaded().collect();
+            let skipped: Vec<_> = report.skipped().collect();
+            let failures: Vec<_> = report.failed().collect();
 
-            println!("Discovered {} plugin(s).", successes.len());
+            println!("Discovered {} plugin(s).", loaded.len());
 
-            if !successes.is_empty() {
+            if !loaded.is_empty() {
                 println!("Successfully loaded plugins:");
-                for meta in successes {
+                for meta in loaded {
                     println!("  {} v{} by {}", meta.name, meta.version, meta.author);
                     println!("    {}", meta.description);
                 }
             }
 
+            let disabled: Vec<_> = report.disabled().collect();
+            if !disabled.is_empty() {
+                println!("Disabled (enable with `plugin enable <name>`): {}", disabled.join(", "));
+            }
+
+            if !skipped.is_empty() {
+                println!("Skipped {} plugin(s):", skipped.len());
+                for (path, reason) in skipped {
+                    println!("  {}: {}", path.display(), reason);
+                }
+            }
+
             if !failures.is_empty() {
                 println!("Failed to load {} plugin(s):", failures.len());
                 for error in failures {
@@ -795,6 +1586,65 @@ fn handle_plugin_command(cmd: PluginCommands) {
                 }
             }
         },
+
+        PluginCommands::Enable { name } => set_plugin_enabled(&plugin_manager, &name, true),
+
+        PluginCommands::Disable { name } => set_plugin_enabled(&plugin_manager, &name, false),
+    }
+}
+
+/// Parse `key=value` plugin parameters, exiting on a malformed one
+fn parse_plugin_params(params: &[String]) -> HashMap<String, String> {
+    let mut param_map = HashMap::new();
+    for param in params {
+        match param.split_once('=') {
+            Some((key, value)) => {
+                param_map.insert(key.to_string(), value.to_string());
+            },
+            None => {
+                eprintln!("Invalid parameter format: {}. Expected key=value", param);
+                std::process::exit(1);
+            },
+        }
+    }
+    param_map
+}
+
+/// Save whether discovery loads a plugin, for `plugin enable` and `plugin disable`
+fn set_plugin_enabled(plugin_manager: &PluginManager, name: &str, enabled: bool) {
+    match plugin_manager.set_enabled(name, enabled) {
+        Ok(()) => println!("Plugin '{}' {}.", name, if enabled { "enabled" } else { "disabled" }),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print one line per profile of a type for `profile list --long`
+fn print_profile_summaries(profile_manager: &ProfileManager, profile_type: ProfileType, indent: &str) {
+    let summaries = match profile_manager.list_profiles_detailed(profile_type) {
+        Ok(summaries) => summaries,
+        Err(e) => {
+            eprintln!("Error listing profiles: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    for summary in summaries {
+        if summary.invalid {
+            println!("{}{}  (unreadable profile file)", indent, summary.name);
+            continue;
+        }
+        let modified = summary.last_modified
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let kind = if summary.builtin { "preset, " } else { "" };
+        print!("{}{}  ({}{} parameter(s), modified {})", indent, summary.name, kind, summary.parameter_count, modified);
+        match summary.description {
+            Some(description) => println!("  {}", description),
+            None => println!(),
+        }
     }
 }
 
@@ -808,7 +1658,47 @@ fn handle_profile_command(cmd: ProfileCommands) {
     };
 
     match cmd {
-        ProfileCommands::List { profile_type } => {
+        ProfileCommands::List { profile_type, long, search: Some(query) } => {
+            let profile_type = profile_type.map(|type_str| match type_str.as_str() {
+                "clipper" => ProfileType::Clipper,
+                "gif_converter" => ProfileType::GifConverter,
+                "gif_transparency" => ProfileType::GifTransparency,
+                "splitter" => ProfileType::Splitter,
+                "merger" => ProfileType::Merger,
+                other => ProfileType::Custom(other.to_string()),
+            });
+
+            let results = match profile_manager.search(&query) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("Error searching profiles: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let results: Vec<_> = results
+                .into_iter()
+                .filter(|(found_type, _)| profile_type.as_ref().is_none_or(|t| t == found_type))
+                .collect();
+
+            if results.is_empty() {
+                println!("No profiles match '{}'.", query);
+                return;
+            }
+
+            println!("Profiles matching '{}':", query);
+            for (found_type, summary) in results {
+                print!("  {:?}: {}", found_type, summary.name);
+                if !summary.tags.is_empty() {
+                    print!("  [{}]", summary.tags.join(", "));
+                }
+                match summary.description.filter(|_| long) {
+                    Some(description) => println!("  {}", description),
+                    None => println!(),
+                }
+            }
+        },
+
+        ProfileCommands::List { profile_type, long, search: None } => {
             if let Some(type_str) = profile_type {
                 // List profiles of a specific type
                 let profile_type = match type_str.as_str() {
@@ -821,16 +1711,28 @@ fn handle_profile_command(cmd: ProfileCommands) {
                 };
                 let profile_type_display = profile_type.clone();
 
-                match profile_manager.list_profiles(profile_type) {
+                let profiles = profile_manager.list_profiles_detailed(profile_type)
+                    .map(|summaries| summaries.into_iter().map(|summary| summary.name).collect::<Vec<_>>());
+                match profiles {
                     Ok(profiles) => {
                         if profiles.is_empty() {
                             println!("No profiles found for type: {:?}", profile_type_display);
+                            if let ProfileType::Custom(_) = profile_type_display {
+                                let custom_types = profile_manager.list_custom_types().unwrap_or_default();
+                                if !custom_types.is_empty() {
+                                    println!("Custom profile types: {}", custom_types.join(", "));
+                                }
+                            }
                             return;
                         }
 
                         println!("Profiles for type {:?}:", profile_type_display);
-                        for name in profiles {
-                            println!("  {}", name);
+                        if long {
+                            print_profile_summaries(&profile_manager, profile_type_display, "  ");
+                        } else {
+                            for name in profiles {
+                                println!("  {}", name);
+                            }
                         }
                     },
                     Err(e) => {
@@ -840,19 +1742,36 @@ fn handle_profile_command(cmd: ProfileCommands) {
                 }
             } else {
                 // List all profiles
-                match profile_manager.list_all_profiles() {
-                    Ok(all_profiles) => {
+                let listed = profile_manager.list_all_profiles().and_then(|all_profiles| {
+                    Ok((all_profiles, profile_manager.list_custom_types()?))
+                });
+                match listed {
+                    Ok((mut all_profiles, custom_types)) => {
                         if all_profiles.is_empty() {
                             println!("No profiles found.");
                             return;
                         }
 
                         println!("Available profiles:");
-                        for (profile_type, profiles) in all_profiles {
+                        let profile_types = [
+                            ProfileType::Clipper,
+                            ProfileType::GifConverter,
+                            ProfileType::GifTransparency,
+                            ProfileType::Splitter,
+                            ProfileType::Merger,
+                        ]
+                        .into_iter()
+                        .chain(custom_types.into_iter().map(ProfileType::Custom));
+                        for profile_type in profile_types {
+                            let profiles = all_profiles.remove(&profile_type).unwrap_or_default();
                             if !profiles.is_empty() {
                                 println!("  {:?}:", profile_type);
-                                for name in profiles {
-                                    println!("    {}", name);
+                                if long {
+                                    print_profile_summaries(&profile_manager, profile_type, "    ");
+                                } else {
+                                    for name in profiles {
+                                        println!("    {}", name);
+                                    }
                                 }
                             }
                         }
@@ -881,6 +1800,9 @@ fn handle_profile_command(cmd: ProfileCommands) {
                     if let Some(desc) = profile.description {
                         println!("Description: {}", desc);
                     }
+                    if !profile.tags.is_empty() {
+                        println!("Tags: {}", profile.tags.join(", "));
+                    }
                     println!("Created: {}", profile.created);
                     println!("Last modified: {}", profile.last_modified);
                     println!("Parameters:");
@@ -895,7 +1817,7 @@ fn handle_profile_command(cmd: ProfileCommands) {
             }
         },
 
-        ProfileCommands::Create { name, profile_type, description, params } => {
+        ProfileCommands::Create { name, profile_type, description, params, tags, no_validate } => {
             // Parse parameters
             let mut parameters = HashMap::new();
             for param in params {
@@ -918,14 +1840,22 @@ fn handle_profile_command(cmd: ProfileCommands) {
                 other => ProfileType::Custom(other.to_string()),
             };
 
-            let mut profile = Profile::new(&name, profile_type, parameters);
+            let mut profile = Profile::new(&name, profile_type, parameters).with_tags(tags);
             if let Some(desc) = description {
                 profile = profile.with_description(&desc);
             }
 
             // Save profile
-            match profile_manager.save_profile(&profile) {
+            match profile_manager.with_validation(!no_validate).save_profile(&profile) {
                 Ok(()) => println!("Profile '{}' created successfully!", name),
+                Err(ProfileError::InvalidParameters(issues)) => {
+                    eprintln!("Profile '{}' was not saved, its parameters have {} problem(s):", name, issues.len());
+                    for issue in issues {
+                        eprintln!("  - {}", issue);
+                    }
+                    eprintln!("Fix them, or pass --no-validate to save it anyway.");
+                    std::process::exit(1);
+                },
                 Err(e) => {
                     eprintln!("Error creating profile: {}", e);
                     std::process::exit(1);
@@ -952,8 +1882,31 @@ fn handle_profile_command(cmd: ProfileCommands) {
             }
         },
 
-        ProfileCommands::Import { path } => {
-            match profile_manager.import_profile(Path::new(&path)) {
+        ProfileCommands::Duplicate { name, profile_type, new_name } => {
+            let profile_type = match profile_type.as_str() {
+                "clipper" => ProfileType::Clipper,
+                "gif_converter" => ProfileType::GifConverter,
+                "gif_transparency" => ProfileType::GifTransparency,
+                "splitter" => ProfileType::Splitter,
+                "merger" => ProfileType::Merger,
+                other => ProfileType::Custom(other.to_string()),
+            };
+
+            match profile_manager.duplicate_profile(&name, profile_type, &new_name) {
+                Ok(_) => println!("Profile '{}' duplicated as '{}'!", name, new_name),
+                Err(e) => {
+                    eprintln!("Error duplicating profile: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+
+        ProfileCommands::Import { path, format } => {
+            let imported = match format {
+                Some(format) => profile_manager.import_profile_as(Path::new(&path), format),
+                None => profile_manager.import_profile(Path::new(&path)),
+            };
+            match imported {
                 Ok(profile) => println!("Profile '{}' imported successfully!", profile.name),
                 Err(e) => {
                     eprintln!("Error importing profile: {}", e);
@@ -962,7 +1915,7 @@ fn handle_profile_command(cmd: ProfileCommands) {
             }
         },
 
-        ProfileCommands::Export { name, profile_type, output } => {
+        ProfileCommands::Export { name, profile_type, output, format } => {
             let profile_type = match profile_type.as_str() {
                 "clipper" => ProfileType::Clipper,
                 "gif_converter" => ProfileType::GifConverter,
@@ -972,7 +1925,11 @@ fn handle_profile_command(cmd: ProfileCommands) {
                 other => ProfileType::Custom(other.to_string()),
             };
 
-            match profile_manager.export_profile(&name, profile_type, Path::new(&output)) {
+            let exported = match format {
+                Some(format) => profile_manager.export_profile_as(&name, profile_type, Path::new(&output), format),
+                None => profile_manager.export_profile(&name, profile_type, Path::new(&output)),
+            };
+            match exported {
                 Ok(()) => println!("Profile '{}' exported to '{}'!", name, output),
                 Err(e) => {
                     eprintln!("Error exporting profile: {}", e);
@@ -980,290 +1937,551 @@ fn handle_profile_command(cmd: ProfileCommands) {
                 }
             }
         },
-    }
-}
-
-fn handle_batch_command(cmd: BatchCommands) {
-    match cmd {
-        BatchCommands::Clipper { inputs, recursive, pattern, output_dir, ranges, copy_codec, suffix, parallel, format } => {
-            println!("Running batch clipper...");
 
-            // Parse time ranges
-            let time_ranges_result = parse_time_ranges(&ranges);
-            if time_ranges_result.is_empty() {
-                eprintln!("Error: No valid time ranges provided.");
-                std::process::exit(1);
-            }
-
-            // Create processor
-            let mut processor = match BatchProcessor::create_clipper(
-                &ranges,
-                Path::new(&output_dir),
-                copy_codec,
-                suffix.as_deref()
-            ) {
-                Ok(p) => p,
+        ProfileCommands::ExportAll { output } => {
+            match profile_manager.export_all(Path::new(&output)) {
+                Ok(count) => println!("{} profile(s) exported to '{}'!", count, output),
                 Err(e) => {
-                    eprintln!("Error configuring batch processor: {}", e);
+                    eprintln!("Error exporting profiles: {}", e);
                     std::process::exit(1);
                 }
-            };
-
-            // Configure processor
-            processor = processor.with_recursive(recursive).with_parallel(parallel);
-
-            if let Some(pat) = pattern {
-                processor = match processor.with_pattern(&pat) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        eprintln!("Error setting pattern: {}", e);
-                        std::process::exit(1);
-                    }
-                };
             }
+        },
 
-            // Process files
-            match processor.process(&inputs) {
-                Ok(results) => {
-                    let success_count = results.iter().filter(|r| r.success).count();
-                    println!("Successfully processed {}/{} files.", success_count, results.len());
-
-                    if success_count < results.len() {
-                        eprintln!("Errors occurred during processing:");
-                        for result in results.iter().filter(|r| !r.success) {
-                            if let Some(ref error) = result.error_message {
-                                eprintln!("  {}: {}", result.input.display(), error);
-                            }
-                        }
-                        std::process::exit(1);
-                    }
-                },
+        ProfileCommands::ImportBundle { path, on_conflict } => {
+            let report = match profile_manager.import_bundle(Path::new(&path), on_conflict) {
+                Ok(report) => report,
                 Err(e) => {
-                    eprintln!("Error during batch processing: {}", e);
+                    eprintln!("Error importing profiles: {}", e);
                     std::process::exit(1);
                 }
+            };
+
+            for (profile_type, name) in &report.imported {
+                println!("  Imported {:?} '{}'", profile_type, name);
+            }
+            for (profile_type, name, new_name) in &report.renamed {
+                println!("  Imported {:?} '{}' as '{}'", profile_type, name, new_name);
+            }
+            for (profile_type, name) in &report.skipped {
+                println!("  Skipped {:?} '{}': a profile of that name exists", profile_type, name);
             }
+            for (name, error) in &report.failed {
+                eprintln!("  Failed '{}': {}", name, error);
+            }
+            println!(
+                "{} imported, {} renamed, {} skipped, {} failed.",
+                report.imported.len(), report.renamed.len(), report.skipped.len(), report.failed.len()
+            );
+            if !report.failed.is_empty() {
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+impl InputFilterArgs {
+    /// Apply these options over `settings`, which may come from a profile
+    fn apply_to(self, settings: &mut BatchSettings) {
+        settings.recursive |= self.recursive;
+        settings.follow_symlinks |= self.follow_symlinks;
+        settings.max_depth = self.max_depth.or(settings.max_depth);
+        settings.pattern = self.pattern.or(settings.pattern.take());
+        if self.pattern_target != PatternTarget::FileName {
+            settings.pattern_target = self.pattern_target;
+        }
+        settings.include.extend(self.include);
+        settings.exclude.extend(self.exclude);
+        settings.by_content |= self.by_content;
+        settings.min_input_size = self.min_input_size.or(settings.min_input_size.take());
+        settings.max_input_size = self.max_input_size.or(settings.max_input_size.take());
+    }
+}
+
+impl FilePolicyArgs {
+    /// Apply these options over `settings`, which may come from a profile
+    fn apply_to(self, settings: &mut BatchSettings) {
+        settings.per_file_timeout = self.per_file_timeout.or(settings.per_file_timeout);
+        if self.retries > 0 {
+            settings.retries = self.retries;
+        }
+        settings.retry_backoff = self.retry_backoff.or(settings.retry_backoff);
+    }
+}
+
+impl BatchArgs {
+    /// Run a batch over the inputs with `settings`, these options taking precedence
+    fn run(self, mut settings: BatchSettings) {
+        self.filters.apply_to(&mut settings);
+        self.policy.apply_to(&mut settings);
+        settings.parallel = self.parallel;
+        settings.files_from = self.files_from;
+        settings.since = self.since;
+        settings.until = self.until;
+        if self.order != FileOrder::Name || self.descending {
+            settings.order = self.order;
+            settings.descending = self.descending;
+        }
+        settings.limit = self.limit;
+        settings.sample = self.sample;
+        settings.seed = self.seed;
+        settings.checkpoint = self.checkpoint;
+        if self.fail_fast || self.max_failures.is_some() {
+            settings.fail_fast = self.fail_fast;
+            settings.max_failures = self.max_failures;
+        }
+
+        run_batch(&settings, &self.inputs, self.report.as_deref());
+    }
+}
+
+fn handle_batch_command(cmd: BatchCommands) {
+    match cmd {
+        BatchCommands::Clipper {
+            batch, mirror_structure, output_dir, ranges, copy_codec, suffix, avoid_negative_ts, verify_outputs, hw_accel,
+            format
+        } => {
+            println!("Running batch clipper...");
+
+            let operation = OperationSettings::Clipper {
+                ranges,
+                output_dir: PathBuf::from(output_dir),
+                copy_codec,
+                suffix,
+                avoid_negative_ts,
+                verify_outputs,
+                hw_accel,
+                format: Some(format),
+            };
+            batch.run(BatchSettings { mirror_structure, ..BatchSettings::new(operation) });
         },
 
-        BatchCommands::GifConverter { inputs, recursive, pattern, output_dir, width, fps, max_size, optimize, parallel } => {
+        BatchCommands::GifConverter {
+            batch, mirror_structure, output_dir, width, fps, max_size, optimize, crop, boomerang, loop_count,
+            gifsicle, gifsicle_level, gifsicle_lossy, poster
+        } => {
             println!("Running batch GIF converter...");
 
-            // Create processor
-            let mut processor = BatchProcessor::create_gif_converter(
+            let operation = OperationSettings::GifConverter {
                 width,
                 fps,
-                max_size,
+                max_size_mb: max_size,
                 optimize,
-                Path::new(&output_dir)
-            );
+                output_dir: PathBuf::from(output_dir),
+                crop,
+                boomerang,
+                loop_count,
+                gifsicle,
+                gifsicle_level,
+                gifsicle_lossy,
+                poster: poster.is_some(),
+                poster_time: poster.flatten(),
+            };
+            batch.run(BatchSettings { mirror_structure, ..BatchSettings::new(operation) });
+        },
 
-            // Configure processor
-            processor = processor.with_recursive(recursive).with_parallel(parallel);
+        BatchCommands::GifTransparency {
+            batch, backup, backup_dir, also_webp, webp_quality, webp_lossless, webp_output_dir, legacy, no_atomic, output_dir
+        } => {
+            println!("Running batch GIF transparency processor...");
 
-            if let Some(pat) = pattern {
-                processor = match processor.with_pattern(&pat) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        eprintln!("Error setting pattern: {}", e);
-                        std::process::exit(1);
-                    }
-                };
-            }
+            let webp = if also_webp {
+                Some(BatchWebpConfig {
+                    quality: webp_quality,
+                    lossless: webp_lossless,
+                    output_dir: webp_output_dir,
+                })
+            } else {
+                None
+            };
 
-            // Process files
-            match processor.process(&inputs) {
-                Ok(results) => {
-                    let success_count = results.iter().filter(|r| r.success).count();
-                    println!("Successfully processed {}/{} files.", success_count, results.len());
-
-                    if success_count < results.len() {
-                        eprintln!("Errors occurred during processing:");
-                        for result in results.iter().filter(|r| !r.success) {
-                            if let Some(ref error) = result.error_message {
-                                eprintln!("  {}: {}", result.input.display(), error);
-                            }
-                        }
-                        std::process::exit(1);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Error during batch processing: {}", e);
-                    std::process::exit(1);
-                }
-            }
+            let operation = OperationSettings::GifTransparency {
+                create_backup: backup,
+                backup_dir,
+                webp,
+                legacy_trailer: legacy,
+                no_atomic,
+                output_dir,
+            };
+            batch.run(BatchSettings::new(operation));
         },
 
-        BatchCommands::GifTransparency { inputs, recursive, pattern, backup, parallel } => {
-            println!("Running batch GIF transparency processor...");
+        BatchCommands::Splitter {
+            batch, mirror_structure, output_dir, prefix, custom_encode, force, slices, direction, expect_dimensions,
+            segment_duration, copy_codec, format, jobs, hw_accel
+        } => {
+            println!("Running batch video splitter...");
 
-            // Create processor
-            let mut processor = BatchProcessor::create_gif_transparency(backup);
+            let operation = OperationSettings::Splitter {
+                output_dir: PathBuf::from(output_dir),
+                prefix,
+                custom_encode,
+                force,
+                slices: Some(slices),
+                direction: Some(direction.to_string()),
+                expected_dimensions: expect_dimensions,
+                format: Some(format),
+                segment_duration,
+                copy_codec,
+                jobs,
+                hw_accel,
+            };
+            batch.run(BatchSettings { mirror_structure, ..BatchSettings::new(operation) });
+        },
 
-            // Configure processor
-            processor = processor.with_recursive(recursive).with_parallel(parallel);
+        BatchCommands::Merger {
+            batch, mirror_structure, audio, audio_ext, audio_dir, output_dir, shortest, copy_codec, audio_fade_in,
+            audio_fade_out, format, hw_accel
+        } => {
+            println!("Running batch audio/video merger...");
 
-            if let Some(pat) = pattern {
-                processor = match processor.with_pattern(&pat) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        eprintln!("Error setting pattern: {}", e);
-                        std::process::exit(1);
-                    }
-                };
-            }
+            let operation = OperationSettings::Merger {
+                audio_file: audio,
+                output_dir: PathBuf::from(output_dir),
+                shortest,
+                copy_codec,
+                audio_ext,
+                audio_dir,
+                audio_fade_in,
+                audio_fade_out,
+                format: Some(format),
+                hw_accel,
+            };
+            batch.run(BatchSettings { mirror_structure, ..BatchSettings::new(operation) });
+        },
 
-            // Process files
-            match processor.process(&inputs) {
-                Ok(results) => {
-                    let success_count = results.iter().filter(|r| r.success).count();
-                    println!("Successfully processed {}/{} files.", success_count, results.len());
-
-                    if success_count < results.len() {
-                        eprintln!("Errors occurred during processing:");
-                        for result in results.iter().filter(|r| !r.success) {
-                            if let Some(ref error) = result.error_message {
-                                eprintln!("  {}: {}", result.input.display(), error);
-                            }
-                        }
-                        std::process::exit(1);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Error during batch processing: {}", e);
-                    std::process::exit(1);
-                }
-            }
+        BatchCommands::Plugin { name, batch, mirror_structure, params, extensions, output_dir } => {
+            println!("Running plugin '{}' in batch...", name);
+
+            let operation = OperationSettings::Plugin {
+                name,
+                params: parse_plugin_params(&params).into_iter().collect(),
+                output_dir: PathBuf::from(output_dir),
+                extensions,
+            };
+            batch.run(BatchSettings { mirror_structure, ..BatchSettings::new(operation) });
         },
 
-        BatchCommands::Splitter { inputs, recursive, pattern, output_dir, prefix, custom_encode, force, parallel, format } => {
-            println!("Running batch video splitter...");
+        BatchCommands::WithProfile { batch, mirror_structure, profile, profile_type } => {
+            println!("Running batch processing with profile '{}'...", profile);
+            let mut settings = load_profile_settings(&profile, &profile_type);
 
-            // Create processor
-            let mut processor = BatchProcessor::create_splitter(
-                Path::new(&output_dir),
-                &prefix,
-                custom_encode.as_deref(),
-                force
-            );
+            // Command-line options take precedence over the profile
+            settings.mirror_structure |= mirror_structure;
+            batch.run(settings);
+        },
+        BatchCommands::Watch {
+            inputs, filters, policy, mirror_structure, poll_interval, profile, profile_type, parallel
+        } => {
+            let mut settings = load_profile_settings(&profile, &profile_type);
+
+            // Command-line options take precedence over the profile
+            filters.apply_to(&mut settings);
+            policy.apply_to(&mut settings);
+            settings.parallel = parallel;
+            settings.mirror_structure |= mirror_structure;
+
+            watch_batch(&settings, &inputs, std::time::Duration::from_secs(poll_interval));
+        },
+    }
+}
 
-            // Configure processor
-            processor = processor.with_recursive(recursive).with_parallel(parallel);
+/// Load a batch profile for `batch with-profile` or `batch watch`, exiting on failure
+fn load_profile_settings(name: &str, profile_type: &str) -> BatchSettings {
+    let profile_manager = match ProfileManager::new() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Error creating profile manager: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-            if let Some(pat) = pattern {
-                processor = match processor.with_pattern(&pat) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        eprintln!("Error setting pattern: {}", e);
-                        std::process::exit(1);
-                    }
-                };
-            }
+    let profile_type = match profile_type {
+        "clipper" => ProfileType::Clipper,
+        "gif_converter" => ProfileType::GifConverter,
+        "gif_transparency" => ProfileType::GifTransparency,
+        "splitter" => ProfileType::Splitter,
+        "merger" => ProfileType::Merger,
+        other => ProfileType::Custom(other.to_string()),
+    };
 
-            // Process files
-            match processor.process(&inputs) {
-                Ok(results) => {
-                    let success_count = results.iter().filter(|r| r.success).count();
-                    println!("Successfully processed {}/{} files.", success_count, results.len());
-
-                    if success_count < results.len() {
-                        eprintln!("Errors occurred during processing:");
-                        for result in results.iter().filter(|r| !r.success) {
-                            if let Some(ref error) = result.error_message {
-                                eprintln!("  {}: {}", result.input.display(), error);
-                            }
-                        }
-                        std::process::exit(1);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Error during batch processing: {}", e);
-                    std::process::exit(1);
-                }
+    let profile = match profile_manager.load_profile(name, profile_type) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error loading profile: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match BatchSettings::from_profile(&profile) {
+        Ok(s) => {
+            if let Err(e) = profile_manager.record_usage(&profile.name, profile.profile_type.clone()) {
+                eprintln!("Warning: could not record the profile as recently used: {}", e);
             }
+            s
         },
+        Err(e) => {
+            eprintln!("Error reading profile '{}': {}", profile.name, e);
+            std::process::exit(1);
+        }
+    }
+}
 
-        BatchCommands::Merger { inputs, recursive, pattern, audio, output_dir, shortest, copy_codec, parallel, format } => {
-            println!("Running batch audio/video merger...");
+/// Watch the inputs with the settings of a profile until Ctrl-C, printing one line per file
+fn watch_batch(settings: &BatchSettings, inputs: &[PathBuf], poll_interval: std::time::Duration) {
+    let token = CancellationToken::new();
+    let stop = token.clone();
+    if let Err(e) = ctrlc::set_handler(move || stop.cancel()) {
+        eprintln!("Warning: could not install the Ctrl-C handler: {}", e);
+    }
 
-            // Create processor
-            let mut processor = BatchProcessor::create_merger(
-                &audio,
-                Path::new(&output_dir),
-                shortest,
-                copy_codec
-            );
+    let processor = match BatchController::build_processor(settings) {
+        Ok(p) => p.with_cancellation(token),
+        Err(e) => {
+            eprintln!("Error configuring batch processor: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-            // Configure processor
-            processor = processor.with_recursive(recursive).with_parallel(parallel);
+    let watched: Vec<String> = inputs.iter().map(|input| input.display().to_string()).collect();
+    println!("Watching {} for new files (press Ctrl-C to stop)...", watched.join(", "));
 
-            if let Some(pat) = pattern {
-                processor = match processor.with_pattern(&pat) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        eprintln!("Error setting pattern: {}", e);
-                        std::process::exit(1);
-                    }
-                };
+    let result = processor.watch(inputs, poll_interval, |result| {
+        if result.success {
+            match result.output {
+                Some(ref output) => println!("OK      {} -> {}", result.input.display(), output.display()),
+                None => println!("OK      {}", result.input.display()),
             }
+            if let Some(ref message) = result.message {
+                println!("        {}", message);
+            }
+        } else {
+            let error = result.error_message.as_deref().unwrap_or("unknown error");
+            eprintln!("FAILED  {}: {}", result.input.display(), error);
+        }
+    });
 
-            // Process files
-            match processor.process(&inputs) {
-                Ok(results) => {
-                    let success_count = results.iter().filter(|r| r.success).count();
-                    println!("Successfully processed {}/{} files.", success_count, results.len());
-
-                    if success_count < results.len() {
-                        eprintln!("Errors occurred during processing:");
-                        for result in results.iter().filter(|r| !r.success) {
-                            if let Some(ref error) = result.error_message {
-                                eprintln!("  {}: {}", result.input.display(), error);
-                            }
-                        }
-                        std::process::exit(1);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Error during batch processing: {}", e);
-                    std::process::exit(1);
+    match result {
+        Ok(processed) => println!("Stopped watching after processing {} file(s).", processed),
+        Err(e) => {
+            eprintln!("Error while watching: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print the "Warnings (N)" section, if there are any
+fn print_warnings(warnings: &[Warning]) {
+    if let Some(section) = format_warnings(warnings) {
+        eprintln!("{}", section);
+    }
+}
+
+/// Exit code of a batch stopped by `--fail-fast` or `--max-failures`, as
+/// opposed to 1 for a batch that ran to the end with errors
+const EXIT_BATCH_ABORTED: i32 = 3;
+
+/// Build and run a batch from CLI settings, exiting with an error status on failure
+fn run_batch(settings: &BatchSettings, inputs: &[PathBuf], report_path: Option<&Path>) {
+    // Catch a bad report name before the run rather than after it
+    let report_format = match report_path.map(ReportFormat::from_path).transpose() {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let processor = match BatchController::build_processor(settings) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error configuring batch processor: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if is_dry_run() {
+        print_plan(&processor, inputs);
+    }
+
+    // The default progress bar is used when no callback is given
+    let report = match BatchController::run(processor, inputs, None::<fn(&BatchProgress)>) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error during batch processing: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    report_exclusions(&report.exclusions);
+    // Notes on finished files, e.g. where backups went and how much a WebP saved
+    for result in report.results.iter().filter(|r| r.success) {
+        if let Some(ref message) = result.message {
+            println!("  {}: {}", result.input.display(), message);
+        }
+    }
+    let success_count = report.success_count();
+    println!("Successfully processed {}/{} files.", success_count, report.results.len());
+    println!("{}", report.summary);
+    let overridden = report.results.iter().filter(|r| r.sidecar.is_some()).count();
+    if overridden > 0 {
+        println!("Sidecar overrides applied to {} file(s).", overridden);
+    }
+    if let Some(ref summary) = report.selection_summary {
+        println!("Note: {}.", summary);
+    }
+
+    if success_count < report.results.len() {
+        eprintln!("Errors occurred during processing:");
+        for result in report.failures() {
+            if let Some(ref error) = result.error_message {
+                if result.attempts > 1 {
+                    eprintln!("  {} (after {} attempts): {}", result.input.display(), result.attempts, error);
+                } else {
+                    eprintln!("  {}: {}", result.input.display(), error);
                 }
             }
-        },
+        }
+    }
 
-        BatchCommands::WithProfile { inputs, recursive, pattern, profile, profile_type, parallel } => {
-            println!("Running batch processing with profile '{}'...", profile);
+    let warnings: Vec<Warning> = report.warnings().cloned().collect();
+    print_warnings(&warnings);
 
-            // Load profile
-            let profile_manager = match ProfileManager::new() {
-                Ok(m) => m,
-                Err(e) => {
-                    eprintln!("Error creating profile manager: {}", e);
-                    std::process::exit(1);
-                }
-            };
+    if let (Some(path), Some(format)) = (report_path, report_format) {
+        match write_report(&report.results, format, path) {
+            Ok(()) => println!("Report written to {}", path.display()),
+            Err(e) => eprintln!("Error writing the report: {}", e),
+        }
+    }
 
-            let profile_type = match profile_type.as_str() {
-                "clipper" => ProfileType::Clipper,
-                "gif_converter" => ProfileType::GifConverter,
-                "gif_transparency" => ProfileType::GifTransparency,
-                "splitter" => ProfileType::Splitter,
-                "merger" => ProfileType::Merger,
-                other => ProfileType::Custom(other.to_string()),
-            };
+    if report.aborted {
+        eprintln!("Batch stopped early: the failure limit was reached and the remaining files were not started.");
+        std::process::exit(EXIT_BATCH_ABORTED);
+    }
+    if success_count < report.results.len() {
+        std::process::exit(1);
+    }
+}
 
-            let profile = match profile_manager.load_profile(&profile, profile_type) {
-                Ok(p) => p,
-                Err(e) => {
-                    eprintln!("Error loading profile: {}", e);
-                    std::process::exit(1);
-                }
-            };
+/// Print the matched files and the outputs they would produce, before a dry run
+/// prints the FFmpeg commands
+fn print_plan(processor: &BatchProcessor, inputs: &[PathBuf]) {
+    let plan = match processor.plan(inputs) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Error during batch processing: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Dry run: {} output(s) planned:", plan.len());
+    for item in &plan {
+        println!("  {} -> {}", item.input.display(), item.output.display());
+    }
+}
 
-            // TODO: Implement profile-based batch processing
-            println!("Profile-based batch processing not fully implemented yet.");
-            println!("Profile details:");
-            println!("  Name: {}", profile.name);
-            println!("  Type: {:?}", profile.profile_type);
-            println!("  Parameters: {:?}", profile.parameters);
+fn transparency_options(legacy: bool, no_atomic: bool) -> TransparencyOptions {
+    TransparencyOptions {
+        mode: if legacy {
+            TransparencyMode::LegacyTrailer
+        } else {
+            TransparencyMode::GraphicControl
         },
+        atomic: !no_atomic,
+    }
+}
+
+fn batch_gif_options(max_parallel: Option<usize>, output_dir: Option<PathBuf>) -> BatchGifOptions {
+    BatchGifOptions { max_parallel, output_dir, ..BatchGifOptions::default() }
+}
+
+/// Check custom encode options while parsing the command line, keeping them as written
+fn encode_options_arg(value: &str) -> Result<String, VideoToolkitError> {
+    parse_encode_options(value).map(|_| value.to_string())
+}
+
+/// Check a merger output format while parsing the command line
+fn merge_format_arg(value: &str) -> Result<String, VideoToolkitError> {
+    merger::validate_output_format(value)
+}
+
+/// Split a video into a grid of tiles and report the tiles that failed
+fn run_grid_split(
+    input: &str,
+    output_dir: &str,
+    prefix: &str,
+    grid: GridSize,
+    encode_options: Option<&str>,
+    force: bool,
+    jobs: Option<usize>,
+) {
+    let options = GridOptions { max_jobs: jobs };
+    let tiles = match split_video_grid_with_options(input, output_dir, prefix, grid, encode_options, force, &options) {
+        Ok(tiles) => tiles,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let failed: Vec<_> = tiles.iter().filter(|tile| tile.error.is_some()).collect();
+    if failed.is_empty() {
+        println!("Successfully split video into a {} grid. Files saved in: {}", grid, output_dir);
+        return;
     }
-}
\ No newline at end of file
+
+    eprintln!("Failed to create {}/{} tiles:", failed.len(), tiles.len());
+    for tile in failed {
+        eprintln!("  r{} c{}: {}", tile.row, tile.col, tile.error.as_deref().unwrap_or_default());
+    }
+    std::process::exit(1);
+}
+
+/// Print the outcome of a GIF transparency run, exiting with an error if any GIF failed
+///
+/// Dry runs list every file; real runs only list the files that need attention.
+fn report_transparency(report: &TransparencyReport) {
+    for entry in &report.entries {
+        match &entry.error {
+            Some(error) => eprintln!("  {}: {} ({})", entry.path.display(), entry.status, error),
+            None if report.dry_run || entry.status != GifStatus::WouldModify => {
+                println!("  {}: {}", entry.path.display(), entry.status);
+            },
+            None => {},
+        }
+    }
+
+    if !report.skipped.is_empty() {
+        println!("Skipped {} file(s) that are not GIFs:", report.skipped.len());
+        for skipped in &report.skipped {
+            println!("  {}: {}", skipped.path.display(), skipped.reason);
+        }
+    }
+
+    println!("{}", report.breakdown());
+
+    if report.dry_run {
+        println!("Dry run: no files were modified");
+        return;
+    }
+
+    let success_count = report.success_count();
+    let total_count = report.gif_count();
+    println!("Successfully processed {}/{} GIF files", success_count, total_count);
+    if success_count < total_count {
+        eprintln!("Failed to process {} GIF files", total_count - success_count);
+        std::process::exit(1);
+    }
+}
+
+/// Print the files that the batch input filters left out
+fn report_exclusions(exclusions: &[(PathBuf, ExclusionReason)]) {
+    if exclusions.is_empty() {
+        return;
+    }
+
+    println!("Excluded {} file(s) by input filters:", exclusions.len());
+    for (path, reason) in exclusions {
+        println!("  {}: {}", path.display(), reason);
+    }
+}